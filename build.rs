@@ -1,5 +1,3 @@
-use crux_build::CargoTarget;
-
 macro_rules! def_cfg {
 	($([$name:literal: $($cond:tt)*])*) => {
 		$(
@@ -23,5 +21,20 @@ fn main() {
 	};
 	println!("cargo::metadata=ROOT={}", root.display());
 
-	crux_build::build_with_crux_root(root, &[CargoTarget::Test]);
+	// Lets `crux::rt::mem::ASSUMED_PAGE_SIZE` be set when building
+	println!("cargo::rerun-if-env-changed=CRUX_ASSUMED_PAGE_SIZE");
+	let assumed_page_size = std::env::var("CRUX_ASSUMED_PAGE_SIZE").unwrap_or_else(|_| {
+		let target = |key| std::env::var(key).unwrap_or_default();
+		if target("CARGO_CFG_TARGET_VENDOR") == "apple"
+			&& target("CARGO_CFG_TARGET_ARCH") == "aarch64"
+		{
+			String::from("16384")
+		} else {
+			String::from("4096")
+		}
+	});
+	println!("cargo::rustc-env=CRUX_ASSUMED_PAGE_SIZE={assumed_page_size}");
+
+	// Crux only has unit tests, which don't get a crate type script
+	crux_build::build_with_crux_root(root, &[]);
 }
@@ -1,43 +1,482 @@
-use std::path::Path;
+use std::{
+	fmt::Write,
+	path::{Path, PathBuf},
+};
 
+/// A kind of Cargo target a crate has. Each one is linked with a linker script
+/// that tells Crux what it was built as; see `crux::rt::crate_type`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum CargoTarget {
+	Bench,
 	Bin,
 	CDylib,
 	Example,
+	/// Integration tests, in the crate's `tests` folder. A library's unit tests
+	/// don't need this: Cargo can't give them their own linker arguments (see
+	/// <https://github.com/rust-lang/cargo/issues/10937>), so Crux treats
+	/// crates without a crate type as tests.
 	Test,
 }
 
+/// A linker to use instead of the C compiler's default one. See
+/// [`CruxBuild::linker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Linker {
+	/// LLVM's linker. It can link for every target LLVM supports, so it's
+	/// used by default when cross-compiling.
+	Lld,
+	/// The mold linker, which is usually the fastest. Only supports ELF
+	/// targets.
+	Mold,
+}
+
+/// Links the crate against Crux. This is the same as calling
+/// [`CruxBuild::crate_type`] for each of `targets`, then [`CruxBuild::build`].
 pub fn build(targets: &[CargoTarget]) {
-	let root = std::env::var("DEP_CRUX_ROOT").unwrap();
-	build_with_crux_root(Path::new(&root), targets);
+	targets
+		.iter()
+		.fold(CruxBuild::new(), |build, target| build.crate_type(*target))
+		.build();
 }
 
+/// Like [`build`], but uses the Crux source tree at `root` instead of finding
+/// it with `DEP_CRUX_ROOT`.
 pub fn build_with_crux_root(root: &Path, targets: &[CargoTarget]) {
-	let link_scripts = root.join("link-scripts");
+	targets
+		.iter()
+		.fold(CruxBuild::new().crux_root(root), |build, target| {
+			build.crate_type(*target)
+		})
+		.build();
+}
 
-	let link = |ty: &'static str, script: &'static str| {
-		println!(
-			"cargo::rustc-link-arg{ty}=-T{}",
-			link_scripts.join(script).display()
-		);
-	};
-
-	link("", "default.ld");
-	for ty in targets {
-		match ty {
-			CargoTarget::Bin => {
-				link("-bins", "bin.ld");
-				println!("cargo::rustc-link-arg=--for-linker");
-				println!("cargo::rustc-link-arg=--wrap=main");
+/// Configures how a crate is linked against Crux. Call this from the crate's
+/// build script:
+///
+/// ```rs
+/// fn main() {
+///     CruxBuild::new()
+///         .crate_type(CargoTarget::Bin)
+///         .crate_type(CargoTarget::Test)
+///         .custom_section(".plugins")
+///         .stack_size(8 * 1024 * 1024)
+///         .build();
+/// }
+/// ```
+///
+/// Crux's own linker sections always come from the linker scripts in its
+/// source tree. Anything configured here goes in a linker script that's
+/// generated in the build script's `OUT_DIR`.
+#[derive(Clone, Debug, Default)]
+pub struct CruxBuild {
+	root: Option<PathBuf>,
+	targets: Vec<CargoTarget>,
+	sections: Vec<String>,
+	stack_size: Option<u64>,
+	linker: Option<Linker>,
+}
+impl CruxBuild {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Uses the Crux source tree at `root`, instead of finding it with
+	/// `DEP_CRUX_ROOT`. Only Crux's own build script should need this.
+	pub fn crux_root(mut self, root: impl Into<PathBuf>) -> Self {
+		self.root = Some(root.into());
+		self
+	}
+	/// Adds a kind of Cargo target the crate has. Cargo rejects linker
+	/// arguments for kinds of targets a crate doesn't have, so this has to
+	/// list exactly the ones it has.
+	pub fn crate_type(mut self, target: CargoTarget) -> Self {
+		if !self.targets.contains(&target) {
+			self.targets.push(target);
+		}
+		self
+	}
+	/// Adds a linker section called `name`, which is placed after `.rodata`.
+	/// Statics can be put in it with `#[unsafe(link_section = "name")]`, and
+	/// they're kept even if nothing references them.
+	///
+	/// The section's bounds are available as the symbols `__start_<name>` and
+	/// `__stop_<name>`, where `<name>` has its leading `.` removed and other
+	/// `.`s replaced with `_` (so `.my.plugins` becomes `__start_my_plugins`).
+	/// Custom sections need a linker script, so they're only supported on
+	/// ELF targets (e.g. Linux).
+	pub fn custom_section(mut self, name: impl Into<String>) -> Self {
+		self.sections.push(name.into());
+		self
+	}
+	/// Sets the main thread's stack size, in bytes.
+	///
+	/// On macOS and Windows, the OS creates the main thread with this much
+	/// stack. On ELF targets, it's stored in the `PT_GNU_STACK` header; Linux
+	/// still sizes the main thread's stack with `ulimit -s`, but some C
+	/// libraries use the header as their default thread stack size.
+	pub fn stack_size(mut self, bytes: u64) -> Self {
+		self.stack_size = Some(bytes);
+		self
+	}
+
+	/// Picks the linker the C compiler runs, with `-fuse-ld`. The linker has
+	/// to be installed, and the C compiler Cargo uses for the target has to
+	/// support it (GCC and Clang both do).
+	///
+	/// If this isn't called, the C compiler's default linker is used, unless
+	/// the crate is being cross-compiled; then [`Linker::Lld`] is used, since
+	/// the default linker often only supports the host's architecture.
+	pub fn linker(mut self, linker: Linker) -> Self {
+		self.linker = Some(linker);
+		self
+	}
+
+	/// Checks the configuration against the target that's being built for,
+	/// then tells Cargo how to link the crate. If anything is wrong, this
+	/// panics with an explanation, which fails the build.
+	pub fn build(self) {
+		if let Err(err) = self.try_build() {
+			panic!("crux-build: {err}");
+		}
+	}
+
+	fn try_build(&self) -> Result<(), String> {
+		let target = Target::from_env();
+		self.validate(&target)?;
+		let root = match &self.root {
+			Some(root) => root.clone(),
+			None => PathBuf::from(std::env::var("DEP_CRUX_ROOT").map_err(|_| {
+				String::from(
+					"`DEP_CRUX_ROOT` isn't set, so the Crux linker scripts couldn't be found. \
+					 Cargo only sets it for crates that depend on `crux` directly, so add `crux` \
+					 to this crate's `[dependencies]` (not just `[build-dependencies]`)",
+				)
+			})?),
+		};
+		let link_scripts = root.join("link-scripts");
+		if !link_scripts.is_dir() {
+			return Err(format!(
+				"Couldn't find Crux's linker scripts in `{}`",
+				link_scripts.display()
+			));
+		}
+
+		let link = |ty: &'static str, script: &Path| {
+			println!("cargo::rustc-link-arg{ty}=-T{}", script.display());
+		};
+
+		// PE files don't have a `.rodata` section to put Crux's sections
+		// after, and Windows runs ini functions from `.CRT$XCU` instead. The
+		// crate type scripts only define symbols, which GNU ld supports on
+		// every format.
+		if target.format != Format::Coff {
+			link("", &link_scripts.join("default.ld"));
+		}
+		for ty in &self.targets {
+			match ty {
+				CargoTarget::Bench => link("-benches", &link_scripts.join("bench.ld")),
+				CargoTarget::Bin => {
+					link("-bins", &link_scripts.join("bin.ld"));
+					println!("cargo::rustc-link-arg=--for-linker");
+					println!("cargo::rustc-link-arg=--wrap=main");
+				}
+				CargoTarget::CDylib => link("-cdylib", &link_scripts.join("cdylib.ld")),
+				CargoTarget::Example => link("-examples", &link_scripts.join("example.ld")),
+				CargoTarget::Test => link("-tests", &link_scripts.join("test.ld")),
 			}
-			CargoTarget::CDylib => link("-cdylib", "cdylib.ld"),
-			CargoTarget::Example => link("-example", "example.ld"),
-			CargoTarget::Test => {
-				// Broken: https://github.com/rust-lang/cargo/issues/10937
-				// link("test", "test.ld");
-				link("", "test-workaround.ld");
+		}
+
+		if !self.sections.is_empty() {
+			let out_dir = std::env::var("OUT_DIR").map_err(|_| {
+				String::from("`OUT_DIR` isn't set; is this running in a build script?")
+			})?;
+			let script = Path::new(&out_dir).join("crux-sections.ld");
+			std::fs::write(&script, sections_script(&self.sections))
+				.map_err(|err| format!("Failed to write `{}`: {err}", script.display()))?;
+			link("", &script);
+		}
+
+		if let Some(size) = self.stack_size {
+			match target.format {
+				Format::Elf => {
+					println!("cargo::rustc-link-arg=--for-linker");
+					println!("cargo::rustc-link-arg=-zstack-size={size}");
+				}
+				Format::MachO => {
+					println!("cargo::rustc-link-arg=--for-linker");
+					println!("cargo::rustc-link-arg=-stack_size");
+					println!("cargo::rustc-link-arg=--for-linker");
+					println!("cargo::rustc-link-arg={size:#x}");
+				}
+				Format::Coff => {
+					println!("cargo::rustc-link-arg=--for-linker");
+					println!("cargo::rustc-link-arg=--stack={size}");
+				}
+			}
+		}
+
+		let linker = self
+			.linker
+			.or_else(|| target.is_cross().then_some(Linker::Lld));
+		match linker {
+			Some(Linker::Lld) => println!("cargo::rustc-link-arg=-fuse-ld=lld"),
+			Some(Linker::Mold) => println!("cargo::rustc-link-arg=-fuse-ld=mold"),
+			None => {}
+		}
+
+		if !build_std_configured() {
+			let cross = if target.is_cross() {
+				format!(
+					", and `{}`'s standard library crates won't be built for the cross build",
+					target.name
+				)
+			} else {
+				String::new()
+			};
+			println!(
+				"cargo::warning=crux-build: Couldn't find a `build-std` setting in Cargo's config. Crux needs the standard library crates to be rebuilt with aborting panics{cross}. See Crux's `docs/setup-no-std.md`."
+			);
+		}
+
+		Ok(())
+	}
+
+	/// Checks that the configuration makes sense, and that everything in it is
+	/// supported by `target`.
+	fn validate(&self, target: &Target) -> Result<(), String> {
+		if target.msvc {
+			return Err(format!(
+				"Crux needs a GNU-compatible linker, so it can't be linked with MSVC when building for `{}`. Build for a `-gnu` target (e.g. `x86_64-pc-windows-gnu`) instead",
+				target.name
+			));
+		}
+		if self.linker == Some(Linker::Mold) && target.format != Format::Elf {
+			return Err(format!(
+				"mold can only link ELF files, so it can't be used when building for `{}`",
+				target.name
+			));
+		}
+
+		if !self.sections.is_empty() && target.format != Format::Elf {
+			return Err(format!(
+				"Custom sections need a linker script, which isn't supported when building for `{}`",
+				target.name
+			));
+		}
+		for (idx, name) in self.sections.iter().enumerate() {
+			validate_section(name)?;
+			if self.sections[..idx].contains(name) {
+				return Err(format!("The custom section `{name}` was added twice"));
 			}
 		}
+
+		if let Some(size) = self.stack_size {
+			if size == 0 {
+				return Err(String::from("The stack size can't be 0"));
+			}
+			// ld64 rejects stack sizes that aren't page-aligned
+			if target.format == Format::MachO && size % MACOS_PAGE_SIZE != 0 {
+				return Err(format!(
+					"The stack size must be a multiple of {MACOS_PAGE_SIZE} bytes on macOS, but it's {size} bytes"
+				));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The largest page size macOS uses, which is the page size on Apple Silicon.
+const MACOS_PAGE_SIZE: u64 = 16384;
+
+/// Checks that `name` can be used as a section name in a linker script.
+fn validate_section(name: &str) -> Result<(), String> {
+	if name.trim_start_matches('.').is_empty() {
+		return Err(format!("`{name}` isn't a valid section name"));
+	}
+	if let Some(invalid) = name
+		.chars()
+		.find(|char| !char.is_ascii_alphanumeric() && !matches!(char, '.' | '_' | '$'))
+	{
+		return Err(format!(
+			"The section name `{name}` can't contain `{invalid}`; section names can only use ASCII letters, numbers, `.`, `_`, and `$`"
+		));
+	}
+	if name.starts_with(".crux.") {
+		return Err(format!(
+			"The section name `{name}` is reserved for Crux; sections starting with `.crux.` are placed by Crux's linker scripts"
+		));
+	}
+
+	Ok(())
+}
+
+/// Generates a linker script that places each of `sections` after `.rodata`.
+fn sections_script(sections: &[String]) -> String {
+	let mut script = String::from("/* Generated by crux-build */\nSECTIONS {\n");
+	for name in sections {
+		let symbol = name.trim_start_matches('.').replace('.', "_");
+		writeln!(
+			script,
+			"\t{name} : {{\n\t\t__start_{symbol} = .;\n\t\tKEEP(*({name}));\n\t\t__stop_{symbol} = .;\n\t}}"
+		)
+		.unwrap();
+	}
+	script.push_str("} INSERT AFTER .rodata;\n");
+
+	script
+}
+
+/// Looks for a `build-std` setting in the Cargo config files that apply to
+/// the crate, or in the environment. Cargo doesn't tell build scripts which
+/// unstable flags it's using, and it could also be set on the command line, so
+/// this is only used for a warning.
+fn build_std_configured() -> bool {
+	if std::env::var_os("CARGO_UNSTABLE_BUILD_STD").is_some() {
+		return true;
+	}
+
+	let mut dirs = Vec::new();
+	if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+		dirs.extend(
+			Path::new(&manifest_dir)
+				.ancestors()
+				.map(|dir| dir.join(".cargo")),
+		);
+	}
+	if let Some(cargo_home) = std::env::var_os("CARGO_HOME") {
+		dirs.push(PathBuf::from(cargo_home));
+	} else if let Some(home) = std::env::var_os("HOME") {
+		dirs.push(Path::new(&home).join(".cargo"));
+	}
+
+	dirs.iter()
+		.flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+		.filter_map(|config| std::fs::read_to_string(config).ok())
+		.any(|config| {
+			config
+				.lines()
+				.any(|line| line.trim_start().starts_with("build-std"))
+		})
+}
+
+/// The object file format a target uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+	Elf,
+	MachO,
+	Coff,
+}
+
+/// The target the crate is being built for, from the variables Cargo sets for
+/// build scripts.
+struct Target {
+	/// The target triple, e.g. `aarch64-unknown-linux-gnu`.
+	name: String,
+	/// The triple of the machine running the build.
+	host: String,
+	format: Format,
+	msvc: bool,
+}
+impl Target {
+	fn from_env() -> Self {
+		let var = |key| std::env::var(key).unwrap_or_default();
+		let format = if var("CARGO_CFG_TARGET_VENDOR") == "apple" {
+			Format::MachO
+		} else if var("CARGO_CFG_TARGET_OS") == "windows" {
+			Format::Coff
+		} else {
+			Format::Elf
+		};
+
+		Self {
+			name: var("TARGET"),
+			host: var("HOST"),
+			format,
+			msvc: var("CARGO_CFG_TARGET_ENV") == "msvc",
+		}
+	}
+
+	/// If the crate is being built for a different target than the machine
+	/// building it.
+	fn is_cross(&self) -> bool {
+		self.name != self.host
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sections() {
+		assert!(validate_section(".plugins").is_ok());
+		assert!(validate_section("my_plugins$1").is_ok());
+		assert!(validate_section(".").is_err());
+		assert!(validate_section(".my plugins").is_err());
+		assert!(validate_section(".crux.ini").is_err());
+
+		let script = sections_script(&[String::from(".my.plugins")]);
+		assert!(script.contains("__start_my_plugins = .;"));
+		assert!(script.contains("KEEP(*(.my.plugins));"));
+		assert!(script.ends_with("} INSERT AFTER .rodata;\n"));
+	}
+
+	#[test]
+	fn validate() {
+		let target = |format| Target {
+			name: String::from("test-target"),
+			host: String::from("test-target"),
+			format,
+			msvc: false,
+		};
+		let build = CruxBuild::new().custom_section(".plugins");
+		assert!(build.validate(&target(Format::Elf)).is_ok());
+		assert!(build.validate(&target(Format::MachO)).is_err());
+		assert!(
+			build
+				.clone()
+				.custom_section(".plugins")
+				.validate(&target(Format::Elf))
+				.is_err()
+		);
+
+		let build = CruxBuild::new().stack_size(1024 * 1024);
+		assert!(build.validate(&target(Format::MachO)).is_ok());
+		assert!(build.validate(&target(Format::Coff)).is_ok());
+		assert!(
+			CruxBuild::new()
+				.stack_size(1000)
+				.validate(&target(Format::MachO))
+				.is_err()
+		);
+		assert!(
+			CruxBuild::new()
+				.stack_size(0)
+				.validate(&target(Format::Elf))
+				.is_err()
+		);
+
+		let build = CruxBuild::new().linker(Linker::Mold);
+		assert!(build.validate(&target(Format::Elf)).is_ok());
+		assert!(build.validate(&target(Format::Coff)).is_err());
+		let msvc = Target {
+			msvc: true,
+			..target(Format::Coff)
+		};
+		assert!(CruxBuild::new().validate(&msvc).is_err());
+
+		let cross = Target {
+			name: String::from("aarch64-unknown-linux-gnu"),
+			..target(Format::Elf)
+		};
+		assert!(cross.is_cross());
+		assert!(!target(Format::Elf).is_cross());
 	}
 }
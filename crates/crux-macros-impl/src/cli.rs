@@ -0,0 +1,558 @@
+//! Derive macros for Crux's CLI parser (`crux::term::cli`).
+
+use crux_rust_ast::{
+	AstComponent, Delimiter, Diagnostic, Group, Ident, Literal, Punct, Spacing, TokenIter,
+	TokenStream, TokenTree,
+	ast::{Attribute, AttributeArgs, Visibility},
+	quote,
+};
+
+pub fn cli_parser(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let mut tokens = input.into_iter().peekable();
+	let tokens = &mut tokens;
+
+	let attrs = parse_attributes(tokens)?;
+	Visibility::skip(tokens);
+	if !tokens.next_is_ident("struct") {
+		return Err(Diagnostic::new(
+			tokens.next_span(),
+			"`CliParser` can only be derived for structs",
+		));
+	}
+	tokens.next();
+
+	let Some(TokenTree::Ident(name)) = tokens.next() else {
+		return Err(Diagnostic::call_site("Expected struct name"));
+	};
+	let lifetime = parse_lifetime(tokens)?;
+	let named_fields_only = || {
+		Diagnostic::new(
+			name.span(),
+			"`CliParser` can only be derived for structs with named fields",
+		)
+	};
+	let Some(TokenTree::Group(body)) = tokens.next() else {
+		return Err(named_fields_only());
+	};
+	if body.delimiter() != Delimiter::Brace {
+		return Err(named_fields_only());
+	}
+
+	let container = CliArgs::from_attributes(&attrs)?;
+	let about = doc_literal(&attrs);
+	let program_name = match container.name {
+		Some(name) => quote! { #name },
+		None => quote! { ::core::env!("CARGO_PKG_NAME") },
+	};
+	let error_handler = match container.error {
+		Some(handler) => quote! { #handler(self, error) },
+		None => quote! { ::core::panic!("CLI error: {error:?}") },
+	};
+
+	let (impl_generics, parser_lifetime, ty_generics) = match &lifetime {
+		Some(lifetime) => {
+			let lifetime = lifetime_tokens(lifetime);
+			(
+				quote! { <#lifetime> },
+				quote! { #lifetime },
+				quote! { <#lifetime> },
+			)
+		}
+		None => (quote! { <'cli> }, quote! { 'cli }, quote! {}),
+	};
+
+	let fields = parse_fields(body.stream())?;
+	let mut flag_arms = Vec::new();
+	let mut flag_specs = Vec::new();
+	let mut subcommand = None;
+	let mut trailing = None;
+
+	for field in fields {
+		let args = CliArgs::from_attributes(&field.attrs)?;
+		if args.skip {
+			continue;
+		}
+
+		let field_name = &field.name;
+		let ty = &field.ty;
+
+		if args.subcommand {
+			if subcommand.is_some() {
+				return Err(Diagnostic::new(
+					field_name.span(),
+					"Only one field can be marked `#[cli(subcommand)]`",
+				));
+			}
+			subcommand = Some((field.name, field.ty));
+			continue;
+		}
+		if args.trailing {
+			if trailing.is_some() {
+				return Err(Diagnostic::new(
+					field_name.span(),
+					"Only one field can be marked `#[cli(trailing)]`",
+				));
+			}
+			trailing = Some(field.name);
+			continue;
+		}
+
+		let long = args
+			.long
+			.unwrap_or_else(|| Literal::string(&kebab_case(&field_name.to_string())));
+		let short = args
+			.short
+			.map(|short| short.unwrap_or_else(|| field_name.to_string().chars().next().unwrap()));
+
+		let pattern = match short {
+			Some(short) => {
+				let short = Literal::string(&short.to_string());
+				quote! { #short | #long }
+			}
+			None => quote! { #long },
+		};
+		flag_arms.push(quote! {
+			#pattern if class.is_flag() => {
+				if <#ty as crux::term::cli::CliField<#parser_lifetime>>::TAKES_ARGUMENT {
+					let ::core::option::Option::Some(arg) = ctx.next_argument(self) else {
+						return crux::term::cli::ParseResult::MissingArgument;
+					};
+					crux::term::cli::CliField::set_argument(&mut self.#field_name, arg);
+				} else {
+					crux::term::cli::CliField::set_flag(&mut self.#field_name);
+				}
+			}
+		});
+
+		let short = match short {
+			Some(short) => {
+				let short = Literal::character(short);
+				quote! { ::core::option::Option::Some(#short) }
+			}
+			None => quote! { ::core::option::Option::None },
+		};
+		let argument_name = Literal::string(&field_name.to_string().to_uppercase());
+		let static_ty = replace_lifetime(ty.clone(), lifetime.as_ref());
+		let help = doc_literal(&field.attrs);
+		flag_specs.push(quote! {
+			crux::term::cli::FlagSpec {
+				short: #short,
+				long: ::core::option::Option::Some(#long),
+				argument: if <#static_ty as crux::term::cli::CliField<'static>>::TAKES_ARGUMENT {
+					::core::option::Option::Some(#argument_name)
+				} else {
+					::core::option::Option::None
+				},
+				help: #help.trim_ascii(),
+			}
+		});
+	}
+
+	let (subcommand_arm, subcommand_specs) = match subcommand {
+		Some((field_name, ty)) => (
+			quote! {
+				if class.is_subcommand() {
+					if let ::core::option::Option::Some(cmd) = <#ty as crux::term::cli::CliSubcommand>::from_name(flag) {
+						self.#field_name = cmd;
+						return crux::term::cli::ParseResult::Recognised;
+					}
+				}
+			},
+			quote! { <#ty as crux::term::cli::CliSubcommand>::SUBCOMMANDS },
+		),
+		None => (quote! {}, quote! { &[] }),
+	};
+
+	let trailing_arm = match trailing {
+		Some(field_name) => quote! {
+			if class.is_trailing() {
+				crux::term::cli::CliField::set_argument(&mut self.#field_name, flag);
+				return crux::term::cli::ParseResult::Recognised;
+			}
+		},
+		None => quote! {},
+	};
+
+	Ok(quote! {
+		impl #impl_generics crux::term::cli::CliParser<#parser_lifetime> for #name #ty_generics {
+			fn parse(
+				&mut self,
+				flag: &#parser_lifetime str,
+				class: crux::term::cli::FlagClass<#parser_lifetime>,
+				ctx: &mut crux::term::cli::CliParsingCtx<#parser_lifetime, Self>,
+			) -> crux::term::cli::ParseResult {
+				match flag {
+					#(#flag_arms)*
+					_ => {
+						#trailing_arm
+						#subcommand_arm
+						return crux::term::cli::ParseResult::NotRecognised;
+					}
+				}
+
+				crux::term::cli::ParseResult::Recognised
+			}
+			fn error(&mut self, error: crux::term::cli::ParseError<#parser_lifetime>) {
+				#error_handler
+			}
+		}
+		impl #impl_generics crux::term::cli::CliHelp for #name #ty_generics {
+			const SPEC: crux::term::cli::CommandSpec<'static> = {
+				const FLAGS: &[crux::term::cli::FlagSpec<'static>] = &[#(#flag_specs),*];
+
+				crux::term::cli::CommandSpec {
+					name: #program_name,
+					about: #about.trim_ascii(),
+					flags: FLAGS,
+					subcommands: #subcommand_specs,
+				}
+			};
+		}
+	})
+}
+
+pub fn cli_subcommand(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let mut tokens = input.into_iter().peekable();
+	let tokens = &mut tokens;
+
+	parse_attributes(tokens)?;
+	Visibility::skip(tokens);
+	if !tokens.next_is_ident("enum") {
+		return Err(Diagnostic::new(
+			tokens.next_span(),
+			"`CliSubcommand` can only be derived for enums",
+		));
+	}
+	tokens.next();
+
+	let Some(TokenTree::Ident(name)) = tokens.next() else {
+		return Err(Diagnostic::call_site("Expected enum name"));
+	};
+	let Some(TokenTree::Group(body)) = tokens.next() else {
+		return Err(Diagnostic::new(
+			name.span(),
+			"`CliSubcommand` can't be derived for generic enums",
+		));
+	};
+
+	let mut arms = Vec::new();
+	let mut specs = Vec::new();
+
+	let mut tokens = body.stream().into_iter().peekable();
+	let tokens = &mut tokens;
+	while tokens.peek().is_some() {
+		let attrs = parse_attributes(tokens)?;
+		let span = tokens.next_span();
+		let Some(TokenTree::Ident(variant)) = tokens.next() else {
+			return Err(Diagnostic::new(span, "Expected variant name"));
+		};
+		if !(tokens.peek().is_none() || tokens.next_is_punct(',')) {
+			return Err(Diagnostic::new(
+				tokens.next_span(),
+				"`CliSubcommand` variants can't have fields or discriminants",
+			));
+		}
+		tokens.next();
+
+		let args = CliArgs::from_attributes(&attrs)?;
+		let cmd_name = args
+			.name
+			.unwrap_or_else(|| Literal::string(&kebab_case(&variant.to_string())));
+		let aliases = &args.aliases;
+		arms.push(quote! {
+			#cmd_name #(| #aliases)* => ::core::option::Option::Some(Self::#variant),
+		});
+
+		let about = doc_literal(&attrs);
+		specs.push(quote! {
+			crux::term::cli::CommandSpec {
+				name: #cmd_name,
+				about: #about.trim_ascii(),
+				flags: &[],
+				subcommands: &[],
+			}
+		});
+	}
+
+	Ok(quote! {
+		impl crux::term::cli::CliSubcommand for #name {
+			const SUBCOMMANDS: &'static [crux::term::cli::CommandSpec<'static>] = &[#(#specs),*];
+
+			fn from_name(name: &str) -> ::core::option::Option<Self> {
+				match name {
+					#(#arms)*
+					_ => ::core::option::Option::None,
+				}
+			}
+		}
+	})
+}
+
+//
+//
+// Parsing helpers
+//
+//
+
+/// The arguments passed to `#[cli(...)]` attributes.
+#[derive(Default)]
+struct CliArgs {
+	/// `short` or `short = 'c'`. The inner value is `None` if the short flag
+	/// should be inferred from the field name.
+	short: Option<Option<char>>,
+	/// `long = "name"`
+	long: Option<Literal>,
+	/// `name = "name"`
+	name: Option<Literal>,
+	/// `alias = "name"`
+	aliases: Vec<Literal>,
+	/// `error = path::to::function`
+	error: Option<TokenStream>,
+	/// `subcommand`
+	subcommand: bool,
+	/// `trailing`
+	trailing: bool,
+	/// `skip`
+	skip: bool,
+}
+impl CliArgs {
+	fn from_attributes(attrs: &[Attribute]) -> Result<Self, Diagnostic> {
+		let mut this = Self::default();
+
+		for attr in attrs {
+			if attr.name != "cli" {
+				continue;
+			}
+			let Some(AttributeArgs::Delimited(TokenTree::Group(group))) = &attr.args else {
+				return Err(Diagnostic::call_site("Expected arguments in `#[cli(...)]`")
+					.help("e.g. `#[cli(short)]` or `#[cli(long = \"name\")]`"));
+			};
+
+			let mut tokens = group.stream().into_iter().peekable();
+			while let Some(token) = tokens.next() {
+				let TokenTree::Ident(key) = token else {
+					return Err(Diagnostic::new(
+						token.span(),
+						format!("Expected argument name in `#[cli(...)]`, found `{token}`"),
+					));
+				};
+
+				let mut value = TokenStream::new();
+				if tokens.next_is_punct('=') {
+					tokens.next();
+					while tokens.peek().is_some() && !tokens.next_is_punct(',') {
+						value.extend(tokens.next());
+					}
+				}
+				tokens.next(); // Comma
+
+				let literal = || match value.clone().into_iter().next() {
+					Some(TokenTree::Literal(literal)) => Ok(literal),
+					Some(other) => Err(Diagnostic::new(
+						other.span(),
+						format!("Expected a literal for `{key}` in `#[cli(...)]`"),
+					)),
+					None => Err(Diagnostic::new(
+						key.span(),
+						format!("Expected a literal for `{key}` in `#[cli(...)]`"),
+					)),
+				};
+
+				match key.to_string().as_str() {
+					"short" if value.is_empty() => this.short = Some(None),
+					"short" => {
+						let literal = literal()?;
+						let short = literal.to_string();
+						let mut chars = short.trim_matches('\'').chars();
+						match (chars.next(), chars.next()) {
+							(Some(short), None) => this.short = Some(Some(short)),
+							_ => {
+								return Err(Diagnostic::new(
+									literal.span(),
+									"`short` must be a single character",
+								)
+								.help("e.g. `short = 'p'`"));
+							}
+						}
+					}
+					"long" => this.long = Some(literal()?),
+					"name" => this.name = Some(literal()?),
+					"alias" => this.aliases.push(literal()?),
+					"error" => this.error = Some(value),
+					"subcommand" => this.subcommand = true,
+					"trailing" => this.trailing = true,
+					"skip" => this.skip = true,
+					other => {
+						return Err(Diagnostic::new(
+							key.span(),
+							format!("Unknown argument `{other}` in `#[cli(...)]`"),
+						));
+					}
+				}
+			}
+		}
+
+		Ok(this)
+	}
+}
+
+/// A named field in a struct.
+struct Field {
+	attrs: Vec<Attribute>,
+	name: Ident,
+	ty: TokenStream,
+}
+
+fn parse_attributes(tokens: &mut impl TokenIter) -> Result<Vec<Attribute>, Diagnostic> {
+	let mut attrs = Vec::new();
+	while let Some(attr) = Attribute::maybe_parse(tokens) {
+		let attr = attr.map_err(|err| {
+			Diagnostic::new(
+				tokens.next_span(),
+				format!("Failed to parse attribute: {err:?}"),
+			)
+		})?;
+		attrs.push(attr);
+	}
+	Ok(attrs)
+}
+
+/// Parses the generics of a struct, which may only be empty or a single
+/// lifetime. Returns the name of the lifetime, if there is one.
+fn parse_lifetime(tokens: &mut impl TokenIter) -> Result<Option<Ident>, Diagnostic> {
+	if !tokens.next_is_punct('<') {
+		return Ok(None);
+	}
+	let span = tokens.next_span();
+	tokens.next();
+
+	match (tokens.next(), tokens.next(), tokens.next()) {
+		(
+			Some(TokenTree::Punct(apostrophe)),
+			Some(TokenTree::Ident(lifetime)),
+			Some(TokenTree::Punct(close)),
+		) if apostrophe.as_char() == '\'' && close.as_char() == '>' => Ok(Some(lifetime)),
+		_ => Err(Diagnostic::new(
+			span,
+			"`CliParser` can only be derived for structs with at most one lifetime parameter",
+		)),
+	}
+}
+
+fn parse_fields(body: TokenStream) -> Result<Vec<Field>, Diagnostic> {
+	let mut tokens = body.into_iter().peekable();
+	let tokens = &mut tokens;
+	let mut fields = Vec::new();
+
+	while tokens.peek().is_some() {
+		let attrs = parse_attributes(tokens)?;
+		Visibility::skip(tokens);
+		let span = tokens.next_span();
+		let Some(TokenTree::Ident(name)) = tokens.next() else {
+			return Err(Diagnostic::new(span, "Expected field name"));
+		};
+		if !tokens.next_is_punct(':') {
+			return Err(Diagnostic::new(
+				tokens.next_span(),
+				format!("Expected `:` after field `{name}`"),
+			));
+		}
+		tokens.next();
+
+		// Collect the type, stopping at the first comma that isn't inside
+		// generic brackets
+		let mut ty = TokenStream::new();
+		let mut depth = 0usize;
+		for token in tokens.by_ref() {
+			if let TokenTree::Punct(punct) = &token {
+				match punct.as_char() {
+					'<' => depth += 1,
+					'>' => depth = depth.saturating_sub(1),
+					',' if depth == 0 => break,
+					_ => {}
+				}
+			}
+			ty.extend([token]);
+		}
+
+		fields.push(Field { attrs, name, ty });
+	}
+
+	Ok(fields)
+}
+
+/// Returns the first line of an item's doc comment as a string literal, or an
+/// empty string literal if the item isn't documented.
+fn doc_literal(attrs: &[Attribute]) -> TokenTree {
+	attrs
+		.iter()
+		.filter(|attr| attr.name == "doc")
+		.find_map(|attr| match &attr.args {
+			Some(AttributeArgs::Assigned(value)) => value
+				.as_literal()
+				.map(|literal| TokenTree::Literal(literal.clone())),
+			_ => None,
+		})
+		.unwrap_or_else(|| TokenTree::Literal(Literal::string("")))
+}
+
+/// Replaces the given lifetime with `'static` in a type, so the type can be
+/// used in a constant.
+fn replace_lifetime(ty: TokenStream, lifetime: Option<&Ident>) -> TokenStream {
+	let Some(lifetime) = lifetime else {
+		return ty;
+	};
+
+	let mut out = TokenStream::new();
+	let mut tokens = ty.into_iter().peekable();
+	while let Some(token) = tokens.next() {
+		match token {
+			TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+				out.extend([TokenTree::Punct(punct)]);
+				match tokens.next() {
+					Some(TokenTree::Ident(ident)) if &ident == lifetime => {
+						out.extend([TokenTree::Ident(Ident::new("static", ident.span()))]);
+					}
+					other => out.extend(other),
+				}
+			}
+			TokenTree::Group(group) => {
+				let mut new_group = Group::new(
+					group.delimiter(),
+					replace_lifetime(group.stream(), Some(lifetime)),
+				);
+				new_group.set_span(group.span());
+				out.extend([TokenTree::Group(new_group)]);
+			}
+			other => out.extend([other]),
+		}
+	}
+
+	out
+}
+
+/// Converts `snake_case` or `PascalCase` to `kebab-case`.
+fn kebab_case(name: &str) -> String {
+	let mut out = String::with_capacity(name.len());
+	for (idx, c) in name.trim_start_matches("r#").chars().enumerate() {
+		if c == '_' {
+			out.push('-');
+		} else if c.is_uppercase() {
+			if idx != 0 {
+				out.push('-');
+			}
+			out.extend(c.to_lowercase());
+		} else {
+			out.push(c);
+		}
+	}
+	out
+}
+
+/// Turns a lifetime's name into tokens (e.g. `a` into `'a`).
+fn lifetime_tokens(name: &Ident) -> TokenStream {
+	TokenStream::from_iter([
+		TokenTree::Punct(Punct::new('\'', Spacing::Joint)),
+		TokenTree::Ident(name.clone()),
+	])
+}
@@ -0,0 +1,115 @@
+//! Literal constructors for Crux's hash maps and sets.
+
+use crux_rust_ast::{Diagnostic, Ident, Span, TokenStream, TokenTree, quote};
+
+pub fn map(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let mut keys = Vec::new();
+	let mut values = Vec::new();
+	for entry in split_entries(input) {
+		let (key, value) = split_arrow(&entry).ok_or_else(|| {
+			Diagnostic::new(entry[0].span(), "Expected a `key => value` pair in `map!`")
+				.help("separate entries with commas, like `map! { 1 => \"one\", 2 => \"two\" }`")
+		})?;
+		keys.push(TokenStream::from_iter(key.iter().cloned()));
+		values.push(TokenStream::from_iter(value.iter().cloned()));
+	}
+
+	let len = keys.len();
+	// Mixed-site hygiene keeps the entries from seeing this variable
+	let map = Ident::new("map", Span::mixed_site());
+	Ok(quote! {
+		{
+			let mut #map = crux::data_structures::HashMap::with_capacity(#len);
+			#(#map.insert(#keys, #values);)*
+			#map
+		}
+	})
+}
+pub fn set(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let items: Vec<_> = split_entries(input)
+		.into_iter()
+		.map(TokenStream::from_iter)
+		.collect();
+
+	let len = items.len();
+	let set = Ident::new("set", Span::mixed_site());
+	Ok(quote! {
+		{
+			let mut #set = crux::data_structures::HashSet::with_capacity(#len);
+			#(#set.insert(#items);)*
+			#set
+		}
+	})
+}
+
+/// Splits `input` at its top-level commas. Empty entries, like the one after
+/// a trailing comma, are skipped.
+///
+/// Commas in groups (`()`, `[]`, `{}`) are part of a single token, so they
+/// don't split entries; commas in generics do, so expressions like
+/// `Foo::<A, B>::new()` have to be wrapped in parentheses.
+pub(crate) fn split_entries(input: TokenStream) -> Vec<Vec<TokenTree>> {
+	let mut entries = vec![Vec::new()];
+	for token in input {
+		match token {
+			TokenTree::Punct(punct) if punct.as_char() == ',' => entries.push(Vec::new()),
+			other => entries.last_mut().unwrap().push(other),
+		}
+	}
+	entries.retain(|entry| !entry.is_empty());
+
+	entries
+}
+
+/// Splits an entry at its first top-level `=>`. Returns [`None`] if there
+/// isn't one, or if either side of it is empty.
+fn split_arrow(entry: &[TokenTree]) -> Option<(&[TokenTree], &[TokenTree])> {
+	let arrow = entry.windows(2).position(|pair| {
+		matches!(
+			pair,
+			[TokenTree::Punct(eq), TokenTree::Punct(gt)] if eq.as_char() == '=' && gt.as_char() == '>'
+		)
+	})?;
+	let (key, value) = (&entry[..arrow], &entry[arrow + 2..]);
+
+	(!key.is_empty() && !value.is_empty()).then_some((key, value))
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn entries() {
+		let input: TokenStream = "\"a\" => vec![1, 2], b => |x| x >= 1, (c, d) => {},"
+			.parse()
+			.unwrap();
+		let entries = split_entries(input);
+		assert_eq!(entries.len(), 3);
+
+		let pairs: Vec<_> = entries
+			.iter()
+			.map(|entry| {
+				let (key, value) = split_arrow(entry).unwrap();
+				(
+					TokenStream::from_iter(key.iter().cloned()).to_string(),
+					TokenStream::from_iter(value.iter().cloned()).to_string(),
+				)
+			})
+			.collect();
+		assert_eq!(pairs[0], ("\"a\"".into(), "vec ! [1 , 2]".into()));
+		assert_eq!(pairs[1], ("b".into(), "| x | x >= 1".into()));
+		assert_eq!(pairs[2], ("(c , d)".into(), "{ }".into()));
+
+		let missing_value: TokenStream = "a =>".parse().unwrap();
+		assert!(map(missing_value).is_err());
+		assert!(map("a".parse().unwrap()).is_err());
+		assert!(map(TokenStream::new()).is_ok());
+	}
+}
@@ -0,0 +1,281 @@
+//! Derive macros for `core` traits, which understand `#[crux(...)]`
+//! attributes that the built-in derives don't have.
+
+use crux_rust_ast::{
+	AstComponent, Diagnostic, Ident, Literal, Span, TokenStream,
+	ast::{Attribute, Enum, Expr, Struct, StructKind},
+	quote,
+};
+
+pub fn crux_debug(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let item = Item::parse(input, "CruxDebug")?;
+	let arms = match &item {
+		Item::Struct(item) => vec![debug_arm(quote! { Self }, &item.name, &item.kind)?],
+		Item::Enum(item) => item
+			.variants
+			.iter()
+			.map(|variant| {
+				let name = &variant.name;
+				debug_arm(quote! { Self::#name }, name, &variant.kind)
+			})
+			.collect::<Result<_, _>>()?,
+	};
+
+	// References are always considered inhabited, so empty enums have to
+	// match on the value itself
+	let matched = if arms.is_empty() {
+		quote! { *self }
+	} else {
+		quote! { self }
+	};
+
+	let header = item.impl_header(quote! { ::core::fmt::Debug });
+	Ok(quote! {
+		#header {
+			fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+				match #matched {
+					#(#arms)*
+				}
+			}
+		}
+	})
+}
+
+pub fn crux_default(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let item = Item::parse(input, "CruxDefault")?;
+	let value = match &item {
+		Item::Struct(item) => default_value(quote! { Self }, &item.kind)?,
+		Item::Enum(item) => {
+			let mut defaults = Vec::new();
+			for variant in &item.variants {
+				if CruxArgs::from_attributes(&variant.attributes)?
+					.default
+					.is_some()
+				{
+					defaults.push(variant);
+				}
+			}
+			let Some(variant) = defaults.first() else {
+				return Err(Diagnostic::new(
+					item.name.span(),
+					"`CruxDefault` enums must mark one variant with `#[crux(default)]`",
+				));
+			};
+			if let Some(extra) = defaults.get(1) {
+				return Err(Diagnostic::new(
+					extra.name.span(),
+					"Only one variant can be marked `#[crux(default)]`",
+				)
+				.note(format!("`{}` is already the default variant", variant.name)));
+			}
+			let name = &variant.name;
+			default_value(quote! { Self::#name }, &variant.kind)?
+		}
+	};
+
+	let header = item.impl_header(quote! { ::core::default::Default });
+	Ok(quote! {
+		#header {
+			fn default() -> Self {
+				#value
+			}
+		}
+	})
+}
+
+/// A match arm that formats a struct, or one variant of an enum.
+fn debug_arm(
+	path: TokenStream,
+	name: &Ident,
+	kind: &StructKind,
+) -> Result<TokenStream, Diagnostic> {
+	let name = unraw(name);
+	Ok(match kind {
+		StructKind::Empty => quote! { #path => f.write_str(#name), },
+		StructKind::Keyed(keyed) => {
+			let mut bindings = Vec::new();
+			let mut fields = Vec::new();
+			let mut skipped = false;
+			for field in &keyed.fields {
+				if CruxArgs::from_attributes(&field.attributes)?.skip {
+					skipped = true;
+					continue;
+				}
+				let field_name = &field.name;
+				let field_str = unraw(field_name);
+				bindings.push(quote! { #field_name });
+				fields.push(quote! { .field(#field_str, #field_name) });
+			}
+			let finish = finish(skipped);
+
+			quote! {
+				#path { #(#bindings,)* .. } => f.debug_struct(#name) #(#fields)* .#finish(),
+			}
+		}
+		StructKind::Tuple(tuple) => {
+			let mut bindings = Vec::new();
+			let mut fields = Vec::new();
+			let mut skipped = false;
+			for (idx, field) in tuple.fields.iter().enumerate() {
+				if CruxArgs::from_attributes(&field.attributes)?.skip {
+					skipped = true;
+					bindings.push(quote! { _ });
+					continue;
+				}
+				let binding = Ident::new(&format!("__field{idx}"), name.span());
+				fields.push(quote! { .field(#binding) });
+				bindings.push(quote! { #binding });
+			}
+			let finish = finish(skipped);
+
+			quote! {
+				#path(#(#bindings),*) => f.debug_tuple(#name) #(#fields)* .#finish(),
+			}
+		}
+	})
+}
+
+/// The method that finishes a debug builder; fields that were skipped are
+/// shown as `..`.
+fn finish(skipped: bool) -> Ident {
+	let name = if skipped {
+		"finish_non_exhaustive"
+	} else {
+		"finish"
+	};
+	Ident::new(name, Span::call_site())
+}
+
+/// Constructs a struct or enum variant, using each field's
+/// `#[crux(default = ...)]` value or its type's default.
+fn default_value(path: TokenStream, kind: &StructKind) -> Result<TokenStream, Diagnostic> {
+	let field_default = |attributes: &[Attribute]| {
+		Ok(match CruxArgs::from_attributes(attributes)?.default {
+			Some(Some(value)) => quote! { #value },
+			_ => quote! { ::core::default::Default::default() },
+		})
+	};
+
+	Ok(match kind {
+		StructKind::Empty => path,
+		StructKind::Keyed(keyed) => {
+			let fields = keyed
+				.fields
+				.iter()
+				.map(|field| {
+					let name = &field.name;
+					let value = field_default(&field.attributes)?;
+					Ok(quote! { #name: #value })
+				})
+				.collect::<Result<Vec<_>, Diagnostic>>()?;
+			quote! { #path { #(#fields),* } }
+		}
+		StructKind::Tuple(tuple) => {
+			let fields = tuple
+				.fields
+				.iter()
+				.map(|field| field_default(&field.attributes))
+				.collect::<Result<Vec<_>, Diagnostic>>()?;
+			quote! { #path(#(#fields),*) }
+		}
+	})
+}
+
+/// A field or variant's name as a string literal, without the `r#` prefix
+/// of raw identifiers.
+fn unraw(name: &Ident) -> Literal {
+	let name = name.to_string();
+	Literal::string(name.strip_prefix("r#").unwrap_or(&name))
+}
+
+//
+//
+// Parsing helpers
+//
+//
+
+/// The item a derive macro was applied to.
+enum Item {
+	Struct(Struct),
+	Enum(Enum),
+}
+impl Item {
+	fn parse(input: TokenStream, derive: &str) -> Result<Self, Diagnostic> {
+		let mut tokens = input.into_iter().peekable();
+		let tokens = &mut tokens;
+
+		if Struct::is_next(tokens) {
+			Ok(Self::Struct(Struct::parse(tokens)?))
+		} else if Enum::is_next(tokens) {
+			Ok(Self::Enum(Enum::parse(tokens)?))
+		} else {
+			Err(Diagnostic::call_site(format!(
+				"`{derive}` can only be derived for structs and enums"
+			)))
+		}
+	}
+
+	/// `impl<...> #trait for Item<...> where ...`, where every generic type
+	/// must implement `trait`.
+	fn impl_header(&self, r#trait: TokenStream) -> TokenStream {
+		let (name, generics, where_clause) = match self {
+			Self::Struct(item) => (&item.name, &item.generics, &item.where_clause),
+			Self::Enum(item) => (&item.name, &item.generics, &item.where_clause),
+		};
+		let impl_generics = generics.impl_generics();
+		let type_generics = generics.type_generics();
+		let predicates = &where_clause.predicates;
+		let params = generics.type_params();
+
+		quote! {
+			impl #impl_generics #r#trait for #name #type_generics
+			where
+				#(#predicates,)*
+				#(#params: #r#trait,)*
+		}
+	}
+}
+
+/// The arguments passed to `#[crux(...)]` attributes.
+#[derive(Default)]
+struct CruxArgs {
+	/// `skip`
+	skip: bool,
+	/// `default` or `default = value`. The inner value is `None` when just
+	/// marking an enum's default variant.
+	default: Option<Option<Expr>>,
+}
+impl CruxArgs {
+	fn from_attributes(attrs: &[Attribute]) -> Result<Self, Diagnostic> {
+		let mut this = Self::default();
+
+		for attr in attrs {
+			if attr.name != "crux" {
+				continue;
+			}
+			let Some(args) = &attr.args else {
+				return Err(
+					Diagnostic::call_site("Expected arguments in `#[crux(...)]`")
+						.help("e.g. `#[crux(skip)]` or `#[crux(default = 1)]`"),
+				);
+			};
+
+			for (key, value) in args.key_values()? {
+				match (key.to_string().as_str(), value) {
+					("skip", None) => this.skip = true,
+					("default", None) => this.default = Some(None),
+					("default", Some(value)) => this.default = Some(Some(value)),
+					(other, _) => {
+						return Err(Diagnostic::new(
+							key.span(),
+							format!("Unknown argument `{other}` in `#[crux(...)]`"),
+						)
+						.note("`#[crux]` accepts `skip`, `default`, and `default = value`"));
+					}
+				}
+			}
+		}
+
+		Ok(this)
+	}
+}
@@ -0,0 +1,252 @@
+//! Macros that embed files in the binary, like `include_bytes!` and
+//! `include_str!`, but with control over where and how they're laid out.
+//!
+//! Embedded files are put in a `static` in their own linker section. Unless a
+//! section is given, that's `.crux.embed`, which Crux's linker scripts place
+//! right after `.rodata`, so every embedded file ends up next to the others.
+
+use {
+	crate::collections::split_entries,
+	crux_rust_ast::{Diagnostic, Literal, Span, TokenStream, TokenTree, quote},
+	std::path::{Path, PathBuf},
+};
+
+/// The largest alignment Rust allows in `#[repr(align(...))]`.
+const MAX_ALIGN: u64 = 1 << 29;
+
+pub fn include_bytes_aligned(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let args = EmbedArgs::parse(input, "include_bytes_aligned", true)?;
+	let (bytes, path) = args.read()?;
+	Ok(args.embed(bytes.len(), &path, quote! { &EMBEDDED.0 }))
+}
+pub fn embed_str(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let args = EmbedArgs::parse(input, "embed_str", false)?;
+	let (bytes, path) = args.read()?;
+	if let Err(err) = str::from_utf8(&bytes) {
+		return Err(Diagnostic::new(
+			args.path.span(),
+			format!("`{}` isn't valid UTF-8: {err}", path.display()),
+		)
+		.help("use `include_bytes_aligned!` to embed binary files"));
+	}
+
+	// The file was checked above, so it's still valid UTF-8 when it's
+	// included
+	Ok(args.embed(
+		bytes.len(),
+		&path,
+		quote! { unsafe { ::core::str::from_utf8_unchecked(&EMBEDDED.0) } },
+	))
+}
+
+/// The arguments to an embedding macro: a path, followed by `align = N` and
+/// `section = "name"` options. `include_bytes_aligned!` takes the alignment
+/// before the path instead, since it's required.
+struct EmbedArgs {
+	path: Literal,
+	align: u64,
+	section: Option<Literal>,
+}
+impl EmbedArgs {
+	fn parse(input: TokenStream, macro_name: &str, align_first: bool) -> Result<Self, Diagnostic> {
+		let usage = if align_first {
+			format!(
+				"use `{macro_name}!(64, \"path\")`, optionally followed by `section = \"name\"`"
+			)
+		} else {
+			format!(
+				"use `{macro_name}!(\"path\")`, optionally followed by `align = 64` and `section = \"name\"`"
+			)
+		};
+
+		let mut entries = split_entries(input).into_iter();
+		let mut align = 1;
+		if align_first {
+			let Some(entry) = entries.next() else {
+				return Err(Diagnostic::call_site(format!(
+					"`{macro_name}!` needs an alignment and a path"
+				))
+				.help(usage));
+			};
+			align = parse_align(&entry)?;
+		}
+		let path = match entries.next().as_deref() {
+			Some([TokenTree::Literal(path)]) if string_value(path).is_some() => path.clone(),
+			Some(entry) => {
+				return Err(Diagnostic::new(
+					entry[0].span(),
+					format!("Expected a path to embed in `{macro_name}!`"),
+				)
+				.help("paths are string literals relative to the crate's root"));
+			}
+			None => {
+				return Err(Diagnostic::call_site(format!(
+					"`{macro_name}!` needs a path to embed"
+				))
+				.help(usage));
+			}
+		};
+
+		let mut section = None;
+		for entry in entries {
+			let (key, value) = match entry.as_slice() {
+				[TokenTree::Ident(key), TokenTree::Punct(eq), value @ ..]
+					if eq.as_char() == '=' && !value.is_empty() =>
+				{
+					(key, value)
+				}
+				_ => {
+					return Err(Diagnostic::new(
+						entry[0].span(),
+						format!("Expected a `key = value` option in `{macro_name}!`"),
+					)
+					.help(usage));
+				}
+			};
+			match (key.to_string().as_str(), value) {
+				("align", value) if !align_first => align = parse_align(value)?,
+				("section", [TokenTree::Literal(name)]) if string_value(name).is_some() => {
+					section = Some(name.clone());
+				}
+				("section", value) => {
+					return Err(Diagnostic::new(
+						value[0].span(),
+						"Expected a string literal for `section`",
+					));
+				}
+				(other, _) => {
+					return Err(Diagnostic::new(
+						key.span(),
+						format!("Unknown option `{other}` in `{macro_name}!`"),
+					)
+					.help(usage));
+				}
+			}
+		}
+
+		Ok(Self {
+			path,
+			align,
+			section,
+		})
+	}
+
+	/// Reads the file being embedded, returning its contents and full path.
+	fn read(&self) -> Result<(Vec<u8>, PathBuf), Diagnostic> {
+		let Ok(root) = std::env::var("CARGO_MANIFEST_DIR") else {
+			return Err(Diagnostic::call_site(
+				"Files can only be embedded in crates built by Cargo",
+			));
+		};
+		let path = Path::new(&root).join(string_value(&self.path).unwrap());
+		let bytes = std::fs::read(&path).map_err(|err| {
+			Diagnostic::new(
+				self.path.span(),
+				format!("Failed to read `{}`: {err}", path.display()),
+			)
+		})?;
+
+		Ok((bytes, path))
+	}
+
+	/// Generates a block that embeds the file at `path` in an aligned
+	/// `static`, called `EMBEDDED`, and evaluates to `value`.
+	fn embed(&self, len: usize, path: &Path, value: TokenStream) -> TokenStream {
+		let align = Literal::u64_unsuffixed(self.align);
+		let path = Literal::string(&path.to_string_lossy());
+		let section = match &self.section {
+			Some(section) => quote! { #[unsafe(link_section = #section)] },
+			None => quote! {
+				#[cfg_attr(
+					all(not(windows), not(target_os = "macos")),
+					unsafe(link_section = ".crux.embed")
+				)]
+				#[cfg_attr(target_os = "macos", unsafe(link_section = "__DATA,__crux_embed"))]
+			},
+		};
+
+		// `include_bytes!` makes Cargo rebuild the crate when the file changes
+		quote! {
+			{
+				#[repr(C, align(#align))]
+				struct Aligned<T: ?Sized>(T);
+				#section
+				static EMBEDDED: Aligned<[u8; #len]> = Aligned(*::core::include_bytes!(#path));
+				#value
+			}
+		}
+	}
+}
+
+/// Parses an alignment, which must be a power of 2 that Rust's
+/// `#[repr(align(...))]` accepts.
+fn parse_align(tokens: &[TokenTree]) -> Result<u64, Diagnostic> {
+	let span = tokens.first().map_or_else(Span::call_site, TokenTree::span);
+	let align = match tokens {
+		[TokenTree::Literal(literal)] => literal.to_string().replace('_', "").parse::<u64>().ok(),
+		_ => None,
+	};
+	match align {
+		Some(align) if align.is_power_of_two() && align <= MAX_ALIGN => Ok(align),
+		_ => Err(Diagnostic::new(
+			span,
+			"Expected an alignment in bytes, which must be a power of 2",
+		)
+		.note(format!(
+			"the largest alignment Rust supports is {MAX_ALIGN}"
+		))),
+	}
+}
+
+/// The contents of a plain string literal, without its quotes.
+fn string_value(literal: &Literal) -> Option<String> {
+	literal
+		.to_string()
+		.strip_prefix('"')
+		.and_then(|string| string.strip_suffix('"'))
+		.map(String::from)
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn embed_args() {
+		let args = EmbedArgs::parse(
+			"4_096, \"Cargo.toml\", section = \".fonts\""
+				.parse()
+				.unwrap(),
+			"include_bytes_aligned",
+			true,
+		)
+		.unwrap();
+		assert_eq!(args.align, 4096);
+		assert_eq!(string_value(&args.path).unwrap(), "Cargo.toml");
+		assert_eq!(args.section.unwrap().to_string(), "\".fonts\"");
+
+		let args = EmbedArgs::parse("\"Cargo.toml\"".parse().unwrap(), "embed_str", false).unwrap();
+		assert_eq!(args.align, 1);
+		assert!(args.section.is_none());
+		let (bytes, _) = args.read().unwrap();
+		assert!(bytes.starts_with(b"[package]"));
+		assert!(embed_str("\"Cargo.toml\", align = 16".parse().unwrap()).is_ok());
+
+		let parse = |input: &str, align_first| {
+			EmbedArgs::parse(input.parse().unwrap(), "test", align_first).is_ok()
+		};
+		assert!(!parse("3, \"Cargo.toml\"", true));
+		assert!(!parse("\"Cargo.toml\"", true));
+		assert!(!parse("\"Cargo.toml\", align = 64", true));
+		assert!(!parse("\"Cargo.toml\", section = fonts", false));
+		assert!(!parse("\"Cargo.toml\", colour = 3", false));
+		assert!(!parse("Cargo", false));
+		assert!(embed_str("\"missing.txt\"".parse().unwrap()).is_err());
+	}
+}
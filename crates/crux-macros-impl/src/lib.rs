@@ -1,35 +1,315 @@
 use crux_rust_ast::{
-	AstComponent, Ident, Span, TokenStream, TokenTree,
-	ast::{Attribute, FunctionQualifiers},
+	AstComponent, Diagnostic, Ident, Span, TokenIter, TokenStream, TokenTree,
+	ast::{Attribute, FunctionQualifiers, Visibility},
 	quote,
 };
 
-pub fn test(_attr: TokenStream, input: TokenStream) -> TokenStream {
+mod cli;
+mod collections;
+mod derive;
+mod embed;
+mod wayland;
+
+pub use {
+	cli::{cli_parser, cli_subcommand},
+	collections::{map, set},
+	derive::{crux_debug, crux_default},
+	embed::{embed_str, include_bytes_aligned},
+	wayland::wayland_protocols,
+};
+
+pub fn test(attr: TokenStream, input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let TestArgs {
+		should_panic,
+		ignore,
+		ignore_reason,
+		timeout,
+	} = TestArgs::parse(attr)?;
+
+	let (qualifiers, function_name) = parse_function(&input, "test")?;
+	let test_name = function_name.to_string();
+	let test = Ident::new(&format!("__crux_test_{test_name}"), function_name.span());
+	// Async tests are run to completion by the test's function pointer
+	let call = if qualifiers.is_async {
+		quote! { crux::task::block_on(#function_name()) }
+	} else {
+		quote! { #function_name() }
+	};
+	// Tests may return `()` or a `Result`, which the harness reports
+	let func = quote! { || crux::rt::test_harness::TestResult::report(#call) };
+
+	Ok(quote! {
+		#[allow(non_upper_case_globals)]
+		const #test: crux::rt::test_harness::Test = crux::rt::test_harness::Test {
+			module: crux::lang::module_path!(),
+			name: #test_name,
+			func: #func,
+			should_panic: #should_panic,
+			ignore: #ignore,
+			ignore_reason: #ignore_reason,
+			timeout: #timeout,
+		};
+		crux::rt::hook::hook! {
+			event: crux::events::run_tests,
+			func: #test,
+			constraints: []
+		}
+		#input
+	})
+}
+pub fn bench(_attr: TokenStream, input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let (_, function_name) = parse_function(&input, "bench")?;
+	let bench_name = function_name.to_string();
+	let bench = Ident::new(&format!("__crux_bench_{bench_name}"), function_name.span());
+
+	Ok(quote! {
+		#[allow(non_upper_case_globals)]
+		const #bench: crux::bench::Bench = crux::bench::Bench {
+			module: crux::lang::module_path!(),
+			name: #bench_name,
+			func: #function_name,
+		};
+		crux::rt::hook::hook! {
+			event: crux::events::run_benches,
+			func: #bench,
+			constraints: []
+		}
+		#input
+	})
+}
+pub fn profile(attr: TokenStream, input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let (_, function_name) = parse_function(&input, "profile")?;
+	let scope_name = match attr.into_iter().next() {
+		Some(TokenTree::Literal(name)) => quote! { #name },
+		Some(other) => {
+			return Err(Diagnostic::new(
+				other.span(),
+				format!("Expected a string literal in `#[profile(...)]`, found `{other}`"),
+			)
+			.help("name the scope with `#[profile(\"name\")]`"));
+		}
+		None => {
+			let name = function_name.to_string();
+			quote! { #name }
+		}
+	};
+
+	// The function body is the last token; everything before it is the
+	// signature
+	let mut tokens: Vec<TokenTree> = input.into_iter().collect();
+	let Some(TokenTree::Group(body)) = tokens.pop() else {
+		return Err(Diagnostic::new(
+			function_name.span(),
+			"`#[profile]` can only be used on functions with a body",
+		));
+	};
+	let body = body.stream();
+	let signature = TokenStream::from_iter(tokens);
+
+	Ok(quote! {
+		#signature {
+			crux::profile::scope!(#scope_name);
+			#body
+		}
+	})
+}
+
+pub fn main(attr: TokenStream, input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	if let Some(token) = attr.into_iter().next() {
+		return Err(Diagnostic::new(
+			token.span(),
+			"`#[crux::main]` doesn't take any arguments",
+		));
+	}
+	let (qualifiers, function_name) = parse_function(&input, "crux::main")?;
+	if function_name == "crux_main" {
+		return Err(Diagnostic::new(
+			function_name.span(),
+			"`#[crux::main]` functions can't be named `crux_main`",
+		)
+		.help("name the function `main` instead"));
+	}
+
+	// The function is called without any arguments or generics
+	let mut tokens = input.clone().into_iter();
+	tokens.find(|token| matches!(token, TokenTree::Ident(ident) if *ident == function_name));
+	match tokens.next() {
+		Some(TokenTree::Group(params)) if params.stream().is_empty() => {}
+		Some(TokenTree::Group(params)) => {
+			return Err(Diagnostic::new(
+				params.span(),
+				"`#[crux::main]` functions can't take arguments",
+			)
+			.help("use `crux::rt::proc::cli_args()` to get the program's arguments"));
+		}
+		other => {
+			return Err(Diagnostic::new(
+				other.map_or(function_name.span(), |token| token.span()),
+				"`#[crux::main]` functions can't be generic",
+			));
+		}
+	}
+
+	let call = if qualifiers.is_async {
+		quote! { crux::task::block_on(#function_name()) }
+	} else {
+		quote! { #function_name() }
+	};
+
+	// Glob imports can't shadow built-in attributes, so Crux's `test` and
+	// `bench` are imported by name. The `extern crate` makes sure Crux's
+	// runtime (panic handler, entrypoint, etc.) gets linked even if nothing
+	// else in the crate names it.
+	Ok(quote! {
+		extern crate crux as _;
+		#[allow(unused_imports)]
+		use crux::prelude::{*, bench, test};
+
+		#[unsafe(no_mangle)]
+		fn crux_main() {
+			crux::rt::entrypoint::MainResult::report(#call)
+		}
+		#input
+	})
+}
+
+/// Returns the qualifiers (`async`, `unsafe`, etc.) and name of the function
+/// in `input`. `macro_name` is the attribute being expanded, for errors.
+fn parse_function(
+	input: &TokenStream,
+	macro_name: &str,
+) -> Result<(FunctionQualifiers, Ident), Diagnostic> {
 	let mut tokens = input.clone().into_iter().peekable();
 	let tokens = &mut tokens;
 
 	while Attribute::is_next(tokens) {
 		Attribute::skip(tokens);
 	}
-	FunctionQualifiers::skip(tokens);
+	Visibility::skip(tokens);
+	let not_a_function = |span| {
+		Diagnostic::new(
+			span,
+			format!("`#[{macro_name}]` can only be used on functions"),
+		)
+	};
+	if !FunctionQualifiers::is_next(tokens) {
+		return Err(not_a_function(tokens.next_span()));
+	}
+	let qualifiers = FunctionQualifiers::parse(tokens).map_err(|_| {
+		Diagnostic::new(tokens.next_span(), "Expected an ABI string after `extern`")
+	})?;
 	tokens.next(); // fn keyword
 
 	let Some(TokenTree::Ident(function_name)) = tokens.next() else {
-		panic!(); // TODO nicer error
+		return Err(not_a_function(tokens.next_span()));
 	};
+	Ok((qualifiers, function_name))
+}
 
-	quote! {
-		crux::rt::hook::hook! {
-			event: crux::events::run_tests,
-			func: #function_name,
-			constraints: []
+/// The arguments to `#[test(...)]`, as tokens for the matching fields of
+/// `crux::rt::test_harness::Test`.
+struct TestArgs {
+	should_panic: TokenStream,
+	ignore: TokenStream,
+	ignore_reason: TokenStream,
+	timeout: TokenStream,
+}
+impl TestArgs {
+	fn parse(attr: TokenStream) -> Result<Self, Diagnostic> {
+		let mut this = Self {
+			should_panic: quote! { crux::rt::test_harness::ShouldPanic::No },
+			ignore: quote! { false },
+			ignore_reason: quote! { ::core::option::Option::None },
+			timeout: quote! { ::core::option::Option::None },
+		};
+
+		let mut tokens = attr.into_iter().peekable();
+		while let Some(token) = tokens.next() {
+			let TokenTree::Ident(key) = token else {
+				return Err(Diagnostic::new(
+					token.span(),
+					format!("Expected argument name in `#[test(...)]`, found `{token}`"),
+				));
+			};
+
+			// Supports both `key = "value"` and libtest's
+			// `should_panic(expected = "value")`
+			let mut value = None;
+			if tokens.next_is_punct('=') {
+				tokens.next();
+				value = tokens.next();
+			} else if let Some(TokenTree::Group(group)) = tokens.peek() {
+				let mut inner = group.stream().into_iter().peekable();
+				let span = group.span();
+				tokens.next();
+				let has_expected = inner.next_is_ident("expected") && {
+					inner.next();
+					inner.next_is_punct('=')
+				};
+				if !has_expected {
+					return Err(Diagnostic::new(
+						span,
+						format!("Expected `expected = \"...\"` in `#[test({key}(...))]`"),
+					));
+				}
+				inner.next();
+				value = inner.next();
+			}
+			if tokens.next_is_punct(',') {
+				tokens.next();
+			}
+
+			let value = match value {
+				Some(TokenTree::Literal(literal)) => Some(literal),
+				Some(other) => {
+					return Err(Diagnostic::new(
+						other.span(),
+						format!("Expected a literal for `{key}`, found `{other}`"),
+					));
+				}
+				None => None,
+			};
+			match (key.to_string().as_str(), value) {
+				("should_panic", None) => {
+					this.should_panic = quote! { crux::rt::test_harness::ShouldPanic::Yes }
+				}
+				("should_panic", Some(message)) => {
+					this.should_panic =
+						quote! { crux::rt::test_harness::ShouldPanic::WithMessage(#message) }
+				}
+				("ignore", reason) => {
+					this.ignore = quote! { true };
+					if let Some(reason) = reason {
+						this.ignore_reason = quote! { ::core::option::Option::Some(#reason) };
+					}
+				}
+				("timeout_ms", Some(millis)) => {
+					this.timeout = quote! {
+						::core::option::Option::Some(crux::rt::time::Duration::from_millis(#millis))
+					}
+				}
+				("timeout_ms", None) => {
+					return Err(
+						Diagnostic::new(key.span(), "Expected a value for `timeout_ms`")
+							.help("set the timeout in milliseconds, e.g. `timeout_ms = 500`"),
+					);
+				}
+				(other, _) => {
+					return Err(Diagnostic::new(
+						key.span(),
+						format!("Unknown argument `{other}` in `#[test(...)]`"),
+					)
+					.note("`#[test]` accepts `should_panic`, `ignore`, and `timeout_ms`"));
+				}
+			}
 		}
-		#input
+
+		Ok(this)
 	}
 }
-pub fn concat_idents(input: TokenStream) -> TokenStream {
-	TokenStream::from_iter([TokenTree::Ident(Ident::new(
+pub fn concat_idents(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	Ok(TokenStream::from_iter([TokenTree::Ident(Ident::new(
 		input.to_string().as_str(),
 		Span::call_site(),
-	))])
+	))]))
 }
@@ -0,0 +1,449 @@
+//! Generates Crux's Wayland interface definitions from protocol XML files
+//! (`wayland.xml`, `xdg-shell.xml`, etc.).
+//!
+//! Each `<interface>` becomes an entry in the `interfaces!` macro from
+//! `crux::ui::display::wayland::interfaces`. Requests and events get their
+//! opcode from their position in the file, like in libwayland's scanner, and
+//! the interface's `error` enum (if it has one) becomes its `errors`.
+
+use {
+	crux_rust_ast::{Diagnostic, Ident, Literal, Span, TokenStream, TokenTree, quote},
+	std::path::Path,
+};
+
+pub fn wayland_protocols(input: TokenStream) -> Result<TokenStream, Diagnostic> {
+	let Ok(root) = std::env::var("CARGO_MANIFEST_DIR") else {
+		return Err(Diagnostic::call_site(
+			"`wayland_protocols!` can only be used in crates built by Cargo",
+		));
+	};
+
+	let mut tracked = Vec::new();
+	let mut interfaces = Vec::new();
+	for token in input {
+		let literal = match token {
+			TokenTree::Punct(punct) if punct.as_char() == ',' => continue,
+			TokenTree::Literal(literal) => literal,
+			other => {
+				return Err(Diagnostic::new(
+					other.span(),
+					format!("Expected a path to a protocol XML file, found `{other}`"),
+				)
+				.help("paths are string literals relative to the crate's root"));
+			}
+		};
+		let Some(path) = literal
+			.to_string()
+			.strip_prefix('"')
+			.and_then(|path| path.strip_suffix('"'))
+			.map(|path| Path::new(&root).join(path))
+		else {
+			return Err(Diagnostic::new(
+				literal.span(),
+				format!("Expected a path to a protocol XML file, found `{literal}`"),
+			));
+		};
+
+		let xml = std::fs::read_to_string(&path).map_err(|err| {
+			Diagnostic::new(
+				literal.span(),
+				format!("Failed to read `{}`: {err}", path.display()),
+			)
+		})?;
+		let protocol = parse_xml(&xml).map_err(|err| {
+			Diagnostic::new(
+				literal.span(),
+				format!("Failed to parse `{}`: {err}", path.display()),
+			)
+		})?;
+		if protocol.name != "protocol" {
+			return Err(Diagnostic::new(
+				literal.span(),
+				format!(
+					"`{}` isn't a Wayland protocol; its root element is `<{}>`",
+					path.display(),
+					protocol.name
+				),
+			));
+		}
+		for interface in protocol.children("interface") {
+			interfaces.push(interface_tokens(interface).map_err(|err| {
+				Diagnostic::new(literal.span(), format!("In `{}`: {err}", path.display()))
+			})?);
+		}
+
+		// Makes Cargo rebuild the crate when the protocol file changes
+		let path = Literal::string(&path.to_string_lossy());
+		tracked.push(quote! { const _: &[u8] = ::core::include_bytes!(#path); });
+	}
+
+	Ok(quote! {
+		#(#tracked)*
+		interfaces! {
+			#(#interfaces)*
+		}
+	})
+}
+
+/// Generates one interface's entry in `interfaces!`.
+fn interface_tokens(interface: &Element) -> Result<TokenStream, String> {
+	let ffi_name = interface.required_attr("name")?;
+	let version = interface
+		.required_attr("version")?
+		.parse()
+		.map(Literal::u32_unsuffixed)
+		.map_err(|_| format!("`{ffi_name}` has an invalid version"))?;
+	let docs = interface.summary();
+	let name = Ident::new(&camel_case(ffi_name), Span::call_site());
+	let ffi_name_ident = Ident::new(ffi_name, Span::call_site());
+
+	let requests = messages_tokens(interface, "request")?;
+	let events = messages_tokens(interface, "event")?;
+	let mut errors = Vec::new();
+	if let Some(error) = interface
+		.children("enum")
+		.find(|element| element.attr("name") == Some("error"))
+	{
+		for entry in error.children("entry") {
+			let entry_name = entry.required_attr("name")?;
+			let value = entry.required_attr("value")?;
+			let value = match value.strip_prefix("0x") {
+				Some(hex) => u32::from_str_radix(hex, 16),
+				None => value.parse(),
+			}
+			.map_err(|_| format!("`{ffi_name}.error.{entry_name}` has an invalid value"))?;
+			let docs = entry
+				.attr("summary")
+				.map(|summary| quote! { #[doc = #summary] });
+			let entry_name = Ident::new(&camel_case(entry_name), Span::call_site());
+			let value = Literal::u32_unsuffixed(value);
+			errors.push(quote! { #docs #entry_name: #value });
+		}
+	}
+
+	Ok(quote! {
+		#docs
+		interface #name {
+			ffi_name: #ffi_name_ident;
+			version: #version;
+
+			requests { #(#requests)* }
+			events { #(#events)* }
+			errors { #(#errors)* }
+		}
+	})
+}
+
+/// Generates the requests or events (depending on `kind`) in an interface.
+fn messages_tokens(interface: &Element, kind: &str) -> Result<Vec<TokenStream>, String> {
+	let interface_name = interface.required_attr("name")?;
+	let mut messages = Vec::new();
+
+	for (opcode, message) in interface.children(kind).enumerate() {
+		let message_name = message.required_attr("name")?;
+		let mut args = Vec::new();
+		for arg in message.children("arg") {
+			let arg_name = arg.required_attr("name")?;
+			let nullable = arg.attr("allow-null") == Some("true");
+			let ty = match (arg.required_attr("type")?, nullable) {
+				("int", _) => quote! { int },
+				("uint", _) => quote! { uint },
+				("fixed", _) => quote! { fixed },
+				("array", _) => quote! { array },
+				("fd", _) => quote! { fd },
+				("string", false) => quote! { string },
+				("string", true) => quote! { nullable string },
+				("object", false) => quote! { object },
+				("object", true) => quote! { nullable object },
+				("new_id", _) if arg.attr("interface").is_none() => quote! { untyped new_id },
+				("new_id", _) => quote! { new_id },
+				(other, _) => {
+					return Err(format!(
+						"`{interface_name}.{message_name}.{arg_name}` has unknown type `{other}`"
+					));
+				}
+			};
+			let arg_name = field_ident(arg_name);
+			args.push(quote! { #arg_name: #ty });
+		}
+
+		let docs = message.summary();
+		let name = Ident::new(&camel_case(message_name), Span::call_site());
+		let args = (!args.is_empty()).then(|| quote! { (#(#args),*) });
+		let opcode = Literal::u16_unsuffixed(opcode as u16);
+		messages.push(quote! { #docs #name #args: #opcode });
+	}
+
+	Ok(messages)
+}
+
+/// Converts a `snake_case` protocol name to `CamelCase`, e.g. `wl_display`
+/// to `WlDisplay`.
+fn camel_case(name: &str) -> String {
+	let mut out = String::with_capacity(name.len());
+	for word in name.split('_') {
+		let mut chars = word.chars();
+		if let Some(first) = chars.next() {
+			out.extend(first.to_uppercase());
+			out.extend(chars);
+		}
+	}
+	out
+}
+
+/// Makes an identifier for an argument name, escaping Rust keywords.
+fn field_ident(name: &str) -> Ident {
+	const KEYWORDS: &[&str] = &[
+		"abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+		"dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+		"let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+		"return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+		"unsized", "use", "virtual", "where", "while", "yield",
+	];
+	if KEYWORDS.contains(&name) {
+		Ident::new_raw(name, Span::call_site())
+	} else {
+		Ident::new(name, Span::call_site())
+	}
+}
+
+//
+//
+// XML
+//
+//
+
+/// An XML element. Protocol files only use text and comments for
+/// documentation, so they're skipped while parsing.
+#[derive(Debug, PartialEq)]
+struct Element {
+	name: String,
+	attributes: Vec<(String, String)>,
+	children: Vec<Element>,
+}
+impl Element {
+	fn attr(&self, name: &str) -> Option<&str> {
+		self.attributes
+			.iter()
+			.find(|(key, _)| key == name)
+			.map(|(_, value)| value.as_str())
+	}
+	fn required_attr(&self, name: &str) -> Result<&str, String> {
+		self.attr(name)
+			.ok_or_else(|| format!("`<{}>` is missing its `{name}` attribute", self.name))
+	}
+	/// The child elements named `name`, in order.
+	fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+		self.children.iter().filter(move |child| child.name == name)
+	}
+	/// A doc attribute with the summary from this element's `<description>`.
+	fn summary(&self) -> Option<TokenStream> {
+		let summary = self
+			.children("description")
+			.find_map(|desc| desc.attr("summary"))?;
+		Some(quote! { #[doc = #summary] })
+	}
+}
+
+/// Parses an XML document and returns its root element.
+fn parse_xml(xml: &str) -> Result<Element, String> {
+	let mut parser = XmlParser { xml, pos: 0 };
+	parser.skip_misc()?;
+	let root = parser.element()?;
+	parser.skip_misc()?;
+	if parser.pos != xml.len() {
+		return Err(parser.error("Unexpected content after the root element"));
+	}
+	Ok(root)
+}
+
+struct XmlParser<'a> {
+	xml: &'a str,
+	pos: usize,
+}
+impl XmlParser<'_> {
+	fn rest(&self) -> &str {
+		&self.xml[self.pos..]
+	}
+	fn error(&self, message: &str) -> String {
+		let line = self.xml[..self.pos].lines().count().max(1);
+		format!("{message} on line {line}")
+	}
+	fn skip_whitespace(&mut self) {
+		let rest = self.rest();
+		self.pos += rest.len() - rest.trim_start().len();
+	}
+	/// Skips past the next `end`.
+	fn skip_past(&mut self, end: &str) -> Result<(), String> {
+		match self.rest().find(end) {
+			Some(idx) => {
+				self.pos += idx + end.len();
+				Ok(())
+			}
+			None => Err(self.error(&format!("Expected `{end}`"))),
+		}
+	}
+	/// Skips whitespace, comments, the XML declaration, and doctypes.
+	fn skip_misc(&mut self) -> Result<(), String> {
+		loop {
+			self.skip_whitespace();
+			let rest = self.rest();
+			if rest.starts_with("<!--") {
+				self.skip_past("-->")?;
+			} else if rest.starts_with("<?") {
+				self.skip_past("?>")?;
+			} else if rest.starts_with("<!") {
+				self.skip_past(">")?;
+			} else {
+				return Ok(());
+			}
+		}
+	}
+	fn name(&mut self) -> Result<String, String> {
+		let rest = self.rest();
+		let len = rest
+			.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')))
+			.unwrap_or(rest.len());
+		if len == 0 {
+			return Err(self.error("Expected a name"));
+		}
+		let name = rest[..len].to_string();
+		self.pos += len;
+		Ok(name)
+	}
+	fn expect(&mut self, expected: &str) -> Result<(), String> {
+		if !self.rest().starts_with(expected) {
+			return Err(self.error(&format!("Expected `{expected}`")));
+		}
+		self.pos += expected.len();
+		Ok(())
+	}
+
+	fn element(&mut self) -> Result<Element, String> {
+		self.expect("<")?;
+		let name = self.name()?;
+		let mut attributes = Vec::new();
+		loop {
+			self.skip_whitespace();
+			if self.rest().starts_with("/>") {
+				self.pos += 2;
+				return Ok(Element {
+					name,
+					attributes,
+					children: Vec::new(),
+				});
+			}
+			if self.rest().starts_with('>') {
+				self.pos += 1;
+				break;
+			}
+
+			let key = self.name()?;
+			self.skip_whitespace();
+			self.expect("=")?;
+			self.skip_whitespace();
+			let quote = match self.rest().chars().next() {
+				Some(quote @ ('"' | '\'')) => quote,
+				_ => return Err(self.error("Expected a quoted attribute value")),
+			};
+			self.pos += 1;
+			let Some(len) = self.rest().find(quote) else {
+				return Err(self.error("Unterminated attribute value"));
+			};
+			let value = unescape(&self.rest()[..len]);
+			self.pos += len + 1;
+			attributes.push((key, value));
+		}
+
+		let mut children = Vec::new();
+		loop {
+			// Text is only ever documentation
+			match self.rest().find('<') {
+				Some(idx) => self.pos += idx,
+				None => return Err(self.error(&format!("Unclosed `<{name}>`"))),
+			}
+			let rest = self.rest();
+			if rest.starts_with("</") {
+				self.pos += 2;
+				let closing = self.name()?;
+				if closing != name {
+					return Err(self.error(&format!("Expected `</{name}>`, found `</{closing}>`")));
+				}
+				self.skip_whitespace();
+				self.expect(">")?;
+				return Ok(Element {
+					name,
+					attributes,
+					children,
+				});
+			} else if rest.starts_with("<!--") {
+				self.skip_past("-->")?;
+			} else if rest.starts_with("<![CDATA[") {
+				self.skip_past("]]>")?;
+			} else {
+				children.push(self.element()?);
+			}
+		}
+	}
+}
+
+/// Replaces XML's predefined entities in an attribute value.
+fn unescape(value: &str) -> String {
+	value
+		.replace("&lt;", "<")
+		.replace("&gt;", ">")
+		.replace("&quot;", "\"")
+		.replace("&apos;", "'")
+		.replace("&amp;", "&")
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn xml() {
+		let root = parse_xml(
+			r#"<?xml version="1.0" encoding="UTF-8"?>
+			<!-- A comment -->
+			<protocol name="test">
+				<interface name="wl_test" version='2'>
+					<description summary="a &quot;test&quot;">
+						Text is skipped, <!-- and so are comments -->
+					</description>
+					<request name="ping"><arg name="serial" type="uint"/></request>
+				</interface>
+			</protocol>
+			"#,
+		)
+		.unwrap();
+
+		assert_eq!(root.name, "protocol");
+		let interface = root.children("interface").next().unwrap();
+		assert_eq!(interface.attr("version"), Some("2"));
+		assert_eq!(interface.children.len(), 2);
+		let description = interface.children("description").next().unwrap();
+		assert_eq!(description.attr("summary"), Some("a \"test\""));
+		assert!(description.children.is_empty());
+		let request = interface.children("request").next().unwrap();
+		assert_eq!(request.children[0].attr("type"), Some("uint"));
+
+		assert!(parse_xml("<a><b></a>").is_err());
+		assert!(parse_xml("<a></a><b/>").is_err());
+		assert!(parse_xml("<a attr=value/>").is_err());
+	}
+
+	#[test]
+	fn names() {
+		assert_eq!(camel_case("wl_display"), "WlDisplay");
+		assert_eq!(camel_case("get_xdg_surface"), "GetXdgSurface");
+		assert_eq!(field_ident("type").to_string(), "r#type");
+		assert_eq!(field_ident("surface").to_string(), "surface");
+	}
+}
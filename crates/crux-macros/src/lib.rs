@@ -1,9 +1,9 @@
 use proc_macro::TokenStream;
 
 macro_rules! def {
-	($($(#[doc = $docs:literal])* $kind:ident$(($type:ident))? $macro:ident),* $(,)?) => {
+	($($(#[doc = $docs:literal])* $kind:ident$(($($type:tt)*))? $macro:ident),* $(,)?) => {
 		$(
-			def!(@$kind$(($type))? $(#[doc = $docs])* $macro);
+			def!(@$kind$(($($type)*))? $(#[doc = $docs])* $macro);
 		)*
 	};
 
@@ -12,7 +12,16 @@ macro_rules! def {
 		$(#[doc = $docs])*
 		#[proc_macro_attribute]
 		pub fn $macro(attr: TokenStream, input: TokenStream) -> TokenStream {
-			crux_macros_impl::$macro(attr.into(), input.into()).into()
+			match crux_macros_impl::$macro(attr.into(), input.clone().into()) {
+				Ok(output) => output.into(),
+				// Keep the item so its uses don't cause a pile of unrelated
+				// errors
+				Err(error) => {
+					let mut output: TokenStream = error.to_compile_error().into();
+					output.extend(input);
+					output
+				}
+			}
 		}
 	};
 	// Procedural macros
@@ -20,21 +29,71 @@ macro_rules! def {
 		$(#[doc = $docs])*
 		#[proc_macro]
 		pub fn $macro(input: TokenStream) -> TokenStream {
-			crux_macros_impl::$macro(input.into()).into()
+			crux_macros_impl::$macro(input.into())
+				.unwrap_or_else(|error| error.to_compile_error())
+				.into()
 		}
 	};
 	// Derive macros
-	(@derive($type:ident) $(#[doc = $docs:literal])* $macro:ident) => {
+	(@derive($($type:tt)*) $(#[doc = $docs:literal])* $macro:ident) => {
 		$(#[doc = $docs])*
-		#[proc_macro_derive($type)]
+		#[proc_macro_derive($($type)*)]
 		pub fn $macro(input: TokenStream) -> TokenStream {
-			crux_macros_impl::$macro(input.into()).into()
+			crux_macros_impl::$macro(input.into())
+				.unwrap_or_else(|error| error.to_compile_error())
+				.into()
 		}
 	}
 }
 
 def! {
+	/// Registers a function as a test with `crux::events::run_tests`.
+	///
+	/// Like libtest, the attribute accepts a few arguments:
+	/// - `#[test(should_panic)]`: The test passes only if it panics.
+	/// - `#[test(should_panic = "msg")]`: The test passes only if it panics
+	///   and its output contains `msg`.
+	/// - `#[test(ignore)]` or `#[test(ignore = "reason")]`: The test is only
+	///   run when `--ignored` or `--include-ignored` is passed to the harness.
+	/// - `#[test(timeout_ms = 500)]`: The test fails if it runs for longer
+	///   than the given number of milliseconds.
+	///
+	/// Tests can also be `async fn`s, in which case they're run with
+	/// `crux::task::block_on`. Like libtest, tests can return `Result<(), E>`;
+	/// returning `Err` fails the test and prints the error's `Debug` output.
 	attr test,
+	/// Registers a function taking a `&mut crux::bench::Bencher` as a
+	/// benchmark with `crux::events::run_benches`. See the docs for
+	/// `crux::bench` for more info.
+	attr bench,
+	/// Times every call to a function with `crux::profile::scope!`, using the
+	/// function's name as the scope's name. A different name can be given
+	/// with `#[profile("name")]`. Requires Crux's `profile` feature.
+	attr profile,
+	/// Marks a function as the program's entrypoint, called by Crux at
+	/// startup. This replaces defining `#[unsafe(no_mangle)] fn crux_main()`
+	/// by hand, and also imports Crux's prelude into the module:
+	///
+	/// ```rs
+	/// #![no_std]
+	/// #![no_main]
+	///
+	/// #[crux::main]
+	/// fn main() -> Result<(), crux::num::ParseIntError> {
+	///     let port: u16 = crux::num::parse("8080")?;
+	///     println!("Listening on port {}", port);
+	///     Ok(())
+	/// }
+	/// ```
+	///
+	/// The function can return `()` or a `Result<(), E>`; if it returns `Err`,
+	/// the error's `Debug` output is printed and the program exits with code
+	/// 1. It can also be an `async fn`, in which case it's run with
+	/// `crux::task::block_on`. The crate still needs `#![no_std]` and
+	/// `#![no_main]`, since attributes can't add those, and Crux's `main`
+	/// feature has to be enabled (it is by default). The attribute also links
+	/// Crux into the binary, so `extern crate crux;` isn't needed.
+	attr main,
 	/// Concatenates the given idents into a single identifier.
 	///
 	/// ```rs
@@ -42,4 +101,70 @@ def! {
 	/// concat_idents!(s t d)::alloc::String::new();
 	/// ```
 	macro concat_idents,
+	/// Creates a `crux::data_structures::HashMap` from `key => value` pairs:
+	///
+	/// ```rs
+	/// let ports = map! {
+	///     "http" => 80,
+	///     "https" => 443,
+	/// };
+	/// assert_eq!(ports["https"], 443);
+	/// ```
+	///
+	/// The map uses the default hasher, and is created with room for every
+	/// pair. If a key is repeated, the last value for it wins. Expressions with
+	/// commas outside of brackets, like `Foo::<A, B>::new()`, have to be
+	/// wrapped in parentheses.
+	macro map,
+	/// Creates a `crux::data_structures::HashSet` from a list of values, like
+	/// `set! { "a", "b", "c" }`. See [`map!`] for more info.
+	macro set,
+	/// Embeds a file in the binary, like `include_bytes!`, but aligned to the
+	/// given number of bytes. The path is relative to the crate's root:
+	///
+	/// ```rs
+	/// static FONT: &[u8] = include_bytes_aligned!(64, "assets/Inter.ttf");
+	/// ```
+	///
+	/// Embedded files are placed in the `.crux.embed` linker section, which
+	/// Crux's linker scripts put right after `.rodata`, so they're kept
+	/// together in the binary. A different section can be given with
+	/// `section = "name"`, like
+	/// `include_bytes_aligned!(4096, "assets/Inter.ttf", section = ".fonts")`;
+	/// the name has to be valid for the target's object format.
+	macro include_bytes_aligned,
+	/// Embeds a UTF-8 file in the binary, like `include_str!`. The file is
+	/// checked when the crate is compiled, and it can be given an alignment
+	/// and linker section like [`include_bytes_aligned!`]:
+	///
+	/// ```rs
+	/// static DEFAULT_CONFIG: &str = embed_str!("config/default.toml", align = 8);
+	/// ```
+	macro embed_str,
+	/// Generates Crux's Wayland interfaces from protocol XML files, given as
+	/// paths relative to the crate's root:
+	///
+	/// ```rs
+	/// wayland_protocols!("protocols/wayland.xml", "protocols/xdg-shell.xml");
+	/// ```
+	///
+	/// Every `<interface>` in the files is turned into an entry in an
+	/// `interfaces!` macro invocation, which has to be in scope where this is
+	/// used. This is only meant for `crux::ui::display::wayland`.
+	macro wayland_protocols,
+	/// Implements `crux::term::cli::CliParser` and `crux::term::cli::CliHelp`
+	/// for a struct, treating each field as a flag. See the docs for
+	/// `crux::term::cli` for more info.
+	derive(CliParser, attributes(cli)) cli_parser,
+	/// Implements `crux::term::cli::CliSubcommand` for an enum, treating each
+	/// variant as a subcommand. See the docs for `crux::term::cli` for more
+	/// info.
+	derive(CliSubcommand, attributes(cli)) cli_subcommand,
+	/// Implements `core::fmt::Debug`, like the built-in derive. Fields marked
+	/// `#[crux(skip)]` are left out of the output.
+	derive(CruxDebug, attributes(crux)) crux_debug,
+	/// Implements `core::default::Default`, like the built-in derive. Fields
+	/// can use `#[crux(default = expr)]` to set their default value, and enums
+	/// mark their default variant with `#[crux(default)]`.
+	derive(CruxDefault, attributes(crux)) crux_default,
 }
@@ -1,8 +1,13 @@
 //! Items for working with Rust's syntax tree.
 
-use std::convert::Infallible;
-
-use crate::{AstComponent, Delimiter, Group, Ident, TokenIter, TokenStream, TokenTree};
+use {
+	crate::{
+		AstComponent, Delimiter, Group, Ident, Literal, Punct, Spacing, Span, Spanned, TokenIter,
+		TokenStream, TokenTree, quote,
+	},
+	quote::ToTokens,
+	std::convert::Infallible,
+};
 
 //
 //
@@ -52,7 +57,7 @@ impl AstComponent for Visibility {
 	fn parse(iter: &mut impl TokenIter) -> Result<Self, VisibilityParseError> {
 		if iter.next_is_ident("pub") {
 			iter.next();
-			if iter.next_is_group_with_delimiter(Delimiter::Parenthesis) {
+			if Self::next_is_scope(iter) {
 				let Some(TokenTree::Group(group)) = iter.next() else {
 					unreachable!()
 				};
@@ -72,66 +77,479 @@ impl AstComponent for Visibility {
 	fn skip(iter: &mut impl TokenIter) {
 		if iter.next_is_ident("pub") {
 			iter.next();
-			if iter.next_is_group_with_delimiter(Delimiter::Parenthesis) {
+			if Self::next_is_scope(iter) {
 				iter.next();
 			}
 		}
 	}
 }
+impl Visibility {
+	/// Checks if the next token is the scope after `pub`. This has to look
+	/// inside the parentheses, since `pub (u8, u8)` is a public tuple field.
+	fn next_is_scope(iter: &mut impl TokenIter) -> bool {
+		let Some(TokenTree::Group(group)) = iter.peek() else {
+			return false;
+		};
+		group.delimiter() == Delimiter::Parenthesis
+			&& matches!(
+				group.stream().into_iter().next(),
+				Some(TokenTree::Ident(ident)) if matches!(
+					ident.to_string().as_str(),
+					"crate" | "self" | "super" | "in"
+				)
+			)
+	}
+}
+impl ToTokens for Visibility {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		match self {
+			Self::Public => tokens.extend(quote! { pub }),
+			Self::Scoped(scope) => tokens.extend(quote! { pub #scope }),
+			Self::Private => {}
+		}
+	}
+}
 
-#[derive(Debug)]
+/// The generic parameters of an item, e.g. `<'a, T: Copy, const N: usize>`.
+///
+/// See: <https://doc.rust-lang.org/reference/items/generics.html>
+#[derive(Debug, Default)]
 pub struct Generics {
-	pub types: Vec<GenericItem>,
+	pub params: Vec<GenericItem>,
+}
+impl Generics {
+	/// The generics as they should appear after `impl`, e.g. `<'a, T: Copy>`.
+	/// Defaults are left out, since they aren't allowed there.
+	pub fn impl_generics(&self) -> TokenStream {
+		if self.params.is_empty() {
+			return TokenStream::new();
+		}
+		let params = self.params.iter().map(|param| match param {
+			GenericItem::Lifetime { lifetime, bounds } => {
+				if bounds.is_empty() {
+					quote! { #lifetime }
+				} else {
+					quote! { #lifetime: #(#bounds)+* }
+				}
+			}
+			GenericItem::Type { name, bounds, .. } => {
+				if bounds.is_empty() {
+					quote! { #name }
+				} else {
+					quote! { #name: #(#bounds)+* }
+				}
+			}
+			GenericItem::Const { name, r#type, .. } => quote! { const #name: #r#type },
+		});
+		quote! { <#(#params),*> }
+	}
+	/// The generics as they should appear after the item's name when it's
+	/// used as a type, e.g. `<'a, T>`.
+	pub fn type_generics(&self) -> TokenStream {
+		if self.params.is_empty() {
+			return TokenStream::new();
+		}
+		let params = self.params.iter().map(|param| match param {
+			GenericItem::Lifetime { lifetime, .. } => quote! { #lifetime },
+			GenericItem::Type { name, .. } | GenericItem::Const { name, .. } => quote! { #name },
+		});
+		quote! { <#(#params),*> }
+	}
+	/// The names of every generic type parameter, e.g. `T` in `<'a, T>`.
+	pub fn type_params(&self) -> impl Iterator<Item = &Ident> {
+		self.params.iter().filter_map(|param| match param {
+			GenericItem::Type { name, .. } => Some(name),
+			_ => None,
+		})
+	}
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GenericsParseError {
+	/// The generics' opening `<` was never closed.
+	Unclosed,
+	/// A parameter didn't start with a lifetime, `const`, or a name.
+	InvalidParameter,
+	/// A `const` parameter didn't have a type.
+	MissingConstType,
+	/// A parameter's bounds, type, or default couldn't be parsed.
+	Syntax(Spanned<SyntaxError>),
 }
 impl AstComponent for Generics {
-	type ParseError = Infallible;
+	type ParseError = GenericsParseError;
 
 	fn is_next(iter: &mut impl TokenIter) -> bool {
 		iter.next_is_punct('<')
 	}
 
+	fn maybe_parse(iter: &mut impl TokenIter) -> Option<Result<Self, Self::ParseError>> {
+		// Items without generics have an empty generics list
+		Some(match Self::is_next(iter) {
+			true => Self::parse(iter),
+			false => Ok(Self::default()),
+		})
+	}
+
 	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
-		todo!()
+		iter.next(); // Skip <
+
+		let mut params = Vec::new();
+		loop {
+			if iter.next_is_punct('>') {
+				iter.next();
+				break;
+			}
+			if iter.peek().is_none() {
+				return Err(GenericsParseError::Unclosed);
+			}
+			params.push(GenericItem::parse(iter)?);
+			if iter.next_is_punct(',') {
+				iter.next();
+			} else if !iter.next_is_punct('>') {
+				return Err(GenericsParseError::Unclosed);
+			}
+		}
+
+		Ok(Self { params })
 	}
 	fn skip(iter: &mut impl TokenIter) {
-		todo!()
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Generics {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		if self.params.is_empty() {
+			return;
+		}
+		let params = &self.params;
+		tokens.extend(quote! { <#(#params),*> });
 	}
 }
 
-// TODO this is incomplete
-// https://doc.rust-lang.org/reference/items/generics.html
+/// A single generic parameter.
+///
+/// See: <https://doc.rust-lang.org/reference/items/generics.html>
 #[derive(Debug)]
 pub enum GenericItem {
+	/// Examples:
+	/// - `const N: usize`
+	/// - `const N: usize = 8`
 	Const {
-		name: String,
+		name: Ident,
 		r#type: Type,
+		default: Option<Expr>,
 	},
+	/// Examples:
+	/// - `T`
+	/// - `T: 'static + Copy`
+	/// - `T: Copy = u8`
 	Type {
-		name: String,
+		name: Ident,
+		bounds: Vec<TypeBound>,
+		default: Option<Type>,
 	},
+	/// Examples:
+	/// - `'a`
+	/// - `'a: 'b + 'c`
 	Lifetime {
 		lifetime: Lifetime,
 		bounds: Vec<Lifetime>,
 	},
 }
+impl GenericItem {
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, GenericsParseError> {
+		if Lifetime::is_next(iter) {
+			let (lifetime, bounds) =
+				parse_lifetime_bounds(iter).map_err(GenericsParseError::Syntax)?;
+			return Ok(Self::Lifetime { lifetime, bounds });
+		}
 
-#[derive(Debug)]
+		let is_const = iter.next_is_ident("const");
+		if is_const {
+			iter.next();
+		}
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return Err(GenericsParseError::InvalidParameter);
+		};
+		let has_bounds = iter.next_is_punct(':');
+		if has_bounds {
+			iter.next();
+		}
+
+		if is_const {
+			if !has_bounds {
+				return Err(GenericsParseError::MissingConstType);
+			}
+			let r#type = Type::parse(iter).map_err(GenericsParseError::Syntax)?;
+			let default = if iter.next_is_punct('=') {
+				iter.next();
+				Some(Expr::parse(iter).map_err(GenericsParseError::Syntax)?)
+			} else {
+				None
+			};
+			Ok(Self::Const {
+				name,
+				r#type,
+				default,
+			})
+		} else {
+			let bounds = if has_bounds {
+				TypeBound::parse_bounds(iter).map_err(GenericsParseError::Syntax)?
+			} else {
+				Vec::new()
+			};
+			let default = if iter.next_is_punct('=') {
+				iter.next();
+				Some(Type::parse(iter).map_err(GenericsParseError::Syntax)?)
+			} else {
+				None
+			};
+			Ok(Self::Type {
+				name,
+				bounds,
+				default,
+			})
+		}
+	}
+}
+impl ToTokens for GenericItem {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(match self {
+			Self::Const {
+				name,
+				r#type,
+				default,
+			} => {
+				let default = default.as_ref().map(|default| quote! { = #default });
+				quote! { const #name: #r#type #default }
+			}
+			Self::Type {
+				name,
+				bounds,
+				default,
+			} => {
+				let default = default.as_ref().map(|default| quote! { = #default });
+				if bounds.is_empty() {
+					quote! { #name #default }
+				} else {
+					quote! { #name: #(#bounds)+* #default }
+				}
+			}
+			Self::Lifetime { lifetime, bounds } => {
+				if bounds.is_empty() {
+					quote! { #lifetime }
+				} else {
+					quote! { #lifetime: #(#bounds)+* }
+				}
+			}
+		});
+	}
+}
+
+/// A lifetime, e.g. `'a` or `'static`.
+///
+/// See: <https://doc.rust-lang.org/reference/trait-bounds.html#lifetime-bounds>
+#[derive(Clone, Debug)]
 pub enum Lifetime {
+	/// The placeholder lifetime, `'_`.
 	Implicit,
 	Static,
-	Custom(String),
+	Custom(Ident),
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LifetimeParseError {
+	MissingName,
+}
+impl AstComponent for Lifetime {
+	type ParseError = LifetimeParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		iter.next_is_punct('\'')
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		iter.next(); // Skip '
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return Err(LifetimeParseError::MissingName);
+		};
+		Ok(match name.to_string().as_str() {
+			"_" => Self::Implicit,
+			"static" => Self::Static,
+			_ => Self::Custom(name),
+		})
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		iter.next();
+		iter.next();
+	}
+}
+impl ToTokens for Lifetime {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let name = match self {
+			Self::Implicit => Ident::new("_", Span::call_site()),
+			Self::Static => Ident::new("static", Span::call_site()),
+			Self::Custom(name) => name.clone(),
+		};
+		tokens.extend([
+			TokenTree::Punct(Punct::new('\'', Spacing::Joint)),
+			TokenTree::Ident(name),
+		]);
+	}
+}
+
+/// A `where` clause, e.g. `where T: Copy, 'a: 'b`.
+///
+/// See: <https://doc.rust-lang.org/reference/items/generics.html#where-clauses>
+#[derive(Debug, Default)]
+pub struct WhereClause {
+	pub predicates: Vec<WherePredicate>,
+}
+impl AstComponent for WhereClause {
+	type ParseError = WherePredicateParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		iter.next_is_ident("where")
+	}
+
+	fn maybe_parse(iter: &mut impl TokenIter) -> Option<Result<Self, Self::ParseError>> {
+		// Items without a where clause have an empty one
+		Some(match Self::is_next(iter) {
+			true => Self::parse(iter),
+			false => Ok(Self::default()),
+		})
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		iter.next(); // Skip where
+
+		// The clause ends at the item's body, or at the `;` of a unit or tuple
+		// struct
+		let mut predicates = Vec::new();
+		while !iter.next_is_punct(';') && !iter.next_is_group_with_delimiter(Delimiter::Brace) {
+			if iter.peek().is_none() {
+				break;
+			}
+			predicates.push(WherePredicate::parse(iter)?);
+			if !iter.next_is_punct(',') {
+				break;
+			}
+			iter.next();
+		}
+
+		Ok(Self { predicates })
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for WhereClause {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		if self.predicates.is_empty() {
+			return;
+		}
+		let predicates = &self.predicates;
+		tokens.extend(quote! { where #(#predicates,)* });
+	}
 }
 
-/// TODO.
+/// A single bound in a `where` clause.
 #[derive(Debug)]
-pub struct WhereClause {}
+pub enum WherePredicate {
+	/// Examples:
+	/// - `'a: 'b`
+	/// - `'a: 'b + 'c`
+	Lifetime {
+		lifetime: Lifetime,
+		bounds: Vec<Lifetime>,
+	},
+	/// Examples:
+	/// - `T: Copy`
+	/// - `Vec<T>: Debug`
+	/// - `for<'a> &'a T: IntoIterator`
+	Type {
+		higher_ranked_lifetimes: Vec<Lifetime>,
+		r#type: Type,
+		bounds: Vec<TypeBound>,
+	},
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WherePredicateParseError {
+	/// The predicate didn't have a `:` between the bounded type and its
+	/// bounds.
+	MissingColon,
+	/// The predicate's type or bounds couldn't be parsed.
+	Syntax(Spanned<SyntaxError>),
+}
+impl WherePredicate {
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, WherePredicateParseError> {
+		if Lifetime::is_next(iter) {
+			let (lifetime, bounds) =
+				parse_lifetime_bounds(iter).map_err(WherePredicateParseError::Syntax)?;
+			return Ok(Self::Lifetime { lifetime, bounds });
+		}
+
+		let higher_ranked_lifetimes =
+			parse_higher_ranked_lifetimes(iter).map_err(WherePredicateParseError::Syntax)?;
+		let r#type = Type::parse(iter).map_err(WherePredicateParseError::Syntax)?;
+		if !iter.next_is_punct(':') {
+			return Err(WherePredicateParseError::MissingColon);
+		}
+		iter.next();
+		let bounds = TypeBound::parse_bounds(iter).map_err(WherePredicateParseError::Syntax)?;
+
+		Ok(Self::Type {
+			higher_ranked_lifetimes,
+			r#type,
+			bounds,
+		})
+	}
+}
+impl ToTokens for WherePredicate {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(match self {
+			Self::Lifetime { lifetime, bounds } => quote! { #lifetime: #(#bounds)+* },
+			Self::Type {
+				higher_ranked_lifetimes,
+				r#type,
+				bounds,
+			} => {
+				let for_lifetimes = higher_ranked_tokens(higher_ranked_lifetimes);
+				quote! { #for_lifetimes #r#type: #(#bounds)+* }
+			}
+		});
+	}
+}
+
+/// Parses a lifetime and its bounds, e.g. `'a: 'b + 'c`.
+fn parse_lifetime_bounds(
+	iter: &mut impl TokenIter,
+) -> Result<(Lifetime, Vec<Lifetime>), Spanned<SyntaxError>> {
+	let lifetime = parse_lifetime(iter)?;
+	let mut bounds = Vec::new();
+	if iter.next_is_punct(':') {
+		iter.next();
+		while Lifetime::is_next(iter) {
+			bounds.push(parse_lifetime(iter)?);
+			if !iter.next_is_punct('+') {
+				break;
+			}
+			iter.next();
+		}
+	}
+	Ok((lifetime, bounds))
+}
+/// Parses a lifetime, giving a [`SyntaxError`] if it's invalid.
+fn parse_lifetime(iter: &mut impl TokenIter) -> Result<Lifetime, Spanned<SyntaxError>> {
+	let span = iter.next_span();
+	Lifetime::parse(iter).map_err(|_| Spanned::new(SyntaxError::InvalidLifetime, span))
+}
 
 /// An attribute macro on a Rust item, e.g. `#[derive(Debug)]`.
 ///
 /// See: <https://doc.rust-lang.org/reference/attributes.html>
 #[derive(Debug)]
 pub struct Attribute {
-	/// The name of the macro, e.g. `derive` for `#[derive(Debug)]`.
+	/// The name of the macro, e.g. `derive` for `#[derive(Debug)]`. Paths are
+	/// joined with `::`, e.g. `crux::main` for `#[crux::main]`.
 	pub name: String,
 	/// If the attribute was declared unsafe. When true, the attribute was
 	/// written surrounded by `unsafe()` e.g.
@@ -165,11 +583,10 @@ impl AstComponent for Attribute {
 
 		let mut iter = group.stream().into_iter().peekable();
 
-		let Some(TokenTree::Ident(name)) = iter.next() else {
+		let Some(name) = Self::parse_name(&mut iter) else {
 			return Err(AttributeParseError::MissingAttributeName);
 		};
 
-		let name = name.to_string();
 		Ok(if name.as_str() == "unsafe" {
 			let Some(TokenTree::Group(attribute_inner)) = iter.next() else {
 				return Err(AttributeParseError::MissingUnsafeInner);
@@ -180,12 +597,12 @@ impl AstComponent for Attribute {
 
 			let mut iter = attribute_inner.stream().into_iter().peekable();
 
-			let Some(TokenTree::Ident(attribute_name)) = iter.next() else {
+			let Some(attribute_name) = Self::parse_name(&mut iter) else {
 				return Err(AttributeParseError::MissingAttributeName);
 			};
 
 			Self {
-				name: attribute_name.to_string(),
+				name: attribute_name,
 				is_unsafe: true,
 				args: AttributeArgs::maybe_parse(&mut iter)
 					.map(|res| unsafe { res.unwrap_unchecked() }),
@@ -204,6 +621,40 @@ impl AstComponent for Attribute {
 		iter.next(); // Skip []
 	}
 }
+impl Attribute {
+	/// Parses the attribute's path, e.g. `crux::main`.
+	fn parse_name(iter: &mut impl TokenIter) -> Option<String> {
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return None;
+		};
+		let mut name = name.to_string();
+		while iter.next_is_punct(':') {
+			iter.next();
+			iter.next();
+			let Some(TokenTree::Ident(segment)) = iter.next() else {
+				return None;
+			};
+			name.push_str("::");
+			name.push_str(&segment.to_string());
+		}
+		Some(name)
+	}
+}
+impl ToTokens for Attribute {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let path = self
+			.name
+			.split("::")
+			.map(|segment| Ident::new(segment, Span::call_site()));
+		let args = &self.args;
+		let inner = quote! { #(#path)::* #args };
+		tokens.extend(if self.is_unsafe {
+			quote! { #[unsafe(#inner)] }
+		} else {
+			quote! { #[#inner] }
+		});
+	}
+}
 
 /// Arguments passed to an attribute.
 ///
@@ -218,9 +669,7 @@ pub enum AttributeArgs {
 	Delimited(TokenTree),
 	/// The attribute was passed arguments after an equals sign. For example,
 	/// `#![crate_type = "lib"]` would use this.
-	// TODO: Technically this should store an expression, not any stream of
-	// tokens.
-	Assigned(TokenStream),
+	Assigned(Expr),
 }
 impl AstComponent for AttributeArgs {
 	type ParseError = Infallible;
@@ -231,7 +680,7 @@ impl AstComponent for AttributeArgs {
 	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
 		if iter.next_is_punct('=') {
 			iter.next();
-			Ok(Self::Assigned(iter.collect()))
+			Ok(Self::Assigned(Expr::from_tokens(iter.collect())))
 		} else {
 			Ok(Self::Delimited(iter.next().unwrap()))
 		}
@@ -247,19 +696,80 @@ impl AstComponent for AttributeArgs {
 		}
 	}
 }
+impl AttributeArgs {
+	/// Parses delimited arguments as a list of `key` or `key = value` pairs,
+	/// e.g. `(short = 'p', long)`.
+	pub fn key_values(&self) -> Result<Vec<(Ident, Option<Expr>)>, Spanned<SyntaxError>> {
+		let Self::Delimited(TokenTree::Group(group)) = self else {
+			return Err(Spanned::new(SyntaxError::UnexpectedToken, self.span()));
+		};
 
-#[derive(Debug)]
+		let mut iter = group.stream().into_iter().peekable();
+		let mut pairs = Vec::new();
+		while let Some(token) = iter.next() {
+			let TokenTree::Ident(key) = token else {
+				return Err(Spanned::new(SyntaxError::UnexpectedToken, token.span()));
+			};
+
+			let value = if iter.next_is_punct('=') {
+				iter.next();
+				let mut value = TokenStream::new();
+				while iter.peek().is_some() && !iter.next_is_punct(',') {
+					value.extend(iter.next());
+				}
+				if value.is_empty() {
+					return Err(Spanned::at_next(SyntaxError::UnexpectedEnd, &mut iter));
+				}
+				Some(Expr::from_tokens(value))
+			} else {
+				None
+			};
+			expect_comma_or_end(&mut iter)?;
+
+			pairs.push((key, value));
+		}
+		Ok(pairs)
+	}
+
+	fn span(&self) -> Span {
+		match self {
+			Self::Delimited(args) => args.span(),
+			Self::Assigned(value) => value
+				.to_token_stream()
+				.into_iter()
+				.next()
+				.map_or_else(Span::call_site, |token| token.span()),
+		}
+	}
+}
+impl ToTokens for AttributeArgs {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		match self {
+			Self::Delimited(args) => args.to_tokens(tokens),
+			Self::Assigned(value) => tokens.extend(quote! { = #value }),
+		}
+	}
+}
+
+/// A type, e.g. `u8`, `&'a mut [T]`, or `Option<T>`.
+///
+/// See: <https://doc.rust-lang.org/reference/types.html>
+#[derive(Clone, Debug)]
 pub enum Type {
 	/// Examples:
 	/// - `fn()`
 	/// - `extern "C" fn() -> int`
 	/// - `unsafe fn()`
+	/// - `for<'a> fn(&'a u8)`
 	FunctionPointer {
-		higher_ranked_lifetimes: (), // TODO
+		higher_ranked_lifetimes: Vec<Lifetime>,
 		safety: Safety,
+		/// The ABI, including its quotes. `extern fn()` uses the C ABI, so
+		/// it's stored as `"C"`.
 		abi: Option<String>,
 		parameters: Vec<(Option<Ident>, Type)>,
 		variadic: bool,
+		return_type: Option<Box<Type>>,
 	},
 	/// Examples:
 	/// - `*const u8`
@@ -272,108 +782,1469 @@ pub enum Type {
 	/// Examples:
 	/// - `&str`
 	/// - `&mut SomeType`
+	/// - `&'a T`
 	Reference {
 		mutability: Mutability,
-		lifetime: (), // TODO
+		lifetime: Option<Lifetime>,
 		inner_type: Box<Type>,
 	},
 	/// Examples:
+	/// - `()`
 	/// - `(i8, u8)`
 	/// - `(&str, SomeType)`
-	Tuple {
-		inner_types: Vec<Type>,
-	},
+	Tuple { inner_types: Vec<Type> },
 	/// Examples:
 	/// - `[u8; 8]`
 	/// - `[c_char]`
 	Array {
 		inner_type: Box<Type>,
-		length: Option<usize>,
-	},
-	Owned {
-		name: Ident,
-		generics: (), // TODO
+		length: Option<Expr>,
 	},
-	Impl {
-		traits: Vec<Ident>,
-		use_bound: (), // TODO
-	},
-	Dyn {
-		traits: Vec<Ident>,
+	/// Examples:
+	/// - `u8`
+	/// - `Vec<T>`
+	/// - `core::fmt::Result`
+	/// - `FnOnce(u8) -> bool`
+	Path(Path),
+	/// Examples:
+	/// - `<T as Iterator>::Item`
+	/// - `<Vec<u8>>::Target`
+	QualifiedPath {
+		self_type: Box<Type>,
+		r#trait: Option<Path>,
+		segments: Vec<PathSegment>,
 	},
+	/// Examples:
+	/// - `impl Iterator<Item = u8>`
+	/// - `impl Fn() + 'a`
+	Impl { bounds: Vec<TypeBound> },
+	/// Examples:
+	/// - `dyn Debug`
+	/// - `dyn Fn() + Send`
+	Dyn { bounds: Vec<TypeBound> },
+	/// A type in parentheses, e.g. `(dyn Debug + Send)` in
+	/// `&(dyn Debug + Send)`.
+	Paren(Box<Type>),
+	/// `_`
+	Inferred,
 	/// `!`
 	Never,
 }
-
-//
-//
-// High-level ASTs
-//
-//
-
-/// A structure declaration.
-///
-/// Examples:
-/// - `struct EmptyStruct;`
-/// - `struct TupleStruct(SomeType);`
-/// - `struct KeyedStruct { field: SomeType }`
-/// - `struct PitaToParse<P: 'static + SomeTrait>{ field: P }`
-///
-/// See: <https://doc.rust-lang.org/reference/items/structs.html>
-#[derive(Debug)]
-pub struct Struct {
-	pub kind: StructKind,
-}
-#[derive(Debug)]
-pub enum StructKind {
-	Empty,
-	Tuple(TupleStruct),
-	Keyed(KeyedStruct),
-}
-/// A struct whose fields are defined in a tuple.
-#[derive(Debug)]
-pub enum TupleStruct {}
-/// A struct whose fields are defined in `key: value` pairs.
-#[derive(Debug)]
-pub enum KeyedStruct {}
-
-#[derive(Debug)]
-pub struct FunctionQualifiers {
-	pub is_const: bool,
-	pub is_async: bool,
-	pub safety: Safety,
-	pub abi: Option<String>,
-}
+/// An error from parsing a [`Type`], [`Path`], or [`Expr`].
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-pub enum FunctionQualifiersParseError {
-	MissingAbi,
+pub enum SyntaxError {
+	/// The tokens ended in the middle of the item.
+	UnexpectedEnd,
+	/// A token that isn't allowed where it was found.
+	UnexpectedToken,
+	/// A raw pointer wasn't marked `const` or `mut`.
+	MissingPointerMutability,
+	/// A `'` wasn't followed by a lifetime name.
+	InvalidLifetime,
+	/// A generic argument list's `<` was never closed.
+	UnclosedGenerics,
 }
-impl AstComponent for FunctionQualifiers {
-	type ParseError = FunctionQualifiersParseError;
+impl AstComponent for Type {
+	type ParseError = Spanned<SyntaxError>;
 
 	fn is_next(iter: &mut impl TokenIter) -> bool {
-		let mut iter = iter.clone();
-		Self::skip(&mut iter);
-		iter.next_is_ident("fn")
+		match iter.peek() {
+			Some(TokenTree::Punct(punct)) => {
+				matches!(punct.as_char(), '!' | '*' | '&' | '<') || next_is_path_separator(iter)
+			}
+			Some(TokenTree::Group(group)) => group.delimiter() != Delimiter::Brace,
+			Some(TokenTree::Ident(_)) => true,
+			Some(TokenTree::Literal(_)) | None => false,
+		}
 	}
 
 	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
-		let mut this = Self {
-			is_const: false,
-			is_async: false,
-			safety: Safety::Safe,
-			abi: None,
+		let Some(token) = iter.peek() else {
+			return Err(Spanned::at_next(SyntaxError::UnexpectedEnd, iter));
 		};
+		let span = token.span();
 
-		if iter.next_is_ident("const") {
-			this.is_const = true;
-			iter.next();
-		}
-		if iter.next_is_ident("async") {
-			this.is_async = true;
-			iter.next();
+		match token {
+			TokenTree::Punct(punct) => match punct.as_char() {
+				'!' => {
+					iter.next();
+					Ok(Self::Never)
+				}
+				'*' => {
+					iter.next();
+					let mutability = if iter.next_is_ident("const") {
+						Mutability::Const
+					} else if iter.next_is_ident("mut") {
+						Mutability::Mut
+					} else {
+						return Err(Spanned::at_next(
+							SyntaxError::MissingPointerMutability,
+							iter,
+						));
+					};
+					iter.next();
+					Ok(Self::Pointer {
+						mutability,
+						inner_type: Box::new(Self::parse(iter)?),
+					})
+				}
+				'&' => {
+					iter.next();
+					let lifetime = match Lifetime::is_next(iter) {
+						true => Some(parse_lifetime(iter)?),
+						false => None,
+					};
+					let mutability = if iter.next_is_ident("mut") {
+						iter.next();
+						Mutability::Mut
+					} else {
+						Mutability::Const
+					};
+					Ok(Self::Reference {
+						mutability,
+						lifetime,
+						inner_type: Box::new(Self::parse(iter)?),
+					})
+				}
+				'<' => {
+					iter.next();
+					let self_type = Box::new(Self::parse(iter)?);
+					let r#trait = if iter.next_is_ident("as") {
+						iter.next();
+						Some(Path::parse(iter)?)
+					} else {
+						None
+					};
+					if !iter.next_is_punct('>') {
+						return Err(Spanned::at_next(SyntaxError::UnclosedGenerics, iter));
+					}
+					iter.next();
+					if !next_is_path_separator(iter) {
+						return Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter));
+					}
+					iter.next();
+					iter.next();
+					let Path { segments, .. } = Path::parse(iter)?;
+
+					Ok(Self::QualifiedPath {
+						self_type,
+						r#trait,
+						segments,
+					})
+				}
+				':' => Ok(Self::Path(Path::parse(iter)?)),
+				_ => Err(Spanned::new(SyntaxError::UnexpectedToken, span)),
+			},
+			TokenTree::Group(group) => {
+				let group = group.clone();
+				iter.next();
+				match group.delimiter() {
+					Delimiter::Parenthesis => {
+						let mut inner = group.stream().into_iter().peekable();
+						let mut inner_types = Vec::new();
+						let mut trailing_comma = false;
+						while inner.peek().is_some() {
+							inner_types.push(Self::parse(&mut inner)?);
+							trailing_comma = expect_comma_or_end(&mut inner)?;
+						}
+						// `(T)` is just `T` in parentheses; a tuple with one
+						// type is written `(T,)`
+						if inner_types.len() == 1 && !trailing_comma {
+							Ok(Self::Paren(Box::new(inner_types.pop().unwrap())))
+						} else {
+							Ok(Self::Tuple { inner_types })
+						}
+					}
+					Delimiter::Bracket => {
+						let mut inner = group.stream().into_iter().peekable();
+						let inner_type = Box::new(Self::parse(&mut inner)?);
+						let length = if inner.next_is_punct(';') {
+							inner.next();
+							Some(Expr::from_tokens(inner.collect()))
+						} else if inner.peek().is_some() {
+							return Err(Spanned::at_next(SyntaxError::UnexpectedToken, &mut inner));
+						} else {
+							None
+						};
+						Ok(Self::Array { inner_type, length })
+					}
+					// Types passed to `macro_rules` macros as `$ty:ty` are
+					// wrapped in an invisible group
+					Delimiter::None => parse_all(group.stream()),
+					Delimiter::Brace => Err(Spanned::new(SyntaxError::UnexpectedToken, span)),
+				}
+			}
+			TokenTree::Ident(ident) => match ident.to_string().as_str() {
+				"_" => {
+					iter.next();
+					Ok(Self::Inferred)
+				}
+				"impl" => {
+					iter.next();
+					Ok(Self::Impl {
+						bounds: TypeBound::parse_bounds(iter)?,
+					})
+				}
+				"dyn" => {
+					iter.next();
+					Ok(Self::Dyn {
+						bounds: TypeBound::parse_bounds(iter)?,
+					})
+				}
+				"fn" | "unsafe" | "extern" | "for" => Self::parse_function_pointer(iter),
+				_ => Ok(Self::Path(Path::parse(iter)?)),
+			},
+			TokenTree::Literal(_) => Err(Spanned::new(SyntaxError::UnexpectedToken, span)),
 		}
-		if iter.next_is_ident("safe") {
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl Type {
+	fn parse_function_pointer(iter: &mut impl TokenIter) -> Result<Self, Spanned<SyntaxError>> {
+		let higher_ranked_lifetimes = parse_higher_ranked_lifetimes(iter)?;
+		let safety = if iter.next_is_ident("unsafe") {
+			iter.next();
+			Safety::Unsafe
+		} else {
+			Safety::Safe
+		};
+		let abi = if iter.next_is_ident("extern") {
+			iter.next();
+			match iter.peek() {
+				Some(TokenTree::Literal(abi)) => {
+					let abi = abi.to_string();
+					iter.next();
+					Some(abi)
+				}
+				_ => Some(String::from("\"C\"")),
+			}
+		} else {
+			None
+		};
+		if !iter.next_is_ident("fn") {
+			return Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter));
+		}
+		iter.next();
+
+		let Some(TokenTree::Group(group)) = iter.peek() else {
+			return Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter));
+		};
+		if group.delimiter() != Delimiter::Parenthesis {
+			return Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter));
+		}
+		let mut inner = group.stream().into_iter().peekable();
+		iter.next();
+
+		let mut parameters = Vec::new();
+		let mut variadic = false;
+		while inner.peek().is_some() {
+			if inner.next_is_punct('.') {
+				for _ in 0..3 {
+					if !inner.next_is_punct('.') {
+						return Err(Spanned::at_next(SyntaxError::UnexpectedToken, &mut inner));
+					}
+					inner.next();
+				}
+				variadic = true;
+				expect_comma_or_end(&mut inner)?;
+				continue;
+			}
+
+			// Parameters can be named, e.g. `fn(len: usize)`
+			let mut lookahead = inner.clone();
+			let name = match lookahead.next() {
+				Some(TokenTree::Ident(name))
+					if lookahead.next_is_punct(':') && !next_is_path_separator(&mut lookahead) =>
+				{
+					inner.next();
+					inner.next();
+					Some(name)
+				}
+				_ => None,
+			};
+			parameters.push((name, Self::parse(&mut inner)?));
+			expect_comma_or_end(&mut inner)?;
+		}
+
+		Ok(Self::FunctionPointer {
+			higher_ranked_lifetimes,
+			safety,
+			abi,
+			parameters,
+			variadic,
+			return_type: parse_return_type(iter)?,
+		})
+	}
+}
+impl ToTokens for Type {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(match self {
+			Self::FunctionPointer {
+				higher_ranked_lifetimes,
+				safety,
+				abi,
+				parameters,
+				variadic,
+				return_type,
+			} => {
+				let for_lifetimes = higher_ranked_tokens(higher_ranked_lifetimes);
+				let safety = match safety {
+					Safety::Safe => None,
+					Safety::Unsafe => Some(quote! { unsafe }),
+				};
+				let abi = abi.as_ref().map(|abi| {
+					let abi: TokenStream = abi.parse().unwrap();
+					quote! { extern #abi }
+				});
+				let parameters = parameters.iter().map(|(name, r#type)| match name {
+					Some(name) => quote! { #name: #r#type },
+					None => quote! { #r#type },
+				});
+				let variadic = variadic.then(|| quote! { ... });
+				let return_type = return_type
+					.as_ref()
+					.map(|return_type| quote! { -> #return_type });
+
+				quote! {
+					#for_lifetimes #safety #abi fn(#(#parameters,)* #variadic) #return_type
+				}
+			}
+			Self::Pointer {
+				mutability,
+				inner_type,
+			} => match mutability {
+				Mutability::Const => quote! { *const #inner_type },
+				Mutability::Mut => quote! { *mut #inner_type },
+			},
+			Self::Reference {
+				mutability,
+				lifetime,
+				inner_type,
+			} => match mutability {
+				Mutability::Const => quote! { &#lifetime #inner_type },
+				Mutability::Mut => quote! { &#lifetime mut #inner_type },
+			},
+			// One-type tuples need a trailing comma to not be parenthesized
+			// types
+			Self::Tuple { inner_types } if inner_types.len() == 1 => {
+				let inner_type = &inner_types[0];
+				quote! { (#inner_type,) }
+			}
+			Self::Tuple { inner_types } => quote! { (#(#inner_types),*) },
+			Self::Array { inner_type, length } => match length {
+				Some(length) => quote! { [#inner_type; #length] },
+				None => quote! { [#inner_type] },
+			},
+			Self::Path(path) => quote! { #path },
+			Self::QualifiedPath {
+				self_type,
+				r#trait,
+				segments,
+			} => {
+				let r#trait = r#trait.as_ref().map(|r#trait| quote! { as #r#trait });
+				quote! { <#self_type #r#trait>::#(#segments)::* }
+			}
+			Self::Impl { bounds } => quote! { impl #(#bounds)+* },
+			Self::Dyn { bounds } => quote! { dyn #(#bounds)+* },
+			Self::Paren(inner_type) => quote! { (#inner_type) },
+			Self::Inferred => quote! { _ },
+			Self::Never => quote! { ! },
+		});
+	}
+}
+
+/// A path to an item, e.g. `core::fmt::Debug` or `Vec<u8>`.
+///
+/// See: <https://doc.rust-lang.org/reference/paths.html>
+#[derive(Clone, Debug)]
+pub struct Path {
+	/// If the path starts with `::`, e.g. `::core::fmt::Debug`.
+	pub leading_colons: bool,
+	pub segments: Vec<PathSegment>,
+}
+impl Path {
+	/// Parses a path in a type, or in an expression if `turbofish` is set.
+	/// Expression paths have to use `::<>` for their generics, since `<`
+	/// would be a comparison.
+	fn parse_path(
+		iter: &mut impl TokenIter,
+		turbofish: bool,
+	) -> Result<Self, Spanned<SyntaxError>> {
+		let leading_colons = next_is_path_separator(iter);
+		if leading_colons {
+			iter.next();
+			iter.next();
+		}
+
+		let mut segments = Vec::new();
+		loop {
+			let Some(TokenTree::Ident(name)) = iter.peek() else {
+				return Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter));
+			};
+			let name = name.clone();
+			iter.next();
+
+			let mut lookahead = iter.clone();
+			let has_turbofish = next_is_path_separator(&mut lookahead) && {
+				lookahead.next();
+				lookahead.next();
+				lookahead.next_is_punct('<')
+			};
+			let arguments = if has_turbofish || (!turbofish && iter.next_is_punct('<')) {
+				if has_turbofish {
+					iter.next();
+					iter.next();
+				}
+				PathArguments::AngleBracketed(parse_generic_args(iter)?)
+			} else if !turbofish && iter.next_is_group_with_delimiter(Delimiter::Parenthesis) {
+				let Some(TokenTree::Group(group)) = iter.next() else {
+					unreachable!()
+				};
+				let mut inner = group.stream().into_iter().peekable();
+				let mut inputs = Vec::new();
+				while inner.peek().is_some() {
+					inputs.push(Type::parse(&mut inner)?);
+					expect_comma_or_end(&mut inner)?;
+				}
+				PathArguments::Parenthesized {
+					inputs,
+					output: parse_return_type(iter)?,
+				}
+			} else {
+				PathArguments::None
+			};
+			segments.push(PathSegment { name, arguments });
+
+			if !next_is_path_separator(iter) {
+				break;
+			}
+			iter.next();
+			iter.next();
+		}
+
+		Ok(Self {
+			leading_colons,
+			segments,
+		})
+	}
+
+	/// Checks if this path is a single identifier without generics, e.g.
+	/// `T` or `u8`, and returns the identifier.
+	pub fn as_ident(&self) -> Option<&Ident> {
+		match self.segments.as_slice() {
+			[segment] if !self.leading_colons => match segment.arguments {
+				PathArguments::None => Some(&segment.name),
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	fn write_tokens(&self, tokens: &mut TokenStream, turbofish: bool) {
+		if self.leading_colons {
+			tokens.extend(quote! { :: });
+		}
+		for (idx, segment) in self.segments.iter().enumerate() {
+			if idx > 0 {
+				tokens.extend(quote! { :: });
+			}
+			segment.write_tokens(tokens, turbofish);
+		}
+	}
+}
+impl AstComponent for Path {
+	type ParseError = Spanned<SyntaxError>;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		matches!(iter.peek(), Some(TokenTree::Ident(_))) || next_is_path_separator(iter)
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		Self::parse_path(iter, false)
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Path {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		self.write_tokens(tokens, false);
+	}
+}
+
+/// One segment of a [`Path`], e.g. `Vec<u8>` in `alloc::vec::Vec<u8>`.
+#[derive(Clone, Debug)]
+pub struct PathSegment {
+	pub name: Ident,
+	pub arguments: PathArguments,
+}
+impl PathSegment {
+	fn write_tokens(&self, tokens: &mut TokenStream, turbofish: bool) {
+		self.name.to_tokens(tokens);
+		match &self.arguments {
+			PathArguments::None => {}
+			PathArguments::AngleBracketed(args) => {
+				if turbofish {
+					tokens.extend(quote! { :: });
+				}
+				tokens.extend(quote! { <#(#args),*> });
+			}
+			PathArguments::Parenthesized { inputs, output } => {
+				let output = output.as_ref().map(|output| quote! { -> #output });
+				tokens.extend(quote! { (#(#inputs),*) #output });
+			}
+		}
+	}
+}
+impl ToTokens for PathSegment {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		self.write_tokens(tokens, false);
+	}
+}
+
+/// The generic arguments of a [`PathSegment`].
+#[derive(Clone, Debug)]
+pub enum PathArguments {
+	None,
+	/// Examples:
+	/// - `<u8>` in `Vec<u8>`
+	/// - `<'a, Item = T>` in `Iter<'a, Item = T>`
+	AngleBracketed(Vec<GenericArg>),
+	/// Examples:
+	/// - `(u8) -> bool` in `Fn(u8) -> bool`
+	/// - `()` in `FnOnce()`
+	Parenthesized {
+		inputs: Vec<Type>,
+		output: Option<Box<Type>>,
+	},
+}
+
+/// A single argument in a [`PathArguments::AngleBracketed`] list.
+#[derive(Clone, Debug)]
+pub enum GenericArg {
+	/// `'a`
+	Lifetime(Lifetime),
+	/// `u8`, `T`, or `N` - const parameters can't be told apart from types
+	/// by their syntax, so they're parsed as types.
+	Type(Type),
+	/// Examples:
+	/// - `8`
+	/// - `{ N + 1 }`
+	Const(Expr),
+	/// `Item = u8`
+	Binding { name: Ident, r#type: Type },
+	/// `Item: Copy`
+	Constraint { name: Ident, bounds: Vec<TypeBound> },
+}
+impl GenericArg {
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Spanned<SyntaxError>> {
+		if Lifetime::is_next(iter) {
+			return parse_lifetime(iter).map(Self::Lifetime);
+		}
+		match iter.peek() {
+			Some(TokenTree::Literal(_)) => return Ok(Self::Const(Expr::parse(iter)?)),
+			Some(TokenTree::Punct(punct)) if punct.as_char() == '-' => {
+				return Ok(Self::Const(Expr::parse(iter)?));
+			}
+			Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+				return Ok(Self::Const(Expr::parse(iter)?));
+			}
+			_ => {}
+		}
+
+		let mut lookahead = iter.clone();
+		if let Some(TokenTree::Ident(name)) = lookahead.next() {
+			if lookahead.next_is_punct('=') {
+				iter.next();
+				iter.next();
+				return Ok(Self::Binding {
+					name,
+					r#type: Type::parse(iter)?,
+				});
+			}
+			if lookahead.next_is_punct(':') && !next_is_path_separator(&mut lookahead) {
+				iter.next();
+				iter.next();
+				return Ok(Self::Constraint {
+					name,
+					bounds: TypeBound::parse_bounds(iter)?,
+				});
+			}
+		}
+
+		Ok(Self::Type(Type::parse(iter)?))
+	}
+}
+impl ToTokens for GenericArg {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(match self {
+			Self::Lifetime(lifetime) => quote! { #lifetime },
+			Self::Type(r#type) => quote! { #r#type },
+			Self::Const(expr) => quote! { #expr },
+			Self::Binding { name, r#type } => quote! { #name = #r#type },
+			Self::Constraint { name, bounds } => quote! { #name: #(#bounds)+* },
+		});
+	}
+}
+
+/// A trait or lifetime bound, e.g. `Copy`, `?Sized`, or `'static`.
+///
+/// See: <https://doc.rust-lang.org/reference/trait-bounds.html>
+#[derive(Clone, Debug)]
+pub enum TypeBound {
+	Lifetime(Lifetime),
+	/// Examples:
+	/// - `Copy`
+	/// - `?Sized`
+	/// - `for<'a> Fn(&'a u8)`
+	Trait {
+		higher_ranked_lifetimes: Vec<Lifetime>,
+		/// If the bound was written with `?`, making it optional.
+		maybe: bool,
+		path: Path,
+	},
+}
+impl TypeBound {
+	/// Parses a list of bounds separated by `+`, e.g. `Copy + 'static`. The
+	/// list may be empty.
+	pub fn parse_bounds(iter: &mut impl TokenIter) -> Result<Vec<Self>, Spanned<SyntaxError>> {
+		let mut bounds = Vec::new();
+		while Self::is_next(iter) {
+			bounds.push(Self::parse(iter)?);
+			if !iter.next_is_punct('+') {
+				break;
+			}
+			iter.next();
+		}
+		Ok(bounds)
+	}
+}
+impl AstComponent for TypeBound {
+	type ParseError = Spanned<SyntaxError>;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		Lifetime::is_next(iter) || iter.next_is_punct('?') || Path::is_next(iter)
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		if Lifetime::is_next(iter) {
+			return parse_lifetime(iter).map(Self::Lifetime);
+		}
+
+		let higher_ranked_lifetimes = parse_higher_ranked_lifetimes(iter)?;
+		let maybe = iter.next_is_punct('?');
+		if maybe {
+			iter.next();
+		}
+		Ok(Self::Trait {
+			higher_ranked_lifetimes,
+			maybe,
+			path: Path::parse(iter)?,
+		})
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for TypeBound {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(match self {
+			Self::Lifetime(lifetime) => quote! { #lifetime },
+			Self::Trait {
+				higher_ranked_lifetimes,
+				maybe,
+				path,
+			} => {
+				let for_lifetimes = higher_ranked_tokens(higher_ranked_lifetimes);
+				let maybe = maybe.then(|| quote! { ? });
+				quote! { #for_lifetimes #maybe #path }
+			}
+		});
+	}
+}
+
+/// An expression. Only simple expressions can be parsed right now; see
+/// [`Expr::from_tokens`] for parsing anything else.
+///
+/// See: <https://doc.rust-lang.org/reference/expressions.html>
+#[derive(Clone, Debug)]
+pub enum Expr {
+	/// Examples:
+	/// - `1`
+	/// - `"text"`
+	/// - `b'a'`
+	Literal(Literal),
+	/// A negated expression, e.g. `-1`.
+	Negate(Box<Expr>),
+	/// Examples:
+	/// - `CONSTANT`
+	/// - `Self::VARIANT`
+	/// - `size_of::<u8>`
+	Path(Path),
+	/// Examples:
+	/// - `function()`
+	/// - `Vec::new()`
+	/// - `Some(1)`
+	Call {
+		function: Box<Expr>,
+		args: Vec<Expr>,
+	},
+	/// A block, e.g. `{ N + 1 }`. Its contents aren't parsed.
+	Block(Group),
+	/// An expression that can't be parsed yet, as its tokens.
+	Verbatim(TokenStream),
+}
+impl Expr {
+	/// Parses `tokens` as one expression. If it can't be parsed, it's stored
+	/// as [`Expr::Verbatim`] instead.
+	pub fn from_tokens(tokens: TokenStream) -> Self {
+		parse_all(tokens.clone()).unwrap_or(Self::Verbatim(tokens))
+	}
+
+	/// Returns this expression's literal, if it's a literal.
+	pub fn as_literal(&self) -> Option<&Literal> {
+		match self {
+			Self::Literal(literal) => Some(literal),
+			_ => None,
+		}
+	}
+}
+impl AstComponent for Expr {
+	type ParseError = Spanned<SyntaxError>;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		match iter.peek() {
+			Some(TokenTree::Literal(_)) => true,
+			Some(TokenTree::Punct(punct)) => punct.as_char() == '-' || next_is_path_separator(iter),
+			Some(TokenTree::Group(group)) => {
+				matches!(group.delimiter(), Delimiter::Brace | Delimiter::None)
+			}
+			Some(TokenTree::Ident(_)) => true,
+			None => false,
+		}
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		let is_path = Path::is_next(iter);
+		let Some(token) = iter.peek() else {
+			return Err(Spanned::at_next(SyntaxError::UnexpectedEnd, iter));
+		};
+		let span = token.span();
+
+		let mut expr = match token {
+			TokenTree::Literal(literal) => {
+				let literal = literal.clone();
+				iter.next();
+				Self::Literal(literal)
+			}
+			TokenTree::Punct(punct) if punct.as_char() == '-' => {
+				iter.next();
+				Self::Negate(Box::new(Self::parse(iter)?))
+			}
+			TokenTree::Group(group) if group.delimiter() == Delimiter::Brace => {
+				let group = group.clone();
+				iter.next();
+				Self::Block(group)
+			}
+			// Expressions passed to `macro_rules` macros as `$expr:expr` are
+			// wrapped in an invisible group
+			TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+				let group = group.clone();
+				iter.next();
+				parse_all(group.stream())?
+			}
+			_ if is_path => Self::Path(Path::parse_path(iter, true)?),
+			_ => return Err(Spanned::new(SyntaxError::UnexpectedToken, span)),
+		};
+
+		while iter.next_is_group_with_delimiter(Delimiter::Parenthesis) {
+			let Some(TokenTree::Group(group)) = iter.next() else {
+				unreachable!()
+			};
+			let mut inner = group.stream().into_iter().peekable();
+			let mut args = Vec::new();
+			while inner.peek().is_some() {
+				args.push(Self::parse(&mut inner)?);
+				expect_comma_or_end(&mut inner)?;
+			}
+			expr = Self::Call {
+				function: Box::new(expr),
+				args,
+			};
+		}
+
+		Ok(expr)
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Expr {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		match self {
+			Self::Literal(literal) => literal.to_tokens(tokens),
+			Self::Negate(expr) => tokens.extend(quote! { -#expr }),
+			Self::Path(path) => path.write_tokens(tokens, true),
+			Self::Call { function, args } => tokens.extend(quote! { #function(#(#args),*) }),
+			Self::Block(block) => block.to_tokens(tokens),
+			Self::Verbatim(expr) => expr.to_tokens(tokens),
+		}
+	}
+}
+
+/// Parses all of `tokens` as `T`, erroring if there are tokens left over.
+fn parse_all<T: AstComponent<ParseError = Spanned<SyntaxError>>>(
+	tokens: TokenStream,
+) -> Result<T, Spanned<SyntaxError>> {
+	let mut iter = tokens.into_iter().peekable();
+	let parsed = T::parse(&mut iter)?;
+	match iter.peek() {
+		Some(_) => Err(Spanned::at_next(SyntaxError::UnexpectedToken, &mut iter)),
+		None => Ok(parsed),
+	}
+}
+/// Parses a `<...>` list of generic arguments.
+fn parse_generic_args(iter: &mut impl TokenIter) -> Result<Vec<GenericArg>, Spanned<SyntaxError>> {
+	iter.next(); // Skip <
+	let mut args = Vec::new();
+	loop {
+		if iter.next_is_punct('>') {
+			iter.next();
+			return Ok(args);
+		}
+		if iter.peek().is_none() {
+			return Err(Spanned::at_next(SyntaxError::UnclosedGenerics, iter));
+		}
+		args.push(GenericArg::parse(iter)?);
+		if iter.next_is_punct(',') {
+			iter.next();
+		} else if !iter.next_is_punct('>') {
+			return Err(Spanned::at_next(SyntaxError::UnclosedGenerics, iter));
+		}
+	}
+}
+/// Parses the `for<'a>` before a function pointer or trait bound, if there
+/// is one.
+fn parse_higher_ranked_lifetimes(
+	iter: &mut impl TokenIter,
+) -> Result<Vec<Lifetime>, Spanned<SyntaxError>> {
+	if !iter.next_is_ident("for") {
+		return Ok(Vec::new());
+	}
+	iter.next();
+	parse_generic_args(iter)?
+		.into_iter()
+		.map(|arg| match arg {
+			GenericArg::Lifetime(lifetime) => Ok(lifetime),
+			_ => Err(Spanned::at_next(SyntaxError::InvalidLifetime, iter)),
+		})
+		.collect()
+}
+fn higher_ranked_tokens(lifetimes: &[Lifetime]) -> Option<TokenStream> {
+	(!lifetimes.is_empty()).then(|| quote! { for<#(#lifetimes),*> })
+}
+/// Parses the `-> Type` after a function pointer or `Fn` bound, if there is
+/// one.
+fn parse_return_type(iter: &mut impl TokenIter) -> Result<Option<Box<Type>>, Spanned<SyntaxError>> {
+	let mut lookahead = iter.clone();
+	let is_arrow = lookahead.next_is_punct('-') && {
+		lookahead.next();
+		lookahead.next_is_punct('>')
+	};
+	if !is_arrow {
+		return Ok(None);
+	}
+	iter.next();
+	iter.next();
+	Ok(Some(Box::new(Type::parse(iter)?)))
+}
+/// Skips the `,` after an item in a list. Returns if there was a comma.
+fn expect_comma_or_end(iter: &mut impl TokenIter) -> Result<bool, Spanned<SyntaxError>> {
+	if iter.next_is_punct(',') {
+		iter.next();
+		Ok(true)
+	} else if iter.peek().is_none() {
+		Ok(false)
+	} else {
+		Err(Spanned::at_next(SyntaxError::UnexpectedToken, iter))
+	}
+}
+/// Checks if the next tokens are `::`.
+fn next_is_path_separator(iter: &mut impl TokenIter) -> bool {
+	let mut lookahead = iter.clone();
+	lookahead.next_is_punct(':') && {
+		lookahead.next();
+		lookahead.next_is_punct(':')
+	}
+}
+
+//
+//
+// High-level ASTs
+//
+//
+
+/// A structure declaration.
+///
+/// Examples:
+/// - `struct EmptyStruct;`
+/// - `struct TupleStruct(SomeType);`
+/// - `struct KeyedStruct { field: SomeType }`
+/// - `struct PitaToParse<P: 'static + SomeTrait>{ field: P }`
+///
+/// See: <https://doc.rust-lang.org/reference/items/structs.html>
+#[derive(Debug)]
+pub struct Struct {
+	pub attributes: Vec<Attribute>,
+	pub visibility: Visibility,
+	pub name: Ident,
+	pub generics: Generics,
+	pub where_clause: WhereClause,
+	pub kind: StructKind,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StructParseError {
+	Attribute(AttributeParseError),
+	Generics(GenericsParseError),
+	WhereClause(WherePredicateParseError),
+	Field(FieldParseError),
+	/// The `struct` keyword wasn't followed by a name.
+	MissingName,
+	/// The struct didn't have a body, or a `;` after a tuple or unit struct.
+	MissingBody,
+}
+impl AstComponent for Struct {
+	type ParseError = StructParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		is_next_item(iter, "struct")
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		let attributes = parse_attributes(iter).map_err(StructParseError::Attribute)?;
+		let visibility = Visibility::parse(iter).unwrap();
+		iter.next(); // Skip struct
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return Err(StructParseError::MissingName);
+		};
+		let generics = Generics::maybe_parse(iter)
+			.unwrap()
+			.map_err(StructParseError::Generics)?;
+		let mut where_clause = WhereClause::maybe_parse(iter)
+			.unwrap()
+			.map_err(StructParseError::WhereClause)?;
+
+		let kind = match iter.next() {
+			Some(TokenTree::Punct(punct)) if punct.as_char() == ';' => StructKind::Empty,
+			Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+				StructKind::Keyed(
+					KeyedStruct::parse_fields(group.stream()).map_err(StructParseError::Field)?,
+				)
+			}
+			Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+				let fields =
+					TupleStruct::parse_fields(group.stream()).map_err(StructParseError::Field)?;
+				// Tuple structs put their where clause after the fields
+				if WhereClause::is_next(iter) {
+					where_clause =
+						WhereClause::parse(iter).map_err(StructParseError::WhereClause)?;
+				}
+				if !iter.next_is_punct(';') {
+					return Err(StructParseError::MissingBody);
+				}
+				iter.next();
+				StructKind::Tuple(fields)
+			}
+			_ => return Err(StructParseError::MissingBody),
+		};
+
+		Ok(Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			kind,
+		})
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Struct {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			kind,
+		} = self;
+		let header = quote! { #(#attributes)* #visibility struct #name #generics };
+		tokens.extend(match kind {
+			StructKind::Empty => quote! { #header #where_clause; },
+			StructKind::Tuple(_) => quote! { #header #kind #where_clause; },
+			StructKind::Keyed(_) => quote! { #header #where_clause #kind },
+		});
+	}
+}
+
+/// The fields of a struct or enum variant.
+#[derive(Debug)]
+pub enum StructKind {
+	Empty,
+	Tuple(TupleStruct),
+	Keyed(KeyedStruct),
+}
+impl ToTokens for StructKind {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		match self {
+			Self::Empty => {}
+			Self::Tuple(tuple) => tuple.to_tokens(tokens),
+			Self::Keyed(keyed) => keyed.to_tokens(tokens),
+		}
+	}
+}
+/// A struct whose fields are defined in a tuple.
+#[derive(Debug)]
+pub struct TupleStruct {
+	pub fields: Vec<TupleField>,
+}
+impl TupleStruct {
+	/// Parses the fields inside a tuple struct's parentheses.
+	pub fn parse_fields(fields: TokenStream) -> Result<Self, FieldParseError> {
+		let mut iter = fields.into_iter().peekable();
+		let iter = &mut iter;
+		let mut fields = Vec::new();
+		while iter.peek().is_some() {
+			let attributes = parse_attributes(iter).map_err(FieldParseError::Attribute)?;
+			let visibility = Visibility::parse(iter).unwrap();
+			if iter.peek().is_none() {
+				return Err(FieldParseError::MissingType);
+			}
+			let r#type = Type::parse(iter).map_err(FieldParseError::Syntax)?;
+			expect_comma_or_end(iter).map_err(FieldParseError::Syntax)?;
+
+			fields.push(TupleField {
+				attributes,
+				visibility,
+				r#type,
+			});
+		}
+		Ok(Self { fields })
+	}
+}
+impl ToTokens for TupleStruct {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let fields = &self.fields;
+		tokens.extend(quote! { (#(#fields,)*) });
+	}
+}
+/// A struct whose fields are defined in `key: value` pairs.
+#[derive(Debug)]
+pub struct KeyedStruct {
+	pub fields: Vec<KeyedField>,
+}
+impl KeyedStruct {
+	/// Parses the fields inside a struct's braces.
+	pub fn parse_fields(fields: TokenStream) -> Result<Self, FieldParseError> {
+		let mut iter = fields.into_iter().peekable();
+		let iter = &mut iter;
+		let mut fields = Vec::new();
+		while iter.peek().is_some() {
+			let attributes = parse_attributes(iter).map_err(FieldParseError::Attribute)?;
+			let visibility = Visibility::parse(iter).unwrap();
+			let Some(TokenTree::Ident(name)) = iter.next() else {
+				return Err(FieldParseError::MissingName);
+			};
+			if !iter.next_is_punct(':') {
+				return Err(FieldParseError::MissingType);
+			}
+			iter.next();
+			let r#type = Type::parse(iter).map_err(FieldParseError::Syntax)?;
+			expect_comma_or_end(iter).map_err(FieldParseError::Syntax)?;
+
+			fields.push(KeyedField {
+				attributes,
+				visibility,
+				name,
+				r#type,
+			});
+		}
+		Ok(Self { fields })
+	}
+}
+impl ToTokens for KeyedStruct {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let fields = &self.fields;
+		tokens.extend(quote! { { #(#fields,)* } });
+	}
+}
+/// A field in a [`TupleStruct`].
+#[derive(Debug)]
+pub struct TupleField {
+	pub attributes: Vec<Attribute>,
+	pub visibility: Visibility,
+	pub r#type: Type,
+}
+impl ToTokens for TupleField {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			visibility,
+			r#type,
+		} = self;
+		tokens.extend(quote! { #(#attributes)* #visibility #r#type });
+	}
+}
+/// A field in a [`KeyedStruct`].
+#[derive(Debug)]
+pub struct KeyedField {
+	pub attributes: Vec<Attribute>,
+	pub visibility: Visibility,
+	pub name: Ident,
+	pub r#type: Type,
+}
+impl ToTokens for KeyedField {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			visibility,
+			name,
+			r#type,
+		} = self;
+		tokens.extend(quote! { #(#attributes)* #visibility #name: #r#type });
+	}
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FieldParseError {
+	Attribute(AttributeParseError),
+	/// A keyed field didn't have a name.
+	MissingName,
+	/// A field didn't have a type.
+	MissingType,
+	/// A field's type couldn't be parsed.
+	Syntax(Spanned<SyntaxError>),
+}
+
+/// An enum declaration.
+///
+/// Examples:
+/// - `enum Never {}`
+/// - `enum Shape { Point, Circle(f32), Rect { width: f32, height: f32 } }`
+/// - `enum Number { One = 1, Two = 2 }`
+///
+/// See: <https://doc.rust-lang.org/reference/items/enumerations.html>
+#[derive(Debug)]
+pub struct Enum {
+	pub attributes: Vec<Attribute>,
+	pub visibility: Visibility,
+	pub name: Ident,
+	pub generics: Generics,
+	pub where_clause: WhereClause,
+	pub variants: Vec<EnumVariant>,
+}
+/// A single variant of an [`Enum`].
+#[derive(Debug)]
+pub struct EnumVariant {
+	pub attributes: Vec<Attribute>,
+	pub name: Ident,
+	pub kind: StructKind,
+	/// The variant's explicit discriminant, e.g. `1` in `One = 1`.
+	pub discriminant: Option<Expr>,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EnumParseError {
+	Attribute(AttributeParseError),
+	Generics(GenericsParseError),
+	WhereClause(WherePredicateParseError),
+	Field(FieldParseError),
+	/// The `enum` keyword wasn't followed by a name.
+	MissingName,
+	/// The enum didn't have a body.
+	MissingBody,
+	/// A variant didn't have a name.
+	MissingVariantName,
+}
+impl AstComponent for Enum {
+	type ParseError = EnumParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		is_next_item(iter, "enum")
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		let attributes = parse_attributes(iter).map_err(EnumParseError::Attribute)?;
+		let visibility = Visibility::parse(iter).unwrap();
+		iter.next(); // Skip enum
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return Err(EnumParseError::MissingName);
+		};
+		let generics = Generics::maybe_parse(iter)
+			.unwrap()
+			.map_err(EnumParseError::Generics)?;
+		let where_clause = WhereClause::maybe_parse(iter)
+			.unwrap()
+			.map_err(EnumParseError::WhereClause)?;
+		let Some(TokenTree::Group(body)) = iter.next() else {
+			return Err(EnumParseError::MissingBody);
+		};
+		if body.delimiter() != Delimiter::Brace {
+			return Err(EnumParseError::MissingBody);
+		}
+
+		let mut variants = Vec::new();
+		let mut iter = body.stream().into_iter().peekable();
+		let iter = &mut iter;
+		while iter.peek().is_some() {
+			let attributes = parse_attributes(iter).map_err(EnumParseError::Attribute)?;
+			// Variants can't actually have a visibility, but it's allowed
+			// syntactically
+			Visibility::skip(iter);
+			let Some(TokenTree::Ident(name)) = iter.next() else {
+				return Err(EnumParseError::MissingVariantName);
+			};
+			let kind = match iter.peek() {
+				Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => {
+					let fields = group.stream();
+					iter.next();
+					StructKind::Keyed(
+						KeyedStruct::parse_fields(fields).map_err(EnumParseError::Field)?,
+					)
+				}
+				Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+					let fields = group.stream();
+					iter.next();
+					StructKind::Tuple(
+						TupleStruct::parse_fields(fields).map_err(EnumParseError::Field)?,
+					)
+				}
+				_ => StructKind::Empty,
+			};
+			let discriminant = if iter.next_is_punct('=') {
+				iter.next();
+				// Discriminants can be any constant expression, but can't
+				// contain a `,` outside of a group
+				let mut tokens = TokenStream::new();
+				while iter.peek().is_some() && !iter.next_is_punct(',') {
+					tokens.extend(iter.next());
+				}
+				Some(Expr::from_tokens(tokens))
+			} else {
+				None
+			};
+			iter.next(); // Skip ,
+
+			variants.push(EnumVariant {
+				attributes,
+				name,
+				kind,
+				discriminant,
+			});
+		}
+
+		Ok(Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			variants,
+		})
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Enum {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			variants,
+		} = self;
+		tokens.extend(quote! {
+			#(#attributes)* #visibility enum #name #generics #where_clause {
+				#(#variants,)*
+			}
+		});
+	}
+}
+impl ToTokens for EnumVariant {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			name,
+			kind,
+			discriminant,
+		} = self;
+		let discriminant = discriminant
+			.as_ref()
+			.map(|discriminant| quote! { = #discriminant });
+		tokens.extend(quote! { #(#attributes)* #name #kind #discriminant });
+	}
+}
+
+/// A union declaration.
+///
+/// Example:
+/// ```rs
+/// union IntOrFloat {
+///     int: u32,
+///     float: f32,
+/// }
+/// ```
+///
+/// See: <https://doc.rust-lang.org/reference/items/unions.html>
+#[derive(Debug)]
+pub struct Union {
+	pub attributes: Vec<Attribute>,
+	pub visibility: Visibility,
+	pub name: Ident,
+	pub generics: Generics,
+	pub where_clause: WhereClause,
+	pub fields: KeyedStruct,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnionParseError {
+	Attribute(AttributeParseError),
+	Generics(GenericsParseError),
+	WhereClause(WherePredicateParseError),
+	Field(FieldParseError),
+	/// The `union` keyword wasn't followed by a name.
+	MissingName,
+	/// The union didn't have a body.
+	MissingBody,
+}
+impl AstComponent for Union {
+	type ParseError = UnionParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		// `union` is only a keyword when it's followed by the union's name
+		if !is_next_item(iter, "union") {
+			return false;
+		}
+		let mut iter = iter.clone();
+		while Attribute::is_next(&mut iter) {
+			Attribute::skip(&mut iter);
+		}
+		Visibility::skip(&mut iter);
+		iter.next();
+		matches!(iter.peek(), Some(TokenTree::Ident(_)))
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		let attributes = parse_attributes(iter).map_err(UnionParseError::Attribute)?;
+		let visibility = Visibility::parse(iter).unwrap();
+		iter.next(); // Skip union
+		let Some(TokenTree::Ident(name)) = iter.next() else {
+			return Err(UnionParseError::MissingName);
+		};
+		let generics = Generics::maybe_parse(iter)
+			.unwrap()
+			.map_err(UnionParseError::Generics)?;
+		let where_clause = WhereClause::maybe_parse(iter)
+			.unwrap()
+			.map_err(UnionParseError::WhereClause)?;
+		let Some(TokenTree::Group(body)) = iter.next() else {
+			return Err(UnionParseError::MissingBody);
+		};
+		if body.delimiter() != Delimiter::Brace {
+			return Err(UnionParseError::MissingBody);
+		}
+		let fields = KeyedStruct::parse_fields(body.stream()).map_err(UnionParseError::Field)?;
+
+		Ok(Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			fields,
+		})
+	}
+	fn skip(iter: &mut impl TokenIter) {
+		let _ = Self::parse(iter);
+	}
+}
+impl ToTokens for Union {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		let Self {
+			attributes,
+			visibility,
+			name,
+			generics,
+			where_clause,
+			fields,
+		} = self;
+		tokens.extend(quote! {
+			#(#attributes)* #visibility union #name #generics #where_clause #fields
+		});
+	}
+}
+
+/// Checks if the next item, after its attributes and visibility, starts with
+/// the given keyword.
+fn is_next_item(iter: &mut impl TokenIter, keyword: &str) -> bool {
+	let mut iter = iter.clone();
+	while Attribute::is_next(&mut iter) {
+		Attribute::skip(&mut iter);
+	}
+	Visibility::skip(&mut iter);
+	iter.next_is_ident(keyword)
+}
+/// Parses every attribute at the start of `iter`.
+fn parse_attributes(iter: &mut impl TokenIter) -> Result<Vec<Attribute>, AttributeParseError> {
+	let mut attributes = Vec::new();
+	while let Some(attribute) = Attribute::maybe_parse(iter) {
+		attributes.push(attribute?);
+	}
+	Ok(attributes)
+}
+
+#[derive(Debug)]
+pub struct FunctionQualifiers {
+	pub is_const: bool,
+	pub is_async: bool,
+	pub safety: Safety,
+	pub abi: Option<String>,
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FunctionQualifiersParseError {
+	MissingAbi,
+}
+impl AstComponent for FunctionQualifiers {
+	type ParseError = FunctionQualifiersParseError;
+
+	fn is_next(iter: &mut impl TokenIter) -> bool {
+		let mut iter = iter.clone();
+		Self::skip(&mut iter);
+		iter.next_is_ident("fn")
+	}
+
+	fn parse(iter: &mut impl TokenIter) -> Result<Self, Self::ParseError> {
+		let mut this = Self {
+			is_const: false,
+			is_async: false,
+			safety: Safety::Safe,
+			abi: None,
+		};
+
+		if iter.next_is_ident("const") {
+			this.is_const = true;
+			iter.next();
+		}
+		if iter.next_is_ident("async") {
+			this.is_async = true;
+			iter.next();
+		}
+		if iter.next_is_ident("safe") {
 			iter.next();
 		} else if iter.next_is_ident("unsafe") {
 			this.safety = Safety::Unsafe;
@@ -452,3 +2323,770 @@ impl AstComponent for Function {
 		todo!()
 	}
 }
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, std::fmt::Debug};
+
+	/// Checks that `tokens` are the same as `expected`. Whitespace is ignored,
+	/// since the spacing of punctuation depends on where the tokens came from.
+	fn assert_tokens(tokens: impl ToTokens, expected: &str) {
+		let strip = |tokens: String| tokens.replace(char::is_whitespace, "");
+		let expected: TokenStream = expected.parse().unwrap();
+		assert_eq!(
+			strip(tokens.to_token_stream().to_string()),
+			strip(expected.to_string())
+		);
+	}
+	/// Parses `source`, then checks that converting it back to tokens gives
+	/// `expected`.
+	fn round_trip<T: AstComponent + ToTokens>(source: &str, expected: &str) -> T {
+		let tokens: TokenStream = source.parse().unwrap();
+		let mut iter = tokens.into_iter().peekable();
+		assert!(T::is_next(&mut iter));
+		let parsed = T::parse(&mut iter).unwrap();
+		assert!(iter.next().is_none());
+
+		assert_tokens(&parsed, expected);
+		parsed
+	}
+	/// Parses `source`, returning the error it fails with.
+	fn parse_err<T: AstComponent + Debug>(source: &str) -> T::ParseError {
+		let tokens: TokenStream = source.parse().unwrap();
+		T::parse(&mut tokens.into_iter().peekable()).unwrap_err()
+	}
+	/// A syntax error. Spans aren't compared, so any span works.
+	fn syntax(error: SyntaxError) -> Spanned<SyntaxError> {
+		Spanned::new(error, Span::call_site())
+	}
+
+	#[test]
+	fn visibility() {
+		let public: Visibility = round_trip("pub", "pub");
+		assert!(matches!(public, Visibility::Public));
+		let scoped: Visibility = round_trip("pub(in crate::a)", "pub(in crate::a)");
+		assert!(matches!(scoped, Visibility::Scoped(_)));
+		let private: Visibility = round_trip("", "");
+		assert!(matches!(private, Visibility::Private));
+
+		// `pub (u8, u8)` is a public tuple field, not a scope
+		let tokens: TokenStream = "pub (u8, u8)".parse().unwrap();
+		let mut iter = tokens.into_iter().peekable();
+		assert!(matches!(
+			Visibility::parse(&mut iter),
+			Ok(Visibility::Public)
+		));
+		assert!(matches!(iter.next(), Some(TokenTree::Group(_))));
+	}
+
+	#[test]
+	fn generics() {
+		let generics: Generics = round_trip(
+			"<'a: 'b + 'c, T: ?Sized + Copy = u8, const N: usize = { 2 + 2 }>",
+			"<'a: 'b + 'c, T: ?Sized + Copy = u8, const N: usize = { 2 + 2 }>",
+		);
+		assert!(matches!(
+			&generics.params[0],
+			GenericItem::Lifetime { bounds, .. } if bounds.len() == 2
+		));
+		assert!(matches!(
+			&generics.params[1],
+			GenericItem::Type {
+				default: Some(_),
+				..
+			}
+		));
+		assert!(matches!(
+			&generics.params[2],
+			GenericItem::Const {
+				default: Some(Expr::Block(_)),
+				..
+			}
+		));
+		assert_tokens(
+			generics.impl_generics(),
+			"<'a: 'b + 'c, T: ?Sized + Copy, const N: usize>",
+		);
+		assert_tokens(generics.type_generics(), "<'a, T, N>");
+		assert_eq!(
+			generics
+				.type_params()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>(),
+			["T"]
+		);
+
+		let empty = Generics::maybe_parse(&mut TokenStream::new().into_iter().peekable())
+			.unwrap()
+			.unwrap();
+		assert!(empty.params.is_empty());
+		assert!(empty.impl_generics().is_empty());
+		assert!(empty.to_token_stream().is_empty());
+
+		assert_eq!(parse_err::<Generics>("<T"), GenericsParseError::Unclosed);
+		assert_eq!(parse_err::<Generics>("<T U>"), GenericsParseError::Unclosed);
+		assert_eq!(
+			parse_err::<Generics>("<'a, 3>"),
+			GenericsParseError::InvalidParameter
+		);
+		assert_eq!(
+			parse_err::<Generics>("<const N>"),
+			GenericsParseError::MissingConstType
+		);
+		assert_eq!(
+			parse_err::<Generics>("<T = *u8>"),
+			GenericsParseError::Syntax(syntax(SyntaxError::MissingPointerMutability))
+		);
+	}
+
+	#[test]
+	fn lifetimes() {
+		let implicit: Lifetime = round_trip("'_", "'_");
+		assert!(matches!(implicit, Lifetime::Implicit));
+		let r#static: Lifetime = round_trip("'static", "'static");
+		assert!(matches!(r#static, Lifetime::Static));
+		let custom: Lifetime = round_trip("'a", "'a");
+		assert!(matches!(custom, Lifetime::Custom(name) if name == "a"));
+
+		// A `'` on its own can't be lexed, so the tokens are built by hand
+		let tokens = TokenStream::from_iter([
+			TokenTree::Punct(Punct::new('\'', Spacing::Joint)),
+			TokenTree::Literal(Literal::u8_unsuffixed(1)),
+		]);
+		assert_eq!(
+			Lifetime::parse(&mut tokens.clone().into_iter().peekable()).unwrap_err(),
+			LifetimeParseError::MissingName
+		);
+		// Types report invalid lifetimes as syntax errors
+		let reference = TokenStream::from_iter(
+			[TokenTree::Punct(Punct::new('&', Spacing::Alone))]
+				.into_iter()
+				.chain(tokens),
+		);
+		assert_eq!(
+			Type::parse(&mut reference.into_iter().peekable()).unwrap_err(),
+			syntax(SyntaxError::InvalidLifetime)
+		);
+	}
+
+	#[test]
+	fn where_clauses() {
+		let clause: WhereClause = round_trip(
+			"where 'a: 'b + 'c, T: Copy, for<'b> F: Fn(&'b T) -> bool",
+			"where 'a: 'b + 'c, T: Copy, for<'b> F: Fn(&'b T) -> bool,",
+		);
+		assert!(matches!(
+			&clause.predicates[0],
+			WherePredicate::Lifetime { bounds, .. } if bounds.len() == 2
+		));
+		assert!(matches!(
+			&clause.predicates[2],
+			WherePredicate::Type { higher_ranked_lifetimes, .. }
+				if higher_ranked_lifetimes.len() == 1
+		));
+
+		// The clause stops at the item's body
+		let tokens: TokenStream = "where T: Copy { a: T }".parse().unwrap();
+		let mut iter = tokens.into_iter().peekable();
+		assert_eq!(WhereClause::parse(&mut iter).unwrap().predicates.len(), 1);
+		assert!(matches!(iter.next(), Some(TokenTree::Group(_))));
+
+		let tokens: TokenStream = "{}".parse().unwrap();
+		let empty = WhereClause::maybe_parse(&mut tokens.into_iter().peekable())
+			.unwrap()
+			.unwrap();
+		assert!(empty.predicates.is_empty());
+		assert!(empty.to_token_stream().is_empty());
+
+		assert_eq!(
+			parse_err::<WhereClause>("where T Copy"),
+			WherePredicateParseError::MissingColon
+		);
+		assert_eq!(
+			parse_err::<WhereClause>("where *u8: Copy"),
+			WherePredicateParseError::Syntax(syntax(SyntaxError::MissingPointerMutability))
+		);
+		assert_eq!(
+			parse_err::<WhereClause>("where for<T> T: Copy"),
+			WherePredicateParseError::Syntax(syntax(SyntaxError::InvalidLifetime))
+		);
+	}
+
+	#[test]
+	fn attributes() {
+		let no_mangle: Attribute = round_trip("#[unsafe(no_mangle)]", "#[unsafe(no_mangle)]");
+		assert!(no_mangle.is_unsafe);
+		assert!(no_mangle.args.is_none());
+
+		let export: Attribute = round_trip(
+			"#[unsafe(export_name = \"main\")]",
+			"#[unsafe(export_name = \"main\")]",
+		);
+		assert_eq!(export.name, "export_name");
+		assert!(matches!(export.args, Some(AttributeArgs::Assigned(_))));
+
+		let doc: Attribute = round_trip("#[doc = \"text\"]", "#[doc = \"text\"]");
+		assert!(!doc.is_unsafe);
+		assert_eq!(
+			doc.args.unwrap().key_values().unwrap_err(),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+
+		let key_values = |source: &str| {
+			let attribute: Attribute = round_trip(source, source);
+			attribute.args.unwrap().key_values()
+		};
+		assert_eq!(
+			key_values("#[cfg_attr(test, derive(Debug))]").unwrap_err(),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			key_values("#[cli(short =)]").unwrap_err(),
+			syntax(SyntaxError::UnexpectedEnd)
+		);
+		assert_eq!(
+			key_values("#[cli(1)]").unwrap_err(),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert!(key_values("#[cli()]").unwrap().is_empty());
+
+		assert_eq!(
+			parse_err::<Attribute>("#x"),
+			AttributeParseError::MissingBrackets
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#(x)"),
+			AttributeParseError::MissingBrackets
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#[]"),
+			AttributeParseError::MissingAttributeName
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#[a::]"),
+			AttributeParseError::MissingAttributeName
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#[unsafe]"),
+			AttributeParseError::MissingUnsafeInner
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#[unsafe[x]]"),
+			AttributeParseError::MissingUnsafeInner
+		);
+		assert_eq!(
+			parse_err::<Attribute>("#[unsafe()]"),
+			AttributeParseError::MissingAttributeName
+		);
+	}
+
+	#[test]
+	fn structs() {
+		let unit: Struct = round_trip("pub struct Unit;", "pub struct Unit;");
+		assert!(matches!(unit.kind, StructKind::Empty));
+		let unit: Struct = round_trip(
+			"struct Unit<T> where T: Copy;",
+			"struct Unit<T> where T: Copy,;",
+		);
+		assert_eq!(unit.where_clause.predicates.len(), 1);
+
+		let tuple: Struct = round_trip(
+			"struct Pair<T>(pub (T, T), #[doc = \"b\"] pub(crate) T) where T: Copy;",
+			"struct Pair<T>(pub (T, T), #[doc = \"b\"] pub(crate) T,) where T: Copy,;",
+		);
+		let StructKind::Tuple(fields) = &tuple.kind else {
+			panic!("Expected a tuple struct");
+		};
+		assert_eq!(fields.fields.len(), 2);
+		assert!(matches!(fields.fields[0].visibility, Visibility::Public));
+
+		let keyed: Struct = round_trip(
+			"#[derive(Debug)] #[crux::main] pub(in crate::a) struct Keyed<'a, 'b: 'a, T: \
+			 Fn() -> u8 = fn() -> u8, const N: usize = 4> where 'a: 'b, <T as Tr>::Out: \
+			 Iterator<Item = u8>, for<'c> &'c T: Sized { r#type: &'a [T; N], pub b: \
+			 Vec<Vec<u8>> }",
+			"#[derive(Debug)] #[crux::main] pub(in crate::a) struct Keyed<'a, 'b: 'a, T: \
+			 Fn() -> u8 = fn() -> u8, const N: usize = 4> where 'a: 'b, <T as Tr>::Out: \
+			 Iterator<Item = u8>, for<'c> &'c T: Sized, { r#type: &'a [T; N], pub b: \
+			 Vec<Vec<u8>>, }",
+		);
+		assert_eq!(keyed.attributes[1].name, "crux::main");
+		assert_eq!(keyed.generics.params.len(), 4);
+		assert_eq!(
+			keyed.generics.type_generics().to_string(),
+			quote! { <'a, 'b, T, N> }.to_string()
+		);
+		assert_eq!(keyed.where_clause.predicates.len(), 3);
+		let WherePredicate::Type { r#type, bounds, .. } = &keyed.where_clause.predicates[1] else {
+			panic!("Expected a type predicate");
+		};
+		assert!(matches!(
+			r#type,
+			Type::QualifiedPath {
+				r#trait: Some(_),
+				..
+			}
+		));
+		assert!(matches!(
+			bounds.as_slice(),
+			[TypeBound::Trait { maybe: false, .. }]
+		));
+
+		assert_eq!(
+			parse_err::<Struct>("struct;"),
+			StructParseError::MissingName
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A"),
+			StructParseError::MissingBody
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A(u8)"),
+			StructParseError::MissingBody
+		);
+		assert_eq!(
+			parse_err::<Struct>("#(x) struct A;"),
+			StructParseError::Attribute(AttributeParseError::MissingBrackets)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A<T;"),
+			StructParseError::Generics(GenericsParseError::Unclosed)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A where T Copy {}"),
+			StructParseError::WhereClause(WherePredicateParseError::MissingColon)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A { a }"),
+			StructParseError::Field(FieldParseError::MissingType)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A { 1: u8 }"),
+			StructParseError::Field(FieldParseError::MissingName)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A(pub);"),
+			StructParseError::Field(FieldParseError::MissingType)
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A { a: u8 b: u8 }"),
+			StructParseError::Field(FieldParseError::Syntax(syntax(
+				SyntaxError::UnexpectedToken
+			)))
+		);
+		assert_eq!(
+			parse_err::<Struct>("struct A(#[] u8);"),
+			StructParseError::Field(FieldParseError::Attribute(
+				AttributeParseError::MissingAttributeName
+			))
+		);
+	}
+
+	#[test]
+	fn types() {
+		round_trip::<Type>("&'a mut [u8]", "&'a mut [u8]");
+		round_trip::<Type>("*const (u8, (u16,), ())", "*const (u8, (u16,), ())");
+		round_trip::<Type>("&(dyn Debug + Send)", "&(dyn Debug + Send)");
+		round_trip::<Type>("[Option<T>; N * 2]", "[Option<T>; N * 2]");
+		round_trip::<Type>(
+			"::core::result::Result<Vec<u8>, <T as Tr<'_>>::Err>",
+			"::core::result::Result<Vec<u8>, <T as Tr<'_>>::Err>",
+		);
+		round_trip::<Type>(
+			"for<'a> unsafe extern \"C\" fn(len: usize, &'a u8, ...) -> !",
+			"for<'a> unsafe extern \"C\" fn(len: usize, &'a u8, ...) -> !",
+		);
+		round_trip::<Type>(
+			"Box<dyn for<'a> Fn(&'a T) -> bool + 'static>",
+			"Box<dyn for<'a> Fn(&'a T) -> bool + 'static>",
+		);
+		round_trip::<Type>(
+			"impl Iterator<Item = (), Item: ?Sized> + use<'a>",
+			"impl Iterator<Item = (), Item: ?Sized> + use<'a>",
+		);
+
+		let array: Type = round_trip("[u8; 4]", "[u8; 4]");
+		let Type::Array {
+			length: Some(Expr::Literal(length)),
+			..
+		} = array
+		else {
+			panic!("Expected an array with a literal length");
+		};
+		assert_eq!(length.to_string(), "4");
+		let slice: Type = round_trip("[c_char]", "[c_char]");
+		assert!(matches!(slice, Type::Array { length: None, .. }));
+
+		let inferred: Type = round_trip("_", "_");
+		assert!(matches!(inferred, Type::Inferred));
+		let never: Type = round_trip("!", "!");
+		assert!(matches!(never, Type::Never));
+		let unit: Type = round_trip("()", "()");
+		assert!(matches!(unit, Type::Tuple { inner_types } if inner_types.is_empty()));
+		let single: Type = round_trip("(u8,)", "(u8,)");
+		assert!(matches!(single, Type::Tuple { inner_types } if inner_types.len() == 1));
+		let paren: Type = round_trip("(u8)", "(u8)");
+		assert!(matches!(paren, Type::Paren(_)));
+
+		// `extern` on its own means the C ABI
+		let extern_fn: Type = round_trip("extern fn(u8)", "extern \"C\" fn(u8,)");
+		assert!(matches!(
+			extern_fn,
+			Type::FunctionPointer {
+				abi: Some(abi),
+				variadic: false,
+				return_type: None,
+				..
+			} if abi == "\"C\""
+		));
+		let qualified: Type = round_trip("<Vec<u8>>::Target", "<Vec<u8>>::Target");
+		assert!(matches!(
+			qualified,
+			Type::QualifiedPath { r#trait: None, .. }
+		));
+
+		// Types passed to `macro_rules` macros are in invisible groups
+		let group = Group::new(Delimiter::None, "*mut u8".parse().unwrap());
+		let tokens = TokenStream::from_iter([TokenTree::Group(group)]);
+		assert!(matches!(
+			Type::parse(&mut tokens.into_iter().peekable()),
+			Ok(Type::Pointer {
+				mutability: Mutability::Mut,
+				..
+			})
+		));
+
+		assert_eq!(parse_err::<Type>(""), syntax(SyntaxError::UnexpectedEnd));
+		assert_eq!(parse_err::<Type>("1"), syntax(SyntaxError::UnexpectedToken));
+		assert_eq!(
+			parse_err::<Type>("{ u8 }"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("*u8"),
+			syntax(SyntaxError::MissingPointerMutability)
+		);
+		assert_eq!(
+			parse_err::<Type>("Vec<u8"),
+			syntax(SyntaxError::UnclosedGenerics)
+		);
+		assert_eq!(
+			parse_err::<Type>("<T as Tr"),
+			syntax(SyntaxError::UnclosedGenerics)
+		);
+		assert_eq!(
+			parse_err::<Type>("<T as Tr>"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("[u8 u8]"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("(u8 u8)"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("unsafe u8"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("fn"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("fn(.., u8)"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("for<T> fn()"),
+			syntax(SyntaxError::InvalidLifetime)
+		);
+	}
+
+	#[test]
+	fn paths() {
+		let path: Path = round_trip("::a::b<T>::c", "::a::b<T>::c");
+		assert!(path.leading_colons);
+		assert_eq!(path.segments.len(), 3);
+		assert!(matches!(
+			path.segments[1].arguments,
+			PathArguments::AngleBracketed(_)
+		));
+
+		let closure: Path = round_trip("FnOnce(u8, &str) -> bool", "FnOnce(u8, &str) -> bool");
+		assert!(matches!(
+			&closure.segments[0].arguments,
+			PathArguments::Parenthesized {
+				inputs,
+				output: Some(_),
+			} if inputs.len() == 2
+		));
+		let closure: Path = round_trip("Fn()", "Fn()");
+		assert!(matches!(
+			&closure.segments[0].arguments,
+			PathArguments::Parenthesized { inputs, output: None } if inputs.is_empty()
+		));
+
+		let ident = |source: &str| {
+			let path: Path = round_trip(source, source);
+			path.as_ident().map(ToString::to_string)
+		};
+		assert_eq!(ident("u8").as_deref(), Some("u8"));
+		assert_eq!(ident("::u8"), None);
+		assert_eq!(ident("a::b"), None);
+		assert_eq!(ident("Vec<u8>"), None);
+
+		assert_eq!(
+			parse_err::<Path>("a::"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Path>("Fn(u8 u8)"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Path>("Fn() -> *u8"),
+			syntax(SyntaxError::MissingPointerMutability)
+		);
+	}
+
+	#[test]
+	fn generic_args() {
+		let Type::Path(path) = round_trip(
+			"Foo<'a, u8, 3, -1, { N }, Item = u8, Item: Copy + 'a>",
+			"Foo<'a, u8, 3, -1, { N }, Item = u8, Item: Copy + 'a>",
+		) else {
+			panic!("Expected a path");
+		};
+		let PathArguments::AngleBracketed(args) = &path.segments[0].arguments else {
+			panic!("Expected generic arguments");
+		};
+		assert!(matches!(
+			args.as_slice(),
+			[
+				GenericArg::Lifetime(_),
+				GenericArg::Type(_),
+				GenericArg::Const(Expr::Literal(_)),
+				GenericArg::Const(Expr::Negate(_)),
+				GenericArg::Const(Expr::Block(_)),
+				GenericArg::Binding { .. },
+				GenericArg::Constraint { .. },
+			]
+		));
+		assert!(matches!(
+			&args[6],
+			GenericArg::Constraint { bounds, .. } if bounds.len() == 2
+		));
+
+		assert_eq!(
+			parse_err::<Type>("Foo<Item = >"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Type>("Foo<u8 u8>"),
+			syntax(SyntaxError::UnclosedGenerics)
+		);
+	}
+
+	#[test]
+	fn type_bounds() {
+		let maybe: TypeBound = round_trip("?Sized", "?Sized");
+		assert!(matches!(maybe, TypeBound::Trait { maybe: true, .. }));
+		let lifetime: TypeBound = round_trip("'static", "'static");
+		assert!(matches!(lifetime, TypeBound::Lifetime(Lifetime::Static)));
+		let higher_ranked: TypeBound = round_trip("for<'a> Fn(&'a u8)", "for<'a> Fn(&'a u8)");
+		assert!(matches!(
+			higher_ranked,
+			TypeBound::Trait { higher_ranked_lifetimes, .. } if higher_ranked_lifetimes.len() == 1
+		));
+
+		let bounds = |source: &str| {
+			let tokens: TokenStream = source.parse().unwrap();
+			TypeBound::parse_bounds(&mut tokens.into_iter().peekable())
+		};
+		assert_eq!(bounds("Copy + 'static + ?Sized").unwrap().len(), 3);
+		assert_eq!(bounds("Copy +").unwrap().len(), 1);
+		assert!(bounds("").unwrap().is_empty());
+
+		assert_eq!(
+			parse_err::<TypeBound>("?'a"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<TypeBound>("for<'a Fn()"),
+			syntax(SyntaxError::UnclosedGenerics)
+		);
+	}
+
+	#[test]
+	fn exprs() {
+		round_trip::<Expr>("-1", "-1");
+		round_trip::<Expr>(
+			"::core::mem::size_of::<u8>()",
+			"::core::mem::size_of::<u8>()",
+		);
+		round_trip::<Expr>("Some(f(\"a\", 2))(3)", "Some(f(\"a\", 2))(3)");
+		round_trip::<Expr>("{ N + 1 }", "{ N + 1 }");
+
+		let literal: Expr = round_trip("b'a'", "b'a'");
+		assert!(literal.as_literal().is_some());
+		let negated: Expr = round_trip("-x", "-x");
+		assert!(matches!(negated, Expr::Negate(inner) if matches!(*inner, Expr::Path(_))));
+		let path: Expr = round_trip("Self::VARIANT", "Self::VARIANT");
+		assert!(path.as_literal().is_none());
+		let call: Expr = round_trip("f()", "f()");
+		assert!(matches!(call, Expr::Call { args, .. } if args.is_empty()));
+		let turbofish: Expr = round_trip("Vec::<u8>::new", "Vec::<u8>::new");
+		assert!(matches!(turbofish, Expr::Path(_)));
+
+		// Expressions passed to `macro_rules` macros are in invisible groups
+		let group = Group::new(Delimiter::None, "1".parse().unwrap());
+		let tokens = TokenStream::from_iter([TokenTree::Group(group)]);
+		assert!(matches!(Expr::from_tokens(tokens), Expr::Literal(_)));
+
+		let tokens: TokenStream = "a + b".parse().unwrap();
+		assert!(matches!(Expr::from_tokens(tokens), Expr::Verbatim(_)));
+		let tokens: TokenStream = "1 2".parse().unwrap();
+		assert!(matches!(Expr::from_tokens(tokens), Expr::Verbatim(_)));
+
+		assert_eq!(parse_err::<Expr>(""), syntax(SyntaxError::UnexpectedEnd));
+		assert_eq!(parse_err::<Expr>("+"), syntax(SyntaxError::UnexpectedToken));
+		assert_eq!(
+			parse_err::<Expr>("(1)"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+		assert_eq!(
+			parse_err::<Expr>("f(1 2)"),
+			syntax(SyntaxError::UnexpectedToken)
+		);
+
+		let attribute: Attribute = round_trip(
+			"#[cli(short = 'p', long, default = 1 + 2)]",
+			"#[cli(short = 'p', long, default = 1 + 2)]",
+		);
+		let args = attribute.args.unwrap().key_values().unwrap();
+		assert_eq!(args.len(), 3);
+		assert_eq!(args[0].0.to_string(), "short");
+		assert!(args[0].1.as_ref().unwrap().as_literal().is_some());
+		assert!(args[1].1.is_none());
+		assert!(matches!(args[2].1, Some(Expr::Verbatim(_))));
+	}
+
+	#[test]
+	fn enums() {
+		let shape: Enum = round_trip(
+			"enum Shape<T> { #[default] Point, Circle(T), Rect { w: T, h: T }, Tag = 1 << 2 }",
+			"enum Shape<T> { #[default] Point, Circle(T,), Rect { w: T, h: T, }, Tag = 1 << 2, }",
+		);
+		assert_eq!(shape.variants.len(), 4);
+		assert!(matches!(shape.variants[1].kind, StructKind::Tuple(_)));
+		assert!(shape.variants[3].discriminant.is_some());
+
+		let never: Enum = round_trip("pub enum Never {}", "pub enum Never {}");
+		assert!(never.variants.is_empty());
+
+		// Variants can't have a visibility, so it's left out
+		let option: Enum = round_trip(
+			"pub(crate) enum Opt<T> where T: Clone { #[doc = \"x\"] pub Some(T), None = -1 }",
+			"pub(crate) enum Opt<T> where T: Clone, { #[doc = \"x\"] Some(T,), None = -1, }",
+		);
+		assert_eq!(option.where_clause.predicates.len(), 1);
+		assert_eq!(option.variants[0].attributes.len(), 1);
+		assert!(matches!(
+			option.variants[1].discriminant,
+			Some(Expr::Negate(_))
+		));
+
+		assert_eq!(parse_err::<Enum>("enum;"), EnumParseError::MissingName);
+		assert_eq!(parse_err::<Enum>("enum A;"), EnumParseError::MissingBody);
+		assert_eq!(parse_err::<Enum>("enum A(u8)"), EnumParseError::MissingBody);
+		assert_eq!(
+			parse_err::<Enum>("enum A { 1 }"),
+			EnumParseError::MissingVariantName
+		);
+		assert_eq!(
+			parse_err::<Enum>("#[] enum A {}"),
+			EnumParseError::Attribute(AttributeParseError::MissingAttributeName)
+		);
+		assert_eq!(
+			parse_err::<Enum>("enum A { #[] B }"),
+			EnumParseError::Attribute(AttributeParseError::MissingAttributeName)
+		);
+		assert_eq!(
+			parse_err::<Enum>("enum A<const N> {}"),
+			EnumParseError::Generics(GenericsParseError::MissingConstType)
+		);
+		assert_eq!(
+			parse_err::<Enum>("enum A where T {}"),
+			EnumParseError::WhereClause(WherePredicateParseError::MissingColon)
+		);
+		assert_eq!(
+			parse_err::<Enum>("enum A { B(*u8) }"),
+			EnumParseError::Field(FieldParseError::Syntax(syntax(
+				SyntaxError::MissingPointerMutability
+			)))
+		);
+		assert_eq!(
+			parse_err::<Enum>("enum A { B { c } }"),
+			EnumParseError::Field(FieldParseError::MissingType)
+		);
+	}
+
+	#[test]
+	fn unions() {
+		let union: Union = round_trip(
+			"#[repr(C)] union Bits<T: Copy> { int: u32, value: T }",
+			"#[repr(C)] union Bits<T: Copy> { int: u32, value: T, }",
+		);
+		assert_eq!(union.fields.fields.len(), 2);
+		let union: Union = round_trip(
+			"pub union Bits<T> where T: Copy { value: T }",
+			"pub union Bits<T> where T: Copy, { value: T, }",
+		);
+		assert!(matches!(union.visibility, Visibility::Public));
+		assert_eq!(union.where_clause.predicates.len(), 1);
+
+		// `union` is only a keyword before a name
+		let is_next = |source: &str| {
+			let tokens: TokenStream = source.parse().unwrap();
+			Union::is_next(&mut tokens.into_iter().peekable())
+		};
+		assert!(!is_next("union::some_fn();"));
+		assert!(!is_next("union = 1;"));
+		assert!(!is_next("union"));
+		assert!(is_next("#[repr(C)] pub(crate) union U {}"));
+
+		assert_eq!(
+			parse_err::<Union>("union 1 {}"),
+			UnionParseError::MissingName
+		);
+		assert_eq!(parse_err::<Union>("union A;"), UnionParseError::MissingBody);
+		assert_eq!(
+			parse_err::<Union>("union A(u8)"),
+			UnionParseError::MissingBody
+		);
+		assert_eq!(
+			parse_err::<Union>("#(repr) union A {}"),
+			UnionParseError::Attribute(AttributeParseError::MissingBrackets)
+		);
+		assert_eq!(
+			parse_err::<Union>("union A<3> {}"),
+			UnionParseError::Generics(GenericsParseError::InvalidParameter)
+		);
+		assert_eq!(
+			parse_err::<Union>("union A where T {}"),
+			UnionParseError::WhereClause(WherePredicateParseError::MissingColon)
+		);
+		assert_eq!(
+			parse_err::<Union>("union A { a }"),
+			UnionParseError::Field(FieldParseError::MissingType)
+		);
+	}
+}
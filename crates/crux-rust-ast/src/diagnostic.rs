@@ -0,0 +1,195 @@
+//! Compiler errors for procedural macros.
+
+use {
+	crate::{
+		Literal, Span, Spanned, TokenStream,
+		ast::{
+			EnumParseError, FieldParseError, GenericsParseError, StructParseError, SyntaxError,
+			UnionParseError, WherePredicateParseError,
+		},
+		quote,
+	},
+	quote::{ToTokens, quote_spanned},
+	std::fmt::{self, Display, Formatter},
+};
+
+/// An error in a macro's input, which is reported by expanding to
+/// `compile_error!`.
+///
+/// Panicking in a macro makes the compiler point at the whole macro
+/// invocation and print "proc macro panicked". A diagnostic instead points at
+/// the exact tokens that caused the error:
+///
+/// ```rs
+/// let Some(TokenTree::Ident(name)) = tokens.next() else {
+///     return Err(Diagnostic::new(span, "Expected a struct name")
+///         .help("structs are declared like `struct Name { ... }`"));
+/// };
+/// ```
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+	start: Span,
+	end: Span,
+	message: String,
+	notes: Vec<String>,
+}
+impl Diagnostic {
+	/// Creates an error pointing at `span`.
+	pub fn new(span: Span, message: impl Into<String>) -> Self {
+		Self {
+			start: span,
+			end: span,
+			message: message.into(),
+			notes: Vec::new(),
+		}
+	}
+	/// Creates an error pointing at the macro's invocation.
+	pub fn call_site(message: impl Into<String>) -> Self {
+		Self::new(Span::call_site(), message)
+	}
+	/// Creates an error pointing at every token in `tokens`, e.g. a whole type
+	/// instead of just its first token.
+	pub fn spanned(tokens: &impl ToTokens, message: impl Into<String>) -> Self {
+		let mut tokens = tokens.to_token_stream().into_iter();
+		let start = tokens
+			.next()
+			.map_or_else(Span::call_site, |token| token.span());
+		let end = tokens.last().map_or(start, |token| token.span());
+
+		Self {
+			start,
+			end,
+			message: message.into(),
+			notes: Vec::new(),
+		}
+	}
+
+	/// Adds a `help:` line to the error, suggesting how to fix it.
+	pub fn help(mut self, help: impl Display) -> Self {
+		self.notes.push(format!("help: {help}"));
+		self
+	}
+	/// Adds a `note:` line to the error, with extra context.
+	pub fn note(mut self, note: impl Display) -> Self {
+		self.notes.push(format!("note: {note}"));
+		self
+	}
+
+	pub fn message(&self) -> &str {
+		&self.message
+	}
+	pub fn span(&self) -> Span {
+		self.start
+	}
+
+	/// Expands to a `compile_error!` invocation that reports this error.
+	pub fn to_compile_error(&self) -> TokenStream {
+		let mut message = self.message.clone();
+		for note in &self.notes {
+			message.push('\n');
+			message.push_str(note);
+		}
+		let mut message = Literal::string(&message);
+		message.set_span(self.end);
+
+		// The compiler highlights everything from the first token of the
+		// invocation to the last, so this covers `start..end`
+		let path = quote_spanned! { self.start=> ::core::compile_error! };
+		let args = quote_spanned! { self.end=> { #message } };
+		quote! { #path #args }
+	}
+}
+impl ToTokens for Diagnostic {
+	fn to_tokens(&self, tokens: &mut TokenStream) {
+		tokens.extend(self.to_compile_error());
+	}
+}
+impl<E: Display> From<Spanned<E>> for Diagnostic {
+	fn from(error: Spanned<E>) -> Self {
+		Self::new(error.span, error.error.to_string())
+	}
+}
+
+// Items given to derive macros were already parsed by the compiler, so these
+// errors come from syntax that `crux-rust-ast` doesn't support yet.
+impl From<StructParseError> for Diagnostic {
+	fn from(error: StructParseError) -> Self {
+		match error {
+			StructParseError::Generics(GenericsParseError::Syntax(error))
+			| StructParseError::WhereClause(WherePredicateParseError::Syntax(error))
+			| StructParseError::Field(FieldParseError::Syntax(error)) => error.into(),
+			other => Self::call_site(format!("Failed to parse struct: {other:?}")),
+		}
+	}
+}
+impl From<EnumParseError> for Diagnostic {
+	fn from(error: EnumParseError) -> Self {
+		match error {
+			EnumParseError::Generics(GenericsParseError::Syntax(error))
+			| EnumParseError::WhereClause(WherePredicateParseError::Syntax(error))
+			| EnumParseError::Field(FieldParseError::Syntax(error)) => error.into(),
+			other => Self::call_site(format!("Failed to parse enum: {other:?}")),
+		}
+	}
+}
+impl From<UnionParseError> for Diagnostic {
+	fn from(error: UnionParseError) -> Self {
+		match error {
+			UnionParseError::Generics(GenericsParseError::Syntax(error))
+			| UnionParseError::WhereClause(WherePredicateParseError::Syntax(error))
+			| UnionParseError::Field(FieldParseError::Syntax(error)) => error.into(),
+			other => Self::call_site(format!("Failed to parse union: {other:?}")),
+		}
+	}
+}
+
+impl Display for SyntaxError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::UnexpectedEnd => "Unexpected end of input",
+			Self::UnexpectedToken => "Unexpected token",
+			Self::MissingPointerMutability => "Expected `const` or `mut` after `*`",
+			Self::InvalidLifetime => "Expected a lifetime name after `'`",
+			Self::UnclosedGenerics => "Expected `>` to close the generic arguments",
+		})
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{AstComponent, ast::Struct},
+	};
+
+	#[test]
+	fn compile_error() {
+		let error = Diagnostic::call_site("Expected a struct name")
+			.help("add a name")
+			.note("structs need names");
+		assert_eq!(
+			error.to_compile_error().to_string(),
+			quote! {
+				::core::compile_error! {
+					"Expected a struct name\nhelp: add a name\nnote: structs need names"
+				}
+			}
+			.to_string()
+		);
+	}
+
+	#[test]
+	fn parse_errors() {
+		let tokens: TokenStream = "struct Foo { a: *u8 }".parse().unwrap();
+		let error: Diagnostic = Struct::parse(&mut tokens.into_iter().peekable())
+			.unwrap_err()
+			.into();
+		assert_eq!(error.message(), "Expected `const` or `mut` after `*`");
+	}
+}
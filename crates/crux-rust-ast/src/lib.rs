@@ -9,6 +9,9 @@ pub mod external {
 	pub extern crate proc_macro;
 }
 pub mod ast;
+pub mod diagnostic;
+
+pub use diagnostic::Diagnostic;
 
 use std::{fmt::Debug, iter::Peekable};
 
@@ -17,6 +20,9 @@ pub trait TokenIter: Iterator<Item = TokenTree> + Clone {
 	fn next_is_punct(&mut self, punct: char) -> bool;
 	fn next_is_group_with_delimiter(&mut self, delimiter: Delimiter) -> bool;
 	fn peek(&mut self) -> Option<&TokenTree>;
+	/// The span of the next token, or the macro's call site if there are no
+	/// tokens left.
+	fn next_span(&mut self) -> Span;
 }
 impl<T: Iterator<Item = TokenTree> + Clone> TokenIter for Peekable<T> {
 	fn next_is_ident(&mut self, ident: &str) -> bool {
@@ -40,6 +46,9 @@ impl<T: Iterator<Item = TokenTree> + Clone> TokenIter for Peekable<T> {
 	fn peek(&mut self) -> Option<&TokenTree> {
 		Peekable::peek(self)
 	}
+	fn next_span(&mut self) -> Span {
+		Peekable::peek(self).map_or_else(Span::call_site, TokenTree::span)
+	}
 }
 
 pub trait AstComponent: Sized {
@@ -60,6 +69,30 @@ pub trait AstComponent: Sized {
 	fn skip(iter: &mut impl TokenIter);
 }
 
+/// A parse error, along with the span of the tokens that caused it.
+#[derive(Clone, Copy, Debug)]
+pub struct Spanned<E> {
+	pub error: E,
+	pub span: Span,
+}
+impl<E> Spanned<E> {
+	pub fn new(error: E, span: Span) -> Self {
+		Self { error, span }
+	}
+	/// Creates an error at the next token in `iter`.
+	pub fn at_next(error: E, iter: &mut impl TokenIter) -> Self {
+		Self::new(error, iter.next_span())
+	}
+}
+// Spans can't be compared, so errors are equal if they're the same kind of
+// error
+impl<E: PartialEq> PartialEq for Spanned<E> {
+	fn eq(&self, other: &Self) -> bool {
+		self.error == other.error
+	}
+}
+impl<E: Eq> Eq for Spanned<E> {}
+
 #[macro_export]
 macro_rules! parse {
 	($src:expr => $($t:tt)*) => {{
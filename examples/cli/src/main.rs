@@ -42,11 +42,11 @@ impl<'a> CliParser<'a> for Command<'a> {
 
 #[unsafe(no_mangle)]
 fn crux_main() {
-	trace!("Starting up! Args: {:?}", crux::os::proc::cli_args());
+	trace!("Starting up! Args: {:?}", crux::rt::proc::cli_args());
 
 	let mut cli = Command::Help;
 
-	crux::term::cli::parse(crux::os::proc::cli_args(), &mut cli, true);
+	crux::term::cli::parse(crux::rt::proc::cli_args(), &mut cli, true);
 
 	match cli {
 		Command::Help => println!("Uhhh... idk use `greet -n name`"),
@@ -0,0 +1,311 @@
+//! Provides a harness for running functions decorated with `#[bench]` via
+//! `cargo bench`.
+//!
+//! Benchmarks take a [`Bencher`] and pass it the code to measure:
+//!
+//! ```rs
+//! #[bench]
+//! fn sum(bencher: &mut Bencher) {
+//!     let numbers: Vec<u64> = (0..1000).collect();
+//!     bencher.iter(|| numbers.iter().sum::<u64>());
+//! }
+//! ```
+//!
+//! `cargo bench` passes `--bench` to the test binary, which makes the
+//! [test harness] run benchmarks instead of tests. Benchmarks support the same
+//! filtering flags as tests (e.g. `cargo bench sum` or
+//! `cargo bench -- --list`).
+//!
+//!
+//! # Measurements
+//!
+//! [`Bencher::iter`] first runs the benchmarked code repeatedly for
+//! [`WARMUP_TIME`], which warms up caches and estimates how long one iteration
+//! takes. It then collects [`SAMPLES`] samples, each of which times enough
+//! iterations to take roughly [`SAMPLE_TIME`]. The mean, median, and standard
+//! deviation of the time per iteration are computed from those samples.
+//!
+//! The harness prints one line per benchmark, with the benchmark names padded
+//! to the same width and all times in nanoseconds, so reports from different
+//! runs can be compared with a plain `diff`.
+//!
+//! Unlike tests, benchmarks run in the harness' process, so a panicking
+//! benchmark ends the benchmark run.
+//!
+//! [test harness]: crate::rt::test_harness
+
+#[doc(inline)]
+pub use core::hint::black_box;
+
+use crate::rt::time::{Duration, Instant};
+#[cfg(feature = "test-harness")]
+use crate::{
+	rt::test_harness::TestConfig,
+	term::{FG_GREEN, RESET},
+};
+
+crate::rt::event! {
+	/// This event is used by the Crux benchmark harness. All benchmarks that
+	/// should be run should register with this event.
+	///
+	/// For convenience, Crux exposes a [`#[bench]`] attribute macro. Putting
+	/// that on any function will register it with this event.
+	///
+	/// [`#[bench]`]: crux_macros::bench
+	run_benches, Bench
+}
+
+/// How long [`Bencher::iter`] runs the benchmarked code before taking
+/// measurements.
+pub const WARMUP_TIME: Duration = Duration::from_millis(100);
+/// How many samples [`Bencher::iter`] takes.
+pub const SAMPLES: usize = 50;
+/// Roughly how long each sample taken by [`Bencher::iter`] should take.
+pub const SAMPLE_TIME: Duration = Duration::from_millis(10);
+
+/// A benchmark registered with the [`run_benches`] event.
+#[derive(Clone, Copy, Debug)]
+pub struct Bench {
+	/// The path of the module the benchmark was declared in, as returned by
+	/// `module_path!()`.
+	pub module: &'static str,
+	/// The name of the benchmark function.
+	pub name: &'static str,
+	/// The benchmark function.
+	pub func: fn(&mut Bencher),
+}
+impl Bench {
+	/// The path to this benchmark, without the crate name (e.g.
+	/// `data_structures::benches::push`). Filters are matched against this
+	/// path.
+	pub fn path(&self) -> String {
+		crate::rt::test_harness::item_path(self.module, self.name)
+	}
+}
+
+/// Times code for a benchmark. See the [module-level docs] for more info.
+///
+/// [module-level docs]: crate::bench
+#[derive(Default, Debug)]
+pub struct Bencher {
+	stats: Option<BenchStats>,
+}
+impl Bencher {
+	/// Creates a new bencher with no measurements.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Measures how long `func` takes to run. The value returned by `func` is
+	/// passed through [`black_box`], so the compiler can't optimise the
+	/// benchmarked code away.
+	///
+	/// If this is called more than once, only the last measurement is kept.
+	pub fn iter<T>(&mut self, mut func: impl FnMut() -> T) {
+		let warmup_start = Instant::now();
+		let mut warmup_iterations = 0u64;
+		while warmup_start.elapsed() < WARMUP_TIME {
+			black_box(func());
+			warmup_iterations += 1;
+		}
+		let iteration_time = warmup_start.elapsed().as_nanos() as f64 / warmup_iterations as f64;
+		let iterations = ((SAMPLE_TIME.as_nanos() as f64 / iteration_time) as u64).max(1);
+
+		let mut samples = Vec::with_capacity(SAMPLES);
+		for _ in 0..SAMPLES {
+			let start = Instant::now();
+			for _ in 0..iterations {
+				black_box(func());
+			}
+			samples.push(start.elapsed().as_nanos() as f64 / iterations as f64);
+		}
+
+		self.stats = Some(BenchStats::from_samples(&mut samples, iterations));
+	}
+
+	/// The measurements taken by the last call to [`Bencher::iter`], if it
+	/// was called.
+	pub fn stats(&self) -> Option<BenchStats> {
+		self.stats
+	}
+}
+
+/// Statistics about the time one iteration of a benchmark took. All times are
+/// in nanoseconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BenchStats {
+	pub mean: f64,
+	pub median: f64,
+	pub std_dev: f64,
+	/// How many samples the statistics were computed from.
+	pub samples: usize,
+	/// How many iterations each sample timed.
+	pub iterations_per_sample: u64,
+}
+impl BenchStats {
+	/// Computes statistics from the given samples, which should be the time
+	/// per iteration of each sample in nanoseconds. The samples are sorted in
+	/// the process.
+	///
+	/// # Panics
+	///
+	/// Panics if `samples` is empty.
+	pub fn from_samples(samples: &mut [f64], iterations_per_sample: u64) -> Self {
+		assert!(
+			!samples.is_empty(),
+			"Crux: Can't compute benchmark statistics without any samples"
+		);
+
+		samples.sort_unstable_by(f64::total_cmp);
+		let len = samples.len();
+		let median = if len.is_multiple_of(2) {
+			(samples[len / 2 - 1] + samples[len / 2]) / 2.0
+		} else {
+			samples[len / 2]
+		};
+		let mean = samples.iter().sum::<f64>() / len as f64;
+		let variance = samples
+			.iter()
+			.map(|sample| (sample - mean) * (sample - mean))
+			.sum::<f64>()
+			/ len as f64;
+
+		Self {
+			mean,
+			median,
+			std_dev: sqrt(variance),
+			samples: len,
+			iterations_per_sample,
+		}
+	}
+}
+
+/// `core` doesn't provide float square roots, so this approximates one with
+/// Newton's method.
+fn sqrt(val: f64) -> f64 {
+	if val <= 0.0 {
+		return 0.0;
+	}
+
+	let mut guess = val;
+	for _ in 0..64 {
+		let next = (guess + val / guess) / 2.0;
+		if next == guess {
+			break;
+		}
+		guess = next;
+	}
+	guess
+}
+
+/// Returns all benchmarks registered in this Crux binary, in the order they
+/// should be run.
+pub fn all_benches() -> &'static [&'static Bench] {
+	#[cfg(feature = "concurrency")]
+	let benches = unsafe { run_benches::EVENT.solved() };
+	#[cfg(not(feature = "concurrency"))]
+	let benches = unsafe { run_benches::EVENT.solve() }.map(|benches| &*benches.leak());
+
+	benches
+		.expect("Crux CRITICAL ERROR: Failed to solve `run_benches` event, cannot run benchmarks")
+}
+
+/// Runs all benchmarks registered in this Crux binary that match the given
+/// config, printing a report as they run. Returns the path and statistics of
+/// every benchmark that was run.
+#[cfg(feature = "test-harness")]
+pub fn run_benches_with_config(config: &TestConfig) -> Vec<(String, BenchStats)> {
+	let benches = all_benches();
+	let mut to_run = Vec::new();
+	for bench in benches {
+		let path = bench.path();
+		if config.matches(&path) {
+			to_run.push((path, *bench));
+		}
+	}
+	let filtered_out = benches.len() - to_run.len();
+
+	if config.list {
+		for (path, _) in &to_run {
+			println!("{}: bench", path);
+		}
+		if !config.quiet {
+			println!("\n{} benchmarks", to_run.len());
+		}
+		return Vec::new();
+	}
+
+	let (green, reset) = if crate::rt::test_harness::use_color() {
+		(FG_GREEN, RESET)
+	} else {
+		("", "")
+	};
+	let width = to_run.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+
+	let start = Instant::now();
+	let mut results = Vec::with_capacity(to_run.len());
+	println!("\nrunning {} benchmarks", to_run.len());
+	for (path, bench) in to_run {
+		let mut bencher = Bencher::new();
+		(bench.func)(&mut bencher);
+
+		match bencher.stats() {
+			Some(stats) => {
+				println!(
+					"bench {:<1$}  mean {2:>14.1} ns/iter  median {3:>14.1} ns/iter  stddev {4:>12.1} ns  ({5} x {6})",
+					path,
+					width,
+					stats.mean,
+					stats.median,
+					stats.std_dev,
+					stats.samples,
+					stats.iterations_per_sample
+				);
+				results.push((path, stats));
+			}
+			None => println!(
+				"bench {:<1$}  (Bencher::iter was never called)",
+				path, width
+			),
+		}
+	}
+
+	println!(
+		"\nbench result: {}ok{}. {} measured; {} filtered out; finished in {:.2}s\n",
+		green,
+		reset,
+		results.len(),
+		filtered_out,
+		start.elapsed().as_secs_f64()
+	);
+
+	results
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stats() {
+		let mut samples = [4.0, 2.0, 8.0, 6.0];
+		let stats = BenchStats::from_samples(&mut samples, 10);
+		assert_eq!(stats.mean, 5.0);
+		assert_eq!(stats.median, 5.0);
+		assert_eq!(stats.std_dev, sqrt(5.0));
+		assert_eq!(stats.samples, 4);
+		assert_eq!(stats.iterations_per_sample, 10);
+
+		let mut samples = [3.0, 1.0, 2.0];
+		assert_eq!(BenchStats::from_samples(&mut samples, 1).median, 2.0);
+
+		assert_eq!(sqrt(16.0), 4.0);
+		assert_eq!(sqrt(0.0), 0.0);
+	}
+}
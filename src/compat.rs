@@ -0,0 +1,213 @@
+//! Bridges between Crux and Rust's standard library. Only available with the
+//! `std-compat` feature.
+//!
+//! [`StdIo`] lets Crux's [`Writer`]s and [`Reader`]s be used where
+//! `std::io::Write` and `std::io::Read` are required, and [`CruxIo`] does the
+//! opposite. This makes it possible to move existing code to Crux a piece at a
+//! time, instead of all at once:
+//!
+//! ```rs
+//! // A Crux writer passed to code that expects `std::io::Write`
+//! let mut out = StdIo(Vec::new());
+//! std::io::Write::write_all(&mut out, b"hello")?;
+//!
+//! // A `std` file passed to code that expects a Crux `Reader`
+//! let mut file = CruxIo(std::fs::File::open("config.ini")?);
+//! let mut contents = Vec::new();
+//! file.read_to_end(&mut contents)?;
+//! ```
+//!
+//! Errors are converted too. Crux errors become [`std::io::Error`]s that wrap
+//! a [`CruxError`] (or, for [`os::Error`], the same OS error code), and `std`
+//! errors become [`StdIoError`]s.
+
+use {
+	crate::{
+		io::{Reader, Writer},
+		rt::os,
+		text::Display,
+	},
+	core::any::Any,
+	std::io::{self, ErrorKind},
+};
+
+/// Wraps a Crux [`Writer`] or [`Reader`] so it implements `std::io::Write`
+/// or `std::io::Read`. See the [module-level docs](self).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct StdIo<T>(pub T);
+impl<T> StdIo<T> {
+	pub fn into_inner(self) -> T {
+		self.0
+	}
+}
+impl<W: Writer> io::Write for StdIo<W>
+where
+	W::Error: Send + Sync + 'static,
+{
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0
+			.write(buf)
+			.map_err(|err| to_io_error(err, W::is_interrupted))
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.0
+			.flush()
+			.map_err(|err| to_io_error(err, W::is_interrupted))
+	}
+}
+impl<R: Reader> io::Read for StdIo<R>
+where
+	R::Error: Send + Sync + 'static,
+{
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.0
+			.read(buf)
+			.map_err(|err| to_io_error(err, R::is_interrupted))
+	}
+}
+
+/// Converts a Crux error into a `std` error. OS errors keep their error code;
+/// other errors are wrapped in a [`CruxError`].
+fn to_io_error<E: Debug + Send + Sync + 'static>(
+	error: E,
+	is_interrupted: fn(&E) -> bool,
+) -> io::Error {
+	if let Some(error) = (&error as &dyn Any).downcast_ref::<os::Error>() {
+		return (*error).into();
+	}
+
+	let kind = if is_interrupted(&error) {
+		ErrorKind::Interrupted
+	} else {
+		ErrorKind::Other
+	};
+	io::Error::new(kind, CruxError(error))
+}
+
+/// A Crux error stored in a [`std::io::Error`] by [`StdIo`]. The original
+/// error can be retrieved with [`std::io::Error::get_ref`] and
+/// [`downcast_ref`](std::error::Error::downcast_ref), or
+/// [`std::io::Error::downcast`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CruxError<E>(pub E);
+impl<E: Debug> Display for CruxError<E> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		Debug::fmt(&self.0, f)
+	}
+}
+impl<E: Debug> std::error::Error for CruxError<E> {}
+
+impl From<os::Error> for io::Error {
+	fn from(error: os::Error) -> Self {
+		io::Error::from_raw_os_error(error.raw())
+	}
+}
+
+/// Wraps a `std::io::Write` or `std::io::Read` type so it implements Crux's
+/// [`Writer`] or [`Reader`]. See the [module-level docs](self).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct CruxIo<T>(pub T);
+impl<T> CruxIo<T> {
+	pub fn into_inner(self) -> T {
+		self.0
+	}
+}
+impl<W: io::Write> Writer for CruxIo<W> {
+	type Error = StdIoError;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		self.0.write(bytes).map_err(StdIoError)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.0.kind() == ErrorKind::Interrupted
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		self.0.flush().map_err(StdIoError)
+	}
+}
+impl<R: io::Read> Reader for CruxIo<R> {
+	type Error = StdIoError;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		self.0.read(buf).map_err(StdIoError)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.0.kind() == ErrorKind::Interrupted
+	}
+}
+
+/// A [`std::io::Error`] returned by [`CruxIo`].
+///
+/// Crux's errors have to implement [`PartialEq`], which `std::io::Error`
+/// doesn't, so two of these are equal if their errors have the same
+/// [`ErrorKind`].
+#[derive(Debug)]
+pub struct StdIoError(pub io::Error);
+impl PartialEq for StdIoError {
+	fn eq(&self, other: &Self) -> bool {
+		self.0.kind() == other.0.kind()
+	}
+}
+impl Eq for StdIoError {}
+impl Display for StdIoError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		Display::fmt(&self.0, f)
+	}
+}
+impl From<StdIoError> for io::Error {
+	fn from(error: StdIoError) -> Self {
+		error.0
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::io::{SliceFullError, SliceWriter},
+		std::io::{Read, Write},
+	};
+
+	#[test]
+	fn std_traits() {
+		let mut out = StdIo(Vec::new());
+		write!(out, "{} + {}", 1, 2).unwrap();
+		assert_eq!(out.into_inner(), b"1 + 2");
+
+		let mut buf = [0; 4];
+		let mut full = StdIo(SliceWriter::new(&mut buf));
+		let error = full.write_all(b"hello").unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::Other);
+		assert_eq!(
+			error.downcast::<CruxError<SliceFullError>>().unwrap(),
+			CruxError(SliceFullError)
+		);
+
+		#[cfg(unix)]
+		assert_eq!(
+			io::Error::from(os::Error::from_raw(libc::ENOENT)).kind(),
+			ErrorKind::NotFound
+		);
+	}
+
+	#[test]
+	fn crux_traits() {
+		let mut reader = CruxIo(&b"hello"[..]);
+		let mut contents = Vec::new();
+		assert_eq!(reader.read_to_end(&mut contents), Ok(5));
+		assert_eq!(contents, b"hello");
+
+		let mut writer = CruxIo(Vec::new());
+		writer.write_all(b"hello").unwrap();
+		assert_eq!(writer.into_inner(), b"hello");
+
+		let mut bytes = [0; 5];
+		assert!(StdIo(CruxIo(&b"abc"[..])).read_exact(&mut bytes).is_err());
+	}
+}
@@ -1,11 +1,51 @@
 //! Items for working with concurrent code - code that performs multiple
 //! tasks simultaneously.
+//!
+//! Without the `concurrency` crate feature, only the atomic types,
+//! [`AtomicCell`], [`park`], and the [`once`] primitives are available, since
+//! the runtime itself uses them.
 
+#[cfg(feature = "concurrency")]
+pub mod arc;
+pub mod atomic_cell;
+#[cfg(feature = "concurrency")]
+pub mod barrier;
+#[cfg(feature = "concurrency")]
+pub mod condvar;
+#[cfg(feature = "concurrency")]
+pub mod lockfree;
+#[cfg(feature = "concurrency")]
+pub mod mutex;
+pub mod once;
+pub mod park;
+#[cfg(feature = "concurrency")]
+pub mod pool;
+#[cfg(feature = "concurrency")]
+pub mod thread;
+#[cfg(feature = "concurrency")]
+pub mod wait_group;
+
+#[cfg(feature = "concurrency")]
+#[doc(inline)]
+pub use self::{
+	arc::{Arc, Weak},
+	barrier::Barrier,
+	condvar::Condvar,
+	lockfree::{ArrayQueue, TreiberStack},
+	mutex::{Mutex, MutexGuard},
+	park::Parker,
+	pool::ThreadPool,
+	thread::{JoinHandle, Scope, scope, spawn},
+	wait_group::WaitGroup,
+};
 #[doc(inline)]
 pub use {
-	alloc::sync::Arc,
+	self::{
+		atomic_cell::AtomicCell,
+		once::{LazyLock, Once, OnceCell},
+	},
 	core::sync::atomic::{
 		AtomicBool, AtomicI8, AtomicI16, AtomicI32, AtomicI64, AtomicIsize, AtomicU8, AtomicU16,
-		AtomicU32, AtomicU64, AtomicUsize, Ordering as AtomicOrdering,
+		AtomicU32, AtomicU64, AtomicUsize, Ordering as AtomicOrdering, fence,
 	},
 };
@@ -0,0 +1,352 @@
+//! Atomically reference-counted pointers that can be allocated with any
+//! [`Allocator`].
+
+use {
+	crate::{
+		concurrency::{AtomicOrdering, AtomicUsize, fence},
+		lang::{Layout, PhantomData, drop_in_place, read_ptr},
+	},
+	core::{marker::Unsize, ops::CoerceUnsized},
+};
+
+/// The largest the strong or weak count may get. Going past this aborts, so
+/// leaking clones in a loop can't overflow the counts.
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+/// The value of the weak count while [`Arc::get_mut`] is checking if the
+/// [`Arc`] is unique.
+const LOCKED: usize = usize::MAX;
+
+struct ArcInner<T: ?Sized> {
+	strong: AtomicUsize,
+	/// The number of [`Weak`]s, plus one shared by all the [`Arc`]s.
+	weak: AtomicUsize,
+	value: T,
+}
+
+/// A thread-safe, reference-counted pointer. Unlike `alloc::sync::Arc`, it can
+/// be allocated with any [`Allocator`], which lets shared values live in
+/// arenas:
+///
+/// ```rs
+/// let arena = VirtualMemoryArena::new(MemoryAmount::megabytes(1));
+/// let shared = Arc::new_in(Config::default(), &arena);
+/// let other = shared.clone();
+/// ```
+///
+/// The value is dropped when the last [`Arc`] is dropped, and its allocation
+/// is freed once the last [`Weak`] is dropped, too.
+pub struct Arc<T: ?Sized, A: Allocator = GlobalAllocator> {
+	ptr: NonNull<ArcInner<T>>,
+	/// Dropped manually, because `Drop` may have to move it into a [`Weak`].
+	alloc: ManuallyDrop<A>,
+	_owns: PhantomData<ArcInner<T>>,
+}
+
+impl<T> Arc<T, GlobalAllocator> {
+	pub fn new(value: T) -> Self {
+		Self::new_in(value, GlobalAllocator)
+	}
+}
+impl<T, A: Allocator> Arc<T, A> {
+	/// Creates a new [`Arc`] in the given allocator.
+	pub fn new_in(value: T, alloc: A) -> Self {
+		let ptr = alloc
+			.allocate(Layout::new::<ArcInner<T>>())
+			.unwrap()
+			.cast::<ArcInner<T>>();
+		unsafe {
+			ptr.write(ArcInner {
+				strong: AtomicUsize::new(1),
+				weak: AtomicUsize::new(1),
+				value,
+			})
+		};
+
+		Self {
+			ptr,
+			alloc: ManuallyDrop::new(alloc),
+			_owns: PhantomData,
+		}
+	}
+
+	/// Returns the inner value if this is the only [`Arc`] pointing to it.
+	/// Otherwise, returns the [`Arc`] back as an error.
+	pub fn try_unwrap(this: Self) -> Result<T, Self> {
+		if this
+			.inner()
+			.strong
+			.compare_exchange(1, 0, AtomicOrdering::Relaxed, AtomicOrdering::Relaxed)
+			.is_err()
+		{
+			return Err(this);
+		}
+		fence(AtomicOrdering::Acquire);
+
+		let mut this = ManuallyDrop::new(this);
+		let value = unsafe { read_ptr(&this.inner().value) };
+		// Release the weak reference shared by all the strong references
+		drop(Weak {
+			ptr: this.ptr,
+			alloc: unsafe { ManuallyDrop::take(&mut this.alloc) },
+		});
+
+		Ok(value)
+	}
+}
+impl<T: ?Sized, A: Allocator> Arc<T, A> {
+	fn inner(&self) -> &ArcInner<T> {
+		unsafe { self.ptr.as_ref() }
+	}
+
+	/// Creates a [`Weak`] pointer to this [`Arc`]'s value.
+	pub fn downgrade(this: &Self) -> Weak<T, A>
+	where
+		A: Clone,
+	{
+		let weak = &this.inner().weak;
+		let mut count = weak.load(AtomicOrdering::Relaxed);
+		loop {
+			// `get_mut` is checking if the `Arc` is unique
+			if count == LOCKED {
+				core::hint::spin_loop();
+				count = weak.load(AtomicOrdering::Relaxed);
+				continue;
+			}
+			assert!(count < MAX_REFCOUNT, "Crux: Weak count overflowed");
+
+			match weak.compare_exchange_weak(
+				count,
+				count + 1,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Relaxed,
+			) {
+				Ok(_) => {
+					return Weak {
+						ptr: this.ptr,
+						alloc: (*this.alloc).clone(),
+					};
+				}
+				Err(actual) => count = actual,
+			}
+		}
+	}
+
+	/// The number of [`Arc`]s pointing to this value.
+	pub fn strong_count(this: &Self) -> usize {
+		this.inner().strong.load(AtomicOrdering::Relaxed)
+	}
+	/// The number of [`Weak`]s pointing to this value.
+	pub fn weak_count(this: &Self) -> usize {
+		match this.inner().weak.load(AtomicOrdering::Relaxed) {
+			LOCKED => 0,
+			count => count - 1,
+		}
+	}
+	/// Returns true if both [`Arc`]s point to the same allocation.
+	pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+		core::ptr::addr_eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+	}
+	/// The allocator this [`Arc`] was allocated with.
+	pub fn allocator(this: &Self) -> &A {
+		&this.alloc
+	}
+
+	/// Returns a mutable reference to the value, if there are no other [`Arc`]s
+	/// or [`Weak`]s pointing to it.
+	pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+		// Lock the weak count, so no `Weak`s can be created from other `Arc`s
+		// while we check the strong count
+		let inner = this.inner();
+		if inner
+			.weak
+			.compare_exchange(1, LOCKED, AtomicOrdering::Acquire, AtomicOrdering::Relaxed)
+			.is_err()
+		{
+			return None;
+		}
+		let unique = inner.strong.load(AtomicOrdering::Acquire) == 1;
+		inner.weak.store(1, AtomicOrdering::Release);
+
+		if unique {
+			Some(unsafe { &mut (*this.ptr.as_ptr()).value })
+		} else {
+			None
+		}
+	}
+}
+
+impl<T: ?Sized, A: Allocator + Clone> Clone for Arc<T, A> {
+	fn clone(&self) -> Self {
+		let count = self.inner().strong.fetch_add(1, AtomicOrdering::Relaxed);
+		assert!(count < MAX_REFCOUNT, "Crux: Arc count overflowed");
+
+		Self {
+			ptr: self.ptr,
+			alloc: self.alloc.clone(),
+			_owns: PhantomData,
+		}
+	}
+}
+impl<T: ?Sized, A: Allocator> Drop for Arc<T, A> {
+	fn drop(&mut self) {
+		if self.inner().strong.fetch_sub(1, AtomicOrdering::Release) != 1 {
+			unsafe { ManuallyDrop::drop(&mut self.alloc) };
+			return;
+		}
+		// Make sure every other thread is done with the value before dropping
+		// it
+		fence(AtomicOrdering::Acquire);
+
+		unsafe { drop_in_place(&mut (*self.ptr.as_ptr()).value) };
+		// Release the weak reference shared by all the strong references
+		drop(Weak {
+			ptr: self.ptr,
+			alloc: unsafe { ManuallyDrop::take(&mut self.alloc) },
+		});
+	}
+}
+impl<T: ?Sized, A: Allocator> Deref for Arc<T, A> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.inner().value
+	}
+}
+impl<T: Default> Default for Arc<T, GlobalAllocator> {
+	fn default() -> Self {
+		Self::new(T::default())
+	}
+}
+impl<T> From<T> for Arc<T, GlobalAllocator> {
+	fn from(value: T) -> Self {
+		Self::new(value)
+	}
+}
+impl<T: ?Sized + PartialEq, A: Allocator> PartialEq for Arc<T, A> {
+	fn eq(&self, other: &Self) -> bool {
+		**self == **other
+	}
+}
+impl<T: ?Sized + Eq, A: Allocator> Eq for Arc<T, A> {}
+impl<T: ?Sized + Debug, A: Allocator> Debug for Arc<T, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		(**self).fmt(f)
+	}
+}
+impl<T: ?Sized + crate::text::Display, A: Allocator> crate::text::Display for Arc<T, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		(**self).fmt(f)
+	}
+}
+impl<T: ?Sized + Unsize<U>, U: ?Sized, A: Allocator> CoerceUnsized<Arc<U, A>> for Arc<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Allocator + Send + Sync> Send for Arc<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Allocator + Send + Sync> Sync for Arc<T, A> {}
+
+/// A pointer to an [`Arc`]'s value that doesn't keep the value alive. Use
+/// [`Weak::upgrade`] to access the value.
+pub struct Weak<T: ?Sized, A: Allocator = GlobalAllocator> {
+	ptr: NonNull<ArcInner<T>>,
+	alloc: A,
+}
+impl<T: ?Sized, A: Allocator> Weak<T, A> {
+	fn inner(&self) -> &ArcInner<T> {
+		unsafe { self.ptr.as_ref() }
+	}
+
+	/// Returns an [`Arc`] to the value, or `None` if the value was already
+	/// dropped.
+	pub fn upgrade(&self) -> Option<Arc<T, A>>
+	where
+		A: Clone,
+	{
+		let strong = &self.inner().strong;
+		let mut count = strong.load(AtomicOrdering::Relaxed);
+		loop {
+			if count == 0 {
+				return None;
+			}
+			assert!(count < MAX_REFCOUNT, "Crux: Arc count overflowed");
+
+			match strong.compare_exchange_weak(
+				count,
+				count + 1,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Relaxed,
+			) {
+				Ok(_) => {
+					return Some(Arc {
+						ptr: self.ptr,
+						alloc: ManuallyDrop::new(self.alloc.clone()),
+						_owns: PhantomData,
+					});
+				}
+				Err(actual) => count = actual,
+			}
+		}
+	}
+
+	/// The number of [`Arc`]s pointing to this value.
+	pub fn strong_count(&self) -> usize {
+		self.inner().strong.load(AtomicOrdering::Relaxed)
+	}
+}
+impl<T: ?Sized, A: Allocator + Clone> Clone for Weak<T, A> {
+	fn clone(&self) -> Self {
+		let count = self.inner().weak.fetch_add(1, AtomicOrdering::Relaxed);
+		assert!(count < MAX_REFCOUNT, "Crux: Weak count overflowed");
+
+		Self {
+			ptr: self.ptr,
+			alloc: self.alloc.clone(),
+		}
+	}
+}
+impl<T: ?Sized, A: Allocator> Drop for Weak<T, A> {
+	fn drop(&mut self) {
+		if self.inner().weak.fetch_sub(1, AtomicOrdering::Release) != 1 {
+			return;
+		}
+		fence(AtomicOrdering::Acquire);
+
+		let layout = Layout::for_value(self.inner());
+		unsafe { self.alloc.deallocate(self.ptr.cast(), layout) };
+	}
+}
+impl<T: ?Sized, A: Allocator> Debug for Weak<T, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str("(Weak)")
+	}
+}
+impl<T: ?Sized + Unsize<U>, U: ?Sized, A: Allocator> CoerceUnsized<Weak<U, A>> for Weak<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Allocator + Send + Sync> Send for Weak<T, A> {}
+unsafe impl<T: ?Sized + Send + Sync, A: Allocator + Send + Sync> Sync for Weak<T, A> {}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn arc() {
+		let mut arc = Arc::new(1);
+		*Arc::get_mut(&mut arc).unwrap() += 1;
+
+		let weak = Arc::downgrade(&arc);
+		assert!(Arc::get_mut(&mut arc).is_none());
+		let clone = weak.upgrade().unwrap();
+		assert!(Arc::ptr_eq(&arc, &clone));
+		assert_eq!((Arc::strong_count(&arc), Arc::weak_count(&arc)), (2, 1));
+
+		drop(clone);
+		assert_eq!(Arc::try_unwrap(arc), Ok(2));
+		assert!(weak.upgrade().is_none());
+
+		let dyn_arc: Arc<dyn Debug> = Arc::new("coerced");
+		assert_eq!(format!("{dyn_arc:?}"), "\"coerced\"");
+	}
+}
@@ -0,0 +1,298 @@
+//! A [`Cell`]-like type that can be shared between threads.
+//!
+//! [`Cell`]: core::cell::Cell
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU8, AtomicU16, AtomicU32, fence, park},
+	lang::{ManuallyDrop, UnsafeCell, align_of, cfg, size_of, transmute_copy},
+};
+
+/// Runs `$op` with `$atomic` bound to `$cell`'s value as a native atomic
+/// integer, and returns its result from the calling function. Does nothing if
+/// the cell's value can't be stored as a native atomic.
+macro_rules! native {
+	($cell:expr, |$atomic:ident| $op:expr) => {
+		if Self::is_lock_free() {
+			let ptr = $cell.value.get();
+			match size_of::<T>() {
+				1 => {
+					let $atomic = unsafe { &*ptr.cast::<AtomicU8>() };
+					return $op;
+				}
+				2 => {
+					let $atomic = unsafe { &*ptr.cast::<AtomicU16>() };
+					return $op;
+				}
+				4 => {
+					let $atomic = unsafe { &*ptr.cast::<AtomicU32>() };
+					return $op;
+				}
+				#[cfg(target_has_atomic = "64")]
+				8 => {
+					let $atomic = unsafe { &*ptr.cast::<crate::concurrency::AtomicU64>() };
+					return $op;
+				}
+				_ => {}
+			}
+		}
+	};
+}
+
+/// A value that can be read and replaced from any thread, like a
+/// [`Cell`] that's [`Sync`].
+///
+/// If `T` is the same size as a native atomic integer (1, 2, 4, or 8 bytes)
+/// and aligned at least as strictly, it's stored as that atomic, so every
+/// operation is a single atomic instruction. Other values are guarded by a
+/// sequence lock: reads never block each other, and only wait if a write is
+/// in progress. [`AtomicCell::is_lock_free`] says which one `T` uses.
+///
+/// ```rs
+/// static HANDLER: AtomicCell<&'static dyn Fn(Event)> = AtomicCell::new(&|_| {});
+///
+/// HANDLER.store(&|event| println!("{event:?}"));
+/// (HANDLER.load())(Event::Startup);
+/// ```
+///
+/// [`Cell`]: core::cell::Cell
+pub struct AtomicCell<T> {
+	value: UnsafeCell<T>,
+	/// The sequence lock guarding `value`, if it isn't stored as a native
+	/// atomic. See [`SeqLock`].
+	lock: SeqLock,
+}
+unsafe impl<T: Send> Send for AtomicCell<T> {}
+unsafe impl<T: Send> Sync for AtomicCell<T> {}
+impl<T> AtomicCell<T> {
+	pub const fn new(value: T) -> Self {
+		Self {
+			value: UnsafeCell::new(value),
+			lock: SeqLock::new(),
+		}
+	}
+
+	/// If `T` is stored as a native atomic integer. If it isn't, the cell uses
+	/// a lock, and writes block other threads from reading it.
+	pub const fn is_lock_free() -> bool {
+		let size = size_of::<T>();
+		let native = matches!(size, 1 | 2 | 4) || (size == 8 && cfg!(target_has_atomic = "64"));
+		native && align_of::<T>() >= size
+	}
+
+	/// Returns a copy of the cell's value.
+	pub fn load(&self) -> T
+	where
+		T: Copy,
+	{
+		native!(self, |atomic| unsafe {
+			transmute_copy(&atomic.load(AtomicOrdering::Acquire))
+		});
+
+		loop {
+			let seq = self.lock.start_read();
+			// The value may be torn if a write starts while it's being copied,
+			// so it can't be treated as a `T` until the read is validated
+			let value = unsafe { self.value.get().cast::<MaybeUninit<T>>().read_volatile() };
+			if self.lock.validate_read(seq) {
+				return unsafe { value.assume_init() };
+			}
+		}
+	}
+	/// Replaces the cell's value with `value`, dropping the old value.
+	pub fn store(&self, value: T) {
+		drop(self.swap(value));
+	}
+	/// Replaces the cell's value with `value`, and returns the old value.
+	pub fn swap(&self, value: T) -> T {
+		let value = ManuallyDrop::new(value);
+		native!(self, |atomic| unsafe {
+			transmute_copy(&atomic.swap(transmute_copy(&*value), AtomicOrdering::AcqRel))
+		});
+
+		let value = ManuallyDrop::into_inner(value);
+		let seq = self.lock.lock();
+		let old = unsafe { self.value.get().read_volatile() };
+		unsafe { self.value.get().write_volatile(value) };
+		self.lock.unlock(seq);
+
+		old
+	}
+	/// Takes the cell's value, leaving [`Default::default`] in its place.
+	pub fn take(&self) -> T
+	where
+		T: Default,
+	{
+		self.swap(T::default())
+	}
+
+	/// Gets a mutable reference to the cell's value. This doesn't need any
+	/// synchronisation, since the mutable borrow means no other thread can
+	/// access the cell.
+	pub fn get_mut(&mut self) -> &mut T {
+		self.value.get_mut()
+	}
+	/// Consumes the cell, returning its value.
+	pub fn into_inner(self) -> T {
+		self.value.into_inner()
+	}
+}
+impl<T: Default> Default for AtomicCell<T> {
+	fn default() -> Self {
+		Self::new(T::default())
+	}
+}
+impl<T: Copy + Debug> Debug for AtomicCell<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("AtomicCell").field(&self.load()).finish()
+	}
+}
+
+/// A sequence lock: a counter that's bumped before and after every write, so
+/// readers can tell if a write happened while they were reading.
+///
+/// The lowest bit of the counter is set while a write is in progress, and the
+/// next bit is set if a thread is sleeping until it finishes.
+struct SeqLock {
+	seq: AtomicU32,
+}
+impl SeqLock {
+	const WRITING: u32 = 0b01;
+	const WAITING: u32 = 0b10;
+	/// How much the counter goes up by after every write.
+	const STEP: u32 = 0b100;
+	/// How many times to spin before going to sleep while a write is in
+	/// progress. Writes to an `AtomicCell` are just a copy, so they usually
+	/// finish quickly.
+	const SPINS: u32 = 100;
+
+	const fn new() -> Self {
+		Self {
+			seq: AtomicU32::new(0),
+		}
+	}
+
+	/// Waits until no write is in progress, and returns the counter to pass
+	/// to [`SeqLock::validate_read`].
+	fn start_read(&self) -> u32 {
+		let mut spins = 0;
+		loop {
+			let seq = self.seq.load(AtomicOrdering::Acquire);
+			if seq & Self::WRITING == 0 {
+				return seq & !Self::WAITING;
+			}
+			self.wait_for_write(seq, &mut spins);
+		}
+	}
+	/// Returns true if no write started since `seq` was returned by
+	/// [`SeqLock::start_read`], so the value read since then isn't torn.
+	fn validate_read(&self, seq: u32) -> bool {
+		fence(AtomicOrdering::Acquire);
+		self.seq.load(AtomicOrdering::Relaxed) & !Self::WAITING == seq
+	}
+
+	/// Waits for other writes to finish, then starts a write. Returns the
+	/// counter to pass to [`SeqLock::unlock`].
+	fn lock(&self) -> u32 {
+		let mut spins = 0;
+		loop {
+			let seq = self.seq.load(AtomicOrdering::Relaxed);
+			if seq & Self::WRITING != 0 {
+				self.wait_for_write(seq, &mut spins);
+				continue;
+			}
+
+			if self
+				.seq
+				.compare_exchange_weak(
+					seq,
+					seq | Self::WRITING,
+					AtomicOrdering::Acquire,
+					AtomicOrdering::Relaxed,
+				)
+				.is_ok()
+			{
+				// Readers that see anything this write changes must also see
+				// that the write started
+				fence(AtomicOrdering::Release);
+				return seq;
+			}
+		}
+	}
+	/// Finishes a write, and wakes up threads that were waiting for it.
+	fn unlock(&self, seq: u32) {
+		let next = (seq & !Self::WAITING).wrapping_add(Self::STEP);
+		if self.seq.swap(next, AtomicOrdering::Release) & Self::WAITING != 0 {
+			park::wake_all(&self.seq);
+		}
+	}
+
+	/// Spins or sleeps until the write that was in progress when the counter
+	/// was `seq` finishes.
+	fn wait_for_write(&self, seq: u32, spins: &mut u32) {
+		if *spins < Self::SPINS {
+			*spins += 1;
+			core::hint::spin_loop();
+			return;
+		}
+
+		// Tell the writer to wake this thread up when it's done
+		if seq & Self::WAITING == 0
+			&& self
+				.seq
+				.compare_exchange(
+					seq,
+					seq | Self::WAITING,
+					AtomicOrdering::Relaxed,
+					AtomicOrdering::Relaxed,
+				)
+				.is_err()
+		{
+			return;
+		}
+		park::wait(&self.seq, seq | Self::WAITING, None);
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(all(test, feature = "concurrency"))]
+mod tests {
+	use {super::*, crate::concurrency::scope};
+
+	#[test]
+	fn atomic_cell() {
+		let small = AtomicCell::new(5u32);
+		assert!(AtomicCell::<u32>::is_lock_free());
+		assert_eq!(small.swap(7), 5);
+		assert_eq!(small.load(), 7);
+		assert_eq!(small.take(), 7);
+		assert_eq!(small.into_inner(), 0);
+
+		let mut big = AtomicCell::new(String::from("a"));
+		assert!(!AtomicCell::<String>::is_lock_free());
+		assert_eq!(big.swap(String::from("b")), "a");
+		big.get_mut().push('c');
+		assert_eq!(big.into_inner(), "bc");
+
+		// A value that isn't lock-free should never be read torn
+		let pair = AtomicCell::new((0u64, 0u64, 0u64));
+		assert!(!AtomicCell::<(u64, u64, u64)>::is_lock_free());
+		scope(|scope| {
+			for thread in 0..4u64 {
+				let pair = &pair;
+				scope.spawn(move || {
+					for idx in 0..10_000 {
+						let num = thread * 10_000 + idx;
+						pair.store((num, num, num));
+						let (a, b, c) = pair.load();
+						assert!(a == b && b == c);
+					}
+				});
+			}
+		});
+	}
+}
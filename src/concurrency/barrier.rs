@@ -0,0 +1,109 @@
+//! Barriers, which make a group of threads wait for each other.
+
+use crate::concurrency::{AtomicOrdering, AtomicU32, park};
+
+/// Makes a fixed number of threads wait until all of them have reached the
+/// same point, so they can move from one phase of work to the next together.
+///
+/// ```rs
+/// let barrier = Barrier::new(4);
+/// thread::scope(|scope| {
+///     for chunk in chunks {
+///         scope.spawn(|| {
+///             simulate(chunk);
+///             // Every chunk has to be simulated before any are rendered
+///             barrier.wait();
+///             render(chunk);
+///         });
+///     }
+/// });
+/// ```
+///
+/// A barrier can be reused: once every thread has passed it, it waits for the
+/// same number of threads again.
+#[derive(Debug)]
+pub struct Barrier {
+	threads: u32,
+	/// How many threads are waiting at the barrier.
+	arrived: AtomicU32,
+	/// Incremented every time the barrier releases its threads, so waiting
+	/// threads can tell when that happens.
+	generation: AtomicU32,
+}
+impl Barrier {
+	/// Creates a barrier that waits for `threads` threads. A barrier for 0
+	/// threads acts like a barrier for 1 thread, and never blocks.
+	pub const fn new(threads: u32) -> Self {
+		Self {
+			threads: if threads == 0 { 1 } else { threads },
+			arrived: AtomicU32::new(0),
+			generation: AtomicU32::new(0),
+		}
+	}
+
+	/// Sleeps until the barrier's number of threads are waiting at it, then
+	/// wakes them all up.
+	///
+	/// Returns true in exactly one of the threads (the last one to arrive),
+	/// which can be used to pick a thread to do work between phases.
+	pub fn wait(&self) -> bool {
+		let generation = self.generation.load(AtomicOrdering::Acquire);
+		let arrived = self.arrived.fetch_add(1, AtomicOrdering::AcqRel) + 1;
+		if arrived == self.threads {
+			// Reset the count before releasing anyone, so threads that pass
+			// the barrier and immediately wait at it again are counted
+			// towards the next generation
+			self.arrived.store(0, AtomicOrdering::Relaxed);
+			self.generation.fetch_add(1, AtomicOrdering::Release);
+			park::wake_all(&self.generation);
+			return true;
+		}
+
+		while self.generation.load(AtomicOrdering::Acquire) == generation {
+			park::wait(&self.generation, generation, None);
+		}
+		false
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::concurrency::{AtomicUsize, scope},
+	};
+
+	#[test]
+	fn barrier() {
+		let barrier = Barrier::new(4);
+		let arrived = AtomicUsize::new(0);
+		let leaders = AtomicUsize::new(0);
+		scope(|scope| {
+			for _ in 0..4 {
+				scope.spawn(|| {
+					for phase in 1..=10 {
+						arrived.fetch_add(1, AtomicOrdering::Relaxed);
+						if barrier.wait() {
+							leaders.fetch_add(1, AtomicOrdering::Relaxed);
+						}
+						// Every thread has arrived for this phase, and none
+						// can arrive for the next one until this one passes
+						// the barrier again
+						assert_eq!(arrived.load(AtomicOrdering::Relaxed), phase * 4);
+						barrier.wait();
+					}
+				});
+			}
+		});
+
+		assert_eq!(arrived.load(AtomicOrdering::Relaxed), 40);
+		assert_eq!(leaders.load(AtomicOrdering::Relaxed), 10);
+		assert!(Barrier::new(0).wait());
+	}
+}
@@ -0,0 +1,113 @@
+//! Condition variables, which let threads sleep until a condition is met.
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU32, MutexGuard, park},
+	rt::time::Duration,
+};
+
+/// A condition variable: lets threads sleep until another thread tells them
+/// something changed.
+///
+/// Condition variables are used together with a [`Mutex`]: a thread locks the
+/// mutex, checks a condition on its value, and if the condition isn't met,
+/// calls [`Condvar::wait`]. This unlocks the mutex and puts the thread to
+/// sleep until another thread changes the value and calls
+/// [`Condvar::notify_one`] or [`Condvar::notify_all`].
+///
+/// Waiting threads can wake up spuriously, so the condition should always be
+/// checked in a loop - or use [`Condvar::wait_while`], which does that for
+/// you.
+///
+/// [`Mutex`]: crate::concurrency::Mutex
+#[derive(Debug, Default)]
+pub struct Condvar {
+	/// Incremented on every notification, so waiters can tell if they missed
+	/// one between unlocking the mutex and going to sleep.
+	seq: AtomicU32,
+}
+impl Condvar {
+	pub const fn new() -> Self {
+		Self {
+			seq: AtomicU32::new(0),
+		}
+	}
+
+	/// Unlocks `guard`'s mutex and sleeps until this condition variable is
+	/// notified, then locks the mutex again.
+	pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+		self.wait_inner(&guard, None);
+		guard
+	}
+	/// Like [`Condvar::wait`], but gives up after `timeout`. Also returns true
+	/// if the wait timed out.
+	pub fn wait_timeout<'a, T: ?Sized>(
+		&self,
+		guard: MutexGuard<'a, T>,
+		timeout: Duration,
+	) -> (MutexGuard<'a, T>, bool) {
+		let timed_out = !self.wait_inner(&guard, Some(timeout));
+		(guard, timed_out)
+	}
+	/// Waits until `condition` returns false. `condition` is called with the
+	/// mutex locked, before waiting and after every wakeup.
+	pub fn wait_while<'a, T: ?Sized>(
+		&self,
+		mut guard: MutexGuard<'a, T>,
+		mut condition: impl FnMut(&mut T) -> bool,
+	) -> MutexGuard<'a, T> {
+		while condition(&mut *guard) {
+			guard = self.wait(guard);
+		}
+		guard
+	}
+
+	fn wait_inner<T: ?Sized>(&self, guard: &MutexGuard<'_, T>, timeout: Option<Duration>) -> bool {
+		let seq = self.seq.load(AtomicOrdering::Relaxed);
+
+		unsafe { guard.mutex.raw_unlock() };
+		let woken = park::wait(&self.seq, seq, timeout);
+		guard.mutex.raw_lock();
+
+		woken
+	}
+
+	/// Wakes up one thread waiting on this condition variable, if there is
+	/// one.
+	pub fn notify_one(&self) {
+		self.seq.fetch_add(1, AtomicOrdering::Relaxed);
+		park::wake_one(&self.seq);
+	}
+	/// Wakes up every thread waiting on this condition variable.
+	pub fn notify_all(&self) {
+		self.seq.fetch_add(1, AtomicOrdering::Relaxed);
+		park::wake_all(&self.seq);
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::concurrency::Mutex};
+
+	#[test]
+	fn condvar() {
+		let mutex = Mutex::new(false);
+		let condvar = Condvar::new();
+
+		let (guard, timed_out) = condvar.wait_timeout(mutex.lock(), Duration::from_millis(10));
+		assert!(timed_out);
+		assert!(!*guard);
+		drop(guard);
+
+		condvar.notify_one();
+		condvar.notify_all();
+		*mutex.lock() = true;
+		let guard = condvar.wait_while(mutex.lock(), |ready| !*ready);
+		assert!(*guard);
+	}
+}
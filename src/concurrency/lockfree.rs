@@ -0,0 +1,401 @@
+//! Collections that can be shared between threads without locks.
+//!
+//! Both collections here are bounded: they allocate all of their storage up
+//! front, and hand values back when they're full. That keeps them from having
+//! to free memory other threads might still be reading, which is what makes
+//! unbounded lock-free collections so tricky.
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU32, AtomicU64, AtomicUsize},
+	lang::UnsafeCell,
+};
+
+/// A bounded, lock-free, multi-producer multi-consumer queue.
+///
+/// Every slot has a sequence number that says whether it's ready to be
+/// written to or read from for a given position in the queue, so producers
+/// and consumers only have to race on `head` and `tail`. See
+/// <https://www.1024cores.net/home/lock-free-algorithms/queues/bounded-mpmc-queue>.
+///
+/// ```rs
+/// let queue = ArrayQueue::new(64);
+/// scope(|scope| {
+///     scope.spawn(|| queue.push(Job::Render).unwrap());
+///     scope.spawn(|| while let Some(job) = queue.pop() { job.run() });
+/// });
+/// ```
+pub struct ArrayQueue<T> {
+	slots: Box<[Slot<T>]>,
+	/// `slots.len() - 1`. The length is a power of 2, so this turns a
+	/// position into an index.
+	mask: usize,
+	/// The next position to read from.
+	head: AtomicUsize,
+	/// The next position to write to.
+	tail: AtomicUsize,
+}
+struct Slot<T> {
+	sequence: AtomicUsize,
+	value: UnsafeCell<MaybeUninit<T>>,
+}
+impl<T> ArrayQueue<T> {
+	/// Creates a queue that can hold at least `capacity` values. The capacity
+	/// is rounded up to a power of 2, and is always at least 2.
+	pub fn new(capacity: usize) -> Self {
+		let capacity = capacity.max(2).next_power_of_two();
+		Self {
+			slots: (0..capacity)
+				.map(|idx| Slot {
+					sequence: AtomicUsize::new(idx),
+					value: UnsafeCell::new(MaybeUninit::uninit()),
+				})
+				.collect(),
+			mask: capacity - 1,
+			head: AtomicUsize::new(0),
+			tail: AtomicUsize::new(0),
+		}
+	}
+
+	/// Pushes `value` to the back of the queue, or gives it back if the queue
+	/// is full.
+	pub fn push(&self, value: T) -> Result<(), T> {
+		let mut pos = self.tail.load(AtomicOrdering::Relaxed);
+		loop {
+			let slot = &self.slots[pos & self.mask];
+			let sequence = slot.sequence.load(AtomicOrdering::Acquire);
+
+			if sequence == pos {
+				match self.tail.compare_exchange_weak(
+					pos,
+					pos.wrapping_add(1),
+					AtomicOrdering::Relaxed,
+					AtomicOrdering::Relaxed,
+				) {
+					Ok(_) => {
+						unsafe { (*slot.value.get()).write(value) };
+						slot.sequence
+							.store(pos.wrapping_add(1), AtomicOrdering::Release);
+						return Ok(());
+					}
+					Err(current) => pos = current,
+				}
+			} else if (sequence.wrapping_sub(pos) as isize) < 0 {
+				// The slot still holds a value from the last lap
+				return Err(value);
+			} else {
+				pos = self.tail.load(AtomicOrdering::Relaxed);
+			}
+		}
+	}
+	/// Pops the value at the front of the queue, if there is one.
+	pub fn pop(&self) -> Option<T> {
+		let mut pos = self.head.load(AtomicOrdering::Relaxed);
+		loop {
+			let slot = &self.slots[pos & self.mask];
+			let sequence = slot.sequence.load(AtomicOrdering::Acquire);
+			let ready = pos.wrapping_add(1);
+
+			if sequence == ready {
+				match self.head.compare_exchange_weak(
+					pos,
+					ready,
+					AtomicOrdering::Relaxed,
+					AtomicOrdering::Relaxed,
+				) {
+					Ok(_) => {
+						let value = unsafe { (*slot.value.get()).assume_init_read() };
+						slot.sequence
+							.store(pos.wrapping_add(self.slots.len()), AtomicOrdering::Release);
+						return Some(value);
+					}
+					Err(current) => pos = current,
+				}
+			} else if (sequence.wrapping_sub(ready) as isize) < 0 {
+				// Nothing's been written to the slot yet
+				return None;
+			} else {
+				pos = self.head.load(AtomicOrdering::Relaxed);
+			}
+		}
+	}
+
+	/// How many values are in the queue. Other threads may push or pop values
+	/// at any time, so this is only a snapshot.
+	pub fn len(&self) -> usize {
+		let head = self.head.load(AtomicOrdering::Acquire);
+		let tail = self.tail.load(AtomicOrdering::Acquire);
+		// `head` may have moved past the `tail` we read, if values were
+		// pushed and popped in between the loads
+		(tail.wrapping_sub(head) as isize).clamp(0, self.slots.len() as isize) as usize
+	}
+	/// If the queue contains 0 values. Like [`ArrayQueue::len`], this is only
+	/// a snapshot.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+	/// How many values the queue can hold.
+	pub fn capacity(&self) -> usize {
+		self.slots.len()
+	}
+}
+impl<T> Drop for ArrayQueue<T> {
+	fn drop(&mut self) {
+		while self.pop().is_some() {}
+	}
+}
+unsafe impl<T: Send> Send for ArrayQueue<T> {}
+unsafe impl<T: Send> Sync for ArrayQueue<T> {}
+
+/// Marks the end of a list of nodes in a [`TreiberStack`].
+const NIL: u32 = u32::MAX;
+
+/// A bounded, lock-free, last-in first-out stack. See
+/// <https://en.wikipedia.org/wiki/Treiber_stack>.
+///
+/// Values are stored in a fixed array of nodes. Nodes move between two
+/// intrusive linked lists - the stack itself, and a list of free nodes - and
+/// are never freed while the stack is alive, so a thread reading a node
+/// another thread just popped never reads freed memory.
+///
+/// The head of each list is an index into the node array, packed with a tag
+/// that's bumped every time the head changes. This stops a thread from
+/// popping a node that was popped and pushed back while it was looking at the
+/// list (the ABA problem), unless the tag wraps all the way around in the
+/// meantime.
+///
+/// ```rs
+/// let buffers = TreiberStack::new(16);
+/// for _ in 0..16 {
+///     buffers.push(vec![0u8; 4096]).unwrap();
+/// }
+/// let buffer = buffers.pop().unwrap();
+/// ```
+pub struct TreiberStack<T> {
+	nodes: Box<[Node<T>]>,
+	/// The top of the stack.
+	head: AtomicU64,
+	/// Nodes that aren't in the stack.
+	free: AtomicU64,
+}
+struct Node<T> {
+	/// The index of the node after this one in its list.
+	next: AtomicU32,
+	value: UnsafeCell<MaybeUninit<T>>,
+}
+impl<T> TreiberStack<T> {
+	/// Creates a stack that can hold up to `capacity` values.
+	pub fn new(capacity: usize) -> Self {
+		assert!(
+			capacity < NIL as usize,
+			"Crux: A TreiberStack can hold at most {} values",
+			NIL - 1
+		);
+
+		let nodes: Box<[Node<T>]> = (0..capacity as u32)
+			.map(|idx| Node {
+				next: AtomicU32::new(if idx + 1 == capacity as u32 {
+					NIL
+				} else {
+					idx + 1
+				}),
+				value: UnsafeCell::new(MaybeUninit::uninit()),
+			})
+			.collect();
+		let free = if nodes.is_empty() { NIL } else { 0 };
+
+		Self {
+			nodes,
+			head: AtomicU64::new(NIL as u64),
+			free: AtomicU64::new(free as u64),
+		}
+	}
+
+	/// Pushes `value` to the top of the stack, or gives it back if the stack
+	/// is full.
+	pub fn push(&self, value: T) -> Result<(), T> {
+		let Some(idx) = self.take(&self.free) else {
+			return Err(value);
+		};
+		unsafe { (*self.nodes[idx as usize].value.get()).write(value) };
+		self.put(&self.head, idx);
+
+		Ok(())
+	}
+	/// Pops the value at the top of the stack, if there is one.
+	pub fn pop(&self) -> Option<T> {
+		let idx = self.take(&self.head)?;
+		let value = unsafe { (*self.nodes[idx as usize].value.get()).assume_init_read() };
+		self.put(&self.free, idx);
+
+		Some(value)
+	}
+
+	/// If the stack contains 0 values. Other threads may push or pop values at
+	/// any time, so this is only a snapshot.
+	pub fn is_empty(&self) -> bool {
+		self.head.load(AtomicOrdering::Acquire) as u32 == NIL
+	}
+	/// How many values the stack can hold.
+	pub fn capacity(&self) -> usize {
+		self.nodes.len()
+	}
+
+	/// Removes the first node from `list`, and returns its index.
+	fn take(&self, list: &AtomicU64) -> Option<u32> {
+		let mut current = list.load(AtomicOrdering::Acquire);
+		loop {
+			let idx = current as u32;
+			if idx == NIL {
+				return None;
+			}
+			// If another thread takes this node first, this reads a stale
+			// `next`, but the tag will have changed, so the exchange fails
+			let next = self.nodes[idx as usize].next.load(AtomicOrdering::Relaxed);
+
+			match list.compare_exchange_weak(
+				current,
+				Self::retag(current, next),
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Acquire,
+			) {
+				Ok(_) => return Some(idx),
+				Err(actual) => current = actual,
+			}
+		}
+	}
+	/// Adds the node at `idx` to the front of `list`. The current thread must
+	/// own the node.
+	fn put(&self, list: &AtomicU64, idx: u32) {
+		let node = &self.nodes[idx as usize];
+		let mut current = list.load(AtomicOrdering::Relaxed);
+		loop {
+			node.next.store(current as u32, AtomicOrdering::Relaxed);
+
+			match list.compare_exchange_weak(
+				current,
+				Self::retag(current, idx),
+				AtomicOrdering::Release,
+				AtomicOrdering::Relaxed,
+			) {
+				Ok(_) => return,
+				Err(actual) => current = actual,
+			}
+		}
+	}
+	/// Packs `idx` with the tag after the one in `head`.
+	fn retag(head: u64, idx: u32) -> u64 {
+		let tag = (head >> 32) as u32;
+		((tag.wrapping_add(1) as u64) << 32) | idx as u64
+	}
+}
+impl<T> Drop for TreiberStack<T> {
+	fn drop(&mut self) {
+		while self.pop().is_some() {}
+	}
+}
+unsafe impl<T: Send> Send for TreiberStack<T> {}
+unsafe impl<T: Send> Sync for TreiberStack<T> {}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			concurrency::{Mutex, scope},
+			lang::Extend,
+		},
+	};
+
+	#[test]
+	fn array_queue() {
+		let queue = ArrayQueue::new(4);
+		for idx in 0..4 {
+			assert_eq!(queue.push(idx), Ok(()));
+		}
+		assert_eq!(queue.push(4), Err(4));
+		assert_eq!(queue.len(), 4);
+		assert_eq!(queue.pop(), Some(0));
+		assert_eq!(queue.push(4), Ok(()));
+		assert_eq!(
+			(0..5).map(|_| queue.pop()).collect::<Vec<_>>(),
+			[Some(1), Some(2), Some(3), Some(4), None]
+		);
+		assert!(queue.is_empty());
+
+		// Threads are spawned in separate scopes so none of them spin waiting
+		// for the others, which is slow on machines with few cores
+		let queue = ArrayQueue::new(4000);
+		scope(|scope| {
+			for thread in 0..4 {
+				let queue = &queue;
+				scope.spawn(move || {
+					for idx in 0..1000 {
+						queue.push(thread * 1000 + idx).unwrap();
+					}
+				});
+			}
+		});
+		assert_eq!(queue.len(), 4000);
+		let popped = Mutex::new(Vec::new());
+		scope(|scope| {
+			for _ in 0..4 {
+				scope.spawn(|| {
+					let popped_here: Vec<_> = core::iter::from_fn(|| queue.pop()).collect();
+					popped.lock().extend(popped_here);
+				});
+			}
+		});
+
+		let mut popped = popped.into_inner();
+		popped.sort();
+		assert_eq!(popped, (0..4000).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn treiber_stack() {
+		let stack = TreiberStack::new(3);
+		assert_eq!(stack.push(String::from("a")), Ok(()));
+		assert_eq!(stack.push(String::from("b")), Ok(()));
+		assert_eq!(stack.push(String::from("c")), Ok(()));
+		assert_eq!(stack.push(String::from("d")), Err(String::from("d")));
+		assert_eq!(stack.pop().as_deref(), Some("c"));
+		assert_eq!(stack.push(String::from("e")), Ok(()));
+		assert_eq!(stack.pop().as_deref(), Some("e"));
+		assert_eq!(stack.pop().as_deref(), Some("b"));
+		// Dropping the stack drops "a"
+
+		let stack = TreiberStack::new(64);
+		let popped = Mutex::new(Vec::new());
+		scope(|scope| {
+			for thread in 0..4 {
+				let (stack, popped) = (&stack, &popped);
+				scope.spawn(move || {
+					let mut popped_here = Vec::new();
+					for idx in 0..1000 {
+						let mut value = thread * 1000 + idx;
+						while let Err(rejected) = stack.push(value) {
+							value = rejected;
+							popped_here.extend(stack.pop());
+						}
+						if idx % 2 == 0 {
+							popped_here.extend(stack.pop());
+						}
+					}
+					popped.lock().extend(popped_here);
+				});
+			}
+		});
+
+		let mut popped = popped.into_inner();
+		popped.extend(core::iter::from_fn(|| stack.pop()));
+		assert!(stack.is_empty());
+		popped.sort();
+		assert_eq!(popped, (0..4000).collect::<Vec<_>>());
+	}
+}
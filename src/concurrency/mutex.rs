@@ -0,0 +1,163 @@
+//! A lock that gives one thread at a time access to a value.
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU32, park},
+	lang::{DerefMut, UnsafeCell},
+};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and other threads may be waiting for the lock.
+const CONTENDED: u32 = 2;
+
+/// A mutual exclusion lock: a value that only one thread at a time can access.
+///
+/// Threads that try to lock the mutex while it's locked are put to sleep until
+/// it's unlocked, with the primitives in [`park`].
+///
+/// Unlike `std`'s mutex, this mutex isn't poisoned if a thread panics while
+/// holding the lock, since Crux programs abort on panic.
+pub struct Mutex<T: ?Sized> {
+	state: AtomicU32,
+	value: UnsafeCell<T>,
+}
+impl<T> Mutex<T> {
+	pub const fn new(value: T) -> Self {
+		Self {
+			state: AtomicU32::new(UNLOCKED),
+			value: UnsafeCell::new(value),
+		}
+	}
+
+	/// Consumes the mutex and returns its value.
+	pub fn into_inner(self) -> T {
+		self.value.into_inner()
+	}
+}
+impl<T: ?Sized> Mutex<T> {
+	/// Locks the mutex, waiting for other threads to unlock it first if
+	/// needed. The mutex is unlocked when the returned guard is dropped.
+	///
+	/// Locking a mutex that the current thread already holds deadlocks.
+	pub fn lock(&self) -> MutexGuard<'_, T> {
+		self.raw_lock();
+		MutexGuard { mutex: self }
+	}
+	/// Locks the mutex if it isn't already locked.
+	pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+		self.state
+			.compare_exchange(
+				UNLOCKED,
+				LOCKED,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Relaxed,
+			)
+			.ok()
+			.map(|_| MutexGuard { mutex: self })
+	}
+
+	/// Returns the mutex's value. This doesn't need to lock the mutex, since
+	/// the mutable reference guarantees no other thread can access it.
+	pub fn get_mut(&mut self) -> &mut T {
+		self.value.get_mut()
+	}
+
+	pub(crate) fn raw_lock(&self) {
+		if self
+			.state
+			.compare_exchange(
+				UNLOCKED,
+				LOCKED,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Relaxed,
+			)
+			.is_err()
+		{
+			self.lock_contended();
+		}
+	}
+	#[cold]
+	fn lock_contended(&self) {
+		// Mark the mutex as contended, so whoever unlocks it wakes us up
+		while self.state.swap(CONTENDED, AtomicOrdering::Acquire) != UNLOCKED {
+			park::wait(&self.state, CONTENDED, None);
+		}
+	}
+
+	/// # Safety
+	///
+	/// The current thread must hold the lock, and not use its guard until it
+	/// locks the mutex again.
+	pub(crate) unsafe fn raw_unlock(&self) {
+		if self.state.swap(UNLOCKED, AtomicOrdering::Release) == CONTENDED {
+			park::wake_one(&self.state);
+		}
+	}
+}
+impl<T: Default> Default for Mutex<T> {
+	fn default() -> Self {
+		Self::new(T::default())
+	}
+}
+impl<T: ?Sized + Debug> Debug for Mutex<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self.try_lock() {
+			Some(guard) => f.debug_tuple("Mutex").field(&&*guard).finish(),
+			None => f.write_str("Mutex(<locked>)"),
+		}
+	}
+}
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+/// Gives access to a locked [`Mutex`]'s value, and unlocks the mutex when it's
+/// dropped.
+pub struct MutexGuard<'a, T: ?Sized> {
+	pub(crate) mutex: &'a Mutex<T>,
+}
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		unsafe { &*self.mutex.value.get() }
+	}
+}
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		unsafe { &mut *self.mutex.value.get() }
+	}
+}
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+	fn drop(&mut self) {
+		unsafe { self.mutex.raw_unlock() };
+	}
+}
+impl<T: ?Sized + Debug> Debug for MutexGuard<'_, T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		(**self).fmt(f)
+	}
+}
+unsafe impl<T: ?Sized + Sync> Sync for MutexGuard<'_, T> {}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mutex() {
+		let mutex = Mutex::new(1);
+		{
+			let mut guard = mutex.lock();
+			*guard += 1;
+			assert!(mutex.try_lock().is_none());
+		}
+		assert_eq!(*mutex.try_lock().unwrap(), 2);
+		assert_eq!(mutex.into_inner(), 2);
+	}
+}
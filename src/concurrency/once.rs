@@ -0,0 +1,245 @@
+//! Primitives for initializing values exactly once, even when multiple threads
+//! try to initialize them at the same time.
+//!
+//! - [`Once`] runs a function exactly once.
+//! - [`OnceCell`] stores a value that's set exactly once.
+//! - [`LazyLock`] stores a value that's computed the first time it's accessed.
+//!
+//! Threads that try to initialize a value while another thread is already
+//! initializing it wait for that thread to finish.
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU8},
+	lang::{Cell, Deref, MaybeUninit, UnsafeCell},
+};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// Runs a function exactly once. See [`Once::call_once`].
+#[derive(Debug)]
+pub struct Once {
+	state: AtomicU8,
+}
+impl Once {
+	pub const fn new() -> Self {
+		Self {
+			state: AtomicU8::new(INCOMPLETE),
+		}
+	}
+
+	/// Calls `func` if no function has been called on this [`Once`] yet. If
+	/// another thread is currently running its function, waits for it to
+	/// finish instead. When this returns, a function has finished running on
+	/// this [`Once`].
+	///
+	/// If `func` panics (with `panic = "unwind"`), the [`Once`] is reset, and
+	/// the next call to this method will try again.
+	pub fn call_once(&self, func: impl FnOnce()) {
+		if self.is_completed() {
+			return;
+		}
+
+		loop {
+			match self.state.compare_exchange_weak(
+				INCOMPLETE,
+				RUNNING,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Acquire,
+			) {
+				Ok(_) => {
+					// Resets the state if `func` unwinds
+					let guard = ResetOnDrop(&self.state);
+					func();
+					crate::lang::forget(guard);
+
+					self.state.store(COMPLETE, AtomicOrdering::Release);
+					return;
+				}
+				Err(COMPLETE) => return,
+				Err(_) => core::hint::spin_loop(),
+			}
+		}
+	}
+
+	/// Returns true if a function has finished running on this [`Once`].
+	pub fn is_completed(&self) -> bool {
+		self.state.load(AtomicOrdering::Acquire) == COMPLETE
+	}
+}
+impl Default for Once {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+struct ResetOnDrop<'a>(&'a AtomicU8);
+impl Drop for ResetOnDrop<'_> {
+	fn drop(&mut self) {
+		self.0.store(INCOMPLETE, AtomicOrdering::Release);
+	}
+}
+
+/// A cell that can be written to exactly once, and can be shared between
+/// threads.
+pub struct OnceCell<T> {
+	once: Once,
+	value: UnsafeCell<MaybeUninit<T>>,
+}
+impl<T> OnceCell<T> {
+	/// Creates an empty cell.
+	pub const fn new() -> Self {
+		Self {
+			once: Once::new(),
+			value: UnsafeCell::new(MaybeUninit::uninit()),
+		}
+	}
+
+	/// Returns the cell's value, or `None` if it hasn't been set yet.
+	pub fn get(&self) -> Option<&T> {
+		if self.once.is_completed() {
+			Some(unsafe { (*self.value.get()).assume_init_ref() })
+		} else {
+			None
+		}
+	}
+	/// Returns the cell's value mutably, or `None` if it hasn't been set yet.
+	pub fn get_mut(&mut self) -> Option<&mut T> {
+		if self.once.is_completed() {
+			Some(unsafe { self.value.get_mut().assume_init_mut() })
+		} else {
+			None
+		}
+	}
+
+	/// Sets the cell's value, if it hasn't been set yet. Otherwise, returns
+	/// `value` back as an error.
+	pub fn set(&self, value: T) -> Result<(), T> {
+		let mut value = Some(value);
+		self.get_or_init(|| value.take().unwrap());
+
+		match value {
+			Some(value) => Err(value),
+			None => Ok(()),
+		}
+	}
+
+	/// Returns the cell's value, initializing it with `init` if it hasn't been
+	/// set yet. If multiple threads call this at once, only one of them runs
+	/// its `init` function; the others wait for it to finish.
+	pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+		self.once.call_once(|| unsafe {
+			(*self.value.get()).write(init());
+		});
+		unsafe { (*self.value.get()).assume_init_ref() }
+	}
+
+	/// Returns the cell's value, if it was set.
+	pub fn into_inner(mut self) -> Option<T> {
+		let value = if self.once.is_completed() {
+			Some(unsafe { self.value.get_mut().assume_init_read() })
+		} else {
+			None
+		};
+		crate::lang::forget(self);
+		value
+	}
+}
+impl<T> Default for OnceCell<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<T> Drop for OnceCell<T> {
+	fn drop(&mut self) {
+		if self.once.is_completed() {
+			unsafe { self.value.get_mut().assume_init_drop() };
+		}
+	}
+}
+impl<T: Debug> Debug for OnceCell<T> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("OnceCell").field(&self.get()).finish()
+	}
+}
+unsafe impl<T: Send + Sync> Sync for OnceCell<T> {}
+unsafe impl<T: Send> Send for OnceCell<T> {}
+
+/// A value that's computed the first time it's accessed, and can be shared
+/// between threads.
+///
+/// ```rs
+/// static CONFIG: LazyLock<Config> = LazyLock::new(|| Config::load());
+///
+/// fn timeout() -> Duration {
+///     CONFIG.timeout
+/// }
+/// ```
+pub struct LazyLock<T, F = fn() -> T> {
+	cell: OnceCell<T>,
+	init: Cell<Option<F>>,
+}
+impl<T, F: FnOnce() -> T> LazyLock<T, F> {
+	/// Creates a new [`LazyLock`] that computes its value with `init`.
+	pub const fn new(init: F) -> Self {
+		Self {
+			cell: OnceCell::new(),
+			init: Cell::new(Some(init)),
+		}
+	}
+
+	/// Computes the value, if it hasn't been computed yet, and returns it.
+	pub fn force(this: &Self) -> &T {
+		this.cell.get_or_init(|| match this.init.take() {
+			Some(init) => init(),
+			None => panic!("Crux: LazyLock's initializer panicked previously"),
+		})
+	}
+}
+impl<T, F: FnOnce() -> T> Deref for LazyLock<T, F> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		Self::force(self)
+	}
+}
+impl<T: Debug, F> Debug for LazyLock<T, F> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("LazyLock").field(&self.cell.get()).finish()
+	}
+}
+// `init` is only accessed by the thread that wins the race in `OnceCell`
+unsafe impl<T: Send + Sync, F: Send> Sync for LazyLock<T, F> {}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn once() {
+		let once = Once::new();
+		let mut calls = 0;
+		once.call_once(|| calls += 1);
+		once.call_once(|| calls += 1);
+		assert_eq!(calls, 1);
+		assert!(once.is_completed());
+
+		let cell = OnceCell::new();
+		assert_eq!(cell.get(), None);
+		assert_eq!(cell.set(1), Ok(()));
+		assert_eq!(cell.set(2), Err(2));
+		assert_eq!(cell.get_or_init(|| 3), &1);
+		assert_eq!(cell.into_inner(), Some(1));
+
+		static LAZY: LazyLock<Vec<u8>> = LazyLock::new(|| Vec::from([1, 2, 3]));
+		assert_eq!(LAZY.len(), 3);
+		assert_eq!(*LAZY, [1, 2, 3]);
+	}
+}
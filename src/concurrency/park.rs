@@ -0,0 +1,260 @@
+//! Low-level primitives for putting threads to sleep until another thread
+//! wakes them up.
+//!
+//! [`wait`], [`wake_one`], and [`wake_all`] work like a futex: a thread waits
+//! on the address of an [`AtomicU32`], and is woken up when another thread
+//! calls one of the wake functions on the same address. They're implemented
+//! with `futex` on Linux, `__ulock_wait` on macOS, and `WaitOnAddress` on
+//! Windows. Other platforms fall back to spinning.
+//!
+//! [`Parker`] builds on these to let one thread sleep until it's unparked.
+//! Higher-level types like [`Mutex`] and [`Condvar`] are built on these, too.
+//!
+//! [`Mutex`]: crate::concurrency::Mutex
+//! [`Condvar`]: crate::concurrency::Condvar
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU32},
+	rt::{os, time::Duration},
+};
+
+/// Puts the current thread to sleep if `atomic` is equal to `expected`, until
+/// another thread calls [`wake_one`] or [`wake_all`] on `atomic`, or until
+/// `timeout` passes.
+///
+/// Checking the value and going to sleep happen atomically, so a wake that
+/// happens after `atomic` changes can't be missed. However, this may also
+/// return spuriously, so callers should always check the value again.
+///
+/// Returns false if the wait timed out, and true otherwise.
+pub fn wait(atomic: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+	#[cfg(linux)]
+	{
+		let timeout = timeout.map(|timeout| os::unix::TimeSpec {
+			tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+			tv_nsec: timeout.subsec_nanos() as _,
+		});
+		let timeout = match &timeout {
+			Some(timeout) => timeout as *const os::unix::TimeSpec,
+			None => crate::lang::null_ptr(),
+		};
+
+		let res = unsafe {
+			os::unix::syscall(
+				libc::SYS_futex,
+				atomic.as_ptr(),
+				libc::FUTEX_WAIT | libc::FUTEX_PRIVATE_FLAG,
+				expected,
+				timeout,
+			)
+		};
+		res == 0 || os::Error::last().kind() != os::ErrorKind::TimedOut
+	}
+	#[cfg(macos)]
+	{
+		const UL_COMPARE_AND_WAIT: u32 = 1;
+		const ULF_NO_ERRNO: u32 = 0x01000000;
+
+		// Zero means no timeout, so round tiny timeouts up
+		let timeout_us = match timeout {
+			Some(timeout) => timeout.as_micros().clamp(1, u32::MAX as u128) as u32,
+			None => 0,
+		};
+
+		let res = unsafe {
+			os::unix::__ulock_wait(
+				UL_COMPARE_AND_WAIT | ULF_NO_ERRNO,
+				NonNull::from(atomic).cast(),
+				expected as u64,
+				timeout_us,
+			)
+		};
+		res != -libc::ETIMEDOUT
+	}
+	#[cfg(windows)]
+	{
+		const INFINITE: u32 = u32::MAX;
+
+		let timeout_ms = match timeout {
+			Some(timeout) => timeout.as_millis().min((INFINITE - 1) as u128) as u32,
+			None => INFINITE,
+		};
+
+		let res = unsafe {
+			os::win32::WaitOnAddress(
+				NonNull::from(atomic).cast(),
+				NonNull::from(&expected).cast(),
+				crate::lang::size_of::<u32>(),
+				timeout_ms,
+			)
+		};
+		res != 0 || os::Error::last().kind() != os::ErrorKind::TimedOut
+	}
+	#[cfg(not(any(linux, macos, windows)))]
+	{
+		let _ = (atomic, expected, timeout);
+		core::hint::spin_loop();
+		true
+	}
+}
+
+/// Wakes up one thread that's waiting on `atomic` with [`wait`], if there is
+/// one.
+pub fn wake_one(atomic: &AtomicU32) {
+	#[cfg(linux)]
+	unsafe {
+		os::unix::syscall(
+			libc::SYS_futex,
+			atomic.as_ptr(),
+			libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+			1,
+		);
+	}
+	#[cfg(macos)]
+	unsafe {
+		const UL_COMPARE_AND_WAIT: u32 = 1;
+		const ULF_NO_ERRNO: u32 = 0x01000000;
+
+		os::unix::__ulock_wake(
+			UL_COMPARE_AND_WAIT | ULF_NO_ERRNO,
+			NonNull::from(atomic).cast(),
+			0,
+		);
+	}
+	#[cfg(windows)]
+	unsafe {
+		os::win32::WakeByAddressSingle(NonNull::from(atomic).cast());
+	}
+	#[cfg(not(any(linux, macos, windows)))]
+	let _ = atomic;
+}
+
+/// Wakes up every thread that's waiting on `atomic` with [`wait`].
+pub fn wake_all(atomic: &AtomicU32) {
+	#[cfg(linux)]
+	unsafe {
+		os::unix::syscall(
+			libc::SYS_futex,
+			atomic.as_ptr(),
+			libc::FUTEX_WAKE | libc::FUTEX_PRIVATE_FLAG,
+			i32::MAX,
+		);
+	}
+	#[cfg(macos)]
+	unsafe {
+		const UL_COMPARE_AND_WAIT: u32 = 1;
+		const ULF_WAKE_ALL: u32 = 0x100;
+		const ULF_NO_ERRNO: u32 = 0x01000000;
+
+		os::unix::__ulock_wake(
+			UL_COMPARE_AND_WAIT | ULF_WAKE_ALL | ULF_NO_ERRNO,
+			NonNull::from(atomic).cast(),
+			0,
+		);
+	}
+	#[cfg(windows)]
+	unsafe {
+		os::win32::WakeByAddressAll(NonNull::from(atomic).cast());
+	}
+	#[cfg(not(any(linux, macos, windows)))]
+	let _ = atomic;
+}
+
+const EMPTY: u32 = 0;
+const NOTIFIED: u32 = 1;
+const PARKED: u32 = u32::MAX;
+
+/// Lets one thread sleep until another thread unparks it.
+///
+/// Each [`Parker`] holds a token. [`Parker::unpark`] makes the token
+/// available, and [`Parker::park`] waits until the token is available, then
+/// consumes it. This means an unpark that happens before a park isn't lost;
+/// the park just returns immediately.
+///
+/// A [`Parker`] should only be parked by one thread at a time, but can be
+/// unparked from any thread.
+#[derive(Debug, Default)]
+pub struct Parker {
+	state: AtomicU32,
+}
+impl Parker {
+	pub const fn new() -> Self {
+		Self {
+			state: AtomicU32::new(EMPTY),
+		}
+	}
+
+	/// Blocks the current thread until this [`Parker`] is unparked. Returns
+	/// immediately if it was already unparked.
+	pub fn park(&self) {
+		self.park_inner(None);
+	}
+	/// Like [`Parker::park`], but gives up after `timeout`. Returns true if the
+	/// [`Parker`] was unparked, and false otherwise. Like [`wait`], this may
+	/// return early.
+	pub fn park_timeout(&self, timeout: Duration) -> bool {
+		self.park_inner(Some(timeout))
+	}
+
+	fn park_inner(&self, timeout: Option<Duration>) -> bool {
+		// NOTIFIED -> EMPTY, or EMPTY -> PARKED
+		if self.state.fetch_sub(1, AtomicOrdering::Acquire) == NOTIFIED {
+			return true;
+		}
+
+		loop {
+			wait(&self.state, PARKED, timeout);
+
+			// If we were unparked, consume the token
+			if self
+				.state
+				.compare_exchange(
+					NOTIFIED,
+					EMPTY,
+					AtomicOrdering::Acquire,
+					AtomicOrdering::Acquire,
+				)
+				.is_ok()
+			{
+				return true;
+			}
+			if timeout.is_some() {
+				// We're giving up, so we're no longer parked. We may have been
+				// unparked in the meantime, though.
+				return self.state.swap(EMPTY, AtomicOrdering::Acquire) == NOTIFIED;
+			}
+		}
+	}
+
+	/// Makes this [`Parker`]'s token available, waking up the thread parked on
+	/// it, if there is one.
+	pub fn unpark(&self) {
+		if self.state.swap(NOTIFIED, AtomicOrdering::Release) == PARKED {
+			wake_one(&self.state);
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parker() {
+		let parker = Parker::new();
+		parker.unpark();
+		parker.park();
+		assert!(!parker.park_timeout(Duration::from_millis(10)));
+
+		let atomic = AtomicU32::new(0);
+		assert!(wait(&atomic, 1, None));
+		wake_one(&atomic);
+		wake_all(&atomic);
+	}
+}
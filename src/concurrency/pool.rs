@@ -0,0 +1,236 @@
+//! A fixed-size pool of worker threads, and helpers for processing slices in
+//! parallel on it.
+
+use {
+	crate::{
+		concurrency::{
+			Arc, Condvar, Mutex, WaitGroup,
+			thread::{self, JoinHandle},
+		},
+		lang::PhantomData,
+	},
+	alloc::collections::VecDeque,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A pool of worker threads that run jobs in the background.
+///
+/// ```rs
+/// let pool = ThreadPool::new(thread::available_parallelism());
+/// let mut pixels = vec![0u32; 1920 * 1080];
+/// pool.par_chunks_mut(&mut pixels, 1920, |row| render_row(row));
+/// ```
+///
+/// Dropping the pool waits for every queued job to finish, then stops the
+/// workers.
+pub struct ThreadPool {
+	shared: Arc<Shared>,
+	workers: Vec<JoinHandle<'static, ()>>,
+}
+impl ThreadPool {
+	/// Spawns a pool with `threads` worker threads. Panics if `threads` is 0.
+	pub fn new(threads: usize) -> Self {
+		assert!(threads > 0, "Crux: A thread pool needs at least 1 thread");
+
+		let shared = Arc::new(Shared {
+			state: Mutex::new(State {
+				jobs: VecDeque::new(),
+				running: 0,
+				shutdown: false,
+			}),
+			job_queued: Condvar::new(),
+			job_finished: Condvar::new(),
+		});
+		let workers = (0..threads)
+			.map(|_| {
+				let shared = shared.clone();
+				thread::spawn(move || shared.work())
+			})
+			.collect();
+
+		Self { shared, workers }
+	}
+
+	/// The number of worker threads in the pool.
+	pub fn threads(&self) -> usize {
+		self.workers.len()
+	}
+
+	/// Queues `job` to run on one of the pool's threads.
+	pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+		self.shared.push(Box::new(job));
+	}
+
+	/// Waits until every queued job has finished running.
+	pub fn wait(&self) {
+		let state = self.shared.state.lock();
+		drop(
+			self.shared
+				.job_finished
+				.wait_while(state, |state| !state.jobs.is_empty() || state.running > 0),
+		);
+	}
+
+	/// Runs `func` with a [`Scope`] that can queue jobs borrowing data from the
+	/// current stack frame. Waits for every job queued on the scope to finish
+	/// before returning.
+	pub fn scope<'env, R>(&self, func: impl FnOnce(&Scope<'_, 'env>) -> R) -> R {
+		let scope = Scope {
+			pool: self,
+			pending: Arc::new(WaitGroup::new()),
+			_env: PhantomData,
+		};
+		let result = func(&scope);
+
+		scope.pending.wait();
+
+		result
+	}
+
+	/// Calls `func` on chunks of `slice` in parallel. Each chunk has
+	/// `chunk_size` elements, except for the last one, which may be shorter.
+	/// Returns once every chunk has been processed.
+	pub fn par_chunks<T: Sync>(&self, slice: &[T], chunk_size: usize, func: impl Fn(&[T]) + Sync) {
+		let func = &func;
+		self.scope(|scope| {
+			for chunk in slice.chunks(chunk_size) {
+				scope.execute(move || func(chunk));
+			}
+		});
+	}
+	/// Like [`ThreadPool::par_chunks`], but with mutable chunks.
+	pub fn par_chunks_mut<T: Send>(
+		&self,
+		slice: &mut [T],
+		chunk_size: usize,
+		func: impl Fn(&mut [T]) + Sync,
+	) {
+		let func = &func;
+		self.scope(|scope| {
+			for chunk in slice.chunks_mut(chunk_size) {
+				scope.execute(move || func(chunk));
+			}
+		});
+	}
+	/// Calls `func` on every element of `slice` in parallel. The slice is split
+	/// into one chunk per thread.
+	pub fn par_for_each<T: Send>(&self, slice: &mut [T], func: impl Fn(&mut T) + Sync) {
+		let chunk_size = slice.len().div_ceil(self.threads()).max(1);
+		self.par_chunks_mut(slice, chunk_size, |chunk| chunk.iter_mut().for_each(&func));
+	}
+}
+impl Drop for ThreadPool {
+	fn drop(&mut self) {
+		self.shared.state.lock().shutdown = true;
+		self.shared.job_queued.notify_all();
+
+		for worker in self.workers.drain(..) {
+			worker.join();
+		}
+	}
+}
+
+/// Lets jobs borrow data that outlives `'env`. See [`ThreadPool::scope`].
+pub struct Scope<'pool, 'env> {
+	pool: &'pool ThreadPool,
+	/// The scope's jobs that haven't finished yet.
+	pending: Arc<WaitGroup>,
+	_env: PhantomData<&'env mut &'env ()>,
+}
+impl<'env> Scope<'_, 'env> {
+	/// Queues `job` to run on one of the pool's threads.
+	pub fn execute(&self, job: impl FnOnce() + Send + 'env) {
+		self.pending.add(1);
+
+		let pending = self.pending.clone();
+		let job: Box<dyn FnOnce() + Send + 'env> = Box::new(move || {
+			job();
+			pending.done();
+		});
+		// Safety: `ThreadPool::scope` doesn't return until every job on the
+		// scope finishes, so nothing the job borrows can be dropped while it's
+		// running
+		let job: Job = unsafe { transmute(job) };
+		self.pool.shared.push(job);
+	}
+}
+
+struct Shared {
+	state: Mutex<State>,
+	/// Notified when a job is queued, or when the pool shuts down.
+	job_queued: Condvar,
+	/// Notified when a worker finishes a job.
+	job_finished: Condvar,
+}
+struct State {
+	jobs: VecDeque<Job>,
+	/// How many jobs are currently running.
+	running: usize,
+	shutdown: bool,
+}
+impl Shared {
+	fn push(&self, job: Job) {
+		self.state.lock().jobs.push_back(job);
+		self.job_queued.notify_one();
+	}
+
+	/// The main loop of a worker thread.
+	fn work(&self) {
+		let mut state = self.state.lock();
+		loop {
+			if let Some(job) = state.jobs.pop_front() {
+				state.running += 1;
+				drop(state);
+
+				job();
+
+				state = self.state.lock();
+				state.running -= 1;
+				self.job_finished.notify_all();
+			} else if state.shutdown {
+				return;
+			} else {
+				state = self.job_queued.wait(state);
+			}
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::concurrency::{AtomicOrdering, AtomicUsize},
+	};
+
+	#[test]
+	fn pool() {
+		let pool = ThreadPool::new(4);
+		let counter = Arc::new(AtomicUsize::new(0));
+		for _ in 0..100 {
+			let counter = counter.clone();
+			pool.execute(move || {
+				counter.fetch_add(1, AtomicOrdering::Relaxed);
+			});
+		}
+		pool.wait();
+		assert_eq!(counter.load(AtomicOrdering::Relaxed), 100);
+
+		let mut numbers: Vec<usize> = (0..1000).collect();
+		pool.par_for_each(&mut numbers, |num| *num *= 2);
+		assert!(numbers.iter().enumerate().all(|(idx, num)| *num == idx * 2));
+
+		let sum = AtomicUsize::new(0);
+		pool.par_chunks(&numbers, 64, |chunk| {
+			sum.fetch_add(chunk.iter().sum(), AtomicOrdering::Relaxed);
+		});
+		assert_eq!(sum.load(AtomicOrdering::Relaxed), 999 * 1000);
+	}
+}
@@ -0,0 +1,304 @@
+//! Spawning and joining OS threads.
+
+use crate::{
+	concurrency::{Arc, WaitGroup},
+	ffi::c_void,
+	lang::{PhantomData, UnsafeCell},
+	rt::os,
+};
+
+/// Spawns a new OS thread that runs `func`, and returns a handle that can be
+/// used to wait for it to finish and get its result.
+///
+/// Panics if the OS fails to create the thread.
+pub fn spawn<F, T>(func: F) -> JoinHandle<'static, T>
+where
+	F: FnOnce() -> T + Send + 'static,
+	T: Send + 'static,
+{
+	unsafe { spawn_unchecked(func) }
+}
+
+/// Like [`spawn`], but `func` and its result only have to live for `'a`.
+///
+///
+/// # Safety
+///
+/// The caller must make sure the thread finishes before `'a` ends, e.g. by
+/// joining it. Dropping the [`JoinHandle`] detaches the thread instead of
+/// joining it.
+pub unsafe fn spawn_unchecked<'a, F, T>(func: F) -> JoinHandle<'a, T>
+where
+	F: FnOnce() -> T + Send + 'a,
+	T: Send + 'a,
+{
+	unsafe { spawn_inner(func, None) }
+}
+
+/// Spawns a thread for [`spawn_unchecked`] or [`Scope::spawn`]. If `scope` is
+/// set, it's marked as done once the thread's result is stored or dropped.
+///
+///
+/// # Safety
+///
+/// See [`spawn_unchecked`].
+unsafe fn spawn_inner<'a, F, T>(func: F, scope: Option<Arc<WaitGroup>>) -> JoinHandle<'a, T>
+where
+	F: FnOnce() -> T + Send + 'a,
+	T: Send + 'a,
+{
+	let packet = Arc::new(Packet {
+		result: UnsafeCell::new(None),
+		scope,
+	});
+	let main: Box<dyn FnOnce() + Send + 'a> = Box::new({
+		let packet = packet.clone();
+		move || {
+			let result = func();
+			unsafe { *packet.result.get() = Some(result) };
+		}
+	});
+	// Double-box the closure, so it can be passed as a thin pointer
+	let main = Box::into_raw(Box::new(main));
+	let arg = unsafe { NonNull::new_unchecked(main).cast::<c_void>() };
+
+	#[cfg(unix)]
+	let native = {
+		extern "C" fn start(arg: NonNull<c_void>) -> Option<NonNull<c_void>> {
+			let main = unsafe { Box::from_raw(arg.cast::<Box<dyn FnOnce() + Send>>().as_ptr()) };
+			main();
+			None
+		}
+
+		let mut native = MaybeUninit::uninit();
+		let res = unsafe {
+			os::unix::pthread_create(
+				NonNull::new_unchecked(native.as_mut_ptr()),
+				crate::lang::null_ptr(),
+				start,
+				arg,
+			)
+		};
+		if res != 0 {
+			drop(unsafe { Box::from_raw(main) });
+			panic!("Crux: Failed to spawn a thread");
+		}
+		unsafe { native.assume_init() }
+	};
+	#[cfg(windows)]
+	let native = {
+		extern "system" fn start(arg: NonNull<c_void>) -> u32 {
+			let main = unsafe { Box::from_raw(arg.cast::<Box<dyn FnOnce() + Send>>().as_ptr()) };
+			main();
+			0
+		}
+
+		match unsafe { os::win32::CreateThread(None, 0, start, arg, 0, None) } {
+			Some(handle) => handle,
+			None => {
+				drop(unsafe { Box::from_raw(main) });
+				panic!("Crux: Failed to spawn a thread");
+			}
+		}
+	};
+
+	JoinHandle {
+		native: Some(native),
+		packet,
+		_lifetime: PhantomData,
+	}
+}
+
+/// Where a thread stores its result, so [`JoinHandle::join`] can return it.
+struct Packet<T> {
+	result: UnsafeCell<Option<T>>,
+	/// The scope the thread was spawned on, if any.
+	scope: Option<Arc<WaitGroup>>,
+}
+impl<T> Drop for Packet<T> {
+	fn drop(&mut self) {
+		// The result may borrow from the scope, so it has to be dropped before
+		// the scope is told this thread is done
+		*self.result.get_mut() = None;
+		if let Some(scope) = &self.scope {
+			scope.done();
+		}
+	}
+}
+// The result is only written by the spawned thread, then read after it's
+// joined
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+#[cfg(unix)]
+type NativeThread = libc::pthread_t;
+#[cfg(windows)]
+type NativeThread = NonNull<c_void>;
+
+/// A handle to a thread spawned with [`spawn`]. Dropping the handle detaches
+/// the thread, so it keeps running in the background.
+pub struct JoinHandle<'a, T> {
+	native: Option<NativeThread>,
+	packet: Arc<Packet<T>>,
+	_lifetime: PhantomData<&'a ()>,
+}
+impl<T> JoinHandle<'_, T> {
+	/// Waits for the thread to finish, then returns its result.
+	pub fn join(mut self) -> T {
+		let native = self.native.take().unwrap();
+
+		#[cfg(unix)]
+		{
+			let res = unsafe { os::unix::pthread_join(native, None) };
+			assert_eq!(res, 0, "Crux: Failed to join a thread");
+		}
+		#[cfg(windows)]
+		unsafe {
+			const INFINITE: u32 = u32::MAX;
+
+			os::win32::WaitForSingleObject(native, INFINITE);
+			os::win32::CloseHandle(native);
+		}
+
+		unsafe { (*self.packet.result.get()).take() }
+			.expect("Crux: A thread exited without producing a result")
+	}
+}
+impl<T> Drop for JoinHandle<'_, T> {
+	fn drop(&mut self) {
+		if let Some(native) = self.native.take() {
+			#[cfg(unix)]
+			unsafe {
+				os::unix::pthread_detach(native);
+			}
+			#[cfg(windows)]
+			unsafe {
+				os::win32::CloseHandle(native);
+			}
+		}
+	}
+}
+unsafe impl<T: Send> Send for JoinHandle<'_, T> {}
+unsafe impl<T: Send> Sync for JoinHandle<'_, T> {}
+
+/// Runs `func` with a [`Scope`] that can spawn threads borrowing data from the
+/// current stack frame. Every thread spawned on the scope is joined before this
+/// returns, so borrowing is safe.
+///
+/// ```rs
+/// let mut numbers = [1, 2, 3, 4];
+/// let (left, right) = numbers.split_at_mut(2);
+/// thread::scope(|scope| {
+///     scope.spawn(|| left.iter_mut().for_each(|num| *num *= 2));
+///     scope.spawn(|| right.iter_mut().for_each(|num| *num *= 3));
+/// });
+/// assert_eq!(numbers, [2, 4, 9, 12]);
+/// ```
+pub fn scope<'env, F, T>(func: F) -> T
+where
+	F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
+{
+	let scope = Scope {
+		running: Arc::new(WaitGroup::new()),
+		_scope: PhantomData,
+		_env: PhantomData,
+	};
+	let result = func(&scope);
+
+	scope.running.wait();
+
+	result
+}
+
+/// Spawns threads that can borrow data outliving `'env`. See [`scope`].
+pub struct Scope<'scope, 'env: 'scope> {
+	/// The threads spawned on the scope that haven't finished yet.
+	running: Arc<WaitGroup>,
+	_scope: PhantomData<&'scope mut &'scope ()>,
+	_env: PhantomData<&'env mut &'env ()>,
+}
+impl<'scope> Scope<'scope, '_> {
+	/// Spawns a thread that runs `func`. Unlike [`spawn`], `func` can borrow
+	/// data from outside the scope.
+	///
+	/// The thread is joined when the scope ends, if it wasn't already joined
+	/// with the returned handle.
+	pub fn spawn<F, T>(&'scope self, func: F) -> JoinHandle<'scope, T>
+	where
+		F: FnOnce() -> T + Send + 'scope,
+		T: Send + 'scope,
+	{
+		self.running.add(1);
+
+		// Safety: `scope` doesn't return until this thread marks itself as
+		// done in `running`, which its packet does once `func` and its result
+		// are gone
+		unsafe { spawn_inner(func, Some(self.running.clone())) }
+	}
+}
+
+/// Returns the number of threads that can run in parallel on this machine,
+/// which is a good default for the size of a thread pool.
+pub fn available_parallelism() -> usize {
+	os::sys::logical_cpus()
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::concurrency::{AtomicOrdering, AtomicUsize},
+	};
+
+	#[test]
+	fn spawn_and_join() {
+		let handles: Vec<_> = (0..4).map(|idx| spawn(move || idx * 2)).collect();
+		let results: Vec<_> = handles.into_iter().map(JoinHandle::join).collect();
+		assert_eq!(results, [0, 2, 4, 6]);
+		assert!(available_parallelism() >= 1);
+	}
+
+	#[test]
+	fn scoped() {
+		let mut numbers = [1, 2, 3, 4];
+		let (left, right) = numbers.split_at_mut(2);
+		let sum = scope(|scope| {
+			let left = scope.spawn(|| {
+				left.iter_mut().for_each(|num| *num *= 2);
+				left.iter().sum::<i32>()
+			});
+			scope.spawn(|| right.iter_mut().for_each(|num| *num *= 3));
+			left.join()
+		});
+		assert_eq!(numbers, [2, 4, 9, 12]);
+		assert_eq!(sum, 6);
+	}
+
+	#[test]
+	fn scoped_results_dropped_in_scope() {
+		struct Borrowed<'a>(&'a AtomicUsize);
+		impl Drop for Borrowed<'_> {
+			fn drop(&mut self) {
+				// Give `scope` a chance to return early if it doesn't wait
+				for _ in 0..1_000_000 {
+					core::hint::spin_loop();
+				}
+				self.0.fetch_add(1, AtomicOrdering::Relaxed);
+			}
+		}
+
+		let dropped = AtomicUsize::new(0);
+		scope(|scope| {
+			for _ in 0..4 {
+				// Detach the thread, so its result is dropped by the thread
+				drop(scope.spawn(|| Borrowed(&dropped)));
+			}
+		});
+		assert_eq!(dropped.load(AtomicOrdering::Relaxed), 4);
+	}
+}
@@ -0,0 +1,114 @@
+//! Wait groups, which let a thread wait for a group of tasks to finish.
+
+use crate::concurrency::{AtomicOrdering, AtomicU32, park};
+
+/// Counts tasks that haven't finished yet, and lets threads sleep until they
+/// all have.
+///
+/// Each task is added to the group with [`WaitGroup::add`] before it starts,
+/// and marked as finished with [`WaitGroup::done`]. [`WaitGroup::wait`] sleeps
+/// until every task that was added is done:
+///
+/// ```rs
+/// let group = Arc::new(WaitGroup::new());
+/// for file in files {
+///     group.add(1);
+///     let group = group.clone();
+///     pool.execute(move || {
+///         compress(file);
+///         group.done();
+///     });
+/// }
+/// group.wait();
+/// ```
+///
+/// Once the count reaches 0, the group can be reused for another batch of
+/// tasks.
+#[derive(Debug, Default)]
+pub struct WaitGroup {
+	/// How many tasks haven't finished yet.
+	count: AtomicU32,
+}
+impl WaitGroup {
+	pub const fn new() -> Self {
+		Self {
+			count: AtomicU32::new(0),
+		}
+	}
+
+	/// Adds `tasks` tasks to the group.
+	pub fn add(&self, tasks: u32) {
+		let prev = self.count.fetch_add(tasks, AtomicOrdering::Relaxed);
+		assert!(
+			prev.checked_add(tasks).is_some(),
+			"Crux: Added too many tasks to a WaitGroup"
+		);
+	}
+	/// Marks one of the group's tasks as finished. Panics if every task that
+	/// was added is already done.
+	pub fn done(&self) {
+		let prev = self.count.fetch_sub(1, AtomicOrdering::Release);
+		assert!(
+			prev != 0,
+			"Crux: WaitGroup::done was called more times than tasks were added"
+		);
+		if prev == 1 {
+			park::wake_all(&self.count);
+		}
+	}
+
+	/// Sleeps until every task in the group is done. Returns immediately if
+	/// the group is empty.
+	pub fn wait(&self) {
+		loop {
+			let count = self.count.load(AtomicOrdering::Acquire);
+			if count == 0 {
+				return;
+			}
+			park::wait(&self.count, count, None);
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::concurrency::{Arc, AtomicUsize, spawn},
+	};
+
+	#[test]
+	fn wait_group() {
+		let group = Arc::new(WaitGroup::new());
+		group.wait();
+
+		let finished = Arc::new(AtomicUsize::new(0));
+		for _ in 0..8 {
+			group.add(1);
+			let (group, finished) = (group.clone(), finished.clone());
+			drop(spawn(move || {
+				finished.fetch_add(1, AtomicOrdering::Relaxed);
+				group.done();
+			}));
+		}
+		group.wait();
+		assert_eq!(finished.load(AtomicOrdering::Relaxed), 8);
+
+		// The group can be reused once it's empty
+		group.add(2);
+		group.done();
+		group.done();
+		group.wait();
+	}
+
+	#[test(should_panic)]
+	fn wait_group_underflow() {
+		WaitGroup::new().done();
+	}
+}
@@ -1,6 +1,6 @@
 //! Items dealing with cryptography.
 
-pub use hash::*;
+pub use {hash::*, random::*};
 
 pub mod hash {
 	//! Hashing traits and implementations.
@@ -12,10 +12,344 @@ pub mod hash {
 	};
 	pub type FoldHashBuilder = DefaultHashBuilder;
 	pub type FoldHasher = <DefaultHashBuilder as BuildHasher>::Hasher;
+	/// Builds [`FnvHasher`]s.
+	pub type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+	/// Builds [`IdentityHasher`]s.
+	pub type IdentityBuildHasher = BuildHasherDefault<IdentityHasher>;
+
+	//
+	// RandomState
+	//
+
+	/// Builds [`SipHasher`]s with random keys, which makes hash maps resistant
+	/// to HashDoS attacks, where an attacker picks keys that all collide.
+	///
+	/// Like the standard library's `RandomState`, the keys are read from the
+	/// OS once per thread with [`fill_random`]. Every `RandomState` created
+	/// after that gets slightly different keys, so iteration orders differ
+	/// between maps.
+	///
+	/// [`fill_random`]: crate::crypto::random::fill_random
+	#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+	pub struct RandomState {
+		k0: u64,
+		k1: u64,
+	}
+	impl RandomState {
+		/// Creates a `RandomState` with new random keys.
+		///
+		/// This panics if the OS fails to provide random bytes the first time
+		/// it's called on a thread.
+		pub fn new() -> Self {
+			#[thread_local]
+			static mut KEYS: Option<(u64, u64)> = None;
+
+			let keys = unsafe { &mut *crate::lang::addr_of_mut!(KEYS) };
+			let (k0, k1) = keys.get_or_insert_with(|| {
+				let mut bytes = [0; 16];
+				super::random::fill_random(&mut bytes)
+					.expect("Failed to read random bytes for a RandomState");
+				let (k0, k1) = bytes.split_at(8);
+				(
+					u64::from_ne_bytes(k0.try_into().unwrap()),
+					u64::from_ne_bytes(k1.try_into().unwrap()),
+				)
+			});
+			let this = Self { k0: *k0, k1: *k1 };
+			*k0 = k0.wrapping_add(1);
+
+			this
+		}
+	}
+	impl Default for RandomState {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+	#[allow(deprecated)]
+	impl BuildHasher for RandomState {
+		type Hasher = SipHasher;
+
+		fn build_hasher(&self) -> SipHasher {
+			SipHasher::new_with_keys(self.k0, self.k1)
+		}
+	}
+
+	//
+	// FnvHasher
+	//
+
+	const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+	const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+	/// Hashes `bytes` with 64-bit FNV-1a. This gives the same result as
+	/// writing `bytes` to an [`FnvHasher`], but can be used in const contexts.
+	pub const fn fnv1a(bytes: &[u8]) -> u64 {
+		let mut hash = FNV_OFFSET_BASIS;
+		let mut idx = 0;
+		while idx < bytes.len() {
+			hash ^= bytes[idx] as u64;
+			hash = hash.wrapping_mul(FNV_PRIME);
+			idx += 1;
+		}
+
+		hash
+	}
+
+	/// The 64-bit FNV-1a hash function. It's very fast for short keys and is
+	/// deterministic, so hashes are the same across runs and machines, but it
+	/// isn't resistant to HashDoS attacks; see [`RandomState`] for that.
+	#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+	pub struct FnvHasher(u64);
+	impl FnvHasher {
+		pub const fn new() -> Self {
+			Self(FNV_OFFSET_BASIS)
+		}
+	}
+	impl Default for FnvHasher {
+		fn default() -> Self {
+			Self::new()
+		}
+	}
+	impl Hasher for FnvHasher {
+		fn write(&mut self, bytes: &[u8]) {
+			for byte in bytes {
+				self.0 ^= *byte as u64;
+				self.0 = self.0.wrapping_mul(FNV_PRIME);
+			}
+		}
+		fn finish(&self) -> u64 {
+			self.0
+		}
+	}
+
+	//
+	// IdentityHasher
+	//
+
+	/// A hasher that uses an integer key as its own hash. This is the fastest
+	/// possible hasher for keys that are already well-distributed integers,
+	/// like random IDs.
+	///
+	/// Only integer keys are supported; hashing anything that writes raw bytes
+	/// (like a string) panics.
+	#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+	pub struct IdentityHasher(u64);
+	impl Hasher for IdentityHasher {
+		fn write(&mut self, _bytes: &[u8]) {
+			panic!("IdentityHasher can only hash integers");
+		}
+		fn write_u8(&mut self, i: u8) {
+			self.0 = i as u64;
+		}
+		fn write_u16(&mut self, i: u16) {
+			self.0 = i as u64;
+		}
+		fn write_u32(&mut self, i: u32) {
+			self.0 = i as u64;
+		}
+		fn write_u64(&mut self, i: u64) {
+			self.0 = i;
+		}
+		fn write_usize(&mut self, i: usize) {
+			self.0 = i as u64;
+		}
+		fn write_i8(&mut self, i: i8) {
+			self.0 = i as u64;
+		}
+		fn write_i16(&mut self, i: i16) {
+			self.0 = i as u64;
+		}
+		fn write_i32(&mut self, i: i32) {
+			self.0 = i as u64;
+		}
+		fn write_i64(&mut self, i: i64) {
+			self.0 = i as u64;
+		}
+		fn write_isize(&mut self, i: isize) {
+			self.0 = i as u64;
+		}
+		fn finish(&self) -> u64 {
+			self.0
+		}
+	}
+
+	//
+	// SHA-1
+	//
+
+	/// Hashes `bytes` with SHA-1.
+	///
+	/// SHA-1 is broken, so it shouldn't be used for anything that needs to be
+	/// secure. It's only here for protocols that still require it, like the
+	/// WebSocket handshake.
+	pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+		let mut state = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+		let (blocks, rest) = bytes.as_chunks::<64>();
+		for block in blocks {
+			sha1_block(&mut state, block);
+		}
+
+		// The message is padded with a 1 bit, then zeroes, then its length in
+		// bits, which can take one or two more blocks
+		let mut tail = [0; 128];
+		tail[..rest.len()].copy_from_slice(rest);
+		tail[rest.len()] = 0x80;
+		let tail_len = if rest.len() < 56 { 64 } else { 128 };
+		let bit_len = (bytes.len() as u64).wrapping_mul(8);
+		tail[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_be_bytes());
+		for block in tail[..tail_len].as_chunks::<64>().0 {
+			sha1_block(&mut state, block);
+		}
+
+		let mut hash = [0; 20];
+		for (out, word) in hash.as_chunks_mut::<4>().0.iter_mut().zip(state) {
+			*out = word.to_be_bytes();
+		}
+		hash
+	}
+	fn sha1_block(state: &mut [u32; 5], block: &[u8; 64]) {
+		let mut schedule = [0; 80];
+		for (word, bytes) in schedule.iter_mut().zip(block.as_chunks::<4>().0) {
+			*word = u32::from_be_bytes(*bytes);
+		}
+		for idx in 16..80 {
+			schedule[idx] = (schedule[idx - 3]
+				^ schedule[idx - 8]
+				^ schedule[idx - 14]
+				^ schedule[idx - 16])
+				.rotate_left(1);
+		}
+
+		let [mut a, mut b, mut c, mut d, mut e] = *state;
+		for (idx, word) in schedule.into_iter().enumerate() {
+			let (f, k) = match idx {
+				0..20 => ((b & c) | (!b & d), 0x5a82_7999),
+				20..40 => (b ^ c ^ d, 0x6ed9_eba1),
+				40..60 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+				_ => (b ^ c ^ d, 0xca62_c1d6),
+			};
+			let temp = a
+				.rotate_left(5)
+				.wrapping_add(f)
+				.wrapping_add(e)
+				.wrapping_add(k)
+				.wrapping_add(word);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = temp;
+		}
+		for (word, new) in state.iter_mut().zip([a, b, c, d, e]) {
+			*word = word.wrapping_add(new);
+		}
+	}
+}
+
+pub mod random {
+	//! Randomness from the operating system.
+
+	use crate::rt::os;
+
+	/// Fills `buf` with cryptographically secure random bytes from the OS.
+	///
+	/// This is slow compared to a userspace RNG, but it's suitable for things
+	/// that need to be unpredictable, like keys or temporary file names.
+	pub fn fill_random(buf: &mut [u8]) -> Result<(), os::Error> {
+		#[cfg(unix)]
+		{
+			// `getentropy` can only fill 256 bytes at a time
+			for chunk in buf.chunks_mut(256) {
+				let len = chunk.len();
+				let res = unsafe { os::unix::getentropy(NonNull::from(chunk).cast(), len) };
+				if res != 0 {
+					return Err(os::Error::last());
+				}
+			}
+
+			Ok(())
+		}
+		#[cfg(windows)]
+		{
+			// Always succeeds
+			let len = buf.len();
+			unsafe { os::win32::ProcessPrng(NonNull::from(buf).cast(), len) };
+			Ok(())
+		}
+		#[cfg(not(supported_os))]
+		compile_error!("unimplemented on this operating system");
+	}
 }
 
 pub use sha2_const;
 
 // TODO:
-// - RNG
+// - Seedable, non-cryptographic RNGs
 // - More hash functions
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::data_structures::{FnvHashMap, HashMapExt, IdentityHashSet},
+	};
+
+	#[test]
+	fn hashers() {
+		const EMPTY: u64 = fnv1a(b"");
+		assert_eq!(EMPTY, 0xcbf2_9ce4_8422_2325);
+		assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+		let mut hasher = FnvHasher::new();
+		hasher.write(b"a");
+		assert_eq!(hasher.finish(), fnv1a(b"a"));
+
+		let mut hasher = IdentityHasher::default();
+		42u32.hash(&mut hasher);
+		assert_eq!(hasher.finish(), 42);
+
+		// Each RandomState gets different keys
+		let (first, second) = (RandomState::new(), RandomState::new());
+		assert_ne!(first.hash_one(1), second.hash_one(1));
+		assert_eq!(first.hash_one(1), first.hash_one(1));
+
+		let mut map = FnvHashMap::with_default_hasher();
+		map.insert("crux", 1);
+		assert_eq!(map.get("crux"), Some(&1));
+		let mut set = IdentityHashSet::with_capacity_and_default_hasher(4);
+		set.insert(7u64);
+		assert!(set.contains(&7));
+	}
+
+	#[test]
+	fn sha1() {
+		let hex = |hash: [u8; 20]| {
+			let mut out = String::new();
+			for byte in hash {
+				out.push_str(&format!("{byte:02x}"));
+			}
+			out
+		};
+		assert_eq!(
+			hex(super::sha1(b"")),
+			"da39a3ee5e6b4b0d3255bfef95601890afd80709"
+		);
+		assert_eq!(
+			hex(super::sha1(b"abc")),
+			"a9993e364706816aba3e25717850c26c9cd0d89d"
+		);
+		// Long enough that the padding needs an extra block
+		assert_eq!(
+			hex(super::sha1(
+				b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+			)),
+			"84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+		);
+	}
+}
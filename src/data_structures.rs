@@ -1,15 +1,22 @@
 //! Structures for storing and organizing data.
 
+pub mod gen_vec;
+pub mod indexed_heap;
+pub mod range_map;
 pub mod sized_vec;
+pub mod typed_vec;
 
 pub use self::{
 	arena::{ArenaString, ArenaVec},
 	binary_heap::BinaryHeap,
 	btree_map::BTreeMap,
 	btree_set::BTreeSet,
+	gen_vec::GenVec,
 	hash_map::HashMap,
 	hash_set::HashSet,
 	hash_table::HashTable,
+	indexed_heap::IndexedHeap,
+	range_map::RangeMap,
 	sized_vec::SizedVec,
 	typed_vec::{TypedVec, typed_vec_idx},
 	vec::Vec,
@@ -24,7 +31,13 @@ pub use {
 	hashbrown::{hash_map, hash_set, hash_table},
 };
 
-use crate::lang::UnsignedInteger;
+use crate::{
+	crypto::hash::{
+		BuildHasher, DefaultHashBuilder, FnvBuildHasher, IdentityBuildHasher, RandomState,
+	},
+	lang::{Borrow, Bound, DoubleEndedIterator, UnsignedInteger},
+	rt::mem::VirtualMemoryArena,
+};
 
 //
 // IndexSize
@@ -55,6 +68,35 @@ macro_rules! impl_nums {
 }
 impl_nums!(u8 u16 u32 u64 u128 usize);
 
+//
+// Fallible allocation
+//
+
+/// Moves `value` into a new [`Box`], or returns an error if allocating fails.
+/// [`Box::new`] calls the alloc error handler instead, which exits the
+/// program.
+///
+/// Most of Crux's allocating types have a `try_` variant of each method that
+/// allocates, like [`SizedVec::try_with_capacity`] and
+/// [`ArenaString::try_push_str`], which all return [`AllocError`]s. Programs
+/// that must not exit when memory runs out can use them to handle allocation
+/// failures themselves:
+///
+/// ```rs
+/// fn load(bytes: &[u8]) -> Result<Box<Document>, AllocError> {
+///     let mut nodes = SizedVec::<Node, u32>::try_with_capacity(64)?;
+///     nodes.try_push(Node::root())?;
+///     try_box_new(Document { nodes })
+/// }
+/// ```
+pub fn try_box_new<T>(value: T) -> Result<Box<T>, AllocError> {
+	Box::try_new(value)
+}
+/// Like [`try_box_new`], but allocates the box with `allocator`.
+pub fn try_box_new_in<T, A: Allocator>(value: T, allocator: A) -> Result<Box<T, A>, AllocError> {
+	Box::try_new_in(value, allocator)
+}
+
 //
 // Arena types
 //
@@ -63,33 +105,71 @@ pub mod arena {
 	//! Variants of standard allocated data structures that are backed by arena
 	//! allocators.
 
-	use crate::{
-		data_structures::IndexSize,
-		lang::UnsafeCell,
-		rt::mem::{ArenaPreallocationError, MemoryAmount, VirtualMemoryArena},
+	use {
+		crate::{
+			data_structures::IndexSize,
+			lang::{
+				Cell, Index, IndexMut, Layout, PhantomData, Range, RangeFrom, RangeFull,
+				RangeInclusive, RangeTo, RangeToInclusive, drop_in_place, size_of,
+				slice_from_raw_parts_mut,
+			},
+			rt::mem::{ArenaPreallocationError, MemoryAmount, VirtualMemoryArena},
+			text::{FormatArgs, TextWrite, write_fmt},
+		},
+		core::slice::SliceIndex,
 	};
 
 	/// A vector backed by an arena allocator.
 	///
 	/// Because arenas never move in memory, this vector can be pushed to
 	/// immutably; pushing will not move anything in memory and therefore
-	/// doesn't need exclusive ownership.
+	/// doesn't need exclusive ownership. The vector owns its arena, so its
+	/// items are stored one after the other, and are never moved or
+	/// reallocated while the vector is alive.
+	///
+	/// This means that references to items stay valid while more items are
+	/// pushed:
+	///
+	/// ```rs
+	/// let names = ArenaVec::new(MemoryAmount::mebibytes(1))?;
+	/// let first = names.push("crux");
+	/// let all = names.as_slice();
+	/// names.push("rust");
+	///
+	/// assert_eq!(*first, "crux");
+	/// // Slices only see items that were pushed before they were created
+	/// assert_eq!(all, ["crux"]);
+	/// assert_eq!(names.as_slice(), ["crux", "rust"]);
+	/// ```
+	///
+	/// Removing items requires a mutable reference, so no references to those
+	/// items can exist when they're removed.
 	///
 	/// Compared to using an arena by itself, [`ArenaVec`] has two advantages:
 	/// 1. It only allows storing one type, which may be nice for some
 	///    scenarios.
 	/// 2. It calls `drop` on objects in the vec when the vec is dropped. The
 	///    standalone arena allocator does not do this.
-	pub struct ArenaVec<T, S: const IndexSize = usize>(
-		UnsafeCell<SizedVec<T, S, VirtualMemoryArena>>,
-	);
+	pub struct ArenaVec<T, S: const IndexSize = usize> {
+		arena: VirtualMemoryArena,
+		/// The first item in the vector. Dangling until the first item is
+		/// pushed.
+		///
+		/// Only this vector allocates in `arena`, and a type's size is always
+		/// a multiple of its alignment, so each item is allocated right after
+		/// the previous one.
+		base: Cell<NonNull<T>>,
+		/// Only ever grows while the vector is shared. Slices of the vector
+		/// store their length when they're created, so they never overlap
+		/// with items pushed after them.
+		len: Cell<S>,
+		_items: PhantomData<T>,
+	}
 	impl<T, S: const IndexSize> ArenaVec<T, S> {
 		/// Reserve virtual memory for a new arena-backed vector. Errors if
 		/// reserving virtual memory fails.
 		pub fn new(to_reserve: MemoryAmount) -> Result<Self, ()> {
-			Ok(Self(UnsafeCell::new(SizedVec::with_allocator(
-				VirtualMemoryArena::new(to_reserve)?,
-			))))
+			Ok(Self::from(VirtualMemoryArena::new(to_reserve)?))
 		}
 		/// Reserve virtual memory for a new arena-backed vector, then
 		/// preallocate some of that memory so it can be used right away.
@@ -97,36 +177,307 @@ pub mod arena {
 			to_reserve: MemoryAmount,
 			to_commit: MemoryAmount,
 		) -> Result<Self, ArenaPreallocationError> {
-			Ok(Self(UnsafeCell::new(SizedVec::with_allocator(
-				VirtualMemoryArena::new_preallocate(to_reserve, to_commit)?,
-			))))
+			Ok(Self::from(VirtualMemoryArena::new_preallocate(
+				to_reserve, to_commit,
+			)?))
 		}
 
-		/// Add an item to the end of this arena-backed vector. Because arenas
-		/// never move in memory, this can be accomplished with an immutable
-		/// reference.
-		pub fn push(&self, val: T) {
-			unsafe { &mut *self.0.get() }.push(val);
+		/// Add an item to the end of this arena-backed vector, and return a
+		/// reference to it. Because arenas never move in memory, this can be
+		/// accomplished with an immutable reference.
+		///
+		/// The reference lives as long as the vector does, so it can be held
+		/// while pushing more items. This lets items refer to items that were
+		/// pushed before them:
+		///
+		/// ```rs
+		/// let nodes = ArenaVec::new(MemoryAmount::mebibytes(1))?;
+		/// let leaf = nodes.push(Node { value: 1, child: None });
+		/// let root = nodes.push(Node { value: 0, child: Some(leaf) });
+		/// ```
+		///
+		/// Panics if the arena runs out of reserved memory, or if the vector's
+		/// length doesn't fit in `S`.
+		pub fn push(&self, item: T) -> &T {
+			let ptr = self.allocate(S::ONE);
+			unsafe {
+				ptr.write(item);
+				ptr.as_ref()
+			}
+		}
+		/// Like [`ArenaVec::push`], but returns an error instead of panicking.
+		/// `item` is dropped if pushing fails.
+		pub fn try_push(&self, item: T) -> Result<&T, AllocError> {
+			let ptr = self.try_allocate(S::ONE)?;
+			unsafe {
+				ptr.write(item);
+				Ok(ptr.as_ref())
+			}
+		}
+		/// Calls `make`, then pushes the item it returns, and returns a
+		/// reference to that item. Since `make` runs before anything is
+		/// allocated, it can push to this vector too - for example, to push an
+		/// item's children before the item itself.
+		///
+		/// Panics in the same cases as [`ArenaVec::push`].
+		pub fn alloc_with(&self, make: impl FnOnce() -> T) -> &T {
+			self.push(make())
 		}
-		pub fn extend_slice(&self, slice: &[T]) {
-			unsafe { &mut *self.0.get() }.extend_slice(slice);
+		/// Pushes every item from `iter` to the end of this vector, and returns
+		/// them as a slice.
+		///
+		/// Panics in the same cases as [`ArenaVec::push`], or if `iter` pushes
+		/// to this vector while it's being iterated, since the items wouldn't
+		/// be next to each other anymore.
+		pub fn alloc_iter(&self, iter: impl IntoIterator<Item = T>) -> &[T] {
+			let start = self.len.get();
+			let mut count = S::ZERO;
+			for item in iter {
+				let ptr = self.allocate(S::ONE);
+				unsafe { ptr.write(item) };
+				count += S::ONE;
+				assert!(
+					self.len.get() == start + count,
+					"Crux: ArenaVec was pushed to while an iterator was being allocated in it"
+				);
+			}
+
+			if count == S::ZERO {
+				return &[];
+			}
+			unsafe {
+				NonNull::slice_from_raw_parts(
+					self.base.get().add(start.as_usize()),
+					count.as_usize(),
+				)
+				.as_ref()
+			}
+		}
+		/// Copy the items from `slice` to the end of this arena-backed vector,
+		/// and return the copies. Because arenas never move in memory, this can
+		/// be accomplished with an immutable reference.
+		///
+		/// Panics if the arena runs out of reserved memory, or if the vector's
+		/// length doesn't fit in `S`.
+		pub fn extend_slice(&self, slice: &[T]) -> &[T]
+		where
+			T: Copy,
+		{
+			self.try_extend_slice(slice).expect(
+				"Crux: ArenaVec's arena ran out of memory, or its length overflowed its index type",
+			)
+		}
+		/// Like [`ArenaVec::extend_slice`], but returns an error instead of
+		/// panicking.
+		pub fn try_extend_slice(&self, slice: &[T]) -> Result<&[T], AllocError>
+		where
+			T: Copy,
+		{
+			if slice.len() > S::MAX.as_usize() {
+				return Err(AllocError);
+			}
+			let ptr = self.try_allocate(S::usize_as_self(slice.len()))?;
+			unsafe {
+				ptr.copy_from_nonoverlapping(NonNull::from(slice).cast(), slice.len());
+				Ok(NonNull::slice_from_raw_parts(ptr, slice.len()).as_ref())
+			}
+		}
+		/// Allocates room for `count` more items at the end of the vector, and
+		/// adds them to the vector's length. The caller must initialise the
+		/// items before the vector is read or dropped.
+		fn allocate(&self, count: S) -> NonNull<T> {
+			assert!(
+				self.len.get().checked_add(count).is_some(),
+				"Crux: ArenaVec's length overflowed its index type"
+			);
+			self.try_allocate(count)
+				.expect("Crux: ArenaVec's arena ran out of memory")
+		}
+		/// Like [`ArenaVec::allocate`], but returns an error if the arena runs
+		/// out of memory, or if the vector's length would overflow `S`.
+		fn try_allocate(&self, count: S) -> Result<NonNull<T>, AllocError> {
+			let len = self.len.get();
+			let new_len = len.checked_add(count).ok_or(AllocError)?;
+			let layout = Layout::array::<T>(count.as_usize()).map_err(|_| AllocError)?;
+			let ptr = self.arena.allocate(layout)?.cast::<T>();
+
+			if len == S::ZERO {
+				self.base.set(ptr);
+			}
+			safety_assert!(ptr == unsafe { self.base.get().add(len.as_usize()) });
+			self.len.set(new_len);
+
+			Ok(ptr)
+		}
+
+		/// Removes the last item from the vector, and returns it, as long as
+		/// the vector isn't empty. Its memory will be reused by the next item
+		/// that's pushed.
+		pub fn pop(&mut self) -> Option<T> {
+			let len = self.len.get();
+			if len == S::ZERO {
+				return None;
+			}
+
+			let item = unsafe { self.base.get().add(len.as_usize() - 1).read() };
+			self.release(S::ONE);
+			Some(item)
+		}
+		/// Drops every item at or after `len`, so the vector has at most `len`
+		/// items. Their memory will be reused by items pushed later.
+		pub fn truncate(&mut self, len: S) {
+			let old_len = self.len.get();
+			if len >= old_len {
+				return;
+			}
+
+			let tail = slice_from_raw_parts_mut(
+				unsafe { self.base.get().add(len.as_usize()) }.as_ptr(),
+				(old_len - len).as_usize(),
+			);
+			// Shorten the vector first, so it doesn't drop the items again if
+			// one of them panics while being dropped
+			self.release(old_len - len);
+			unsafe { drop_in_place(tail) };
+		}
+		/// Drops every item in the vector.
+		pub fn clear(&mut self) {
+			self.truncate(S::ZERO);
+		}
+		/// Removes the last `count` items from the vector's length, and gives
+		/// their memory back to the arena. Doesn't drop them.
+		fn release(&mut self, count: S) {
+			self.len.set(self.len.get() - count);
+			let used = &self.arena.used;
+			used.set(used.get() - MemoryAmount::bytes(count.as_usize() * size_of::<T>()));
+		}
+
+		/// If the vector contains 0 elements.
+		pub fn is_empty(&self) -> bool {
+			self.len.get() == S::ZERO
+		}
+		/// How many elements the vector has.
+		pub const fn len(&self) -> S {
+			self.len.get()
+		}
+
+		/// The items in the vector. Items pushed after this is called won't
+		/// be in the slice.
+		pub const fn as_slice(&self) -> &[T] {
+			unsafe {
+				NonNull::slice_from_raw_parts(self.base.get(), self.len.get().as_usize()).as_ref()
+			}
+		}
+		pub const fn as_slice_mut(&mut self) -> &mut [T] {
+			unsafe {
+				NonNull::slice_from_raw_parts(self.base.get(), self.len.get().as_usize()).as_mut()
+			}
 		}
 	}
 	impl<T, S: const IndexSize> From<VirtualMemoryArena> for ArenaVec<T, S> {
 		fn from(value: VirtualMemoryArena) -> Self {
-			Self(UnsafeCell::new(SizedVec::with_allocator(value)))
+			Self {
+				arena: value,
+				base: Cell::new(NonNull::dangling()),
+				len: Cell::new(S::ZERO),
+				_items: PhantomData,
+			}
 		}
 	}
 	impl<T, S: const IndexSize> const Deref for ArenaVec<T, S> {
-		type Target = SizedVec<T, S, VirtualMemoryArena>;
+		type Target = [T];
 
 		fn deref(&self) -> &Self::Target {
-			unsafe { &*self.0.get() }
+			self.as_slice()
 		}
 	}
 	impl<T, S: const IndexSize> const DerefMut for ArenaVec<T, S> {
 		fn deref_mut(&mut self) -> &mut Self::Target {
-			self.0.get_mut()
+			self.as_slice_mut()
+		}
+	}
+	impl<T, S: const IndexSize, I: ArenaVecIndex<S>> Index<I> for ArenaVec<T, S>
+	where
+		I::SliceIndex: SliceIndex<[T]>,
+	{
+		type Output = <I::SliceIndex as SliceIndex<[T]>>::Output;
+
+		fn index(&self, index: I) -> &Self::Output {
+			&self.as_slice()[index.to_slice_index()]
+		}
+	}
+	impl<T, S: const IndexSize, I: ArenaVecIndex<S>> IndexMut<I> for ArenaVec<T, S>
+	where
+		I::SliceIndex: SliceIndex<[T]>,
+	{
+		fn index_mut(&mut self, index: I) -> &mut Self::Output {
+			&mut self.as_slice_mut()[index.to_slice_index()]
+		}
+	}
+
+	/// Implemented for types that can be used in the indexing operation (`[]`)
+	/// for [`ArenaVec`]s: the vector's index type `S`, and ranges of `S`.
+	pub trait ArenaVecIndex<S: const IndexSize> {
+		/// The equivalent index into a slice.
+		type SliceIndex;
+
+		fn to_slice_index(self) -> Self::SliceIndex;
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for S {
+		type SliceIndex = usize;
+
+		fn to_slice_index(self) -> usize {
+			self.as_usize()
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for Range<S> {
+		type SliceIndex = Range<usize>;
+
+		fn to_slice_index(self) -> Range<usize> {
+			self.start.as_usize()..self.end.as_usize()
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for RangeInclusive<S> {
+		type SliceIndex = RangeInclusive<usize>;
+
+		fn to_slice_index(self) -> RangeInclusive<usize> {
+			self.start().as_usize()..=self.end().as_usize()
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for RangeFrom<S> {
+		type SliceIndex = RangeFrom<usize>;
+
+		fn to_slice_index(self) -> RangeFrom<usize> {
+			self.start.as_usize()..
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for RangeTo<S> {
+		type SliceIndex = RangeTo<usize>;
+
+		fn to_slice_index(self) -> RangeTo<usize> {
+			..self.end.as_usize()
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for RangeToInclusive<S> {
+		type SliceIndex = RangeToInclusive<usize>;
+
+		fn to_slice_index(self) -> RangeToInclusive<usize> {
+			..=self.end.as_usize()
+		}
+	}
+	impl<S: const IndexSize> ArenaVecIndex<S> for RangeFull {
+		type SliceIndex = RangeFull;
+
+		fn to_slice_index(self) -> RangeFull {
+			..
+		}
+	}
+	// `may_dangle` lets items borrow from the vector they're stored in. Items'
+	// own `Drop` impls still can't observe dangling references, because the
+	// vector owns a `PhantomData<T>`.
+	unsafe impl<#[may_dangle] T, S: const IndexSize> Drop for ArenaVec<T, S> {
+		fn drop(&mut self) {
+			unsafe { drop_in_place(self.as_slice_mut()) };
 		}
 	}
 
@@ -153,20 +504,123 @@ pub mod arena {
 		) -> Result<Self, ArenaPreallocationError> {
 			Ok(Self(ArenaVec::new_preallocate(to_reserve, to_commit)?))
 		}
+		/// Reserve virtual memory for a new arena-backed string, then
+		/// preallocate enough of it to store `chars` characters, no matter how
+		/// many bytes they take up in UTF-8.
+		pub fn with_capacity_chars(
+			to_reserve: MemoryAmount,
+			chars: usize,
+		) -> Result<Self, ArenaPreallocationError> {
+			// A UTF-8 character is at most 4 bytes
+			Self::new_preallocate(to_reserve, MemoryAmount::bytes(chars.saturating_mul(4)))
+		}
+
+		/// Creates an arena string from `bytes`, replacing invalid UTF-8 with
+		/// `U+FFFD REPLACEMENT CHARACTER`.
+		pub fn from_utf8_lossy(bytes: &[u8]) -> Self {
+			let this = Self::new_preallocate(
+				Self::DEFAULT_RESERVE_AMOUNT,
+				MemoryAmount::bytes(bytes.len()),
+			)
+			.unwrap();
+			this.push_utf8_lossy(bytes);
+			this
+		}
 
 		pub fn push_char(&self, c: char) {
 			let mut buf = [0; 4];
-			c.encode_utf8(&mut buf);
-			self.0.extend_slice(&buf);
+			self.push_str(c.encode_utf8(&mut buf));
 		}
 		pub fn push_str(&self, s: &str) {
 			self.0.extend_slice(s.as_bytes());
 		}
+		/// Like [`ArenaString::push_char`], but returns an error if the arena
+		/// runs out of memory, instead of panicking.
+		pub fn try_push_char(&self, c: char) -> Result<(), AllocError> {
+			let mut buf = [0; 4];
+			self.try_push_str(c.encode_utf8(&mut buf))
+		}
+		/// Like [`ArenaString::push_str`], but returns an error if the arena
+		/// runs out of memory, instead of panicking.
+		pub fn try_push_str(&self, s: &str) -> Result<(), AllocError> {
+			self.0.try_extend_slice(s.as_bytes()).map(|_| ())
+		}
+		/// Appends `s` to this string, and returns the appended copy. Like
+		/// [`ArenaVec::push`], the copy lives as long as the string, so it can
+		/// be held while more text is pushed.
+		pub fn alloc_str(&self, s: &str) -> &str {
+			unsafe { str::from_utf8_unchecked(self.0.extend_slice(s.as_bytes())) }
+		}
+		/// Like [`ArenaString::alloc_str`], but returns an error if the arena
+		/// runs out of memory, instead of panicking.
+		pub fn try_alloc_str(&self, s: &str) -> Result<&str, AllocError> {
+			let bytes = self.0.try_extend_slice(s.as_bytes())?;
+			Ok(unsafe { str::from_utf8_unchecked(bytes) })
+		}
+		/// Formats `args` onto the end of this string, and returns the
+		/// formatted text. Like [`ArenaString::alloc_str`], the text lives as
+		/// long as the string.
+		///
+		/// ```rs
+		/// let log = ArenaString::new(MemoryAmount::mebibytes(1))?;
+		/// let line = log.format_into(format_args!("{} + {} = {}\n", 1, 2, 1 + 2));
+		/// assert_eq!(line, "1 + 2 = 3\n");
+		/// ```
+		pub fn format_into(&self, args: FormatArgs) -> &str {
+			let start = self.len();
+			write_fmt(&mut &*self, args)
+				.expect("Crux: A formatting trait implementation returned an error");
+			&self.as_str()[start..]
+		}
+		/// Appends `bytes` to this string, replacing invalid UTF-8 with
+		/// `U+FFFD REPLACEMENT CHARACTER`.
+		pub fn push_utf8_lossy(&self, bytes: &[u8]) {
+			for chunk in bytes.utf8_chunks() {
+				self.push_str(chunk.valid());
+				if !chunk.invalid().is_empty() {
+					self.push_char(char::REPLACEMENT_CHARACTER);
+				}
+			}
+		}
+
+		/// Shortens this string to `new_len` bytes. Does nothing if the string
+		/// is already shorter than `new_len`.
+		///
+		/// Panics if `new_len` isn't on a character boundary.
+		pub fn truncate(&mut self, new_len: usize) {
+			if new_len >= self.len() {
+				return;
+			}
+			assert!(
+				self.is_char_boundary(new_len),
+				"Crux: Tried to truncate an ArenaString in the middle of a character"
+			);
+			self.0.truncate(S::usize_as_self(new_len));
+		}
+		/// Removes all the text in this string. Its memory will be reused by
+		/// text pushed later.
+		pub fn clear(&mut self) {
+			self.0.clear();
+		}
 
 		pub const fn as_str(&self) -> &str {
 			unsafe { str::from_utf8_unchecked(&self.0) }
 		}
 	}
+	impl<S: const IndexSize> TextWrite for ArenaString<S> {
+		fn write_str(&mut self, s: &str) -> core::fmt::Result {
+			self.push_str(s);
+			Ok(())
+		}
+	}
+	/// Arena strings can be written to immutably, so a shared reference can
+	/// be used with `write!` too.
+	impl<S: const IndexSize> TextWrite for &ArenaString<S> {
+		fn write_str(&mut self, s: &str) -> core::fmt::Result {
+			self.push_str(s);
+			Ok(())
+		}
+	}
 	impl<S: const IndexSize> From<&str> for ArenaString<S> {
 		fn from(value: &str) -> Self {
 			let this = Self::new_preallocate(
@@ -190,72 +644,442 @@ pub mod arena {
 			unsafe { str::from_utf8_unchecked_mut(&mut self.0) }
 		}
 	}
-}
 
-pub mod typed_vec {
-	use crate::data_structures::IndexSize;
+	//
+	//
+	// Tests
+	//
+	//
 
-	pub trait TypedVecIndex: Clone + Copy {
-		type Index: const IndexSize;
+	#[cfg(test)]
+	mod tests {
+		use {
+			super::*,
+			crate::text::{format_args, write},
+		};
 
-		fn raw(self) -> Self::Index;
-		unsafe fn from_raw(raw: Self::Index) -> Self;
-	}
+		#[test]
+		fn arena_vec_references() {
+			let vec = ArenaVec::<usize>::new(MemoryAmount::mebibytes(1)).unwrap();
+			let first = vec.push(0);
+			let before = vec.as_slice();
+
+			// Enough items to need several pages; none of them move
+			let mut items = Vec::new();
+			for i in 1..10_000 {
+				items.push(vec.push(i));
+			}
+			let copies = vec.extend_slice(&[1, 2, 3]);
 
-	pub struct TypedVec<T, S: TypedVecIndex, A: Allocator = GlobalAllocator>(
-		SizedVec<T, S::Index, A>,
-	);
-	impl<T, S: TypedVecIndex> Default for TypedVec<T, S, GlobalAllocator> {
-		fn default() -> Self {
-			Self::new()
+			assert_eq!(*first, 0);
+			assert!(items.iter().enumerate().all(|(i, item)| **item == i + 1));
+			assert_eq!(copies, [1, 2, 3]);
+			// Slices keep the length they had when they were created
+			assert_eq!(before, [0]);
+			assert_eq!(vec.len(), 10_003);
+			assert_eq!(vec[9_999], 9_999);
 		}
-	}
-	impl<T, S: TypedVecIndex> TypedVec<T, S, GlobalAllocator> {
-		pub const fn new() -> Self {
-			Self(SizedVec::new())
+
+		#[test]
+		fn arena_vec_removal() {
+			let drops = Cell::new(0);
+			struct Counted<'a>(&'a Cell<usize>);
+			impl Drop for Counted<'_> {
+				fn drop(&mut self) {
+					self.0.set(self.0.get() + 1);
+				}
+			}
+
+			let mut vec = ArenaVec::<Counted, u8>::new(MemoryAmount::kibibytes(4)).unwrap();
+			for _ in 0..5 {
+				vec.push(Counted(&drops));
+			}
+			drop(vec.pop());
+			assert_eq!((vec.len(), drops.get()), (4, 1));
+			vec.truncate(2);
+			assert_eq!((vec.len(), drops.get()), (2, 3));
+
+			// Removed items' memory is reused
+			let used = vec.arena.used.get();
+			vec.push(Counted(&drops));
+			assert_eq!(
+				vec.arena.used.get(),
+				used + MemoryAmount::bytes(size_of::<Counted>())
+			);
+
+			drop(vec);
+			assert_eq!(drops.get(), 6);
 		}
-		pub fn with_capacity(num_items: S::Index) -> Self {
-			Self(SizedVec::with_capacity(num_items))
+
+		#[test]
+		fn arena_vec_allocation() {
+			struct Node<'a> {
+				value: u32,
+				child: Option<&'a Node<'a>>,
+			}
+
+			// Items can refer to other items in the same vector
+			let nodes = ArenaVec::<Node>::new(MemoryAmount::mebibytes(1)).unwrap();
+			let root = nodes.alloc_with(|| Node {
+				value: 0,
+				child: Some(nodes.push(Node {
+					value: 1,
+					child: None,
+				})),
+			});
+			let parent = nodes.push(Node {
+				value: 2,
+				child: Some(root),
+			});
+			assert_eq!(root.child.map(|node| node.value), Some(1));
+			assert_eq!(parent.child.map(|node| node.value), Some(0));
+			assert_eq!(
+				nodes.iter().map(|node| node.value).collect::<Vec<_>>(),
+				[1, 0, 2]
+			);
+
+			let numbers = ArenaVec::<u32, u16>::new(MemoryAmount::kibibytes(4)).unwrap();
+			numbers.push(0);
+			let evens = numbers.alloc_iter((1..5).map(|n| n * 2));
+			assert_eq!(evens, [2, 4, 6, 8]);
+			assert_eq!(numbers.alloc_iter(None), []);
+			assert_eq!(numbers.as_slice(), [0, 2, 4, 6, 8]);
 		}
-	}
-	impl<T, S: TypedVecIndex, A: Allocator> TypedVec<T, S, A> {
-		pub const fn with_allocator(allocator: A) -> Self {
-			Self(SizedVec::with_allocator(allocator))
+
+		#[test]
+		fn arena_string() {
+			let string = ArenaString::<u32>::new(MemoryAmount::mebibytes(1)).unwrap();
+			for _ in 0..2_000 {
+				string.push_str("crux");
+			}
+			string.push_char('!');
+			assert_eq!(string.len(), 8_001);
+			assert!(string.ends_with("crux!"));
+
+			let words = ["arena", "strings"].map(|word| string.alloc_str(word));
+			assert_eq!(words, ["arena", "strings"]);
+			assert!(string.ends_with("crux!arenastrings"));
 		}
-		pub fn with_allocator_and_capacity(allocator: A, num_items: S::Index) -> Self {
-			Self(SizedVec::with_allocator_and_capacity(allocator, num_items))
+
+		#[test(should_panic)]
+		fn arena_extend_slice_overflow() {
+			// 300 truncates to 44 as a `u8`, so this must panic instead of
+			// copying the whole slice into a 44-item allocation
+			let bytes = ArenaVec::<u8, u8>::new(MemoryAmount::kibibytes(4)).unwrap();
+			bytes.extend_slice(&[1; 300]);
 		}
 
-		pub fn get(&self, idx: S) -> Option<&T> {
-			self.0.get(idx.raw())
+		#[test]
+		fn arena_string_formatting() {
+			let mut string =
+				ArenaString::<u16>::with_capacity_chars(MemoryAmount::kibibytes(4), 16).unwrap();
+			let sum = string.format_into(format_args!("{} + {} = {}", 1, 2, 1 + 2));
+			assert_eq!(sum, "1 + 2 = 3");
+			// Arena strings can be written to through a shared reference
+			let umlaut = 'ü';
+			write!(&mut &string, ", {umlaut}").unwrap();
+			assert_eq!(string.as_str(), "1 + 2 = 3, ü");
+
+			string.truncate(100);
+			string.truncate(9);
+			assert_eq!(string.as_str(), "1 + 2 = 3");
+			string.clear();
+			write!(string, "{:?}", "crux").unwrap();
+			assert_eq!(string.as_str(), "\"crux\"");
 		}
-		pub fn get_mut(&mut self, idx: S) -> Option<&mut T> {
-			self.0.get_mut(idx.raw())
+
+		#[test]
+		fn arena_fallible_allocation() {
+			// Lengths that don't fit in the index type are errors, not panics
+			let bytes = ArenaVec::<u8, u8>::new(MemoryAmount::kibibytes(4)).unwrap();
+			assert_eq!(bytes.try_extend_slice(&[1; 255]).map(<[u8]>::len), Ok(255));
+			assert_eq!(bytes.try_push(1), Err(AllocError));
+			assert_eq!(bytes.try_extend_slice(&[1; 300]), Err(AllocError));
+			assert_eq!(bytes.len(), 255);
+
+			let string = ArenaString::<u32>::new(MemoryAmount::kibibytes(4)).unwrap();
+			string.try_push_str("crux").unwrap();
+			assert_eq!(string.try_push_str(&"x".repeat(1 << 20)), Err(AllocError));
+			assert_eq!(string.try_alloc_str("!"), Ok("!"));
+			assert_eq!(string.try_push_char('?'), Ok(()));
+			assert_eq!(string.as_str(), "crux!?");
 		}
 
-		pub fn push(&mut self, item: T) -> &mut T {
-			self.0.push(item)
+		#[test(should_panic)]
+		fn arena_string_truncate_boundary() {
+			let mut string = ArenaString::<u8>::from("ü");
+			string.truncate(1);
 		}
 	}
+}
 
-	#[macro_export]
-	macro_rules! typed_vec_idx {
-		($($ty:ident: $size:ty),*) => {
-			$(
-			#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-			pub struct $ty($size);
-			impl $crate::data_structures::typed_vec::TypedVecIndex for $ty {
-				type Index = $size;
+//
+// Hash map types
+//
 
-				fn raw(self) -> Self::Index {
-					self.0
-				}
-				unsafe fn from_raw(raw: Self::Index) -> Self {
-					Self(raw)
+/// A [`HashMap`] that uses [`FnvHasher`]s.
+///
+/// [`FnvHasher`]: crate::crypto::hash::FnvHasher
+pub type FnvHashMap<K, V> = HashMap<K, V, FnvBuildHasher>;
+/// A [`HashSet`] that uses [`FnvHasher`]s.
+///
+/// [`FnvHasher`]: crate::crypto::hash::FnvHasher
+pub type FnvHashSet<T> = HashSet<T, FnvBuildHasher>;
+/// A [`HashMap`] that uses [`IdentityHasher`]s, for integer keys.
+///
+/// [`IdentityHasher`]: crate::crypto::hash::IdentityHasher
+pub type IdentityHashMap<K, V> = HashMap<K, V, IdentityBuildHasher>;
+/// A [`HashSet`] that uses [`IdentityHasher`]s, for integer keys.
+///
+/// [`IdentityHasher`]: crate::crypto::hash::IdentityHasher
+pub type IdentityHashSet<T> = HashSet<T, IdentityBuildHasher>;
+/// A [`HashMap`] with randomly seeded keys, which is resistant to HashDoS
+/// attacks. See [`RandomState`].
+pub type RandomHashMap<K, V> = HashMap<K, V, RandomState>;
+/// A [`HashSet`] with randomly seeded keys, which is resistant to HashDoS
+/// attacks. See [`RandomState`].
+pub type RandomHashSet<T> = HashSet<T, RandomState>;
+
+/// A [`HashMap`] that allocates in a [`VirtualMemoryArena`]. Create one with
+/// [`ArenaExt::hash_map`].
+///
+/// [`ArenaExt::hash_map`]: crate::rt::mem::ArenaExt::hash_map
+pub type ArenaHashMap<'a, K, V> = HashMap<K, V, DefaultHashBuilder, &'a VirtualMemoryArena>;
+/// A [`HashSet`] that allocates in a [`VirtualMemoryArena`]. Create one with
+/// [`ArenaExt::hash_set`].
+///
+/// [`ArenaExt::hash_set`]: crate::rt::mem::ArenaExt::hash_set
+pub type ArenaHashSet<'a, T> = HashSet<T, DefaultHashBuilder, &'a VirtualMemoryArena>;
+
+/// Constructors for [`HashMap`]s and [`HashSet`]s that use any hasher with a
+/// default value. `HashMap::new` only exists for the default hasher, so this
+/// is the easiest way to create maps that use a different hasher:
+///
+/// ```rs
+/// let mut ids: IdentityHashMap<u64, Entity> = HashMap::with_default_hasher();
+/// let mut names = FnvHashSet::with_capacity_and_default_hasher(16);
+/// names.insert("crux");
+/// ```
+pub trait HashMapExt: Sized {
+	/// Creates an empty map.
+	fn with_default_hasher() -> Self;
+	/// Creates an empty map with room for at least `capacity` items.
+	fn with_capacity_and_default_hasher(capacity: usize) -> Self;
+}
+impl<K, V, S: BuildHasher + Default> HashMapExt for HashMap<K, V, S> {
+	fn with_default_hasher() -> Self {
+		Self::with_hasher(S::default())
+	}
+	fn with_capacity_and_default_hasher(capacity: usize) -> Self {
+		Self::with_capacity_and_hasher(capacity, S::default())
+	}
+}
+impl<T, S: BuildHasher + Default> HashMapExt for HashSet<T, S> {
+	fn with_default_hasher() -> Self {
+		Self::with_hasher(S::default())
+	}
+	fn with_capacity_and_default_hasher(capacity: usize) -> Self {
+		Self::with_capacity_and_hasher(capacity, S::default())
+	}
+}
+
+//
+// B-tree types
+//
+
+/// A [`BTreeMap`] that allocates in a [`VirtualMemoryArena`]. Create one with
+/// [`ArenaExt::btree_map`].
+///
+/// [`ArenaExt::btree_map`]: crate::rt::mem::ArenaExt::btree_map
+pub type ArenaBTreeMap<'a, K, V> = BTreeMap<K, V, &'a VirtualMemoryArena>;
+/// A [`BTreeSet`] that allocates in a [`VirtualMemoryArena`]. Create one with
+/// [`ArenaExt::btree_set`].
+///
+/// [`ArenaExt::btree_set`]: crate::rt::mem::ArenaExt::btree_set
+pub type ArenaBTreeSet<'a, T> = BTreeSet<T, &'a VirtualMemoryArena>;
+
+/// Finds the neighbours of a key in a [`BTreeMap`], whether or not the key is
+/// in the map:
+///
+/// ```rs
+/// let mut regions = BTreeMap::new();
+/// regions.insert(0x1000, "text");
+/// regions.insert(0x8000, "data");
+///
+/// // Which region contains 0x2345?
+/// assert_eq!(regions.last_at_or_before(&0x2345), Some((&0x1000, &"text")));
+/// assert_eq!(regions.first_after(&0x2345), Some((&0x8000, &"data")));
+/// ```
+///
+/// For everything between two keys, use [`BTreeMap::range`].
+pub trait BTreeMapExt<K, V> {
+	/// The entry with the smallest key that's greater than or equal to `key`.
+	fn first_at_or_after<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>;
+	/// The entry with the smallest key that's greater than `key`.
+	fn first_after<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>;
+	/// The entry with the largest key that's less than or equal to `key`.
+	fn last_at_or_before<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>;
+	/// The entry with the largest key that's less than `key`.
+	fn last_before<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>;
+}
+impl<K: Ord, V, A: Allocator + Clone> BTreeMapExt<K, V> for BTreeMap<K, V, A> {
+	fn first_at_or_after<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+	{
+		self.range((Bound::Included(key), Bound::Unbounded)).next()
+	}
+	fn first_after<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+	{
+		self.range((Bound::Excluded(key), Bound::Unbounded)).next()
+	}
+	fn last_at_or_before<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+	{
+		self.range((Bound::Unbounded, Bound::Included(key)))
+			.next_back()
+	}
+	fn last_before<Q: Ord + ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+	{
+		self.range((Bound::Unbounded, Bound::Excluded(key)))
+			.next_back()
+	}
+}
+
+/// Finds the neighbours of a value in a [`BTreeSet`], whether or not the value
+/// is in the set. See [`BTreeMapExt`].
+pub trait BTreeSetExt<T> {
+	/// The smallest value that's greater than or equal to `value`.
+	fn first_at_or_after<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>;
+	/// The smallest value that's greater than `value`.
+	fn first_after<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>;
+	/// The largest value that's less than or equal to `value`.
+	fn last_at_or_before<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>;
+	/// The largest value that's less than `value`.
+	fn last_before<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>;
+}
+impl<T: Ord, A: Allocator + Clone> BTreeSetExt<T> for BTreeSet<T, A> {
+	fn first_at_or_after<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>,
+	{
+		self.range((Bound::Included(value), Bound::Unbounded))
+			.next()
+	}
+	fn first_after<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>,
+	{
+		self.range((Bound::Excluded(value), Bound::Unbounded))
+			.next()
+	}
+	fn last_at_or_before<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>,
+	{
+		self.range((Bound::Unbounded, Bound::Included(value)))
+			.next_back()
+	}
+	fn last_before<Q: Ord + ?Sized>(&self, value: &Q) -> Option<&T>
+	where
+		T: Borrow<Q>,
+	{
+		self.range((Bound::Unbounded, Bound::Excluded(value)))
+			.next_back()
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			lang::Extend,
+			rt::mem::{ArenaExt, MemoryAmount},
+		},
+	};
+
+	#[test]
+	fn btree_neighbours() {
+		let arena = VirtualMemoryArena::new(MemoryAmount::mebibytes(1)).unwrap();
+		let mut regions: ArenaBTreeMap<u32, &str> = arena.btree_map();
+		regions.insert(0x1000, "text");
+		regions.insert(0x8000, "data");
+
+		assert_eq!(regions.first_at_or_after(&0x1000), Some((&0x1000, &"text")));
+		assert_eq!(regions.first_after(&0x1000), Some((&0x8000, &"data")));
+		assert_eq!(regions.first_after(&0x8000), None);
+		assert_eq!(regions.last_at_or_before(&0x2345), Some((&0x1000, &"text")));
+		assert_eq!(regions.last_before(&0x1000), None);
+		assert_eq!(regions.last_before(&0x8000), Some((&0x1000, &"text")));
+		assert_eq!(regions.last_at_or_before(&0x8000), Some((&0x8000, &"data")));
+		assert_eq!(regions.first_at_or_after(&0x8001), None);
+
+		let mut words = arena.btree_set();
+		words.extend(["b", "d"]);
+		assert_eq!(words.first_at_or_after("c"), Some(&"d"));
+		assert_eq!(words.last_before("d"), Some(&"b"));
+		assert_eq!(words.last_at_or_before("a"), None);
+		assert_eq!(words.first_after("b"), Some(&"d"));
+		assert_eq!(words.first_after("d"), None);
+		assert_eq!(words.last_at_or_before("b"), Some(&"b"));
+
+		// The helpers work with any allocator, not just arenas
+		let heap = BTreeSet::from([1, 5, 9]);
+		assert_eq!(heap.last_before(&5), Some(&1));
+		assert_eq!(heap.first_after(&5), Some(&9));
+		assert_eq!(heap.first_at_or_after(&10), None);
+	}
+
+	#[test]
+	fn hash_map_helpers() {
+		let arena = VirtualMemoryArena::new(MemoryAmount::mebibytes(1)).unwrap();
+		let mut counts: ArenaHashMap<&str, u32> = arena.hash_map_with_capacity(4);
+		for word in ["a", "b", "a"] {
+			match counts.entry(word) {
+				Entry::Occupied(mut entry) => *entry.get_mut() += 1,
+				Entry::Vacant(entry) => {
+					entry.insert(1);
 				}
 			}
-			)*
+		}
+		assert_eq!(counts.len(), 2);
+		assert_eq!(counts["a"], 2);
+
+		let literal = map! {
+			"a" => 2,
+			"b" => 1,
 		};
+		assert!(literal.iter().all(|(word, count)| counts[word] == *count));
+		let mut words = arena.hash_set();
+		words.extend(["a", "b"]);
+		assert!(set! { "a", "b" }.iter().all(|word| words.contains(word)));
 	}
-	pub use crate::typed_vec_idx;
 }
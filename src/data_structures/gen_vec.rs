@@ -0,0 +1,444 @@
+use crate::{
+	data_structures::{
+		IndexSize,
+		sized_vec::{self, EnumerateSized},
+	},
+	lang::{DoubleEndedIterator, ExactSizeIterator, FusedIterator, Index, IndexMut, replace},
+};
+
+/// A handle to an item in a [`GenVec`].
+///
+/// Along with the item's position in the vector, handles store the
+/// generation of the slot the item was inserted into. Removing the item bumps
+/// that slot's generation, so the handle stops working even if another item
+/// gets inserted in the same slot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GenIdx<S: const IndexSize = u32> {
+	idx: S,
+	generation: S,
+}
+impl<S: const IndexSize> GenIdx<S> {
+	/// The position of the item in the vector.
+	pub const fn index(self) -> S {
+		self.idx
+	}
+	/// How many items were removed from this handle's slot before its item
+	/// was inserted.
+	pub const fn generation(self) -> S {
+		self.generation
+	}
+}
+
+#[derive(Clone)]
+enum Slot<T, S> {
+	Occupied {
+		generation: S,
+		value: T,
+	},
+	/// `generation` is the generation the next item stored in this slot will
+	/// get.
+	Vacant {
+		generation: S,
+		next_free: Option<S>,
+	},
+}
+
+/// A vector whose handles are invalidated when their item is removed.
+///
+/// [`TypedVec`] indices are just positions in the vector, so after an item is
+/// removed, its indices end up pointing at whatever item moves into its slot.
+/// [`GenVec`] handles ([`GenIdx`]) also store a generation counter that's
+/// bumped every time an item is removed from a slot, so stale handles return
+/// [`None`] instead of another item. Items never move, and removed slots are
+/// reused by later insertions.
+///
+/// This makes [`GenVec`] a good fit for entity systems, resource managers,
+/// and anything else that hands out long-lived handles to objects that might
+/// be destroyed.
+///
+/// ```rs
+/// let mut textures = GenVec::<Texture>::new();
+/// let grass = textures.insert(Texture::load("grass.png"));
+/// textures.remove(grass);
+/// assert!(textures.get(grass).is_none());
+/// ```
+///
+/// `S` is the type used for both the index and the generation. If a slot's
+/// generation would overflow `S`, the slot is retired and never reused, so
+/// handles can't be mistaken for each other even after wrapping around.
+pub struct GenVec<T, S: const IndexSize = u32, A: Allocator = GlobalAllocator> {
+	slots: SizedVec<Slot<T, S>, S, A>,
+	/// The most recently vacated slot that can be reused.
+	next_free: Option<S>,
+	len: S,
+}
+impl<T, S: const IndexSize> Default for GenVec<T, S, GlobalAllocator> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<T, S: const IndexSize> GenVec<T, S, GlobalAllocator> {
+	pub const fn new() -> Self {
+		Self::with_allocator(GlobalAllocator)
+	}
+	pub fn with_capacity(num_items: S) -> Self {
+		Self::with_allocator_and_capacity(GlobalAllocator, num_items)
+	}
+}
+impl<T, S: const IndexSize, A: Allocator> GenVec<T, S, A> {
+	pub const fn with_allocator(allocator: A) -> Self {
+		Self {
+			slots: SizedVec::with_allocator(allocator),
+			next_free: None,
+			len: S::ZERO,
+		}
+	}
+	pub fn with_allocator_and_capacity(allocator: A, num_items: S) -> Self {
+		Self {
+			slots: SizedVec::with_allocator_and_capacity(allocator, num_items),
+			next_free: None,
+			len: S::ZERO,
+		}
+	}
+
+	/// Adds an item to the vector, and returns a handle to it.
+	pub fn insert(&mut self, item: T) -> GenIdx<S> {
+		self.insert_with(|_| item)
+	}
+	/// Adds the item `make` returns to the vector, and returns a handle to it.
+	/// `make` is given the handle the item will have, so items can store their
+	/// own handle.
+	pub fn insert_with(&mut self, make: impl FnOnce(GenIdx<S>) -> T) -> GenIdx<S> {
+		let handle = match self.next_free {
+			Some(idx) => {
+				let slot = unsafe { self.slots.get_mut_unchecked(idx) };
+				let Slot::Vacant {
+					generation,
+					next_free,
+				} = *slot
+				else {
+					unreachable!("GenVec free list pointed to an occupied slot")
+				};
+				let handle = GenIdx { idx, generation };
+				*slot = Slot::Occupied {
+					generation,
+					value: make(handle),
+				};
+				self.next_free = next_free;
+
+				handle
+			}
+			None => {
+				let handle = GenIdx {
+					idx: self.slots.len(),
+					generation: S::ZERO,
+				};
+				self.slots.push(Slot::Occupied {
+					generation: S::ZERO,
+					value: make(handle),
+				});
+
+				handle
+			}
+		};
+		self.len += S::ONE;
+
+		handle
+	}
+
+	/// Removes the item `handle` points to, and returns it. Returns [`None`] if
+	/// the item was already removed.
+	///
+	/// After this, `handle` - and any copies of it - will no longer point to
+	/// any item.
+	pub fn remove(&mut self, handle: GenIdx<S>) -> Option<T> {
+		let slot = self.slots.get_mut(handle.idx)?;
+		match slot {
+			Slot::Occupied { generation, .. } if *generation == handle.generation => {}
+			_ => return None,
+		}
+
+		// If the generation would overflow, the slot is retired instead of
+		// being added to the free list, so old handles can never match it
+		// again.
+		let (generation, next_free) = match handle.generation.checked_add(S::ONE) {
+			Some(generation) => (generation, self.next_free.replace(handle.idx)),
+			None => (handle.generation, None),
+		};
+		let Slot::Occupied { value, .. } = replace(
+			slot,
+			Slot::Vacant {
+				generation,
+				next_free,
+			},
+		) else {
+			unreachable!()
+		};
+		self.len -= S::ONE;
+
+		Some(value)
+	}
+	/// Removes every item that `keep` returns `false` for.
+	pub fn retain(&mut self, mut keep: impl FnMut(GenIdx<S>, &mut T) -> bool) {
+		let mut idx = S::ZERO;
+		while idx < self.slots.len() {
+			if let Slot::Occupied { generation, value } =
+				unsafe { self.slots.get_mut_unchecked(idx) }
+			{
+				let handle = GenIdx {
+					idx,
+					generation: *generation,
+				};
+				if !keep(handle, value) {
+					self.remove(handle);
+				}
+			}
+			idx += S::ONE;
+		}
+	}
+	/// Removes every item in the vector. Existing handles are invalidated,
+	/// just like with [`GenVec::remove`].
+	pub fn clear(&mut self) {
+		self.retain(|_, _| false);
+	}
+
+	/// Gets the item `handle` points to, if it hasn't been removed.
+	pub fn get(&self, handle: GenIdx<S>) -> Option<&T> {
+		match self.slots.get(handle.idx)? {
+			Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+			_ => None,
+		}
+	}
+	/// Mutably gets the item `handle` points to, if it hasn't been removed.
+	pub fn get_mut(&mut self, handle: GenIdx<S>) -> Option<&mut T> {
+		match self.slots.get_mut(handle.idx)? {
+			Slot::Occupied { generation, value } if *generation == handle.generation => Some(value),
+			_ => None,
+		}
+	}
+	/// If `handle` points to an item that hasn't been removed.
+	pub fn contains(&self, handle: GenIdx<S>) -> bool {
+		self.get(handle).is_some()
+	}
+
+	/// If the vector contains 0 elements.
+	pub fn is_empty(&self) -> bool {
+		self.len == S::ZERO
+	}
+	/// How many elements the vector has.
+	pub const fn len(&self) -> S {
+		self.len
+	}
+	/// How many slots the vector can store without reallocating. This
+	/// includes slots of removed items.
+	pub const fn capacity(&self) -> S {
+		self.slots.capacity()
+	}
+
+	/// Iterates over every item in the vector, along with its handle.
+	pub fn iter(&self) -> Iter<'_, T, S> {
+		Iter {
+			slots: self.slots.iter().enumerate_sized(),
+			remaining: self.len,
+		}
+	}
+	/// Iterates over mutable references to every item in the vector, along
+	/// with its handle.
+	pub fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+		IterMut {
+			slots: self.slots.iter_mut().enumerate_sized(),
+			remaining: self.len,
+		}
+	}
+	/// Iterates over the handle of every item in the vector.
+	pub fn keys(&self) -> impl DoubleEndedIterator<Item = GenIdx<S>> + ExactSizeIterator {
+		self.iter().map(|(handle, _)| handle)
+	}
+	/// Iterates over every item in the vector, without their handles.
+	pub fn values(&self) -> impl DoubleEndedIterator<Item = &T> + ExactSizeIterator {
+		self.iter().map(|(_, item)| item)
+	}
+	/// Iterates over mutable references to every item in the vector, without
+	/// their handles.
+	pub fn values_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut T> + ExactSizeIterator {
+		self.iter_mut().map(|(_, item)| item)
+	}
+}
+impl<T, S: const IndexSize, A: Allocator> Index<GenIdx<S>> for GenVec<T, S, A> {
+	type Output = T;
+
+	fn index(&self, handle: GenIdx<S>) -> &T {
+		self.get(handle)
+			.expect("GenVec handle was out of bounds or removed")
+	}
+}
+impl<T, S: const IndexSize, A: Allocator> IndexMut<GenIdx<S>> for GenVec<T, S, A> {
+	fn index_mut(&mut self, handle: GenIdx<S>) -> &mut T {
+		self.get_mut(handle)
+			.expect("GenVec handle was out of bounds or removed")
+	}
+}
+impl<T: Clone, S: const IndexSize, A: Allocator + Clone> Clone for GenVec<T, S, A> {
+	fn clone(&self) -> Self {
+		Self {
+			slots: self.slots.clone(),
+			next_free: self.next_free,
+			len: self.len,
+		}
+	}
+}
+impl<T: Debug, S: const IndexSize, A: Allocator> Debug for GenVec<T, S, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+impl<'a, T, S: const IndexSize, A: Allocator> IntoIterator for &'a GenVec<T, S, A> {
+	type Item = (GenIdx<S>, &'a T);
+	type IntoIter = Iter<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+impl<'a, T, S: const IndexSize, A: Allocator> IntoIterator for &'a mut GenVec<T, S, A> {
+	type Item = (GenIdx<S>, &'a mut T);
+	type IntoIter = IterMut<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+macro_rules! gen_vec_iter {
+	($name:ident, $($mutability:tt)?) => {
+		impl<'a, T, S: const IndexSize> Iterator for $name<'a, T, S> {
+			type Item = (GenIdx<S>, &'a $($mutability)? T);
+
+			fn next(&mut self) -> Option<Self::Item> {
+				for (idx, slot) in self.slots.by_ref() {
+					if let Slot::Occupied { generation, value } = slot {
+						self.remaining -= S::ONE;
+						return Some((
+							GenIdx {
+								idx,
+								generation: *generation,
+							},
+							value,
+						));
+					}
+				}
+
+				None
+			}
+			fn size_hint(&self) -> (usize, Option<usize>) {
+				(self.remaining.as_usize(), Some(self.remaining.as_usize()))
+			}
+		}
+		impl<T, S: const IndexSize> DoubleEndedIterator for $name<'_, T, S> {
+			fn next_back(&mut self) -> Option<Self::Item> {
+				while let Some((idx, slot)) = self.slots.next_back() {
+					if let Slot::Occupied { generation, value } = slot {
+						self.remaining -= S::ONE;
+						return Some((
+							GenIdx {
+								idx,
+								generation: *generation,
+							},
+							value,
+						));
+					}
+				}
+
+				None
+			}
+		}
+		impl<T, S: const IndexSize> ExactSizeIterator for $name<'_, T, S> {}
+		impl<T, S: const IndexSize> FusedIterator for $name<'_, T, S> {}
+	};
+}
+
+/// Iterator from [`GenVec::iter`].
+pub struct Iter<'a, T, S: const IndexSize> {
+	slots: EnumerateSized<sized_vec::Iter<'a, Slot<T, S>, S>, S>,
+	remaining: S,
+}
+gen_vec_iter!(Iter,);
+impl<T, S: const IndexSize> Clone for Iter<'_, T, S> {
+	fn clone(&self) -> Self {
+		Self {
+			slots: self.slots.clone(),
+			remaining: self.remaining,
+		}
+	}
+}
+
+/// Iterator from [`GenVec::iter_mut`].
+pub struct IterMut<'a, T, S: const IndexSize> {
+	slots: EnumerateSized<sized_vec::IterMut<'a, Slot<T, S>, S>, S>,
+	remaining: S,
+}
+gen_vec_iter!(IterMut, mut);
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn gen_vec() {
+		let mut vec = GenVec::<String, u32>::new();
+		let a = vec.insert(String::from("a"));
+		let b = vec.insert_with(|handle| format!("b{}", handle.index()));
+		let c = vec.insert(String::from("c"));
+		assert_eq!(vec.len(), 3);
+		assert_eq!((vec[a].as_str(), vec[b].as_str()), ("a", "b1"));
+
+		assert_eq!(vec.remove(b).as_deref(), Some("b1"));
+		assert_eq!(vec.remove(b), None);
+		assert!(!vec.contains(b));
+		assert_eq!(vec.len(), 2);
+		assert_eq!(vec.keys().collect::<Vec<_>>(), [a, c]);
+
+		// The removed slot is reused, but with a new generation.
+		let d = vec.insert(String::from("d"));
+		assert_eq!((d.index(), d.generation()), (1, 1));
+		assert_eq!(vec.get(b), None);
+		assert_eq!(vec[d], "d");
+
+		vec[a].push('!');
+		vec.retain(|handle, _| handle != c);
+		assert_eq!(
+			vec.iter().rev().collect::<Vec<_>>(),
+			[(d, &String::from("d")), (a, &String::from("a!"))]
+		);
+		assert_eq!(vec.iter().len(), 2);
+
+		vec.clear();
+		assert!(vec.is_empty());
+		assert!(!vec.contains(a) && !vec.contains(d));
+		assert_eq!(vec.values().count(), 0);
+	}
+
+	#[test]
+	fn gen_vec_generation_overflow() {
+		let mut vec = GenVec::<(), u8>::new();
+		let mut handle = vec.insert(());
+		for _ in 0..u8::MAX {
+			vec.remove(handle);
+			handle = vec.insert(());
+			assert_eq!(handle.index(), 0);
+		}
+		assert_eq!(handle.generation(), u8::MAX);
+
+		// The slot's generation can't be bumped again, so it gets retired.
+		vec.remove(handle);
+		let new = vec.insert(());
+		assert_eq!((new.index(), new.generation()), (1, 0));
+		assert!(!vec.contains(handle));
+	}
+}
@@ -0,0 +1,260 @@
+use crate::{
+	data_structures::{
+		IndexSize,
+		gen_vec::{GenIdx, GenVec},
+	},
+	lang::ExactSizeIterator,
+};
+
+struct Entry<T, S> {
+	item: T,
+	/// Where this entry's handle is in the heap.
+	pos: S,
+}
+
+/// A priority queue that can change the priority of, or remove, items that
+/// are already in the queue.
+///
+/// Like [`BinaryHeap`], this is a max-heap: [`IndexedHeap::pop`] always
+/// returns the largest item. Wrap items in [`Reverse`] to get the smallest
+/// item first instead.
+///
+/// Pushing an item returns a handle to it, which stays valid until the item
+/// is popped or removed. Handles can be used to update the item in place,
+/// which is what algorithms like Dijkstra's need to "decrease the key" of a
+/// node they've already queued:
+///
+/// ```rs
+/// let mut queue = IndexedHeap::<Reverse<u32>>::new();
+/// let far = queue.push(Reverse(10));
+/// queue.push(Reverse(5));
+///
+/// // Found a shorter path
+/// queue.change_priority(far, |dist| *dist = Reverse(3));
+/// assert_eq!(queue.pop().map(|(_, dist)| dist), Some(Reverse(3)));
+/// ```
+///
+/// [`BinaryHeap`]: crate::data_structures::BinaryHeap
+/// [`Reverse`]: core::cmp::Reverse
+pub struct IndexedHeap<T, S: const IndexSize = u32, A: Allocator = GlobalAllocator> {
+	entries: GenVec<Entry<T, S>, S, A>,
+	/// Handles to every item, in heap order.
+	heap: SizedVec<GenIdx<S>, S, A>,
+}
+impl<T: Ord, S: const IndexSize> Default for IndexedHeap<T, S, GlobalAllocator> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<T: Ord, S: const IndexSize> IndexedHeap<T, S, GlobalAllocator> {
+	pub const fn new() -> Self {
+		Self {
+			entries: GenVec::new(),
+			heap: SizedVec::new(),
+		}
+	}
+}
+impl<T: Ord, S: const IndexSize, A: Allocator> IndexedHeap<T, S, A> {
+	pub fn with_allocator(allocator: A) -> Self
+	where
+		A: Clone,
+	{
+		Self {
+			entries: GenVec::with_allocator(allocator.clone()),
+			heap: SizedVec::with_allocator(allocator),
+		}
+	}
+
+	/// Adds an item to the queue, and returns a handle to it.
+	pub fn push(&mut self, item: T) -> GenIdx<S> {
+		let pos = self.heap.len();
+		let handle = self.entries.insert(Entry { item, pos });
+		self.heap.push(handle);
+		self.sift_up(pos);
+
+		handle
+	}
+	/// The largest item in the queue, and its handle.
+	pub fn peek(&self) -> Option<(GenIdx<S>, &T)> {
+		let handle = *self.heap.get(S::ZERO)?;
+		Some((handle, &self.entries[handle].item))
+	}
+	/// Removes the largest item from the queue, and returns it with its
+	/// handle. The handle won't point to any item afterwards.
+	pub fn pop(&mut self) -> Option<(GenIdx<S>, T)> {
+		let handle = *self.heap.get(S::ZERO)?;
+		self.remove(handle).map(|item| (handle, item))
+	}
+
+	/// Gets the item `handle` points to, if it's still in the queue.
+	pub fn get(&self, handle: GenIdx<S>) -> Option<&T> {
+		self.entries.get(handle).map(|entry| &entry.item)
+	}
+	/// If `handle` points to an item that's still in the queue.
+	pub fn contains(&self, handle: GenIdx<S>) -> bool {
+		self.entries.contains(handle)
+	}
+	/// Calls `change` on the item `handle` points to, then moves the item to
+	/// its new place in the queue. Returns `false` if the item isn't in the
+	/// queue anymore.
+	pub fn change_priority(&mut self, handle: GenIdx<S>, change: impl FnOnce(&mut T)) -> bool {
+		let Some(entry) = self.entries.get_mut(handle) else {
+			return false;
+		};
+		change(&mut entry.item);
+		let pos = entry.pos;
+		self.sift_up(pos);
+		self.sift_down(pos);
+
+		true
+	}
+	/// Removes the item `handle` points to from the queue, and returns it.
+	/// Returns [`None`] if it was already popped or removed.
+	pub fn remove(&mut self, handle: GenIdx<S>) -> Option<T> {
+		let pos = self.entries.get(handle)?.pos;
+		let last = self.heap.len() - S::ONE;
+		if pos != last {
+			self.swap(pos, last);
+		}
+		self.heap.pop();
+		let item = self.entries.remove(handle)?.item;
+		if pos < self.heap.len() {
+			self.sift_up(pos);
+			self.sift_down(pos);
+		}
+
+		Some(item)
+	}
+	/// Removes every item from the queue. Existing handles are invalidated.
+	pub fn clear(&mut self) {
+		self.entries.clear();
+		self.heap.clear();
+	}
+
+	/// If the queue contains 0 items.
+	pub fn is_empty(&self) -> bool {
+		self.heap.is_empty()
+	}
+	/// How many items are in the queue.
+	pub const fn len(&self) -> S {
+		self.heap.len()
+	}
+	/// Iterates over every item in the queue, along with its handle, in no
+	/// particular order.
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = (GenIdx<S>, &T)> {
+		self.entries
+			.iter()
+			.map(|(handle, entry)| (handle, &entry.item))
+	}
+
+	fn item_at(&self, pos: S) -> &T {
+		&self.entries[self.heap[pos]].item
+	}
+	/// Swaps two positions in the heap, and updates their entries to match.
+	fn swap(&mut self, a: S, b: S) {
+		self.heap.as_slice_mut().swap(a.as_usize(), b.as_usize());
+		self.entries[self.heap[a]].pos = a;
+		self.entries[self.heap[b]].pos = b;
+	}
+	/// Moves the item at `pos` towards the top of the heap until its parent is
+	/// larger than it.
+	fn sift_up(&mut self, mut pos: S) {
+		while pos > S::ZERO {
+			let parent = (pos - S::ONE) / S::TWO;
+			if self.item_at(pos) <= self.item_at(parent) {
+				break;
+			}
+			self.swap(pos, parent);
+			pos = parent;
+		}
+	}
+	/// Moves the item at `pos` towards the bottom of the heap until its
+	/// children are smaller than it.
+	fn sift_down(&mut self, mut pos: S) {
+		let len = self.heap.len();
+		// Children that don't fit in `S` can't be in the heap
+		while let Some(left) = pos
+			.checked_mul(S::TWO)
+			.and_then(|pos| pos.checked_add(S::ONE))
+			&& left < len
+		{
+			let right = left + S::ONE;
+			let child = if right < len && self.item_at(right) > self.item_at(left) {
+				right
+			} else {
+				left
+			};
+			if self.item_at(child) <= self.item_at(pos) {
+				break;
+			}
+			self.swap(pos, child);
+			pos = child;
+		}
+	}
+}
+impl<T: Ord + Debug, S: const IndexSize, A: Allocator> Debug for IndexedHeap<T, S, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, core::cmp::Reverse};
+
+	#[test]
+	fn indexed_heap() {
+		let mut heap = IndexedHeap::<u32, u8>::new();
+		let handles = [5, 1, 8, 3, 9, 2].map(|item| heap.push(item));
+		assert_eq!(heap.len(), 6);
+		assert_eq!(heap.peek(), Some((handles[4], &9)));
+
+		assert!(heap.change_priority(handles[1], |item| *item = 10));
+		assert!(heap.change_priority(handles[2], |item| *item = 0));
+		assert_eq!(heap.remove(handles[0]), Some(5));
+		assert_eq!(heap.remove(handles[0]), None);
+		assert!(!heap.change_priority(handles[0], |_| unreachable!()));
+
+		let mut popped = Vec::new();
+		while let Some((handle, item)) = heap.pop() {
+			assert!(!heap.contains(handle));
+			popped.push(item);
+		}
+		assert_eq!(popped, [10, 9, 3, 2, 0]);
+	}
+
+	#[test]
+	fn indexed_heap_dijkstra() {
+		// Edges as (from, to, cost)
+		let edges = [(0, 1, 7), (0, 2, 2), (2, 1, 3), (1, 3, 1), (2, 3, 8)];
+		let mut dist = [u32::MAX; 4];
+		let mut queued = [None; 4];
+		let mut queue = IndexedHeap::<Reverse<(u32, usize)>>::new();
+
+		dist[0] = 0;
+		queued[0] = Some(queue.push(Reverse((0, 0))));
+		while let Some((_, Reverse((node_dist, node)))) = queue.pop() {
+			for &(_, to, cost) in edges.iter().filter(|edge| edge.0 == node) {
+				let new_dist = node_dist + cost;
+				if new_dist >= dist[to] {
+					continue;
+				}
+				dist[to] = new_dist;
+				match queued[to] {
+					Some(handle) if queue.contains(handle) => {
+						queue.change_priority(handle, |item| *item = Reverse((new_dist, to)));
+					}
+					_ => queued[to] = Some(queue.push(Reverse((new_dist, to)))),
+				}
+			}
+		}
+
+		assert_eq!(dist, [0, 5, 2, 6]);
+	}
+}
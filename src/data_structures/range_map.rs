@@ -0,0 +1,217 @@
+use crate::{
+	data_structures::BTreeMap,
+	lang::{DoubleEndedIterator, Integer, Range},
+};
+
+/// Maps ranges of integers to values. Ranges in the map never overlap;
+/// inserting a range overwrites any part of the map it overlaps, splitting
+/// ranges that are only partly covered.
+///
+/// ```rs
+/// let mut regions = RangeMap::new();
+/// regions.insert(0x1000..0x4000, Protection::ReadWrite);
+/// // Splits the first range into 0x1000..0x2000 and 0x3000..0x4000
+/// regions.insert(0x2000..0x3000, Protection::ReadOnly);
+///
+/// assert_eq!(regions.get(0x3800), Some(&Protection::ReadWrite));
+/// assert_eq!(regions.gaps(0..0x5000).collect::<Vec<_>>(), [0..0x1000, 0x4000..0x5000]);
+/// ```
+///
+/// Ranges are half-open, like [`Range`]: `0..10` contains `0`, but not `10`.
+/// Empty ranges are never stored.
+#[derive(Clone)]
+pub struct RangeMap<K: Integer, V> {
+	/// Maps the start of each range to its end and value.
+	ranges: BTreeMap<K, (K, V)>,
+}
+impl<K: Integer, V> Default for RangeMap<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<K: Integer, V> RangeMap<K, V> {
+	pub const fn new() -> Self {
+		Self {
+			ranges: BTreeMap::new(),
+		}
+	}
+
+	/// Maps every key in `range` to `value`, replacing any values those keys
+	/// were already mapped to.
+	pub fn insert(&mut self, range: Range<K>, value: V)
+	where
+		V: Clone,
+	{
+		if range.is_empty() {
+			return;
+		}
+
+		self.carve(&range);
+		self.ranges.insert(range.start, (range.end, value));
+	}
+	/// Unmaps every key in `range`.
+	pub fn remove(&mut self, range: Range<K>)
+	where
+		V: Clone,
+	{
+		if !range.is_empty() {
+			self.carve(&range);
+		}
+	}
+	/// Removes every key in `range` from the map, shortening or splitting
+	/// ranges that are partly in it. `range` must not be empty.
+	fn carve(&mut self, range: &Range<K>)
+	where
+		V: Clone,
+	{
+		// A range starting before `range` may overlap its start, or contain
+		// all of it
+		let mut tail = None;
+		if let Some((_, (end, value))) = self.ranges.range_mut(..range.start).next_back()
+			&& *end > range.start
+		{
+			if *end > range.end {
+				tail = Some((*end, value.clone()));
+			}
+			*end = range.start;
+		}
+		if let Some(tail) = tail {
+			self.ranges.insert(range.end, tail);
+			return;
+		}
+
+		// Ranges starting in `range` are removed, but may overlap its end
+		while let Some((&start, _)) = self.ranges.range(range.clone()).next() {
+			let (end, value) = self.ranges.remove(&start).unwrap();
+			if end > range.end {
+				self.ranges.insert(range.end, (end, value));
+			}
+		}
+	}
+	/// Removes every range from the map.
+	pub fn clear(&mut self) {
+		self.ranges.clear();
+	}
+
+	/// Gets the value `key` is mapped to.
+	pub fn get(&self, key: K) -> Option<&V> {
+		self.get_key_value(key).map(|(_, value)| value)
+	}
+	/// Gets the range containing `key`, and the value it's mapped to.
+	pub fn get_key_value(&self, key: K) -> Option<(Range<K>, &V)> {
+		let (&start, (end, value)) = self.ranges.range(..=key).next_back()?;
+		(key < *end).then_some((start..*end, value))
+	}
+	/// If `key` is in any range in the map.
+	pub fn contains_key(&self, key: K) -> bool {
+		self.get_key_value(key).is_some()
+	}
+
+	/// How many ranges are in the map.
+	pub fn len(&self) -> usize {
+		self.ranges.len()
+	}
+	/// If the map contains 0 ranges.
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	/// Iterates over every range in the map and its value, from lowest to
+	/// highest.
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = (Range<K>, &V)> {
+		self.ranges
+			.iter()
+			.map(|(&start, (end, value))| (start..*end, value))
+	}
+	/// Iterates over every range in the map that shares at least one key with
+	/// `range`, from lowest to highest. The ranges aren't clipped to `range`.
+	pub fn overlapping(&self, range: Range<K>) -> impl Iterator<Item = (Range<K>, &V)> {
+		let first = self
+			.ranges
+			.range(..range.start)
+			.next_back()
+			.filter(|(_, (end, _))| *end > range.start);
+		// `BTreeMap::range` panics if the range's end is before its start
+		let rest = self.ranges.range(range.start..range.end.max(range.start));
+
+		first
+			.into_iter()
+			.chain(rest)
+			.map(|(&start, (end, value))| (start..*end, value))
+	}
+	/// Iterates over every part of `within` that isn't in the map, from lowest
+	/// to highest.
+	pub fn gaps(&self, within: Range<K>) -> impl Iterator<Item = Range<K>> {
+		let mut cursor = within.start;
+		let end = within.end;
+
+		self.overlapping(within)
+			.map(|(range, _)| range)
+			.chain(Some(end..end))
+			.filter_map(move |range| {
+				let gap = cursor..range.start.min(end);
+				cursor = cursor.max(range.end);
+				(!gap.is_empty()).then_some(gap)
+			})
+	}
+}
+impl<K: Integer, V: Debug> Debug for RangeMap<K, V> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn range_map() {
+		let mut map = RangeMap::<u32, char>::new();
+		map.insert(10..40, 'a');
+		map.insert(20..30, 'b');
+		map.insert(5..12, 'c');
+		map.insert(0..0, 'x');
+		assert_eq!(
+			map.iter().collect::<Vec<_>>(),
+			[
+				(5..12, &'c'),
+				(12..20, &'a'),
+				(20..30, &'b'),
+				(30..40, &'a')
+			]
+		);
+
+		assert_eq!(map.get(4), None);
+		assert_eq!(map.get(5), Some(&'c'));
+		assert_eq!(map.get_key_value(35), Some((30..40, &'a')));
+		assert!(!map.contains_key(40));
+
+		// Overwrites the end of one range and the start of another
+		map.insert(25..35, 'd');
+		assert_eq!(
+			map.overlapping(21..26).collect::<Vec<_>>(),
+			[(20..25, &'b'), (25..35, &'d')]
+		);
+
+		map.remove(8..32);
+		assert_eq!(
+			map.iter().collect::<Vec<_>>(),
+			[(5..8, &'c'), (32..35, &'d'), (35..40, &'a')]
+		);
+		assert_eq!(map.gaps(0..50).collect::<Vec<_>>(), [0..5, 8..32, 40..50]);
+		assert_eq!(map.gaps(6..7).count(), 0);
+		assert_eq!(map.gaps(33..38).count(), 0);
+		assert!(map.gaps(36..45).eq(Some(40..45)));
+
+		map.clear();
+		assert!(map.is_empty());
+		assert!(map.gaps(1..3).eq(Some(1..3)));
+	}
+}
@@ -2,7 +2,7 @@ use crate::{
 	data_structures::IndexSize,
 	io::Writer,
 	lang::{
-		self, Integer,
+		self, Integer, Ordering, PhantomData,
 		iter::*,
 		mem::{self, Layout},
 		op::*,
@@ -44,6 +44,11 @@ impl<T, S: const IndexSize> SizedVec<T, S, GlobalAllocator> {
 	pub fn with_capacity(num_items: S) -> Self {
 		Self::with_allocator_and_capacity(GlobalAllocator, num_items)
 	}
+	/// Like [`SizedVec::with_capacity`], but returns an error if allocating
+	/// fails.
+	pub fn try_with_capacity(num_items: S) -> Result<Self, AllocError> {
+		Self::try_with_allocator_and_capacity(GlobalAllocator, num_items)
+	}
 }
 impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 	const BASE_ALLOC_COUNT: S = if size_of::<T>() == 1 {
@@ -68,13 +73,30 @@ impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 		}
 	}
 	pub fn with_allocator_and_capacity(allocator: A, num_items: S) -> Self {
+		Self::try_with_allocator_and_capacity(allocator, num_items).unwrap()
+	}
+	/// Like [`SizedVec::with_allocator_and_capacity`], but returns an error if
+	/// allocating fails.
+	pub fn try_with_allocator_and_capacity(allocator: A, num_items: S) -> Result<Self, AllocError> {
 		const { assert!(S::SIZE_BITS <= usize::SIZE_BITS) };
-		let base_ptr = allocator.allocate(Self::layout(num_items)).unwrap().cast();
-		Self {
-			capacity: S::ZERO,
+		let base_ptr = allocator.allocate(Self::layout(num_items))?.cast();
+		Ok(Self {
+			capacity: num_items,
 			len: S::ZERO,
 			base_ptr,
 			alloc: allocator,
+		})
+	}
+
+	/// Consumes the vector and returns its items as a slice that lives for the
+	/// rest of the program. The vector's allocation is never freed.
+	pub fn leak<'a>(self) -> &'a mut [T]
+	where
+		A: 'a,
+	{
+		let this = ManuallyDrop::new(self);
+		unsafe {
+			&mut *slice_from_raw_parts_mut(this.base_ptr.as_ptr().cast(), this.len.as_usize())
 		}
 	}
 
@@ -94,16 +116,21 @@ impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 		}
 	}
 }
-impl<T, S: const IndexSize, A: Allocator + Clone> Clone for SizedVec<T, S, A> {
+impl<T: Clone, S: const IndexSize, A: Allocator + Clone> Clone for SizedVec<T, S, A> {
 	fn clone(&self) -> Self {
 		let mut res = Self::with_allocator_and_capacity(self.alloc.clone(), self.len);
-		unsafe {
-			crate::lang::copy_nonoverlapping(self.as_ptr(), res.as_mut_ptr(), self.len.as_usize())
-		};
+		for item in self {
+			res.push(item.clone());
+		}
 
 		res
 	}
 }
+impl<T: Debug, S: const IndexSize, A: Allocator> Debug for SizedVec<T, S, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
 
 impl<T, S: const IndexSize, A: Allocator> Drop for SizedVec<T, S, A> {
 	fn drop(&mut self) {
@@ -115,7 +142,7 @@ impl<T, S: const IndexSize, A: Allocator> Drop for SizedVec<T, S, A> {
 		}
 		unsafe {
 			self.alloc
-				.deallocate(self.base_ptr.cast(), Self::layout(self.len))
+				.deallocate(self.base_ptr.cast(), Self::layout(self.capacity))
 		};
 	}
 }
@@ -160,6 +187,13 @@ impl From<SizedVecGrowthError> for SizedVecInsertError {
 		Self::GrowthError(value)
 	}
 }
+/// Lets `?` turn growth errors into [`AllocError`]s, for code that only cares
+/// that an allocation failed.
+impl From<SizedVecGrowthError> for AllocError {
+	fn from(_: SizedVecGrowthError) -> Self {
+		AllocError
+	}
+}
 
 impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 	pub fn push(&mut self, item: T) -> &mut T {
@@ -292,6 +326,7 @@ impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 					.map_err(|_| SizedVecReallocError::ReallocationFailed)?
 					.cast()
 			};
+			self.capacity = count;
 			Ok(())
 		} else {
 			Err(SizedVecReallocError::CannotShrink)
@@ -360,6 +395,72 @@ impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 		res
 	}
 
+	/// Removes the item at `idx` and returns it. Every item after it is shifted
+	/// one slot to the left; see [`SizedVec::swap_remove`] for a faster
+	/// alternative that doesn't preserve the order of items.
+	///
+	/// Panics if `idx` is out of bounds.
+	pub fn remove(&mut self, idx: S) -> T {
+		if idx >= self.len {
+			panic!("SizedVec::remove: index out of bounds");
+		}
+
+		unsafe {
+			let target = self.base_ptr.add(idx.as_usize());
+			let item = target.read().assume_init();
+			target
+				.add(1)
+				.copy_to(target, (self.len - idx - S::ONE).as_usize());
+			self.len -= S::ONE;
+			item
+		}
+	}
+	/// Removes the item at `idx` and returns it. The last item in the vector is
+	/// moved into its slot.
+	///
+	/// Panics if `idx` is out of bounds.
+	pub fn swap_remove(&mut self, idx: S) -> T {
+		if idx >= self.len {
+			panic!("SizedVec::swap_remove: index out of bounds");
+		}
+
+		let last = self.len - S::ONE;
+		unsafe {
+			let target = self.base_ptr.add(idx.as_usize());
+			let item = target.read().assume_init();
+			if idx != last {
+				self.base_ptr
+					.add(last.as_usize())
+					.copy_to_nonoverlapping(target, 1);
+			}
+			self.len = last;
+			item
+		}
+	}
+	/// Drops every item at or after `len`, so the vector has at most `len`
+	/// items. This doesn't change the vector's capacity.
+	pub fn truncate(&mut self, len: S) {
+		if len >= self.len {
+			return;
+		}
+
+		let tail = slice_from_raw_parts_mut(
+			unsafe { self.base_ptr.add(len.as_usize()) }
+				.as_ptr()
+				.cast::<T>(),
+			(self.len - len).as_usize(),
+		);
+		// Shorten the vector first, so it doesn't drop the items again if
+		// one of them panics while being dropped
+		self.len = len;
+		unsafe { mem::drop_in_place(tail) };
+	}
+	/// Drops every item in the vector. This doesn't change the vector's
+	/// capacity.
+	pub fn clear(&mut self) {
+		self.truncate(S::ZERO);
+	}
+
 	/// If the vector contains 0 elements.
 	pub fn is_empty(&self) -> bool {
 		self.len == S::ZERO
@@ -768,6 +869,73 @@ impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
 	}
 }
 
+//
+//
+// Sorting & Searching
+//
+//
+
+impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
+	/// Sorts the vector with `compare`. Items that compare as equal may be
+	/// reordered.
+	pub fn sort_unstable_by(&mut self, compare: impl FnMut(&T, &T) -> Ordering) {
+		self.as_slice_mut().sort_unstable_by(compare);
+	}
+	/// Sorts the vector by the key `key` returns for each item. Items with
+	/// equal keys stay in the same order.
+	pub fn sort_by_key<K: Ord>(&mut self, key: impl FnMut(&T) -> K) {
+		self.as_slice_mut().sort_by_key(key);
+	}
+
+	/// Searches this sorted vector for an item. `compare` should return how an
+	/// item compares to the one being searched for.
+	///
+	/// Returns `Ok` with the index of a matching item, or `Err` with the index
+	/// the item could be inserted at to keep the vector sorted.
+	pub fn binary_search_by(&self, compare: impl FnMut(&T) -> Ordering) -> Result<S, S> {
+		self.as_slice()
+			.binary_search_by(compare)
+			.map(S::usize_as_self)
+			.map_err(S::usize_as_self)
+	}
+	/// Searches this sorted vector for `item`. See
+	/// [`SizedVec::binary_search_by`].
+	pub fn binary_search(&self, item: &T) -> Result<S, S>
+	where
+		T: Ord,
+	{
+		self.binary_search_by(|probe| probe.cmp(item))
+	}
+	/// Searches this vector, which is sorted by the key `key` returns, for an
+	/// item with the key `target`. See [`SizedVec::binary_search_by`].
+	pub fn binary_search_by_key<K: Ord>(
+		&self,
+		target: &K,
+		mut key: impl FnMut(&T) -> K,
+	) -> Result<S, S> {
+		self.binary_search_by(|probe| key(probe).cmp(target))
+	}
+
+	/// Returns the indices of the vector's items in sorted order, without
+	/// moving the items; `vec[vec.sort_indices()[0]]` is the smallest item.
+	/// Items that compare as equal stay in the same order.
+	pub fn sort_indices(&self) -> SizedVec<S, S>
+	where
+		T: Ord,
+	{
+		self.sort_indices_by(T::cmp)
+	}
+	/// Like [`SizedVec::sort_indices`], but compares items with `compare`.
+	pub fn sort_indices_by(&self, mut compare: impl FnMut(&T, &T) -> Ordering) -> SizedVec<S, S> {
+		let mut indices = SizedVec::with_capacity(self.len);
+		indices.extend(S::ZERO..self.len);
+		indices
+			.as_slice_mut()
+			.sort_by(|a, b| unsafe { compare(self.get_unchecked(*a), self.get_unchecked(*b)) });
+		indices
+	}
+}
+
 impl<T, S: const IndexSize, A: Allocator, SO: SizedVecIndexOp<T, S, A>> Index<SO>
 	for SizedVec<T, S, A>
 {
@@ -787,11 +955,323 @@ impl<T, S: const IndexSize, A: Allocator, SO: SizedVecIndexOp<T, S, A>> IndexMut
 
 //
 //
-// Iterator
+// Iterators
 //
 //
 
-// TODO
+impl<T, S: const IndexSize, A: Allocator> SizedVec<T, S, A> {
+	/// Iterates over references to the vector's items.
+	pub const fn iter(&self) -> Iter<'_, T, S> {
+		Iter {
+			ptr: self.base_ptr.cast(),
+			len: self.len,
+			_lifetime: PhantomData,
+		}
+	}
+	/// Iterates over mutable references to the vector's items.
+	pub const fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+		IterMut {
+			ptr: self.base_ptr.cast(),
+			len: self.len,
+			_lifetime: PhantomData,
+		}
+	}
+
+	/// Iterates over the vector in slices of `size` items. The last slice is
+	/// shorter if the vector's length isn't a multiple of `size`.
+	///
+	/// Panics if `size` is 0.
+	pub fn chunks(&self, size: S) -> Chunks<'_, T, S> {
+		if size == S::ZERO {
+			panic!("SizedVec::chunks: chunk size must be non-zero");
+		}
+		Chunks { slice: self, size }
+	}
+	/// Iterates over every run of `size` consecutive items in the vector. The
+	/// runs overlap; a vector of 4 items has 2 windows of size 3.
+	///
+	/// Panics if `size` is 0.
+	pub fn windows(&self, size: S) -> Windows<'_, T, S> {
+		if size == S::ZERO {
+			panic!("SizedVec::windows: window size must be non-zero");
+		}
+		Windows { slice: self, size }
+	}
+
+	/// Splits the vector's items into the ones before `mid` and the ones at or
+	/// after it.
+	///
+	/// Panics if `mid` is greater than the vector's length.
+	pub fn split_at(&self, mid: S) -> (&[T], &[T]) {
+		self.as_slice().split_at(mid.as_usize())
+	}
+	/// Mutable version of [`SizedVec::split_at`].
+	pub fn split_at_mut(&mut self, mid: S) -> (&mut [T], &mut [T]) {
+		self.as_slice_mut().split_at_mut(mid.as_usize())
+	}
+}
+impl<'a, T, S: const IndexSize, A: Allocator> IntoIterator for &'a SizedVec<T, S, A> {
+	type Item = &'a T;
+	type IntoIter = Iter<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+impl<'a, T, S: const IndexSize, A: Allocator> IntoIterator for &'a mut SizedVec<T, S, A> {
+	type Item = &'a mut T;
+	type IntoIter = IterMut<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+/// Iterator from [`SizedVec::iter`]. Unlike [`slice::Iter`], this tracks how
+/// many items are left with the vector's index type.
+///
+/// [`slice::Iter`]: core::slice::Iter
+pub struct Iter<'a, T, S: const IndexSize = usize> {
+	ptr: NonNull<T>,
+	len: S,
+	_lifetime: PhantomData<&'a T>,
+}
+impl<'a, T, S: const IndexSize> Iter<'a, T, S> {
+	/// The items that haven't been iterated over yet.
+	pub const fn as_slice(&self) -> &'a [T] {
+		unsafe { &*slice_from_raw_parts(self.ptr.as_ptr(), self.len.as_usize()) }
+	}
+}
+impl<'a, T, S: const IndexSize> Iterator for Iter<'a, T, S> {
+	type Item = &'a T;
+
+	fn next(&mut self) -> Option<&'a T> {
+		if self.len == S::ZERO {
+			return None;
+		}
+
+		let item = unsafe { self.ptr.as_ref() };
+		self.ptr = unsafe { self.ptr.add(1) };
+		self.len -= S::ONE;
+		Some(item)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len.as_usize(), Some(self.len.as_usize()))
+	}
+}
+impl<T, S: const IndexSize> DoubleEndedIterator for Iter<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.len == S::ZERO {
+			return None;
+		}
+
+		self.len -= S::ONE;
+		Some(unsafe { self.ptr.add(self.len.as_usize()).as_ref() })
+	}
+}
+impl<T, S: const IndexSize> ExactSizeIterator for Iter<'_, T, S> {}
+impl<T, S: const IndexSize> FusedIterator for Iter<'_, T, S> {}
+impl<T, S: const IndexSize> Clone for Iter<'_, T, S> {
+	fn clone(&self) -> Self {
+		Self { ..*self }
+	}
+}
+impl<T: Debug, S: const IndexSize> Debug for Iter<'_, T, S> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("Iter").field(&self.as_slice()).finish()
+	}
+}
+unsafe impl<T: Sync, S: const IndexSize> Send for Iter<'_, T, S> {}
+unsafe impl<T: Sync, S: const IndexSize> Sync for Iter<'_, T, S> {}
+
+/// Iterator from [`SizedVec::iter_mut`]. Unlike [`slice::IterMut`], this
+/// tracks how many items are left with the vector's index type.
+///
+/// [`slice::IterMut`]: core::slice::IterMut
+pub struct IterMut<'a, T, S: const IndexSize = usize> {
+	ptr: NonNull<T>,
+	len: S,
+	_lifetime: PhantomData<&'a mut T>,
+}
+impl<'a, T, S: const IndexSize> IterMut<'a, T, S> {
+	/// The items that haven't been iterated over yet.
+	pub const fn as_slice(&self) -> &[T] {
+		unsafe { &*slice_from_raw_parts(self.ptr.as_ptr(), self.len.as_usize()) }
+	}
+	/// Mutable references to the items that haven't been iterated over yet.
+	pub const fn into_slice(self) -> &'a mut [T] {
+		unsafe { &mut *slice_from_raw_parts_mut(self.ptr.as_ptr(), self.len.as_usize()) }
+	}
+}
+impl<'a, T, S: const IndexSize> Iterator for IterMut<'a, T, S> {
+	type Item = &'a mut T;
+
+	fn next(&mut self) -> Option<&'a mut T> {
+		if self.len == S::ZERO {
+			return None;
+		}
+
+		let item = unsafe { self.ptr.as_mut() };
+		self.ptr = unsafe { self.ptr.add(1) };
+		self.len -= S::ONE;
+		Some(item)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len.as_usize(), Some(self.len.as_usize()))
+	}
+}
+impl<T, S: const IndexSize> DoubleEndedIterator for IterMut<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.len == S::ZERO {
+			return None;
+		}
+
+		self.len -= S::ONE;
+		Some(unsafe { self.ptr.add(self.len.as_usize()).as_mut() })
+	}
+}
+impl<T, S: const IndexSize> ExactSizeIterator for IterMut<'_, T, S> {}
+impl<T, S: const IndexSize> FusedIterator for IterMut<'_, T, S> {}
+impl<T: Debug, S: const IndexSize> Debug for IterMut<'_, T, S> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_tuple("IterMut").field(&self.as_slice()).finish()
+	}
+}
+unsafe impl<T: Send, S: const IndexSize> Send for IterMut<'_, T, S> {}
+unsafe impl<T: Sync, S: const IndexSize> Sync for IterMut<'_, T, S> {}
+
+/// Iterator from [`SizedVec::chunks`].
+#[derive(Debug)]
+pub struct Chunks<'a, T, S: const IndexSize = usize> {
+	slice: &'a [T],
+	size: S,
+}
+impl<'a, T, S: const IndexSize> Iterator for Chunks<'a, T, S> {
+	type Item = &'a [T];
+
+	fn next(&mut self) -> Option<&'a [T]> {
+		if self.slice.is_empty() {
+			return None;
+		}
+
+		let (chunk, rest) = self
+			.slice
+			.split_at(self.size.as_usize().min(self.slice.len()));
+		self.slice = rest;
+		Some(chunk)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.slice.len().div_ceil(self.size.as_usize());
+		(len, Some(len))
+	}
+}
+impl<T, S: const IndexSize> DoubleEndedIterator for Chunks<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.slice.is_empty() {
+			return None;
+		}
+
+		// Only the last chunk can be short
+		let last_len = match self.slice.len() % self.size.as_usize() {
+			0 => self.size.as_usize(),
+			len => len,
+		};
+		let (rest, chunk) = self.slice.split_at(self.slice.len() - last_len);
+		self.slice = rest;
+		Some(chunk)
+	}
+}
+impl<T, S: const IndexSize> ExactSizeIterator for Chunks<'_, T, S> {}
+impl<T, S: const IndexSize> FusedIterator for Chunks<'_, T, S> {}
+impl<T, S: const IndexSize> Clone for Chunks<'_, T, S> {
+	fn clone(&self) -> Self {
+		Self { ..*self }
+	}
+}
+
+/// Iterator from [`SizedVec::windows`].
+#[derive(Debug)]
+pub struct Windows<'a, T, S: const IndexSize = usize> {
+	slice: &'a [T],
+	size: S,
+}
+impl<'a, T, S: const IndexSize> Iterator for Windows<'a, T, S> {
+	type Item = &'a [T];
+
+	fn next(&mut self) -> Option<&'a [T]> {
+		let window = self.slice.get(..self.size.as_usize())?;
+		self.slice = &self.slice[1..];
+		Some(window)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = (self.slice.len() + 1).saturating_sub(self.size.as_usize());
+		(len, Some(len))
+	}
+}
+impl<T, S: const IndexSize> DoubleEndedIterator for Windows<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let start = self.slice.len().checked_sub(self.size.as_usize())?;
+		let window = &self.slice[start..];
+		self.slice = &self.slice[..self.slice.len() - 1];
+		Some(window)
+	}
+}
+impl<T, S: const IndexSize> ExactSizeIterator for Windows<'_, T, S> {}
+impl<T, S: const IndexSize> FusedIterator for Windows<'_, T, S> {}
+impl<T, S: const IndexSize> Clone for Windows<'_, T, S> {
+	fn clone(&self) -> Self {
+		Self { ..*self }
+	}
+}
+
+macro_rules! impl_enumerate_sized {
+	($($iter:ident)*) => {
+		$(
+			impl<T, S: const IndexSize> $iter<'_, T, S> {
+				/// Like [`Iterator::enumerate`], but counts with the vector's
+				/// index type instead of [`usize`].
+				pub fn enumerate_sized(self) -> EnumerateSized<Self, S> {
+					EnumerateSized {
+						iter: self,
+						idx: S::ZERO,
+					}
+				}
+			}
+		)*
+	};
+}
+impl_enumerate_sized!(Iter IterMut Chunks Windows);
+
+/// Iterator from `enumerate_sized` on [`SizedVec`]'s iterators. Yields each
+/// item along with its index, as the vector's index type.
+#[derive(Clone, Debug)]
+pub struct EnumerateSized<I, S: const IndexSize> {
+	iter: I,
+	idx: S,
+}
+impl<I: Iterator, S: const IndexSize> Iterator for EnumerateSized<I, S> {
+	type Item = (S, I::Item);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let item = self.iter.next()?;
+		let idx = self.idx;
+		self.idx += S::ONE;
+		Some((idx, item))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+impl<I: DoubleEndedIterator + ExactSizeIterator, S: const IndexSize> DoubleEndedIterator
+	for EnumerateSized<I, S>
+{
+	fn next_back(&mut self) -> Option<Self::Item> {
+		let item = self.iter.next_back()?;
+		Some((self.idx + S::usize_as_self(self.iter.len()), item))
+	}
+}
+impl<I: ExactSizeIterator, S: const IndexSize> ExactSizeIterator for EnumerateSized<I, S> {}
+impl<I: FusedIterator, S: const IndexSize> FusedIterator for EnumerateSized<I, S> {}
 
 //
 //
@@ -801,9 +1281,109 @@ impl<T, S: const IndexSize, A: Allocator, SO: SizedVecIndexOp<T, S, A>> IndexMut
 
 #[cfg(test)]
 mod tests {
+	use super::*;
+
 	#[test]
 	fn sized_vec_idx_usize_limit() {
 		// When uncommented the below should fail to compile.
 		// let vec = SizedVec::<(), u128>::default();
 	}
+
+	#[test]
+	fn iterators() {
+		let mut vec = SizedVec::<u32, u8>::new();
+		vec.extend_slice(&[1, 2, 3, 4, 5]);
+		for item in &mut vec {
+			*item *= 2;
+		}
+		assert_eq!(vec.iter().copied().collect::<Vec<_>>(), [2, 4, 6, 8, 10]);
+
+		let mut iter = vec.iter().enumerate_sized();
+		assert_eq!(iter.next(), Some((0_u8, &2)));
+		assert_eq!(iter.next_back(), Some((4_u8, &10)));
+		assert_eq!(iter.len(), 3);
+		assert_eq!(iter.next(), Some((1_u8, &4)));
+
+		let chunks = vec.chunks(2).collect::<Vec<_>>();
+		assert_eq!(chunks, [&[2, 4][..], &[6, 8], &[10]]);
+		let chunks = vec.chunks(2).rev().collect::<Vec<_>>();
+		assert_eq!(chunks, [&[10][..], &[6, 8], &[2, 4]]);
+		assert_eq!(vec.chunks(2).len(), 3);
+
+		let windows = vec.windows(3).enumerate_sized().collect::<Vec<_>>();
+		assert_eq!(
+			windows,
+			[(0_u8, &[2, 4, 6][..]), (1, &[4, 6, 8]), (2, &[6, 8, 10])]
+		);
+		assert_eq!(vec.windows(3).next_back(), Some(&[6, 8, 10][..]));
+		assert_eq!(vec.windows(6).next(), None);
+
+		assert_eq!(vec.split_at(2), (&[2, 4][..], &[6, 8, 10][..]));
+	}
+
+	#[test]
+	fn removal() {
+		let mut vec = SizedVec::<String, u8>::new();
+		for item in ["a", "b", "c", "d", "e"] {
+			vec.push(String::from(item));
+		}
+
+		assert_eq!(vec.remove(1), "b");
+		assert_eq!(vec.as_slice(), ["a", "c", "d", "e"]);
+		assert_eq!(vec.swap_remove(0), "a");
+		assert_eq!(vec.as_slice(), ["e", "c", "d"]);
+		assert_eq!(vec.swap_remove(2), "d");
+		assert_eq!(vec.as_slice(), ["e", "c"]);
+
+		let clone = vec.clone();
+		vec.truncate(1);
+		assert_eq!(vec.as_slice(), ["e"]);
+		vec.clear();
+		assert!(vec.is_empty());
+		assert_eq!(clone.as_slice(), ["e", "c"]);
+	}
+
+	#[test]
+	fn sorting() {
+		let mut vec = SizedVec::<i32, u16>::new();
+		vec.extend_slice(&[30, -10, 20, 10, -20]);
+
+		let indices = vec.sort_indices();
+		assert_eq!(indices.as_slice(), [4, 1, 3, 2, 0]);
+		assert_eq!(vec.as_slice(), [30, -10, 20, 10, -20]);
+		let indices = vec.sort_indices_by(|a, b| a.abs().cmp(&b.abs()));
+		assert_eq!(indices.as_slice(), [1, 3, 2, 4, 0]);
+
+		vec.sort_by_key(|item| item.abs());
+		assert_eq!(vec.as_slice(), [-10, 10, 20, -20, 30]);
+		vec.sort_unstable_by(|a, b| b.cmp(a));
+		assert_eq!(vec.as_slice(), [30, 20, 10, -10, -20]);
+
+		assert_eq!(vec.binary_search_by(|probe| 10.cmp(probe)), Ok(2_u16));
+		assert_eq!(vec.binary_search_by(|probe| 0.cmp(probe)), Err(3_u16));
+		vec.sort_unstable_by(Ord::cmp);
+		assert_eq!(vec.binary_search(&-20), Ok(0));
+		assert_eq!(vec.binary_search_by_key(&40, |item| item + 10), Ok(4));
+	}
+
+	#[test]
+	fn fallible_allocation() {
+		assert_eq!(
+			SizedVec::<u8>::try_with_capacity(4).map(|vec| vec.capacity()),
+			Ok(4)
+		);
+
+		let arena = VirtualMemoryArena::new(MemoryAmount::kibibytes(4)).unwrap();
+		assert!(SizedVec::<u8, u32, _>::try_with_allocator_and_capacity(&arena, 1 << 20).is_err());
+		let mut vec = SizedVec::<u8, u32, _>::try_with_allocator_and_capacity(&arena, 16).unwrap();
+		vec.try_extend_slice(&[0; 16]).unwrap();
+
+		// Growth errors can be turned into `AllocError`s with `?`
+		let mut grow = || -> Result<(), AllocError> {
+			vec.try_extend_slice(&[0; 8192])?;
+			Ok(())
+		};
+		assert_eq!(grow(), Err(AllocError));
+		assert_eq!(vec.len(), 16);
+	}
 }
@@ -0,0 +1,399 @@
+use crate::{
+	data_structures::{
+		IndexSize,
+		sized_vec::{self, EnumerateSized},
+	},
+	lang::{
+		DoubleEndedIterator, ExactSizeIterator, Extend, FusedIterator, Index, IndexMut, Integer,
+		Ordering, Range,
+	},
+	prelude::*,
+};
+
+/// A type that can index into a [`TypedVec`]. Use [`typed_vec_idx`] to make
+/// one.
+pub trait TypedVecIndex: Clone + Copy {
+	type Index: const IndexSize;
+
+	fn raw(self) -> Self::Index;
+	/// # Safety
+	///
+	/// Index types may assume they're only made from indices into the vector
+	/// they're used with.
+	unsafe fn from_raw(raw: Self::Index) -> Self;
+}
+
+/// A [`SizedVec`] that's indexed with a custom type, instead of an integer.
+/// This makes it possible to give each vector its own index type, so indices
+/// for one vector can't accidentally be used with another.
+///
+/// ```rs
+/// typed_vec_idx!(TextureId: u16);
+///
+/// let mut textures = TypedVec::<Texture, TextureId>::new();
+/// let grass = textures.push_and_get_key(Texture::load("grass.png"));
+/// draw(&textures[grass]);
+/// ```
+///
+/// Indices are positions in the vector, so removing an item (other than the
+/// last one) or sorting the vector changes which item they refer to. See
+/// [`GenVec`] for a vector whose indices are invalidated when their item is
+/// removed.
+///
+/// [`GenVec`]: crate::data_structures::GenVec
+pub struct TypedVec<T, S: TypedVecIndex, A: Allocator = GlobalAllocator>(SizedVec<T, S::Index, A>);
+impl<T, S: TypedVecIndex> Default for TypedVec<T, S, GlobalAllocator> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<T, S: TypedVecIndex> TypedVec<T, S, GlobalAllocator> {
+	pub const fn new() -> Self {
+		Self(SizedVec::new())
+	}
+	pub fn with_capacity(num_items: S::Index) -> Self {
+		Self(SizedVec::with_capacity(num_items))
+	}
+}
+impl<T, S: TypedVecIndex, A: Allocator> TypedVec<T, S, A> {
+	pub const fn with_allocator(allocator: A) -> Self {
+		Self(SizedVec::with_allocator(allocator))
+	}
+	pub fn with_allocator_and_capacity(allocator: A, num_items: S::Index) -> Self {
+		Self(SizedVec::with_allocator_and_capacity(allocator, num_items))
+	}
+
+	pub fn get(&self, idx: S) -> Option<&T> {
+		self.0.get(idx.raw())
+	}
+	pub fn get_mut(&mut self, idx: S) -> Option<&mut T> {
+		self.0.get_mut(idx.raw())
+	}
+	/// Gets the item at `idx`. If `idx` is past the end of the vector, `make`
+	/// is called to push new items until it isn't.
+	///
+	/// This is useful for storing extra data about indices from another
+	/// [`TypedVec`], like a component for an entity.
+	pub fn get_or_insert_with(&mut self, idx: S, mut make: impl FnMut() -> T) -> &mut T {
+		let raw = idx.raw();
+		if raw >= self.0.len() {
+			self.0
+				.ensure_additional_capacity(raw - self.0.len() + <S::Index as Integer>::ONE)
+				.unwrap();
+			while self.0.len() <= raw {
+				self.0.push(make());
+			}
+		}
+
+		unsafe { self.0.get_mut_unchecked(raw) }
+	}
+
+	pub fn push(&mut self, item: T) -> &mut T {
+		self.0.push(item)
+	}
+	/// Pushes `item` to the end of the vector, and returns its index.
+	pub fn push_and_get_key(&mut self, item: T) -> S {
+		let idx = self.0.len();
+		self.0.push(item);
+		unsafe { S::from_raw(idx) }
+	}
+
+	/// Removes the last item from the vector, and returns it, as long as the
+	/// vector isn't empty.
+	pub fn pop(&mut self) -> Option<T> {
+		self.0.pop()
+	}
+	/// Removes the item at `idx` and returns it. Every item after it is shifted
+	/// one slot to the left, so their indices change.
+	///
+	/// Panics if `idx` is out of bounds.
+	pub fn remove(&mut self, idx: S) -> T {
+		self.0.remove(idx.raw())
+	}
+	/// Removes the item at `idx` and returns it. The last item in the vector is
+	/// moved into its slot, so `idx` will refer to that item afterwards.
+	///
+	/// Panics if `idx` is out of bounds.
+	pub fn swap_remove(&mut self, idx: S) -> T {
+		self.0.swap_remove(idx.raw())
+	}
+	/// Drops every item at or after index `len`.
+	pub fn truncate(&mut self, len: S::Index) {
+		self.0.truncate(len);
+	}
+	/// Drops every item in the vector.
+	pub fn clear(&mut self) {
+		self.0.clear();
+	}
+
+	/// If the vector contains 0 elements.
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+	/// How many elements the vector has.
+	pub const fn len(&self) -> S::Index {
+		self.0.len()
+	}
+	/// How many elements the vector can store without reallocating.
+	pub const fn capacity(&self) -> S::Index {
+		self.0.capacity()
+	}
+
+	pub const fn as_slice(&self) -> &[T] {
+		self.0.as_slice()
+	}
+	pub const fn as_slice_mut(&mut self) -> &mut [T] {
+		self.0.as_slice_mut()
+	}
+	/// The [`SizedVec`] this vector stores its items in.
+	pub const fn as_sized_vec(&self) -> &SizedVec<T, S::Index, A> {
+		&self.0
+	}
+
+	/// Iterates over every index in the vector.
+	pub fn keys(&self) -> Keys<S> {
+		Keys(<S::Index as Integer>::ZERO..self.0.len())
+	}
+	/// Iterates over every item in the vector, along with its index.
+	pub fn iter(&self) -> Iter<'_, T, S> {
+		Iter(self.0.iter().enumerate_sized())
+	}
+	/// Iterates over mutable references to every item in the vector, along
+	/// with its index.
+	pub fn iter_mut(&mut self) -> IterMut<'_, T, S> {
+		IterMut(self.0.iter_mut().enumerate_sized())
+	}
+	/// Iterates over every item in the vector, without their indices.
+	pub const fn values(&self) -> sized_vec::Iter<'_, T, S::Index> {
+		self.0.iter()
+	}
+	/// Iterates over mutable references to every item in the vector, without
+	/// their indices.
+	pub const fn values_mut(&mut self) -> sized_vec::IterMut<'_, T, S::Index> {
+		self.0.iter_mut()
+	}
+
+	/// Sorts the vector with `compare`. Items that compare as equal may be
+	/// reordered.
+	///
+	/// This moves items, so existing indices will refer to different items
+	/// afterwards. [`TypedVec::sort_indices_by`] sorts without moving them.
+	pub fn sort_unstable_by(&mut self, compare: impl FnMut(&T, &T) -> Ordering) {
+		self.0.sort_unstable_by(compare);
+	}
+	/// Sorts the vector by the key `key` returns for each item. Items with
+	/// equal keys stay in the same order.
+	///
+	/// This moves items, so existing indices will refer to different items
+	/// afterwards. [`TypedVec::sort_indices_by`] sorts without moving them.
+	pub fn sort_by_key<K: Ord>(&mut self, key: impl FnMut(&T) -> K) {
+		self.0.sort_by_key(key);
+	}
+	/// Searches this sorted vector for an item. See
+	/// [`SizedVec::binary_search_by`].
+	pub fn binary_search_by(&self, compare: impl FnMut(&T) -> Ordering) -> Result<S, S> {
+		match self.0.binary_search_by(compare) {
+			Ok(idx) => Ok(unsafe { S::from_raw(idx) }),
+			Err(idx) => Err(unsafe { S::from_raw(idx) }),
+		}
+	}
+	/// Returns the indices of the vector's items in sorted order, without
+	/// moving the items. Items that compare as equal stay in the same
+	/// order.
+	pub fn sort_indices_by(
+		&self,
+		mut compare: impl FnMut(&T, &T) -> Ordering,
+	) -> SizedVec<S, S::Index> {
+		let mut indices = SizedVec::with_capacity(self.0.len());
+		indices.extend(self.keys());
+		indices.as_slice_mut().sort_by(|a: &S, b: &S| unsafe {
+			compare(self.0.get_unchecked(a.raw()), self.0.get_unchecked(b.raw()))
+		});
+		indices
+	}
+}
+impl<T, S: TypedVecIndex, A: Allocator> Index<S> for TypedVec<T, S, A> {
+	type Output = T;
+
+	fn index(&self, idx: S) -> &T {
+		&self.0[idx.raw()]
+	}
+}
+impl<T, S: TypedVecIndex, A: Allocator> IndexMut<S> for TypedVec<T, S, A> {
+	fn index_mut(&mut self, idx: S) -> &mut T {
+		&mut self.0[idx.raw()]
+	}
+}
+impl<T: Clone, S: TypedVecIndex, A: Allocator + Clone> Clone for TypedVec<T, S, A> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+impl<T: Debug, S: TypedVecIndex + Debug, A: Allocator> Debug for TypedVec<T, S, A> {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+impl<'a, T, S: TypedVecIndex, A: Allocator> IntoIterator for &'a TypedVec<T, S, A> {
+	type Item = (S, &'a T);
+	type IntoIter = Iter<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+impl<'a, T, S: TypedVecIndex, A: Allocator> IntoIterator for &'a mut TypedVec<T, S, A> {
+	type Item = (S, &'a mut T);
+	type IntoIter = IterMut<'a, T, S>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+/// Iterator from [`TypedVec::keys`].
+#[derive(Clone, Debug)]
+pub struct Keys<S: TypedVecIndex>(Range<S::Index>);
+impl<S: TypedVecIndex> Iterator for Keys<S> {
+	type Item = S;
+
+	fn next(&mut self) -> Option<S> {
+		self.0.next().map(|idx| unsafe { S::from_raw(idx) })
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+impl<S: TypedVecIndex> DoubleEndedIterator for Keys<S> {
+	fn next_back(&mut self) -> Option<S> {
+		self.0.next_back().map(|idx| unsafe { S::from_raw(idx) })
+	}
+}
+impl<S: TypedVecIndex> ExactSizeIterator for Keys<S> {}
+impl<S: TypedVecIndex> FusedIterator for Keys<S> {}
+
+/// Iterator from [`TypedVec::iter`].
+#[derive(Debug)]
+pub struct Iter<'a, T, S: TypedVecIndex>(
+	EnumerateSized<sized_vec::Iter<'a, T, S::Index>, S::Index>,
+);
+impl<'a, T, S: TypedVecIndex> Iterator for Iter<'a, T, S> {
+	type Item = (S, &'a T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0
+			.next()
+			.map(|(idx, item)| (unsafe { S::from_raw(idx) }, item))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+impl<T, S: TypedVecIndex> DoubleEndedIterator for Iter<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|(idx, item)| (unsafe { S::from_raw(idx) }, item))
+	}
+}
+impl<T, S: TypedVecIndex> ExactSizeIterator for Iter<'_, T, S> {}
+impl<T, S: TypedVecIndex> FusedIterator for Iter<'_, T, S> {}
+impl<T, S: TypedVecIndex> Clone for Iter<'_, T, S> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+/// Iterator from [`TypedVec::iter_mut`].
+#[derive(Debug)]
+pub struct IterMut<'a, T, S: TypedVecIndex>(
+	EnumerateSized<sized_vec::IterMut<'a, T, S::Index>, S::Index>,
+);
+impl<'a, T, S: TypedVecIndex> Iterator for IterMut<'a, T, S> {
+	type Item = (S, &'a mut T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0
+			.next()
+			.map(|(idx, item)| (unsafe { S::from_raw(idx) }, item))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+impl<T, S: TypedVecIndex> DoubleEndedIterator for IterMut<'_, T, S> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0
+			.next_back()
+			.map(|(idx, item)| (unsafe { S::from_raw(idx) }, item))
+	}
+}
+impl<T, S: TypedVecIndex> ExactSizeIterator for IterMut<'_, T, S> {}
+impl<T, S: TypedVecIndex> FusedIterator for IterMut<'_, T, S> {}
+
+#[macro_export]
+macro_rules! typed_vec_idx {
+	($($ty:ident: $size:ty),*) => {
+		$(
+		#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+		pub struct $ty($size);
+		impl $crate::data_structures::typed_vec::TypedVecIndex for $ty {
+			type Index = $size;
+
+			fn raw(self) -> Self::Index {
+				self.0
+			}
+			unsafe fn from_raw(raw: Self::Index) -> Self {
+				Self(raw)
+			}
+		}
+		)*
+	};
+}
+pub use crate::typed_vec_idx;
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	typed_vec_idx!(Id: u16);
+
+	#[test]
+	fn typed_vec() {
+		let mut vec = TypedVec::<&str, Id>::new();
+		let c = vec.push_and_get_key("c");
+		let a = vec.push_and_get_key("a");
+		vec.push("b");
+		assert_eq!(vec.len(), 3);
+		assert_eq!((vec[c], vec[a]), ("c", "a"));
+
+		assert_eq!(vec.keys().collect::<Vec<_>>(), [Id(0), Id(1), Id(2)]);
+		assert_eq!(vec.iter().next_back(), Some((Id(2), &"b")));
+		for (idx, item) in &mut vec {
+			if idx == a {
+				*item = "aa";
+			}
+		}
+		assert_eq!(vec.values().copied().collect::<Vec<_>>(), ["c", "aa", "b"]);
+
+		let indices = vec.sort_indices_by(|a, b| a.cmp(b));
+		assert_eq!(indices.as_slice(), [Id(1), Id(2), Id(0)]);
+		vec.sort_unstable_by(|a, b| a.cmp(b));
+		assert_eq!(vec.binary_search_by(|probe| probe.cmp(&"b")), Ok(Id(1)));
+		assert_eq!(vec.binary_search_by(|probe| probe.cmp(&"bb")), Err(Id(2)));
+
+		assert_eq!(*vec.get_or_insert_with(Id(4), || "new"), "new");
+		assert_eq!(vec.as_slice(), ["aa", "b", "c", "new", "new"]);
+		assert_eq!(*vec.get_or_insert_with(Id(0), || "unused"), "aa");
+
+		assert_eq!(vec.swap_remove(Id(0)), "aa");
+		assert_eq!(vec.remove(Id(1)), "b");
+		assert_eq!(vec.clone().as_slice(), ["new", "c", "new"]);
+	}
+}
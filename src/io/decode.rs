@@ -0,0 +1,452 @@
+//! Zero-copy parsing for binary data, like file formats and network
+//! protocols.
+//!
+//! ```rs
+//! let mut decoder = Decoder::new(bytes);
+//! if decoder.bytes(4)? != b"\x7fELF" {
+//!     return Err(DecodeError::InvalidValue { position: 0 });
+//! }
+//! let version = decoder.le::<u32>()?;
+//! let name = decoder.length_prefixed::<u16>(Endian::Big)?;
+//! let kind = decoder.tagged(Endian::Little, |tag: u8, decoder| {
+//!     Ok(match tag {
+//!         0 => Some(Kind::Empty),
+//!         1 => Some(Kind::Sized(decoder.le::<u64>()?)),
+//!         _ => None,
+//!     })
+//! })?;
+//! decoder.finish()?;
+//! ```
+//!
+//! A [`Decoder`] reads from the front of a byte slice, and slices it returns
+//! borrow the input instead of copying it. Errors say where in the input they
+//! happened, counting from the start of the slice passed to
+//! [`Decoder::new`], even when they come from a [sub-decoder](Decoder::sub).
+//! Types can implement [`Decode`] to be read with [`Decoder::decode`].
+
+use {
+	crate::{
+		lang::Integer,
+		text::{Display, str_from_utf8, write},
+	},
+	core::convert::{AsMut, AsRef},
+};
+
+/// The byte order of an integer.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Endian {
+	Little,
+	Big,
+	/// Whichever of the two the current target uses.
+	Native,
+}
+
+/// A type that can be read from binary data. Multi-byte integers don't
+/// implement this, since they don't have a single byte order; use
+/// [`Decoder::int`] for those.
+pub trait Decode<'a>: Sized {
+	fn decode(decoder: &mut Decoder<'a>) -> Result<Self, DecodeError>;
+}
+
+/// Reads values from the front of a byte slice. See the
+/// [module-level docs](self).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Decoder<'a> {
+	bytes: &'a [u8],
+	pos: usize,
+	/// Where `bytes` starts in the original input, so sub-decoders report
+	/// positions in the original input.
+	base: usize,
+}
+impl<'a> Decoder<'a> {
+	pub const fn new(bytes: &'a [u8]) -> Self {
+		Self {
+			bytes,
+			pos: 0,
+			base: 0,
+		}
+	}
+
+	/// How far into the original input the decoder is.
+	pub const fn position(&self) -> usize {
+		self.base + self.pos
+	}
+	/// The bytes that haven't been read yet.
+	pub fn remaining(&self) -> &'a [u8] {
+		&self.bytes[self.pos..]
+	}
+	/// Whether every byte has been read.
+	pub const fn is_empty(&self) -> bool {
+		self.pos == self.bytes.len()
+	}
+	/// Errors with [`DecodeError::TrailingBytes`] if there are any bytes
+	/// left. Use this once a value is decoded, if it should take up the whole
+	/// input.
+	pub fn finish(self) -> Result<(), DecodeError> {
+		if self.is_empty() {
+			Ok(())
+		} else {
+			Err(DecodeError::TrailingBytes {
+				position: self.position(),
+			})
+		}
+	}
+
+	/// Moves to `offset` bytes from the start of this decoder's input, e.g.
+	/// to follow an offset stored in a file's header.
+	pub fn seek(&mut self, offset: usize) -> Result<(), DecodeError> {
+		if offset > self.bytes.len() {
+			return Err(DecodeError::UnexpectedEnd {
+				position: self.base + self.bytes.len(),
+				needed: offset - self.bytes.len(),
+			});
+		}
+		self.pos = offset;
+		Ok(())
+	}
+	/// Skips `len` bytes.
+	pub fn skip(&mut self, len: usize) -> Result<(), DecodeError> {
+		self.bytes(len).map(|_| ())
+	}
+	/// Skips padding until the position in this decoder's input is a multiple
+	/// of `align`.
+	pub fn align(&mut self, align: usize) -> Result<(), DecodeError> {
+		self.skip(self.pos.next_multiple_of(align) - self.pos)
+	}
+
+	/// Returns the next `len` bytes without moving past them.
+	pub fn peek(&self, len: usize) -> Result<&'a [u8], DecodeError> {
+		let remaining = self.remaining();
+		let Some(bytes) = remaining.get(..len) else {
+			return Err(DecodeError::UnexpectedEnd {
+				position: self.position(),
+				needed: len - remaining.len(),
+			});
+		};
+		Ok(bytes)
+	}
+	/// Reads the next `len` bytes.
+	pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+		let bytes = self.peek(len)?;
+		self.pos += len;
+		Ok(bytes)
+	}
+	/// Reads the next `N` bytes.
+	pub fn array<const N: usize>(&mut self) -> Result<&'a [u8; N], DecodeError> {
+		Ok(self.bytes(N)?.try_into().unwrap())
+	}
+	/// Reads the bytes up to the next `delimiter`, then skips the delimiter.
+	/// For example, `until(0)` reads a nul-terminated string.
+	pub fn until(&mut self, delimiter: u8) -> Result<&'a [u8], DecodeError> {
+		let remaining = self.remaining();
+		let Some(len) = remaining.iter().position(|byte| *byte == delimiter) else {
+			return Err(DecodeError::UnexpectedEnd {
+				position: self.base + self.bytes.len(),
+				needed: 1,
+			});
+		};
+		self.pos += len + 1;
+		Ok(&remaining[..len])
+	}
+	/// Reads `len` bytes of UTF-8 text.
+	pub fn str(&mut self, len: usize) -> Result<&'a str, DecodeError> {
+		let Ok(text) = str_from_utf8(self.peek(len)?) else {
+			return Err(DecodeError::InvalidUtf8 {
+				position: self.position(),
+			});
+		};
+		self.pos += len;
+		Ok(text)
+	}
+
+	pub fn u8(&mut self) -> Result<u8, DecodeError> {
+		Ok(self.array::<1>()?[0])
+	}
+	/// Reads an integer with the given byte order.
+	pub fn int<I: Integer>(&mut self, endian: Endian) -> Result<I, DecodeError> {
+		let mut bytes = I::Bytes::default();
+		let len = bytes.as_ref().len();
+		bytes.as_mut().copy_from_slice(self.bytes(len)?);
+		Ok(match endian {
+			Endian::Little => I::from_le_bytes(bytes),
+			Endian::Big => I::from_be_bytes(bytes),
+			Endian::Native => I::from_ne_bytes(bytes),
+		})
+	}
+	/// Reads a little-endian integer.
+	pub fn le<I: Integer>(&mut self) -> Result<I, DecodeError> {
+		self.int(Endian::Little)
+	}
+	/// Reads a big-endian integer.
+	pub fn be<I: Integer>(&mut self) -> Result<I, DecodeError> {
+		self.int(Endian::Big)
+	}
+	/// Reads a length as an `L`, then that many bytes. Negative lengths are
+	/// [invalid](DecodeError::InvalidValue).
+	pub fn length_prefixed<L: Integer>(&mut self, endian: Endian) -> Result<&'a [u8], DecodeError> {
+		let position = self.position();
+		let len: L = self.int(endian)?;
+		if L::SIGNED && len < L::ZERO {
+			self.pos = position - self.base;
+			return Err(DecodeError::InvalidValue { position });
+		}
+		match usize::try_from(len.as_u128()) {
+			Ok(len) => self.bytes(len),
+			// Too long to fit in memory, so the input can't be that long
+			Err(_) => Err(DecodeError::UnexpectedEnd {
+				position: self.position(),
+				needed: usize::MAX,
+			}),
+		}
+	}
+
+	/// Reads a `T`.
+	pub fn decode<T: Decode<'a>>(&mut self) -> Result<T, DecodeError> {
+		T::decode(self)
+	}
+	/// Reads a tag as an `I`, then calls `variant` with it to decode the rest
+	/// of a tagged value, like an enum with data. `variant` returns `None` for
+	/// unknown tags, which errors with [`DecodeError::InvalidTag`].
+	pub fn tagged<I: Integer, T>(
+		&mut self,
+		endian: Endian,
+		variant: impl FnOnce(I, &mut Self) -> Result<Option<T>, DecodeError>,
+	) -> Result<T, DecodeError> {
+		let position = self.position();
+		let tag = self.int(endian)?;
+		variant(tag, self)?.ok_or(DecodeError::InvalidTag {
+			position,
+			tag: tag.as_u128(),
+		})
+	}
+	/// Reads `count` `T`s one at a time. The iterator stops after the first
+	/// error.
+	pub fn repeat<T: Decode<'a>>(&mut self, count: usize) -> Repeat<'_, 'a, T> {
+		Repeat {
+			decoder: self,
+			count,
+			_item: core::marker::PhantomData,
+		}
+	}
+	/// Takes the next `len` bytes, and returns a decoder for just those bytes.
+	/// Useful for length-prefixed structures, so decoding one can't read
+	/// past its end.
+	pub fn sub(&mut self, len: usize) -> Result<Self, DecodeError> {
+		let base = self.position();
+		Ok(Self {
+			bytes: self.bytes(len)?,
+			pos: 0,
+			base,
+		})
+	}
+}
+
+/// Iterator from [`Decoder::repeat`].
+#[derive(Debug)]
+pub struct Repeat<'d, 'a, T> {
+	decoder: &'d mut Decoder<'a>,
+	count: usize,
+	_item: core::marker::PhantomData<T>,
+}
+impl<'a, T: Decode<'a>> Iterator for Repeat<'_, 'a, T> {
+	type Item = Result<T, DecodeError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.count == 0 {
+			return None;
+		}
+		let item = self.decoder.decode();
+		self.count = if item.is_ok() { self.count - 1 } else { 0 };
+		Some(item)
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, Some(self.count))
+	}
+}
+
+impl Decode<'_> for u8 {
+	fn decode(decoder: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+		decoder.u8()
+	}
+}
+impl Decode<'_> for i8 {
+	fn decode(decoder: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+		Ok(decoder.u8()? as i8)
+	}
+}
+impl<'a, const N: usize> Decode<'a> for &'a [u8; N] {
+	fn decode(decoder: &mut Decoder<'a>) -> Result<Self, DecodeError> {
+		decoder.array()
+	}
+}
+impl<const N: usize> Decode<'_> for [u8; N] {
+	fn decode(decoder: &mut Decoder<'_>) -> Result<Self, DecodeError> {
+		decoder.array().copied()
+	}
+}
+
+/// An error from decoding binary data. `position` is where in the original
+/// input it happened.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+	/// The input ended, but a value needed `needed` more bytes.
+	UnexpectedEnd { position: usize, needed: usize },
+	/// There were bytes left after the value ended.
+	TrailingBytes { position: usize },
+	/// A tag didn't match any known variant.
+	InvalidTag { position: usize, tag: u128 },
+	/// Text wasn't valid UTF-8.
+	InvalidUtf8 { position: usize },
+	/// A value was out of range, or otherwise invalid. [`Decode`]
+	/// implementations can return this for their own checks.
+	InvalidValue { position: usize },
+}
+impl DecodeError {
+	pub const fn position(self) -> usize {
+		match self {
+			Self::UnexpectedEnd { position, .. }
+			| Self::TrailingBytes { position }
+			| Self::InvalidTag { position, .. }
+			| Self::InvalidUtf8 { position }
+			| Self::InvalidValue { position } => position,
+		}
+	}
+}
+impl Display for DecodeError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::UnexpectedEnd { .. } => f.write_str("input ended too early")?,
+			Self::TrailingBytes { .. } => f.write_str("unexpected trailing bytes")?,
+			Self::InvalidTag { tag, .. } => write!(f, "invalid tag {tag}")?,
+			Self::InvalidUtf8 { .. } => f.write_str("invalid UTF-8")?,
+			Self::InvalidValue { .. } => f.write_str("invalid value")?,
+		}
+		write!(f, " at byte {}", self.position())
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn integers() {
+		let mut decoder = Decoder::new(&[1, 2, 3, 4, 5, 0xff, 0xfe, 0xff, 0xff, 0xff]);
+		assert_eq!(decoder.u8(), Ok(1));
+		assert_eq!(decoder.le::<u16>(), Ok(0x0302));
+		assert_eq!(decoder.be::<u16>(), Ok(0x0405));
+		assert_eq!(decoder.int::<i8>(Endian::Native), Ok(-1));
+		assert_eq!(decoder.position(), 6);
+		assert_eq!(
+			decoder.be::<u64>(),
+			Err(DecodeError::UnexpectedEnd {
+				position: 6,
+				needed: 4,
+			})
+		);
+		// Failed reads don't move the decoder
+		assert_eq!(decoder.le::<i32>(), Ok(-2));
+		assert!(decoder.is_empty());
+		assert_eq!(decoder.finish(), Ok(()));
+	}
+
+	#[test]
+	fn slices() {
+		let input = b"\x00\x03abc\x02hi\xffname\0\xfe\x80rest";
+		let mut decoder = Decoder::new(input);
+		assert_eq!(decoder.length_prefixed::<u16>(Endian::Big), Ok(&b"abc"[..]));
+		assert_eq!(decoder.peek(3), Ok(&b"\x02hi"[..]));
+		assert_eq!(
+			decoder.length_prefixed::<i8>(Endian::Little),
+			Ok(&b"hi"[..])
+		);
+		assert_eq!(
+			decoder.length_prefixed::<i8>(Endian::Little),
+			Err(DecodeError::InvalidValue { position: 8 })
+		);
+		decoder.skip(1).unwrap();
+		assert_eq!(decoder.until(0), Ok(&b"name"[..]));
+		assert_eq!(
+			decoder.str(2),
+			Err(DecodeError::InvalidUtf8 { position: 14 })
+		);
+		assert_eq!(decoder.array::<2>(), Ok(&[0xfe, 0x80]));
+		assert_eq!(
+			decoder.until(0),
+			Err(DecodeError::UnexpectedEnd {
+				position: 20,
+				needed: 1,
+			})
+		);
+		assert_eq!(decoder.str(4), Ok("rest"));
+
+		decoder.seek(4).unwrap();
+		decoder.align(4).unwrap();
+		assert_eq!(decoder.position(), 4);
+		decoder.skip(1).unwrap();
+		decoder.align(4).unwrap();
+		assert_eq!(decoder.remaining(), b"\xffname\0\xfe\x80rest");
+		assert_eq!(
+			decoder.finish(),
+			Err(DecodeError::TrailingBytes { position: 8 })
+		);
+	}
+
+	#[derive(PartialEq, Eq, Debug)]
+	enum Shape<'a> {
+		Point,
+		Circle(u32),
+		Named(&'a [u8]),
+	}
+	impl<'a> Decode<'a> for Shape<'a> {
+		fn decode(decoder: &mut Decoder<'a>) -> Result<Self, DecodeError> {
+			decoder.tagged(Endian::Little, |tag: u16, decoder| {
+				Ok(match tag {
+					0 => Some(Self::Point),
+					1 => Some(Self::Circle(decoder.le()?)),
+					2 => Some(Self::Named(decoder.length_prefixed::<u8>(Endian::Little)?)),
+					_ => None,
+				})
+			})
+		}
+	}
+
+	#[test]
+	fn combinators() {
+		let input = b"\x04\0\0\0\x00\x00\x01\x00\x05\x00\x00\x00\x02\x00\x02ab\x07\x00";
+		let mut decoder = Decoder::new(input);
+		let count = decoder.le::<u32>().unwrap();
+		let mut shapes = decoder.sub(12).unwrap();
+		assert_eq!(shapes.position(), 4);
+		let shapes = shapes.repeat::<Shape>(count as usize).collect::<Vec<_>>();
+		assert_eq!(
+			shapes,
+			[
+				Ok(Shape::Point),
+				Ok(Shape::Circle(5)),
+				// The sub-decoder ends partway through the name
+				Err(DecodeError::UnexpectedEnd {
+					position: 15,
+					needed: 1,
+				}),
+			]
+		);
+
+		let mut decoder = Decoder::new(&input[12..]);
+		assert_eq!(decoder.decode(), Ok(Shape::Named(b"ab")));
+		assert_eq!(
+			decoder.decode::<Shape>(),
+			Err(DecodeError::InvalidTag {
+				position: 5,
+				tag: 7,
+			})
+		);
+		assert_eq!(decoder.decode::<[u8; 0]>(), Ok([]));
+	}
+}
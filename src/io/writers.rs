@@ -0,0 +1,261 @@
+//! [`Writer`] implementations for in-memory buffers, and writers that wrap
+//! other writers.
+//!
+//! Text buffers ([`String`] and [`ArenaString`]) only accept valid UTF-8, so
+//! writing raw bytes to them can fail. Writing formatted text (e.g. with
+//! `write!`) always writes valid UTF-8.
+
+use {
+	crate::{
+		data_structures::{ArenaString, IndexSize},
+		io::Writer,
+		lang::Infallible,
+		text::str_from_utf8,
+	},
+	core::str::Utf8Error,
+};
+
+impl<A: Allocator> Writer for Vec<u8, A> {
+	type Error = Infallible;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		self.extend_from_slice(bytes);
+		Ok(bytes.len())
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+impl Writer for String {
+	type Error = Utf8Error;
+
+	/// Appends `bytes` to the string if they're valid UTF-8. Otherwise, nothing
+	/// is written.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		self.push_str(str_from_utf8(bytes)?);
+		Ok(bytes.len())
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+impl<S: const IndexSize> Writer for ArenaString<S> {
+	type Error = Utf8Error;
+
+	/// Appends `bytes` to the string if they're valid UTF-8. Otherwise, nothing
+	/// is written.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		self.push_str(str_from_utf8(bytes)?);
+		Ok(bytes.len())
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+/// Writes into a fixed-size byte slice. Useful for formatting text without
+/// allocating:
+///
+/// ```rs
+/// let mut buf = [0; 64];
+/// let mut writer = SliceWriter::new(&mut buf);
+/// write!(writer, "{} + {} = {}", 1, 2, 1 + 2).unwrap();
+/// assert_eq!(writer.written(), b"1 + 2 = 3");
+/// ```
+pub struct SliceWriter<'a> {
+	slice: &'a mut [u8],
+	position: usize,
+}
+impl<'a> SliceWriter<'a> {
+	pub fn new(slice: &'a mut [u8]) -> Self {
+		Self { slice, position: 0 }
+	}
+
+	/// The bytes that have been written so far.
+	pub fn written(&self) -> &[u8] {
+		&self.slice[..self.position]
+	}
+	/// How many more bytes can be written before the slice is full.
+	pub fn remaining(&self) -> usize {
+		self.slice.len() - self.position
+	}
+	/// Returns the part of the slice that's been written to.
+	pub fn into_written(self) -> &'a mut [u8] {
+		&mut self.slice[..self.position]
+	}
+}
+impl Writer for SliceWriter<'_> {
+	type Error = SliceFullError;
+
+	/// Copies as many of `bytes` as fit into the slice. Errors if the slice is
+	/// already full.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		if self.remaining() == 0 && !bytes.is_empty() {
+			return Err(SliceFullError);
+		}
+
+		let len = bytes.len().min(self.remaining());
+		self.slice[self.position..self.position + len].copy_from_slice(&bytes[..len]);
+		self.position += len;
+
+		Ok(len)
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+/// Returned by [`SliceWriter`] when it runs out of room.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SliceFullError;
+
+/// Wraps a writer and counts how many bytes are written to it.
+pub struct CountingWriter<W: Writer> {
+	writer: W,
+	count: usize,
+}
+impl<W: Writer> CountingWriter<W> {
+	pub fn new(writer: W) -> Self {
+		Self { writer, count: 0 }
+	}
+
+	/// How many bytes have been written so far.
+	pub fn count(&self) -> usize {
+		self.count
+	}
+	pub fn into_inner(self) -> W {
+		self.writer
+	}
+}
+impl<W: Writer> Writer for CountingWriter<W> {
+	type Error = W::Error;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		let written = self.writer.write(bytes)?;
+		self.count += written;
+		Ok(written)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		W::is_interrupted(error)
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		self.writer.flush()
+	}
+}
+
+/// Writes the same bytes to two writers.
+///
+/// Bytes are written to the first writer, then however many bytes the first
+/// writer accepted are written to the second one.
+pub struct TeeWriter<A: Writer, B: Writer> {
+	pub first: A,
+	pub second: B,
+}
+impl<A: Writer, B: Writer> TeeWriter<A, B> {
+	pub fn new(first: A, second: B) -> Self {
+		Self { first, second }
+	}
+
+	pub fn into_inner(self) -> (A, B) {
+		(self.first, self.second)
+	}
+}
+impl<A: Writer, B: Writer> Writer for TeeWriter<A, B> {
+	type Error = TeeError<A::Error, B::Error>;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		let written = self.first.write(bytes).map_err(TeeError::First)?;
+		self.second
+			.write_all(&bytes[..written])
+			.map_err(TeeError::Second)?;
+		Ok(written)
+	}
+	/// Only errors from the first writer can be retried, since the second
+	/// writer's errors happen after the first one accepted the bytes.
+	fn is_interrupted(error: &Self::Error) -> bool {
+		match error {
+			TeeError::First(error) => A::is_interrupted(error),
+			TeeError::Second(_) => false,
+		}
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		self.first.flush().map_err(TeeError::First)?;
+		self.second.flush().map_err(TeeError::Second)
+	}
+}
+
+/// An error from one of the writers in a [`TeeWriter`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TeeError<A, B> {
+	First(A),
+	Second(B),
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::text::format_args};
+
+	#[test]
+	fn writers() {
+		let mut buf = [0; 8];
+		let mut slice = SliceWriter::new(&mut buf);
+		assert_eq!(slice.write(b"hello"), Ok(5));
+		assert_eq!(slice.write(b"world"), Ok(3));
+		assert_eq!(slice.write(b"!"), Err(SliceFullError));
+		assert_eq!(slice.written(), b"hellowor");
+
+		let mut tee = TeeWriter::new(CountingWriter::new(Vec::new()), String::new());
+		tee.write_fmt(format_args!("{} + {}", 1, 2)).unwrap();
+		let (counter, string) = tee.into_inner();
+		assert_eq!(counter.count(), 5);
+		assert_eq!(counter.into_inner(), b"1 + 2");
+		assert_eq!(string, "1 + 2");
+
+		assert!(String::new().write(&[0xff]).is_err());
+	}
+
+	#[test]
+	fn write_all_retries_interrupts() {
+		/// Gets interrupted before every other write, and only writes 2 bytes
+		/// at a time.
+		struct Flaky {
+			written: Vec<u8>,
+			interrupt: bool,
+		}
+		impl Writer for Flaky {
+			type Error = bool;
+
+			fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+				self.interrupt = !self.interrupt;
+				if self.interrupt {
+					return Err(true);
+				}
+				let len = bytes.len().min(2);
+				self.written.extend_from_slice(&bytes[..len]);
+				Ok(len)
+			}
+			fn is_interrupted(error: &Self::Error) -> bool {
+				*error
+			}
+			fn flush(&mut self) -> Result<(), Self::Error> {
+				Ok(())
+			}
+		}
+
+		let mut flaky = CountingWriter::new(Flaky {
+			written: Vec::new(),
+			interrupt: false,
+		});
+		flaky.write_all(b"hello").unwrap();
+		assert_eq!(flaky.count(), 5);
+		assert_eq!(flaky.into_inner().written, b"hello");
+	}
+}
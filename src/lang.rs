@@ -24,9 +24,10 @@ pub mod op {
 		cmp::{Eq, Ord, PartialEq, PartialOrd},
 		ops::{
 			Add, AddAssign, AsyncFn, AsyncFnMut, AsyncFnOnce, BitAnd, BitAndAssign, BitOr,
-			BitOrAssign, BitXor, BitXorAssign, Deref, DerefMut, Div, DivAssign, Drop, Fn, FnMut,
-			FnOnce, Index, IndexMut, Mul, MulAssign, Range, RangeBounds, RangeFrom, RangeFull,
-			RangeInclusive, RangeTo, RangeToInclusive, Sub, SubAssign,
+			BitOrAssign, BitXor, BitXorAssign, Bound, Deref, DerefMut, Div, DivAssign, Drop, Fn,
+			FnMut, FnOnce, Index, IndexMut, Mul, MulAssign, Neg, Not, Range, RangeBounds,
+			RangeFrom, RangeFull, RangeInclusive, RangeTo, RangeToInclusive, Rem, RemAssign, Sub,
+			SubAssign,
 		},
 	};
 }
@@ -46,6 +47,7 @@ pub mod essential {
 		panic,
 		prelude::rust_2024::derive,
 		result::Result::{self, Err, Ok},
+		str::FromStr,
 		todo,
 		unreachable,
 	};
@@ -68,6 +70,7 @@ pub mod compiler {
 		},
 		module_path,
 		ptr::{slice_from_raw_parts, slice_from_raw_parts_mut},
+		stringify,
 	};
 }
 
@@ -94,7 +97,10 @@ pub mod mem {
 		},
 	};
 
-	use crate::ffi::{CStr, c_char};
+	use crate::{
+		ffi::{CStr, c_char},
+		text::Display,
+	};
 
 	pub trait AsStatic {
 		type Ref: ToOwned + ?Sized;
@@ -122,6 +128,69 @@ pub mod mem {
 		}
 	}
 
+	/// [`Cow`], but the owned value is stored in an [`Allocator`] of your
+	/// choice (e.g. an arena) instead of the global allocator.
+	///
+	/// ```rs
+	/// let arena = VirtualMemoryArena::new(MemoryAmount::mebibytes(1))?;
+	/// let name: CowIn<str, _> = text::maybe_format_in(format_args!("{id}"), &arena);
+	/// ```
+	pub enum CowIn<'a, T: ?Sized, A: Allocator = GlobalAllocator> {
+		Borrowed(&'a T),
+		Owned(Box<T, A>),
+	}
+	impl<T: ?Sized, A: Allocator> CowIn<'_, T, A> {
+		pub const fn is_borrowed(&self) -> bool {
+			matches!(self, Self::Borrowed(_))
+		}
+		pub const fn is_owned(&self) -> bool {
+			!self.is_borrowed()
+		}
+	}
+	impl<T: ?Sized, A: Allocator> Deref for CowIn<'_, T, A> {
+		type Target = T;
+
+		fn deref(&self) -> &Self::Target {
+			match self {
+				Self::Borrowed(borrowed) => borrowed,
+				Self::Owned(owned) => owned,
+			}
+		}
+	}
+	impl<T: ?Sized, A: Allocator> Borrow<T> for CowIn<'_, T, A> {
+		fn borrow(&self) -> &T {
+			self
+		}
+	}
+	impl<'a, T: ?Sized, A: Allocator> From<&'a T> for CowIn<'a, T, A> {
+		fn from(value: &'a T) -> Self {
+			Self::Borrowed(value)
+		}
+	}
+	impl<T: ?Sized, A: Allocator> From<Box<T, A>> for CowIn<'_, T, A> {
+		fn from(value: Box<T, A>) -> Self {
+			Self::Owned(value)
+		}
+	}
+	impl<T: ?Sized + PartialEq, A: Allocator, B: Allocator> PartialEq<CowIn<'_, T, B>>
+		for CowIn<'_, T, A>
+	{
+		fn eq(&self, other: &CowIn<'_, T, B>) -> bool {
+			**self == **other
+		}
+	}
+	impl<T: ?Sized + Eq, A: Allocator> Eq for CowIn<'_, T, A> {}
+	impl<T: ?Sized + Debug, A: Allocator> Debug for CowIn<'_, T, A> {
+		fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+			Debug::fmt(&**self, f)
+		}
+	}
+	impl<T: ?Sized + Display, A: Allocator> Display for CowIn<'_, T, A> {
+		fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+			Display::fmt(&**self, f)
+		}
+	}
+
 	/// [`NonNull`], but with a const pointer instead of a mutable pointer.
 	#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 	#[repr(transparent)]
@@ -190,7 +259,9 @@ pub mod mem {
 pub mod iter {
 	//! Items for working with iterators.
 
-	pub use core::iter::{Extend, IntoIterator, Iterator};
+	pub use core::iter::{
+		DoubleEndedIterator, ExactSizeIterator, Extend, FusedIterator, IntoIterator, Iterator,
+	};
 }
 
 pub mod panic {
@@ -243,6 +314,38 @@ pub mod xstat {
 				node: unsafe { *self.base.get() },
 			}
 		}
+
+		/// Unlinks the first entry whose value matches `matches`, and returns
+		/// its value. The entry itself isn't freed, since it's `'static`.
+		///
+		/// # Safety
+		///
+		/// This function cannot be called in concurrent contexts.
+		pub unsafe fn remove(&self, mut matches: impl FnMut(&T) -> bool) -> Option<&'static T> {
+			let mut prev: Option<&'static XStatEntry<T>> = None;
+			let mut node = unsafe { *self.base.get() };
+			while let Some(current) = node {
+				let next = unsafe { *current.next.get() };
+				if !matches(&current.value) {
+					prev = Some(current);
+					node = next;
+					continue;
+				}
+
+				match prev {
+					Some(prev) => unsafe { *prev.next.get() = next },
+					None => unsafe { *self.base.get() = next },
+				}
+				if next.is_none() {
+					unsafe { *self.head.get() = prev };
+				}
+				unsafe { *current.next.get() = None };
+
+				return Some(&current.value);
+			}
+
+			None
+		}
 	}
 	// yea ts unsafe af tbh
 	unsafe impl<T: 'static + ?Sized> Sync for XStat<T> {}
@@ -1,4 +1,7 @@
-use core::iter::Step;
+use core::{
+	convert::{AsMut, AsRef},
+	iter::Step,
+};
 
 use crate::{
 	lang::{Add, AddAssign, Copy, Div, DivAssign, Mul, MulAssign, Ord, Sized, Sub, SubAssign},
@@ -157,6 +160,49 @@ pub const trait Integer:
 	fn checked_sub(self, rhs: Self) -> Option<Self>;
 	fn checked_div(self, rhs: Self) -> Option<Self>;
 	fn checked_mul(self, rhs: Self) -> Option<Self>;
+	fn checked_pow(self, exp: u32) -> Option<Self>;
+
+	fn wrapping_add(self, rhs: Self) -> Self;
+	fn wrapping_sub(self, rhs: Self) -> Self;
+	fn wrapping_div(self, rhs: Self) -> Self;
+	fn wrapping_mul(self, rhs: Self) -> Self;
+	fn wrapping_neg(self) -> Self;
+	fn wrapping_pow(self, exp: u32) -> Self;
+
+	fn overflowing_add(self, rhs: Self) -> (Self, bool);
+	fn overflowing_sub(self, rhs: Self) -> (Self, bool);
+	fn overflowing_div(self, rhs: Self) -> (Self, bool);
+	fn overflowing_mul(self, rhs: Self) -> (Self, bool);
+	fn overflowing_pow(self, exp: u32) -> (Self, bool);
+
+	fn pow(self, exp: u32) -> Self;
+	fn saturating_pow(self, exp: u32) -> Self;
+
+	fn leading_zeros(self) -> u32;
+	fn trailing_zeros(self) -> u32;
+	fn count_ones(self) -> u32;
+	fn count_zeros(self) -> u32;
+	fn rotate_left(self, n: u32) -> Self;
+	fn rotate_right(self, n: u32) -> Self;
+	fn swap_bytes(self) -> Self;
+
+	/// The integer's memory representation; a `[u8; N]`, where `N` is
+	/// [`Integer::SIZE_BYTES`].
+	type Bytes: Copy + AsRef<[u8]> + AsMut<[u8]> + Default + Debug;
+
+	fn to_le_bytes(self) -> Self::Bytes;
+	fn to_be_bytes(self) -> Self::Bytes;
+	fn to_ne_bytes(self) -> Self::Bytes;
+	fn from_le_bytes(bytes: Self::Bytes) -> Self;
+	fn from_be_bytes(bytes: Self::Bytes) -> Self;
+	fn from_ne_bytes(bytes: Self::Bytes) -> Self;
+
+	/// Casts this integer to a [`u128`], like `self as u128`. Negative numbers
+	/// are sign-extended, so `-1` becomes `u128::MAX`.
+	fn as_u128(self) -> u128;
+	/// Casts a [`u128`] to this integer type, like `value as Self`. Extra
+	/// high bits are truncated.
+	fn from_u128_truncating(value: u128) -> Self;
 }
 
 macro_rules! impl_integer {
@@ -201,6 +247,101 @@ macro_rules! impl_integer {
 			fn checked_mul(self, rhs: Self) -> Option<Self> {
 				self.checked_mul(rhs)
 			}
+			fn checked_pow(self, exp: u32) -> Option<Self> {
+				self.checked_pow(exp)
+			}
+
+			fn wrapping_add(self, rhs: Self) -> Self {
+				self.wrapping_add(rhs)
+			}
+			fn wrapping_sub(self, rhs: Self) -> Self {
+				self.wrapping_sub(rhs)
+			}
+			fn wrapping_div(self, rhs: Self) -> Self {
+				self.wrapping_div(rhs)
+			}
+			fn wrapping_mul(self, rhs: Self) -> Self {
+				self.wrapping_mul(rhs)
+			}
+			fn wrapping_neg(self) -> Self {
+				self.wrapping_neg()
+			}
+			fn wrapping_pow(self, exp: u32) -> Self {
+				self.wrapping_pow(exp)
+			}
+
+			fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+				self.overflowing_add(rhs)
+			}
+			fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+				self.overflowing_sub(rhs)
+			}
+			fn overflowing_div(self, rhs: Self) -> (Self, bool) {
+				self.overflowing_div(rhs)
+			}
+			fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+				self.overflowing_mul(rhs)
+			}
+			fn overflowing_pow(self, exp: u32) -> (Self, bool) {
+				self.overflowing_pow(exp)
+			}
+
+			fn pow(self, exp: u32) -> Self {
+				self.pow(exp)
+			}
+			fn saturating_pow(self, exp: u32) -> Self {
+				self.saturating_pow(exp)
+			}
+
+			fn leading_zeros(self) -> u32 {
+				self.leading_zeros()
+			}
+			fn trailing_zeros(self) -> u32 {
+				self.trailing_zeros()
+			}
+			fn count_ones(self) -> u32 {
+				self.count_ones()
+			}
+			fn count_zeros(self) -> u32 {
+				self.count_zeros()
+			}
+			fn rotate_left(self, n: u32) -> Self {
+				self.rotate_left(n)
+			}
+			fn rotate_right(self, n: u32) -> Self {
+				self.rotate_right(n)
+			}
+			fn swap_bytes(self) -> Self {
+				self.swap_bytes()
+			}
+
+			type Bytes = [u8; <$ty>::BITS as usize / 8];
+
+			fn to_le_bytes(self) -> Self::Bytes {
+				self.to_le_bytes()
+			}
+			fn to_be_bytes(self) -> Self::Bytes {
+				self.to_be_bytes()
+			}
+			fn to_ne_bytes(self) -> Self::Bytes {
+				self.to_ne_bytes()
+			}
+			fn from_le_bytes(bytes: Self::Bytes) -> Self {
+				Self::from_le_bytes(bytes)
+			}
+			fn from_be_bytes(bytes: Self::Bytes) -> Self {
+				Self::from_be_bytes(bytes)
+			}
+			fn from_ne_bytes(bytes: Self::Bytes) -> Self {
+				Self::from_ne_bytes(bytes)
+			}
+
+			fn as_u128(self) -> u128 {
+				self as u128
+			}
+			fn from_u128_truncating(value: u128) -> Self {
+				value as Self
+			}
 		})*
 	};
 }
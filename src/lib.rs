@@ -12,16 +12,32 @@
 #![feature(const_convert)]
 #![feature(const_default)]
 #![feature(step_trait)]
+#![feature(coerce_unsized)]
+#![feature(unsize)]
+#![feature(thread_local)]
+#![feature(core_float_math)]
+#![feature(dropck_eyepatch)]
+#![feature(btreemap_alloc)]
+#![feature(linkage)]
+#![cfg_attr(not(feature = "std-compat"), feature(alloc_error_handler))]
 #![cfg_attr(not(feature = "std-compat"), no_std)]
 
-#[cfg(feature = "concurrency")]
+pub mod bench;
+#[cfg(feature = "std-compat")]
+pub mod compat;
 pub mod concurrency;
 pub mod crypto;
 pub mod data_structures;
 pub mod ffi;
 pub mod lang;
 pub mod logging;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod num;
+#[cfg(feature = "profile")]
+pub mod profile;
 pub mod rt;
+pub mod task;
 #[cfg(feature = "term")]
 pub mod term;
 pub mod test;
@@ -30,13 +46,20 @@ pub mod ui;
 
 pub extern crate alloc;
 pub extern crate core;
-pub use crux_macros as macros;
+pub use crux_macros::{self as macros, main};
 
 pub mod hooks {
-	pub use crate::rt::{entrypoint::call_main, startup_hook};
+	#[cfg(feature = "probe-page-size")]
+	pub use crate::rt::mem::load_page_size;
+	#[cfg(feature = "cli-args")]
+	pub use crate::rt::proc::load_args;
+	pub use crate::rt::{entrypoint::call_main, proc::free_cli_args, startup_hook};
 }
 pub mod events {
-	pub use crate::rt::{startup, test_harness::run_tests};
+	pub use crate::{
+		bench::run_benches,
+		rt::{shutdown, startup, test_harness::run_tests},
+	};
 }
 
 pub mod prelude {
@@ -47,8 +70,9 @@ pub mod prelude {
 		self as crux, bitset,
 		crypto::hash::Hash,
 		data_structures::{
-			ArenaString, ArenaVec, BTreeMap, BTreeSet, BinaryHeap, Box, HashMap, HashSet,
-			HashTable, SizedVec, TypedVec, Vec, typed_vec_idx,
+			ArenaString, ArenaVec, BTreeMap, BTreeMapExt, BTreeSet, BTreeSetExt, BinaryHeap, Box,
+			GenVec, HashMap, HashMapExt, HashSet, HashTable, IndexedHeap, RangeMap, SizedVec,
+			TypedVec, Vec, hash_map::Entry, try_box_new, try_box_new_in, typed_vec_idx,
 		},
 		lang::{
 			AllocError, Allocator, AsyncFn, AsyncFnMut, AsyncFnOnce, Clone, Copy, Default, Deref,
@@ -57,15 +81,17 @@ pub mod prelude {
 			PartialOrd, Result, Send, Sized, Some, Sync, TryFrom, TryInto, derive, drop, matches,
 			panic, todo, transmute, transmute_copy, unreachable,
 		},
-		logging::{error, fatal, info, trace, warn},
-		macros::test,
+		logging::{dbg, error, fatal, info, trace, warn},
+		macros::{CruxDebug, CruxDefault, bench, embed_str, include_bytes_aligned, map, set, test},
 		rt::{
-			mem::{GlobalAllocator, MemoryAmount, VirtualMemoryArena},
+			mem::{ArenaExt, GlobalAllocator, MemoryAmount, VirtualMemoryArena},
 			proc::{print, println},
 		},
 		test::{assert, assert_eq, assert_ne, safety_assert, safety_assert_eq, safety_assert_ne},
-		text::{CString, Debug, String, format},
+		text::{CString, Debug, String, format, format_in},
 	};
+	#[cfg(feature = "global-arena")]
+	pub use crate::{rt::global_arena, text::format_global};
 }
 
 #[allow(unused_imports)]
@@ -75,6 +101,14 @@ use prelude::*;
 pub mod io {
 	//! General-purpose utilities for transferring data.
 
+	pub mod decode;
+	pub mod writers;
+
+	pub use self::{
+		decode::{Decode, DecodeError, Decoder, Endian},
+		writers::{CountingWriter, SliceFullError, SliceWriter, TeeError, TeeWriter},
+	};
+
 	use crate::text::FormatArgs;
 
 	/// Represents a data source that bytes can be transferred into.
@@ -87,7 +121,14 @@ pub mod io {
 		/// error, if one occurred.
 		fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error>;
 		/// Calls [`Writer::write`] continuously until all of the give `bytes`
-		/// have been transferred to this writer.
+		/// have been transferred to this writer. Writes that fail with an
+		/// interrupted error (see [`Writer::is_interrupted`]) are retried.
+		///
+		/// If this returns an error, some of the bytes may have already been
+		/// written. For example, a non-blocking file whose buffer fills up
+		/// partway through will error with [`ErrorKind::WouldBlock`].
+		///
+		/// [`ErrorKind::WouldBlock`]: crate::rt::os::ErrorKind::WouldBlock
 		fn write_all(&mut self, bytes: &[u8]) -> Result<(), Self::Error> {
 			let mut written = 0;
 			let goal = bytes.len();
@@ -96,9 +137,19 @@ pub mod io {
 				if written == goal {
 					break Ok(());
 				}
-				written += self.write(&bytes[written..])?;
+				match self.write(&bytes[written..]) {
+					Ok(amount) => written += amount,
+					Err(err) if Self::is_interrupted(&err) => {}
+					Err(err) => break Err(err),
+				}
 			}
 		}
+		/// Whether `error` means a write was interrupted (e.g. by a signal)
+		/// before it transferred anything, so it can just be tried again.
+		/// Defaults to `false`.
+		fn is_interrupted(_error: &Self::Error) -> bool {
+			false
+		}
 		/// Write formatted text (e.g. text generated with [`format_args`]) to
 		/// this writer.
 		///
@@ -169,11 +220,57 @@ pub mod io {
 			<Self as Writer>::flush(self).map_err(|_| ())
 		}
 	}
+
+	/// Represents a data source that bytes can be transferred out of.
+	pub trait Reader: Sized {
+		/// An error that occurred while using this reader.
+		type Error: Debug + PartialEq + Eq;
+
+		/// Transfer bytes from this reader into `buf`. Returns how many bytes
+		/// were read, or an error, if one occurred. Reading 0 bytes into a
+		/// non-empty `buf` means the reader has run out of bytes (e.g. it
+		/// reached the end of a file).
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error>;
+		/// Calls [`Reader::read`] continuously until the reader runs out of
+		/// bytes, appending everything it reads to `buf`. Reads that fail with
+		/// an interrupted error (see [`Reader::is_interrupted`]) are retried.
+		/// Returns how many bytes were read.
+		fn read_to_end<A: Allocator>(
+			&mut self,
+			buf: &mut Vec<u8, A>,
+		) -> Result<usize, Self::Error> {
+			let mut chunk = [0; 4096];
+			let start = buf.len();
+
+			loop {
+				match self.read(&mut chunk) {
+					Ok(0) => break Ok(buf.len() - start),
+					Ok(amount) => buf.extend_from_slice(&chunk[..amount]),
+					Err(err) if Self::is_interrupted(&err) => {}
+					Err(err) => break Err(err),
+				}
+			}
+		}
+		/// Whether `error` means a read was interrupted (e.g. by a signal)
+		/// before it transferred anything, so it can just be tried again.
+		/// Defaults to `false`.
+		fn is_interrupted(_error: &Self::Error) -> bool {
+			false
+		}
+	}
 }
 
 pub mod text {
 	//! Functions and types for working with text.
 
+	pub mod intern;
+	pub mod strings;
+	pub mod wide;
+
+	pub use self::{
+		intern::{Interner, Symbol},
+		strings::{Case, SplitByte, Words, convert_case, truncate_chars, truncate_graphemes},
+	};
 	#[doc(inline)]
 	pub use {
 		alloc::{ffi::CString, fmt::format, format, string::String},
@@ -185,11 +282,15 @@ pub mod text {
 			},
 			format_args,
 			str::from_utf8 as str_from_utf8,
-			stringify,
+			stringify, write,
 		},
 	};
 
-	use crate::lang::{AsStatic, Cow};
+	#[cfg(feature = "global-arena")]
+	pub use crate::format_global;
+	pub use crate::format_in;
+
+	use crate::lang::{AsStatic, Cow, CowIn};
 
 	/// Converts the given [`FormatArgs`] to an `&str`, if possible; otherwise
 	/// allocates them to a string.
@@ -205,36 +306,254 @@ pub mod text {
 			None => Cow::Owned(format(args)),
 		}
 	}
+	/// [`maybe_format`], but allocates the string in `allocator` instead of the
+	/// global allocator.
+	pub fn maybe_format_in<'a, A: Allocator>(
+		args: FormatArgs<'a>,
+		allocator: A,
+	) -> CowIn<'a, str, A> {
+		if let Some(str) = args.as_str() {
+			return CowIn::Borrowed(str);
+		}
+
+		let mut bytes = Vec::new_in(allocator);
+		crate::io::Writer::write_fmt(&mut bytes, args)
+			.expect("Crux: A formatting trait implementation returned an error");
+		let (bytes, allocator) = Box::into_raw_with_allocator(bytes.into_boxed_slice());
+		// Formatting only writes valid UTF-8
+		CowIn::Owned(unsafe { Box::from_raw_in(bytes as *mut str, allocator) })
+	}
 }
 
+/// Generates bitset types: integer newtypes with a named constant for each
+/// flag.
+///
+/// ```rs
+/// bitset! {
+///     pub bitset Permissions: u8 {
+///         READ = 0b001,
+///         WRITE = 0b010,
+///         EXECUTE = 0b100,
+///     }
+/// }
+///
+/// let perms = Permissions::READ | Permissions::WRITE;
+/// assert!(perms.contains(Permissions::READ));
+/// assert_eq!(perms.toggle(Permissions::WRITE), Permissions::READ);
+/// assert_eq!(format!("{perms:?}"), "Permissions(READ | WRITE)");
+/// ```
+///
+/// Each bitset gets:
+/// - `FLAGS`, a list of every flag and its name
+/// - `empty`, `all`, `from_bits`, `from_bits_truncate`, and `bits` to convert
+///   to and from the underlying integer
+/// - `contains`, `is_empty`, `add_flag`, `remove_flag`, and `toggle`
+/// - `iter` and `iter_names`, which iterate over the set flags
+/// - The `|`, `&`, `^`, and `!` operators, and their assigning versions
+/// - A [`Debug`](crate::text::Debug) implementation that prints flag names
 #[macro_export]
 macro_rules! bitset {
 	($($(#[$($struct_attr:tt)*])* $(pub bitset $pub_name:ident)? $(bitset $name:ident)?: $size:ty {$($(#[$($variant_attr:tt)*])* $variant:ident = $val:expr $(,)?)*})*) => {
-        $(
-        		$(#[$($struct_attr)*])*
-        		#[derive(Clone, Copy, PartialEq, Eq)]
-          	#[repr(transparent)]
-        		$(pub struct $pub_name)? $(struct $name)?($size);
-          	impl $($pub_name)? $($name)? {
-           		$(
-             		$(#[$($variant_attr)*])*
-               	pub const $variant: Self = Self($val);
-             	)*
-
-            	pub fn contains(self, flag: Self) -> bool {
-             		(self.0 & flag.0) == flag.0
-               }
-	           	pub fn add_flag(self, flag: Self) -> Self {
-	             	Self(self.0 | flag.0)
-	            }
-           	}
-            impl $crate::lang::op::BitOr for $($pub_name)? $($name)? {
-            	type Output = Self;
-
-             	fn bitor(self, other: Self) -> Self {
-              		Self(self.0 | other.0)
-               }
-            }
-        )*
-    };
+		$(
+			$(#[$($struct_attr)*])*
+			#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+			#[repr(transparent)]
+			$(pub struct $pub_name)? $(struct $name)?($size);
+			#[allow(dead_code)]
+			impl $($pub_name)? $($name)? {
+				$(
+					$(#[$($variant_attr)*])*
+					pub const $variant: Self = Self($val);
+				)*
+
+				/// Every flag in this bitset, and its name.
+				#[allow(unused_doc_comments)]
+				pub const FLAGS: &'static [(&'static str, Self)] = &[$(
+					$(#[$($variant_attr)*])*
+					($crate::text::stringify!($variant), Self::$variant),
+				)*];
+
+				/// A bitset with no flags set.
+				pub const fn empty() -> Self {
+					Self(0)
+				}
+				/// A bitset with every flag set.
+				pub const fn all() -> Self {
+					let mut bits = 0;
+					let mut idx = 0;
+					while idx < Self::FLAGS.len() {
+						bits |= Self::FLAGS[idx].1.0;
+						idx += 1;
+					}
+					Self(bits)
+				}
+				/// Creates a bitset from its underlying integer. Bits that don't
+				/// correspond to a flag are kept.
+				pub const fn from_bits(bits: $size) -> Self {
+					Self(bits)
+				}
+				/// Creates a bitset from its underlying integer, dropping bits
+				/// that don't correspond to a flag.
+				pub const fn from_bits_truncate(bits: $size) -> Self {
+					Self(bits & Self::all().0)
+				}
+				/// The bitset's underlying integer.
+				pub const fn bits(self) -> $size {
+					self.0
+				}
+
+				pub const fn contains(self, flag: Self) -> bool {
+					(self.0 & flag.0) == flag.0
+				}
+				pub const fn is_empty(self) -> bool {
+					self.0 == 0
+				}
+				pub const fn add_flag(self, flag: Self) -> Self {
+					Self(self.0 | flag.0)
+				}
+				pub const fn remove_flag(self, flag: Self) -> Self {
+					Self(self.0 & !flag.0)
+				}
+				pub const fn toggle(self, flag: Self) -> Self {
+					Self(self.0 ^ flag.0)
+				}
+
+				/// Iterates over the flags in this bitset. Flags with a value of
+				/// zero are skipped, and if several flags share bits, only the
+				/// first one is yielded.
+				pub fn iter(self) -> impl Iterator<Item = Self> {
+					self.iter_names().map(|(_, flag)| flag)
+				}
+				/// Iterates over the flags in this bitset, and their names. See
+				/// [`Self::iter`].
+				pub fn iter_names(self) -> impl Iterator<Item = (&'static str, Self)> {
+					Self::FLAGS
+						.iter()
+						.scan(self.0, move |remaining, &(name, flag)| {
+							let yielded = self.contains(flag) && (*remaining & flag.0) != 0;
+							*remaining &= !flag.0;
+							Some(yielded.then_some((name, flag)))
+						})
+						.flatten()
+				}
+			}
+			impl $crate::lang::op::BitOr for $($pub_name)? $($name)? {
+				type Output = Self;
+
+				fn bitor(self, other: Self) -> Self {
+					Self(self.0 | other.0)
+				}
+			}
+			impl $crate::lang::op::BitOrAssign for $($pub_name)? $($name)? {
+				fn bitor_assign(&mut self, other: Self) {
+					self.0 |= other.0;
+				}
+			}
+			impl $crate::lang::op::BitAnd for $($pub_name)? $($name)? {
+				type Output = Self;
+
+				fn bitand(self, other: Self) -> Self {
+					Self(self.0 & other.0)
+				}
+			}
+			impl $crate::lang::op::BitAndAssign for $($pub_name)? $($name)? {
+				fn bitand_assign(&mut self, other: Self) {
+					self.0 &= other.0;
+				}
+			}
+			impl $crate::lang::op::BitXor for $($pub_name)? $($name)? {
+				type Output = Self;
+
+				fn bitxor(self, other: Self) -> Self {
+					Self(self.0 ^ other.0)
+				}
+			}
+			impl $crate::lang::op::BitXorAssign for $($pub_name)? $($name)? {
+				fn bitxor_assign(&mut self, other: Self) {
+					self.0 ^= other.0;
+				}
+			}
+			/// Flips every flag. Bits that don't correspond to a flag stay
+			/// unset.
+			impl $crate::lang::op::Not for $($pub_name)? $($name)? {
+				type Output = Self;
+
+				fn not(self) -> Self {
+					Self(!self.0 & Self::all().0)
+				}
+			}
+			impl $crate::text::Debug for $($pub_name)? $($name)? {
+				fn fmt(
+					&self,
+					f: &mut $crate::core::fmt::Formatter<'_>,
+				) -> $crate::core::fmt::Result {
+					f.write_str($crate::text::concat!(
+						$crate::text::stringify!($($pub_name)? $($name)?),
+						"("
+					))?;
+					let mut remaining = self.0;
+					for (idx, (name, flag)) in self.iter_names().enumerate() {
+						if idx > 0 {
+							f.write_str(" | ")?;
+						}
+						f.write_str(name)?;
+						remaining &= !flag.0;
+					}
+					if remaining != 0 {
+						if remaining != self.0 {
+							f.write_str(" | ")?;
+						}
+						$crate::text::write!(f, "{remaining:#x}")?;
+					} else if self.0 == 0 {
+						f.write_str("empty")?;
+					}
+					f.write_str(")")
+				}
+			}
+		)*
+	};
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	bitset! {
+		bitset Permissions: u8 {
+			READ = 0b001,
+			WRITE = 0b010,
+			EXECUTE = 0b100,
+			READ_WRITE = 0b011,
+		}
+	}
+
+	#[test]
+	fn bitset() {
+		let perms = Permissions::READ | Permissions::WRITE;
+		assert!(perms.contains(Permissions::READ_WRITE));
+		assert!(!perms.contains(Permissions::EXECUTE));
+		assert_eq!(perms.remove_flag(Permissions::READ), Permissions::WRITE);
+		assert_eq!(perms.toggle(Permissions::EXECUTE).bits(), 0b111);
+		assert_eq!(perms & Permissions::WRITE, Permissions::WRITE);
+		assert_eq!(perms ^ Permissions::READ_WRITE, Permissions::empty());
+		assert_eq!(!perms, Permissions::EXECUTE);
+		assert_eq!(Permissions::all().bits(), 0b111);
+		assert_eq!(Permissions::from_bits_truncate(0xFF), Permissions::all());
+		assert!(Permissions::empty().is_empty());
+
+		assert_eq!(
+			perms.iter().collect::<Vec<_>>(),
+			[Permissions::READ, Permissions::WRITE]
+		);
+		assert_eq!(format!("{perms:?}"), "Permissions(READ | WRITE)");
+		assert_eq!(
+			format!("{:?}", Permissions::from_bits(0b1001)),
+			"Permissions(READ | 0x8)"
+		);
+		assert_eq!(format!("{:?}", Permissions::empty()), "Permissions(empty)");
+	}
 }
@@ -23,6 +23,18 @@ use crate::{
 	text::{Display, format},
 };
 
+#[cfg(feature = "concurrency")]
+pub mod async_logger;
+#[cfg(feature = "concurrency")]
+pub mod multi_logger;
+pub mod spans;
+#[cfg(feature = "concurrency")]
+#[doc(inline)]
+pub use self::{
+	async_logger::{AsyncLogger, Backpressure},
+	multi_logger::{ChildId, MultiLogger},
+};
+
 //
 //
 // Log struct
@@ -30,7 +42,7 @@ use crate::{
 //
 
 /// Represents a single logged event.
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Log {
 	/// The severity of the log - see [`LogLevel`].
 	pub level: LogLevel,
@@ -40,6 +52,11 @@ pub struct Log {
 	/// known at compile-time, or a `String` for dynamically generated log
 	/// messages.
 	pub msg: Cow<'static, str>,
+	/// The [spans] that were active when the log was created, outermost first
+	/// (e.g. `server > request{id=5}`). Empty if no spans were active.
+	///
+	/// [spans]: crate::logging::spans
+	pub spans: Cow<'static, str>,
 	/// The line in the Rust source code where the log was created.
 	pub line: u32,
 	/// The column in the Rust source code where the log was created.
@@ -95,6 +112,7 @@ macro_rules! mklog {
 			level: $level,
 			module: $crate::lang::compiler::module_path!(),
 			msg: $crate::text::maybe_format_static($crate::text::format_args!($msg)),
+			spans: $crate::logging::spans::current(),
 			line: $crate::lang::compiler::line!(),
 			column: $crate::lang::compiler::column!(),
 			file: $crate::lang::compiler::file!()
@@ -105,6 +123,7 @@ macro_rules! mklog {
 			level: $level,
 			module: $crate::lang::compiler::module_path!(),
 			msg: $crate::text::maybe_format_static($crate::text::format_args!($msg, $($arg),*)),
+			spans: $crate::logging::spans::current(),
 			line: $crate::lang::compiler::line!(),
 			column: $crate::lang::compiler::column!(),
 			file: $crate::lang::compiler::file!()
@@ -180,6 +199,61 @@ leveled_log!(mkerror, error, Error);
 leveled_log!(mkfatal, fatal, Fatal);
 pub use warning as warn;
 
+#[doc(inline)]
+pub use crate::span;
+
+/// Logs an expression's source code and its [`Debug`] value at
+/// [`LogLevel::Trace`], then returns the value. Works like `std`'s `dbg!`, but
+/// sends the value through the logging runtime instead of printing it to
+/// stderr:
+///
+/// ```rs
+/// let area = dbg!(width * height);
+/// // Logs "... TRACE: width * height = 12"
+/// ```
+///
+/// Multiple expressions can be passed at once; they're returned as a tuple.
+/// If logging is disabled, this just returns the value(s).
+///
+/// [`Debug`]: crate::text::Debug
+#[cfg(logging)]
+#[macro_export]
+macro_rules! dbg {
+	() => {
+		$crate::rt::emit_log($crate::logging::mktrace!())
+	};
+	($val:expr $(,)?) => {
+		// `match` keeps temporaries in `$val` alive until the value is returned
+		match $val {
+			val => {
+				$crate::rt::emit_log($crate::logging::mktrace!(
+					"{} = {:#?}",
+					$crate::lang::compiler::stringify!($val),
+					&val
+				));
+				val
+			}
+		}
+	};
+	($($val:expr),+ $(,)?) => {
+		($($crate::logging::dbg!($val)),+,)
+	};
+}
+#[cfg(not(logging))]
+#[macro_export]
+macro_rules! dbg {
+	() => {
+		()
+	};
+	($val:expr $(,)?) => {
+		$val
+	};
+	($($val:expr),+ $(,)?) => {
+		($($val),+,)
+	};
+}
+pub use crate::dbg;
+
 //
 //
 // Logger trait & default impls
@@ -206,16 +280,18 @@ pub fn colour_formatter(log: Log) -> String {
 		level,
 		module,
 		msg,
+		spans,
 		line,
 		column,
 		file,
 	} = log;
+	let pad = if spans.is_empty() { "" } else { " " };
 	let colour = match level {
 		LogLevel::Trace | LogLevel::Info => FG_DEFAULT,
 		LogLevel::Warn => FG_YELLOW,
 		LogLevel::Error | LogLevel::Fatal => FG_RED,
 	};
-	format!("{colour}[{module} {RESET}<{file}@{line}:{column}>{colour}] {level}: {RESET}{msg}\n")
+	format!("{colour}[{module} {RESET}<{file}@{line}:{column}>{colour}] {level}{pad}{spans}: {RESET}{msg}\n")
 }
 /// Crux's default formatter for displaying plaintext [`Log`]s.
 pub fn default_formatter(log: Log) -> String {
@@ -223,11 +299,13 @@ pub fn default_formatter(log: Log) -> String {
 		level,
 		module,
 		msg,
+		spans,
 		line,
 		column,
 		file,
 	} = log;
-	format!("[{module} <{file}@{line}:{column}>] {level}: {msg}\n")
+	let pad = if spans.is_empty() { "" } else { " " };
+	format!("[{module} <{file}@{line}:{column}>] {level}{pad}{spans}: {msg}\n")
 }
 
 /// A logger that prints all logs to stdout.
@@ -0,0 +1,239 @@
+//! A logger that hands logs off to a background thread.
+
+use crate::{
+	concurrency::{AtomicOrdering, AtomicU32, AtomicUsize, lockfree::ArrayQueue, park, spawn},
+	logging::{Log, LogLevel, Logger},
+};
+
+/// What an [`AsyncLogger`] does when a log is emitted while its queue is
+/// full.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Backpressure {
+	/// Wait for the background thread to make room in the queue. No logs are
+	/// lost, but logging can stall the program if logs are emitted faster
+	/// than they can be written.
+	Block,
+	/// Throw away the oldest queued log to make room for the new one.
+	DropOldest,
+	/// Throw away the new log.
+	DropNewest,
+}
+
+/// Wraps another logger, and passes logs to it from a background thread.
+///
+/// Emitting a log just pushes it into a bounded queue, so logging in hot
+/// loops doesn't make a syscall for every log. If logs are emitted faster than
+/// the inner logger can handle them, the queue fills up, and the logger
+/// follows its [`Backpressure`] policy.
+///
+/// ```rs
+/// let logger = AsyncLogger::spawn(StdoutLogger::default(), 1024, Backpressure::Block);
+/// rt::set_logger(logger);
+/// ```
+///
+/// [`Fatal`] logs are written immediately after flushing the queue, since the
+/// program is usually about to exit when they're emitted.
+///
+/// [`Fatal`]: LogLevel::Fatal
+pub struct AsyncLogger<L: Logger + Send + Sync + 'static> {
+	logger: L,
+	queue: ArrayQueue<Log>,
+	policy: Backpressure,
+	/// Logs that have been emitted, but not yet written by the inner logger.
+	pending: AtomicUsize,
+	/// Bumped whenever a log is pushed, so the background thread can wait on
+	/// it.
+	pushed: AtomicU32,
+	/// Bumped whenever the background thread finishes writing a log, so
+	/// blocked threads can wait on it.
+	written: AtomicU32,
+	/// How many threads are waiting on `written`.
+	waiting: AtomicU32,
+	dropped: AtomicUsize,
+}
+impl<L: Logger + Send + Sync + 'static> AsyncLogger<L> {
+	/// Creates an async logger that can queue up to `capacity` logs, and
+	/// spawns the background thread that passes them to `logger`.
+	///
+	/// The async logger lives for the rest of the program, so it's leaked and
+	/// returned as a `&'static` reference, which can be passed directly to
+	/// [`rt::set_logger`].
+	///
+	/// [`rt::set_logger`]: crate::rt::set_logger
+	pub fn spawn(logger: L, capacity: usize, policy: Backpressure) -> &'static Self {
+		let this: &'static Self = Box::leak(Box::new(Self {
+			logger,
+			queue: ArrayQueue::new(capacity),
+			policy,
+			pending: AtomicUsize::new(0),
+			pushed: AtomicU32::new(0),
+			written: AtomicU32::new(0),
+			waiting: AtomicU32::new(0),
+			dropped: AtomicUsize::new(0),
+		}));
+		drop(spawn(move || this.drain()));
+
+		this
+	}
+
+	/// How many logs have been thrown away because the queue was full.
+	pub fn dropped(&self) -> usize {
+		self.dropped.load(AtomicOrdering::Relaxed)
+	}
+
+	/// Waits until every log emitted so far has been written by the inner
+	/// logger.
+	pub fn flush(&self) {
+		self.wait_for_write(|| self.pending.load(AtomicOrdering::Acquire) == 0);
+	}
+
+	/// The background thread's main loop.
+	fn drain(&self) {
+		loop {
+			let seen = self.pushed.load(AtomicOrdering::Acquire);
+			let Some(log) = self.queue.pop() else {
+				park::wait(&self.pushed, seen, None);
+				continue;
+			};
+
+			self.logger.log(log);
+			self.finish_log();
+		}
+	}
+
+	/// Marks a pending log as finished, and wakes up threads waiting for it.
+	fn finish_log(&self) {
+		self.pending.fetch_sub(1, AtomicOrdering::Release);
+		self.written.fetch_add(1, AtomicOrdering::SeqCst);
+		if self.waiting.load(AtomicOrdering::SeqCst) > 0 {
+			park::wake_all(&self.written);
+		}
+	}
+
+	/// Sleeps until the background thread writes a log and `done` returns
+	/// true.
+	fn wait_for_write(&self, mut done: impl FnMut() -> bool) {
+		self.waiting.fetch_add(1, AtomicOrdering::SeqCst);
+		loop {
+			let seen = self.written.load(AtomicOrdering::SeqCst);
+			if done() {
+				break;
+			}
+			park::wait(&self.written, seen, None);
+		}
+		self.waiting.fetch_sub(1, AtomicOrdering::SeqCst);
+	}
+}
+impl<L: Logger + Send + Sync + 'static> Logger for AsyncLogger<L> {
+	fn log(&self, mut log: Log) {
+		if log.level == LogLevel::Fatal {
+			self.flush();
+			self.logger.log(log);
+			return;
+		}
+
+		self.pending.fetch_add(1, AtomicOrdering::Relaxed);
+		loop {
+			match self.queue.push(log) {
+				Ok(()) => break,
+				Err(rejected) => log = rejected,
+			}
+
+			match self.policy {
+				Backpressure::Block => {
+					let mut rejected = Some(log);
+					self.wait_for_write(|| match self.queue.push(rejected.take().unwrap()) {
+						Ok(()) => true,
+						Err(log) => {
+							rejected = Some(log);
+							false
+						}
+					});
+					break;
+				}
+				Backpressure::DropOldest => {
+					if self.queue.pop().is_some() {
+						self.dropped.fetch_add(1, AtomicOrdering::Relaxed);
+						self.finish_log();
+					}
+				}
+				Backpressure::DropNewest => {
+					self.dropped.fetch_add(1, AtomicOrdering::Relaxed);
+					self.finish_log();
+					return;
+				}
+			}
+		}
+
+		self.pushed.fetch_add(1, AtomicOrdering::Release);
+		park::wake_one(&self.pushed);
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			concurrency::{Mutex, scope},
+			lang::{Cow, file, module_path},
+		},
+	};
+
+	struct Collect(Mutex<Vec<usize>>);
+	impl Logger for Collect {
+		fn log(&self, log: Log) {
+			self.0.lock().push(log.line as usize);
+		}
+	}
+
+	fn log(line: u32) -> Log {
+		Log {
+			level: LogLevel::Info,
+			module: module_path!(),
+			msg: Cow::Borrowed(""),
+			spans: Cow::Borrowed(""),
+			line,
+			column: 0,
+			file: file!(),
+		}
+	}
+
+	#[test]
+	fn async_logger() {
+		let logger = AsyncLogger::spawn(Collect(Mutex::new(Vec::new())), 4, Backpressure::Block);
+		scope(|scope| {
+			for thread in 0..4 {
+				scope.spawn(move || {
+					for idx in 0..100 {
+						logger.log(log(thread * 100 + idx));
+					}
+				});
+			}
+		});
+		logger.flush();
+
+		let mut lines = logger.logger.0.lock().clone();
+		lines.sort();
+		assert_eq!(lines, (0..400).collect::<Vec<_>>());
+		assert_eq!(logger.dropped(), 0);
+
+		// Stall the background thread, so the queue fills up
+		let logger =
+			AsyncLogger::spawn(Collect(Mutex::new(Vec::new())), 2, Backpressure::DropNewest);
+		let stall = logger.logger.0.lock();
+		for line in 0..10 {
+			logger.log(log(line));
+		}
+		drop(stall);
+		logger.flush();
+		assert!(logger.dropped() >= 7);
+		assert_eq!(logger.logger.0.lock().len(), 10 - logger.dropped());
+	}
+}
@@ -0,0 +1,190 @@
+//! A logger that sends logs to several other loggers.
+
+use crate::{
+	concurrency::Mutex,
+	logging::{Log, LogLevel, Logger, SyncLogger},
+};
+
+/// Identifies a logger that was added to a [`MultiLogger`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ChildId(usize);
+
+/// Sends each log to several child loggers. Each child has a minimum
+/// [`LogLevel`], and only receives logs at or above that level.
+///
+/// Children can be added, removed, and have their levels changed while the
+/// logger is in use:
+///
+/// ```rs
+/// static LOGGER: MultiLogger = MultiLogger::new();
+///
+/// let stdout = LOGGER.add(StdoutLogger::default(), LogLevel::Info);
+/// LOGGER.add(MyFileLogger::new(), LogLevel::Trace);
+/// rt::set_logger(&LOGGER);
+///
+/// // Later...
+/// LOGGER.set_level(stdout, LogLevel::Trace);
+/// ```
+///
+/// Children are called with the multi-logger's lock held, so a child that
+/// emits its own logs to the same multi-logger will deadlock.
+pub struct MultiLogger {
+	children: Mutex<Children>,
+}
+struct Children {
+	list: Vec<Child>,
+	next_id: usize,
+}
+struct Child {
+	id: ChildId,
+	level: LogLevel,
+	logger: Box<dyn SyncLogger + Send>,
+}
+impl MultiLogger {
+	/// Creates a multi-logger with no children.
+	pub const fn new() -> Self {
+		Self {
+			children: Mutex::new(Children {
+				list: Vec::new(),
+				next_id: 0,
+			}),
+		}
+	}
+
+	/// Adds a child logger, which will receive every log at or above `level`.
+	pub fn add(&self, logger: impl SyncLogger + Send + 'static, level: LogLevel) -> ChildId {
+		let mut children = self.children.lock();
+		let id = ChildId(children.next_id);
+		children.next_id += 1;
+		children.list.push(Child {
+			id,
+			level,
+			logger: Box::new(logger),
+		});
+
+		id
+	}
+	/// Removes a child logger. Returns false if it was already removed.
+	pub fn remove(&self, id: ChildId) -> bool {
+		let mut children = self.children.lock();
+		let Some(idx) = children.list.iter().position(|child| child.id == id) else {
+			return false;
+		};
+		children.list.remove(idx);
+
+		true
+	}
+
+	/// Changes the minimum level of logs a child logger receives. Returns
+	/// false if the child was removed.
+	pub fn set_level(&self, id: ChildId, level: LogLevel) -> bool {
+		match self
+			.children
+			.lock()
+			.list
+			.iter_mut()
+			.find(|child| child.id == id)
+		{
+			Some(child) => {
+				child.level = level;
+				true
+			}
+			None => false,
+		}
+	}
+	/// The minimum level of logs a child logger receives, or `None` if the
+	/// child was removed.
+	pub fn level(&self, id: ChildId) -> Option<LogLevel> {
+		self.children
+			.lock()
+			.list
+			.iter()
+			.find(|child| child.id == id)
+			.map(|child| child.level)
+	}
+}
+impl Default for MultiLogger {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl Logger for MultiLogger {
+	fn log(&self, log: Log) {
+		let children = self.children.lock();
+		let mut receivers = children
+			.list
+			.iter()
+			.filter(|child| log.level >= child.level)
+			.peekable();
+
+		while let Some(child) = receivers.next() {
+			// The last receiver can take the log instead of a copy of it
+			if receivers.peek().is_none() {
+				child.logger.log(log);
+				break;
+			}
+			child.logger.log(log.clone());
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			concurrency::Arc,
+			lang::{Cow, file, line, module_path},
+		},
+	};
+
+	struct Collect(Arc<Mutex<Vec<LogLevel>>>);
+	impl Logger for Collect {
+		fn log(&self, log: Log) {
+			self.0.lock().push(log.level);
+		}
+	}
+
+	fn log(level: LogLevel) -> Log {
+		Log {
+			level,
+			module: module_path!(),
+			msg: Cow::Borrowed(""),
+			spans: Cow::Borrowed(""),
+			line: line!(),
+			column: 0,
+			file: file!(),
+		}
+	}
+
+	#[test]
+	fn multi_logger() {
+		let verbose = Arc::new(Mutex::new(Vec::new()));
+		let quiet = Arc::new(Mutex::new(Vec::new()));
+		let logger = MultiLogger::new();
+		logger.add(Collect(verbose.clone()), LogLevel::Trace);
+		let quiet_id = logger.add(Collect(quiet.clone()), LogLevel::Warn);
+
+		logger.log(log(LogLevel::Trace));
+		logger.log(log(LogLevel::Error));
+		assert_eq!(*verbose.lock(), [LogLevel::Trace, LogLevel::Error]);
+		assert_eq!(*quiet.lock(), [LogLevel::Error]);
+
+		assert!(logger.set_level(quiet_id, LogLevel::Info));
+		assert_eq!(logger.level(quiet_id), Some(LogLevel::Info));
+		logger.log(log(LogLevel::Info));
+		assert_eq!(*quiet.lock(), [LogLevel::Error, LogLevel::Info]);
+
+		assert!(logger.remove(quiet_id));
+		assert!(!logger.remove(quiet_id));
+		logger.log(log(LogLevel::Fatal));
+		assert_eq!(quiet.lock().len(), 2);
+		assert_eq!(verbose.lock().len(), 4);
+	}
+}
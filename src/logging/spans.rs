@@ -0,0 +1,219 @@
+//! Spans, which attach context to every log emitted while they're active.
+//!
+//! A span is entered with the [`span`] macro, and exited when the returned
+//! guard is dropped. Spans nest; while any are active, every [`Log`] created
+//! on the same thread lists them in its [`Log::spans`] field, outermost first:
+//!
+//! ```rs
+//! fn handle(id: u32) {
+//!     let _span = span!("request", id = id);
+//!     info!("handling request");
+//!     // Logs "... INFO request{id=5}: handling request"
+//! }
+//! ```
+//!
+//! Entering and exiting a span also emits [`LogLevel::Trace`] logs, and the
+//! exit log says how long the span was active.
+//!
+//! [`span`]: crate::logging::span
+
+use crate::{
+	lang::{Cow, PhantomData, panic::Location},
+	logging::{Log, LogLevel},
+	rt::{self, time::Instant},
+	text::Debug,
+};
+
+/// An active span, stored in the current thread's span stack.
+struct ActiveSpan {
+	/// Distinguishes this span from spans entered later at the same depth.
+	id: u64,
+	/// The span's name and fields, e.g. `request{id=5}`.
+	label: String,
+	/// Every active span up to and including this one, e.g.
+	/// `server > request{id=5}`.
+	path: String,
+	module: &'static str,
+	location: &'static Location<'static>,
+	entered: Instant,
+}
+
+// Thread-locals' destructors don't run, so the stack's allocation is leaked
+// when a thread that used spans exits.
+#[thread_local]
+static mut STACK: Vec<ActiveSpan> = Vec::new();
+#[thread_local]
+static mut NEXT_ID: u64 = 0;
+
+fn stack() -> &'static mut Vec<ActiveSpan> {
+	unsafe { &mut *crate::lang::addr_of_mut!(STACK) }
+}
+
+/// Keeps a span active until it's dropped. Created by the [`span`] macro.
+///
+/// Spans are tied to the thread that entered them, so this can't be sent to
+/// other threads.
+///
+/// [`span`]: crate::logging::span
+#[must_use = "the span is exited as soon as its guard is dropped"]
+pub struct SpanGuard {
+	id: u64,
+	/// Where this span is in the span stack.
+	depth: usize,
+	_not_send: PhantomData<*const ()>,
+}
+impl Drop for SpanGuard {
+	fn drop(&mut self) {
+		let stack = stack();
+		// If an outer span was exited first, this span was already removed
+		if stack.get(self.depth).is_none_or(|span| span.id != self.id) {
+			return;
+		}
+		// Exit any spans nested in this one that are still active
+		stack.truncate(self.depth + 1);
+		let span = stack.pop().unwrap();
+
+		rt::emit_log(span_log(
+			&span,
+			format!("exited {} after {:?}", span.label, span.entered.elapsed()),
+		));
+	}
+}
+
+/// Enters a span. This is used by the [`span`] macro; use that instead.
+///
+/// [`span`]: crate::logging::span
+#[doc(hidden)]
+#[track_caller]
+pub fn enter(name: &'static str, module: &'static str, fields: &[(&str, &dyn Debug)]) -> SpanGuard {
+	let mut label = String::from(name);
+	if !fields.is_empty() {
+		label.push('{');
+		for (idx, (field, value)) in fields.iter().enumerate() {
+			if idx != 0 {
+				label.push_str(", ");
+			}
+			label.push_str(&format!("{field}={value:?}"));
+		}
+		label.push('}');
+	}
+
+	let id = unsafe {
+		let id = NEXT_ID;
+		NEXT_ID += 1;
+		id
+	};
+	let stack = stack();
+	let path = match stack.last() {
+		Some(parent) => format!("{} > {label}", parent.path),
+		None => label.clone(),
+	};
+	let span = ActiveSpan {
+		id,
+		label,
+		path,
+		module,
+		location: Location::caller(),
+		entered: Instant::now(),
+	};
+	let log = span_log(&span, format!("entered {}", span.label));
+	stack.push(span);
+	rt::emit_log(log);
+
+	SpanGuard {
+		id,
+		depth: stack.len() - 1,
+		_not_send: PhantomData,
+	}
+}
+
+/// Every span active on the current thread, outermost first and separated by
+/// ` > `. Empty if no spans are active.
+///
+/// This is what [`Log::spans`] is set to when a log is created.
+pub fn current() -> Cow<'static, str> {
+	match stack().last() {
+		Some(span) => Cow::Owned(span.path.clone()),
+		None => Cow::Borrowed(""),
+	}
+}
+
+fn span_log(span: &ActiveSpan, msg: String) -> Log {
+	Log {
+		level: LogLevel::Trace,
+		module: span.module,
+		msg: Cow::Owned(msg),
+		spans: current(),
+		line: span.location.line(),
+		column: span.location.column(),
+		file: span.location.file(),
+	}
+}
+
+/// Enters a span with a name and optional fields, which is exited when the
+/// returned [`SpanGuard`] is dropped. See the [module-level docs].
+///
+/// ```rs
+/// let _span = span!("request", id = id, user = user.name);
+/// ```
+///
+/// Field values are formatted with [`Debug`].
+///
+/// [module-level docs]: crate::logging::spans
+#[cfg(logging)]
+#[macro_export]
+macro_rules! span {
+	($name:literal $(, $field:ident = $value:expr)* $(,)?) => {
+		$crate::logging::spans::enter(
+			$name,
+			$crate::lang::compiler::module_path!(),
+			&[$((
+				$crate::lang::compiler::stringify!($field),
+				&$value as &dyn $crate::text::Debug,
+			)),*],
+		)
+	};
+}
+#[cfg(not(logging))]
+#[macro_export]
+macro_rules! span {
+	($($t:tt)*) => {
+		()
+	};
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::lang::module_path};
+
+	#[test]
+	fn spans() {
+		assert_eq!(current(), "");
+		{
+			let _outer = enter("outer", module_path!(), &[]);
+			assert_eq!(current(), "outer");
+			{
+				let _inner = enter("inner", module_path!(), &[("id", &5), ("name", &"x")]);
+				assert_eq!(current(), "outer > inner{id=5, name=\"x\"}");
+			}
+			assert_eq!(current(), "outer");
+		}
+		assert_eq!(current(), "");
+
+		// Exiting an outer span exits the spans inside it too
+		let outer = enter("outer", module_path!(), &[]);
+		let inner = enter("inner", module_path!(), &[]);
+		drop(outer);
+		let _next = enter("next", module_path!(), &[]);
+		let _nested = enter("nested", module_path!(), &[]);
+		// `inner` was already exited, so this shouldn't exit `nested`
+		drop(inner);
+		assert_eq!(current(), "next > nested");
+	}
+}
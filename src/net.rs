@@ -0,0 +1,10 @@
+//! Network protocols, built on the TCP sockets in [`crate::rt::os::net`].
+
+pub mod http;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod websocket;
+
+pub use crate::rt::os::net::{
+	ConnectError, IpAddr, ResolveError, SocketAddr, TcpListener, TcpStream, resolve,
+};
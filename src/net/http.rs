@@ -0,0 +1,1065 @@
+//! A minimal, blocking HTTP/1.1 client, and a [server](server).
+//!
+//! ```rs
+//! let client = Client::new();
+//! let mut response = client.get("http://example.com/")?;
+//! assert_eq!(response.status(), 200);
+//! println!("{:?}", response.header("Content-Type"));
+//!
+//! let mut body = Vec::new();
+//! response.read_to_end(&mut body)?;
+//! ```
+//!
+//! Every request opens a new connection, which is closed once the response is
+//! dropped. Responses implement [`Reader`], so their bodies can be streamed
+//! instead of being read into memory all at once. `https://` URLs need the
+//! `tls` feature.
+
+pub mod server;
+
+pub use self::server::{Request, ResponseWriter, Server};
+
+#[cfg(feature = "tls")]
+use super::tls::{TlsConnector, TlsError, TlsStream};
+use {
+	super::{ConnectError, TcpStream},
+	crate::{
+		io::{Reader, Writer},
+		lang::{Infallible, Range, cfg},
+		rt::os,
+		text::{Display, str_from_utf8, write},
+	},
+};
+
+/// The most bytes a message's start line and headers can take up.
+const MAX_HEAD_LEN: usize = 64 * 1024;
+/// The most bytes a chunk size line or trailer in a chunked body can take up.
+const MAX_LINE_LEN: usize = 4 * 1024;
+/// How many bytes are read from the connection at once.
+const BUF_LEN: usize = 8 * 1024;
+
+/// An HTTP request method.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Method {
+	Get,
+	Head,
+	Post,
+	Put,
+	Delete,
+	Patch,
+	Options,
+}
+impl Method {
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Get => "GET",
+			Self::Head => "HEAD",
+			Self::Post => "POST",
+			Self::Put => "PUT",
+			Self::Delete => "DELETE",
+			Self::Patch => "PATCH",
+			Self::Options => "OPTIONS",
+		}
+	}
+	/// Parses a method's name, which is case-sensitive. Returns `None` for
+	/// methods that aren't supported.
+	pub fn parse(name: &str) -> Option<Self> {
+		Some(match name {
+			"GET" => Self::Get,
+			"HEAD" => Self::Head,
+			"POST" => Self::Post,
+			"PUT" => Self::Put,
+			"DELETE" => Self::Delete,
+			"PATCH" => Self::Patch,
+			"OPTIONS" => Self::Options,
+			_ => return None,
+		})
+	}
+	/// Whether requests with this method normally have a body, so an empty
+	/// body still needs a `Content-Length`.
+	const fn expects_body(self) -> bool {
+		matches!(self, Self::Post | Self::Put | Self::Patch)
+	}
+}
+
+/// A header in a request or response.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Header<'a> {
+	pub name: &'a str,
+	pub value: &'a str,
+}
+impl<'a> Header<'a> {
+	pub const fn new(name: &'a str, value: &'a str) -> Self {
+		Self { name, value }
+	}
+}
+
+/// The parts of an `http://` or `https://` URL that are needed to make a
+/// request.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Url<'a> {
+	/// Whether the URL is `https://`, so the connection has to be encrypted.
+	pub secure: bool,
+	/// The host, without the brackets around IPv6 addresses.
+	pub host: &'a str,
+	pub port: u16,
+	/// The path and query. Empty if the URL doesn't have either.
+	pub path: &'a str,
+	/// The host and port as they appeared in the URL, for the `Host` header.
+	authority: &'a str,
+}
+impl<'a> Url<'a> {
+	pub fn parse(url: &'a str) -> Result<Self, HttpError> {
+		let (rest, secure) = if let Some(rest) = url.strip_prefix("http://") {
+			(rest, false)
+		} else if let Some(rest) = url.strip_prefix("https://") {
+			(rest, true)
+		} else if url.contains("://") {
+			return Err(HttpError::UnsupportedScheme);
+		} else {
+			return Err(HttpError::InvalidUrl);
+		};
+		// The fragment is only for the client
+		let rest = rest.split('#').next().unwrap();
+		let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+
+		let (host, port) = match authority.strip_prefix('[') {
+			Some(v6) => {
+				let (host, port) = v6.split_once(']').ok_or(HttpError::InvalidUrl)?;
+				match port {
+					"" => (host, None),
+					port => (
+						host,
+						Some(port.strip_prefix(':').ok_or(HttpError::InvalidUrl)?),
+					),
+				}
+			}
+			None => match authority.split_once(':') {
+				Some((host, port)) => (host, Some(port)),
+				None => (authority, None),
+			},
+		};
+		// Credentials in URLs aren't supported
+		if host.is_empty() || host.contains(['@', ' ']) {
+			return Err(HttpError::InvalidUrl);
+		}
+		let port = match port {
+			Some(port) => port.parse().map_err(|_| HttpError::InvalidUrl)?,
+			None if secure => 443,
+			None => 80,
+		};
+
+		Ok(Self {
+			secure,
+			host,
+			port,
+			path,
+			authority,
+		})
+	}
+}
+
+/// Makes HTTP requests. See the [module-level docs](self).
+#[derive(Clone, Debug)]
+pub struct Client {
+	/// Headers sent with every request.
+	headers: Vec<(String, String)>,
+}
+impl Default for Client {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl Client {
+	/// Creates a client that identifies itself with `User-Agent: crux`.
+	pub fn new() -> Self {
+		Self {
+			headers: Vec::from([(String::from("User-Agent"), String::from("crux"))]),
+		}
+	}
+
+	/// Sends `header` with every request, replacing any header this client
+	/// already sends with the same name.
+	pub fn set_header(&mut self, header: Header<'_>) -> Result<(), HttpError> {
+		validate_header(header)?;
+		self.headers
+			.retain(|(name, _)| !name.eq_ignore_ascii_case(header.name));
+		self.headers
+			.push((String::from(header.name), String::from(header.value)));
+		Ok(())
+	}
+
+	/// Sends a `GET` request to `url`.
+	pub fn get(&self, url: &str) -> Result<Response, HttpError> {
+		self.request(Method::Get, url, &[], &[])
+	}
+	/// Sends a `POST` request with `body` to `url`. `headers` should usually
+	/// include a `Content-Type`.
+	pub fn post(
+		&self,
+		url: &str,
+		headers: &[Header<'_>],
+		body: &[u8],
+	) -> Result<Response, HttpError> {
+		self.request(Method::Post, url, headers, body)
+	}
+	/// Sends a request to `url`, and waits for the response's headers.
+	/// `Host`, `Connection` and `Content-Length` are set automatically.
+	pub fn request(
+		&self,
+		method: Method,
+		url: &str,
+		headers: &[Header<'_>],
+		body: &[u8],
+	) -> Result<Response, HttpError> {
+		let url = Url::parse(url)?;
+		let mut head = Vec::new();
+		write_head(&mut head, method, url, "close", self.headers(headers))?;
+		if !body.is_empty() || method.expects_body() {
+			// Writing to a `Vec` can't fail
+			write!(head, "Content-Length: {}\r\n", body.len()).unwrap();
+		}
+		head.extend_from_slice(b"\r\n");
+
+		let mut stream = Stream::connect(url)?;
+		stream.write_all(&head)?;
+		stream.write_all(body)?;
+		Response::from_stream(stream, method)
+	}
+	/// Like [`Client::request`], but streams the body from `body` instead of
+	/// sending it all at once. Since its length isn't known up front, it's
+	/// sent with chunked transfer encoding.
+	pub fn request_streaming<R: Reader>(
+		&self,
+		method: Method,
+		url: &str,
+		headers: &[Header<'_>],
+		mut body: R,
+	) -> Result<Response, HttpError>
+	where
+		R::Error: Into<HttpError>,
+	{
+		let url = Url::parse(url)?;
+		let mut head = Vec::new();
+		write_head(&mut head, method, url, "close", self.headers(headers))?;
+		head.extend_from_slice(b"Transfer-Encoding: chunked\r\n\r\n");
+
+		let mut stream = Stream::connect(url)?;
+		stream.write_all(&head)?;
+		let mut chunk = [0; BUF_LEN];
+		loop {
+			let len = match body.read(&mut chunk) {
+				Ok(0) => break,
+				Ok(len) => len,
+				Err(err) if R::is_interrupted(&err) => continue,
+				Err(err) => return Err(err.into()),
+			};
+			head.clear();
+			write!(head, "{len:x}\r\n").unwrap();
+			stream.write_all(&head)?;
+			stream.write_all(&chunk[..len])?;
+			stream.write_all(b"\r\n")?;
+		}
+		stream.write_all(b"0\r\n\r\n")?;
+
+		Response::from_stream(stream, method)
+	}
+	/// Sends a `GET` request to `url` that asks the server to switch the
+	/// connection to another protocol, e.g. `websocket`. If the server agrees,
+	/// it responds with status `101`, and [`Response::into_stream`] gives
+	/// back the connection for the new protocol to use.
+	pub fn upgrade(
+		&self,
+		url: &str,
+		protocol: &str,
+		headers: &[Header<'_>],
+	) -> Result<Response, HttpError> {
+		let url = Url::parse(url)?;
+		let upgrade = [Header::new("Upgrade", protocol)];
+		let headers = self.headers(headers).chain(upgrade);
+		let mut head = Vec::new();
+		write_head(&mut head, Method::Get, url, "Upgrade", headers)?;
+		head.extend_from_slice(b"\r\n");
+
+		let mut stream = Stream::connect(url)?;
+		stream.write_all(&head)?;
+		Response::from_stream(stream, Method::Get)
+	}
+
+	/// This client's headers, followed by `extra`.
+	fn headers<'a>(&'a self, extra: &'a [Header<'a>]) -> impl Iterator<Item = Header<'a>> {
+		self.headers
+			.iter()
+			.map(|(name, value)| Header::new(name, value))
+			.chain(extra.iter().copied())
+	}
+}
+
+/// A connection to a server, which is encrypted for `https://` URLs.
+#[derive(Debug)]
+pub enum Stream {
+	Tcp(TcpStream),
+	#[cfg(feature = "tls")]
+	Tls(TlsStream<TcpStream>),
+}
+impl Stream {
+	/// Connects to the server `url` points to. `https://` URLs use a new
+	/// `TlsConnector`, which trusts the system's root certificates.
+	pub fn connect(url: Url<'_>) -> Result<Self, HttpError> {
+		if url.secure && cfg!(not(feature = "tls")) {
+			return Err(HttpError::UnsupportedScheme);
+		}
+
+		let stream = TcpStream::connect_to_host(url.host, url.port)?;
+		#[cfg(feature = "tls")]
+		if url.secure {
+			return Ok(Self::Tls(TlsConnector::new()?.connect(url.host, stream)?));
+		}
+		Ok(Self::Tcp(stream))
+	}
+}
+impl Reader for Stream {
+	type Error = HttpError;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		match self {
+			Self::Tcp(stream) => Ok(stream.read(buf)?),
+			#[cfg(feature = "tls")]
+			Self::Tls(stream) => Ok(stream.read(buf)?),
+		}
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		matches!(error, HttpError::Os(err) if err.kind() == os::ErrorKind::Interrupted)
+	}
+}
+impl Writer for Stream {
+	type Error = HttpError;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		match self {
+			Self::Tcp(stream) => Ok(stream.write(bytes)?),
+			#[cfg(feature = "tls")]
+			Self::Tls(stream) => Ok(stream.write(bytes)?),
+		}
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		match self {
+			Self::Tcp(stream) => Ok(stream.flush()?),
+			#[cfg(feature = "tls")]
+			Self::Tls(stream) => Ok(stream.flush()?),
+		}
+	}
+}
+
+/// Writes a request's line and headers, without the empty line that ends
+/// them. `connection` is the `Connection` header's value.
+fn write_head<'a>(
+	head: &mut Vec<u8>,
+	method: Method,
+	url: Url<'_>,
+	connection: &str,
+	headers: impl Iterator<Item = Header<'a>>,
+) -> Result<(), HttpError> {
+	let slash = if url.path.starts_with('/') { "" } else { "/" };
+	write!(
+		head,
+		"{} {slash}{} HTTP/1.1\r\nHost: {}\r\nConnection: {connection}\r\n",
+		method.as_str(),
+		url.path,
+		url.authority,
+	)
+	.unwrap();
+	for header in headers {
+		validate_header(header)?;
+		write!(head, "{}: {}\r\n", header.name, header.value).unwrap();
+	}
+
+	Ok(())
+}
+/// Makes sure a header can't end early and inject other headers.
+fn validate_header(header: Header<'_>) -> Result<(), HttpError> {
+	let valid_name = !header.name.is_empty()
+		&& header
+			.name
+			.bytes()
+			.all(|byte| byte.is_ascii_graphic() && byte != b':');
+	let valid_value = !header.value.contains(['\r', '\n', '\0']);
+	if valid_name && valid_value {
+		Ok(())
+	} else {
+		Err(HttpError::InvalidHeader)
+	}
+}
+
+/// A response to an HTTP request. Reading from it reads the response's body,
+/// with any transfer encoding removed.
+#[derive(Debug)]
+pub struct Response<S = Stream> {
+	stream: BufStream<S>,
+	/// The status line and headers.
+	head: String,
+	status: u16,
+	reason: Range<usize>,
+	headers: HeaderRanges,
+	body: Body,
+}
+impl<S: Reader> Response<S>
+where
+	HttpError: From<S::Error>,
+{
+	/// Reads a response's status line and headers from `stream`. `method` is
+	/// the method of the request it's a response to, since responses to `HEAD`
+	/// requests don't have a body.
+	///
+	/// Informational (`1xx`) responses are skipped, except for `101 Switching
+	/// Protocols`, which ends the HTTP part of the connection.
+	pub fn from_stream(stream: S, method: Method) -> Result<Self, HttpError> {
+		let mut stream = BufStream::new(stream);
+		let mut this = loop {
+			let head = stream.read_head()?;
+			let mut lines = head.lines();
+			let (status, reason) = parse_status_line(lines.next().unwrap_or_default())?;
+			let reason = range_in(&head, reason);
+			let headers = parse_headers(&head, lines)?;
+			if status == 101 || !(100..200).contains(&status) {
+				break Self {
+					stream,
+					head,
+					status,
+					reason,
+					headers,
+					body: Body::UntilClose,
+				};
+			}
+		};
+
+		this.body = if method == Method::Head || matches!(this.status, 101 | 204 | 304) {
+			Body::Done
+		} else if let Some(encoding) = this.header("Transfer-Encoding") {
+			// Any other encoding is applied on top of chunked encoding, which
+			// is always last, so the body can't be decoded
+			if is_chunked(encoding) {
+				Body::Chunked(0)
+			} else {
+				Body::UntilClose
+			}
+		} else if let Some(len) = this.header("Content-Length") {
+			Body::from_length(len)?
+		} else {
+			Body::UntilClose
+		};
+
+		Ok(this)
+	}
+}
+impl<S> Response<S> {
+	/// The status code, e.g. `200`.
+	pub fn status(&self) -> u16 {
+		self.status
+	}
+	/// The text after the status code, e.g. `OK`. Clients shouldn't rely on
+	/// this; it's often empty.
+	pub fn reason(&self) -> &str {
+		&self.head[self.reason.clone()]
+	}
+	/// Every header in the response, in the order they were sent.
+	pub fn headers(&self) -> impl Iterator<Item = Header<'_>> {
+		headers_in(&self.head, &self.headers)
+	}
+	/// The value of the first header called `name`, which is case-insensitive.
+	pub fn header(&self, name: &str) -> Option<&str> {
+		find_header(self.headers(), name)
+	}
+
+	/// Takes back the connection, e.g. to use another protocol after a `101
+	/// Switching Protocols` response. Also returns the bytes that were read
+	/// from the connection but haven't been used yet, which have to be handled
+	/// before reading anything else. Any of the body that hasn't been read is
+	/// lost.
+	pub fn into_stream(self) -> (S, Vec<u8>) {
+		let BufStream {
+			inner,
+			mut buf,
+			pos,
+		} = self.stream;
+		buf.drain(..pos);
+		(inner, buf)
+	}
+}
+impl<S: Reader> Reader for Response<S>
+where
+	HttpError: From<S::Error>,
+{
+	type Error = HttpError;
+
+	/// Reads some of the body. Returns 0 once all of it has been read.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		self.body.read(&mut self.stream, buf)
+	}
+}
+
+/// How much of a message's body is left.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Body {
+	/// There's a `Content-Length`, and this many bytes haven't been read yet.
+	Length(u64),
+	/// The body uses chunked transfer encoding, and this many bytes of the
+	/// current chunk haven't been read yet. 0 means the next chunk's size
+	/// hasn't been read.
+	Chunked(u64),
+	/// The body ends when the server closes the connection.
+	UntilClose,
+	Done,
+}
+impl Body {
+	/// The body for a `Content-Length` header with the value `len`.
+	fn from_length(len: &str) -> Result<Self, HttpError> {
+		match len.parse().map_err(|_| HttpError::InvalidMessage)? {
+			0 => Ok(Self::Done),
+			len => Ok(Self::Length(len)),
+		}
+	}
+
+	/// Reads some of the body from `stream`, removing any transfer encoding.
+	/// Returns 0 once all of it has been read.
+	fn read<S: Reader>(
+		&mut self,
+		stream: &mut BufStream<S>,
+		buf: &mut [u8],
+	) -> Result<usize, HttpError>
+	where
+		HttpError: From<S::Error>,
+	{
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			match *self {
+				Self::Done => return Ok(0),
+				Self::Length(remaining) => {
+					let len = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+					let read = stream.read(&mut buf[..len])?;
+					if read == 0 {
+						return Err(HttpError::UnexpectedEof);
+					}
+					let remaining = remaining - read as u64;
+					*self = if remaining == 0 {
+						Self::Done
+					} else {
+						Self::Length(remaining)
+					};
+					return Ok(read);
+				}
+				Self::UntilClose => {
+					let read = stream.read(buf)?;
+					if read == 0 {
+						*self = Self::Done;
+					}
+					return Ok(read);
+				}
+				Self::Chunked(0) => {
+					let mut line = Vec::new();
+					stream.read_line(&mut line, MAX_LINE_LEN)?;
+					// Chunk extensions come after a `;`, and can be ignored
+					let size = trim_newline(&line)
+						.split(|byte| *byte == b';')
+						.next()
+						.unwrap();
+					let size = str_from_utf8(size)
+						.ok()
+						.and_then(|size| u64::from_str_radix(size.trim(), 16).ok())
+						.ok_or(HttpError::InvalidMessage)?;
+					if size > 0 {
+						*self = Self::Chunked(size);
+						continue;
+					}
+
+					// Trailers aren't exposed, so they're skipped
+					loop {
+						line.clear();
+						stream.read_line(&mut line, MAX_LINE_LEN)?;
+						if trim_newline(&line).is_empty() {
+							break;
+						}
+					}
+					*self = Self::Done;
+				}
+				Self::Chunked(remaining) => {
+					let len = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+					let read = stream.read(&mut buf[..len])?;
+					if read == 0 {
+						return Err(HttpError::UnexpectedEof);
+					}
+					let remaining = remaining - read as u64;
+					if remaining == 0 {
+						// Each chunk's data is followed by a newline
+						let mut line = Vec::new();
+						stream.read_line(&mut line, MAX_LINE_LEN)?;
+						if !trim_newline(&line).is_empty() {
+							return Err(HttpError::InvalidMessage);
+						}
+					}
+					*self = Self::Chunked(remaining);
+					return Ok(read);
+				}
+			}
+		}
+	}
+}
+
+/// The name and value of each header in a message, as ranges in its head.
+type HeaderRanges = Vec<(Range<usize>, Range<usize>)>;
+
+/// Parses a response's status line. Returns the status code and the reason
+/// phrase.
+fn parse_status_line(line: &str) -> Result<(u16, &str), HttpError> {
+	let rest = line
+		.strip_prefix("HTTP/1.")
+		.and_then(|rest| rest.get(1..))
+		.and_then(|rest| rest.strip_prefix(' '))
+		.ok_or(HttpError::InvalidMessage)?;
+	let (status, reason) = rest.split_at(rest.len().min(3));
+	if status.len() != 3 || !status.bytes().all(|byte| byte.is_ascii_digit()) {
+		return Err(HttpError::InvalidMessage);
+	}
+
+	Ok((status.parse().unwrap(), reason.trim_start_matches(' ')))
+}
+/// Parses the header lines of a message's head.
+fn parse_headers<'a>(
+	head: &str,
+	lines: impl Iterator<Item = &'a str>,
+) -> Result<HeaderRanges, HttpError> {
+	let mut headers = Vec::new();
+	for line in lines {
+		// Headers continued on the next line were deprecated in RFC 7230
+		if line.starts_with([' ', '\t']) {
+			return Err(HttpError::InvalidMessage);
+		}
+		let (name, value) = line.split_once(':').ok_or(HttpError::InvalidMessage)?;
+		if name.is_empty() || name.contains([' ', '\t']) {
+			return Err(HttpError::InvalidMessage);
+		}
+		headers.push((
+			range_in(head, name),
+			range_in(head, value.trim_matches([' ', '\t'])),
+		));
+	}
+
+	Ok(headers)
+}
+/// The range `slice` takes up in `head`, which it must be part of.
+fn range_in(head: &str, slice: &str) -> Range<usize> {
+	let start = slice.as_ptr() as usize - head.as_ptr() as usize;
+	start..start + slice.len()
+}
+fn headers_in<'a>(head: &'a str, headers: &'a HeaderRanges) -> impl Iterator<Item = Header<'a>> {
+	headers.iter().map(|(name, value)| Header {
+		name: &head[name.clone()],
+		value: &head[value.clone()],
+	})
+}
+fn find_header<'a>(mut headers: impl Iterator<Item = Header<'a>>, name: &str) -> Option<&'a str> {
+	headers
+		.find(|header| header.name.eq_ignore_ascii_case(name))
+		.map(|header| header.value)
+}
+/// Whether a `Transfer-Encoding` header's value ends with chunked encoding.
+fn is_chunked(encoding: &str) -> bool {
+	let last = encoding.rsplit(',').next().unwrap().trim();
+	last.eq_ignore_ascii_case("chunked")
+}
+
+/// Removes the `\r\n` or `\n` from the end of a line.
+fn trim_newline(line: &[u8]) -> &[u8] {
+	let line = line.strip_suffix(b"\n").unwrap_or(line);
+	line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Buffers reads from a connection, so lines can be read without reading
+/// past their end one byte at a time.
+#[derive(Debug)]
+struct BufStream<S> {
+	inner: S,
+	buf: Vec<u8>,
+	/// How much of `buf` has been used already.
+	pos: usize,
+}
+impl<S> BufStream<S> {
+	fn new(inner: S) -> Self {
+		Self {
+			inner,
+			buf: Vec::new(),
+			pos: 0,
+		}
+	}
+}
+impl<S: Reader> BufStream<S>
+where
+	HttpError: From<S::Error>,
+{
+	/// Returns the buffered bytes, reading more if there aren't any. Returns
+	/// an empty slice once the connection is closed.
+	fn fill(&mut self) -> Result<&[u8], HttpError> {
+		if self.pos == self.buf.len() {
+			self.buf.resize(BUF_LEN, 0);
+			let read = loop {
+				match self.inner.read(&mut self.buf) {
+					Ok(read) => break read,
+					Err(err) if S::is_interrupted(&err) => {}
+					Err(err) => {
+						self.buf.clear();
+						return Err(err.into());
+					}
+				}
+			};
+			self.buf.truncate(read);
+			self.pos = 0;
+		}
+
+		Ok(&self.buf[self.pos..])
+	}
+	fn read(&mut self, out: &mut [u8]) -> Result<usize, HttpError> {
+		let buffered = self.fill()?;
+		let len = buffered.len().min(out.len());
+		out[..len].copy_from_slice(&buffered[..len]);
+		self.pos += len;
+		Ok(len)
+	}
+	/// Reads a message's start line and headers, up to the empty line that
+	/// ends them.
+	fn read_head(&mut self) -> Result<String, HttpError> {
+		let mut head = Vec::new();
+		loop {
+			let start = head.len();
+			self.read_line(&mut head, MAX_HEAD_LEN)?;
+			if trim_newline(&head[start..]).is_empty() {
+				head.truncate(start);
+				break;
+			}
+		}
+
+		Ok(String::from(
+			str_from_utf8(&head).map_err(|_| HttpError::InvalidMessage)?,
+		))
+	}
+	/// Appends a line, including its newline, to `line`. Errors if the line
+	/// would make `line` longer than `max`.
+	fn read_line(&mut self, line: &mut Vec<u8>, max: usize) -> Result<(), HttpError> {
+		loop {
+			let buffered = self.fill()?;
+			if buffered.is_empty() {
+				return Err(HttpError::UnexpectedEof);
+			}
+			let (len, done) = match buffered.iter().position(|byte| *byte == b'\n') {
+				Some(idx) => (idx + 1, true),
+				None => (buffered.len(), false),
+			};
+			if line.len() + len > max {
+				return Err(HttpError::HeadTooLarge);
+			}
+			line.extend_from_slice(&buffered[..len]);
+			self.pos += len;
+			if done {
+				return Ok(());
+			}
+		}
+	}
+}
+
+/// An error from sending or receiving an HTTP message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HttpError {
+	/// The URL couldn't be parsed.
+	InvalidUrl,
+	/// The URL's scheme isn't `http`, or is `https` without the `tls`
+	/// feature.
+	UnsupportedScheme,
+	/// A header's name or value contained characters that aren't allowed,
+	/// such as newlines.
+	InvalidHeader,
+	Connect(ConnectError),
+	Os(os::Error),
+	#[cfg(feature = "tls")]
+	Tls(TlsError),
+	/// A request or response wasn't valid HTTP/1.1.
+	InvalidMessage,
+	/// A message's headers, or a line in a chunked body, were too long.
+	HeadTooLarge,
+	/// The connection was closed before the whole message was read.
+	UnexpectedEof,
+	/// A [`ResponseWriter`] was changed after its headers were sent.
+	AlreadySent,
+}
+impl From<ConnectError> for HttpError {
+	fn from(err: ConnectError) -> Self {
+		Self::Connect(err)
+	}
+}
+impl From<os::Error> for HttpError {
+	fn from(err: os::Error) -> Self {
+		Self::Os(err)
+	}
+}
+#[cfg(feature = "tls")]
+impl From<TlsError> for HttpError {
+	fn from(err: TlsError) -> Self {
+		Self::Tls(err)
+	}
+}
+impl From<Infallible> for HttpError {
+	fn from(err: Infallible) -> Self {
+		match err {}
+	}
+}
+impl Display for HttpError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::InvalidUrl => f.write_str("invalid URL"),
+			Self::UnsupportedScheme if cfg!(feature = "tls") => {
+				f.write_str("only http:// and https:// URLs are supported")
+			}
+			Self::UnsupportedScheme => f.write_str("only http:// URLs are supported"),
+			Self::InvalidHeader => f.write_str("invalid header"),
+			Self::Connect(err) => write!(f, "couldn't connect: {err}"),
+			Self::Os(err) => write!(f, "{err}"),
+			#[cfg(feature = "tls")]
+			Self::Tls(err) => write!(f, "TLS error: {err}"),
+			Self::InvalidMessage => f.write_str("invalid HTTP message"),
+			Self::HeadTooLarge => f.write_str("message headers are too large"),
+			Self::UnexpectedEof => f.write_str("connection closed before the message ended"),
+			Self::AlreadySent => f.write_str("the response's headers have already been sent"),
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Returns a few bytes at a time, so lines and chunks get split across
+	/// reads.
+	#[derive(Debug)]
+	struct Trickle<'a>(&'a [u8]);
+	impl Reader for Trickle<'_> {
+		type Error = Infallible;
+
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+			let len = buf.len().min(self.0.len()).min(3);
+			buf[..len].copy_from_slice(&self.0[..len]);
+			self.0 = &self.0[len..];
+			Ok(len)
+		}
+	}
+
+	fn parse(bytes: &[u8], method: Method) -> Result<(Response<Trickle<'_>>, Vec<u8>), HttpError> {
+		let mut response = Response::from_stream(Trickle(bytes), method)?;
+		let mut body = Vec::new();
+		response.read_to_end(&mut body)?;
+		Ok((response, body))
+	}
+
+	#[test]
+	fn urls() {
+		assert_eq!(
+			Url::parse("http://example.com"),
+			Ok(Url {
+				secure: false,
+				host: "example.com",
+				port: 80,
+				path: "",
+				authority: "example.com",
+			})
+		);
+		assert_eq!(
+			Url::parse("http://[::1]:8080/a/b?c=d#e"),
+			Ok(Url {
+				secure: false,
+				host: "::1",
+				port: 8080,
+				path: "/a/b?c=d",
+				authority: "[::1]:8080",
+			})
+		);
+		assert_eq!(Url::parse("http://localhost?q").unwrap().path, "?q");
+		assert_eq!(
+			Url::parse("https://example.com/"),
+			Ok(Url {
+				secure: true,
+				host: "example.com",
+				port: 443,
+				path: "/",
+				authority: "example.com",
+			})
+		);
+		assert_eq!(
+			Url::parse("ftp://example.com"),
+			Err(HttpError::UnsupportedScheme)
+		);
+		assert_eq!(Url::parse("example.com"), Err(HttpError::InvalidUrl));
+		assert_eq!(Url::parse("http://:80/"), Err(HttpError::InvalidUrl));
+		assert_eq!(Url::parse("http://host:port/"), Err(HttpError::InvalidUrl));
+		assert_eq!(Url::parse("http://user@host/"), Err(HttpError::InvalidUrl));
+	}
+
+	#[test]
+	fn request_head() {
+		let url = Url::parse("http://localhost:8080?q").unwrap();
+		let mut head = Vec::new();
+		let headers = [Header::new("Accept", "*/*")];
+		write_head(&mut head, Method::Get, url, "close", headers.into_iter()).unwrap();
+		assert_eq!(
+			head,
+			b"GET /?q HTTP/1.1\r\nHost: localhost:8080\r\nConnection: close\r\nAccept: */*\r\n"
+		);
+
+		let mut client = Client::new();
+		assert_eq!(
+			client.set_header(Header::new("X-Evil", "a\r\nHost: evil.com")),
+			Err(HttpError::InvalidHeader)
+		);
+		assert_eq!(
+			client.set_header(Header::new("Bad Name", "value")),
+			Err(HttpError::InvalidHeader)
+		);
+		client
+			.set_header(Header::new("user-agent", "test"))
+			.unwrap();
+		assert_eq!(
+			client.headers(&[]).collect::<Vec<_>>(),
+			[Header::new("user-agent", "test")]
+		);
+	}
+
+	#[test]
+	fn content_length() {
+		let (response, body) = parse(
+			b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\ncontent-length:  5 \r\n\r\nhello, extra",
+			Method::Get,
+		)
+		.unwrap();
+		assert_eq!(response.status(), 200);
+		assert_eq!(response.reason(), "OK");
+		assert_eq!(response.header("Content-Length"), Some("5"));
+		assert_eq!(
+			response.headers().collect::<Vec<_>>(),
+			[
+				Header::new("Content-Type", "text/plain"),
+				Header::new("content-length", "5"),
+			]
+		);
+		assert_eq!(body, b"hello");
+
+		assert_eq!(
+			parse(
+				b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort",
+				Method::Get
+			)
+			.unwrap_err(),
+			HttpError::UnexpectedEof
+		);
+	}
+
+	#[test]
+	fn chunked() {
+		let (response, body) = parse(
+			b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+			  5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nTrailer: x\r\n\r\n",
+			Method::Get,
+		)
+		.unwrap();
+		assert_eq!(response.status(), 200);
+		assert_eq!(body, b"hello, world");
+
+		assert_eq!(
+			parse(
+				b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhelloX\r\n0\r\n\r\n",
+				Method::Get,
+			)
+			.unwrap_err(),
+			HttpError::InvalidMessage
+		);
+		assert_eq!(
+			parse(
+				b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+				Method::Get,
+			)
+			.unwrap_err(),
+			HttpError::InvalidMessage
+		);
+	}
+
+	#[test]
+	fn bodyless() {
+		let head = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n";
+		let (_, body) = parse(head, Method::Head).unwrap();
+		assert!(body.is_empty());
+
+		// Informational responses come before the real one
+		let (response, body) = parse(
+			b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 204 No Content\r\n\r\n",
+			Method::Post,
+		)
+		.unwrap();
+		assert_eq!(response.status(), 204);
+		assert!(body.is_empty());
+	}
+
+	#[test]
+	fn switching_protocols() {
+		let (response, body) = parse(
+			b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\nframes",
+			Method::Get,
+		)
+		.unwrap();
+		assert_eq!(response.status(), 101);
+		assert!(body.is_empty());
+
+		// Whatever came after the head is handed back with the stream
+		let (mut stream, buffered) = response.into_stream();
+		let mut rest = Vec::new();
+		stream.read_to_end(&mut rest).unwrap();
+		assert_eq!([buffered, rest].concat(), b"frames");
+	}
+
+	#[test]
+	fn until_close() {
+		let (response, body) = parse(b"HTTP/1.0 200\r\n\r\nall of it", Method::Get).unwrap();
+		assert_eq!(response.reason(), "");
+		assert_eq!(body, b"all of it");
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(
+			parse(b"HTP/1.1 200 OK\r\n\r\n", Method::Get).unwrap_err(),
+			HttpError::InvalidMessage
+		);
+		assert_eq!(
+			parse(b"HTTP/1.1 20 OK\r\n\r\n", Method::Get).unwrap_err(),
+			HttpError::InvalidMessage
+		);
+		assert_eq!(
+			parse(b"HTTP/1.1 200 OK\r\nNo colon\r\n\r\n", Method::Get).unwrap_err(),
+			HttpError::InvalidMessage
+		);
+		assert_eq!(
+			parse(b"HTTP/1.1 200 OK\r\nA: b\r\n", Method::Get).unwrap_err(),
+			HttpError::UnexpectedEof
+		);
+
+		let mut huge = Vec::from(*b"HTTP/1.1 200 OK\r\n");
+		huge.resize(MAX_HEAD_LEN + 1, b'a');
+		assert_eq!(
+			parse(&huge, Method::Get).unwrap_err(),
+			HttpError::HeadTooLarge
+		);
+	}
+}
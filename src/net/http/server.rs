@@ -0,0 +1,661 @@
+//! A minimal, blocking HTTP/1.1 server.
+//!
+//! ```rs
+//! let server = Server::bind(SocketAddr::new(IpAddr::LOCALHOST, 8080))?;
+//! server.serve(|request, response| {
+//!     if request.path() != "/" {
+//!         response.set_status(404)?;
+//!         return response.send(b"not found");
+//!     }
+//!
+//!     response.header(Header::new("Content-Type", "text/plain"))?;
+//!     response.send(b"hello")
+//! })?;
+//! ```
+//!
+//! Connections are handled one at a time, on the thread that calls
+//! [`Server::serve`], and each one is closed after a single request. Requests
+//! are parsed in place: a [`Request`]'s target and headers borrow from the
+//! buffer its head was read into, and its body is streamed through [`Reader`].
+//!
+//! To accept connections without blocking, call [`Server::set_nonblocking`],
+//! wait for [`Server::fd`] to become readable with `poll` or a similar API,
+//! then call [`Server::handle_one`]. Once a connection has been accepted, its
+//! request is still handled to completion.
+
+use {
+	super::{
+		Body, BufStream, Header, HeaderRanges, HttpError, Method, find_header, headers_in,
+		is_chunked, parse_headers, validate_header,
+	},
+	crate::{
+		io::{Reader, SliceWriter, Writer},
+		lang::Infallible,
+		net::{SocketAddr, TcpListener, TcpStream},
+		rt::os::{self, unix::FileDescriptor},
+		text::write,
+	},
+};
+
+/// The most bytes of a request's body that are read and thrown away if the
+/// handler doesn't read them. Closing a connection with unread data makes the
+/// OS reset it, which can lose the response before the client reads it.
+const MAX_DRAIN_LEN: usize = 1024 * 1024;
+
+/// Accepts connections from a [`TcpListener`] and responds to their requests.
+/// See the [module-level docs](self).
+#[derive(Debug)]
+pub struct Server {
+	listener: TcpListener,
+	nonblocking: bool,
+}
+impl Server {
+	/// Listens for connections to `addr`.
+	pub fn bind(addr: SocketAddr) -> Result<Self, os::Error> {
+		Ok(Self::new(TcpListener::bind(addr)?))
+	}
+	/// Serves connections from `listener`, which should be blocking; use
+	/// [`Server::set_nonblocking`] instead of
+	/// [`TcpListener::set_nonblocking`].
+	pub fn new(listener: TcpListener) -> Self {
+		Self {
+			listener,
+			nonblocking: false,
+		}
+	}
+
+	/// The address the server is listening on.
+	pub fn local_addr(&self) -> Result<SocketAddr, os::Error> {
+		self.listener.local_addr()
+	}
+	/// Sets whether [`Server::handle_one`] blocks when there aren't any
+	/// connections waiting.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), os::Error> {
+		self.listener.set_nonblocking(nonblocking)?;
+		self.nonblocking = nonblocking;
+		Ok(())
+	}
+	/// The listener's file descriptor. It becomes readable when there's a
+	/// connection to accept, so it can be waited on with `poll` and similar
+	/// APIs.
+	pub fn fd(&self) -> FileDescriptor {
+		self.listener.fd()
+	}
+
+	/// Handles connections one after another, until accepting one fails.
+	/// `handler` is called with each request, and writes its response.
+	///
+	/// Errors from individual connections, such as a client disconnecting
+	/// early, are ignored.
+	pub fn serve<F>(&self, mut handler: F) -> Result<Infallible, os::Error>
+	where
+		F: FnMut(&mut Request<'_>, &mut ResponseWriter<'_>) -> Result<(), HttpError>,
+	{
+		loop {
+			match self.listener.accept() {
+				Ok((stream, _)) => {
+					let _ = self.handle(stream, &mut handler);
+				}
+				Err(err) if err.kind() == os::ErrorKind::Interrupted => {}
+				Err(err) => return Err(err),
+			}
+		}
+	}
+	/// Accepts one connection, and calls `handler` with its request. Returns
+	/// `false` without calling `handler` if the server is non-blocking and
+	/// there aren't any connections waiting.
+	///
+	/// Requests that aren't valid HTTP are answered with an error status
+	/// instead of being passed to `handler`, and their error is returned.
+	pub fn handle_one<F>(&self, handler: F) -> Result<bool, HttpError>
+	where
+		F: FnOnce(&mut Request<'_>, &mut ResponseWriter<'_>) -> Result<(), HttpError>,
+	{
+		let stream = match self.listener.accept() {
+			Ok((stream, _)) => stream,
+			Err(err) if self.nonblocking && err.kind() == os::ErrorKind::WouldBlock => {
+				return Ok(false);
+			}
+			Err(err) => return Err(err.into()),
+		};
+		self.handle(stream, handler)?;
+
+		Ok(true)
+	}
+
+	fn handle<F>(&self, stream: TcpStream, handler: F) -> Result<(), HttpError>
+	where
+		F: FnOnce(&mut Request<'_>, &mut ResponseWriter<'_>) -> Result<(), HttpError>,
+	{
+		// Some platforms give accepted connections the listener's flags
+		if self.nonblocking {
+			stream.set_nonblocking(false)?;
+		}
+
+		let mut reader = BufStream::new(&stream);
+		let mut response = ResponseWriter::new(&stream);
+		let head = match reader.read_head() {
+			Ok(head) => head,
+			Err(err) => return Err(response.reject(err)),
+		};
+
+		let mut lines = head.lines();
+		let parsed = parse_request_line(lines.next().unwrap_or_default())
+			.and_then(|(method, target)| Ok((method, target, parse_headers(&head, lines)?)));
+		let (method, target, headers) = match parsed {
+			Ok(parsed) => parsed,
+			Err(err) => return Err(response.reject(err)),
+		};
+		let Some(method) = Method::parse(method) else {
+			response.reject_with(501);
+			return Ok(());
+		};
+
+		let header = |name| find_header(headers_in(&head, &headers), name);
+		let body = match (header("Transfer-Encoding"), header("Content-Length")) {
+			(Some(encoding), _) if is_chunked(encoding) => Ok(Body::Chunked(0)),
+			// Without chunked encoding last, there's no way to tell where the
+			// body ends
+			(Some(_), _) => Err(HttpError::InvalidMessage),
+			(None, Some(len)) => Body::from_length(len),
+			(None, None) => Ok(Body::Done),
+		};
+		let body = match body {
+			Ok(body) => body,
+			Err(err) => return Err(response.reject(err)),
+		};
+		let expects_continue = body != Body::Done
+			&& header("Expect").is_some_and(|value| value.eq_ignore_ascii_case("100-continue"));
+
+		let mut request = Request {
+			method,
+			target,
+			head: &head,
+			headers: &headers,
+			stream: reader,
+			body,
+			expects_continue,
+		};
+		response.head_only = method == Method::Head;
+
+		let result = match handler(&mut request, &mut response) {
+			Ok(()) => response.finish(),
+			Err(err) => {
+				if response.state == ResponseState::Head {
+					response.reject_with(500);
+				}
+				Err(err)
+			}
+		};
+		request.drain();
+
+		result
+	}
+}
+
+/// Parses a request line. Returns the method and the target.
+fn parse_request_line(line: &str) -> Result<(&str, &str), HttpError> {
+	let mut parts = line.split(' ');
+	let (Some(method), Some(target), Some(version), None) =
+		(parts.next(), parts.next(), parts.next(), parts.next())
+	else {
+		return Err(HttpError::InvalidMessage);
+	};
+	if method.is_empty() || target.is_empty() || !matches!(version, "HTTP/1.1" | "HTTP/1.0") {
+		return Err(HttpError::InvalidMessage);
+	}
+
+	Ok((method, target))
+}
+
+/// A request to a [`Server`]. Reading from it reads the request's body, with
+/// any transfer encoding removed.
+#[derive(Debug)]
+pub struct Request<'a> {
+	method: Method,
+	target: &'a str,
+	/// The request line and headers.
+	head: &'a str,
+	headers: &'a HeaderRanges,
+	stream: BufStream<&'a TcpStream>,
+	body: Body,
+	/// Whether the client is waiting for a `100 Continue` response before it
+	/// sends the body.
+	expects_continue: bool,
+}
+impl<'a> Request<'a> {
+	pub fn method(&self) -> Method {
+		self.method
+	}
+	/// The target from the request line, e.g. `/search?q=crux`.
+	pub fn target(&self) -> &'a str {
+		self.target
+	}
+	/// The target without its query, e.g. `/search`.
+	pub fn path(&self) -> &'a str {
+		self.target.split('?').next().unwrap()
+	}
+	/// The part of the target after the `?`, e.g. `q=crux`.
+	pub fn query(&self) -> Option<&'a str> {
+		self.target.split_once('?').map(|(_, query)| query)
+	}
+	/// Every header in the request, in the order they were sent.
+	pub fn headers(&self) -> impl Iterator<Item = Header<'a>> {
+		headers_in(self.head, self.headers)
+	}
+	/// The value of the first header called `name`, which is case-insensitive.
+	pub fn header(&self, name: &str) -> Option<&'a str> {
+		find_header(self.headers(), name)
+	}
+
+	/// Reads and throws away what's left of the body, if it isn't too long.
+	fn drain(&mut self) {
+		// The client hasn't sent the body, and won't until it's asked to
+		if self.expects_continue {
+			return;
+		}
+
+		let mut buf = [0; 1024];
+		let mut drained = 0;
+		while drained < MAX_DRAIN_LEN {
+			match self.body.read(&mut self.stream, &mut buf) {
+				Ok(0) | Err(_) => break,
+				Ok(read) => drained += read,
+			}
+		}
+	}
+}
+impl Reader for Request<'_> {
+	type Error = HttpError;
+
+	/// Reads some of the body. Returns 0 once all of it has been read.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		if self.expects_continue && !buf.is_empty() {
+			self.expects_continue = false;
+			self.stream
+				.inner
+				.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+		}
+
+		self.body.read(&mut self.stream, buf)
+	}
+}
+
+/// Writes the response to a [`Request`].
+///
+/// A response can either be sent all at once with [`ResponseWriter::send`],
+/// or streamed by writing to it. Streamed responses use chunked transfer
+/// encoding, and their headers are sent on the first write. If the handler
+/// doesn't do either, an empty response is sent.
+#[derive(Debug)]
+pub struct ResponseWriter<'a> {
+	stream: &'a TcpStream,
+	status: u16,
+	/// The headers that have been added so far, already formatted.
+	headers: Vec<u8>,
+	state: ResponseState,
+	/// Whether this is a response to a `HEAD` request, so the body shouldn't
+	/// be sent.
+	head_only: bool,
+}
+impl<'a> ResponseWriter<'a> {
+	fn new(stream: &'a TcpStream) -> Self {
+		Self {
+			stream,
+			status: 200,
+			headers: Vec::new(),
+			state: ResponseState::Head,
+			head_only: false,
+		}
+	}
+
+	/// Sets the status code, which is `200` by default. Errors if the status
+	/// isn't a three-digit, non-informational code.
+	pub fn set_status(&mut self, status: u16) -> Result<(), HttpError> {
+		self.check_unsent()?;
+		if !(200..1000).contains(&status) {
+			return Err(HttpError::InvalidMessage);
+		}
+		self.status = status;
+		Ok(())
+	}
+	/// Adds a header to the response. `Content-Length`, `Transfer-Encoding`
+	/// and `Connection` are set by the writer, so adding them is an error.
+	pub fn header(&mut self, header: Header<'_>) -> Result<(), HttpError> {
+		self.check_unsent()?;
+		validate_header(header)?;
+		if ["Content-Length", "Transfer-Encoding", "Connection"]
+			.iter()
+			.any(|name| header.name.eq_ignore_ascii_case(name))
+		{
+			return Err(HttpError::InvalidHeader);
+		}
+		write!(self.headers, "{}: {}\r\n", header.name, header.value).unwrap();
+		Ok(())
+	}
+	/// Sends the response, with `body` as its body.
+	pub fn send(&mut self, body: &[u8]) -> Result<(), HttpError> {
+		self.check_unsent()?;
+		self.state = ResponseState::Done;
+		self.write_head(Some(body.len()))?;
+		if self.sends_body() {
+			self.stream.write_all(body)?;
+		}
+		Ok(())
+	}
+
+	fn check_unsent(&self) -> Result<(), HttpError> {
+		if self.state == ResponseState::Head {
+			Ok(())
+		} else {
+			Err(HttpError::AlreadySent)
+		}
+	}
+	/// Whether the body is actually sent. `HEAD` responses, and ones with a
+	/// `204` or `304` status, don't have one.
+	fn sends_body(&self) -> bool {
+		!self.head_only && !matches!(self.status, 204 | 304)
+	}
+	/// Sends the status line and headers. `len` is the body's length, or
+	/// `None` if it's chunked.
+	fn write_head(&mut self, len: Option<usize>) -> Result<(), HttpError> {
+		let mut head = Vec::new();
+		write!(
+			head,
+			"HTTP/1.1 {} {}\r\nConnection: close\r\n",
+			self.status,
+			reason_phrase(self.status),
+		)
+		.unwrap();
+		head.extend_from_slice(&self.headers);
+		match len {
+			_ if matches!(self.status, 204 | 304) => {}
+			Some(len) => write!(head, "Content-Length: {len}\r\n").unwrap(),
+			None => head.extend_from_slice(b"Transfer-Encoding: chunked\r\n"),
+		}
+		head.extend_from_slice(b"\r\n");
+
+		self.stream.write_all(&head)?;
+		Ok(())
+	}
+	/// Sends an empty response if nothing has been sent yet, or ends a
+	/// streamed body.
+	fn finish(&mut self) -> Result<(), HttpError> {
+		match self.state {
+			ResponseState::Head => self.send(&[]),
+			ResponseState::Chunked => {
+				self.state = ResponseState::Done;
+				if self.sends_body() {
+					self.stream.write_all(b"0\r\n\r\n")?;
+				}
+				Ok(())
+			}
+			ResponseState::Done => Ok(()),
+		}
+	}
+	/// Tries to respond to a request that couldn't be parsed, then returns
+	/// the error it failed with.
+	fn reject(&mut self, err: HttpError) -> HttpError {
+		match err {
+			HttpError::InvalidMessage => self.reject_with(400),
+			HttpError::HeadTooLarge => self.reject_with(431),
+			// The client is gone, or didn't send a whole request
+			_ => {}
+		}
+		err
+	}
+	/// Tries to send an empty response with `status`. If the client has
+	/// already disconnected, there's no one to tell about the error.
+	fn reject_with(&mut self, status: u16) {
+		self.status = status;
+		self.headers.clear();
+		let _ = self.send(&[]);
+	}
+}
+impl Writer for ResponseWriter<'_> {
+	type Error = HttpError;
+
+	/// Writes part of the body as one chunk. The first write sends the status
+	/// line and headers.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		match self.state {
+			ResponseState::Head => {
+				self.state = ResponseState::Chunked;
+				self.write_head(None)?;
+			}
+			ResponseState::Chunked => {}
+			ResponseState::Done => return Err(HttpError::AlreadySent),
+		}
+		// An empty chunk would end the body
+		if bytes.is_empty() || !self.sends_body() {
+			return Ok(bytes.len());
+		}
+
+		let mut buf = [0; 20];
+		let mut size = SliceWriter::new(&mut buf);
+		write!(size, "{:x}\r\n", bytes.len()).unwrap();
+		self.stream.write_all(size.written())?;
+		self.stream.write_all(bytes)?;
+		self.stream.write_all(b"\r\n")?;
+
+		Ok(bytes.len())
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+
+/// How much of a response has been sent.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum ResponseState {
+	/// The status and headers can still be changed.
+	Head,
+	/// The headers have been sent, and the body is being streamed.
+	Chunked,
+	Done,
+}
+
+/// The standard reason phrase for a status code, or an empty string for codes
+/// without one here.
+fn reason_phrase(status: u16) -> &'static str {
+	match status {
+		200 => "OK",
+		201 => "Created",
+		202 => "Accepted",
+		204 => "No Content",
+		301 => "Moved Permanently",
+		302 => "Found",
+		303 => "See Other",
+		304 => "Not Modified",
+		307 => "Temporary Redirect",
+		308 => "Permanent Redirect",
+		400 => "Bad Request",
+		401 => "Unauthorized",
+		403 => "Forbidden",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		409 => "Conflict",
+		413 => "Content Too Large",
+		429 => "Too Many Requests",
+		431 => "Request Header Fields Too Large",
+		500 => "Internal Server Error",
+		501 => "Not Implemented",
+		502 => "Bad Gateway",
+		503 => "Service Unavailable",
+		_ => "",
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{net::IpAddr, text::str_from_utf8},
+	};
+
+	/// Sends `request` to a new server, handles it with `handler`, and returns
+	/// what `handle_one` returned and the raw response.
+	fn exchange<F>(request: &[u8], handler: F) -> (Result<bool, HttpError>, String)
+	where
+		F: FnOnce(&mut Request<'_>, &mut ResponseWriter<'_>) -> Result<(), HttpError>,
+	{
+		let server = Server::bind(SocketAddr::new(IpAddr::LOCALHOST, 0)).unwrap();
+		let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+		client.write_all(request).unwrap();
+		let result = server.handle_one(handler);
+
+		let mut response = Vec::new();
+		client.read_to_end(&mut response).unwrap();
+		(result, String::from(str_from_utf8(&response).unwrap()))
+	}
+
+	#[test]
+	fn get() {
+		let request = b"GET /search?q=crux HTTP/1.1\r\nHost: localhost\r\nAccept:  */*\r\n\r\n";
+		let (result, response) = exchange(request, |request, response| {
+			assert_eq!(request.method(), Method::Get);
+			assert_eq!(request.target(), "/search?q=crux");
+			assert_eq!(request.path(), "/search");
+			assert_eq!(request.query(), Some("q=crux"));
+			assert_eq!(request.header("accept"), Some("*/*"));
+			assert_eq!(request.headers().count(), 2);
+			let mut body = Vec::new();
+			request.read_to_end(&mut body)?;
+			assert!(body.is_empty());
+
+			response.header(Header::new("Content-Type", "text/plain"))?;
+			assert_eq!(
+				response.header(Header::new("Content-Length", "5")),
+				Err(HttpError::InvalidHeader)
+			);
+			response.send(b"hello")?;
+			assert_eq!(response.send(b"again"), Err(HttpError::AlreadySent));
+			Ok(())
+		});
+
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Type: text/plain\r\n\
+			 Content-Length: 5\r\n\r\nhello"
+		);
+	}
+
+	#[test]
+	fn streaming() {
+		let request =
+			b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nping\r\n0\r\n\r\n";
+		let (result, response) = exchange(request, |request, response| {
+			let mut body = Vec::new();
+			request.read_to_end(&mut body)?;
+			assert_eq!(body, b"ping");
+
+			response.set_status(201)?;
+			response.write_all(b"po")?;
+			response.write_all(b"ng")?;
+			assert_eq!(response.set_status(200), Err(HttpError::AlreadySent));
+			Ok(())
+		});
+
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 201 Created\r\nConnection: close\r\nTransfer-Encoding: chunked\r\n\r\n\
+			 2\r\npo\r\n2\r\nng\r\n0\r\n\r\n"
+		);
+	}
+
+	#[test]
+	fn head() {
+		let (result, response) = exchange(b"HEAD / HTTP/1.1\r\n\r\n", |request, response| {
+			assert_eq!(request.method(), Method::Head);
+			response.send(b"hello")
+		});
+
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 5\r\n\r\n"
+		);
+	}
+
+	#[test]
+	fn unread_body() {
+		let request = b"PUT /file HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+		let (result, response) = exchange(request, |_, _| Ok(()));
+
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+		);
+	}
+
+	#[test]
+	fn expect_continue() {
+		let request = b"POST / HTTP/1.1\r\nContent-Length: 4\r\nExpect: 100-continue\r\n\r\nping";
+		let (result, response) = exchange(request, |request, response| {
+			let mut body = Vec::new();
+			request.read_to_end(&mut body)?;
+			response.send(&body)
+		});
+
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\nConnection: close\r\n\
+			 Content-Length: 4\r\n\r\nping"
+		);
+	}
+
+	#[test]
+	fn errors() {
+		let (result, response) = exchange(b"nonsense\r\n\r\n", |_, _| unreachable!());
+		assert_eq!(result, Err(HttpError::InvalidMessage));
+		assert_eq!(
+			response,
+			"HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+		);
+
+		let (result, response) = exchange(b"BREW /pot HTTP/1.1\r\n\r\n", |_, _| unreachable!());
+		assert_eq!(result, Ok(true));
+		assert_eq!(
+			response,
+			"HTTP/1.1 501 Not Implemented\r\nConnection: close\r\nContent-Length: 0\r\n\r\n"
+		);
+
+		let (result, response) = exchange(b"GET / HTTP/1.1\r\n\r\n", |_, response| {
+			response.header(Header::new("X-Dropped", "yes"))?;
+			Err(HttpError::UnexpectedEof)
+		});
+		assert_eq!(result, Err(HttpError::UnexpectedEof));
+		assert_eq!(
+			response,
+			"HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\n\
+			 Content-Length: 0\r\n\r\n"
+		);
+	}
+
+	#[test]
+	fn nonblocking() {
+		let mut server = Server::bind(SocketAddr::new(IpAddr::LOCALHOST, 0)).unwrap();
+		server.set_nonblocking(true).unwrap();
+		assert_eq!(server.handle_one(|_, _| unreachable!()), Ok(false));
+
+		let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+		client.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+		// The connection may take a moment to show up
+		let handler = |_: &mut Request<'_>, response: &mut ResponseWriter<'_>| response.send(b"hi");
+		while !server.handle_one(handler).unwrap() {}
+
+		let mut response = Vec::new();
+		client.read_to_end(&mut response).unwrap();
+		assert!(response.ends_with(b"\r\n\r\nhi"));
+	}
+}
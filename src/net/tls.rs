@@ -0,0 +1,381 @@
+//! TLS-encrypted streams, for talking to servers securely.
+//!
+//! ```rs
+//! let connector = TlsConnector::new()?;
+//! let tcp = TcpStream::connect_to_host("example.com", 443)?;
+//! let mut stream = connector.connect("example.com", tcp)?;
+//! stream.write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n")?;
+//! ```
+//!
+//! The TLS protocol itself is implemented by a [`TlsBackend`].
+//! [`TlsConnector::new`] uses [`OpenSsl`], which loads the system's OpenSSL
+//! library at runtime; other implementations, like bindings to a pure-Rust TLS
+//! library, can be used with [`TlsConnector::with_backend`]. Backends don't do
+//! any I/O themselves. [`TlsStream`] moves encrypted bytes between them and
+//! the underlying stream, so they work over any [`Reader`] and [`Writer`].
+
+pub mod openssl;
+
+pub use self::openssl::{OpenSsl, OpenSslSession};
+
+use crate::{
+	io::{Reader, Writer},
+	rt::os,
+	text::{Display, write},
+};
+
+/// How many encrypted bytes are read from the underlying stream at once.
+const BUF_LEN: usize = 16 * 1024;
+
+/// An implementation of the TLS protocol. See the
+/// [module-level docs](self).
+pub trait TlsBackend {
+	type Session: TlsSession;
+
+	/// Starts a client session with the server called `host`. The session
+	/// has to check that the server's certificate is valid, and issued for
+	/// `host`.
+	fn client_session(&self, host: &str) -> Result<Self::Session, TlsError>;
+}
+
+/// One end of a TLS connection, without any I/O. Encrypted bytes from the
+/// peer are passed in with [`TlsSession::receive`], and encrypted bytes for
+/// the peer are taken out with [`TlsSession::take_outgoing`].
+pub trait TlsSession {
+	/// Gives the session encrypted bytes that were received from the peer.
+	fn receive(&mut self, bytes: &[u8]) -> Result<(), TlsError>;
+	/// Appends the encrypted bytes the session needs to send to the peer to
+	/// `out`.
+	fn take_outgoing(&mut self, out: &mut Vec<u8>) -> Result<(), TlsError>;
+
+	/// Continues the handshake. Returns `true` once it's finished, or `false`
+	/// if it needs more bytes from the peer.
+	fn handshake(&mut self) -> Result<bool, TlsError>;
+	/// Decrypts received data into `buf`. Returns `None` if more bytes are
+	/// needed from the peer, or `Some(0)` once the peer has closed the
+	/// connection.
+	fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, TlsError>;
+	/// Encrypts some of `bytes`, and returns how many were encrypted. Returns
+	/// `None` if more bytes are needed from the peer first.
+	fn write(&mut self, bytes: &[u8]) -> Result<Option<usize>, TlsError>;
+	/// Tells the peer that no more data will be sent.
+	fn close(&mut self) -> Result<(), TlsError>;
+}
+
+/// Starts TLS connections with a [`TlsBackend`].
+#[derive(Debug)]
+pub struct TlsConnector<B = OpenSsl> {
+	backend: B,
+}
+impl TlsConnector {
+	/// Creates a connector that uses the system's OpenSSL library, and trusts
+	/// the system's root certificates. See [`OpenSsl::new`].
+	pub fn new() -> Result<Self, TlsError> {
+		Ok(Self::with_backend(OpenSsl::new()?))
+	}
+}
+impl<B: TlsBackend> TlsConnector<B> {
+	pub fn with_backend(backend: B) -> Self {
+		Self { backend }
+	}
+
+	pub fn backend(&self) -> &B {
+		&self.backend
+	}
+
+	/// Performs a TLS handshake with the server called `host` over `stream`,
+	/// which is usually a [`TcpStream`](crate::net::TcpStream). Errors if the
+	/// server's certificate isn't valid for `host`.
+	pub fn connect<T>(&self, host: &str, stream: T) -> Result<TlsStream<T, B::Session>, TlsError>
+	where
+		T: Reader + Writer,
+		TlsError: From<<T as Reader>::Error> + From<<T as Writer>::Error>,
+	{
+		let mut stream = TlsStream {
+			stream,
+			session: self.backend.client_session(host)?,
+			outgoing: Vec::new(),
+		};
+		while !stream.session.handshake()? {
+			stream.send_outgoing()?;
+			if !stream.receive()? {
+				return Err(TlsError::UnexpectedEof);
+			}
+		}
+		stream.send_outgoing()?;
+
+		Ok(stream)
+	}
+}
+
+/// A stream encrypted with TLS. Reading from and writing to it reads and
+/// writes decrypted data, while the underlying stream carries the encrypted
+/// bytes.
+#[derive(Debug)]
+pub struct TlsStream<T, S = OpenSslSession> {
+	stream: T,
+	session: S,
+	/// Encrypted bytes waiting to be sent. Only used while sending, but kept
+	/// around so it isn't reallocated every time.
+	outgoing: Vec<u8>,
+}
+impl<T, S> TlsStream<T, S> {
+	/// The underlying, encrypted stream.
+	pub fn inner(&self) -> &T {
+		&self.stream
+	}
+	pub fn session(&self) -> &S {
+		&self.session
+	}
+}
+impl<T, S: TlsSession> TlsStream<T, S>
+where
+	T: Reader + Writer,
+	TlsError: From<<T as Reader>::Error> + From<<T as Writer>::Error>,
+{
+	/// Tells the server that no more data will be sent. The server may still
+	/// send data until it closes the connection too.
+	pub fn close(&mut self) -> Result<(), TlsError> {
+		self.session.close()?;
+		self.send_outgoing()
+	}
+
+	/// Sends any encrypted bytes the session has produced.
+	fn send_outgoing(&mut self) -> Result<(), TlsError> {
+		self.session.take_outgoing(&mut self.outgoing)?;
+		if !self.outgoing.is_empty() {
+			let result = self.stream.write_all(&self.outgoing);
+			self.outgoing.clear();
+			result?;
+		}
+		Ok(())
+	}
+	/// Reads more encrypted bytes from the underlying stream into the session.
+	/// Returns `false` if the underlying stream has ended.
+	fn receive(&mut self) -> Result<bool, TlsError> {
+		let mut buf = [0; BUF_LEN];
+		let read = loop {
+			match self.stream.read(&mut buf) {
+				Ok(read) => break read,
+				Err(err) if <T as Reader>::is_interrupted(&err) => {}
+				Err(err) => return Err(err.into()),
+			}
+		};
+		if read == 0 {
+			return Ok(false);
+		}
+		self.session.receive(&buf[..read])?;
+		Ok(true)
+	}
+}
+impl<T, S: TlsSession> Reader for TlsStream<T, S>
+where
+	T: Reader + Writer,
+	TlsError: From<<T as Reader>::Error> + From<<T as Writer>::Error>,
+{
+	type Error = TlsError;
+
+	/// Reads and decrypts some data. Returns 0 once the server has closed the
+	/// connection. Errors if the underlying stream ends without the server
+	/// closing the TLS connection first, since that could mean an attacker cut
+	/// the data short.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			let read = self.session.read(buf)?;
+			// The session can respond to messages from the server while
+			// reading, e.g. to update keys
+			self.send_outgoing()?;
+			if let Some(read) = read {
+				return Ok(read);
+			}
+			if !self.receive()? {
+				return Err(TlsError::UnexpectedEof);
+			}
+		}
+	}
+}
+impl<T, S: TlsSession> Writer for TlsStream<T, S>
+where
+	T: Reader + Writer,
+	TlsError: From<<T as Reader>::Error> + From<<T as Writer>::Error>,
+{
+	type Error = TlsError;
+
+	/// Encrypts and sends some data.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		if bytes.is_empty() {
+			return Ok(0);
+		}
+
+		loop {
+			let written = self.session.write(bytes)?;
+			self.send_outgoing()?;
+			if let Some(written) = written {
+				return Ok(written);
+			}
+			if !self.receive()? {
+				return Err(TlsError::UnexpectedEof);
+			}
+		}
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		self.stream.flush()?;
+		Ok(())
+	}
+}
+
+/// An error from a TLS connection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TlsError {
+	/// The TLS backend isn't available, e.g. because OpenSSL isn't installed.
+	Unavailable,
+	/// The host name can't be used to start a connection, e.g. because it
+	/// contains a nul byte.
+	InvalidHost,
+	/// The server's certificate isn't trusted, has expired, or wasn't issued
+	/// for the host being connected to.
+	InvalidCertificate,
+	/// The backend failed with a backend-specific error code; see
+	/// [`OpenSsl`] for OpenSSL's.
+	Backend(u64),
+	Os(os::Error),
+	/// The underlying stream ended before the TLS connection was closed.
+	UnexpectedEof,
+}
+impl From<os::Error> for TlsError {
+	fn from(err: os::Error) -> Self {
+		Self::Os(err)
+	}
+}
+impl Display for TlsError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Unavailable => f.write_str("no TLS library is available"),
+			Self::InvalidHost => f.write_str("invalid host name"),
+			Self::InvalidCertificate => f.write_str("the server's certificate isn't valid"),
+			Self::Backend(code) => write!(f, "TLS error {code:#x}"),
+			Self::Os(err) => write!(f, "{err}"),
+			Self::UnexpectedEof => f.write_str("connection closed before TLS was shut down"),
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			lang::Extend,
+			net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+		},
+	};
+
+	/// A "backend" that XORs every byte with a key, which the server sends
+	/// as its half of the handshake. Only useful for testing [`TlsStream`].
+	struct Xor;
+	impl TlsBackend for Xor {
+		type Session = XorSession;
+
+		fn client_session(&self, host: &str) -> Result<Self::Session, TlsError> {
+			if host.is_empty() {
+				return Err(TlsError::InvalidHost);
+			}
+			Ok(XorSession {
+				key: None,
+				received: Vec::new(),
+				outgoing: Vec::new(),
+			})
+		}
+	}
+	struct XorSession {
+		key: Option<u8>,
+		received: Vec<u8>,
+		outgoing: Vec<u8>,
+	}
+	impl TlsSession for XorSession {
+		fn receive(&mut self, bytes: &[u8]) -> Result<(), TlsError> {
+			self.received.extend_from_slice(bytes);
+			Ok(())
+		}
+		fn take_outgoing(&mut self, out: &mut Vec<u8>) -> Result<(), TlsError> {
+			out.extend_from_slice(&self.outgoing);
+			self.outgoing.clear();
+			Ok(())
+		}
+		fn handshake(&mut self) -> Result<bool, TlsError> {
+			if self.key.is_none() && !self.received.is_empty() {
+				self.key = Some(self.received.remove(0));
+				self.outgoing.extend_from_slice(b"hello");
+			}
+			Ok(self.key.is_some())
+		}
+		fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, TlsError> {
+			if self.received.is_empty() {
+				return Ok(None);
+			}
+			let len = buf.len().min(self.received.len());
+			for (out, byte) in buf.iter_mut().zip(self.received.drain(..len)) {
+				*out = byte ^ self.key.unwrap();
+			}
+			Ok(Some(len))
+		}
+		fn write(&mut self, bytes: &[u8]) -> Result<Option<usize>, TlsError> {
+			let key = self.key.unwrap();
+			self.outgoing.extend(bytes.iter().map(|byte| byte ^ key));
+			Ok(Some(bytes.len()))
+		}
+		fn close(&mut self) -> Result<(), TlsError> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn stream() {
+		let listener = TcpListener::bind(SocketAddr::new(IpAddr::LOCALHOST, 0)).unwrap();
+		let addr = listener.local_addr().unwrap();
+		let client = TcpStream::connect(addr).unwrap();
+		let (mut server, _) = listener.accept().unwrap();
+		// The server's half of the handshake, and some encrypted data
+		server.write_all(&[0x20, b'P' ^ 0x20, b'O' ^ 0x20]).unwrap();
+
+		let connector = TlsConnector::with_backend(Xor);
+		assert_eq!(
+			connector.connect("", &client).err(),
+			Some(TlsError::InvalidHost)
+		);
+		let mut stream = connector.connect("localhost", &client).unwrap();
+		let mut buf = [0; 2];
+		assert_eq!(stream.read(&mut buf), Ok(2));
+		assert_eq!(&buf, b"PO");
+		stream.write_all(b"ok").unwrap();
+		drop(stream);
+		drop(client);
+
+		let mut received = Vec::new();
+		server.read_to_end(&mut received).unwrap();
+		assert_eq!(received, b"hello\x4f\x4b");
+	}
+
+	#[test]
+	fn unexpected_eof() {
+		let listener = TcpListener::bind(SocketAddr::new(IpAddr::LOCALHOST, 0)).unwrap();
+		let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+		drop(listener.accept().unwrap());
+
+		let connector = TlsConnector::with_backend(Xor);
+		assert_eq!(
+			connector.connect("localhost", client).err(),
+			Some(TlsError::UnexpectedEof)
+		);
+	}
+}
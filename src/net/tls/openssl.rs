@@ -0,0 +1,371 @@
+//! A [`TlsBackend`] built on the system's OpenSSL library.
+//!
+//! OpenSSL (1.1.1 or later) is loaded with [`DynamicLibrary`] the first time
+//! it's needed, instead of being linked, so programs still run on systems
+//! without it; [`OpenSsl::new`] just fails with [`TlsError::Unavailable`].
+//!
+//! [`TlsError::Backend`] holds the code from OpenSSL's `ERR_get_error`, which
+//! can be looked up with `openssl errstr`.
+
+use {
+	super::{TlsBackend, TlsError, TlsSession},
+	crate::{
+		concurrency::LazyLock,
+		ffi::{CStr, CString, c_char, c_int, c_long, c_ulong, c_void},
+		lang::{null_ptr, null_ptr_mut},
+		rt::DynamicLibrary,
+		text::{Debug, concat, stringify},
+	},
+};
+
+/// The names OpenSSL's library is tried under, from most to least preferred.
+#[cfg(not(macos))]
+const LIBRARY_NAMES: [&str; 3] = ["libssl.so.3", "libssl.so.1.1", "libssl.so"];
+/// The names OpenSSL's library is tried under, from most to least preferred.
+/// macOS's own `libssl.dylib` is a stub that crashes programs that load it,
+/// so only Homebrew's OpenSSL is used.
+#[cfg(macos)]
+const LIBRARY_NAMES: [&str; 3] = [
+	"libssl.3.dylib",
+	"/opt/homebrew/opt/openssl@3/lib/libssl.3.dylib",
+	"/usr/local/opt/openssl@3/lib/libssl.3.dylib",
+];
+
+static LIBSSL: LazyLock<Option<LibSsl>> = LazyLock::new(LibSsl::load);
+
+/// Declares [`LibSsl`], with a field for each of OpenSSL's functions.
+macro_rules! functions {
+	($($name:ident: fn($($arg:ty),*) $(-> $ret:ty)?;)*) => {
+		/// OpenSSL's library, and the functions this backend uses from it.
+		#[allow(non_snake_case)]
+		struct LibSsl {
+			$($name: unsafe extern "C" fn($($arg),*) $(-> $ret)?,)*
+			/// Never unloaded, since it's stored in a static.
+			_library: DynamicLibrary,
+		}
+		impl LibSsl {
+			fn load() -> Option<Self> {
+				let library = LIBRARY_NAMES
+					.iter()
+					.find_map(|name| DynamicLibrary::open(name).ok())?;
+				Some(Self {
+					$($name: unsafe {
+						let name = concat!(stringify!($name), "\0");
+						library.get(CStr::from_bytes_with_nul(name.as_bytes()).unwrap())
+					}?,)*
+					_library: library,
+				})
+			}
+		}
+	};
+}
+functions! {
+	TLS_client_method: fn() -> NonNullConst<SslMethod>;
+	SSL_CTX_new: fn(NonNullConst<SslMethod>) -> Option<NonNull<SslCtx>>;
+	SSL_CTX_free: fn(NonNull<SslCtx>);
+	SSL_CTX_set_default_verify_paths: fn(NonNull<SslCtx>) -> c_int;
+	SSL_CTX_set_verify: fn(NonNull<SslCtx>, c_int, *const c_void);
+	SSL_CTX_ctrl: fn(NonNull<SslCtx>, c_int, c_long, *mut c_void) -> c_long;
+	SSL_new: fn(NonNull<SslCtx>) -> Option<NonNull<Ssl>>;
+	SSL_free: fn(NonNull<Ssl>);
+	SSL_ctrl: fn(NonNull<Ssl>, c_int, c_long, *mut c_void) -> c_long;
+	SSL_set_connect_state: fn(NonNull<Ssl>);
+	SSL_set1_host: fn(NonNull<Ssl>, NonNullConst<c_char>) -> c_int;
+	SSL_get0_param: fn(NonNull<Ssl>) -> NonNull<X509VerifyParam>;
+	SSL_set_bio: fn(NonNull<Ssl>, NonNull<Bio>, NonNull<Bio>);
+	SSL_get_rbio: fn(NonNull<Ssl>) -> NonNull<Bio>;
+	SSL_get_wbio: fn(NonNull<Ssl>) -> NonNull<Bio>;
+	SSL_do_handshake: fn(NonNull<Ssl>) -> c_int;
+	SSL_read: fn(NonNull<Ssl>, NonNull<c_void>, c_int) -> c_int;
+	SSL_write: fn(NonNull<Ssl>, NonNullConst<c_void>, c_int) -> c_int;
+	SSL_shutdown: fn(NonNull<Ssl>) -> c_int;
+	SSL_get_error: fn(NonNull<Ssl>, c_int) -> c_int;
+	SSL_get_verify_result: fn(NonNull<Ssl>) -> c_long;
+	// These are from libcrypto, which libssl loads
+	X509_VERIFY_PARAM_set1_ip_asc: fn(NonNull<X509VerifyParam>, NonNullConst<c_char>) -> c_int;
+	BIO_s_mem: fn() -> NonNullConst<BioMethod>;
+	BIO_new: fn(NonNullConst<BioMethod>) -> Option<NonNull<Bio>>;
+	BIO_free: fn(NonNull<Bio>) -> c_int;
+	BIO_read: fn(NonNull<Bio>, NonNull<c_void>, c_int) -> c_int;
+	BIO_write: fn(NonNull<Bio>, NonNullConst<c_void>, c_int) -> c_int;
+	BIO_ctrl_pending: fn(NonNull<Bio>) -> usize;
+	ERR_get_error: fn() -> c_ulong;
+	ERR_clear_error: fn();
+}
+impl LibSsl {
+	/// Gets the library, loading it if it hasn't been loaded yet.
+	fn get() -> Result<&'static Self, TlsError> {
+		LazyLock::force(&LIBSSL)
+			.as_ref()
+			.ok_or(TlsError::Unavailable)
+	}
+
+	/// Takes the oldest error from OpenSSL's error queue, and clears the rest.
+	fn last_error(&self) -> TlsError {
+		let code = unsafe { (self.ERR_get_error)() };
+		unsafe { (self.ERR_clear_error)() };
+		// `c_ulong` is only 32 bits on some platforms
+		#[allow(clippy::unnecessary_cast)]
+		TlsError::Backend(code as u64)
+	}
+}
+
+impl Debug for LibSsl {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.debug_struct("LibSsl").finish_non_exhaustive()
+	}
+}
+
+// Opaque OpenSSL types
+#[repr(C)]
+struct SslMethod {
+	_private: [u8; 0],
+}
+#[repr(C)]
+struct SslCtx {
+	_private: [u8; 0],
+}
+#[repr(C)]
+struct Ssl {
+	_private: [u8; 0],
+}
+#[repr(C)]
+struct Bio {
+	_private: [u8; 0],
+}
+#[repr(C)]
+struct BioMethod {
+	_private: [u8; 0],
+}
+#[repr(C)]
+struct X509VerifyParam {
+	_private: [u8; 0],
+}
+
+const SSL_VERIFY_PEER: c_int = 1;
+const SSL_CTRL_SET_TLSEXT_HOSTNAME: c_int = 55;
+const SSL_CTRL_SET_MIN_PROTO_VERSION: c_int = 123;
+const TLSEXT_NAMETYPE_HOST_NAME: c_long = 0;
+const TLS1_2_VERSION: c_long = 0x0303;
+const SSL_ERROR_SSL: c_int = 1;
+const SSL_ERROR_WANT_READ: c_int = 2;
+const SSL_ERROR_ZERO_RETURN: c_int = 6;
+const X509_V_OK: c_long = 0;
+
+/// OpenSSL's TLS implementation. Connections use TLS 1.2 or later, and trust
+/// the system's root certificates.
+#[derive(Debug)]
+pub struct OpenSsl {
+	ctx: NonNull<SslCtx>,
+	lib: &'static LibSsl,
+}
+impl OpenSsl {
+	/// Loads OpenSSL, if it hasn't been loaded yet, and sets up a context for
+	/// new connections. Errors with [`TlsError::Unavailable`] if OpenSSL isn't
+	/// installed.
+	///
+	/// Root certificates are read from OpenSSL's default locations, which can
+	/// be changed with the `SSL_CERT_FILE` and `SSL_CERT_DIR` environment
+	/// variables.
+	pub fn new() -> Result<Self, TlsError> {
+		let lib = LibSsl::get()?;
+		unsafe { (lib.ERR_clear_error)() };
+		let method = unsafe { (lib.TLS_client_method)() };
+		let ctx = unsafe { (lib.SSL_CTX_new)(method) }.ok_or_else(|| lib.last_error())?;
+		let this = Self { ctx, lib };
+
+		unsafe {
+			if (lib.SSL_CTX_set_default_verify_paths)(ctx) != 1 {
+				return Err(lib.last_error());
+			}
+			(lib.SSL_CTX_set_verify)(ctx, SSL_VERIFY_PEER, null_ptr());
+			if (lib.SSL_CTX_ctrl)(
+				ctx,
+				SSL_CTRL_SET_MIN_PROTO_VERSION,
+				TLS1_2_VERSION,
+				null_ptr_mut(),
+			) != 1
+			{
+				return Err(lib.last_error());
+			}
+		}
+
+		Ok(this)
+	}
+}
+impl TlsBackend for OpenSsl {
+	type Session = OpenSslSession;
+
+	fn client_session(&self, host: &str) -> Result<Self::Session, TlsError> {
+		let lib = self.lib;
+		let name = CString::new(host).map_err(|_| TlsError::InvalidHost)?;
+		let name_ptr = NonNullConst::from_ref(name.as_c_str()).cast();
+		unsafe { (lib.ERR_clear_error)() };
+
+		let ssl = unsafe { (lib.SSL_new)(self.ctx) }.ok_or_else(|| lib.last_error())?;
+		let session = OpenSslSession { ssl, lib };
+		let Some(rbio) = (unsafe { (lib.BIO_new)((lib.BIO_s_mem)()) }) else {
+			return Err(lib.last_error());
+		};
+		let Some(wbio) = (unsafe { (lib.BIO_new)((lib.BIO_s_mem)()) }) else {
+			unsafe { (lib.BIO_free)(rbio) };
+			return Err(lib.last_error());
+		};
+		unsafe {
+			// The session owns the BIOs from here on
+			(lib.SSL_set_bio)(ssl, rbio, wbio);
+			(lib.SSL_set_connect_state)(ssl);
+
+			// Servers are only sent the host's name (SNI) if it's a domain,
+			// and IP addresses have to be checked against a different part of
+			// the certificate
+			let is_ip = host.contains(':') || host.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+			let verified = if is_ip {
+				(lib.X509_VERIFY_PARAM_set1_ip_asc)((lib.SSL_get0_param)(ssl), name_ptr)
+			} else {
+				let sni = (lib.SSL_ctrl)(
+					ssl,
+					SSL_CTRL_SET_TLSEXT_HOSTNAME,
+					TLSEXT_NAMETYPE_HOST_NAME,
+					name_ptr.as_ptr().cast_mut().cast(),
+				);
+				if sni != 1 {
+					return Err(lib.last_error());
+				}
+				(lib.SSL_set1_host)(ssl, name_ptr)
+			};
+			if verified != 1 {
+				(lib.ERR_clear_error)();
+				return Err(TlsError::InvalidHost);
+			}
+		}
+
+		Ok(session)
+	}
+}
+impl Drop for OpenSsl {
+	fn drop(&mut self) {
+		unsafe { (self.lib.SSL_CTX_free)(self.ctx) };
+	}
+}
+// OpenSSL contexts are reference counted and locked internally
+unsafe impl Send for OpenSsl {}
+unsafe impl Sync for OpenSsl {}
+
+/// A connection's state in OpenSSL. Encrypted bytes go through two memory
+/// BIOs (OpenSSL's stream abstraction): one for bytes from the server, and
+/// one for bytes to it.
+#[derive(Debug)]
+pub struct OpenSslSession {
+	ssl: NonNull<Ssl>,
+	lib: &'static LibSsl,
+}
+impl OpenSslSession {
+	/// The BIO that bytes from the server are written to.
+	fn rbio(&self) -> NonNull<Bio> {
+		unsafe { (self.lib.SSL_get_rbio)(self.ssl) }
+	}
+	/// The BIO that bytes for the server are read from.
+	fn wbio(&self) -> NonNull<Bio> {
+		unsafe { (self.lib.SSL_get_wbio)(self.ssl) }
+	}
+
+	/// Turns the return value of an `SSL_*` I/O function into its result, or
+	/// `None` if it needs more bytes from the server.
+	fn check(&self, ret: c_int) -> Result<Option<usize>, TlsError> {
+		if ret > 0 {
+			return Ok(Some(ret as usize));
+		}
+		match unsafe { (self.lib.SSL_get_error)(self.ssl, ret) } {
+			SSL_ERROR_WANT_READ => Ok(None),
+			SSL_ERROR_ZERO_RETURN => Ok(Some(0)),
+			SSL_ERROR_SSL if unsafe { (self.lib.SSL_get_verify_result)(self.ssl) } != X509_V_OK => {
+				unsafe { (self.lib.ERR_clear_error)() };
+				Err(TlsError::InvalidCertificate)
+			}
+			_ => Err(self.lib.last_error()),
+		}
+	}
+}
+impl TlsSession for OpenSslSession {
+	fn receive(&mut self, bytes: &[u8]) -> Result<(), TlsError> {
+		// Writing to a memory BIO only fails if it can't allocate
+		for chunk in bytes.chunks(c_int::MAX as usize) {
+			let ret = unsafe {
+				(self.lib.BIO_write)(
+					self.rbio(),
+					NonNullConst::from_ref(&chunk[0]).cast(),
+					chunk.len() as c_int,
+				)
+			};
+			if ret != chunk.len() as c_int {
+				return Err(self.lib.last_error());
+			}
+		}
+		Ok(())
+	}
+	fn take_outgoing(&mut self, out: &mut Vec<u8>) -> Result<(), TlsError> {
+		let wbio = self.wbio();
+		let pending = unsafe { (self.lib.BIO_ctrl_pending)(wbio) }.min(c_int::MAX as usize);
+		if pending == 0 {
+			return Ok(());
+		}
+
+		let start = out.len();
+		out.resize(start + pending, 0);
+		let read = unsafe {
+			(self.lib.BIO_read)(
+				wbio,
+				NonNull::from_mut(&mut out[start]).cast(),
+				pending as c_int,
+			)
+		};
+		out.truncate(start + read.max(0) as usize);
+		Ok(())
+	}
+
+	fn handshake(&mut self) -> Result<bool, TlsError> {
+		unsafe { (self.lib.ERR_clear_error)() };
+		let ret = unsafe { (self.lib.SSL_do_handshake)(self.ssl) };
+		match self.check(ret)? {
+			Some(0) => Err(TlsError::UnexpectedEof),
+			result => Ok(result.is_some()),
+		}
+	}
+	fn read(&mut self, buf: &mut [u8]) -> Result<Option<usize>, TlsError> {
+		if buf.is_empty() {
+			return Ok(Some(0));
+		}
+		unsafe { (self.lib.ERR_clear_error)() };
+		let len = buf.len().min(c_int::MAX as usize) as c_int;
+		let ret =
+			unsafe { (self.lib.SSL_read)(self.ssl, NonNull::from_mut(&mut buf[0]).cast(), len) };
+		self.check(ret)
+	}
+	fn write(&mut self, bytes: &[u8]) -> Result<Option<usize>, TlsError> {
+		if bytes.is_empty() {
+			return Ok(Some(0));
+		}
+		unsafe { (self.lib.ERR_clear_error)() };
+		let len = bytes.len().min(c_int::MAX as usize) as c_int;
+		let ret = unsafe {
+			(self.lib.SSL_write)(self.ssl, NonNullConst::from_ref(&bytes[0]).cast(), len)
+		};
+		self.check(ret)
+	}
+	fn close(&mut self) -> Result<(), TlsError> {
+		unsafe { (self.lib.ERR_clear_error)() };
+		// 0 means the server hasn't closed its side yet, which is fine
+		if unsafe { (self.lib.SSL_shutdown)(self.ssl) } < 0 {
+			return Err(self.lib.last_error());
+		}
+		Ok(())
+	}
+}
+impl Drop for OpenSslSession {
+	fn drop(&mut self) {
+		unsafe { (self.lib.SSL_free)(self.ssl) };
+	}
+}
+// Each connection is only used by one thread at a time
+unsafe impl Send for OpenSslSession {}
@@ -0,0 +1,985 @@
+//! WebSocket connections ([RFC 6455]), on top of the [HTTP
+//! client](super::http).
+//!
+//! ```rs
+//! let mut socket = WebSocket::connect("ws://localhost:8080/chat")?;
+//! socket.send(Message::Text("hello"))?;
+//! loop {
+//!     match socket.receive()? {
+//!         Message::Text(text) => println!("{text}"),
+//!         Message::Close { .. } => break,
+//!         _ => {}
+//!     }
+//! }
+//! ```
+//!
+//! [`WebSocket::receive`] reassembles fragmented messages and answers pings,
+//! so it always returns whole messages. Messages borrow the socket's buffers,
+//! so each one has to be used or copied before the next is received. Messages
+//! can also be sent in fragments with [`WebSocket::send_frame`], e.g. to
+//! stream one whose length isn't known up front. `wss://` URLs need the `tls`
+//! feature.
+//!
+//! [RFC 6455]: https://www.rfc-editor.org/rfc/rfc6455
+
+use {
+	super::http::{Client, Header, HttpError, Stream},
+	crate::{
+		crypto::{fill_random, sha1},
+		io::{Reader, Writer},
+		lang::Infallible,
+		rt::os,
+		text::{Display, str_from_utf8, write},
+	},
+};
+
+/// Appended to the client's key to compute the server's
+/// `Sec-WebSocket-Accept` header.
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// How many bytes are read from the connection at once.
+const BUF_LEN: usize = 8 * 1024;
+/// The largest payload a control frame can have.
+const MAX_CONTROL_LEN: usize = 125;
+
+/// Status codes for [`Message::Close`]. Codes `3000` to `4999` can also be
+/// used by libraries and applications.
+pub mod close_code {
+	/// The connection did what it was opened for.
+	pub const NORMAL: u16 = 1000;
+	/// The endpoint is going away, e.g. a server shutting down.
+	pub const GOING_AWAY: u16 = 1001;
+	/// The peer broke the WebSocket protocol.
+	pub const PROTOCOL_ERROR: u16 = 1002;
+	/// The endpoint received a kind of message it can't handle.
+	pub const UNSUPPORTED_DATA: u16 = 1003;
+	/// The close message didn't have a status code. This is never actually
+	/// sent.
+	pub const NO_STATUS: u16 = 1005;
+	/// A message's contents were invalid, e.g. a text message that isn't
+	/// UTF-8.
+	pub const INVALID_DATA: u16 = 1007;
+	/// A message broke the endpoint's policy.
+	pub const POLICY_VIOLATION: u16 = 1008;
+	/// A message was too large for the endpoint to handle.
+	pub const MESSAGE_TOO_LARGE: u16 = 1009;
+	/// The server ran into an unexpected error.
+	pub const INTERNAL_ERROR: u16 = 1011;
+}
+
+/// What a frame holds.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Opcode {
+	/// The next part of a fragmented message.
+	Continuation,
+	Text,
+	Binary,
+	Close,
+	Ping,
+	Pong,
+}
+impl Opcode {
+	/// The opcode's value in a frame's header.
+	pub const fn raw(self) -> u8 {
+		match self {
+			Self::Continuation => 0,
+			Self::Text => 1,
+			Self::Binary => 2,
+			Self::Close => 8,
+			Self::Ping => 9,
+			Self::Pong => 10,
+		}
+	}
+	/// Parses an opcode from a frame's header. Returns `None` for reserved
+	/// opcodes.
+	pub const fn from_raw(raw: u8) -> Option<Self> {
+		Some(match raw {
+			0 => Self::Continuation,
+			1 => Self::Text,
+			2 => Self::Binary,
+			8 => Self::Close,
+			9 => Self::Ping,
+			10 => Self::Pong,
+			_ => return None,
+		})
+	}
+	/// Whether this is a control frame's opcode. Control frames can't be
+	/// fragmented, but can be sent between the fragments of another message.
+	pub const fn is_control(self) -> bool {
+		matches!(self, Self::Close | Self::Ping | Self::Pong)
+	}
+}
+
+/// One frame of a message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Frame<'a> {
+	/// Whether this is the last frame of its message.
+	pub fin: bool,
+	pub opcode: Opcode,
+	/// The frame's payload, which is masked and unmasked automatically.
+	pub payload: &'a [u8],
+}
+
+/// A whole message.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Message<'a> {
+	Text(&'a str),
+	Binary(&'a [u8]),
+	/// A ping, which [`WebSocket::receive`] answers automatically.
+	Ping(&'a [u8]),
+	Pong(&'a [u8]),
+	/// The connection is closing. `code` is [`close_code::NO_STATUS`] if the
+	/// peer didn't send one.
+	Close {
+		code: u16,
+		reason: &'a str,
+	},
+}
+
+/// Which end of the connection a [`WebSocket`] is. Clients mask the frames
+/// they send, and servers don't.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Role {
+	Client,
+	Server,
+}
+
+/// How far along the closing handshake is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum State {
+	Open,
+	/// This end sent a close message, and is waiting for the peer's.
+	CloseSent,
+	/// Both ends sent close messages, or the connection broke.
+	Closed,
+}
+
+/// A WebSocket connection. See the [module-level docs](self).
+#[derive(Debug)]
+pub struct WebSocket<S = Stream> {
+	stream: S,
+	role: Role,
+	/// Bytes read from the stream that haven't been parsed yet start at
+	/// `pos`.
+	buf: Vec<u8>,
+	pos: usize,
+	/// The payload of the last frame received.
+	frame: Vec<u8>,
+	/// The fragments received so far of a fragmented message, and its
+	/// opcode.
+	message: Vec<u8>,
+	message_opcode: Option<Opcode>,
+	/// Whether a fragmented message is being sent.
+	sending_fragments: bool,
+	state: State,
+	max_message_len: usize,
+}
+impl WebSocket {
+	/// Connects to a `ws://` or `wss://` URL, and does the opening handshake.
+	pub fn connect(url: &str) -> Result<Self, WebSocketError> {
+		Self::connect_with(&Client::new(), url, &[])
+	}
+	/// Like [`WebSocket::connect`], but sends the handshake with `client`,
+	/// and with extra `headers`, e.g. `Sec-WebSocket-Protocol`.
+	pub fn connect_with(
+		client: &Client,
+		url: &str,
+		headers: &[Header<'_>],
+	) -> Result<Self, WebSocketError> {
+		let url = if let Some(rest) = url.strip_prefix("ws://") {
+			format!("http://{rest}")
+		} else if let Some(rest) = url.strip_prefix("wss://") {
+			format!("https://{rest}")
+		} else if url.contains("://") {
+			return Err(HttpError::UnsupportedScheme.into());
+		} else {
+			return Err(HttpError::InvalidUrl.into());
+		};
+
+		let mut key = [0; 16];
+		fill_random(&mut key)?;
+		let key = base64(&key);
+		let mut all_headers = Vec::from([
+			Header::new("Sec-WebSocket-Key", &key),
+			Header::new("Sec-WebSocket-Version", "13"),
+		]);
+		all_headers.extend_from_slice(headers);
+
+		let response = client.upgrade(&url, "websocket", &all_headers)?;
+		if response.status() != 101 {
+			return Err(WebSocketError::Rejected(response.status()));
+		}
+		let upgraded = response
+			.header("Upgrade")
+			.is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+		let connection = response.header("Connection").is_some_and(|value| {
+			value
+				.split(',')
+				.any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+		});
+		let accepted = response.header("Sec-WebSocket-Accept") == Some(&accept_key(&key));
+		if !(upgraded && connection && accepted) {
+			return Err(WebSocketError::InvalidHandshake);
+		}
+
+		let (stream, buffered) = response.into_stream();
+		Ok(Self::from_stream(stream, Role::Client, buffered))
+	}
+}
+impl<S> WebSocket<S> {
+	/// Uses `stream` as a WebSocket connection, once the opening handshake
+	/// is done. `buffered` is anything that was read from the stream after
+	/// the handshake, like the bytes returned by
+	/// [`Response::into_stream`](super::http::Response::into_stream).
+	pub fn from_stream(stream: S, role: Role, buffered: Vec<u8>) -> Self {
+		Self {
+			stream,
+			role,
+			buf: buffered,
+			pos: 0,
+			frame: Vec::new(),
+			message: Vec::new(),
+			message_opcode: None,
+			sending_fragments: false,
+			state: State::Open,
+			max_message_len: 16 * 1024 * 1024,
+		}
+	}
+	/// Sets the most bytes a received message can have. Larger messages fail
+	/// with [`WebSocketError::MessageTooLarge`] and close the connection.
+	/// Defaults to 16 MiB.
+	pub fn set_max_message_len(&mut self, len: usize) {
+		self.max_message_len = len;
+	}
+	pub fn role(&self) -> Role {
+		self.role
+	}
+	/// The underlying stream.
+	pub fn inner(&self) -> &S {
+		&self.stream
+	}
+}
+impl<S: Reader + Writer> WebSocket<S>
+where
+	WebSocketError: From<<S as Reader>::Error> + From<<S as Writer>::Error>,
+{
+	/// Sends a whole message. Sending [`Message::Close`] is the same as
+	/// calling [`WebSocket::close`].
+	pub fn send(&mut self, message: Message<'_>) -> Result<(), WebSocketError> {
+		let (opcode, payload) = match message {
+			Message::Text(text) => (Opcode::Text, text.as_bytes()),
+			Message::Binary(bytes) => (Opcode::Binary, bytes),
+			Message::Ping(bytes) => (Opcode::Ping, bytes),
+			Message::Pong(bytes) => (Opcode::Pong, bytes),
+			Message::Close { code, reason } => return self.close(code, reason),
+		};
+		self.send_frame(Frame {
+			fin: true,
+			opcode,
+			payload,
+		})
+	}
+	/// Sends a single frame. A fragmented message starts with a text or
+	/// binary frame without `fin`, followed by continuation frames, the last
+	/// of which has `fin` set. Control frames can be sent between fragments,
+	/// but can't be fragmented themselves or have more than 125 bytes.
+	pub fn send_frame(&mut self, frame: Frame<'_>) -> Result<(), WebSocketError> {
+		if self.state != State::Open {
+			return Err(WebSocketError::Closed);
+		}
+		if frame.opcode.is_control() {
+			if !frame.fin || frame.payload.len() > MAX_CONTROL_LEN {
+				return Err(WebSocketError::InvalidFrame);
+			}
+		} else if self.sending_fragments != (frame.opcode == Opcode::Continuation) {
+			return Err(WebSocketError::InvalidFrame);
+		} else {
+			self.sending_fragments = !frame.fin;
+		}
+
+		if frame.opcode == Opcode::Close {
+			self.state = State::CloseSent;
+		}
+		self.write_frame(frame)
+	}
+	/// Starts closing the connection by sending a close message. `reason`
+	/// can be at most 123 bytes, and has to be empty if `code` is
+	/// [`close_code::NO_STATUS`], which sends a close message without a
+	/// status code. The peer should answer with its own close message, which
+	/// [`WebSocket::receive`] returns.
+	pub fn close(&mut self, code: u16, reason: &str) -> Result<(), WebSocketError> {
+		let mut payload = [0; MAX_CONTROL_LEN];
+		let len = close_payload(&mut payload, code, reason)?;
+		self.send_frame(Frame {
+			fin: true,
+			opcode: Opcode::Close,
+			payload: &payload[..len],
+		})
+	}
+
+	/// Waits for the next whole message. Pings are answered automatically,
+	/// but still returned. Once a [`Message::Close`] is returned, the
+	/// connection is closed (after answering it, if the peer started
+	/// closing), and receiving again fails with [`WebSocketError::Closed`].
+	///
+	/// If the peer breaks the protocol, the connection is closed with the
+	/// matching status code, and this returns the error.
+	pub fn receive(&mut self) -> Result<Message<'_>, WebSocketError> {
+		if self.state == State::Closed {
+			return Err(WebSocketError::Closed);
+		}
+
+		let (opcode, fragmented) = loop {
+			let (fin, opcode) = self.read_frame().map_err(|err| self.fail(err))?;
+			match opcode {
+				Opcode::Text | Opcode::Binary if self.message_opcode.is_some() => {
+					return Err(self.fail(WebSocketError::InvalidFrame));
+				}
+				Opcode::Text | Opcode::Binary if !fin => {
+					self.message_opcode = Some(opcode);
+					self.message.clear();
+					self.message.extend_from_slice(&self.frame);
+				}
+				Opcode::Continuation => {
+					let Some(message_opcode) = self.message_opcode else {
+						return Err(self.fail(WebSocketError::InvalidFrame));
+					};
+					self.message.extend_from_slice(&self.frame);
+					if fin {
+						self.message_opcode = None;
+						break (message_opcode, true);
+					}
+				}
+				opcode => break (opcode, false),
+			}
+		};
+		if let Err(err) = check_payload(opcode, self.payload(fragmented)) {
+			return Err(self.fail(err));
+		}
+
+		match opcode {
+			Opcode::Ping if self.state == State::Open => {
+				let payload = core::mem::take(&mut self.frame);
+				let sent = self.write_frame(Frame {
+					fin: true,
+					opcode: Opcode::Pong,
+					payload: &payload,
+				});
+				self.frame = payload;
+				sent.map_err(|err| self.fail(err))?;
+			}
+			Opcode::Close => {
+				let answer = self.state == State::Open;
+				self.state = State::Closed;
+				if answer {
+					// Only the status code is echoed back, not the reason
+					let len = self.frame.len().min(2);
+					let mut payload = [0; 2];
+					payload[..len].copy_from_slice(&self.frame[..len]);
+					self.write_frame(Frame {
+						fin: true,
+						opcode: Opcode::Close,
+						payload: &payload[..len],
+					})?;
+				}
+			}
+			_ => {}
+		}
+
+		let payload = self.payload(fragmented);
+		Ok(match opcode {
+			// Safety: `check_payload` made sure the text is UTF-8
+			Opcode::Text => Message::Text(unsafe { core::str::from_utf8_unchecked(payload) }),
+			Opcode::Binary => Message::Binary(payload),
+			Opcode::Ping => Message::Ping(payload),
+			Opcode::Pong => Message::Pong(payload),
+			Opcode::Close => match payload {
+				[high, low, reason @ ..] => Message::Close {
+					code: u16::from_be_bytes([*high, *low]),
+					// Safety: `check_payload` made sure the reason is UTF-8
+					reason: unsafe { core::str::from_utf8_unchecked(reason) },
+				},
+				_ => Message::Close {
+					code: close_code::NO_STATUS,
+					reason: "",
+				},
+			},
+			Opcode::Continuation => unreachable!("continuation frames are always reassembled"),
+		})
+	}
+
+	/// The payload of the message that was just received, which is in
+	/// `self.message` if it was fragmented.
+	fn payload(&self, fragmented: bool) -> &[u8] {
+		if fragmented {
+			&self.message
+		} else {
+			&self.frame
+		}
+	}
+	/// Marks the connection as closed after `err`. If the peer broke the
+	/// protocol, it's told why first.
+	fn fail(&mut self, err: WebSocketError) -> WebSocketError {
+		let code = match err {
+			WebSocketError::InvalidFrame => close_code::PROTOCOL_ERROR,
+			WebSocketError::InvalidUtf8 => close_code::INVALID_DATA,
+			WebSocketError::MessageTooLarge => close_code::MESSAGE_TOO_LARGE,
+			_ => 0,
+		};
+		if code != 0 && self.state == State::Open {
+			// The connection is closing because of `err` either way, so it
+			// doesn't matter if this fails
+			let _ = self.write_frame(Frame {
+				fin: true,
+				opcode: Opcode::Close,
+				payload: &code.to_be_bytes(),
+			});
+		}
+		self.state = State::Closed;
+		err
+	}
+	/// Reads the next frame, and puts its unmasked payload in `self.frame`.
+	/// Returns the frame's `fin` bit and opcode.
+	fn read_frame(&mut self) -> Result<(bool, Opcode), WebSocketError> {
+		let mut head = [0; 2];
+		self.read_exact(&mut head)?;
+		let fin = head[0] & 0x80 != 0;
+		// The reserved bits are only used by extensions, which are never
+		// negotiated
+		if head[0] & 0x70 != 0 {
+			return Err(WebSocketError::InvalidFrame);
+		}
+		let opcode = Opcode::from_raw(head[0] & 0x0f).ok_or(WebSocketError::InvalidFrame)?;
+		// Clients always mask their frames, and servers never do
+		let masked = head[1] & 0x80 != 0;
+		if masked != (self.role == Role::Server) {
+			return Err(WebSocketError::InvalidFrame);
+		}
+
+		let len = match head[1] & 0x7f {
+			126 => {
+				let mut len = [0; 2];
+				self.read_exact(&mut len)?;
+				u64::from(u16::from_be_bytes(len))
+			}
+			127 => {
+				let mut len = [0; 8];
+				self.read_exact(&mut len)?;
+				u64::from_be_bytes(len)
+			}
+			len => u64::from(len),
+		};
+		if opcode.is_control() {
+			if !fin || len > MAX_CONTROL_LEN as u64 {
+				return Err(WebSocketError::InvalidFrame);
+			}
+		} else {
+			let received = if opcode == Opcode::Continuation {
+				self.message.len()
+			} else {
+				0
+			};
+			let limit = self.max_message_len.saturating_sub(received);
+			if len > limit as u64 {
+				return Err(WebSocketError::MessageTooLarge);
+			}
+		}
+
+		let mut mask = [0; 4];
+		if masked {
+			self.read_exact(&mut mask)?;
+		}
+		let mut frame = core::mem::take(&mut self.frame);
+		frame.clear();
+		frame.resize(len as usize, 0);
+		let read = self.read_exact(&mut frame);
+		apply_mask(&mut frame, mask);
+		self.frame = frame;
+		read.map(|()| (fin, opcode))
+	}
+	/// Fills `out` with bytes from the connection.
+	fn read_exact(&mut self, out: &mut [u8]) -> Result<(), WebSocketError> {
+		let mut filled = 0;
+		while filled < out.len() {
+			if self.pos == self.buf.len() {
+				self.buf.resize(BUF_LEN, 0);
+				self.pos = 0;
+				let read = loop {
+					match self.stream.read(&mut self.buf) {
+						Ok(read) => break read,
+						Err(err) if <S as Reader>::is_interrupted(&err) => {}
+						Err(err) => {
+							self.buf.clear();
+							return Err(err.into());
+						}
+					}
+				};
+				self.buf.truncate(read);
+				if read == 0 {
+					return Err(WebSocketError::UnexpectedEof);
+				}
+			}
+
+			let len = (out.len() - filled).min(self.buf.len() - self.pos);
+			out[filled..filled + len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+			self.pos += len;
+			filled += len;
+		}
+		Ok(())
+	}
+	/// Encodes and sends `frame`, masking it if this is a client.
+	fn write_frame(&mut self, frame: Frame<'_>) -> Result<(), WebSocketError> {
+		let mask = if self.role == Role::Client {
+			let mut mask = [0; 4];
+			fill_random(&mut mask)?;
+			Some(mask)
+		} else {
+			None
+		};
+		let mut bytes = Vec::with_capacity(frame.payload.len() + 14);
+		frame.encode(mask, &mut bytes);
+		self.stream.write_all(&bytes)?;
+		self.stream.flush()?;
+		Ok(())
+	}
+}
+
+impl Frame<'_> {
+	/// Appends the encoded frame to `out`. Frames sent by clients have to be
+	/// masked with a random `mask`, and frames sent by servers can't be.
+	pub fn encode(&self, mask: Option<[u8; 4]>, out: &mut Vec<u8>) {
+		out.push(u8::from(self.fin) << 7 | self.opcode.raw());
+		let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+		match self.payload.len() {
+			len @ 0..=125 => out.push(mask_bit | len as u8),
+			len @ 126..=0xffff => {
+				out.push(mask_bit | 126);
+				out.extend_from_slice(&(len as u16).to_be_bytes());
+			}
+			len => {
+				out.push(mask_bit | 127);
+				out.extend_from_slice(&(len as u64).to_be_bytes());
+			}
+		}
+
+		let start = out.len();
+		if let Some(mask) = mask {
+			out.extend_from_slice(&mask);
+			out.extend_from_slice(self.payload);
+			apply_mask(&mut out[start + 4..], mask);
+		} else {
+			out.extend_from_slice(self.payload);
+		}
+	}
+}
+
+/// Masks or unmasks a frame's payload. An all-zero mask does nothing.
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+	for (byte, key) in payload.iter_mut().zip(mask.iter().cycle()) {
+		*byte ^= key;
+	}
+}
+
+/// Whether `code` can be sent in a close message.
+fn valid_close_code(code: u16) -> bool {
+	matches!(code, 1000..=1003 | 1007..=1014 | 3000..=4999)
+}
+
+/// Writes a close message's payload into `buf`, and returns its length.
+fn close_payload(
+	buf: &mut [u8; MAX_CONTROL_LEN],
+	code: u16,
+	reason: &str,
+) -> Result<usize, WebSocketError> {
+	if code == close_code::NO_STATUS {
+		return if reason.is_empty() {
+			Ok(0)
+		} else {
+			Err(WebSocketError::InvalidFrame)
+		};
+	}
+	if !valid_close_code(code) || reason.len() > MAX_CONTROL_LEN - 2 {
+		return Err(WebSocketError::InvalidFrame);
+	}
+	buf[..2].copy_from_slice(&code.to_be_bytes());
+	buf[2..2 + reason.len()].copy_from_slice(reason.as_bytes());
+	Ok(2 + reason.len())
+}
+
+/// Checks that a received message's payload is valid for its opcode.
+fn check_payload(opcode: Opcode, payload: &[u8]) -> Result<(), WebSocketError> {
+	match (opcode, payload) {
+		(Opcode::Text, text) if str_from_utf8(text).is_err() => Err(WebSocketError::InvalidUtf8),
+		(Opcode::Close, [_]) => Err(WebSocketError::InvalidFrame),
+		(Opcode::Close, [high, low, reason @ ..]) => {
+			if !valid_close_code(u16::from_be_bytes([*high, *low])) {
+				Err(WebSocketError::InvalidFrame)
+			} else if str_from_utf8(reason).is_err() {
+				Err(WebSocketError::InvalidUtf8)
+			} else {
+				Ok(())
+			}
+		}
+		_ => Ok(()),
+	}
+}
+
+/// The `Sec-WebSocket-Accept` header a server responds to `key` with.
+fn accept_key(key: &str) -> String {
+	let mut input = String::from(key);
+	input.push_str(ACCEPT_GUID);
+	base64(&sha1(input.as_bytes()))
+}
+
+/// Encodes `bytes` with standard, padded base64.
+fn base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+	let mut out = String::new();
+	for chunk in bytes.chunks(3) {
+		let mut group = [0; 3];
+		group[..chunk.len()].copy_from_slice(chunk);
+		let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+		for idx in 0..4 {
+			if idx <= chunk.len() {
+				let sextet = (bits >> (18 - 6 * idx)) & 0x3f;
+				out.push(char::from(ALPHABET[sextet as usize]));
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+/// An error from a WebSocket connection.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WebSocketError {
+	/// The opening handshake's HTTP request failed.
+	Http(HttpError),
+	Os(os::Error),
+	/// The server didn't switch to WebSockets, and responded with this status
+	/// instead.
+	Rejected(u16),
+	/// The server's handshake response was invalid.
+	InvalidHandshake,
+	/// The peer sent a frame that broke the protocol, or a frame passed to
+	/// [`WebSocket::send_frame`] was invalid.
+	InvalidFrame,
+	/// A text message, or a close message's reason, wasn't UTF-8.
+	InvalidUtf8,
+	/// A message was longer than [`WebSocket::set_max_message_len`] allows.
+	MessageTooLarge,
+	/// The connection has already been closed.
+	Closed,
+	/// The connection was closed before the closing handshake.
+	UnexpectedEof,
+}
+impl From<HttpError> for WebSocketError {
+	fn from(err: HttpError) -> Self {
+		Self::Http(err)
+	}
+}
+impl From<os::Error> for WebSocketError {
+	fn from(err: os::Error) -> Self {
+		Self::Os(err)
+	}
+}
+impl From<Infallible> for WebSocketError {
+	fn from(err: Infallible) -> Self {
+		match err {}
+	}
+}
+impl Display for WebSocketError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Http(err) => write!(f, "{err}"),
+			Self::Os(err) => write!(f, "{err}"),
+			Self::Rejected(status) => {
+				write!(
+					f,
+					"server refused to switch to WebSockets (status {status})"
+				)
+			}
+			Self::InvalidHandshake => f.write_str("invalid WebSocket handshake"),
+			Self::InvalidFrame => f.write_str("invalid WebSocket frame"),
+			Self::InvalidUtf8 => f.write_str("WebSocket text wasn't valid UTF-8"),
+			Self::MessageTooLarge => f.write_str("WebSocket message is too large"),
+			Self::Closed => f.write_str("WebSocket connection is closed"),
+			Self::UnexpectedEof => f.write_str("connection closed before the WebSocket closed"),
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::lang::Extend};
+
+	/// Reads a few bytes at a time from `input`, and collects everything
+	/// that's written in `output`.
+	#[derive(Debug)]
+	struct Peer<'a> {
+		input: &'a [u8],
+		output: Vec<u8>,
+	}
+	impl Reader for Peer<'_> {
+		type Error = Infallible;
+
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+			let len = buf.len().min(self.input.len()).min(3);
+			buf[..len].copy_from_slice(&self.input[..len]);
+			self.input = &self.input[len..];
+			Ok(len)
+		}
+	}
+	impl Writer for Peer<'_> {
+		type Error = Infallible;
+
+		fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+			self.output.extend_from_slice(bytes);
+			Ok(bytes.len())
+		}
+		fn flush(&mut self) -> Result<(), Self::Error> {
+			Ok(())
+		}
+	}
+
+	fn websocket(input: &[u8], role: Role) -> WebSocket<Peer<'_>> {
+		let peer = Peer {
+			input,
+			output: Vec::new(),
+		};
+		WebSocket::from_stream(peer, role, Vec::new())
+	}
+	fn encode(fin: bool, opcode: Opcode, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+		let mut out = Vec::new();
+		Frame {
+			fin,
+			opcode,
+			payload,
+		}
+		.encode(mask, &mut out);
+		out
+	}
+	/// Decodes small frames sent by a socket, unmasking them if needed.
+	fn decode(mut bytes: &[u8]) -> Vec<(u8, Vec<u8>)> {
+		let mut frames = Vec::new();
+		while let [first, second, rest @ ..] = bytes {
+			let len = usize::from(second & 0x7f);
+			assert!(len < 126);
+			let (mask, rest) = if second & 0x80 != 0 {
+				(rest[..4].try_into().unwrap(), &rest[4..])
+			} else {
+				([0; 4], rest)
+			};
+			let mut payload = Vec::from(&rest[..len]);
+			apply_mask(&mut payload, mask);
+			frames.push((*first, payload));
+			bytes = &rest[len..];
+		}
+		frames
+	}
+
+	#[test]
+	fn handshake_keys() {
+		assert_eq!(base64(b""), "");
+		assert_eq!(base64(b"f"), "Zg==");
+		assert_eq!(base64(b"fo"), "Zm8=");
+		assert_eq!(base64(b"foo"), "Zm9v");
+		assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+		assert_eq!(base64(&[0xfb, 0xff]), "+/8=");
+		// The example from RFC 6455 section 1.3
+		assert_eq!(
+			accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+			"s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+		);
+	}
+
+	#[test]
+	fn frames() {
+		// The examples from RFC 6455 section 5.7
+		assert_eq!(
+			encode(true, Opcode::Text, b"Hello", None),
+			[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]
+		);
+		assert_eq!(
+			encode(true, Opcode::Text, b"Hello", Some([0x37, 0xfa, 0x21, 0x3d])),
+			[
+				0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58
+			]
+		);
+		assert_eq!(
+			encode(false, Opcode::Text, b"Hel", None),
+			[0x01, 0x03, 0x48, 0x65, 0x6c]
+		);
+		assert_eq!(
+			encode(true, Opcode::Continuation, b"lo", None),
+			[0x80, 0x02, 0x6c, 0x6f]
+		);
+		assert_eq!(
+			encode(true, Opcode::Binary, &[0; 256], None)[..4],
+			[0x82, 0x7e, 0x01, 0x00]
+		);
+		assert_eq!(
+			encode(true, Opcode::Binary, &[0; 65536], None)[..10],
+			[0x82, 0x7f, 0, 0, 0, 0, 0, 1, 0, 0]
+		);
+	}
+
+	#[test]
+	fn receive() {
+		let mut input = Vec::new();
+		input.extend(encode(false, Opcode::Text, b"Hel", None));
+		input.extend(encode(true, Opcode::Ping, b"hi", None));
+		input.extend(encode(true, Opcode::Continuation, b"lo", None));
+		input.extend(encode(true, Opcode::Binary, &[7; 300], None));
+		input.extend(encode(true, Opcode::Pong, b"", None));
+		input.extend(encode(true, Opcode::Close, b"\x03\xe8bye", None));
+		let mut socket = websocket(&input, Role::Client);
+
+		assert_eq!(socket.receive(), Ok(Message::Ping(b"hi")));
+		assert_eq!(socket.receive(), Ok(Message::Text("Hello")));
+		assert_eq!(socket.receive(), Ok(Message::Binary(&[7; 300])));
+		assert_eq!(socket.receive(), Ok(Message::Pong(b"")));
+		assert_eq!(
+			socket.receive(),
+			Ok(Message::Close {
+				code: close_code::NORMAL,
+				reason: "bye",
+			})
+		);
+		assert_eq!(socket.receive(), Err(WebSocketError::Closed));
+		assert_eq!(
+			socket.send(Message::Text("late")),
+			Err(WebSocketError::Closed)
+		);
+
+		// The ping is answered, and the close is echoed, both masked
+		let output = &socket.inner().output;
+		assert_eq!(output[1] & 0x80, 0x80);
+		assert_eq!(
+			decode(output),
+			[(0x8a, Vec::from(*b"hi")), (0x88, Vec::from(*b"\x03\xe8"))]
+		);
+	}
+
+	#[test]
+	fn send() {
+		let input = encode(true, Opcode::Close, b"", Some([1, 2, 3, 4]));
+		let mut socket = websocket(&input, Role::Server);
+
+		socket.send(Message::Text("hey")).unwrap();
+		let fragment = |fin, opcode, payload| Frame {
+			fin,
+			opcode,
+			payload,
+		};
+		socket
+			.send_frame(fragment(false, Opcode::Binary, b"ab"))
+			.unwrap();
+		assert_eq!(
+			socket.send_frame(fragment(true, Opcode::Text, b"x")),
+			Err(WebSocketError::InvalidFrame)
+		);
+		socket.send(Message::Ping(b"")).unwrap();
+		socket
+			.send_frame(fragment(true, Opcode::Continuation, b"c"))
+			.unwrap();
+		assert_eq!(
+			socket.send_frame(fragment(true, Opcode::Continuation, b"d")),
+			Err(WebSocketError::InvalidFrame)
+		);
+		assert_eq!(
+			socket.send(Message::Pong(&[0; 126])),
+			Err(WebSocketError::InvalidFrame)
+		);
+		assert_eq!(
+			socket.close(close_code::NO_STATUS, "reason"),
+			Err(WebSocketError::InvalidFrame)
+		);
+		assert_eq!(socket.close(1006, ""), Err(WebSocketError::InvalidFrame));
+		socket.close(close_code::GOING_AWAY, "bye").unwrap();
+		assert_eq!(
+			socket.send(Message::Text("late")),
+			Err(WebSocketError::Closed)
+		);
+
+		// The peer finishes the closing handshake, which isn't echoed
+		assert_eq!(
+			socket.receive(),
+			Ok(Message::Close {
+				code: close_code::NO_STATUS,
+				reason: "",
+			})
+		);
+		assert_eq!(
+			decode(&socket.inner().output),
+			[
+				(0x81, Vec::from(*b"hey")),
+				(0x02, Vec::from(*b"ab")),
+				(0x89, Vec::new()),
+				(0x80, Vec::from(*b"c")),
+				(0x88, Vec::from(*b"\x03\xe9bye")),
+			]
+		);
+	}
+
+	#[test]
+	fn protocol_errors() {
+		let fails = |input: &[u8], max_len, err, code: u16| {
+			let mut socket = websocket(input, Role::Client);
+			socket.set_max_message_len(max_len);
+			assert_eq!(socket.receive(), Err(err));
+			assert_eq!(socket.receive(), Err(WebSocketError::Closed));
+			let output = decode(&socket.inner().output);
+			assert_eq!(output, [(0x88, Vec::from(code.to_be_bytes()))]);
+		};
+
+		// Masked by a server
+		let masked = encode(true, Opcode::Text, b"hi", Some([1, 2, 3, 4]));
+		fails(&masked, 100, WebSocketError::InvalidFrame, 1002);
+		// Reserved bits and opcodes
+		fails(&[0xc1, 0x00], 100, WebSocketError::InvalidFrame, 1002);
+		fails(&[0x83, 0x00], 100, WebSocketError::InvalidFrame, 1002);
+		// Fragmented and oversized control frames
+		let ping = encode(false, Opcode::Ping, b"", None);
+		fails(&ping, 100, WebSocketError::InvalidFrame, 1002);
+		let ping = encode(true, Opcode::Ping, &[0; 126], None);
+		fails(&ping, 1000, WebSocketError::InvalidFrame, 1002);
+		// Continuations without a message to continue, and vice versa
+		let continuation = encode(true, Opcode::Continuation, b"", None);
+		fails(&continuation, 100, WebSocketError::InvalidFrame, 1002);
+		let mut interrupted = encode(false, Opcode::Text, b"a", None);
+		interrupted.extend(encode(true, Opcode::Text, b"b", None));
+		fails(&interrupted, 100, WebSocketError::InvalidFrame, 1002);
+		// Invalid close messages
+		let close = encode(true, Opcode::Close, b"\x03", None);
+		fails(&close, 100, WebSocketError::InvalidFrame, 1002);
+		let close = encode(true, Opcode::Close, b"\x03\xed", None);
+		fails(&close, 100, WebSocketError::InvalidFrame, 1002);
+		let close = encode(true, Opcode::Close, b"\x03\xe8\xff", None);
+		fails(&close, 100, WebSocketError::InvalidUtf8, 1007);
+		// Invalid UTF-8, split across fragments
+		let mut text = encode(false, Opcode::Text, b"\xe2\x82", None);
+		text.extend(encode(true, Opcode::Continuation, b"\xac", None));
+		let mut socket = websocket(&text, Role::Client);
+		assert_eq!(socket.receive(), Ok(Message::Text("\u{20ac}")));
+		let text = encode(true, Opcode::Text, b"\xe2\x82", None);
+		fails(&text, 100, WebSocketError::InvalidUtf8, 1007);
+		// Too large, in one frame or across fragments
+		let binary = encode(true, Opcode::Binary, &[0; 5], None);
+		fails(&binary, 4, WebSocketError::MessageTooLarge, 1009);
+		let mut binary = encode(false, Opcode::Binary, &[0; 3], None);
+		binary.extend(encode(true, Opcode::Continuation, &[0; 2], None));
+		fails(&binary, 4, WebSocketError::MessageTooLarge, 1009);
+
+		let mut socket = websocket(b"\x81\x05hel", Role::Client);
+		assert_eq!(socket.receive(), Err(WebSocketError::UnexpectedEof));
+		assert!(socket.inner().output.is_empty());
+	}
+}
@@ -0,0 +1,35 @@
+//! Numeric traits, and fast number parsing and formatting.
+//!
+//! [`Integer`] and [`Float`] abstract over Rust's integer and floating point
+//! types, so numeric code can be written once for every width. [`Fixed`]
+//! provides fixed-point numbers, and [`Saturating`] and [`Wrapping`] are
+//! integers whose operators saturate or wrap instead of overflowing.
+//!
+//! [`parse`] and [`format`] convert numbers to and from text without going
+//! through `core::fmt`, which pulls a lot of code into small binaries and is
+//! slow for simple cases like printing an integer:
+//!
+//! ```rs
+//! let port: u16 = num::parse("8080")?;
+//!
+//! let mut buf = num::IntBuffer::new();
+//! writer.write_all(buf.format(port).as_bytes())?;
+//! ```
+
+pub mod fixed;
+pub mod float;
+pub mod format;
+pub mod parse;
+pub mod wrappers;
+
+#[doc(inline)]
+pub use {
+	self::{
+		fixed::{Fixed, I16F16, I32F32},
+		float::Float,
+		format::{FloatBuffer, IntBuffer, write_float, write_int},
+		parse::{ParseAmountError, ParseIntError, from_str_radix, parse, parse_f32, parse_f64},
+		wrappers::{Saturating, Wrapping},
+	},
+	crate::lang::{Integer, SignedInteger, UnsignedInteger},
+};
@@ -0,0 +1,326 @@
+//! Fixed-point numbers, for fractional math without floats.
+//!
+//! A [`Fixed`] number is an integer with a fixed number of its low bits used
+//! for the fraction. Unlike floats, fixed-point math gives the same results on
+//! every CPU and doesn't need an FPU, which makes it useful for deterministic
+//! simulations and small targets:
+//!
+//! ```rs
+//! let speed = I16F16::from_int(3) / I16F16::from_int(2);
+//! assert_eq!(speed.to_f64(), 1.5);
+//! assert_eq!(format!("{}", speed * speed), "2.25");
+//! ```
+
+use {
+	super::format::split_sign,
+	crate::{
+		lang::{
+			Add, AddAssign, Div, DivAssign, Integer, Mul, MulAssign, Neg, SignedInteger, Sub,
+			SubAssign,
+		},
+		text::write,
+	},
+	core::fmt::{self, Debug, Display, Formatter, Write},
+};
+
+/// A fixed-point number stored in the integer `I`, with `FRAC` fractional
+/// bits. The number's value is `raw / 2^FRAC`.
+///
+/// `I` can be any integer up to 64 bits wide, and `FRAC` must be less than
+/// `I`'s width; other combinations fail to compile.
+///
+/// The arithmetic operators panic on overflow or division by zero. Use the
+/// `checked_`, `saturating_`, or `wrapping_` methods to handle overflow
+/// differently. Multiplication and division round towards zero.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Fixed<I, const FRAC: u32> {
+	raw: I,
+}
+
+/// A 32-bit signed fixed-point number, with 16 fractional bits.
+pub type I16F16 = Fixed<i32, 16>;
+/// A 64-bit signed fixed-point number, with 32 fractional bits.
+pub type I32F32 = Fixed<i64, 32>;
+
+impl<I: Integer, const FRAC: u32> Fixed<I, FRAC> {
+	/// Products of two 64-bit integers, and 64-bit integers shifted by up to
+	/// 63 bits, both fit in a `u128`, so all math is done in `u128`s.
+	const VALID: () = assert!(
+		I::SIZE_BITS <= 64 && FRAC < I::SIZE_BITS as u32,
+		"Fixed: the integer must be at most 64 bits, with fewer fractional bits than its width"
+	);
+
+	pub const ZERO: Self = Self { raw: I::ZERO };
+	pub const MAX: Self = Self { raw: I::MAX };
+	pub const MIN: Self = Self { raw: I::MIN };
+	/// The smallest positive number, `1 / 2^FRAC`.
+	pub const DELTA: Self = Self { raw: I::ONE };
+
+	/// Creates a fixed-point number from its underlying integer.
+	pub const fn from_raw(raw: I) -> Self {
+		Self { raw }
+	}
+	/// The underlying integer; that is, this number multiplied by `2^FRAC`.
+	pub const fn to_raw(self) -> I {
+		self.raw
+	}
+
+	/// Converts an integer to a fixed-point number. Panics if it's out of
+	/// range.
+	pub fn from_int(value: I) -> Self {
+		Self::checked_from_int(value).expect("Fixed::from_int: integer out of range")
+	}
+	/// Converts an integer to a fixed-point number, or returns `None` if it's
+	/// out of range.
+	pub fn checked_from_int(value: I) -> Option<Self> {
+		let (negative, magnitude) = split_sign(value);
+		Self::join(negative, magnitude << Self::frac())
+	}
+	/// Converts this number to an integer, rounding towards zero.
+	pub fn to_int(self) -> I {
+		let (negative, magnitude) = split_sign(self.raw);
+		Self::join(negative, magnitude >> Self::frac()).unwrap().raw
+	}
+
+	/// Converts an [`f64`] to the nearest fixed-point number. Like `as`, this
+	/// saturates at [`Fixed::MIN`] and [`Fixed::MAX`], and NaN becomes zero.
+	pub fn from_f64(value: f64) -> Self {
+		let scaled = core::f64::math::round(value * (1u128 << Self::frac()) as f64);
+		Self::join_saturating(scaled < 0.0, scaled.abs() as u128)
+	}
+	/// Converts this number to the nearest [`f64`].
+	pub fn to_f64(self) -> f64 {
+		let (negative, magnitude) = split_sign(self.raw);
+		let value = magnitude as f64 / (1u128 << Self::frac()) as f64;
+		if negative { -value } else { value }
+	}
+
+	pub fn checked_add(self, rhs: Self) -> Option<Self> {
+		self.raw.checked_add(rhs.raw).map(Self::from_raw)
+	}
+	pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+		self.raw.checked_sub(rhs.raw).map(Self::from_raw)
+	}
+	pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+		let (negative, product) = self.mul_magnitude(rhs);
+		Self::join(negative, product)
+	}
+	/// Returns `None` if `rhs` is zero or the quotient overflows.
+	pub fn checked_div(self, rhs: Self) -> Option<Self> {
+		let (negative, quotient) = self.div_magnitude(rhs)?;
+		Self::join(negative, quotient)
+	}
+
+	pub fn saturating_add(self, rhs: Self) -> Self {
+		Self::from_raw(self.raw.saturating_add(rhs.raw))
+	}
+	pub fn saturating_sub(self, rhs: Self) -> Self {
+		Self::from_raw(self.raw.saturating_sub(rhs.raw))
+	}
+	pub fn saturating_mul(self, rhs: Self) -> Self {
+		let (negative, product) = self.mul_magnitude(rhs);
+		Self::join_saturating(negative, product)
+	}
+	/// Panics if `rhs` is zero.
+	pub fn saturating_div(self, rhs: Self) -> Self {
+		let (negative, quotient) = self
+			.div_magnitude(rhs)
+			.expect("Fixed::saturating_div: attempt to divide by zero");
+		Self::join_saturating(negative, quotient)
+	}
+
+	pub fn wrapping_add(self, rhs: Self) -> Self {
+		Self::from_raw(self.raw.wrapping_add(rhs.raw))
+	}
+	pub fn wrapping_sub(self, rhs: Self) -> Self {
+		Self::from_raw(self.raw.wrapping_sub(rhs.raw))
+	}
+	pub fn wrapping_mul(self, rhs: Self) -> Self {
+		let (negative, product) = self.mul_magnitude(rhs);
+		Self::join_wrapping(negative, product)
+	}
+	/// Panics if `rhs` is zero.
+	pub fn wrapping_div(self, rhs: Self) -> Self {
+		let (negative, quotient) = self
+			.div_magnitude(rhs)
+			.expect("Fixed::wrapping_div: attempt to divide by zero");
+		Self::join_wrapping(negative, quotient)
+	}
+
+	fn frac() -> u32 {
+		let () = Self::VALID;
+		FRAC
+	}
+
+	/// The sign and magnitude of `self * rhs`'s raw value.
+	fn mul_magnitude(self, rhs: Self) -> (bool, u128) {
+		let (lhs_negative, lhs) = split_sign(self.raw);
+		let (rhs_negative, rhs) = split_sign(rhs.raw);
+		(lhs_negative != rhs_negative, (lhs * rhs) >> Self::frac())
+	}
+	/// The sign and magnitude of `self / rhs`'s raw value, or `None` if `rhs`
+	/// is zero.
+	fn div_magnitude(self, rhs: Self) -> Option<(bool, u128)> {
+		let (lhs_negative, lhs) = split_sign(self.raw);
+		let (rhs_negative, rhs) = split_sign(rhs.raw);
+		let quotient = (lhs << Self::frac()).checked_div(rhs)?;
+		Some((lhs_negative != rhs_negative, quotient))
+	}
+
+	/// Creates a number from a raw value's sign and magnitude, or returns
+	/// `None` if it doesn't fit in `I`.
+	fn join(negative: bool, magnitude: u128) -> Option<Self> {
+		let limit = if negative {
+			I::MIN.as_u128().wrapping_neg()
+		} else {
+			I::MAX.as_u128()
+		};
+		(magnitude <= limit).then(|| Self::join_wrapping(negative, magnitude))
+	}
+	fn join_saturating(negative: bool, magnitude: u128) -> Self {
+		Self::join(negative, magnitude).unwrap_or(if negative { Self::MIN } else { Self::MAX })
+	}
+	fn join_wrapping(negative: bool, magnitude: u128) -> Self {
+		let raw = if negative {
+			magnitude.wrapping_neg()
+		} else {
+			magnitude
+		};
+		Self::from_raw(I::from_u128_truncating(raw))
+	}
+}
+
+impl<I: Integer, const FRAC: u32> Add for Fixed<I, FRAC> {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self {
+		self.checked_add(rhs)
+			.expect("Fixed: attempt to add with overflow")
+	}
+}
+impl<I: Integer, const FRAC: u32> AddAssign for Fixed<I, FRAC> {
+	fn add_assign(&mut self, rhs: Self) {
+		*self = *self + rhs;
+	}
+}
+impl<I: Integer, const FRAC: u32> Sub for Fixed<I, FRAC> {
+	type Output = Self;
+
+	fn sub(self, rhs: Self) -> Self {
+		self.checked_sub(rhs)
+			.expect("Fixed: attempt to subtract with overflow")
+	}
+}
+impl<I: Integer, const FRAC: u32> SubAssign for Fixed<I, FRAC> {
+	fn sub_assign(&mut self, rhs: Self) {
+		*self = *self - rhs;
+	}
+}
+impl<I: Integer, const FRAC: u32> Mul for Fixed<I, FRAC> {
+	type Output = Self;
+
+	fn mul(self, rhs: Self) -> Self {
+		self.checked_mul(rhs)
+			.expect("Fixed: attempt to multiply with overflow")
+	}
+}
+impl<I: Integer, const FRAC: u32> MulAssign for Fixed<I, FRAC> {
+	fn mul_assign(&mut self, rhs: Self) {
+		*self = *self * rhs;
+	}
+}
+impl<I: Integer, const FRAC: u32> Div for Fixed<I, FRAC> {
+	type Output = Self;
+
+	fn div(self, rhs: Self) -> Self {
+		self.checked_div(rhs)
+			.expect("Fixed: attempt to divide by zero or with overflow")
+	}
+}
+impl<I: Integer, const FRAC: u32> DivAssign for Fixed<I, FRAC> {
+	fn div_assign(&mut self, rhs: Self) {
+		*self = *self / rhs;
+	}
+}
+impl<I: SignedInteger, const FRAC: u32> Neg for Fixed<I, FRAC> {
+	type Output = Self;
+
+	fn neg(self) -> Self {
+		Self::ZERO - self
+	}
+}
+
+/// Fixed-point numbers are always written out exactly; every binary fraction
+/// has a finite decimal representation.
+impl<I: Integer, const FRAC: u32> Display for Fixed<I, FRAC> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let (negative, magnitude) = split_sign(self.raw);
+		let mask = (1u128 << Self::frac()) - 1;
+		if negative {
+			f.write_char('-')?;
+		}
+		write!(f, "{}", magnitude >> FRAC)?;
+
+		let mut frac = magnitude & mask;
+		if frac != 0 {
+			f.write_char('.')?;
+		}
+		while frac != 0 {
+			frac *= 10;
+			f.write_char((b'0' + (frac >> FRAC) as u8) as char)?;
+			frac &= mask;
+		}
+
+		Ok(())
+	}
+}
+impl<I: Integer, const FRAC: u32> Debug for Fixed<I, FRAC> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Display::fmt(self, f)
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fixed() {
+		let half = I16F16::from_raw(1 << 15);
+		assert_eq!(half.to_f64(), 0.5);
+		assert_eq!(I16F16::from_f64(0.5), half);
+		assert_eq!(I16F16::from_int(3) * half, I16F16::from_f64(1.5));
+		assert_eq!(
+			I16F16::from_int(3) / I16F16::from_int(2),
+			I16F16::from_f64(1.5)
+		);
+		assert_eq!(
+			-I16F16::from_int(3) / I16F16::from_int(2),
+			I16F16::from_f64(-1.5)
+		);
+		assert_eq!(I16F16::from_f64(-1.75).to_int(), -1);
+		assert_eq!(I16F16::from_f64(1e10), I16F16::MAX);
+		assert_eq!(I16F16::checked_from_int(40_000), None);
+		assert_eq!(I16F16::MAX.checked_mul(I16F16::from_int(2)), None);
+		assert_eq!(I16F16::MIN.saturating_mul(I16F16::from_int(2)), I16F16::MIN);
+		assert_eq!(I16F16::ZERO.checked_div(I16F16::ZERO), None);
+
+		// Unsigned, and every bit but one is fractional
+		let small = Fixed::<u8, 7>::from_f64(0.75);
+		assert_eq!(small.to_raw(), 96);
+		assert_eq!(small * small, Fixed::from_f64(0.5625));
+		assert_eq!(Fixed::<u8, 7>::from_f64(-1.0), Fixed::ZERO);
+
+		assert_eq!(format!("{}", I16F16::from_f64(-2.25)), "-2.25");
+		assert_eq!(format!("{}", I16F16::from_int(7)), "7");
+		assert_eq!(format!("{:?}", I16F16::DELTA), "0.0000152587890625");
+		assert_eq!(format!("{}", I32F32::MIN), "-2147483648");
+	}
+}
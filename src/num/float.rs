@@ -0,0 +1,300 @@
+//! The [`Float`] trait, which abstracts over [`f32`] and [`f64`].
+
+#[doc(inline)]
+pub use core::num::FpCategory;
+
+use crate::{
+	lang::{
+		Add, AddAssign, Div, DivAssign, Integer, Mul, MulAssign, Neg, Rem, RemAssign, Sub,
+		SubAssign,
+	},
+	text::{Debug, Display},
+};
+
+/// Abstracts over Rust's floating point types, allowing you to create
+/// functions that accept either [`f32`] or [`f64`].
+///
+/// Crux doesn't use Rust's standard library, so the math functions here come
+/// from `core`. They compile to CPU instructions where the target has them
+/// (e.g. `sqrt` on x86_64), and otherwise to calls to the soft-float
+/// implementations in `compiler_builtins`. Trigonometric, exponential, and
+/// logarithmic functions aren't available yet, since `core` doesn't have them.
+pub trait Float:
+	Sized
+	+ Copy
+	+ PartialOrd
+	+ Default
+	+ Debug
+	+ Display
+	+ Add<Output = Self>
+	+ AddAssign
+	+ Sub<Output = Self>
+	+ SubAssign
+	+ Mul<Output = Self>
+	+ MulAssign
+	+ Div<Output = Self>
+	+ DivAssign
+	+ Rem<Output = Self>
+	+ RemAssign
+	+ Neg<Output = Self>
+{
+	/// The unsigned integer type with the same size as this float, which is
+	/// what [`Float::to_bits`] returns.
+	type Bits: Integer;
+
+	const ZERO: Self;
+	const ONE: Self;
+	const TWO: Self;
+	const HALF: Self;
+
+	const MAX: Self;
+	const MIN: Self;
+	/// The smallest positive normal number.
+	const MIN_POSITIVE: Self;
+	/// The difference between `1.0` and the next largest number.
+	const EPSILON: Self;
+	const INFINITY: Self;
+	const NEG_INFINITY: Self;
+	const NAN: Self;
+	/// The number of significant binary digits.
+	const MANTISSA_DIGITS: u32;
+	const SIZE_BITS: u8;
+
+	const PI: Self;
+	const TAU: Self;
+	const E: Self;
+	const SQRT_2: Self;
+	const LN_2: Self;
+	const LN_10: Self;
+
+	//
+	// Classification
+	//
+
+	fn is_nan(self) -> bool;
+	fn is_infinite(self) -> bool;
+	fn is_finite(self) -> bool;
+	fn is_normal(self) -> bool;
+	fn is_subnormal(self) -> bool;
+	fn is_sign_positive(self) -> bool;
+	fn is_sign_negative(self) -> bool;
+	fn classify(self) -> FpCategory;
+
+	//
+	// Rounding
+	//
+
+	fn floor(self) -> Self;
+	fn ceil(self) -> Self;
+	/// Rounds to the nearest integer, rounding half-way cases away from zero.
+	fn round(self) -> Self;
+	/// Rounds to the nearest integer, rounding half-way cases to the nearest
+	/// even integer.
+	fn round_ties_even(self) -> Self;
+	fn trunc(self) -> Self;
+	fn fract(self) -> Self;
+
+	//
+	// Math
+	//
+
+	fn abs(self) -> Self;
+	fn signum(self) -> Self;
+	fn copysign(self, sign: Self) -> Self;
+	fn min(self, other: Self) -> Self;
+	fn max(self, other: Self) -> Self;
+	fn clamp(self, min: Self, max: Self) -> Self;
+	/// Computes `(self * a) + b` with only one rounding error.
+	fn mul_add(self, a: Self, b: Self) -> Self;
+	fn sqrt(self) -> Self;
+	fn cbrt(self) -> Self;
+	fn powi(self, n: i32) -> Self;
+	fn recip(self) -> Self;
+	fn div_euclid(self, rhs: Self) -> Self;
+	fn rem_euclid(self, rhs: Self) -> Self;
+	fn to_degrees(self) -> Self;
+	fn to_radians(self) -> Self;
+	fn total_cmp(&self, other: &Self) -> core::cmp::Ordering;
+
+	//
+	// Conversions
+	//
+
+	fn to_bits(self) -> Self::Bits;
+	fn from_bits(bits: Self::Bits) -> Self;
+	/// Converts an [`f64`] to this type, like `value as Self`.
+	fn from_f64(value: f64) -> Self;
+	/// Converts this float to an [`f64`], like `self as f64`.
+	fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_float {
+	($($ty:ident $bits:ty)*) => {
+		$(impl Float for $ty {
+			type Bits = $bits;
+
+			const ZERO: Self = 0.0;
+			const ONE: Self = 1.0;
+			const TWO: Self = 2.0;
+			const HALF: Self = 0.5;
+
+			const MAX: Self = <$ty>::MAX;
+			const MIN: Self = <$ty>::MIN;
+			const MIN_POSITIVE: Self = <$ty>::MIN_POSITIVE;
+			const EPSILON: Self = <$ty>::EPSILON;
+			const INFINITY: Self = <$ty>::INFINITY;
+			const NEG_INFINITY: Self = <$ty>::NEG_INFINITY;
+			const NAN: Self = <$ty>::NAN;
+			const MANTISSA_DIGITS: u32 = <$ty>::MANTISSA_DIGITS;
+			const SIZE_BITS: u8 = <$bits>::BITS as _;
+
+			const PI: Self = core::$ty::consts::PI;
+			const TAU: Self = core::$ty::consts::TAU;
+			const E: Self = core::$ty::consts::E;
+			const SQRT_2: Self = core::$ty::consts::SQRT_2;
+			const LN_2: Self = core::$ty::consts::LN_2;
+			const LN_10: Self = core::$ty::consts::LN_10;
+
+			fn is_nan(self) -> bool {
+				self.is_nan()
+			}
+			fn is_infinite(self) -> bool {
+				self.is_infinite()
+			}
+			fn is_finite(self) -> bool {
+				self.is_finite()
+			}
+			fn is_normal(self) -> bool {
+				self.is_normal()
+			}
+			fn is_subnormal(self) -> bool {
+				self.is_subnormal()
+			}
+			fn is_sign_positive(self) -> bool {
+				self.is_sign_positive()
+			}
+			fn is_sign_negative(self) -> bool {
+				self.is_sign_negative()
+			}
+			fn classify(self) -> FpCategory {
+				self.classify()
+			}
+
+			fn floor(self) -> Self {
+				core::$ty::math::floor(self)
+			}
+			fn ceil(self) -> Self {
+				core::$ty::math::ceil(self)
+			}
+			fn round(self) -> Self {
+				core::$ty::math::round(self)
+			}
+			fn round_ties_even(self) -> Self {
+				core::$ty::math::round_ties_even(self)
+			}
+			fn trunc(self) -> Self {
+				core::$ty::math::trunc(self)
+			}
+			fn fract(self) -> Self {
+				core::$ty::math::fract(self)
+			}
+
+			fn abs(self) -> Self {
+				self.abs()
+			}
+			fn signum(self) -> Self {
+				self.signum()
+			}
+			fn copysign(self, sign: Self) -> Self {
+				self.copysign(sign)
+			}
+			fn min(self, other: Self) -> Self {
+				self.min(other)
+			}
+			fn max(self, other: Self) -> Self {
+				self.max(other)
+			}
+			fn clamp(self, min: Self, max: Self) -> Self {
+				self.clamp(min, max)
+			}
+			fn mul_add(self, a: Self, b: Self) -> Self {
+				core::$ty::math::mul_add(self, a, b)
+			}
+			fn sqrt(self) -> Self {
+				core::$ty::math::sqrt(self)
+			}
+			fn cbrt(self) -> Self {
+				core::$ty::math::cbrt(self)
+			}
+			fn powi(self, n: i32) -> Self {
+				core::$ty::math::powi(self, n)
+			}
+			fn recip(self) -> Self {
+				self.recip()
+			}
+			fn div_euclid(self, rhs: Self) -> Self {
+				core::$ty::math::div_euclid(self, rhs)
+			}
+			fn rem_euclid(self, rhs: Self) -> Self {
+				core::$ty::math::rem_euclid(self, rhs)
+			}
+			fn to_degrees(self) -> Self {
+				self.to_degrees()
+			}
+			fn to_radians(self) -> Self {
+				self.to_radians()
+			}
+			fn total_cmp(&self, other: &Self) -> core::cmp::Ordering {
+				<$ty>::total_cmp(self, other)
+			}
+
+			fn to_bits(self) -> Self::Bits {
+				self.to_bits()
+			}
+			fn from_bits(bits: Self::Bits) -> Self {
+				<$ty>::from_bits(bits)
+			}
+			fn from_f64(value: f64) -> Self {
+				value as Self
+			}
+			fn to_f64(self) -> f64 {
+				self as f64
+			}
+		})*
+	};
+}
+impl_float!(f32 u32 f64 u64);
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn hypot<F: Float>(a: F, b: F) -> F {
+		a.mul_add(a, b * b).sqrt()
+	}
+
+	#[test]
+	fn float() {
+		assert_eq!(hypot(3.0f32, 4.0), 5.0);
+		assert_eq!(hypot(3.0f64, 4.0), 5.0);
+
+		assert_eq!(Float::floor(-1.5f64), -2.0);
+		assert_eq!(Float::round(2.5f32), 3.0);
+		assert_eq!(Float::round_ties_even(2.5f32), 2.0);
+		assert_eq!(Float::rem_euclid(-1.0f64, 3.0), 2.0);
+		assert_eq!(Float::powi(2.0f64, -2), 0.25);
+		assert!(Float::is_nan(f32::NAN));
+		assert_eq!(
+			Float::classify(f64::MIN_POSITIVE / 2.0),
+			FpCategory::Subnormal
+		);
+		assert_eq!(<f32 as Float>::from_bits(Float::to_bits(1.5f32)), 1.5);
+		assert_eq!(<f64 as Float>::SIZE_BITS, 64);
+	}
+}
@@ -0,0 +1,349 @@
+//! Formatting numbers as text, without going through `core::fmt`.
+//!
+//! Numbers are formatted into a stack buffer ([`IntBuffer`] or
+//! [`FloatBuffer`]), which returns the formatted text as a `&str`:
+//!
+//! ```rs
+//! let mut buf = IntBuffer::new();
+//! assert_eq!(buf.format(-42i32), "-42");
+//! assert_eq!(buf.format_radix(255u8, 16), "ff");
+//! ```
+
+use {
+	crate::{
+		io::{SliceWriter, Writer},
+		lang::Integer,
+		text::{format_args, write},
+	},
+	core::fmt::{self, Formatter},
+};
+
+/// Every number from `00` to `99`, so integers can be formatted two digits at
+/// a time.
+const DIGIT_PAIRS: &[u8; 200] = b"\
+	0001020304050607080910111213141516171819\
+	2021222324252627282930313233343536373839\
+	4041424344454647484950515253545556575859\
+	6061626364656667686970717273747576777879\
+	8081828384858687888990919293949596979899";
+const RADIX_DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// A buffer that integers can be formatted into.
+#[derive(Clone, Copy)]
+pub struct IntBuffer {
+	bytes: [u8; Self::LEN],
+}
+impl IntBuffer {
+	/// Enough space for any integer in any radix: `i128::MIN` in binary is a
+	/// `-` followed by 128 digits.
+	const LEN: usize = 129;
+
+	pub const fn new() -> Self {
+		Self {
+			bytes: [0; Self::LEN],
+		}
+	}
+
+	/// Formats `value` in base 10.
+	pub fn format<T: Integer>(&mut self, value: T) -> &str {
+		let (negative, mut magnitude) = split_sign(value);
+		let mut pos = Self::LEN;
+		// Formatting u64s is much faster than u128s, so large numbers are
+		// formatted 19 digits at a time
+		const CHUNK: u128 = 10_000_000_000_000_000_000;
+		while magnitude > u64::MAX as u128 {
+			let chunk = (magnitude % CHUNK) as u64;
+			magnitude /= CHUNK;
+			let end = pos;
+			pos = write_u64(chunk, &mut self.bytes, pos);
+			let padded = end - 19;
+			self.bytes[padded..pos].fill(b'0');
+			pos = padded;
+		}
+		pos = write_u64(magnitude as u64, &mut self.bytes, pos);
+
+		self.finish(negative, pos)
+	}
+	/// Formats `value` in the given radix, with lowercase letters for digits
+	/// above 9.
+	///
+	/// Panics if `radix` isn't in the range `2..=36`.
+	pub fn format_radix<T: Integer>(&mut self, value: T, radix: u32) -> &str {
+		assert!(
+			(2..=36).contains(&radix),
+			"format_radix: radix must be in the range 2..=36, got {radix}"
+		);
+
+		let (negative, mut magnitude) = split_sign(value);
+		let radix = radix as u128;
+		let mut pos = Self::LEN;
+		loop {
+			pos -= 1;
+			self.bytes[pos] = RADIX_DIGITS[(magnitude % radix) as usize];
+			magnitude /= radix;
+			if magnitude == 0 {
+				break;
+			}
+		}
+
+		self.finish(negative, pos)
+	}
+
+	fn finish(&mut self, negative: bool, mut pos: usize) -> &str {
+		if negative {
+			pos -= 1;
+			self.bytes[pos] = b'-';
+		}
+		unsafe { str::from_utf8_unchecked(&self.bytes[pos..]) }
+	}
+}
+impl Default for IntBuffer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Splits an integer into whether it's negative, and its magnitude.
+pub(super) fn split_sign<T: Integer>(value: T) -> (bool, u128) {
+	if T::SIGNED && value < T::ZERO {
+		(true, value.as_u128().wrapping_neg())
+	} else {
+		(false, value.as_u128())
+	}
+}
+
+/// Writes `n`'s digits to the end of `buf[..pos]`, and returns the index of
+/// the first digit.
+fn write_u64(mut n: u64, buf: &mut [u8], mut pos: usize) -> usize {
+	while n >= 100 {
+		let pair = (n % 100) as usize * 2;
+		n /= 100;
+		pos -= 2;
+		buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+	}
+	if n >= 10 {
+		let pair = n as usize * 2;
+		pos -= 2;
+		buf[pos..pos + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+	} else {
+		pos -= 1;
+		buf[pos] = b'0' + n as u8;
+	}
+
+	pos
+}
+
+/// A buffer that floats can be formatted into.
+///
+/// Floats are formatted with the fewest digits that parse back to the same
+/// value. Numbers between `1e-5` and `1e16` are written as decimals (e.g.
+/// `0.1` or `12.5`); others use scientific notation (e.g. `1e100`). Like
+/// `core::fmt`, infinity is `inf` and NaN is `NaN`.
+#[derive(Clone, Copy)]
+pub struct FloatBuffer {
+	bytes: [u8; Self::LEN],
+	len: usize,
+}
+impl FloatBuffer {
+	/// Enough space for a sign, 16 integer digits, a decimal point, and 17
+	/// fractional digits, or for the longest float in scientific notation.
+	const LEN: usize = 40;
+	/// The most fractional digits needed to exactly represent an `f64`.
+	const MAX_DECIMALS: u32 = 17;
+
+	pub const fn new() -> Self {
+		Self {
+			bytes: [0; Self::LEN],
+			len: 0,
+		}
+	}
+
+	/// Formats an [`f64`].
+	pub fn format_f64(&mut self, value: f64) -> &str {
+		self.format(
+			value,
+			|s| s.parse::<f64>() == Ok(value),
+			|writer| writer.write_fmt(format_args!("{value:e}")),
+		)
+	}
+	/// Formats an [`f32`].
+	pub fn format_f32(&mut self, value: f32) -> &str {
+		self.format(
+			value as f64,
+			|s| s.parse::<f32>() == Ok(value),
+			|writer| writer.write_fmt(format_args!("{value:e}")),
+		)
+	}
+
+	fn format(
+		&mut self,
+		value: f64,
+		roundtrips: impl Fn(&str) -> bool,
+		scientific: impl FnOnce(&mut SliceWriter<'_>) -> Result<(), ()>,
+	) -> &str {
+		if value.is_nan() {
+			return "NaN";
+		}
+		if value.is_infinite() {
+			return if value < 0.0 { "-inf" } else { "inf" };
+		}
+
+		let abs = value.abs();
+		if abs == 0.0 || (1e-5..1e16).contains(&abs) {
+			// Find the fewest decimals that still parse to the same number
+			for decimals in 0..=Self::MAX_DECIMALS {
+				self.len = self.write_fixed(value.is_sign_negative(), abs, decimals);
+				if roundtrips(self.as_str()) {
+					return self.as_str();
+				}
+			}
+		}
+
+		// Out of range for fixed notation, or rounding errors kept it from
+		// round-tripping
+		let mut writer = SliceWriter::new(&mut self.bytes);
+		scientific(&mut writer).unwrap();
+		self.len = writer.written().len();
+		self.as_str()
+	}
+
+	/// Writes `abs` with a fixed number of decimals, and returns the length
+	/// of the written text.
+	fn write_fixed(&mut self, negative: bool, abs: f64, decimals: u32) -> usize {
+		let scale = 10u64.pow(decimals);
+		let mut int = abs as u64;
+		let mut frac = ((abs - int as f64) * scale as f64 + 0.5) as u64;
+		if frac >= scale {
+			frac -= scale;
+			int += 1;
+		}
+
+		let mut digits = [0; Self::LEN];
+		let mut pos = Self::LEN;
+		if decimals > 0 {
+			let end = pos;
+			pos = write_u64(frac, &mut digits, pos);
+			let padded = end - decimals as usize;
+			digits[padded..pos].fill(b'0');
+			pos = padded - 1;
+			digits[pos] = b'.';
+		}
+		pos = write_u64(int, &mut digits, pos);
+		if negative {
+			pos -= 1;
+			digits[pos] = b'-';
+		}
+
+		let len = Self::LEN - pos;
+		self.bytes[..len].copy_from_slice(&digits[pos..]);
+		len
+	}
+
+	fn as_str(&self) -> &str {
+		unsafe { str::from_utf8_unchecked(&self.bytes[..self.len]) }
+	}
+}
+impl Default for FloatBuffer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Writes an integer to `writer` in base 10. See [`IntBuffer`].
+pub fn write_int<W: Writer, T: Integer>(writer: &mut W, value: T) -> Result<(), W::Error> {
+	writer.write_all(IntBuffer::new().format(value).as_bytes())
+}
+/// Writes an [`f64`] to `writer`. See [`FloatBuffer`].
+pub fn write_float<W: Writer>(writer: &mut W, value: f64) -> Result<(), W::Error> {
+	writer.write_all(FloatBuffer::new().format_f64(value).as_bytes())
+}
+
+/// Writes `amount` in the largest unit it's at least one of, with up to two
+/// decimals, like `1.5 MiB`. `units` is a list of unit names and their scales,
+/// sorted from smallest to largest.
+pub(crate) fn write_amount(
+	f: &mut Formatter<'_>,
+	amount: u128,
+	units: &[(&str, u128)],
+) -> fmt::Result {
+	let &(name, scale) = units
+		.iter()
+		.rev()
+		.find(|(_, scale)| *scale <= amount)
+		.unwrap_or(&units[0]);
+	let int = amount / scale;
+	let hundredths = (amount % scale) * 100 / scale;
+
+	write!(f, "{int}")?;
+	if hundredths.is_multiple_of(10) {
+		if hundredths != 0 {
+			write!(f, ".{}", hundredths / 10)?;
+		}
+	} else {
+		write!(f, ".{hundredths:02}")?;
+	}
+	write!(f, " {name}")
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn format_ints() {
+		let mut buf = IntBuffer::new();
+		assert_eq!(buf.format(0u8), "0");
+		assert_eq!(buf.format(7u8), "7");
+		assert_eq!(buf.format(-42i32), "-42");
+		assert_eq!(buf.format(1234567u32), "1234567");
+		assert_eq!(buf.format(i8::MIN), "-128");
+		assert_eq!(
+			buf.format(u128::MAX),
+			"340282366920938463463374607431768211455"
+		);
+		assert_eq!(
+			buf.format(i128::MIN),
+			"-170141183460469231731687303715884105728"
+		);
+		// A chunk with leading zeros
+		assert_eq!(
+			buf.format(10_000_000_000_000_000_000_000u128),
+			"10000000000000000000000"
+		);
+
+		assert_eq!(buf.format_radix(255u8, 16), "ff");
+		assert_eq!(buf.format_radix(-5i8, 2), "-101");
+		assert_eq!(buf.format_radix(i128::MIN, 2).len(), 129);
+
+		let mut out = Vec::new();
+		write_int(&mut out, -1i64).unwrap();
+		assert_eq!(out, b"-1");
+	}
+
+	#[test]
+	fn format_floats() {
+		let mut buf = FloatBuffer::new();
+		assert_eq!(buf.format_f64(0.0), "0");
+		assert_eq!(buf.format_f64(-0.0), "-0");
+		assert_eq!(buf.format_f64(3.0), "3");
+		assert_eq!(buf.format_f64(0.1), "0.1");
+		assert_eq!(buf.format_f64(-12.5), "-12.5");
+		assert_eq!(buf.format_f64(0.1 + 0.2), "0.30000000000000004");
+		assert_eq!(buf.format_f64(1e100), "1e100");
+		assert_eq!(buf.format_f64(f64::NAN), "NaN");
+		assert_eq!(buf.format_f64(f64::NEG_INFINITY), "-inf");
+		assert_eq!(buf.format_f32(0.1), "0.1");
+		assert_eq!(buf.format_f32(16777216.0), "16777216");
+
+		// Everything round-trips
+		for value in [f64::MAX, f64::MIN_POSITIVE, 1.0 / 3.0, 123456.789, 5e-324] {
+			assert_eq!(buf.format_f64(value).parse::<f64>(), Ok(value));
+		}
+	}
+}
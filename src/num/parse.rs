@@ -0,0 +1,196 @@
+//! Parsing numbers from text, without allocating.
+
+#[doc(inline)]
+pub use core::num::ParseFloatError;
+
+use crate::lang::Integer;
+
+/// An error from parsing an integer.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParseIntError {
+	/// The string was empty, or only had a sign.
+	Empty,
+	/// The string had a character that isn't a digit in the radix being
+	/// parsed. `position` is the character's byte index.
+	InvalidDigit { position: usize },
+	/// The number is too large to fit in the integer type.
+	PositiveOverflow,
+	/// The number is too small to fit in the integer type.
+	NegativeOverflow,
+}
+
+/// Parses a base 10 integer. See [`from_str_radix`].
+pub fn parse<T: Integer>(s: &str) -> Result<T, ParseIntError> {
+	from_str_radix(s, 10)
+}
+
+/// Parses an integer in the given radix. The integer may start with `+`, or
+/// with `-` if `T` is signed. Digits above 9 are letters, in either case (so
+/// hexadecimal `ff` and `FF` are both 255).
+///
+/// Panics if `radix` isn't in the range `2..=36`.
+pub fn from_str_radix<T: Integer>(s: &str, radix: u32) -> Result<T, ParseIntError> {
+	assert!(
+		(2..=36).contains(&radix),
+		"from_str_radix: radix must be in the range 2..=36, got {radix}"
+	);
+
+	let bytes = s.as_bytes();
+	let (negative, digits_start) = match bytes.first() {
+		Some(b'+') => (false, 1),
+		Some(b'-') if T::SIGNED => (true, 1),
+		_ => (false, 0),
+	};
+	if bytes.len() == digits_start {
+		return Err(ParseIntError::Empty);
+	}
+
+	// The number's magnitude is built up as a u128, then checked against the
+	// largest magnitude `T` can hold
+	let (limit, overflow) = if negative {
+		(
+			T::MIN.as_u128().wrapping_neg(),
+			ParseIntError::NegativeOverflow,
+		)
+	} else {
+		(T::MAX.as_u128(), ParseIntError::PositiveOverflow)
+	};
+	let mut magnitude: u128 = 0;
+	for (position, byte) in bytes.iter().enumerate().skip(digits_start) {
+		let digit = match byte {
+			b'0'..=b'9' => byte - b'0',
+			b'a'..=b'z' => byte - b'a' + 10,
+			b'A'..=b'Z' => byte - b'A' + 10,
+			_ => 36,
+		} as u32;
+		if digit >= radix {
+			return Err(ParseIntError::InvalidDigit { position });
+		}
+		magnitude = magnitude
+			.checked_mul(radix as u128)
+			.and_then(|magnitude| magnitude.checked_add(digit as u128))
+			.filter(|magnitude| *magnitude <= limit)
+			.ok_or(overflow)?;
+	}
+
+	Ok(if negative {
+		T::from_u128_truncating(magnitude.wrapping_neg())
+	} else {
+		T::from_u128_truncating(magnitude)
+	})
+}
+
+/// Parses an [`f32`]. This accepts the same syntax as `f32`'s `FromStr`
+/// implementation, e.g. `1.5`, `-2e10`, `inf`, and `NaN`.
+pub fn parse_f32(s: &str) -> Result<f32, ParseFloatError> {
+	s.parse()
+}
+/// Parses an [`f64`]. This accepts the same syntax as `f64`'s `FromStr`
+/// implementation, e.g. `1.5`, `-2e10`, `inf`, and `NaN`.
+pub fn parse_f64(s: &str) -> Result<f64, ParseFloatError> {
+	s.parse()
+}
+
+/// An error from parsing an amount with a unit, like `512k` or `1.5s`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParseAmountError {
+	/// The string was empty.
+	Empty,
+	/// The number before the unit wasn't a valid decimal number.
+	InvalidNumber,
+	/// The unit after the number wasn't recognised.
+	UnknownUnit,
+	/// The amount is too large to be represented.
+	Overflow,
+}
+
+/// Parses a decimal number followed by an optional unit, like `512k` or
+/// `1.5 s`, and returns the number multiplied by the unit's scale. `units`
+/// maps unit names (matched case-insensitively) to their scales; an empty
+/// name allows the unit to be left out.
+///
+/// Fractional amounts are rounded down to a whole number of the smallest unit.
+pub(crate) fn parse_amount(s: &str, units: &[(&str, u128)]) -> Result<u128, ParseAmountError> {
+	let s = s.trim();
+	if s.is_empty() {
+		return Err(ParseAmountError::Empty);
+	}
+	let number_len = s
+		.bytes()
+		.position(|byte| !byte.is_ascii_digit() && byte != b'.')
+		.unwrap_or(s.len());
+	let (number, unit) = s.split_at(number_len);
+	let unit = unit.trim_start();
+	let (int, frac) = number.split_once('.').unwrap_or((number, ""));
+	if (int.is_empty() && frac.is_empty()) || frac.contains('.') {
+		return Err(ParseAmountError::InvalidNumber);
+	}
+	let &(_, scale) = units
+		.iter()
+		.find(|(name, _)| name.eq_ignore_ascii_case(unit))
+		.ok_or(ParseAmountError::UnknownUnit)?;
+
+	let mut amount: u128 = 0;
+	for byte in int.bytes() {
+		amount = amount
+			.checked_mul(10)
+			.and_then(|amount| amount.checked_add((byte - b'0') as u128))
+			.ok_or(ParseAmountError::Overflow)?;
+	}
+	amount = amount
+		.checked_mul(scale)
+		.ok_or(ParseAmountError::Overflow)?;
+
+	// Digits past the 18th can't change the result for any scale below 10^18
+	let mut numerator: u128 = 0;
+	let mut denominator: u128 = 1;
+	for byte in frac.bytes().take(18) {
+		numerator = numerator * 10 + (byte - b'0') as u128;
+		denominator *= 10;
+	}
+	amount
+		.checked_add(numerator * scale / denominator)
+		.ok_or(ParseAmountError::Overflow)
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_ints() {
+		assert_eq!(parse::<u8>("255"), Ok(255));
+		assert_eq!(parse::<u8>("+7"), Ok(7));
+		assert_eq!(parse::<u8>("256"), Err(ParseIntError::PositiveOverflow));
+		assert_eq!(
+			parse::<u8>("-1"),
+			Err(ParseIntError::InvalidDigit { position: 0 })
+		);
+		assert_eq!(parse::<i8>("-128"), Ok(-128));
+		assert_eq!(parse::<i8>("-129"), Err(ParseIntError::NegativeOverflow));
+		assert_eq!(
+			parse::<i128>("-170141183460469231731687303715884105728"),
+			Ok(i128::MIN)
+		);
+		assert_eq!(
+			parse::<u128>("340282366920938463463374607431768211455"),
+			Ok(u128::MAX)
+		);
+		assert_eq!(parse::<u32>(""), Err(ParseIntError::Empty));
+		assert_eq!(parse::<i32>("-"), Err(ParseIntError::Empty));
+		assert_eq!(
+			parse::<u32>("12a"),
+			Err(ParseIntError::InvalidDigit { position: 2 })
+		);
+
+		assert_eq!(from_str_radix::<u32>("fF", 16), Ok(255));
+		assert_eq!(from_str_radix::<i16>("-101", 2), Ok(-5));
+		assert_eq!(parse_f64("-2.5e3"), Ok(-2500.0));
+	}
+}
@@ -0,0 +1,355 @@
+//! Integer wrappers whose arithmetic operators saturate or wrap on overflow,
+//! instead of panicking.
+//!
+//! Both wrappers implement [`Integer`], so they can be passed to any code
+//! that's generic over integers:
+//!
+//! ```rs
+//! let health = Saturating(10u8) - Saturating(25);
+//! assert_eq!(health, Saturating(0));
+//!
+//! let tick = Wrapping(u32::MAX) + Wrapping(1);
+//! assert_eq!(tick, Wrapping(0));
+//! ```
+
+use {
+	crate::lang::{
+		Add, AddAssign, Div, DivAssign, Integer, Mul, MulAssign, SignedInteger, Sub, SubAssign,
+		UnsignedInteger,
+	},
+	core::{
+		fmt::{self, Debug, Display, Formatter},
+		iter::Step,
+	},
+};
+
+/// An integer whose arithmetic operators saturate at its minimum and maximum
+/// values, instead of overflowing.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Saturating<T>(pub T);
+
+/// An integer whose arithmetic operators wrap around on overflow.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Wrapping<T>(pub T);
+
+macro_rules! impl_wrapper_traits {
+	($($wrapper:ident)*) => {
+		$(
+			impl<T: Debug> Debug for $wrapper<T> {
+				fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+					self.0.fmt(f)
+				}
+			}
+			impl<T: Display> Display for $wrapper<T> {
+				fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+					self.0.fmt(f)
+				}
+			}
+
+			impl<T: Step> Step for $wrapper<T> {
+				fn steps_between(start: &Self, end: &Self) -> (usize, Option<usize>) {
+					T::steps_between(&start.0, &end.0)
+				}
+				fn forward_checked(start: Self, count: usize) -> Option<Self> {
+					T::forward_checked(start.0, count).map(Self)
+				}
+				fn forward_overflowing(start: Self, count: usize) -> (Self, bool) {
+					let (val, overflowed) = T::forward_overflowing(start.0, count);
+					(Self(val), overflowed)
+				}
+				fn backward_checked(start: Self, count: usize) -> Option<Self> {
+					T::backward_checked(start.0, count).map(Self)
+				}
+				fn backward_overflowing(start: Self, count: usize) -> (Self, bool) {
+					let (val, overflowed) = T::backward_overflowing(start.0, count);
+					(Self(val), overflowed)
+				}
+			}
+		)*
+	};
+}
+impl_wrapper_traits!(Saturating Wrapping);
+
+/// Implements the arithmetic operators and [`Integer`] for a wrapper, for
+/// every integer type. `$add`, `$sub`, `$mul`, `$div`, and `$pow` are the
+/// integer methods the wrapper's operators (and [`Integer::pow`]) use.
+macro_rules! impl_wrapper_integer {
+	(
+		$wrapper:ident: $add:ident $sub:ident $mul:ident $div:ident $pow:ident;
+		$($ty:ty)*
+	) => {
+		$(
+			impl const Add for $wrapper<$ty> {
+				type Output = Self;
+
+				fn add(self, rhs: Self) -> Self {
+					Self(self.0.$add(rhs.0))
+				}
+			}
+			impl AddAssign for $wrapper<$ty> {
+				fn add_assign(&mut self, rhs: Self) {
+					*self = *self + rhs;
+				}
+			}
+			impl const Sub for $wrapper<$ty> {
+				type Output = Self;
+
+				fn sub(self, rhs: Self) -> Self {
+					Self(self.0.$sub(rhs.0))
+				}
+			}
+			impl SubAssign for $wrapper<$ty> {
+				fn sub_assign(&mut self, rhs: Self) {
+					*self = *self - rhs;
+				}
+			}
+			impl const Mul for $wrapper<$ty> {
+				type Output = Self;
+
+				fn mul(self, rhs: Self) -> Self {
+					Self(self.0.$mul(rhs.0))
+				}
+			}
+			impl MulAssign for $wrapper<$ty> {
+				fn mul_assign(&mut self, rhs: Self) {
+					*self = *self * rhs;
+				}
+			}
+			impl const Div for $wrapper<$ty> {
+				type Output = Self;
+
+				fn div(self, rhs: Self) -> Self {
+					Self(self.0.$div(rhs.0))
+				}
+			}
+			impl DivAssign for $wrapper<$ty> {
+				fn div_assign(&mut self, rhs: Self) {
+					*self = *self / rhs;
+				}
+			}
+
+			impl const Integer for $wrapper<$ty> {
+				const MAX: Self = Self(<$ty>::MAX);
+				const MIN: Self = Self(<$ty>::MIN);
+				const SIZE_BITS: u8 = <$ty as Integer>::SIZE_BITS;
+				const SIZE_BYTES: u8 = <$ty as Integer>::SIZE_BYTES;
+				const SIGNED: bool = <$ty as Integer>::SIGNED;
+
+				const ZERO: Self = Self(0);
+				const ONE: Self = Self(1);
+				const TWO: Self = Self(2);
+				const THREE: Self = Self(3);
+				const FOUR: Self = Self(4);
+				const FIVE: Self = Self(5);
+
+				fn saturating_add(self, rhs: Self) -> Self {
+					Self(self.0.saturating_add(rhs.0))
+				}
+				fn saturating_sub(self, rhs: Self) -> Self {
+					Self(self.0.saturating_sub(rhs.0))
+				}
+				fn saturating_div(self, rhs: Self) -> Self {
+					Self(self.0.saturating_div(rhs.0))
+				}
+				fn saturating_mul(self, rhs: Self) -> Self {
+					Self(self.0.saturating_mul(rhs.0))
+				}
+
+				fn checked_add(self, rhs: Self) -> Option<Self> {
+					match self.0.checked_add(rhs.0) {
+						Some(val) => Some(Self(val)),
+						None => None,
+					}
+				}
+				fn checked_sub(self, rhs: Self) -> Option<Self> {
+					match self.0.checked_sub(rhs.0) {
+						Some(val) => Some(Self(val)),
+						None => None,
+					}
+				}
+				fn checked_div(self, rhs: Self) -> Option<Self> {
+					match self.0.checked_div(rhs.0) {
+						Some(val) => Some(Self(val)),
+						None => None,
+					}
+				}
+				fn checked_mul(self, rhs: Self) -> Option<Self> {
+					match self.0.checked_mul(rhs.0) {
+						Some(val) => Some(Self(val)),
+						None => None,
+					}
+				}
+				fn checked_pow(self, exp: u32) -> Option<Self> {
+					match self.0.checked_pow(exp) {
+						Some(val) => Some(Self(val)),
+						None => None,
+					}
+				}
+
+				fn wrapping_add(self, rhs: Self) -> Self {
+					Self(self.0.wrapping_add(rhs.0))
+				}
+				fn wrapping_sub(self, rhs: Self) -> Self {
+					Self(self.0.wrapping_sub(rhs.0))
+				}
+				fn wrapping_div(self, rhs: Self) -> Self {
+					Self(self.0.wrapping_div(rhs.0))
+				}
+				fn wrapping_mul(self, rhs: Self) -> Self {
+					Self(self.0.wrapping_mul(rhs.0))
+				}
+				fn wrapping_neg(self) -> Self {
+					Self(self.0.wrapping_neg())
+				}
+				fn wrapping_pow(self, exp: u32) -> Self {
+					Self(self.0.wrapping_pow(exp))
+				}
+
+				fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+					let (val, overflowed) = self.0.overflowing_add(rhs.0);
+					(Self(val), overflowed)
+				}
+				fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+					let (val, overflowed) = self.0.overflowing_sub(rhs.0);
+					(Self(val), overflowed)
+				}
+				fn overflowing_div(self, rhs: Self) -> (Self, bool) {
+					let (val, overflowed) = self.0.overflowing_div(rhs.0);
+					(Self(val), overflowed)
+				}
+				fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+					let (val, overflowed) = self.0.overflowing_mul(rhs.0);
+					(Self(val), overflowed)
+				}
+				fn overflowing_pow(self, exp: u32) -> (Self, bool) {
+					let (val, overflowed) = self.0.overflowing_pow(exp);
+					(Self(val), overflowed)
+				}
+
+				fn pow(self, exp: u32) -> Self {
+					Self(self.0.$pow(exp))
+				}
+				fn saturating_pow(self, exp: u32) -> Self {
+					Self(self.0.saturating_pow(exp))
+				}
+
+				fn leading_zeros(self) -> u32 {
+					self.0.leading_zeros()
+				}
+				fn trailing_zeros(self) -> u32 {
+					self.0.trailing_zeros()
+				}
+				fn count_ones(self) -> u32 {
+					self.0.count_ones()
+				}
+				fn count_zeros(self) -> u32 {
+					self.0.count_zeros()
+				}
+				fn rotate_left(self, n: u32) -> Self {
+					Self(self.0.rotate_left(n))
+				}
+				fn rotate_right(self, n: u32) -> Self {
+					Self(self.0.rotate_right(n))
+				}
+				fn swap_bytes(self) -> Self {
+					Self(self.0.swap_bytes())
+				}
+
+				type Bytes = <$ty as Integer>::Bytes;
+
+				fn to_le_bytes(self) -> Self::Bytes {
+					self.0.to_le_bytes()
+				}
+				fn to_be_bytes(self) -> Self::Bytes {
+					self.0.to_be_bytes()
+				}
+				fn to_ne_bytes(self) -> Self::Bytes {
+					self.0.to_ne_bytes()
+				}
+				fn from_le_bytes(bytes: Self::Bytes) -> Self {
+					Self(<$ty>::from_le_bytes(bytes))
+				}
+				fn from_be_bytes(bytes: Self::Bytes) -> Self {
+					Self(<$ty>::from_be_bytes(bytes))
+				}
+				fn from_ne_bytes(bytes: Self::Bytes) -> Self {
+					Self(<$ty>::from_ne_bytes(bytes))
+				}
+
+				fn as_u128(self) -> u128 {
+					self.0 as u128
+				}
+				fn from_u128_truncating(value: u128) -> Self {
+					Self(value as $ty)
+				}
+			}
+		)*
+	};
+}
+impl_wrapper_integer!(
+	Saturating: saturating_add saturating_sub saturating_mul saturating_div saturating_pow;
+	u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize
+);
+impl_wrapper_integer!(
+	Wrapping: wrapping_add wrapping_sub wrapping_mul wrapping_div wrapping_pow;
+	u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize
+);
+
+macro_rules! impl_wrapper_markers {
+	($($wrapper:ident)*) => {
+		$(
+			impl UnsignedInteger for $wrapper<u8> {}
+			impl SignedInteger for $wrapper<i8> {}
+			impl UnsignedInteger for $wrapper<u16> {}
+			impl SignedInteger for $wrapper<i16> {}
+			impl UnsignedInteger for $wrapper<u32> {}
+			impl SignedInteger for $wrapper<i32> {}
+			impl UnsignedInteger for $wrapper<u64> {}
+			impl SignedInteger for $wrapper<i64> {}
+			impl UnsignedInteger for $wrapper<u128> {}
+			impl SignedInteger for $wrapper<i128> {}
+			impl UnsignedInteger for $wrapper<usize> {}
+			impl SignedInteger for $wrapper<isize> {}
+		)*
+	};
+}
+impl_wrapper_markers!(Saturating Wrapping);
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sum<T: Integer>(values: &[T]) -> T {
+		values.iter().fold(T::ZERO, |acc, val| acc + *val)
+	}
+
+	#[test]
+	fn wrappers() {
+		assert_eq!(Saturating(10u8) - Saturating(25), Saturating(0));
+		assert_eq!(Saturating(i8::MIN) / Saturating(-1), Saturating(i8::MAX));
+		assert_eq!(Saturating(3u8).pow(6), Saturating(255));
+		let mut health = Saturating(250u8);
+		health += Saturating(10);
+		assert_eq!(health, Saturating::MAX);
+
+		assert_eq!(Wrapping(u32::MAX) + Wrapping(1), Wrapping(0));
+		assert_eq!(Wrapping(0i16) - Wrapping(1), Wrapping(-1));
+		assert_eq!(Wrapping(3u8).pow(6), Wrapping(217));
+
+		assert_eq!(sum(&[Saturating(200u8), Saturating(100)]), Saturating(255));
+		assert_eq!(sum(&[Wrapping(200u8), Wrapping(100)]), Wrapping(44));
+		assert_eq!(
+			(Saturating(1u8)..Saturating(4)).collect::<Vec<_>>(),
+			[Saturating(1), Saturating(2), Saturating(3)]
+		);
+		assert_eq!(format!("{:?}", Wrapping(-5i32)), "-5");
+	}
+}
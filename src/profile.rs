@@ -0,0 +1,302 @@
+//! Lightweight timing instrumentation for finding where an app spends its
+//! time.
+//!
+//! Code is timed by putting a [`scope`] at the start of a block. The scope
+//! measures how long it takes for the rest of the block to run:
+//!
+//! ```rs
+//! fn update(world: &mut World) {
+//!     profile::scope!("update");
+//!     physics(world);
+//!     {
+//!         profile::scope!("render");
+//!         render(world);
+//!     }
+//! }
+//! ```
+//!
+//! Functions can be timed as a whole with the [`#[profile]`] attribute, which
+//! puts a scope named after the function at the start of its body.
+//!
+//! Each timed scope is recorded as a [`ProfileEvent`] in an in-memory ring
+//! buffer, which holds the most recent [`capacity`] events; older events are
+//! overwritten. The recorded events can be inspected with [`events`], or
+//! exported with [`write_chrome_trace`] and opened in a trace viewer like
+//! `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+//!
+//! This module is only available with the `profile` crate feature.
+//!
+//! [`scope`]: crate::profile::scope
+//! [`#[profile]`]: crux_macros::profile
+
+use {
+	crate::{
+		concurrency::Mutex,
+		io::Writer,
+		rt::time::{Duration, Instant},
+		text::{format_args, write},
+	},
+	core::{
+		fmt::{self, Display, Formatter},
+		sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+	},
+};
+
+/// How many events the ring buffer holds by default.
+pub const DEFAULT_CAPACITY: usize = 65536;
+
+/// One timed scope.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ProfileEvent {
+	/// The scope's name.
+	pub name: &'static str,
+	/// The path of the module the scope was in, as returned by
+	/// `module_path!()`.
+	pub module: &'static str,
+	/// When the scope started.
+	pub start: Instant,
+	/// How long the scope took.
+	pub duration: Duration,
+	/// The thread the scope ran on. This is an ID assigned by this module, not
+	/// an OS thread ID; the first thread to record an event gets ID 1.
+	pub thread: u64,
+}
+
+/// A bounded buffer that overwrites its oldest event once it's full.
+struct Recorder {
+	events: Vec<ProfileEvent>,
+	/// Where the next event goes once the buffer is full.
+	next: usize,
+	capacity: usize,
+}
+impl Recorder {
+	fn push(&mut self, event: ProfileEvent) {
+		if self.capacity == 0 {
+			return;
+		}
+		if self.events.len() < self.capacity {
+			self.events.push(event);
+		} else {
+			self.events[self.next] = event;
+			self.next = (self.next + 1) % self.capacity;
+		}
+	}
+}
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder {
+	events: Vec::new(),
+	next: 0,
+	capacity: DEFAULT_CAPACITY,
+});
+
+static NEXT_THREAD: AtomicU64 = AtomicU64::new(1);
+#[thread_local]
+static mut THREAD: u64 = 0;
+
+/// The ID of the current thread, as stored in [`ProfileEvent::thread`].
+fn thread() -> u64 {
+	unsafe {
+		if THREAD == 0 {
+			THREAD = NEXT_THREAD.fetch_add(1, AtomicOrdering::Relaxed);
+		}
+		THREAD
+	}
+}
+
+/// Times a scope, and records it when dropped. Created by the [`scope`]
+/// macro.
+///
+/// [`scope`]: crate::profile::scope
+#[must_use = "the scope is recorded as soon as its timer is dropped"]
+pub struct ScopeTimer {
+	name: &'static str,
+	module: &'static str,
+	start: Instant,
+}
+impl ScopeTimer {
+	/// Starts timing a scope. Usually the [`scope`] macro is used instead.
+	///
+	/// [`scope`]: crate::profile::scope
+	pub fn new(name: &'static str, module: &'static str) -> Self {
+		Self {
+			name,
+			module,
+			start: Instant::now(),
+		}
+	}
+}
+impl Drop for ScopeTimer {
+	fn drop(&mut self) {
+		record(ProfileEvent {
+			name: self.name,
+			module: self.module,
+			start: self.start,
+			duration: self.start.elapsed(),
+			thread: thread(),
+		});
+	}
+}
+
+/// Times the rest of the current block, under the given name. See the
+/// [module-level docs].
+///
+/// ```rs
+/// profile::scope!("load assets");
+/// ```
+///
+/// [module-level docs]: crate::profile
+#[macro_export]
+macro_rules! scope {
+	($name:expr $(,)?) => {
+		let _scope =
+			$crate::profile::ScopeTimer::new($name, $crate::lang::compiler::module_path!());
+	};
+}
+#[doc(inline)]
+pub use crate::scope;
+
+/// Adds an event to the ring buffer. This is useful for events measured some
+/// other way than with a [`ScopeTimer`].
+pub fn record(event: ProfileEvent) {
+	RECORDER.lock().push(event);
+}
+
+/// Every event in the ring buffer, in the order they were recorded. Since
+/// scopes are recorded when they end, nested scopes come before the scopes
+/// they're in.
+pub fn events() -> Vec<ProfileEvent> {
+	let recorder = RECORDER.lock();
+	let mut events = Vec::with_capacity(recorder.events.len());
+	events.extend_from_slice(&recorder.events[recorder.next..]);
+	events.extend_from_slice(&recorder.events[..recorder.next]);
+
+	events
+}
+
+/// Removes every event from the ring buffer.
+pub fn clear() {
+	let mut recorder = RECORDER.lock();
+	recorder.events.clear();
+	recorder.next = 0;
+}
+
+/// How many events the ring buffer holds before it starts overwriting the
+/// oldest ones. Defaults to [`DEFAULT_CAPACITY`].
+pub fn capacity() -> usize {
+	RECORDER.lock().capacity
+}
+/// Changes how many events the ring buffer holds. This clears the buffer.
+///
+/// A capacity of 0 disables recording.
+pub fn set_capacity(capacity: usize) {
+	let mut recorder = RECORDER.lock();
+	recorder.events = Vec::with_capacity(capacity);
+	recorder.next = 0;
+	recorder.capacity = capacity;
+}
+
+/// Writes every event in the ring buffer to `writer` in the Chrome trace event
+/// format (JSON), which can be loaded by `chrome://tracing` or
+/// [Perfetto](https://ui.perfetto.dev).
+///
+/// Timestamps are relative to the earliest event in the buffer.
+pub fn write_chrome_trace<W: Writer>(writer: &mut W) -> Result<(), ()> {
+	let events = events();
+	let Some(epoch) = events.iter().map(|event| event.start).min() else {
+		return writer.write_fmt(format_args!("{{\"traceEvents\":[]}}\n"));
+	};
+
+	writer.write_fmt(format_args!("{{\"traceEvents\":["))?;
+	for (idx, event) in events.iter().enumerate() {
+		if idx != 0 {
+			writer.write_fmt(format_args!(","))?;
+		}
+		writer.write_fmt(format_args!("\n{{\"name\":\""))?;
+		write_json_str(writer, event.name)?;
+		writer.write_fmt(format_args!("\",\"cat\":\""))?;
+		write_json_str(writer, event.module)?;
+		writer.write_fmt(format_args!(
+			"\",\"ph\":\"X\",\"ts\":{},\"dur\":{},\"pid\":0,\"tid\":{}}}",
+			Micros(event.start.duration_since(epoch)),
+			Micros(event.duration),
+			event.thread,
+		))?;
+	}
+	writer.write_fmt(format_args!("\n]}}\n"))
+}
+
+/// Formats a duration as a decimal number of microseconds, which is the unit
+/// Chrome traces use.
+struct Micros(Duration);
+impl Display for Micros {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let nanos = self.0.as_nanos();
+		write!(f, "{}.{:03}", nanos / 1000, nanos % 1000)
+	}
+}
+
+/// Writes `s` with JSON string escapes, without the surrounding quotes.
+fn write_json_str<W: Writer>(writer: &mut W, s: &str) -> Result<(), ()> {
+	for c in s.chars() {
+		match c {
+			'"' => writer.write_fmt(format_args!("\\\""))?,
+			'\\' => writer.write_fmt(format_args!("\\\\"))?,
+			'\n' => writer.write_fmt(format_args!("\\n"))?,
+			c if (c as u32) < 0x20 => writer.write_fmt(format_args!("\\u{:04x}", c as u32))?,
+			c => writer.write_fmt(format_args!("{c}"))?,
+		}
+	}
+
+	Ok(())
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn profile() {
+		set_capacity(2);
+		{
+			scope!("outer");
+			{
+				scope!("inner");
+			}
+		}
+		let names: Vec<_> = events().iter().map(|event| event.name).collect();
+		assert_eq!(names, ["inner", "outer"]);
+		assert!(events().iter().all(|event| event.thread == thread()));
+
+		// The oldest event is overwritten once the buffer is full
+		{
+			scope!("third");
+		}
+		let names: Vec<_> = events().iter().map(|event| event.name).collect();
+		assert_eq!(names, ["outer", "third"]);
+
+		clear();
+		assert!(events().is_empty());
+		record(ProfileEvent {
+			name: "a \"quoted\" name",
+			module: "app",
+			start: Instant::now(),
+			duration: Duration::from_nanos(1500),
+			thread: 1,
+		});
+
+		let mut json = String::new();
+		write_chrome_trace(&mut json).unwrap();
+		assert_eq!(
+			json,
+			"{\"traceEvents\":[\n{\"name\":\"a \\\"quoted\\\" name\",\"cat\":\"app\",\"ph\":\"X\",\"ts\":0.000,\"dur\":1.500,\"pid\":0,\"tid\":1}\n]}\n"
+		);
+
+		set_capacity(DEFAULT_CAPACITY);
+	}
+}
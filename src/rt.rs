@@ -13,30 +13,46 @@
 //!
 //! The runtime currently offers the following features:
 //! 1. Basic information about the platform the program is running on, such as
-//!    the operating system name; see [`CURRENT_OS`] and [`RUNTIME_INFO`].
+//!    the operating system name; see [`CURRENT_OS`] and [`proc::args`].
 //! 2. Features Crux was compiled with; see [`SAFETY_CHECKS_ENABLED`] and
 //!    [`LOGGING_ENABLED`].
-//! 3. Allocation APIs; see [`RuntimeInfo::page_size`] and
-//!    [`GLOBAL_OS_ALLOCATOR`].
+//! 3. Allocation APIs; see [`page_size`], [`GLOBAL_OS_ALLOCATOR`], and
+//!    [`GLOBAL_ARENA`].
 //! 4. Global program logging; see [`LOGGER`].
+//! 5. Custom code that runs when the program panics or runs out of memory; see
+//!    [`panic::set_hook`] and [`set_alloc_error_hook`].
 
+pub mod backtrace;
+#[cfg(feature = "logging")]
+pub mod crash_handler;
 pub mod entrypoint;
+#[cfg(unix)]
+pub mod fs;
+#[cfg(windows)]
+pub mod fs {
+	//! Items for working with the filesystem.
+	//!
+	//! Only [`watch`] is available on Windows for now.
+
+	pub mod watch;
+}
 pub mod hook;
+pub mod image;
 pub mod mem;
 pub mod os;
+pub mod panic;
 pub mod proc;
+pub mod test_harness;
+pub mod time;
 
-#[cfg(target_os = "windows")]
-use crate::mem::NonNull;
 use crate::{
+	concurrency::AtomicCell,
 	ffi::c_void,
-	lang::{
-		self, Layout, MaybeUninit, cfg,
-		mem::{addr_of, addr_of_mut},
-		panic,
-	},
+	lang::{self, cfg, mem::addr_of},
 	logging::{Log, SyncLogger},
 };
+#[cfg(not(feature = "std-compat"))]
+use core::prelude::rust_2024::alloc_error_handler;
 
 #[cfg(all(test, feature = "test-harness"))]
 pub use test_harness::*;
@@ -81,35 +97,40 @@ pub const LOGGING_ENABLED: bool = cfg!(logging);
 //
 //
 
-/// Information Crux needs that has to be loaded at runtime. This is stored
-/// globally in [`RUNTIME_INFO`] and is loaded by [`startup_hook`].
-pub struct RuntimeInfo {
-	/// The size of a page of memory on the current machine.
-	pub page_size: usize,
-	/// The raw CLI args passed to the program at startup.
-	pub cli_args_raw: &'static [&'static [u8]],
-	/// The CLI args passed to the program at startup, lossily converted to
-	/// UTF-8.
-	pub cli_args: &'static [&'static str],
-}
-
-/// Global instance of [`RuntimeInfo`]. Loaded by [`startup_hook`]. Accessible
-/// by [`info`].
-pub static mut RUNTIME_INFO: MaybeUninit<RuntimeInfo> = MaybeUninit::uninit();
-
-/// Gets the global [`RuntimeInfo`] instance.
-///
-/// This function will cause UB if [`startup_hook`] was not called at the
-/// program's start. It is assumed that [`startup_hook`] will always be called
-/// at the program's start.
-pub fn info() -> &'static RuntimeInfo {
-	unsafe { (&*addr_of!(RUNTIME_INFO)).assume_init_ref() }
-}
-
 #[cfg(feature = "global-os-allocator")]
 #[mem::global_allocator]
 pub static GLOBAL_OS_ALLOCATOR: mem::OsAllocator = mem::OsAllocator;
 
+/// An arena that lives for the whole program, for values that are never freed
+/// and shouldn't have to be passed around. Values allocated in it borrow it for
+/// `'static`:
+///
+/// ```rs
+/// let name: &'static str = global_arena().alloc_str(&config.name);
+/// let greeting = format_global!("Hello, {}!", name);
+/// ```
+///
+/// The arena reserves [`GLOBAL_ARENA_SIZE`] of virtual memory the first time
+/// it's used, so programs that never use it don't pay for it. Requires the
+/// `global-arena` crate feature.
+#[cfg(feature = "global-arena")]
+pub static GLOBAL_ARENA: mem::StaticArena = mem::StaticArena::new(GLOBAL_ARENA_SIZE);
+/// How much virtual memory [`GLOBAL_ARENA`] reserves. This is 1 GiB unless
+/// `CRUX_GLOBAL_ARENA_SIZE` is set to a number of bytes when building.
+#[cfg(feature = "global-arena")]
+pub const GLOBAL_ARENA_SIZE: mem::MemoryAmount = match core::option_env!("CRUX_GLOBAL_ARENA_SIZE") {
+	Some(size) => match usize::from_str_radix(size, 10) {
+		Ok(size) => mem::MemoryAmount::bytes(size).align_to_assumed_page(),
+		Err(_) => panic!("Crux: CRUX_GLOBAL_ARENA_SIZE must be a number of bytes"),
+	},
+	None => mem::MemoryAmount::gibibytes(1),
+};
+/// Gets [`GLOBAL_ARENA`].
+#[cfg(feature = "global-arena")]
+pub fn global_arena() -> &'static mem::StaticArena {
+	&GLOBAL_ARENA
+}
+
 #[cfg(all(feature = "logging-panic-handler", feature = "std-compat"))]
 compile_error!(
 	"Crux: You can't enable the crate feature `logging-panic-handler` and the crate feature `std-compat`. `std` brings its own panic handler, and the logging panic handler would conflict with that."
@@ -119,6 +140,11 @@ compile_error!(
 	panic_handler
 )]
 pub fn logging_panic_handler(info: &crate::lang::panic::PanicInfo) -> ! {
+	panic::call_hook(info);
+
+	#[cfg(feature = "backtrace")]
+	crate::logging::fatal!("{}\n{}", info, backtrace::Backtrace::capture());
+	#[cfg(not(feature = "backtrace"))]
 	crate::logging::fatal!("{}", info);
 
 	#[cfg(supported_os)]
@@ -131,6 +157,35 @@ pub fn logging_panic_handler(info: &crate::lang::panic::PanicInfo) -> ! {
 	}
 }
 
+/// Called when an allocation fails. Calls the hook registered with
+/// [`set_alloc_error_hook`], logs the layout that couldn't be allocated as a
+/// fatal log, then exits the process (on supported OSes), or spin-loops (on
+/// unsupported OSes).
+///
+/// When the `std-compat` feature is enabled, `std` provides the alloc error
+/// handler instead, and this function is never called.
+#[cfg_attr(not(feature = "std-compat"), alloc_error_handler)]
+pub fn alloc_error_handler(layout: lang::mem::Layout) -> ! {
+	// If an allocation failed in the hook or while logging, skip straight to
+	// exiting
+	if mem::call_alloc_error_hook(layout) {
+		crate::logging::fatal!(
+			"Crux: Failed to allocate {} bytes (aligned to {} bytes)",
+			layout.size(),
+			layout.align()
+		);
+	}
+
+	#[cfg(supported_os)]
+	{
+		crate::rt::proc::exit_with_code(101)
+	}
+	#[cfg(not(supported_os))]
+	{
+		loop {}
+	}
+}
+
 //
 //
 // Logging runtime
@@ -142,27 +197,18 @@ pub fn logging_panic_handler(info: &crate::lang::panic::PanicInfo) -> ! {
 ///
 /// [`log`]: crate::logging::log
 /// [`fatal`]: crate::logging::fatal
-pub static mut LOGGER: &'static dyn SyncLogger = &crate::logging::StdoutLogger::default();
+pub static LOGGER: AtomicCell<&'static dyn SyncLogger> =
+	AtomicCell::new(&crate::logging::StdoutLogger::default());
 /// Sends a log to the global [`LOGGER`] instance.
 pub fn emit_log(log: Log) {
-	unsafe { &*addr_of_mut!(LOGGER) }.log(log);
+	LOGGER.load().log(log);
 }
 /// Sets the global [`LOGGER`] instance.
 ///
-///
-/// # Safety
-///
-/// Calling this function updates a global static variable and can therefore
-/// lead to race conditions in concurrent code. The caller is responsible for
-/// ensuring [`LOGGER`] is not being used by any other code when they call this
-/// function.
-///
-/// The simplest way to use this function safely is to call it one time at
-/// startup, and never again.
-pub unsafe fn set_logger(mut logger: &'static dyn SyncLogger) {
-	let global_logger = unsafe { &mut *addr_of_mut!(LOGGER) };
-	lang::mem::swap(&mut logger, global_logger);
-	unsafe { lang::mem::drop_in_place(&mut logger) };
+/// This can be called at any time, from any thread. Logs emitted while the
+/// logger is being replaced go to either the old or the new logger.
+pub fn set_logger(logger: &'static dyn SyncLogger) {
+	LOGGER.store(logger);
 }
 
 //
@@ -174,16 +220,30 @@ pub unsafe fn set_logger(mut logger: &'static dyn SyncLogger) {
 event! {
 	/// An event Crux calls after the binary has been loaded in-memory.
 	///
-	/// Crux defines two hooks for this event:
-	/// - [`rt_startup`]: Loads the Crux runtime.
+	/// Crux defines these hooks for this event:
+	/// - [`load_page_size`]: Loads the page size, and checks it against
+	///   [`ASSUMED_PAGE_SIZE`]. Only used if the `probe-page-size` crate
+	///   feature is enabled. Runs in the [`Early`](hook::Phase::Early) phase.
+	/// - [`load_args`]: Stores the program's CLI arguments for [`proc::args`].
+	///   Only used if the `cli-args` crate feature is enabled. Runs in the
+	///   [`Early`](hook::Phase::Early) phase.
+	/// - [`startup_hook`]: Runs after every other hook that loads part of the
+	///   runtime. Runs in the [`Early`](hook::Phase::Early) phase.
 	/// - [`call_main`]: Calls the `crux_main` function. Only used if the `main`
-	///   crate feature is enabled.
+	///   crate feature is enabled. Runs in the [`Late`](hook::Phase::Late)
+	///   phase.
+	///
+	/// The global [`LOGGER`] is set when the program is compiled, so logging
+	/// doesn't need a startup hook.
+	///
+	/// [`load_page_size`]: mem::load_page_size
+	/// [`load_args`]: proc::load_args
 	startup,
 	fn(StartupHookInfo)
 }
 
-/// Information that needs to be passed to [`startup_hook`]. Note that this
-/// struct's fields are platform-specific, since different platforms need
+/// Information that's passed to hooks for the [`startup`] event. Note that
+/// this struct's fields are platform-specific, since different platforms need
 /// different data at startup.
 #[derive(Clone, Copy)]
 pub struct StartupHookInfo {
@@ -194,103 +254,44 @@ pub struct StartupHookInfo {
 	pub args: &'static [*const u8],
 }
 
-/// A function that must be called at startup by all binaries using Crux. Don't
-/// call it yourself unless you know what you're doing.
-///
-/// Currently, this function just loads the [`RUNTIME_INFO`] global.
-///
+/// Marks the point in the [`startup`] event where the Crux runtime is loaded.
+/// It doesn't do anything itself: every other Crux hook that loads part of
+/// the runtime runs before it, so hooks that use the runtime should run
+/// `after` it.
 ///
-/// # Safety
-///
-/// This function should only be called one time, at program start, and never
-/// again. It's not marked as unsafe so it can conform to the function signature
-/// expected by the startup event.
-///
-/// This function updates global static data and can therefore cause race
-/// conditions in concurrent code.
-///
-/// Many Crux APIs assume this function has been called already when they run,
-/// because it loads important OS information used by those APIs. Using Crux
-/// APIs before this hook has run may lead to UB.
-pub fn startup_hook(info: StartupHookInfo) {
-	fn args_to_utf8(raw: &'static [&'static [u8]]) -> &'static [&'static str] {
-		let str_len = raw.iter().map(|buf| buf.len()).sum();
-		let string: ArenaString<usize> = ArenaString::new_preallocate(
-			MemoryAmount::bytes(str_len),
-			MemoryAmount::bytes(str_len),
-		)
-		.unwrap(); // TODO how to handle possible panics during startup?
-		let mut out = Vec::with_capacity(Layout::array::<&'static str>(raw.len()).unwrap().size());
-
-		let mut next_base = 0;
-		for buf in raw {
-			for chunk in buf.utf8_chunks() {
-				string.push_str(chunk.valid());
-				if !chunk.invalid().is_empty() {
-					string.push_char(char::REPLACEMENT_CHARACTER);
-				}
-
-				// Safety: string never moves in memory and we leak it at the
-				// end of this function. References to it can be static.
-				out.push(unsafe { &*(&string[next_base..] as *const str) });
-				next_base = string.len();
-			}
-		}
-
-		lang::forget(string);
-		out.leak()
-	}
-
-	let runtime_info = {
-		#[cfg(target_family = "unix")]
-		{
-			let page_size = os::unix::sysconf(libc::_SC_PAGE_SIZE) as usize;
-
-			let mut buf = Vec::with_capacity(
-				Layout::array::<&'static [u8]>(info.args.len())
-					.unwrap()
-					.size(),
-			);
-
-			for arg in info.args {
-				let Some(ptr) = NonNullConst::new(*arg) else {
-					continue;
-				};
-				let slice = unsafe { crate::ffi::null_terminated_pointer_to_slice::<false>(ptr) };
-				buf.push(slice);
-			}
-			let buf: &'static [&'static [u8]] = buf.leak();
-
-			RuntimeInfo {
-				page_size,
-				cli_args_raw: buf,
-				cli_args: args_to_utf8(buf),
-			}
-		}
-		#[cfg(target_os = "windows")]
-		{
-			let mut sysinfo = MaybeUninit::uninit();
-			unsafe { os::win32::GetSystemInfo(NonNull::new_unchecked(sysinfo.as_mut_ptr())) };
-			let sysinfo = unsafe { sysinfo.assume_init() };
-
-			RuntimeInfo {
-				page_size: sysinfo.page_size as usize,
-			}
-		}
-		#[cfg(not(supported_os))]
-		compile_error!("unimplemented on this operating system");
-	};
-
-	let global = unsafe { &mut *addr_of_mut!(RUNTIME_INFO) };
-	global.write(runtime_info);
-}
+/// Each part of the runtime is loaded by its own hook, and can be turned off
+/// with a crate feature to make startup cheaper:
+/// - `probe-page-size`: Without it, the page size is loaded the first time
+///   [`page_size`] is called.
+/// - `cli-args`: Without it, [`proc::args`] and friends are always empty.
+pub fn startup_hook(_: StartupHookInfo) {}
 hook::hook! {
 	/// See [`crate::rt::startup_hook`].
 	event: crate::events::startup,
 	func: startup_hook,
+	phase: Early,
 	constraints: []
 }
 
+//
+//
+// Shutdown hook
+//
+//
+
+event! {
+	/// An event Crux calls after `crux_main` returns, right before the program
+	/// exits. It isn't called if the program exits early, e.g. from
+	/// [`proc::exit`] or a panic.
+	///
+	/// Crux defines one hook for this event:
+	/// - [`free_cli_args`]: Frees the arguments cached by [`proc::cli_args`]
+	///   and [`proc::cli_args_raw`]. Runs in the [`Late`](hook::Phase::Late)
+	///   phase, so other hooks can still read the arguments.
+	shutdown,
+	fn()
+}
+
 //
 //
 // Information stored in the binary
@@ -302,32 +303,42 @@ hook::hook! {
 #[repr(u8)]
 pub enum CrateType {
 	/// The crate is a benchmark that Cargo will run.
-	Benchmark = 0,
+	Benchmark = 1,
 	/// The crate is being compiled as an executable the user can run.
-	Binary = 1,
+	Binary = 2,
 	/// The crate is being compiled as a dynamic library (`cdylib`) that other
 	/// executables can load at runtime.
-	Cdylib = 2,
+	Cdylib = 3,
 	/// The crate is an example in a library crate.
-	Example = 3,
+	Example = 4,
 	/// The crate is a set of unit or integration tests.
-	Test = 4,
+	Test = 5,
 }
 
 // Variables defined in the linker script set by `crux-build`
 // Note that you can't get the value of these by reading the static (hence why
-// their types are all set to `c_void`). You instead read the value by calling
+// their types are set to `c_void`). You instead read the value by calling
 // `addr_of!(static)`... which will then return a pointer that you can cast to
 // a number to get the value.
 //
-// For example, `__crux_crate_type` is set to a number between 0 and 4 in the
+// For example, `__crux_crate_type` is set to a number between 1 and 5 in the
 // linker scripts. However, reading `__crux_crate_type` is (afaik) straight up
-// UB. Instead, you call `addr_of!(__crux_crate_type)` and cast the resulting
-// pointer to a u8, which will then have the number between 0 and 4.
+// UB. Instead, you get its address and cast the resulting pointer to a u8,
+// which will then have the number between 1 and 5. It's weakly linked, so Rust
+// gets its address for us.
 unsafe extern "C" {
+	// Mach-O doesn't have linker scripts, but ld64 defines symbols for the
+	// start and end of every section
+	#[cfg(not(windows))]
+	#[cfg_attr(macos, link_name = "\x01section$start$__DATA$__crux_ini")]
 	static __crux_ini_start: c_void;
+	#[cfg(not(windows))]
+	#[cfg_attr(macos, link_name = "\x01section$end$__DATA$__crux_ini")]
 	static __crux_ini_end: c_void;
-	static __crux_crate_type: c_void;
+	// Crates without a crate type script still link; the static's value is
+	// the symbol's address, or null if it isn't defined
+	#[linkage = "extern_weak"]
+	static __crux_crate_type: *const c_void;
 }
 
 /// Returns function pointers for all functions that have been registered as ini
@@ -343,31 +354,76 @@ unsafe extern "C" {
 /// runs before the `main` function, but after the Crux runtime has loaded, you
 /// should look at the [`startup` event] instead.
 ///
+/// Where ini functions are stored depends on the file format:
+/// - ELF: the `.crux.ini` section, which Crux's linker scripts mark the start
+///   and end of.
+/// - Mach-O: the `__DATA,__crux_ini` section.
+/// - PE: the `.CRT$XCU` section, which is where MSVC stores C++ static
+///   constructors. The C runtime calls every function in it before `main`, so
+///   Crux doesn't have to; this returns nothing on Windows.
+///
+/// Null pointers in the section are skipped, since linkers may pad it with
+/// zeroes.
+///
 /// [`startup` event]: crate::events::startup
-pub fn ini_functions() -> &'static [unsafe fn()] {
-	let ini_start = addr_of!(__crux_ini_start) as usize;
-	let ini_end = addr_of!(__crux_ini_end) as usize;
-	let size = ini_end - ini_start;
-	let len = size / (usize::BITS as usize / 8);
-	unsafe { &*lang::slice_from_raw_parts(addr_of!(__crux_ini_start) as *const unsafe fn(), len) }
+pub fn ini_functions() -> impl Iterator<Item = unsafe fn()> {
+	#[cfg(not(windows))]
+	let functions = {
+		let ini_start = addr_of!(__crux_ini_start) as usize;
+		let ini_end = addr_of!(__crux_ini_end) as usize;
+		let len = (ini_end - ini_start) / lang::size_of::<Option<unsafe fn()>>();
+		unsafe {
+			&*lang::slice_from_raw_parts(
+				addr_of!(__crux_ini_start).cast::<Option<unsafe fn()>>(),
+				len,
+			)
+		}
+	};
+	#[cfg(windows)]
+	let functions: &[Option<unsafe fn()>] = &[];
+
+	functions.iter().flatten().copied()
 }
 /// Returns the [`CrateType`] of the final compiled app Crux is being used in.
+///
+/// Cargo can't give a library's unit tests their own linker arguments (see
+/// <https://github.com/rust-lang/cargo/issues/10937>), so they're the only
+/// crates that don't get a crate type script. Crates without one are treated
+/// as tests.
 pub fn crate_type() -> CrateType {
-	let val = addr_of!(__crux_crate_type) as usize as u8;
-	unsafe { lang::transmute(val) }
+	match unsafe { __crux_crate_type } as usize as u8 {
+		0 => CrateType::Test,
+		val => unsafe { lang::transmute::<u8, CrateType>(val) },
+	}
 }
 
 /// Register a function as an ini function.
 ///
 /// Usage: `register_ini_function!(function_name);`
 ///
-/// For more information about ini functions, see [`ini_functions`].
+/// This can be used any number of times in the same module. For more
+/// information about ini functions, see [`ini_functions`].
 #[macro_export]
 macro_rules! register_ini_function {
-	($func:ident) => {
-		#[unsafe(link_section = ".crux.ini")]
-		#[used]
-		static INI_FUNC: unsafe fn() = $func;
+	($func:path) => {
+		const _: () = {
+			#[cfg(not(windows))]
+			#[cfg_attr(not(target_os = "macos"), unsafe(link_section = ".crux.ini"))]
+			#[cfg_attr(target_os = "macos", unsafe(link_section = "__DATA,__crux_ini"))]
+			#[used]
+			static INI_FUNC: unsafe fn() = $func;
+
+			// The C runtime calls these, so they have to use the C ABI
+			#[cfg(windows)]
+			#[unsafe(link_section = ".CRT$XCU")]
+			#[used]
+			static INI_FUNC: unsafe extern "C" fn() = {
+				unsafe extern "C" fn ini_func() {
+					unsafe { $func() }
+				}
+				ini_func
+			};
+		};
 	};
 }
 pub use crate::register_ini_function;
@@ -378,76 +434,40 @@ pub use crate::register_ini_function;
 //
 //
 
+/// Declares a static that's initialized the first time it's accessed.
+///
+/// ```rs
+/// lazy_static! {
+///     /// The first 100 square numbers.
+///     pub static SQUARES: Vec<u32>;
+///     fn load() -> Vec<u32> {
+///         (0..100).map(|num| num * num).collect()
+///     }
+/// }
+/// ```
+///
+/// This expands to a [`LazyLock`], so the static is initialized safely even if
+/// several threads access it at once, and can be used at any point in the
+/// program - including before `main` runs.
+///
+/// [`LazyLock`]: crate::concurrency::LazyLock
+#[cfg(feature = "concurrency")]
 #[macro_export]
 macro_rules! lazy_static {
 	(
-		$(#[doc = $doc:literal])*
-		$(pub)? static $name:ident: $ty:ty;
+		$(#[$attr:meta])*
+		$vis:vis static $name:ident: $ty:ty;
 		fn load() -> $ty2:ty {
 			$($body:tt)*
 		}
 	) => {
-		mod $name {
-			use super::*;
-
-			fn load() {
-				fn inner() -> $ty2 {
-					$($body)*
-				}
-
-				unsafe { *$crate::lang::addr_of_mut!($name) = inner() };
-			}
-
-			$crate::rt::hook::hook! {
-				event: $crate::events::startup,
-				func: load,
-				constraints: [
-					After($crate::hooks::startup_hook),
-					Before($crate::hooks::call_main)
-				]
-			}
-		}
-		$(#[doc = $doc:literal])*
-		$(pub)? static mut $name: $ty = unsafe { $crate::lang::MaybeUninit::uninit().assume_init() };
+		$(#[$attr])*
+		$vis static $name: $crate::concurrency::LazyLock<$ty> =
+			$crate::concurrency::LazyLock::new(|| -> $ty2 { $($body)* });
 	};
 }
-
-//
-//
-// Test harness
-//
-//
-
-/// Provides a harness for running functions decorated with `#[test]` via
-/// `cargo test`.
-pub mod test_harness {
-	crate::rt::event! {
-		/// This event is used by the Crux test harness. All tests that should be
-		/// run should register with this event.
-		///
-		/// For convenience, Crux exposes a [`#[test]`] attribute macro. Putting
-		/// that on any function will register it with this event.
-		///
-		/// [`#[test]`]: crux_macros::test
-		run_tests, fn()
-	}
-
-	/// Runs all tests registered in this Crux binary.
-	pub fn run_all_tests() {
-		let event = unsafe { run_tests::EVENT.solve() }.expect(
-			"Crux CRITICAL ERROR: Failed to solve `run_tests` event, cannot run unit tests",
-		);
-		for hook in event.as_slice() {
-			hook()
-		}
-	}
-
-	#[cfg(all(feature = "test-harness", test))]
-	#[unsafe(no_mangle)]
-	fn crux_main() {
-		run_all_tests();
-	}
-}
+#[cfg(feature = "concurrency")]
+pub use crate::lazy_static;
 
 //
 //
@@ -456,16 +476,72 @@ pub mod test_harness {
 //
 
 /// Code for loading dynamic libraries.
+///
+/// ```rs
+/// let library = DynamicLibrary::open("./libplugin.so")?;
+/// let add: extern "C" fn(u32, u32) -> u32 = unsafe { library.get(c"add") }.unwrap();
+/// assert_eq!(add(1, 2), 3);
+/// ```
+///
+/// Dynamic libraries built with Crux run their [`startup`] event when they're
+/// loaded, so a Crux binary can load Crux libraries as plugins. Plugins can
+/// then participate in the host's events by exporting a function that the
+/// host looks up with [`DynamicLibrary::get`], and registering hooks with
+/// [`Event::register`].
+///
+/// [`startup`]: crate::events::startup
+/// [`Event::register`]: crate::rt::hook::Event::register
 pub mod dl {
 	use crate::{
 		ffi::{CStr, c_void},
+		lang::{Cow, size_of, transmute_copy},
 		rt::os,
+		text::{CString, Display},
 	};
 
+	/// A dynamic library loaded into the current process. The library is
+	/// unloaded when this is dropped.
 	pub struct DynamicLibrary {
 		raw_handle: usize,
 	}
 	impl DynamicLibrary {
+		/// Loads the dynamic library at `path`, with `dlopen` on Unix and
+		/// `LoadLibraryW` on Windows.
+		///
+		/// Loading a library runs its initialisers (including its Crux startup
+		/// event, if it was built with Crux), so only load libraries you
+		/// trust.
+		pub fn open(path: &str) -> Result<Self, DlError> {
+			#[cfg(unix)]
+			{
+				let path =
+					CString::new(path).map_err(|_| DlError::new("path contains a nul byte"))?;
+				let handle = unsafe {
+					os::unix::dlopen(
+						NonNullConst::from_ref(path.as_c_str()).cast(),
+						libc::RTLD_NOW | libc::RTLD_LOCAL,
+					)
+				};
+				match handle {
+					Some(handle) => Ok(Self {
+						raw_handle: handle.as_ptr() as usize,
+					}),
+					None => Err(DlError::last()),
+				}
+			}
+			#[cfg(windows)]
+			{
+				let path = crate::text::wide::encode_nul::<usize>(path)
+					.map_err(|_| DlError::new("path contains a nul byte"))?;
+				match unsafe { os::win32::LoadLibraryW(NonNullConst::from_ref(&path[0])) } {
+					Some(handle) => Ok(Self {
+						raw_handle: handle.as_ptr() as usize,
+					}),
+					None => Err(DlError::last()),
+				}
+			}
+		}
+
 		/// The raw operating system handle to the opened dynamic library.
 		pub fn raw_handle(&self) -> usize {
 			self.raw_handle
@@ -512,9 +588,34 @@ pub mod dl {
 					NonNullConst::new_unchecked(symbol.as_ptr().cast()),
 				)
 			}
+			#[cfg(windows)]
+			unsafe {
+				os::win32::GetProcAddress(
+					self.to_handle(),
+					NonNullConst::new_unchecked(symbol.as_ptr().cast()),
+				)
+			}
+		}
+		/// Gets a symbol from the library as a `T` - usually a function pointer
+		/// (e.g. `extern "C" fn(u32) -> u32`) or a pointer to a static.
+		///
+		///
+		/// # Safety
+		///
+		/// See [`DynamicLibrary::get_symbol_raw`]. Additionally, `T` must be
+		/// the correct type for the symbol, and the symbol must not be used
+		/// after the library is dropped.
+		pub unsafe fn get<T: Copy>(&self, symbol: &CStr) -> Option<T> {
+			const {
+				assert!(
+					size_of::<T>() == size_of::<*const c_void>(),
+					"symbols can only be loaded as pointer-sized types"
+				)
+			};
+			let ptr = unsafe { self.get_symbol_raw(symbol) }?;
+			Some(unsafe { transmute_copy(&ptr) })
 		}
 
-		#[cfg(unix)]
 		#[inline(always)]
 		fn to_handle(&self) -> NonNull<c_void> {
 			unsafe { NonNull::new_unchecked(self.raw_handle as *mut _) }
@@ -527,6 +628,53 @@ pub mod dl {
 			unsafe {
 				os::unix::dlclose(self.to_handle())
 			};
+			#[cfg(windows)]
+			unsafe {
+				os::win32::FreeLibrary(self.to_handle())
+			};
+		}
+	}
+
+	/// An error from loading a [`DynamicLibrary`].
+	#[derive(Clone, PartialEq, Eq, Debug)]
+	pub struct DlError {
+		message: Cow<'static, str>,
+	}
+	impl DlError {
+		fn new(message: &'static str) -> Self {
+			Self {
+				message: Cow::Borrowed(message),
+			}
+		}
+		/// The error from the most recent failed dynamic library operation.
+		fn last() -> Self {
+			#[cfg(unix)]
+			{
+				let message = match NonNullConst::new(unsafe { os::unix::dlerror() }) {
+					Some(message) => {
+						let message = unsafe { message.as_c_str() };
+						Cow::Owned(message.to_string_lossy().into_owned())
+					}
+					None => Cow::Borrowed("unknown error"),
+				};
+				Self { message }
+			}
+			#[cfg(windows)]
+			{
+				Self {
+					message: Cow::Owned(format!("{}", os::Error::last())),
+				}
+			}
+		}
+
+		/// A description of the error, as reported by the OS.
+		pub fn message(&self) -> &str {
+			&self.message
+		}
+	}
+	impl Display for DlError {
+		fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+			f.write_str(&self.message)
 		}
 	}
 }
@@ -0,0 +1,488 @@
+//! Captures the call stack of the current thread.
+//!
+//! On Unix, [`Backtrace::capture`] walks the chain of frame pointers, then
+//! looks up each return address with `dladdr`. This has a few consequences:
+//! - The program has to be compiled with frame pointers, or backtraces will be
+//!   cut short or contain garbage. Pass `-C force-frame-pointers=yes` to rustc
+//!   to enable them.
+//! - `dladdr` can only see symbols in the dynamic symbol table, so functions in
+//!   the executable itself are looked up in its full symbol table with
+//!   [`Image`](crate::rt::image::Image) instead. Stripped executables don't
+//!   have one, so their functions only get names if they're linked with
+//!   `-rdynamic`. Frames without a symbol are still printed with their address,
+//!   which can be resolved later with `addr2line`.
+//!
+//! On Windows, frames are captured with `RtlCaptureStackBackTrace`, then
+//! looked up with DbgHelp's `SymFromAddr`. DbgHelp only finds functions that
+//! aren't exported if the program's PDB file is next to it. DbgHelp copies
+//! symbol names out of the PDB, so each symbol that's resolved leaks its name
+//! and module path.
+//!
+//! If the `backtrace` crate feature is enabled, Crux's logging panic handler
+//! prints a backtrace when the program panics.
+
+use {
+	crate::{ffi::CStr, text::write},
+	core::fmt::{self, Display, Formatter, Write},
+};
+
+/// The maximum number of frames [`Backtrace::capture`] records.
+pub const MAX_FRAMES: usize = 128;
+
+/// A captured call stack. The first frame is the function that called
+/// [`Backtrace::capture`]; each following frame is the caller of the frame
+/// before it.
+#[derive(Clone, Debug)]
+pub struct Backtrace {
+	pub frames: Vec<Frame>,
+}
+impl Backtrace {
+	/// Captures the current thread's call stack and symbolizes it. See the
+	/// [module-level docs] for caveats.
+	///
+	/// [module-level docs]: crate::rt::backtrace
+	#[inline(never)]
+	pub fn capture() -> Self {
+		Self::from_addresses(capture_return_addresses())
+	}
+
+	/// Captures the call stack of a function that was interrupted at `ip`,
+	/// with its frame pointer set to `frame_pointer`. This is useful for
+	/// getting the backtrace of a thread that was interrupted by a signal,
+	/// since signal handlers run on a separate frame chain.
+	///
+	/// Only supported on Unix; returns an empty backtrace elsewhere.
+	///
+	///
+	/// # Safety
+	///
+	/// `frame_pointer` must be a valid frame pointer of the current thread.
+	pub unsafe fn from_frame_pointer(ip: usize, frame_pointer: usize) -> Self {
+		let mut addresses = Vec::from([ip]);
+		#[cfg(all(unix, any(target_arch = "x86_64", target_arch = "aarch64")))]
+		unsafe {
+			walk_frame_pointers(frame_pointer, &mut addresses)
+		}
+		#[cfg(not(all(unix, any(target_arch = "x86_64", target_arch = "aarch64"))))]
+		let _ = (frame_pointer, &mut addresses);
+
+		Self::from_addresses(addresses)
+	}
+
+	fn from_addresses(addresses: Vec<usize>) -> Self {
+		let mut frames = Vec::with_capacity(addresses.len());
+		for ip in addresses {
+			frames.push(Frame {
+				ip,
+				symbol: Symbol::resolve(ip),
+			});
+		}
+
+		Self { frames }
+	}
+}
+impl Display for Backtrace {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str("stack backtrace:")?;
+		for (idx, frame) in self.frames.iter().enumerate() {
+			write!(f, "\n{idx:>4}: {frame}")?;
+		}
+		Ok(())
+	}
+}
+
+/// One function call in a [`Backtrace`].
+#[derive(Clone, Debug)]
+pub struct Frame {
+	/// The instruction pointer of the frame; that is, the address execution
+	/// will return to in this function.
+	pub ip: usize,
+	/// The symbol containing [`Frame::ip`], if one could be found.
+	pub symbol: Option<Symbol>,
+}
+impl Display for Frame {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{:#018x}", self.ip)?;
+		if let Some(symbol) = &self.symbol {
+			write!(f, " - {symbol}")?;
+		}
+		Ok(())
+	}
+}
+
+/// The symbol an address belongs to.
+#[derive(Clone, Debug)]
+pub struct Symbol {
+	/// The (mangled) name of the symbol, if it has one.
+	pub name: Option<&'static CStr>,
+	/// How far into the symbol the address is.
+	pub offset: usize,
+	/// The path of the executable or library the symbol is in.
+	pub object: Option<&'static CStr>,
+}
+impl Symbol {
+	/// Finds the symbol containing the given address.
+	pub fn resolve(addr: usize) -> Option<Self> {
+		#[cfg(unix)]
+		{
+			use crate::rt::{image::Image, os::unix};
+
+			// Return addresses point to the instruction after the call, which
+			// may be in the next symbol
+			let addr = addr.checked_sub(1)?;
+			let ptr = NonNullConst::new(addr as *const _)?;
+			let mut info = unix::DlInfo {
+				dli_fname: None,
+				dli_fbase: None,
+				dli_sname: None,
+				dli_saddr: None,
+			};
+			if unsafe { unix::dladdr(ptr, NonNull::from(&mut info)) } == 0 {
+				return None;
+			}
+
+			let to_cstr = |ptr: Option<NonNull<crate::ffi::c_char>>| {
+				ptr.map(|ptr| unsafe { CStr::from_ptr(ptr.as_ptr()) })
+			};
+			let object = to_cstr(info.dli_fname);
+
+			// Functions that aren't exported are still in the executable's own
+			// symbol table
+			if info.dli_sname.is_none()
+				&& let Some(symbol) = Image::current()
+					.ok()
+					.and_then(|image| image.symbol_at(addr))
+			{
+				return Some(Self {
+					name: Some(symbol.name),
+					offset: addr + 1 - symbol.address,
+					object,
+				});
+			}
+
+			let start = info.dli_saddr.or(info.dli_fbase)?.as_ptr() as usize;
+			Some(Self {
+				name: to_cstr(info.dli_sname),
+				offset: addr + 1 - start,
+				object,
+			})
+		}
+		#[cfg(windows)]
+		{
+			use {
+				crate::{
+					ffi::{CString, c_char},
+					lang::size_of,
+					rt::os::win32,
+				},
+				core::sync::atomic::{AtomicBool, Ordering as AtomicOrdering},
+			};
+
+			/// DbgHelp isn't thread-safe, so this serialises calls to it.
+			static DBGHELP_LOCK: AtomicBool = AtomicBool::new(false);
+			/// Whether `SymInitialize` succeeded. Only accessed while holding
+			/// `DBGHELP_LOCK`.
+			static DBGHELP_INITIALISED: AtomicBool = AtomicBool::new(false);
+
+			/// A `SYMBOL_INFO`, followed by space for the rest of its name.
+			#[repr(C)]
+			struct SymbolBuffer {
+				info: win32::SymbolInfo,
+				name: [c_char; 255],
+			}
+
+			// Return addresses point to the instruction after the call, which
+			// may be in the next symbol
+			let addr = addr.checked_sub(1)?;
+			let process = win32::GetCurrentProcess();
+			let mut buffer: SymbolBuffer = unsafe { MaybeUninit::zeroed().assume_init() };
+			buffer.info.size_of_struct = size_of::<win32::SymbolInfo>() as u32;
+			buffer.info.max_name_len = buffer.name.len() as u32 + 1;
+			let mut displacement = 0;
+
+			while DBGHELP_LOCK
+				.compare_exchange_weak(
+					false,
+					true,
+					AtomicOrdering::Acquire,
+					AtomicOrdering::Relaxed,
+				)
+				.is_err()
+			{
+				core::hint::spin_loop();
+			}
+			if !DBGHELP_INITIALISED.load(AtomicOrdering::Relaxed) {
+				// Loads the symbols of every module in the process
+				let initialised =
+					unsafe { win32::SymInitialize(process, core::ptr::null(), 1) } != 0;
+				DBGHELP_INITIALISED.store(initialised, AtomicOrdering::Relaxed);
+			}
+			let found = DBGHELP_INITIALISED.load(AtomicOrdering::Relaxed)
+				&& unsafe {
+					win32::SymFromAddr(
+						process,
+						addr as u64,
+						Some(NonNull::from(&mut displacement)),
+						NonNull::from(&mut buffer.info),
+					)
+				} != 0;
+			DBGHELP_LOCK.store(false, AtomicOrdering::Release);
+			if !found {
+				return None;
+			}
+
+			// DbgHelp writes the name into the buffer, so it has to be copied
+			// somewhere that lives forever
+			let name = unsafe {
+				CStr::from_ptr(
+					(&raw const buffer)
+						.byte_add(core::mem::offset_of!(win32::SymbolInfo, name))
+						.cast(),
+				)
+			};
+			let name: &'static CStr = Box::leak(CString::from(name).into_boxed_c_str());
+
+			let mut path = [0u16; 260];
+			let path_len = unsafe {
+				win32::GetModuleFileNameW(
+					NonNull::new(buffer.info.mod_base as *mut _),
+					NonNull::from(&mut path).cast(),
+					path.len() as u32,
+				)
+			} as usize;
+			let object = (path_len != 0 && path_len < path.len())
+				.then(|| CString::new(crate::text::wide::decode_lossy(&path[..path_len])).ok())
+				.flatten()
+				.map(|object| &*Box::leak(object.into_boxed_c_str()));
+
+			Some(Self {
+				name: Some(name),
+				offset: displacement as usize + 1,
+				object,
+			})
+		}
+		#[cfg(not(any(unix, windows)))]
+		{
+			let _ = addr;
+			None
+		}
+	}
+
+	/// The demangled name of the symbol, if it has one.
+	pub fn demangled_name(&self) -> Option<Demangled<'_>> {
+		self.name
+			.map(|name| Demangled(name.to_str().unwrap_or("<non-UTF-8 symbol>")))
+	}
+}
+impl Display for Symbol {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self.demangled_name() {
+			Some(name) => write!(f, "{name} + {:#x}", self.offset)?,
+			None => write!(f, "<unknown> + {:#x}", self.offset)?,
+		}
+		if let Some(object) = self.object {
+			write!(f, " in {}", object.to_str().unwrap_or("<non-UTF-8 path>"))?;
+		}
+		Ok(())
+	}
+}
+
+/// A symbol name that's demangled when displayed. Rust's legacy mangling
+/// scheme (`_ZN...E`) is demangled; other names are displayed as-is.
+#[derive(Clone, Copy, Debug)]
+pub struct Demangled<'a>(pub &'a str);
+impl Display for Demangled<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let Some(mut rest) = self
+			.0
+			.strip_prefix("_ZN")
+			.or_else(|| self.0.strip_prefix("__ZN"))
+		else {
+			return f.write_str(self.0);
+		};
+
+		let mut segments = Vec::new();
+		while !rest.starts_with('E') {
+			let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+			let Ok(len) = rest[..digits].parse::<usize>() else {
+				return f.write_str(self.0);
+			};
+			let Some(segment) = rest.get(digits..digits + len) else {
+				return f.write_str(self.0);
+			};
+			segments.push(segment);
+			rest = &rest[digits + len..];
+		}
+
+		// The last segment is a hash, e.g. `h0123456789abcdef`
+		if segments
+			.last()
+			.is_some_and(|hash| hash.len() == 17 && hash.starts_with('h'))
+		{
+			segments.pop();
+		}
+
+		for (idx, segment) in segments.iter().enumerate() {
+			if idx > 0 {
+				f.write_str("::")?;
+			}
+			write_demangled_segment(f, segment)?;
+		}
+		Ok(())
+	}
+}
+
+/// Writes one segment of a legacy mangled symbol, replacing its escape
+/// sequences (e.g. `$LT$`) with the characters they represent.
+fn write_demangled_segment(f: &mut Formatter<'_>, segment: &str) -> fmt::Result {
+	// Segments starting with `$` are prefixed by `_`
+	let mut rest = if segment.starts_with("_$") {
+		&segment[1..]
+	} else {
+		segment
+	};
+
+	while !rest.is_empty() {
+		if let Some(after) = rest.strip_prefix("..") {
+			f.write_str("::")?;
+			rest = after;
+			continue;
+		}
+		if rest.starts_with('$')
+			&& let Some(end) = rest[1..].find('$')
+		{
+			let escape = &rest[1..end + 1];
+			let replacement = match escape {
+				"SP" => Some('@'),
+				"BP" => Some('*'),
+				"RF" => Some('&'),
+				"LT" => Some('<'),
+				"GT" => Some('>'),
+				"LP" => Some('('),
+				"RP" => Some(')'),
+				"C" => Some(','),
+				_ => escape
+					.strip_prefix('u')
+					.and_then(|hex| u32::from_str_radix(hex, 16).ok())
+					.and_then(char::from_u32),
+			};
+			if let Some(replacement) = replacement {
+				f.write_char(replacement)?;
+				rest = &rest[end + 2..];
+				continue;
+			}
+		}
+
+		let mut chars = rest.chars();
+		f.write_char(chars.next().unwrap())?;
+		rest = chars.as_str();
+	}
+
+	Ok(())
+}
+
+/// Returns the return address of every frame on the stack, starting with the
+/// caller of the function that called this function.
+#[inline(always)]
+fn capture_return_addresses() -> Vec<usize> {
+	let mut addresses = Vec::new();
+
+	#[cfg(all(unix, any(target_arch = "x86_64", target_arch = "aarch64")))]
+	{
+		let frame_pointer: usize;
+		unsafe {
+			#[cfg(target_arch = "x86_64")]
+			core::arch::asm!("mov {}, rbp", out(reg) frame_pointer, options(nomem, nostack));
+			#[cfg(target_arch = "aarch64")]
+			core::arch::asm!("mov {}, x29", out(reg) frame_pointer, options(nomem, nostack));
+
+			walk_frame_pointers(frame_pointer, &mut addresses);
+		}
+	}
+	#[cfg(windows)]
+	{
+		use crate::{lang::Extend, rt::os::win32};
+
+		let mut frames = [core::ptr::null_mut::<crate::ffi::c_void>(); MAX_FRAMES];
+		let captured = unsafe {
+			win32::RtlCaptureStackBackTrace(
+				1,
+				MAX_FRAMES as u32,
+				NonNull::from(&mut frames).cast(),
+				None,
+			)
+		};
+		addresses.extend(
+			frames[..captured as usize]
+				.iter()
+				.map(|addr| *addr as usize),
+		);
+	}
+
+	addresses
+}
+
+/// Follows the chain of frame pointers starting at `frame_pointer`, pushing
+/// each frame's return address to `addresses`.
+///
+///
+/// # Safety
+///
+/// `frame_pointer` must be a valid frame pointer of the current thread.
+#[cfg(all(unix, any(target_arch = "x86_64", target_arch = "aarch64")))]
+unsafe fn walk_frame_pointers(mut frame_pointer: usize, addresses: &mut Vec<usize>) {
+	use crate::lang::align_of;
+
+	// Each frame starts with the previous frame pointer, followed by the
+	// return address
+	while frame_pointer != 0
+		&& frame_pointer.is_multiple_of(align_of::<usize>())
+		&& addresses.len() < MAX_FRAMES
+	{
+		let frame = frame_pointer as *const usize;
+		let (next, return_address) = unsafe { (*frame, *frame.add(1)) };
+		if return_address == 0 {
+			break;
+		}
+		addresses.push(return_address);
+
+		// The stack grows downwards, so callers' frames are always at higher
+		// addresses; anything else means the chain is broken
+		if next <= frame_pointer {
+			break;
+		}
+		frame_pointer = next;
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn demangle() {
+		let cases = [
+			(
+				"_ZN4crux2rt9backtrace9Backtrace7capture17h0123456789abcdefE",
+				"crux::rt::backtrace::Backtrace::capture",
+			),
+			(
+				"_ZN65_$LT$crux..rt..backtrace..Frame$u20$as$u20$core..fmt..Display$GT$3fmt17h0123456789abcdefE",
+				"<crux::rt::backtrace::Frame as core::fmt::Display>::fmt",
+			),
+			("_ZN3foo3barE", "foo::bar"),
+			("main", "main"),
+			("_ZN3foo", "_ZN3foo"),
+		];
+
+		for (mangled, demangled) in cases {
+			assert_eq!(format!("{}", Demangled(mangled)), demangled);
+		}
+	}
+}
@@ -0,0 +1,209 @@
+//! Logs diagnostics when the program crashes from a hardware fault, such as a
+//! segmentation fault.
+//!
+//! The crash handler is opt-in: call [`install`], or enable the
+//! `crash-handler` crate feature to install it automatically at startup. Once
+//! installed, it catches `SIGSEGV`, `SIGBUS`, and `SIGILL` on Unix (and
+//! unhandled exceptions on Windows), then sends a fatal log with the fault
+//! address, the CPU's registers, and a [backtrace] through the global
+//! [`LOGGER`].
+//!
+//! After logging, the handler restores the default behaviour for the signal
+//! and lets the fault happen again, so the program still dies from the signal
+//! (and produces a core dump, if those are enabled).
+//!
+//! Signal handlers are only allowed to do very little, and formatting a log
+//! isn't on that list, so the crash handler is best-effort: if the program
+//! crashed because its heap is corrupted, for example, logging might crash,
+//! too.
+//!
+//! The handler runs on a separate stack, so stack overflows can be reported.
+//! However, alternate signal stacks are per-thread, and [`install`] only sets
+//! one up for the thread that calls it.
+//!
+//! [backtrace]: crate::rt::backtrace
+//! [`LOGGER`]: crate::rt::LOGGER
+
+use crate::rt::backtrace::Backtrace;
+#[cfg(unix)]
+use crate::{
+	ffi::{c_int, c_void},
+	lang::{MaybeUninit, mem::addr_of_mut},
+	rt::os::unix,
+	text::write,
+};
+
+/// The size of the stack the crash handler runs on.
+pub const CRASH_STACK_SIZE: usize = 64 * 1024;
+
+#[cfg(unix)]
+static mut CRASH_STACK: [u8; CRASH_STACK_SIZE] = [0; CRASH_STACK_SIZE];
+
+/// Installs the crash handler. See the [module-level docs] for more info.
+///
+/// [module-level docs]: crate::rt::crash_handler
+pub fn install() {
+	#[cfg(unix)]
+	{
+		let stack = libc::stack_t {
+			ss_sp: addr_of_mut!(CRASH_STACK).cast(),
+			ss_flags: 0,
+			ss_size: CRASH_STACK_SIZE,
+		};
+		let res = unsafe { unix::sigaltstack(NonNullConst::from_ref(&stack), None) };
+		assert_eq!(res, 0, "Crux: Failed to set up the crash handler's stack");
+
+		let mut action: libc::sigaction = unsafe { MaybeUninit::zeroed().assume_init() };
+		action.sa_sigaction = handle_signal as extern "C" fn(_, _, _) as usize;
+		action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK | libc::SA_RESETHAND;
+
+		for signal in [libc::SIGSEGV, libc::SIGBUS, libc::SIGILL] {
+			let res = unsafe { unix::sigaction(signal, NonNullConst::from_ref(&action), None) };
+			assert_eq!(res, 0, "Crux: Failed to install the crash handler");
+		}
+	}
+	#[cfg(windows)]
+	{
+		use crate::rt::os::win32;
+
+		unsafe { win32::SetUnhandledExceptionFilter(Some(handle_exception)) };
+	}
+}
+
+#[cfg(feature = "crash-handler")]
+fn install_hook(_: crate::rt::StartupHookInfo) {
+	install();
+}
+#[cfg(feature = "crash-handler")]
+crate::rt::hook::hook! {
+	/// Installs the crash handler at startup, if the crate feature
+	/// `crash-handler` is enabled.
+	event: crate::events::startup,
+	func: install_hook,
+	constraints: [
+		after(crate::hooks::startup_hook),
+		before(crate::hooks::call_main),
+	]
+}
+
+#[cfg(unix)]
+extern "C" fn handle_signal(
+	signal: c_int,
+	info: NonNull<libc::siginfo_t>,
+	context: NonNull<c_void>,
+) {
+	let name = match signal {
+		libc::SIGSEGV => "SIGSEGV (segmentation fault)",
+		libc::SIGBUS => "SIGBUS (bus error)",
+		libc::SIGILL => "SIGILL (illegal instruction)",
+		_ => "an unknown signal",
+	};
+	#[cfg(linux)]
+	let address = unsafe { info.as_ref().si_addr() } as usize;
+	#[cfg(not(linux))]
+	let address = unsafe { info.as_ref().si_addr } as usize;
+
+	let registers = unsafe { Registers::from_context(context) };
+	let backtrace = match registers.frame() {
+		Some((ip, frame_pointer)) => unsafe { Backtrace::from_frame_pointer(ip, frame_pointer) },
+		None => Backtrace::capture(),
+	};
+
+	crate::logging::fatal!(
+		"Crux: The program crashed with {} at address {:#x}\n{}\n{}",
+		name,
+		address,
+		registers,
+		backtrace
+	);
+	// Returning retries the faulting instruction, which now triggers the
+	// signal's default action because of `SA_RESETHAND`
+}
+
+/// The general-purpose registers of a thread interrupted by a signal.
+#[cfg(unix)]
+struct Registers(Vec<(&'static str, usize)>);
+#[cfg(unix)]
+impl Registers {
+	/// Reads registers from the `ucontext_t` passed to a signal handler.
+	///
+	///
+	/// # Safety
+	///
+	/// `context` must be the context passed to a signal handler.
+	#[allow(unused_variables)]
+	unsafe fn from_context(context: NonNull<c_void>) -> Self {
+		#[allow(unused_mut)]
+		let mut registers = Vec::new();
+
+		#[cfg(all(linux, target_arch = "x86_64"))]
+		{
+			const NAMES: [&str; 17] = [
+				"r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15", "rdi", "rsi", "rbp", "rbx",
+				"rdx", "rax", "rcx", "rsp", "rip",
+			];
+			let context = unsafe { context.cast::<libc::ucontext_t>().as_ref() };
+			for (idx, name) in NAMES.into_iter().enumerate() {
+				registers.push((name, context.uc_mcontext.gregs[idx] as usize));
+			}
+		}
+		#[cfg(all(linux, target_arch = "aarch64"))]
+		{
+			let context = unsafe { context.cast::<libc::ucontext_t>().as_ref() };
+			let mcontext = &context.uc_mcontext;
+			registers.extend([
+				("pc", mcontext.pc as usize),
+				("sp", mcontext.sp as usize),
+				("fp", mcontext.regs[29] as usize),
+				("lr", mcontext.regs[30] as usize),
+			]);
+		}
+
+		Self(registers)
+	}
+
+	fn get(&self, name: &str) -> Option<usize> {
+		self.0
+			.iter()
+			.find(|(register, _)| *register == name)
+			.map(|(_, value)| *value)
+	}
+
+	/// The instruction pointer and frame pointer of the interrupted thread,
+	/// if they could be read.
+	fn frame(&self) -> Option<(usize, usize)> {
+		self.get("rip")
+			.zip(self.get("rbp"))
+			.or_else(|| self.get("pc").zip(self.get("fp")))
+	}
+}
+#[cfg(unix)]
+impl crate::text::Display for Registers {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		if self.0.is_empty() {
+			return f.write_str("registers: unavailable on this platform");
+		}
+
+		f.write_str("registers:")?;
+		for (idx, (name, value)) in self.0.iter().enumerate() {
+			let separator = if idx % 4 == 0 { "\n  " } else { "  " };
+			write!(f, "{separator}{name:>3}={value:#018x}")?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(windows)]
+extern "system" fn handle_exception(info: NonNull<crate::rt::os::win32::ExceptionPointers>) -> i32 {
+	let record = unsafe { info.as_ref().exception_record.as_ref() };
+
+	crate::logging::fatal!(
+		"Crux: The program crashed with exception {:#x} at address {:#x}\n{}",
+		record.exception_code,
+		record.exception_address as usize,
+		Backtrace::capture()
+	);
+
+	// EXCEPTION_CONTINUE_SEARCH: let Windows handle the exception as usual
+	0
+}
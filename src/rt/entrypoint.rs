@@ -7,9 +7,12 @@
 //! sections and symbols to execute code as soon as the library gets loaded in
 //! memory.
 
-use crate::{
-	ffi::*,
-	rt::{StartupHookInfo, hook::hook},
+use {
+	crate::{
+		ffi::*,
+		rt::{StartupHookInfo, hook::hook},
+	},
+	core::fmt::Debug,
 };
 
 //
@@ -43,11 +46,38 @@ hook! {
 	/// Otherwise does nothing.
 	event: crate::events::startup,
 	func: call_main,
+	phase: Late,
 	constraints: [
 		after(crate::hooks::startup_hook),
 	]
 }
 
+/// A type that can be returned from a [`#[crux::main]`] function.
+///
+/// `()` does nothing. Like `std`'s `main`, returning `Result<(), E>` prints
+/// the error's `Debug` output and exits with code 1 if it's `Err`.
+///
+/// [`#[crux::main]`]: crux_macros::main
+#[diagnostic::on_unimplemented(
+	message = "`#[crux::main]` functions must return `()` or `Result<(), E>`, not `{Self}`",
+	note = "the error type of a `Result` must implement `Debug`"
+)]
+pub trait MainResult {
+	/// Reports the main function's result, exiting the process if it failed.
+	fn report(self);
+}
+impl MainResult for () {
+	fn report(self) {}
+}
+impl<E: Debug> MainResult for Result<(), E> {
+	fn report(self) {
+		if let Err(error) = self {
+			println!("Error: {:?}", error);
+			crate::rt::proc::exit_with_code(1);
+		}
+	}
+}
+
 /// Entrypoint for binaries.
 #[cfg(unix)]
 #[unsafe(no_mangle)]
@@ -59,7 +89,7 @@ extern "C" fn __wrap_main(
 	use crate::{io::Writer, rt::os::unix::FileDescriptor};
 
 	let args = unsafe { &*crate::lang::slice_from_raw_parts(argv.cast(), argc as usize) };
-	match entrypoint(StartupHookInfo { args }) {
+	match entrypoint(StartupHookInfo { args }).and_then(|()| dispatch_shutdown()) {
 		Ok(()) => {}
 		Err(err) => {
 			println!("Crux CRITICAL ERROR: {}", err.error_msg());
@@ -72,12 +102,11 @@ extern "C" fn __wrap_main(
 	0
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(unix)]
 mod library_entrypoint {
 	use super::*;
 
-	/// Entrypoint for dynamic libraries compiled with Crux on Linux systems.
-	#[cfg(unix)]
+	/// Entrypoint for dynamic libraries compiled with Crux on Unix systems.
 	extern "C" fn on_library_load() {
 		use crate::rt::{self, CrateType};
 
@@ -95,14 +124,22 @@ mod library_entrypoint {
 	/// Puts a function pointer to the library entrypoint in the `.init_array`
 	/// ELF section. This causes Linux to call the function when the library is
 	/// loaded.
+	#[cfg(target_os = "linux")]
 	#[used]
 	#[unsafe(link_section = ".init_array")]
 	static ON_LIBRARY_LOAD: extern "C" fn() = on_library_load;
+	/// The Mach-O equivalent of `.init_array`, which macOS calls when the
+	/// library is loaded.
+	#[cfg(target_os = "macos")]
+	#[used]
+	#[unsafe(link_section = "__DATA,__mod_init_func")]
+	static ON_LIBRARY_LOAD: extern "C" fn() = on_library_load;
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CruxEntrypointError {
 	UnsolvableStartupEvent,
+	UnsolvableShutdownEvent,
 }
 impl CruxEntrypointError {
 	pub const fn error_msg(self) -> &'static str {
@@ -110,6 +147,9 @@ impl CruxEntrypointError {
 			Self::UnsolvableStartupEvent => {
 				"The startup event has hooks that conflict with each other, so Crux cannot start running the app."
 			}
+			Self::UnsolvableShutdownEvent => {
+				"The shutdown event has hooks that conflict with each other, so Crux cannot shut down the app cleanly."
+			}
 		}
 	}
 }
@@ -135,3 +175,12 @@ pub fn entrypoint(info: StartupHookInfo) -> Result<(), CruxEntrypointError> {
 
 	Ok(())
 }
+
+/// Called by binaries' entrypoint after [`entrypoint`] returns (and so after
+/// `crux_main` returns), to run the [`shutdown`] event.
+///
+/// [`shutdown`]: crate::events::shutdown
+pub fn dispatch_shutdown() -> Result<(), CruxEntrypointError> {
+	unsafe { crate::events::shutdown::EVENT.dispatch(|hook| hook()) }
+		.map_err(|_| CruxEntrypointError::UnsolvableShutdownEvent)
+}
@@ -0,0 +1,608 @@
+//! Items for working with the filesystem.
+//!
+//! Paths are passed as `&str`s, and are converted to C strings before they're
+//! handed to the OS. Paths containing a nul byte are rejected with
+//! [`os::ErrorKind::InvalidInput`].
+//!
+//! On Windows, only [`watch`] is available for now.
+
+use crate::{
+	crypto::fill_random,
+	ffi::c_int,
+	io::{Reader, Writer},
+	rt::{os, proc, time::SystemTime},
+	text::{CStr, CString},
+};
+
+pub mod watch;
+
+/// Information about a file, as returned by [`metadata`] and
+/// [`symlink_metadata`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Metadata {
+	len: u64,
+	file_type: FileType,
+	permissions: Permissions,
+	modified: SystemTime,
+	accessed: SystemTime,
+	created: Option<SystemTime>,
+}
+impl Metadata {
+	/// The size of the file, in bytes.
+	pub fn len(&self) -> u64 {
+		self.len
+	}
+	/// Whether the file is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	pub fn file_type(&self) -> FileType {
+		self.file_type
+	}
+	pub fn is_file(&self) -> bool {
+		self.file_type == FileType::File
+	}
+	pub fn is_dir(&self) -> bool {
+		self.file_type == FileType::Directory
+	}
+	pub fn is_symlink(&self) -> bool {
+		self.file_type == FileType::Symlink
+	}
+	pub fn permissions(&self) -> Permissions {
+		self.permissions
+	}
+	/// When the file's contents were last changed.
+	pub fn modified(&self) -> SystemTime {
+		self.modified
+	}
+	/// When the file was last read from. Many systems only update this
+	/// occasionally, or not at all, to save disk writes.
+	pub fn accessed(&self) -> SystemTime {
+		self.accessed
+	}
+	/// When the file was created. Not every platform or filesystem tracks
+	/// this, in which case this is `None`.
+	pub fn created(&self) -> Option<SystemTime> {
+		self.created
+	}
+
+	fn from_stat(stat: &libc::stat) -> Self {
+		let file_type = match stat.st_mode & libc::S_IFMT {
+			libc::S_IFREG => FileType::File,
+			libc::S_IFDIR => FileType::Directory,
+			libc::S_IFLNK => FileType::Symlink,
+			_ => FileType::Other,
+		};
+		#[cfg(linux)]
+		let created = None;
+		#[cfg(macos)]
+		let created = Some(SystemTime::from_unix(
+			stat.st_birthtime,
+			stat.st_birthtime_nsec as u32,
+		));
+
+		// `mode_t` is only 16 bits on macOS
+		#[allow(clippy::unnecessary_cast)]
+		let mode = stat.st_mode as u32;
+
+		Self {
+			len: stat.st_size as u64,
+			file_type,
+			permissions: Permissions::from_mode(mode),
+			modified: SystemTime::from_unix(stat.st_mtime, stat.st_mtime_nsec as u32),
+			accessed: SystemTime::from_unix(stat.st_atime, stat.st_atime_nsec as u32),
+			created,
+		}
+	}
+}
+
+/// What kind of file a [`Metadata`] describes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FileType {
+	File,
+	Directory,
+	/// A symbolic link. Only returned by [`symlink_metadata`], since
+	/// [`metadata`] follows symlinks.
+	Symlink,
+	/// Anything else, e.g. a device, socket, or named pipe.
+	Other,
+}
+
+/// A file's permissions.
+///
+/// These are the file's Unix mode bits (e.g. `0o644`). Use
+/// [`Permissions::mode`] to access them directly.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Permissions {
+	mode: u32,
+}
+impl Permissions {
+	pub const fn from_mode(mode: u32) -> Self {
+		Self {
+			mode: mode & 0o7777,
+		}
+	}
+	/// The file's permission bits, e.g. `0o644`.
+	pub const fn mode(&self) -> u32 {
+		self.mode
+	}
+
+	/// Whether nobody is allowed to write to the file.
+	pub const fn readonly(&self) -> bool {
+		self.mode & 0o222 == 0
+	}
+	/// Removes or restores write access for everyone who can read the file.
+	/// Call [`set_permissions`] to actually apply the change.
+	pub fn set_readonly(&mut self, readonly: bool) {
+		if readonly {
+			self.mode &= !0o222;
+		} else {
+			// Give write access to everyone with read access
+			self.mode |= (self.mode & 0o444) >> 1;
+		}
+	}
+}
+
+/// Returns information about the file at `path`. If `path` is a symlink, this
+/// returns information about the file it points to; see [`symlink_metadata`]
+/// for information about the symlink itself.
+pub fn metadata(path: &str) -> Result<Metadata, os::Error> {
+	let path = c_path(path)?;
+	let mut stat = MaybeUninit::uninit();
+	let res = unsafe {
+		os::unix::stat(
+			NonNullConst::from_ref(path.as_c_str()).cast(),
+			NonNull::new_unchecked(stat.as_mut_ptr()),
+		)
+	};
+	if res != 0 {
+		return Err(os::Error::last());
+	}
+
+	Ok(Metadata::from_stat(unsafe { stat.assume_init_ref() }))
+}
+
+/// Like [`metadata`], but doesn't follow symlinks: if `path` is a symlink, this
+/// returns information about the symlink itself.
+pub fn symlink_metadata(path: &str) -> Result<Metadata, os::Error> {
+	lstat(&c_path(path)?)
+}
+fn lstat(path: &CStr) -> Result<Metadata, os::Error> {
+	let mut stat = MaybeUninit::uninit();
+	let res = unsafe {
+		os::unix::lstat(
+			NonNullConst::from_ref(path).cast(),
+			NonNull::new_unchecked(stat.as_mut_ptr()),
+		)
+	};
+	if res != 0 {
+		return Err(os::Error::last());
+	}
+
+	Ok(Metadata::from_stat(unsafe { stat.assume_init_ref() }))
+}
+
+/// Returns the path the symlink at `path` points to. Errors if `path` isn't a
+/// symlink.
+///
+/// The target path isn't necessarily valid UTF-8, so it's returned as raw
+/// bytes.
+pub fn read_link(path: &str) -> Result<Vec<u8>, os::Error> {
+	let path = c_path(path)?;
+	let mut buf: Vec<u8> = Vec::with_capacity(256);
+	loop {
+		let res = unsafe {
+			os::unix::readlink(
+				NonNullConst::from_ref(path.as_c_str()).cast(),
+				NonNull::new_unchecked(buf.as_mut_ptr()).cast(),
+				buf.capacity(),
+			)
+		};
+		if res == -1 {
+			return Err(os::Error::last());
+		}
+
+		// `readlink` silently truncates the path if it doesn't fit, so
+		// only trust it if there was room to spare
+		let len = res as usize;
+		if len < buf.capacity() {
+			unsafe { buf.set_len(len) };
+			return Ok(buf);
+		}
+		buf.reserve(buf.capacity() * 2);
+	}
+}
+
+/// Changes the permissions of the file at `path`.
+///
+/// ```rs
+/// let mut permissions = fs::metadata("notes.txt")?.permissions();
+/// permissions.set_readonly(true);
+/// fs::set_permissions("notes.txt", permissions)?;
+/// ```
+pub fn set_permissions(path: &str, permissions: Permissions) -> Result<(), os::Error> {
+	let path = c_path(path)?;
+	let res = unsafe {
+		os::unix::chmod(
+			NonNullConst::from_ref(path.as_c_str()).cast(),
+			permissions.mode as libc::mode_t,
+		)
+	};
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+/// Creates a new, empty directory at `path`.
+pub fn create_dir(path: &str) -> Result<(), os::Error> {
+	let path = c_path(path)?;
+	let res = unsafe { os::unix::mkdir(NonNullConst::from_ref(path.as_c_str()).cast(), 0o777) };
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+/// Deletes the file at `path`.
+pub fn remove_file(path: &str) -> Result<(), os::Error> {
+	unlink(&c_path(path)?)
+}
+fn unlink(path: &CStr) -> Result<(), os::Error> {
+	let res = unsafe { os::unix::unlink(NonNullConst::from_ref(path).cast()) };
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+/// Deletes the empty directory at `path`. See [`remove_dir_all`] to delete a
+/// directory and everything in it.
+pub fn remove_dir(path: &str) -> Result<(), os::Error> {
+	rmdir(&c_path(path)?)
+}
+fn rmdir(path: &CStr) -> Result<(), os::Error> {
+	let res = unsafe { os::unix::rmdir(NonNullConst::from_ref(path).cast()) };
+	if res == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+/// Deletes the directory at `path`, and everything inside of it. Symlinks
+/// are deleted, not followed - including `path` itself, if it's a symlink.
+pub fn remove_dir_all(path: &str) -> Result<(), os::Error> {
+	let path = c_path(path)?;
+	if lstat(&path)?.is_dir() {
+		remove_dir_all_inner(&path)
+	} else {
+		unlink(&path)
+	}
+}
+/// Does the work for [`remove_dir_all`], once `path` is known to be a real
+/// directory. Entry names aren't necessarily UTF-8, so child paths are built
+/// from raw bytes.
+fn remove_dir_all_inner(path: &CStr) -> Result<(), os::Error> {
+	let dir = unsafe { os::unix::opendir(NonNullConst::from_ref(path).cast()) }
+		.ok_or_else(os::Error::last)?;
+
+	let mut result = Ok(());
+	while let Some(entry) = unsafe { os::unix::readdir(dir) } {
+		let name = unsafe { CStr::from_ptr(entry.as_ref().d_name.as_ptr()) }.to_bytes();
+		if name == b"." || name == b".." {
+			continue;
+		}
+
+		let mut child = path.to_bytes().to_vec();
+		child.push(b'/');
+		child.extend_from_slice(name);
+		// Neither the path nor the name can contain a nul byte
+		let child = unsafe { CString::from_vec_unchecked(child) };
+
+		let is_dir = match unsafe { entry.as_ref().d_type } {
+			libc::DT_DIR => true,
+			libc::DT_UNKNOWN => lstat(&child).is_ok_and(|meta| meta.is_dir()),
+			_ => false,
+		};
+		result = if is_dir {
+			remove_dir_all_inner(&child)
+		} else {
+			unlink(&child)
+		};
+		if result.is_err() {
+			break;
+		}
+	}
+	unsafe { os::unix::closedir(dir) };
+
+	result.and_then(|_| rmdir(path))
+}
+
+//
+//
+// Temporary files
+//
+//
+
+/// Returns the directory temporary files should be created in: the `TMPDIR`
+/// environment variable if it's set, or `/tmp` otherwise.
+pub fn temp_dir() -> String {
+	proc::get_env("TMPDIR")
+		.filter(|dir| !dir.is_empty())
+		.unwrap_or_else(|| String::from("/tmp"))
+}
+
+/// Generates a random, unpredictable path in [`temp_dir`].
+fn temp_path() -> Result<String, os::Error> {
+	const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+	let mut random = [0; 16];
+	fill_random(&mut random)?;
+
+	let mut path = temp_dir();
+	path.push_str("/crux-");
+	for byte in random {
+		path.push(ALPHABET[byte as usize % ALPHABET.len()] as char);
+	}
+
+	Ok(path)
+}
+
+/// Creates a new, empty file in [`temp_dir`] that only the current user can
+/// access. The file is deleted when the returned [`TempFile`] is dropped.
+///
+/// ```rs
+/// let mut file = fs::tempfile()?;
+/// file.write_all(b"scratch data")?;
+/// let path = file.path();
+/// ```
+pub fn tempfile() -> Result<TempFile, os::Error> {
+	loop {
+		use os::unix::OpenFlags;
+
+		const MODE: c_int = 0o600;
+
+		let path = temp_path()?;
+		let c_str = c_path(&path)?;
+		let fd = unsafe {
+			os::unix::open(
+				c_str.as_ptr(),
+				OpenFlags::RDWR | OpenFlags::CREAT | OpenFlags::EXCL | OpenFlags::CLOEXEC,
+				MODE,
+			)
+		};
+		if fd.as_raw() != -1 {
+			return Ok(TempFile {
+				fd,
+				path,
+				cleanup: true,
+			});
+		}
+
+		// On the off chance the random name is taken, just try another one
+		let err = os::Error::last();
+		if err.kind() != os::ErrorKind::AlreadyExists {
+			return Err(err);
+		}
+	}
+}
+
+/// A temporary file created with [`tempfile`]. It can be read from and written
+/// to, and is deleted when it's dropped (unless cleanup is disabled with
+/// [`TempFile::set_cleanup`]).
+#[derive(Debug)]
+pub struct TempFile {
+	fd: os::unix::FileDescriptor,
+	path: String,
+	cleanup: bool,
+}
+impl TempFile {
+	/// Where the file is.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	pub fn fd(&self) -> os::unix::FileDescriptor {
+		self.fd
+	}
+	/// Sets whether the file is deleted when this is dropped. Defaults to
+	/// `true`.
+	pub fn set_cleanup(&mut self, cleanup: bool) {
+		self.cleanup = cleanup;
+	}
+}
+impl Writer for TempFile {
+	type Error = os::Error;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		unsafe { os::unix::FileWriter::new(self.fd) }.write(bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		unsafe { os::unix::FileWriter::new(self.fd) }.flush()
+	}
+}
+impl Reader for TempFile {
+	type Error = os::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		unsafe { os::unix::FileReader::new(self.fd) }.read(buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		unsafe { os::unix::close(self.fd) };
+		if self.cleanup {
+			let _ = remove_file(&self.path);
+		}
+	}
+}
+
+/// A temporary directory in [`temp_dir`] that only the current user can
+/// access. The directory and everything in it is deleted when this is dropped
+/// (unless cleanup is disabled with [`TempDir::set_cleanup`]).
+///
+/// ```rs
+/// let dir = TempDir::new()?;
+/// fs::create_dir(&dir.join("build"))?;
+/// ```
+#[derive(Debug)]
+pub struct TempDir {
+	path: String,
+	cleanup: bool,
+}
+impl TempDir {
+	pub fn new() -> Result<Self, os::Error> {
+		loop {
+			let path = temp_path()?;
+			let c_str = c_path(&path)?;
+			let res =
+				unsafe { os::unix::mkdir(NonNullConst::from_ref(c_str.as_c_str()).cast(), 0o700) };
+			if res == 0 {
+				return Ok(Self {
+					path,
+					cleanup: true,
+				});
+			}
+
+			// On the off chance the random name is taken, just try another one
+			let err = os::Error::last();
+			if err.kind() != os::ErrorKind::AlreadyExists {
+				return Err(err);
+			}
+		}
+	}
+
+	/// Where the directory is.
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+	/// Returns the path of `name` inside this directory.
+	pub fn join(&self, name: &str) -> String {
+		format!("{}/{name}", self.path)
+	}
+	/// Sets whether the directory and its contents are deleted when this is
+	/// dropped. Defaults to `true`.
+	pub fn set_cleanup(&mut self, cleanup: bool) {
+		self.cleanup = cleanup;
+	}
+}
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		if self.cleanup {
+			let _ = remove_dir_all(&self.path);
+		}
+	}
+}
+
+/// Converts `path` to a C string, so it can be passed to the OS.
+pub(crate) fn c_path(path: &str) -> Result<CString, os::Error> {
+	CString::new(path).map_err(|_| invalid_input())
+}
+fn invalid_input() -> os::Error {
+	os::Error::from_raw(libc::EINVAL)
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::rt::os::ErrorKind};
+
+	#[test]
+	fn metadata_and_permissions() {
+		let dir = metadata("/").unwrap();
+		assert!(dir.is_dir());
+		assert!(dir.modified() > SystemTime::UNIX_EPOCH);
+
+		assert_eq!(
+			metadata("/this/path/does/not/exist").unwrap_err().kind(),
+			ErrorKind::NotFound
+		);
+		assert_eq!(
+			metadata("nul\0byte").unwrap_err().kind(),
+			ErrorKind::InvalidInput
+		);
+
+		let mut permissions = Permissions::from_mode(0o644);
+		assert!(!permissions.readonly());
+		permissions.set_readonly(true);
+		assert_eq!(permissions.mode(), 0o444);
+		permissions.set_readonly(false);
+		assert_eq!(permissions.mode(), 0o666);
+	}
+
+	#[test]
+	fn temp_files() {
+		let dir = TempDir::new().unwrap();
+		assert!(metadata(dir.path()).unwrap().is_dir());
+		create_dir(&dir.join("nested")).unwrap();
+
+		let path = {
+			let mut file = tempfile().unwrap();
+			file.write_all(b"hello").unwrap();
+			assert_eq!(metadata(file.path()).unwrap().len(), 5);
+			assert_eq!(metadata(file.path()).unwrap().permissions().mode(), 0o600);
+			String::from(file.path())
+		};
+		assert!(metadata(&path).is_err());
+
+		let path = String::from(dir.path());
+		drop(dir);
+		assert_eq!(metadata(&path).unwrap_err().kind(), ErrorKind::NotFound);
+	}
+
+	#[test]
+	fn remove_dir_all_symlinks() {
+		let target = TempDir::new().unwrap();
+		create_dir(&target.join("kept")).unwrap();
+		let target_c = c_path(target.path()).unwrap();
+
+		let dir = TempDir::new().unwrap();
+		let link = c_path(&dir.join("link")).unwrap();
+		let res = unsafe {
+			os::unix::symlink(
+				NonNullConst::from_ref(target_c.as_c_str()).cast(),
+				NonNullConst::from_ref(link.as_c_str()).cast(),
+			)
+		};
+		assert_eq!(res, 0);
+		// A name that isn't valid UTF-8
+		let raw = CString::new([dir.path().as_bytes(), b"/\xff"].concat()).unwrap();
+		let res = unsafe { os::unix::mkdir(NonNullConst::from_ref(raw.as_c_str()).cast(), 0o777) };
+		assert_eq!(res, 0);
+
+		// Symlinks are deleted instead of followed, even at the top level
+		let outer_link = dir.join("outer");
+		let outer_link_c = c_path(&outer_link).unwrap();
+		let res = unsafe {
+			os::unix::symlink(
+				NonNullConst::from_ref(target_c.as_c_str()).cast(),
+				NonNullConst::from_ref(outer_link_c.as_c_str()).cast(),
+			)
+		};
+		assert_eq!(res, 0);
+		remove_dir_all(&outer_link).unwrap();
+		assert!(symlink_metadata(&outer_link).is_err());
+
+		let path = String::from(dir.path());
+		remove_dir_all(&path).unwrap();
+		assert_eq!(
+			symlink_metadata(&path).unwrap_err().kind(),
+			ErrorKind::NotFound
+		);
+		assert!(metadata(&target.join("kept")).unwrap().is_dir());
+	}
+}
@@ -0,0 +1,707 @@
+//! Notifications for when files change.
+//!
+//! A [`Watcher`] watches any number of files or directories, and reports
+//! changes to them as [`Event`]s. It's implemented with `inotify` on Linux,
+//! `kqueue` on macOS, and `ReadDirectoryChangesW` on Windows.
+//!
+//! ```rs
+//! let mut watcher = Watcher::new()?;
+//! watcher.watch("src")?;
+//! loop {
+//!     for event in watcher.read_events()? {
+//!         println!("{event:?}");
+//!     }
+//! }
+//! ```
+//!
+//! [`Watcher::read_events`] blocks until something changes. To wait on a
+//! watcher alongside other files (e.g. with `poll`), use
+//! [`Watcher::set_nonblocking`] and wait for [`Watcher::fd`] to become
+//! readable. Windows has no equivalent of `fd`, so a non-blocking watcher has
+//! to be checked with [`Watcher::read_events`] instead.
+
+#[cfg(unix)]
+use crate::rt::fs::c_path;
+use crate::{ffi::c_int, rt::os};
+#[cfg(windows)]
+use crate::{
+	ffi::c_void,
+	lang::{size_of, size_of_val},
+	text::wide,
+};
+
+/// Identifies something being watched by a [`Watcher`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WatchId(c_int);
+
+/// A change reported by a [`Watcher`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Event {
+	/// Which watched path the change happened to.
+	pub watch: WatchId,
+	pub kind: EventKind,
+	/// When watching a directory, the name of the file inside of it that
+	/// changed. `None` if the watched path itself changed, or if the platform
+	/// doesn't report names (macOS).
+	pub name: Option<String>,
+}
+
+/// What kind of change an [`Event`] describes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EventKind {
+	Created,
+	Modified,
+	Removed,
+	/// The file was moved or renamed.
+	Renamed,
+	/// The file's metadata (permissions, timestamps, etc) changed.
+	Metadata,
+	/// Too many events happened at once, so some of them were dropped.
+	Overflowed,
+}
+
+/// Watches files and directories for changes. See the [module-level docs].
+///
+/// [module-level docs]: crate::rt::fs::watch
+#[derive(Debug)]
+pub struct Watcher {
+	#[cfg(unix)]
+	fd: os::unix::FileDescriptor,
+	/// The I/O completion port every watched directory reports changes to.
+	#[cfg(windows)]
+	port: NonNull<c_void>,
+	/// kqueue needs an open file for every watched path.
+	#[cfg(macos)]
+	watched: Vec<os::unix::FileDescriptor>,
+	#[cfg(windows)]
+	watched: Vec<Box<DirectoryWatch>>,
+	/// The ID of the last path watched.
+	#[cfg(windows)]
+	last_id: c_int,
+	/// `kevent` ignores `O_NONBLOCK`, and Windows has no equivalent, so it's
+	/// tracked separately.
+	#[cfg(any(macos, windows))]
+	nonblocking: bool,
+}
+impl Watcher {
+	pub fn new() -> Result<Self, os::Error> {
+		#[cfg(linux)]
+		let fd = os::unix::inotify_init1(libc::IN_CLOEXEC);
+		#[cfg(macos)]
+		let fd = os::unix::kqueue();
+		#[cfg(unix)]
+		if fd.as_raw() == -1 {
+			return Err(os::Error::last());
+		}
+
+		#[cfg(windows)]
+		let port = unsafe {
+			os::win32::CreateIoCompletionPort(os::win32::INVALID_HANDLE_VALUE, None, 0, 1)
+		}
+		.ok_or_else(os::Error::last)?;
+
+		Ok(Self {
+			#[cfg(unix)]
+			fd,
+			#[cfg(windows)]
+			port,
+			#[cfg(any(macos, windows))]
+			watched: Vec::new(),
+			#[cfg(windows)]
+			last_id: 0,
+			#[cfg(any(macos, windows))]
+			nonblocking: false,
+		})
+	}
+
+	/// Starts watching `path`. If `path` is a directory, changes to files
+	/// directly inside it are reported too.
+	pub fn watch(&mut self, path: &str) -> Result<WatchId, os::Error> {
+		#[cfg(unix)]
+		let path = c_path(path)?;
+
+		#[cfg(linux)]
+		{
+			const MASK: u32 = libc::IN_CREATE
+				| libc::IN_MODIFY
+				| libc::IN_DELETE
+				| libc::IN_DELETE_SELF
+				| libc::IN_MOVED_FROM
+				| libc::IN_MOVED_TO
+				| libc::IN_MOVE_SELF
+				| libc::IN_ATTRIB;
+
+			let wd = unsafe {
+				os::unix::inotify_add_watch(
+					self.fd,
+					NonNullConst::from_ref(path.as_c_str()).cast(),
+					MASK,
+				)
+			};
+			if wd == -1 {
+				Err(os::Error::last())
+			} else {
+				Ok(WatchId(wd))
+			}
+		}
+		#[cfg(macos)]
+		{
+			const FFLAGS: u32 = libc::NOTE_WRITE
+				| libc::NOTE_EXTEND
+				| libc::NOTE_DELETE
+				| libc::NOTE_RENAME
+				| libc::NOTE_ATTRIB;
+
+			let file = unsafe { os::unix::open(path.as_ptr(), os::unix::OpenFlags::EVTONLY) };
+			if file.as_raw() == -1 {
+				return Err(os::Error::last());
+			}
+
+			let change = libc::kevent {
+				ident: file.as_raw() as usize,
+				filter: libc::EVFILT_VNODE,
+				flags: libc::EV_ADD | libc::EV_CLEAR,
+				fflags: FFLAGS,
+				data: 0,
+				udata: crate::lang::null_ptr_mut(),
+			};
+			let res = unsafe {
+				os::unix::kevent(
+					self.fd,
+					&change,
+					1,
+					crate::lang::null_ptr_mut(),
+					0,
+					crate::lang::null_ptr(),
+				)
+			};
+			if res == -1 {
+				let err = os::Error::last();
+				unsafe { os::unix::close(file) };
+				return Err(err);
+			}
+
+			self.watched.push(file);
+			Ok(WatchId(file.as_raw()))
+		}
+		#[cfg(windows)]
+		{
+			use os::win32::*;
+
+			let encode_nul = |path| {
+				wide::encode_nul::<usize>(path)
+					.map_err(|_| os::Error::from_raw(ERROR_INVALID_PARAMETER))
+			};
+
+			let wide_path = encode_nul(path)?;
+			let attributes = unsafe { GetFileAttributesW(NonNullConst::from_ref(&wide_path[0])) };
+			if attributes == INVALID_FILE_ATTRIBUTES {
+				return Err(os::Error::last());
+			}
+			// `ReadDirectoryChangesW` only watches directories, so a file is
+			// watched through its parent directory
+			let (dir, file) = if attributes & FILE_ATTRIBUTE_DIRECTORY != 0 {
+				(wide_path, None)
+			} else {
+				let (dir, name) = match path.rfind(['/', '\\']) {
+					// Keep the separator, so e.g. `C:\file` watches `C:\`
+					Some(idx) => (&path[..=idx], &path[idx + 1..]),
+					None => (".", path),
+				};
+				(encode_nul(dir)?, Some(wide::encode::<usize>(name)))
+			};
+
+			let handle = unsafe {
+				CreateFileW(
+					NonNullConst::from_ref(&dir[0]),
+					FILE_LIST_DIRECTORY,
+					FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+					None,
+					OPEN_EXISTING,
+					FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
+					None,
+				)
+			};
+			if handle == INVALID_HANDLE_VALUE {
+				return Err(os::Error::last());
+			}
+			// Boxed straight away, so the handle is closed if anything below
+			// fails
+			let mut watch = Box::new(DirectoryWatch {
+				id: WatchId(self.last_id + 1),
+				handle: unsafe { NonNull::new_unchecked(handle as *mut c_void) },
+				file,
+				overlapped: unsafe { core::mem::zeroed() },
+				buf: [0; 1024],
+			});
+			let id = watch.id;
+
+			let port = unsafe { CreateIoCompletionPort(handle, Some(self.port), id.0 as usize, 0) };
+			if port.is_none() {
+				return Err(os::Error::last());
+			}
+			watch.read()?;
+
+			self.last_id = id.0;
+			self.watched.push(watch);
+			Ok(id)
+		}
+	}
+	/// Stops watching something that was watched with [`Watcher::watch`].
+	pub fn unwatch(&mut self, watch: WatchId) -> Result<(), os::Error> {
+		#[cfg(linux)]
+		{
+			if os::unix::inotify_rm_watch(self.fd, watch.0) == -1 {
+				Err(os::Error::last())
+			} else {
+				Ok(())
+			}
+		}
+		#[cfg(macos)]
+		{
+			// Closing the file removes it from the kqueue
+			let idx = self
+				.watched
+				.iter()
+				.position(|file| file.as_raw() == watch.0)
+				.ok_or(os::Error::from_raw(libc::EINVAL))?;
+			unsafe { os::unix::close(self.watched.swap_remove(idx)) };
+			Ok(())
+		}
+		#[cfg(windows)]
+		{
+			let idx = self
+				.watched
+				.iter()
+				.position(|watched| watched.id == watch)
+				.ok_or(os::Error::from_raw(os::win32::ERROR_INVALID_PARAMETER))?;
+			self.watched.swap_remove(idx);
+			Ok(())
+		}
+	}
+
+	/// Waits for at least one change, then returns every change that's
+	/// happened since the last call.
+	///
+	/// If the watcher is non-blocking and nothing has changed, this errors
+	/// with [`os::ErrorKind::WouldBlock`].
+	pub fn read_events(&mut self) -> Result<Vec<Event>, os::Error> {
+		#[cfg(linux)]
+		{
+			const HEADER_SIZE: usize = crate::lang::size_of::<libc::inotify_event>();
+
+			let mut buf = [0u8; 4096];
+			let len = loop {
+				let res =
+					unsafe { os::unix::read(self.fd, NonNull::from(&mut buf).cast(), buf.len()) };
+				if res != -1 {
+					break res as usize;
+				}
+				let err = os::Error::last();
+				if err.kind() != os::ErrorKind::Interrupted {
+					return Err(err);
+				}
+			};
+
+			let mut events = Vec::new();
+			let mut offset = 0;
+			while offset + HEADER_SIZE <= len {
+				let header = unsafe {
+					core::ptr::read_unaligned(buf[offset..].as_ptr().cast::<libc::inotify_event>())
+				};
+				let name = &buf[offset + HEADER_SIZE..offset + HEADER_SIZE + header.len as usize];
+				offset += HEADER_SIZE + header.len as usize;
+
+				let kind = if header.mask & libc::IN_Q_OVERFLOW != 0 {
+					EventKind::Overflowed
+				} else if header.mask & libc::IN_CREATE != 0 {
+					EventKind::Created
+				} else if header.mask & libc::IN_MODIFY != 0 {
+					EventKind::Modified
+				} else if header.mask & (libc::IN_DELETE | libc::IN_DELETE_SELF) != 0 {
+					EventKind::Removed
+				} else if header.mask
+					& (libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_MOVE_SELF)
+					!= 0
+				{
+					EventKind::Renamed
+				} else if header.mask & libc::IN_ATTRIB != 0 {
+					EventKind::Metadata
+				} else {
+					// e.g. `IN_IGNORED`, when a watch is removed
+					continue;
+				};
+				// The name is padded with nul bytes
+				let name = name
+					.split(|byte| *byte == 0)
+					.next()
+					.filter(|name| !name.is_empty())
+					.map(|name| String::from_utf8_lossy(name).into_owned());
+
+				events.push(Event {
+					watch: WatchId(header.wd),
+					kind,
+					name,
+				});
+			}
+
+			Ok(events)
+		}
+		#[cfg(macos)]
+		{
+			let mut buf: [libc::kevent; 32] = unsafe { core::mem::zeroed() };
+			// A zero timeout makes `kevent` return immediately
+			let no_wait = os::unix::TimeSpec {
+				tv_sec: 0,
+				tv_nsec: 0,
+			};
+			let timeout = if self.nonblocking {
+				&no_wait
+			} else {
+				crate::lang::null_ptr()
+			};
+			let count = loop {
+				let res = unsafe {
+					os::unix::kevent(
+						self.fd,
+						crate::lang::null_ptr(),
+						0,
+						buf.as_mut_ptr(),
+						buf.len() as c_int,
+						timeout,
+					)
+				};
+				if res == 0 && self.nonblocking {
+					return Err(os::Error::from_raw(libc::EAGAIN));
+				}
+				if res != -1 {
+					break res as usize;
+				}
+				let err = os::Error::last();
+				if err.kind() != os::ErrorKind::Interrupted {
+					return Err(err);
+				}
+			};
+
+			let mut events = Vec::new();
+			for event in &buf[..count] {
+				let watch = WatchId(event.ident as c_int);
+				// One kqueue event can describe several changes
+				let kinds = [
+					(libc::NOTE_DELETE, EventKind::Removed),
+					(libc::NOTE_RENAME, EventKind::Renamed),
+					(libc::NOTE_WRITE | libc::NOTE_EXTEND, EventKind::Modified),
+					(libc::NOTE_ATTRIB, EventKind::Metadata),
+				];
+				for (flags, kind) in kinds {
+					if event.fflags & flags != 0 {
+						events.push(Event {
+							watch,
+							kind,
+							name: None,
+						});
+					}
+				}
+			}
+
+			Ok(events)
+		}
+		#[cfg(windows)]
+		{
+			const INFINITE: u32 = u32::MAX;
+
+			let mut events = Vec::new();
+			let mut timeout = if self.nonblocking { 0 } else { INFINITE };
+			loop {
+				let mut len = 0;
+				let mut key = 0;
+				let mut overlapped = crate::lang::null_ptr_mut();
+				let res = unsafe {
+					os::win32::GetQueuedCompletionStatus(
+						self.port,
+						NonNull::from_mut(&mut len),
+						NonNull::from_mut(&mut key),
+						NonNull::from_mut(&mut overlapped),
+						timeout,
+					)
+				};
+				// Nothing was dequeued, either because there's nothing left
+				// or because the port failed
+				if overlapped.is_null() {
+					let err = os::Error::last();
+					if err.kind() != os::ErrorKind::TimedOut {
+						return Err(err);
+					}
+					if !events.is_empty() {
+						return Ok(events);
+					}
+					if self.nonblocking {
+						return Err(os::Error::from_raw(os::win32::WSAEWOULDBLOCK));
+					}
+					// Everything dequeued so far was filtered out
+					timeout = INFINITE;
+					continue;
+				}
+
+				// Reads cancelled by `unwatch` still complete, after their
+				// watch is gone
+				let Some(watch) = self
+					.watched
+					.iter_mut()
+					.find(|watch| watch.id.0 as usize == key)
+				else {
+					continue;
+				};
+				if res == 0 {
+					return Err(os::Error::last());
+				}
+				watch.parse_events(len as usize, &mut events);
+				watch.read()?;
+
+				// Collect anything else that's already queued, without waiting
+				timeout = 0;
+			}
+		}
+	}
+
+	/// Sets whether [`Watcher::read_events`] blocks when nothing has changed.
+	pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<(), os::Error> {
+		#[cfg(any(macos, windows))]
+		{
+			self.nonblocking = nonblocking;
+		}
+
+		#[cfg(unix)]
+		{
+			let flags = unsafe { os::unix::fcntl(self.fd, libc::F_GETFL) };
+			if flags == -1 {
+				return Err(os::Error::last());
+			}
+			let flags = if nonblocking {
+				flags | libc::O_NONBLOCK
+			} else {
+				flags & !libc::O_NONBLOCK
+			};
+			if unsafe { os::unix::fcntl(self.fd, libc::F_SETFL, flags) } == -1 {
+				return Err(os::Error::last());
+			}
+		}
+
+		Ok(())
+	}
+	/// The file descriptor the watcher reads events from. It becomes readable
+	/// when there are events to read, so it can be waited on with `poll` and
+	/// similar APIs.
+	#[cfg(unix)]
+	pub fn fd(&self) -> os::unix::FileDescriptor {
+		self.fd
+	}
+}
+impl Drop for Watcher {
+	fn drop(&mut self) {
+		#[cfg(unix)]
+		unsafe {
+			os::unix::close(self.fd)
+		};
+		#[cfg(macos)]
+		for file in self.watched.drain(..) {
+			unsafe { os::unix::close(file) };
+		}
+		#[cfg(windows)]
+		{
+			// Every read has to finish before the port is closed
+			self.watched.clear();
+			unsafe { os::win32::CloseHandle(self.port) };
+		}
+	}
+}
+
+/// A directory being watched with `ReadDirectoryChangesW`. It's boxed, so the
+/// buffer and `OVERLAPPED` the OS writes to don't move while a read is in
+/// progress.
+#[cfg(windows)]
+#[derive(Debug)]
+struct DirectoryWatch {
+	id: WatchId,
+	handle: NonNull<c_void>,
+	/// If a file is being watched instead of a directory, its name. Only
+	/// changes to that file are reported.
+	file: Option<SizedVec<u16, usize>>,
+	overlapped: os::win32::Overlapped,
+	/// Stored as `u32`s so `FILE_NOTIFY_INFORMATION`s are aligned.
+	buf: [u32; 1024],
+}
+#[cfg(windows)]
+impl DirectoryWatch {
+	/// Starts reading the next batch of changes, which are reported to the
+	/// [`Watcher`]'s completion port.
+	fn read(&mut self) -> Result<(), os::Error> {
+		use os::win32::*;
+
+		const FILTER: u32 = FILE_NOTIFY_CHANGE_FILE_NAME
+			| FILE_NOTIFY_CHANGE_DIR_NAME
+			| FILE_NOTIFY_CHANGE_ATTRIBUTES
+			| FILE_NOTIFY_CHANGE_SIZE
+			| FILE_NOTIFY_CHANGE_LAST_WRITE;
+
+		self.overlapped = unsafe { core::mem::zeroed() };
+		let res = unsafe {
+			ReadDirectoryChangesW(
+				self.handle,
+				NonNull::from_mut(&mut self.buf).cast(),
+				size_of_val(&self.buf) as u32,
+				0,
+				FILTER,
+				None,
+				NonNull::from_mut(&mut self.overlapped),
+				None,
+			)
+		};
+		if res == 0 {
+			Err(os::Error::last())
+		} else {
+			Ok(())
+		}
+	}
+	/// Adds the changes from a finished read of `len` bytes to `events`.
+	fn parse_events(&self, len: usize, events: &mut Vec<Event>) {
+		use os::win32::*;
+
+		// Windows reports an empty read when there were too many changes to
+		// fit in the buffer
+		if len == 0 {
+			events.push(Event {
+				watch: self.id,
+				kind: EventKind::Overflowed,
+				name: None,
+			});
+			return;
+		}
+
+		let mut offset = 0;
+		loop {
+			let info = unsafe {
+				self.buf
+					.as_ptr()
+					.byte_add(offset)
+					.cast::<FileNotifyInformation>()
+			};
+			let (next, action, name) = unsafe {
+				(
+					(*info).next_entry_offset as usize,
+					(*info).action,
+					core::slice::from_raw_parts(
+						(&raw const (*info).file_name).cast::<u16>(),
+						(*info).file_name_length as usize / size_of::<u16>(),
+					),
+				)
+			};
+
+			let kind = match action {
+				FILE_ACTION_ADDED => Some(EventKind::Created),
+				FILE_ACTION_REMOVED => Some(EventKind::Removed),
+				FILE_ACTION_MODIFIED => Some(EventKind::Modified),
+				FILE_ACTION_RENAMED_OLD_NAME | FILE_ACTION_RENAMED_NEW_NAME => {
+					Some(EventKind::Renamed)
+				}
+				_ => None,
+			};
+			if let Some(kind) = kind {
+				match &self.file {
+					None => events.push(Event {
+						watch: self.id,
+						kind,
+						name: Some(wide::decode_lossy(name)),
+					}),
+					Some(file) if name == file.as_slice() => events.push(Event {
+						watch: self.id,
+						kind,
+						name: None,
+					}),
+					Some(_) => {}
+				}
+			}
+
+			if next == 0 {
+				break;
+			}
+			offset += next;
+		}
+	}
+}
+#[cfg(windows)]
+impl Drop for DirectoryWatch {
+	fn drop(&mut self) {
+		// The OS writes to the buffer until the read finishes, so wait for the
+		// cancelled read before freeing it
+		let mut len = 0;
+		unsafe {
+			os::win32::CancelIoEx(self.handle, Some(NonNull::from_mut(&mut self.overlapped)));
+			os::win32::GetOverlappedResult(
+				self.handle,
+				NonNull::from_mut(&mut self.overlapped),
+				NonNull::from_mut(&mut len),
+				1,
+			);
+			os::win32::CloseHandle(self.handle);
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(all(test, unix))]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			io::Writer,
+			rt::fs::{TempDir, remove_file},
+		},
+	};
+
+	#[test]
+	fn watcher() {
+		let dir = TempDir::new().unwrap();
+		let mut watcher = Watcher::new().unwrap();
+		watcher.set_nonblocking(true).unwrap();
+
+		let file = dir.join("file");
+		let mode: c_int = 0o600;
+		let fd = unsafe {
+			os::unix::open(
+				c_path(&file).unwrap().as_ptr(),
+				os::unix::OpenFlags::WRONLY | os::unix::OpenFlags::CREAT,
+				mode,
+			)
+		};
+		let watch = watcher.watch(&file).unwrap();
+		assert_eq!(
+			watcher.read_events().unwrap_err().kind(),
+			os::ErrorKind::WouldBlock
+		);
+
+		unsafe { os::unix::FileWriter::new(fd) }
+			.write_all(b"hi")
+			.unwrap();
+		unsafe { os::unix::close(fd) };
+		let events = watcher.read_events().unwrap();
+		assert!(
+			events
+				.iter()
+				.any(|event| event.watch == watch && event.kind == EventKind::Modified)
+		);
+
+		remove_file(&file).unwrap();
+		let events = watcher.read_events().unwrap();
+		assert!(
+			events
+				.iter()
+				.any(|event| event.watch == watch && event.kind == EventKind::Removed)
+		);
+	}
+}
@@ -2,7 +2,15 @@
 //! your program's lifecycle - for example, just after Crux loads, but before
 //! the main function is called, or right before your program exits.
 
-use crate::{lang::XStat, rt::OsAllocator};
+#[cfg(feature = "concurrency")]
+use crate::{concurrency::OnceCell, lang::UnsafeCell};
+use {
+	crate::{
+		lang::{XStat, XStatEntry},
+		rt::OsAllocator,
+	},
+	core::sync::atomic::{AtomicU64, Ordering as AtomicOrdering},
+};
 
 //
 // Hooks
@@ -24,6 +32,16 @@ impl HookId {
 	pub fn raw(self) -> u128 {
 		self.0
 	}
+
+	/// Creates a unique ID for a hook registered with [`Event::register`].
+	///
+	/// IDs made by the [`hook`] macro repeat the same 64 bits twice, so IDs
+	/// with two different halves can't collide with them.
+	fn runtime() -> Self {
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		let id = NEXT.fetch_add(1, AtomicOrdering::Relaxed);
+		Self(((id as u128) << 64) | !id as u128)
+	}
 }
 
 /// Constraints allow a programmer to specify when a hook must be executed,
@@ -36,7 +54,7 @@ impl HookId {
 ///   event: crux::events::startup,
 ///   func: some_callback,
 ///   constraints: [
-///     After(crux::hooks::startup)
+///     after(crux::hooks::startup_hook),
 ///   ]
 /// }
 /// ```
@@ -52,6 +70,35 @@ pub enum Constraint {
 	After(HookId),
 }
 
+/// A coarse position for a hook within its event. Every hook in an earlier
+/// phase runs before every hook in a later phase; [`Constraint`]s order hooks
+/// within a phase.
+///
+/// Phases let hooks from crates that don't know about each other order
+/// themselves without naming each other's hooks. Hooks are in the
+/// [`Phase::Default`] phase unless their `hook!` call says otherwise:
+/// ```rs
+/// hook! {
+///   event: crux::events::startup,
+///   func: some_callback,
+///   phase: Late,
+///   constraints: []
+/// }
+/// ```
+///
+/// A constraint that contradicts the phases (e.g. an [`Phase::Early`] hook
+/// that must run after a [`Phase::Late`] one) makes the event unsolvable.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Phase {
+	Early,
+	Default,
+	Late,
+}
+impl Phase {
+	/// Every phase, in the order they run.
+	pub const ALL: [Self; 3] = [Self::Early, Self::Default, Self::Late];
+}
+
 /// A function that executes in response to a specific event.
 pub struct Hook<F> {
 	/// The function to execute.
@@ -60,77 +107,197 @@ pub struct Hook<F> {
 	/// event to order themselves before or after this hook; see [`Constraint`]
 	/// for more information.
 	pub id: HookId,
+	/// Which [`Phase`] of the event this hook runs in.
+	pub phase: Phase,
 	/// An unsized array of [`Constraint`]s.
 	pub constraints: &'static [Constraint],
 }
 
+/// Attaches a hook to an event, which is called when the event is dispatched.
+///
+/// The hook can be a function or a non-capturing closure. Closures and paths
+/// make anonymous hooks: they're registered with the event, but nothing else
+/// can refer to them, so they can't collide with other hooks:
+/// ```rs
+/// hook! {
+///   event: crux::events::startup,
+///   func: |_| crux::logging::info!("Starting up"),
+///   constraints: []
+/// }
+/// ```
+///
+/// To let other hooks order themselves around this one with [`Constraint`]s,
+/// give it a name. This declares a module with that name and visibility,
+/// which constraints refer to:
+/// ```rs
+/// hook! {
+///   event: crux::events::startup,
+///   name: pub(crate) load_config,
+///   func: |_| CONFIG.load(),
+///   constraints: []
+/// }
+/// hook! {
+///   event: crux::events::startup,
+///   func: connect,
+///   constraints: [after(crate::load_config),]
+/// }
+/// ```
+///
+/// For compatibility, hooking a function by its name without a `name:` names
+/// the hook after the function (as `pub mod <func>`); use `name: _` to hook it
+/// anonymously instead.
 #[macro_export]
 macro_rules! hook {
 	(
 		$(#[doc = $doc:literal])*
 		event: $event:path,
-		func: $func:ident,
+		name: _,
+		func: $func:expr,
+		$(phase: $phase:ident,)?
+		constraints: [$($order:ident($constraint:path),)*]
+	) => {
+		const _: () = {
+			$crate::rt::hook::hook! {
+				@items
+				$(#[doc = $doc])*
+				event: $event,
+				func: $func,
+				phase: $($phase)?,
+				constraints: [$($order($constraint),)*]
+			}
+		};
+	};
+	(
+		$(#[doc = $doc:literal])*
+		event: $event:path,
+		name: $vis:vis $name:ident,
+		func: $func:expr,
+		$(phase: $phase:ident,)?
 		constraints: [$($order:ident($constraint:path),)*]
 	) => {
 		$(#[doc = $doc])*
-		pub mod $func {
+		$vis mod $name {
+			#[allow(unused_imports)]
 			use super::*;
 
-			pub use $event as event;
-
-			pub static CONSTRAINTS: &'static [$crate::rt::hook::Constraint] = &[
-				$($crate::rt::hook::hook!(@$order $constraint)),*
-			];
-
-			pub static HOOK: $crate::lang::XStatEntry<$crate::rt::hook::Hook<event::Func>> = $crate::lang::XStatEntry {
-				next: $crate::lang::UnsafeCell::new($crate::lang::Option::None),
-				value: $crate::rt::hook::Hook {
-					func: $func,
-					id: const {
-						let hash = $crate::crypto::sha2_const::Sha256::new()
-							.update(&$crate::lang::line!().to_ne_bytes())
-							.update(&$crate::lang::column!().to_ne_bytes())
-							.update($crate::lang::file!().as_bytes())
-							.update($crate::lang::module_path!().as_bytes())
-							.finalize();
-						let total = u128::from_ne_bytes([
-							hash[0],
-							hash[1],
-							hash[2],
-							hash[3],
-							hash[4],
-							hash[5],
-							hash[6],
-							hash[7],
-							hash[0],
-							hash[1],
-							hash[2],
-							hash[3],
-							hash[4],
-							hash[5],
-							hash[6],
-							hash[7]
-						]);
-						unsafe { $crate::rt::hook::HookId::new(total) }
-					},
-					constraints: CONSTRAINTS
-				},
-			};
-
-			/// Registers [`HOOK`] with [`event::EVENT`].
-			///
-			///
-			/// # Safety
-			///
-			/// This will be called automatically as a Crux ini function, so you
-			/// shouldn't need to call it yourself. This is unsafe because it
-			/// calls `XStat::push`; see the safety docs for that method.
-			pub unsafe fn preexec() {
-				unsafe { event::EVENT.push(&HOOK) }
+			$crate::rt::hook::hook! {
+				@items
+				event: $event,
+				func: $func,
+				phase: $($phase)?,
+				constraints: [$($order($constraint),)*]
 			}
-			$crate::rt::register_ini_function!(preexec);
 		}
 	};
+	(
+		$(#[doc = $doc:literal])*
+		event: $event:path,
+		func: $func:ident,
+		$(phase: $phase:ident,)?
+		constraints: [$($order:ident($constraint:path),)*]
+	) => {
+		$crate::rt::hook::hook! {
+			$(#[doc = $doc])*
+			event: $event,
+			name: pub $func,
+			func: $func,
+			$(phase: $phase,)?
+			constraints: [$($order($constraint),)*]
+		}
+	};
+	(
+		$(#[doc = $doc:literal])*
+		event: $event:path,
+		func: $func:expr,
+		$(phase: $phase:ident,)?
+		constraints: [$($order:ident($constraint:path),)*]
+	) => {
+		$crate::rt::hook::hook! {
+			$(#[doc = $doc])*
+			event: $event,
+			name: _,
+			func: $func,
+			$(phase: $phase,)?
+			constraints: [$($order($constraint),)*]
+		}
+	};
+
+	// The statics that register a hook. Anonymous hooks put these in a
+	// `const _` block, so every hook gets its own statics without having to
+	// name them.
+	(
+		@items
+		$(#[doc = $doc:literal])*
+		event: $event:path,
+		func: $func:expr,
+		phase: $($phase:ident)?,
+		constraints: [$($order:ident($constraint:path),)*]
+	) => {
+		pub use $event as event;
+
+		pub static CONSTRAINTS: &'static [$crate::rt::hook::Constraint] = &[
+			$($crate::rt::hook::hook!(@$order $constraint)),*
+		];
+
+		$(#[doc = $doc])*
+		pub static HOOK: $crate::lang::XStatEntry<$crate::rt::hook::Hook<event::Func>> = $crate::lang::XStatEntry {
+			next: $crate::lang::UnsafeCell::new($crate::lang::Option::None),
+			value: $crate::rt::hook::Hook {
+				func: $func,
+				id: const {
+					// Hooks made by the same macro expansion share a location,
+					// so the function is hashed too
+					let hash = $crate::crypto::sha2_const::Sha256::new()
+						.update(&$crate::lang::line!().to_ne_bytes())
+						.update(&$crate::lang::column!().to_ne_bytes())
+						.update($crate::lang::file!().as_bytes())
+						.update($crate::lang::module_path!().as_bytes())
+						.update($crate::lang::stringify!($func).as_bytes())
+						.finalize();
+					let total = u128::from_ne_bytes([
+						hash[0],
+						hash[1],
+						hash[2],
+						hash[3],
+						hash[4],
+						hash[5],
+						hash[6],
+						hash[7],
+						hash[0],
+						hash[1],
+						hash[2],
+						hash[3],
+						hash[4],
+						hash[5],
+						hash[6],
+						hash[7]
+					]);
+					unsafe { $crate::rt::hook::HookId::new(total) }
+				},
+				phase: $crate::rt::hook::hook!(@phase $($phase)?),
+				constraints: CONSTRAINTS
+			},
+		};
+
+		/// Registers [`HOOK`] with [`event::EVENT`].
+		///
+		///
+		/// # Safety
+		///
+		/// This will be called automatically as a Crux ini function, so you
+		/// shouldn't need to call it yourself. This is unsafe because it
+		/// calls `XStat::push`; see the safety docs for that method.
+		pub unsafe fn preexec() {
+			unsafe { event::EVENT.push(&HOOK) }
+		}
+		$crate::rt::register_ini_function!(preexec);
+	};
+	(@phase) => {
+		$crate::rt::hook::Phase::Default
+	};
+	(@phase $phase:ident) => {
+		$crate::rt::hook::Phase::$phase
+	};
 	// macros get unhappy if we try to do `$constraint::HOOK.value.id`
 	// idk why but we do a `use` instead to solve it
 	(@after $constraint:path) => {{
@@ -148,29 +315,128 @@ pub use crate::hook;
 // Events
 //
 
-pub struct Event<F: 'static>(XStat<Hook<F>>);
+pub struct Event<F: 'static> {
+	hooks: XStat<Hook<F>>,
+	/// The event's hooks in the order they should run, cached by
+	/// [`Event::solved`].
+	#[cfg(feature = "concurrency")]
+	solved: UnsafeCell<OnceCell<Result<&'static [&'static F], EventSolvingError>>>,
+}
 impl<F> Deref for Event<F> {
 	type Target = XStat<Hook<F>>;
 
 	fn deref(&self) -> &Self::Target {
-		&self.0
+		&self.hooks
 	}
 }
 impl<F> const Default for Event<F> {
 	fn default() -> Self {
-		Self(XStat::default())
+		Self {
+			hooks: XStat::default(),
+			#[cfg(feature = "concurrency")]
+			solved: UnsafeCell::new(OnceCell::new()),
+		}
 	}
 }
+// The solved cache is only written to by `OnceCell` (which is thread-safe) or
+// by methods that require exclusive access to the event
+unsafe impl<F> Sync for Event<F> {}
 
 /// An error from [`Event::solve`].
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum EventSolvingError {
 	/// Item has to go before and after itself.
 	Recursive,
-	/// Two items have to go before and after each other.
+	/// An item has a constraint on a hook that isn't registered with the event,
+	/// e.g. because it was removed with [`Event::unregister`].
+	UnknownHook(HookId),
+	/// Two or more items have to go before and after each other, or a
+	/// constraint contradicts the items' [`Phase`]s.
 	Cyclical,
 }
 impl<F> Event<F> {
+	/// Adds a hook to this event at runtime - for example, from a plugin loaded
+	/// with [`dl`]. Returns the new hook's ID, which other hooks can use in
+	/// their [`Constraint`]s, and which can be passed to
+	/// [`Event::unregister`].
+	///
+	/// The hook only runs if the event is solved after it's registered. This
+	/// clears the order cached by [`Event::solved`], but events that were
+	/// already solved and run (e.g. [`startup`], which runs before `main`)
+	/// won't be run again automatically.
+	///
+	///
+	/// # Safety
+	///
+	/// This writes to the [`Event`] value. You're responsible for ensuring
+	/// nothing is reading from or writing to the [`Event`] at the same time.
+	///
+	/// [`dl`]: crate::rt::dl
+	/// [`startup`]: crate::events::startup
+	pub unsafe fn register(
+		&self,
+		func: F,
+		phase: Phase,
+		constraints: &'static [Constraint],
+	) -> HookId {
+		let id = HookId::runtime();
+		let entry = Box::leak(Box::new(XStatEntry::new(Hook {
+			func,
+			id,
+			phase,
+			constraints,
+		})));
+		unsafe { self.hooks.push(entry) };
+		self.invalidate_solved();
+
+		id
+	}
+	/// Removes a hook from this event. Returns false if the event doesn't have
+	/// a hook with the given ID.
+	///
+	/// This works for hooks made with the [`hook`] macro, too. The hook's
+	/// memory is never freed, since function references returned by earlier
+	/// calls to [`Event::solve`] may still point to it.
+	///
+	///
+	/// # Safety
+	///
+	/// This writes to the [`Event`] value. You're responsible for ensuring
+	/// nothing is reading from or writing to the [`Event`] at the same time.
+	pub unsafe fn unregister(&self, id: HookId) -> bool {
+		let removed = unsafe { self.hooks.remove(|hook| hook.id == id) }.is_some();
+		if removed {
+			self.invalidate_solved();
+		}
+
+		removed
+	}
+
+	/// Like [`Event::solve`], but only solves the event the first time it's
+	/// called. The order is cached for the rest of the program (or until a hook
+	/// is registered or unregistered), so later calls don't allocate.
+	///
+	///
+	/// # Safety
+	///
+	/// See [`Event::solve`].
+	#[cfg(feature = "concurrency")]
+	pub unsafe fn solved(&self) -> Result<&'static [&'static F], EventSolvingError> {
+		*unsafe { &*self.solved.get() }.get_or_init(|| {
+			// The OS allocation is never freed, so the cached order stays valid
+			// even after it's invalidated
+			unsafe { self.solve() }.map(|hooks| &*hooks.leak())
+		})
+	}
+	/// Clears the order cached by [`Event::solved`]. Only called by methods
+	/// that require exclusive access to the event.
+	fn invalidate_solved(&self) {
+		#[cfg(feature = "concurrency")]
+		unsafe {
+			*self.solved.get() = OnceCell::new();
+		}
+	}
+
 	/// Solve the event and return its function hooks in the order they should
 	/// be called.
 	///
@@ -197,7 +463,7 @@ impl<F> Event<F> {
 		// i.e. (1, 2) means hook idx 1 must run before hook idx 2
 		let mut links = SizedVec::with_allocator(OsAllocator);
 
-		for hook in unsafe { self.0.entries() } {
+		for hook in unsafe { self.hooks.entries() } {
 			stable_idx_map.insert(hook.id, hooks_stable.len());
 			hooks_stable.push(hook);
 		}
@@ -211,57 +477,141 @@ impl<F> Event<F> {
 			for &constraint in hook.constraints {
 				match constraint {
 					Constraint::Before(other_hook_id) => {
-						links.push((idx, *stable_idx_map.get(&other_hook_id).unwrap()));
+						let Some(&other) = stable_idx_map.get(&other_hook_id) else {
+							return Err(EventSolvingError::UnknownHook(other_hook_id));
+						};
+						links.push((idx, other));
 					}
 					Constraint::After(other_hook_id) => {
-						links.push((*stable_idx_map.get(&other_hook_id).unwrap(), idx));
+						let Some(&other) = stable_idx_map.get(&other_hook_id) else {
+							return Err(EventSolvingError::UnknownHook(other_hook_id));
+						};
+						links.push((other, idx));
 					}
 				}
 			}
 		}
 		let links = links;
 
-		// key: stable idx
-		// output: actual idx
-		let mut hooks_real = SizedVec::with_allocator(OsAllocator);
-		for idx in 0..hooks_stable.len() {
-			hooks_real.push(idx);
+		// How many hooks still have to run before each hook can run
+		let mut blockers = SizedVec::with_allocator(OsAllocator);
+		let mut done = SizedVec::with_allocator(OsAllocator);
+		for _ in 0..hooks_stable.len() {
+			blockers.push(0u16);
+			done.push(false);
+		}
+		for &(before, after) in links.as_slice() {
+			if before == after {
+				return Err(EventSolvingError::Recursive);
+			}
+			*unsafe { blockers.get_mut_unchecked(after) } += 1;
 		}
 
-		'outer: loop {
-			for &(stable_before, stable_after) in links.as_slice() {
-				let before = *unsafe { hooks_real.get_unchecked(stable_before) };
-				let after = *unsafe { hooks_real.get_unchecked(stable_after) };
-
-				if before > after {
-					for real_idx in hooks_real.as_slice_mut() {
-						if *real_idx > after {
-							// after element we're moving down; fill gap
-							*real_idx -= 1;
-						} else if *real_idx >= before {
-							// after or the element we're moving up; move up
-							*real_idx += 1;
-						}
-					}
-					// move item
-					*unsafe { hooks_real.get_mut_unchecked(stable_after) } = before;
+		// Go through the phases in order. In each phase, repeatedly run the first
+		// hook that isn't waiting on any other hooks, so hooks without
+		// constraints keep the order they were added in.
+		let mut output = SizedVec::with_allocator(OsAllocator);
+		for phase in Phase::ALL {
+			loop {
+				let next = (0..hooks_stable.len()).find(|&idx| unsafe {
+					!*done.get_unchecked(idx)
+						&& *blockers.get_unchecked(idx) == 0
+						&& hooks_stable.get_unchecked(idx).phase == phase
+				});
+				let Some(idx) = next else {
+					break;
+				};
 
-					// we changed one, recheck all links
-					continue 'outer;
+				*unsafe { done.get_mut_unchecked(idx) } = true;
+				output.push(&unsafe { hooks_stable.get_unchecked(idx) }.func);
+				for &(before, after) in links.as_slice() {
+					if before == idx {
+						*unsafe { blockers.get_mut_unchecked(after) } -= 1;
+					}
 				}
 			}
 
-			break;
+			// Hooks left in this phase are waiting on each other, or on a hook
+			// in a later phase
+			let stuck = (0..hooks_stable.len()).any(|idx| unsafe {
+				!*done.get_unchecked(idx) && hooks_stable.get_unchecked(idx).phase == phase
+			});
+			if stuck {
+				return Err(EventSolvingError::Cyclical);
+			}
 		}
 
-		let mut output = SizedVec::with_allocator(OsAllocator);
-		for &idx in hooks_real.as_slice() {
-			output.push(&unsafe { hooks_stable.get_unchecked(idx) }.func);
-		}
 		Ok(output)
 	}
+
+	/// Solves the event, then calls each of its hooks in order with `call`.
+	///
+	///
+	/// # Safety
+	///
+	/// See [`Event::solve`].
+	pub unsafe fn dispatch(&self, mut call: impl FnMut(&F)) -> Result<(), EventSolvingError> {
+		for hook in unsafe { self.solve() }?.as_slice() {
+			call(hook);
+		}
+
+		Ok(())
+	}
+	/// Solves the event, then calls its hooks in order until one of them
+	/// returns `Some`, and returns that value. Useful for events where hooks
+	/// answer a request, and the first hook to answer wins:
+	///
+	/// ```rs
+	/// event!(resolve_config_path, fn() -> Option<String>);
+	///
+	/// let path = unsafe { resolve_config_path::EVENT.first_some(|hook| hook()) }?
+	///     .unwrap_or_else(|| String::from("config.ini"));
+	/// ```
+	///
+	///
+	/// # Safety
+	///
+	/// See [`Event::solve`].
+	pub unsafe fn first_some<R>(
+		&self,
+		mut call: impl FnMut(&F) -> Option<R>,
+	) -> Result<Option<R>, EventSolvingError> {
+		Ok(unsafe { self.solve() }?.as_slice().iter().find_map(|hook| call(hook)))
+	}
+	/// Solves the event, then calls each of its hooks in order and collects
+	/// what they return.
+	///
+	///
+	/// # Safety
+	///
+	/// See [`Event::solve`].
+	pub unsafe fn collect<R>(
+		&self,
+		mut call: impl FnMut(&F) -> R,
+	) -> Result<Vec<R>, EventSolvingError> {
+		Ok(unsafe { self.solve() }?.as_slice().iter().map(|hook| call(hook)).collect())
+	}
 }
 
+/// Declares an event that hooks can be attached to with the [`hook`] macro.
+///
+/// The second argument is the function signature hooks must have. Hooks can
+/// take arguments and return values; [`Event::dispatch`],
+/// [`Event::first_some`], and [`Event::collect`] call the hooks and combine
+/// their results:
+///
+/// ```rs
+/// event!(
+///     /// Asks plugins for extra search paths.
+///     search_paths,
+///     fn(&str) -> Vec<String>
+/// );
+///
+/// let paths: Vec<String> = unsafe { search_paths::EVENT.collect(|hook| hook("assets")) }?
+///     .into_iter()
+///     .flatten()
+///     .collect();
+/// ```
 #[macro_export]
 macro_rules! event {
 	($(#[doc = $doc:literal])* $name:ident, $sig:ty) => {
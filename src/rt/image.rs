@@ -0,0 +1,929 @@
+//! Introspection of the running executable's image: the sections and symbols
+//! its headers describe.
+//!
+//! ```rs
+//! let image = Image::current()?;
+//! let text = image.section(".text").unwrap();
+//! println!("code is at {:#x}..{:#x}", text.address, text.address + text.size);
+//! if let Some(symbol) = image.symbol_at(crux_main as usize) {
+//!     println!("{:?}", symbol.name);
+//! }
+//! ```
+//!
+//! ELF (Linux), Mach-O (macOS) and PE (Windows) images are supported. Section
+//! headers and full symbol tables usually aren't loaded into memory, so on
+//! Unix [`Image::current`] maps the executable's file and parses that. PE
+//! executables don't have a symbol table (MSVC keeps symbols in a separate PDB
+//! file), so on Windows only exported symbols are listed.
+//!
+//! Addresses are adjusted for where the image was actually loaded, so they can
+//! be compared with pointers at runtime. [`Image::parse`] can also parse an
+//! image that isn't running, like a plugin that hasn't been loaded yet.
+
+use {
+	crate::{
+		ffi::CStr,
+		io::{DecodeError, Decoder, Endian},
+		lang::null_ptr_mut,
+		rt::{os, proc::cached},
+		text::{Display, str_from_utf8, write},
+	},
+	core::sync::atomic::AtomicPtr,
+};
+
+/// The file format of an image.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Format {
+	Elf,
+	MachO,
+	Pe,
+}
+
+/// A section of an image, like `.text` or `.data`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Section<'a> {
+	/// The section's name, e.g. `.text` (ELF and PE) or `__text` (Mach-O).
+	pub name: &'a str,
+	/// The segment the section belongs to, e.g. `__TEXT`. Only Mach-O has
+	/// these, so this is empty in other formats.
+	pub segment: &'a str,
+	/// Where the section is in memory, or 0 if it isn't loaded (e.g. debug
+	/// info).
+	pub address: usize,
+	/// How many bytes the section takes up in memory.
+	pub size: usize,
+	/// The section's contents in the image, or `None` if it doesn't have any
+	/// (e.g. `.bss`, which is zeroed when the image is loaded).
+	pub data: Option<&'a [u8]>,
+}
+
+/// A function or static in an image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Symbol<'a> {
+	/// The symbol's name, which is mangled for Rust symbols. Mach-O's
+	/// leading underscore is removed, so names match the ones `dladdr`
+	/// returns.
+	pub name: &'a CStr,
+	pub address: usize,
+	/// How many bytes the symbol covers, or 0 if that isn't known. Mach-O
+	/// doesn't record sizes, so they're estimated from where the next symbol
+	/// starts.
+	pub size: usize,
+}
+
+/// The parsed headers of an executable or library. See the
+/// [module-level docs](self).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Image<'a> {
+	format: Format,
+	sections: Vec<Section<'a>>,
+	/// Sorted by address.
+	symbols: Vec<Symbol<'a>>,
+}
+impl Image<'static> {
+	/// Parses the running executable's image. It's parsed the first time this
+	/// is called, and cached for the rest of the program. On Unix, the
+	/// executable's file stays mapped into memory after that.
+	pub fn current() -> Result<&'static Self, ImageError> {
+		cached(&CURRENT, load_current).as_ref().map_err(|err| *err)
+	}
+}
+impl<'a> Image<'a> {
+	/// Parses an image from the contents of its file. `slide` is added to
+	/// every address, so it should be how far the image was loaded from the
+	/// addresses it was linked at, or 0 to keep those addresses.
+	pub fn parse(data: &'a [u8], slide: usize) -> Result<Self, ImageError> {
+		let image = match data {
+			[0x7f, b'E', b'L', b'F', ..] => elf::parse(data)?,
+			[0xcf, 0xfa, 0xed, 0xfe, ..] | [0xca, 0xfe, 0xba, 0xbe, ..] => macho::parse(data)?,
+			[b'M', b'Z', ..] => pe::parse(data, false)?,
+			_ => return Err(ImageError::Unsupported),
+		};
+		Ok(image.relocate(slide))
+	}
+
+	pub fn format(&self) -> Format {
+		self.format
+	}
+	/// Every section in the image, in the order its headers list them.
+	pub fn sections(&self) -> &[Section<'a>] {
+		&self.sections
+	}
+	/// The first section called `name`.
+	pub fn section(&self, name: &str) -> Option<&Section<'a>> {
+		self.sections.iter().find(|section| section.name == name)
+	}
+	/// Every defined function and static in the image's symbol table, sorted
+	/// by address.
+	pub fn symbols(&self) -> &[Symbol<'a>] {
+		&self.symbols
+	}
+	/// The first symbol called `name`.
+	pub fn symbol(&self, name: &CStr) -> Option<&Symbol<'a>> {
+		self.symbols.iter().find(|symbol| symbol.name == name)
+	}
+	/// The symbol that contains `address`. Symbols without a known size never
+	/// match.
+	pub fn symbol_at(&self, address: usize) -> Option<&Symbol<'a>> {
+		let end = self
+			.symbols
+			.partition_point(|symbol| symbol.address <= address);
+		let below = &self.symbols[..end];
+		let start = below.last()?.address;
+		// Several symbols can start at the same address, with different sizes
+		below
+			.iter()
+			.rev()
+			.take_while(|symbol| symbol.address == start)
+			.find(|symbol| address - symbol.address < symbol.size)
+	}
+
+	/// Moves every address by `slide`, then sorts the symbols.
+	fn relocate(mut self, slide: usize) -> Self {
+		for section in &mut self.sections {
+			if section.address != 0 {
+				section.address = section.address.wrapping_add(slide);
+			}
+		}
+		for symbol in &mut self.symbols {
+			symbol.address = symbol.address.wrapping_add(slide);
+		}
+		self.symbols.sort_by_key(|symbol| symbol.address);
+
+		if self.format == Format::MachO {
+			for idx in 0..self.symbols.len() {
+				let address = self.symbols[idx].address;
+				if let Some(next) = self.symbols[idx..]
+					.iter()
+					.map(|symbol| symbol.address)
+					.find(|next| *next > address)
+				{
+					self.symbols[idx].size = next - address;
+				}
+			}
+		}
+
+		self
+	}
+}
+
+static CURRENT: AtomicPtr<Result<Image<'static>, ImageError>> = AtomicPtr::new(null_ptr_mut());
+
+#[cfg(linux)]
+fn load_current() -> Result<Image<'static>, ImageError> {
+	let data = map_file(c"/proc/self/exe")?;
+	// The kernel says where the program headers were loaded, which gives
+	// the slide when compared with where they were linked
+	let headers = os::unix::getauxval(libc::AT_PHDR) as usize;
+	let slide = headers.wrapping_sub(elf::program_headers_address(data)?);
+	Image::parse(data, slide)
+}
+#[cfg(macos)]
+fn load_current() -> Result<Image<'static>, ImageError> {
+	let path = os::unix::_dyld_get_image_name(0).ok_or(ImageError::Unsupported)?;
+	let data = map_file(unsafe { CStr::from_ptr(path.as_ptr()) })?;
+	Image::parse(data, os::unix::_dyld_get_image_vmaddr_slide(0) as usize)
+}
+#[cfg(windows)]
+fn load_current() -> Result<Image<'static>, ImageError> {
+	let base = unsafe { os::win32::GetModuleHandleW(None) }.ok_or_else(os::Error::last)?;
+	// The headers are loaded along with the rest of the image, so it can be
+	// parsed in place
+	let headers = unsafe { core::slice::from_raw_parts(base.as_ptr().cast::<u8>(), pe::MIN_LEN) };
+	let (link_base, len) = pe::base_and_len(headers)?;
+	let data = unsafe { core::slice::from_raw_parts(base.as_ptr().cast::<u8>(), len) };
+	let slide = (base.as_ptr() as usize).wrapping_sub(link_base);
+	Ok(pe::parse(data, true)?.relocate(slide))
+}
+#[cfg(not(any(linux, macos, windows)))]
+fn load_current() -> Result<Image<'static>, ImageError> {
+	Err(ImageError::Unsupported)
+}
+
+/// Maps the file at `path` into memory, read-only. It's never unmapped.
+#[cfg(unix)]
+fn map_file(path: &CStr) -> Result<&'static [u8], os::Error> {
+	use os::unix::{self, OpenFlags};
+
+	let fd = unsafe { unix::open(path.as_ptr(), OpenFlags::RDONLY | OpenFlags::CLOEXEC) };
+	if fd.as_raw() == -1 {
+		return Err(os::Error::last());
+	}
+
+	let mut stat = MaybeUninit::<libc::stat>::uninit();
+	let len = if unsafe { unix::fstat(fd, NonNull::from(&mut stat).cast()) } == 0 {
+		Ok(unsafe { stat.assume_init() }.st_size as usize)
+	} else {
+		Err(os::Error::last())
+	};
+	let ptr = len.map(|len| {
+		unix::mmap(
+			None,
+			len,
+			libc::PROT_READ,
+			libc::MAP_PRIVATE,
+			fd.as_raw(),
+			0,
+		)
+	});
+	// The mapping stays valid after the file is closed
+	unsafe { unix::close(fd) };
+
+	let (len, ptr) = (len?, ptr?);
+	if ptr == libc::MAP_FAILED {
+		return Err(os::Error::last());
+	}
+	Ok(unsafe { core::slice::from_raw_parts(ptr.cast::<u8>(), len) })
+}
+
+/// Reads the nul-terminated string at `offset` in `data`.
+fn c_str_at(data: &[u8], offset: usize) -> Result<&CStr, DecodeError> {
+	let mut decoder = Decoder::new(data);
+	decoder.seek(offset)?;
+	let len = decoder.until(0)?.len();
+	Ok(CStr::from_bytes_with_nul(&data[offset..=offset + len]).unwrap())
+}
+/// Like [`c_str_at`], but for text that has to be UTF-8.
+fn str_at(data: &[u8], offset: usize) -> Result<&str, DecodeError> {
+	let text = c_str_at(data, offset)?;
+	text.to_str()
+		.map_err(|_| DecodeError::InvalidUtf8 { position: offset })
+}
+/// Reads a name padded with nul bytes to a fixed length, like a Mach-O
+/// section name.
+fn padded_str<'a>(decoder: &mut Decoder<'a>, len: usize) -> Result<&'a str, DecodeError> {
+	let position = decoder.position();
+	let bytes = decoder.bytes(len)?;
+	let len = bytes.iter().position(|byte| *byte == 0).unwrap_or(len);
+	str_from_utf8(&bytes[..len]).map_err(|_| DecodeError::InvalidUtf8 { position })
+}
+/// `data[offset..offset + len]`, if it's in bounds.
+fn slice(data: &[u8], offset: u64, len: u64) -> Option<&[u8]> {
+	let start = usize::try_from(offset).ok()?;
+	data.get(start..start.checked_add(usize::try_from(len).ok()?)?)
+}
+
+mod elf {
+	use super::*;
+
+	const SECTION_SYMBOLS: u32 = 2;
+	const SECTION_NO_BITS: u32 = 8;
+	const SECTION_DYNAMIC_SYMBOLS: u32 = 11;
+	const SEGMENT_LOAD: u32 = 1;
+	const SEGMENT_HEADERS: u32 = 6;
+	const SYMBOL_OBJECT: u8 = 1;
+	const SYMBOL_FUNCTION: u8 = 2;
+	const SECTION_INDEX_UNDEFINED: u16 = 0;
+	const SECTION_INDEX_ABSOLUTE: u16 = 0xfff1;
+	const SECTION_INDEX_EXTENDED: u16 = 0xffff;
+
+	/// How to read an ELF file's fields, which depend on its class (32 or
+	/// 64-bit) and byte order.
+	#[derive(Clone, Copy)]
+	struct Layout {
+		wide: bool,
+		endian: Endian,
+	}
+	impl Layout {
+		fn new(data: &[u8]) -> Result<Self, ImageError> {
+			let mut decoder = Decoder::new(data);
+			decoder.skip(4)?;
+			let wide = match decoder.u8()? {
+				1 => false,
+				2 => true,
+				_ => return Err(ImageError::Unsupported),
+			};
+			let endian = match decoder.u8()? {
+				1 => Endian::Little,
+				2 => Endian::Big,
+				_ => return Err(ImageError::Unsupported),
+			};
+			Ok(Self { wide, endian })
+		}
+
+		fn u16(self, decoder: &mut Decoder<'_>) -> Result<u16, DecodeError> {
+			decoder.int(self.endian)
+		}
+		fn u32(self, decoder: &mut Decoder<'_>) -> Result<u32, DecodeError> {
+			decoder.int(self.endian)
+		}
+		/// Reads an address-sized field.
+		fn word(self, decoder: &mut Decoder<'_>) -> Result<u64, DecodeError> {
+			if self.wide {
+				decoder.int(self.endian)
+			} else {
+				decoder.int::<u32>(self.endian).map(u64::from)
+			}
+		}
+	}
+
+	struct Header {
+		program_headers: u64,
+		program_header_count: u16,
+		program_header_size: u16,
+		section_headers: u64,
+		section_count: u16,
+		section_header_size: u16,
+		names_index: u16,
+	}
+	impl Header {
+		fn parse(data: &[u8], layout: Layout) -> Result<Self, DecodeError> {
+			let mut decoder = Decoder::new(data);
+			decoder.seek(24)?;
+			let _entry = layout.word(&mut decoder)?;
+			let program_headers = layout.word(&mut decoder)?;
+			let section_headers = layout.word(&mut decoder)?;
+			// Flags and the header's own size
+			decoder.skip(6)?;
+			Ok(Self {
+				program_headers,
+				program_header_size: layout.u16(&mut decoder)?,
+				program_header_count: layout.u16(&mut decoder)?,
+				section_headers,
+				section_header_size: layout.u16(&mut decoder)?,
+				section_count: layout.u16(&mut decoder)?,
+				names_index: layout.u16(&mut decoder)?,
+			})
+		}
+	}
+
+	struct SectionHeader {
+		name: u32,
+		kind: u32,
+		address: u64,
+		offset: u64,
+		size: u64,
+		link: u32,
+		entry_size: u64,
+	}
+	impl SectionHeader {
+		fn parse(
+			data: &[u8],
+			layout: Layout,
+			header: &Header,
+			idx: usize,
+		) -> Result<Self, DecodeError> {
+			let mut decoder = Decoder::new(data);
+			let offset =
+				header.section_headers as usize + idx * header.section_header_size as usize;
+			decoder.seek(offset)?;
+			let name = layout.u32(&mut decoder)?;
+			let kind = layout.u32(&mut decoder)?;
+			let _flags = layout.word(&mut decoder)?;
+			let address = layout.word(&mut decoder)?;
+			let offset = layout.word(&mut decoder)?;
+			let size = layout.word(&mut decoder)?;
+			let link = layout.u32(&mut decoder)?;
+			let _info = layout.u32(&mut decoder)?;
+			let _align = layout.word(&mut decoder)?;
+			let entry_size = layout.word(&mut decoder)?;
+			Ok(Self {
+				name,
+				kind,
+				address,
+				offset,
+				size,
+				link,
+				entry_size,
+			})
+		}
+	}
+
+	pub fn parse(data: &[u8]) -> Result<Image<'_>, ImageError> {
+		let layout = Layout::new(data)?;
+		let header = Header::parse(data, layout)?;
+		if header.section_headers == 0 {
+			return Ok(Image {
+				format: Format::Elf,
+				sections: Vec::new(),
+				symbols: Vec::new(),
+			});
+		}
+
+		// Files with too many sections for the header's fields store the
+		// real values in the first section header
+		let first = SectionHeader::parse(data, layout, &header, 0)?;
+		let count = match header.section_count {
+			0 => first.size as usize,
+			count => count as usize,
+		};
+		let names_index = match header.names_index {
+			SECTION_INDEX_EXTENDED => first.link as usize,
+			idx => idx as usize,
+		};
+		let headers = (0..count)
+			.map(|idx| SectionHeader::parse(data, layout, &header, idx))
+			.collect::<Result<Vec<_>, _>>()?;
+		let names = headers
+			.get(names_index)
+			.ok_or(DecodeError::InvalidValue { position: 0 })?;
+
+		let mut sections = Vec::with_capacity(count);
+		for section in &headers {
+			sections.push(Section {
+				name: str_at(data, (names.offset + u64::from(section.name)) as usize)?,
+				segment: "",
+				address: section.address as usize,
+				size: section.size as usize,
+				data: if section.kind == SECTION_NO_BITS {
+					None
+				} else {
+					slice(data, section.offset, section.size)
+				},
+			});
+		}
+
+		// Stripped executables only have the dynamic symbols
+		let table = headers
+			.iter()
+			.find(|section| section.kind == SECTION_SYMBOLS)
+			.or_else(|| {
+				headers
+					.iter()
+					.find(|section| section.kind == SECTION_DYNAMIC_SYMBOLS)
+			});
+		let symbols = match table {
+			Some(table) => symbols(data, layout, table, &headers)?,
+			None => Vec::new(),
+		};
+
+		Ok(Image {
+			format: Format::Elf,
+			sections,
+			symbols,
+		})
+	}
+
+	fn symbols<'a>(
+		data: &'a [u8],
+		layout: Layout,
+		table: &SectionHeader,
+		headers: &[SectionHeader],
+	) -> Result<Vec<Symbol<'a>>, DecodeError> {
+		let names = headers
+			.get(table.link as usize)
+			.ok_or(DecodeError::InvalidValue { position: 0 })?;
+		let entry_size = match table.entry_size {
+			0 if layout.wide => 24,
+			0 => 16,
+			size => size,
+		};
+
+		let mut symbols = Vec::new();
+		for idx in 0..table.size / entry_size {
+			let mut decoder = Decoder::new(data);
+			decoder.seek((table.offset + idx * entry_size) as usize)?;
+			let name = layout.u32(&mut decoder)?;
+			// The fields are in a different order in 64-bit files
+			let (info, section, address, size) = if layout.wide {
+				let info = decoder.u8()?;
+				let _other = decoder.u8()?;
+				let section = layout.u16(&mut decoder)?;
+				(
+					info,
+					section,
+					layout.word(&mut decoder)?,
+					layout.word(&mut decoder)?,
+				)
+			} else {
+				let address = layout.word(&mut decoder)?;
+				let size = layout.word(&mut decoder)?;
+				let info = decoder.u8()?;
+				let _other = decoder.u8()?;
+				(info, layout.u16(&mut decoder)?, address, size)
+			};
+
+			let kind = info & 0xf;
+			if name == 0
+				|| !matches!(kind, SYMBOL_OBJECT | SYMBOL_FUNCTION)
+				|| matches!(section, SECTION_INDEX_UNDEFINED | SECTION_INDEX_ABSOLUTE)
+			{
+				continue;
+			}
+			symbols.push(Symbol {
+				name: c_str_at(data, (names.offset + u64::from(name)) as usize)?,
+				address: address as usize,
+				size: size as usize,
+			});
+		}
+		Ok(symbols)
+	}
+
+	/// Where the program headers are when the image is loaded at the address
+	/// it was linked at.
+	#[cfg_attr(not(linux), allow(dead_code))]
+	pub fn program_headers_address(data: &[u8]) -> Result<usize, ImageError> {
+		let layout = Layout::new(data)?;
+		let header = Header::parse(data, layout)?;
+
+		let mut load_address = None;
+		for idx in 0..header.program_header_count as usize {
+			let mut decoder = Decoder::new(data);
+			decoder.seek(
+				header.program_headers as usize + idx * header.program_header_size as usize,
+			)?;
+			let kind = layout.u32(&mut decoder)?;
+			if layout.wide {
+				let _flags = layout.u32(&mut decoder)?;
+			}
+			let offset = layout.word(&mut decoder)?;
+			let address = layout.word(&mut decoder)?;
+			let _physical_address = layout.word(&mut decoder)?;
+			let file_size = layout.word(&mut decoder)?;
+
+			match kind {
+				SEGMENT_HEADERS => return Ok(address as usize),
+				// Otherwise, the headers are part of whichever segment loads
+				// their part of the file
+				SEGMENT_LOAD
+					if load_address.is_none()
+						&& (offset..offset + file_size).contains(&header.program_headers) =>
+				{
+					load_address = Some(address + header.program_headers - offset);
+				}
+				_ => {}
+			}
+		}
+		load_address
+			.map(|address| address as usize)
+			.ok_or(ImageError::Unsupported)
+	}
+}
+
+mod macho {
+	use super::*;
+
+	const FAT_MAGIC: u32 = 0xcafe_babe;
+	#[cfg(target_arch = "aarch64")]
+	const CPU_TYPE: u32 = 0x0100_000c;
+	#[cfg(not(target_arch = "aarch64"))]
+	const CPU_TYPE: u32 = 0x0100_0007;
+	const COMMAND_SYMBOL_TABLE: u32 = 0x2;
+	const COMMAND_SEGMENT: u32 = 0x19;
+	const SECTION_ZERO_FILL: [u32; 3] = [0x1, 0xc, 0x12];
+	const SYMBOL_DEBUG: u8 = 0xe0;
+	const SYMBOL_KIND: u8 = 0x0e;
+	const SYMBOL_IN_SECTION: u8 = 0x0e;
+
+	pub fn parse(data: &[u8]) -> Result<Image<'_>, ImageError> {
+		let mut decoder = Decoder::new(data);
+		if decoder.be::<u32>()? != FAT_MAGIC {
+			return parse_thin(data);
+		}
+
+		// Universal binaries contain an image for each architecture; only
+		// the current one's can be running
+		let count = decoder.be::<u32>()?;
+		for _ in 0..count {
+			let cpu_type = decoder.be::<u32>()?;
+			let _cpu_subtype = decoder.be::<u32>()?;
+			let offset = decoder.be::<u32>()?;
+			let size = decoder.be::<u32>()?;
+			let _align = decoder.be::<u32>()?;
+			if cpu_type == CPU_TYPE {
+				let image = slice(data, offset.into(), size.into())
+					.ok_or(DecodeError::InvalidValue { position: 0 })?;
+				return parse_thin(image);
+			}
+		}
+		Err(ImageError::Unsupported)
+	}
+
+	fn parse_thin(data: &[u8]) -> Result<Image<'_>, ImageError> {
+		let mut decoder = Decoder::new(data);
+		if decoder.le::<u32>()? != 0xfeed_facf {
+			// 32-bit or big-endian, neither of which macOS runs anymore
+			return Err(ImageError::Unsupported);
+		}
+		decoder.skip(12)?;
+		let command_count = decoder.le::<u32>()?;
+		decoder.skip(12)?;
+
+		let mut sections = Vec::new();
+		let mut symbols = Vec::new();
+		for _ in 0..command_count {
+			let kind = decoder.le::<u32>()?;
+			let size = decoder.le::<u32>()?;
+			let mut command = decoder.sub((size as usize).saturating_sub(8))?;
+			match kind {
+				COMMAND_SEGMENT => {
+					command.skip(16 + 4 * 8 + 2 * 4)?;
+					let section_count = command.le::<u32>()?;
+					command.skip(4)?;
+					for _ in 0..section_count {
+						let name = padded_str(&mut command, 16)?;
+						let segment = padded_str(&mut command, 16)?;
+						let address = command.le::<u64>()?;
+						let size = command.le::<u64>()?;
+						let offset = command.le::<u32>()?;
+						command.skip(12)?;
+						let flags = command.le::<u32>()?;
+						command.skip(12)?;
+
+						sections.push(Section {
+							name,
+							segment,
+							address: address as usize,
+							size: size as usize,
+							data: if SECTION_ZERO_FILL.contains(&(flags & 0xff)) {
+								None
+							} else {
+								slice(data, offset.into(), size)
+							},
+						});
+					}
+				}
+				COMMAND_SYMBOL_TABLE => {
+					let offset = command.le::<u32>()? as usize;
+					let count = command.le::<u32>()? as usize;
+					let names = command.le::<u32>()? as usize;
+					symbols = symbol_table(data, offset, count, names)?;
+				}
+				_ => {}
+			}
+		}
+
+		Ok(Image {
+			format: Format::MachO,
+			sections,
+			symbols,
+		})
+	}
+
+	fn symbol_table(
+		data: &[u8],
+		offset: usize,
+		count: usize,
+		names: usize,
+	) -> Result<Vec<Symbol<'_>>, DecodeError> {
+		let mut decoder = Decoder::new(data);
+		decoder.seek(offset)?;
+		let mut symbols = Vec::new();
+		for _ in 0..count {
+			let name = decoder.le::<u32>()? as usize;
+			let kind = decoder.u8()?;
+			let _section = decoder.u8()?;
+			let _description = decoder.le::<u16>()?;
+			let address = decoder.le::<u64>()?;
+			if name == 0 || kind & SYMBOL_DEBUG != 0 || kind & SYMBOL_KIND != SYMBOL_IN_SECTION {
+				continue;
+			}
+
+			let mut name = names + name;
+			if data.get(name) == Some(&b'_') {
+				name += 1;
+			}
+			symbols.push(Symbol {
+				name: c_str_at(data, name)?,
+				address: address as usize,
+				size: 0,
+			});
+		}
+		Ok(symbols)
+	}
+}
+
+mod pe {
+	use super::*;
+
+	/// Enough bytes to read the headers [`base_and_len`] needs.
+	#[cfg_attr(not(windows), allow(dead_code))]
+	pub const MIN_LEN: usize = 512;
+	const PE32_PLUS: u16 = 0x20b;
+
+	struct Headers {
+		/// Where the COFF header starts.
+		coff: usize,
+		section_count: u16,
+		optional_len: u16,
+		wide: bool,
+		/// The address the image was linked to be loaded at.
+		base: u64,
+	}
+	impl Headers {
+		fn parse(data: &[u8]) -> Result<Self, ImageError> {
+			let mut decoder = Decoder::new(data);
+			decoder.seek(0x3c)?;
+			let signature = decoder.le::<u32>()? as usize;
+			decoder.seek(signature)?;
+			if decoder.bytes(4)? != b"PE\0\0" {
+				return Err(ImageError::Unsupported);
+			}
+			let coff = decoder.position();
+			decoder.skip(2)?;
+			let section_count = decoder.le::<u16>()?;
+			decoder.skip(12)?;
+			let optional_len = decoder.le::<u16>()?;
+			decoder.skip(2)?;
+
+			let wide = decoder.le::<u16>()? == PE32_PLUS;
+			let base = if wide {
+				decoder.skip(22)?;
+				decoder.le::<u64>()?
+			} else {
+				decoder.skip(26)?;
+				decoder.le::<u32>()?.into()
+			};
+			Ok(Self {
+				coff,
+				section_count,
+				optional_len,
+				wide,
+				base,
+			})
+		}
+	}
+
+	/// The address a PE image was linked at, and how many bytes it takes up
+	/// once it's loaded.
+	#[cfg_attr(not(windows), allow(dead_code))]
+	pub fn base_and_len(headers: &[u8]) -> Result<(usize, usize), ImageError> {
+		let parsed = Headers::parse(headers)?;
+		let mut decoder = Decoder::new(headers);
+		// `SizeOfImage` is at the same offset in PE32 and PE32+
+		decoder.seek(parsed.coff + 20 + 56)?;
+		Ok((parsed.base as usize, decoder.le::<u32>()? as usize))
+	}
+
+	/// Parses a PE image. If it's `loaded`, `data` is the image as it's
+	/// laid out in memory, instead of its file.
+	pub fn parse(data: &[u8], loaded: bool) -> Result<Image<'_>, ImageError> {
+		let headers = Headers::parse(data)?;
+		let mut decoder = Decoder::new(data);
+		decoder.seek(headers.coff + 20)?;
+		let mut optional = decoder.sub(headers.optional_len as usize)?;
+
+		// (name, virtual address, virtual size, file offset, file size)
+		let mut raw_sections = Vec::with_capacity(headers.section_count as usize);
+		for _ in 0..headers.section_count {
+			let name = padded_str(&mut decoder, 8)?;
+			let virtual_size = decoder.le::<u32>()?;
+			let address = decoder.le::<u32>()?;
+			let file_size = decoder.le::<u32>()?;
+			let offset = decoder.le::<u32>()?;
+			decoder.skip(16)?;
+			raw_sections.push((name, address, virtual_size, offset, file_size));
+		}
+		// Converts an address relative to the image's base to an offset in
+		// `data`
+		let offset_of = |address: u32| -> Option<usize> {
+			if loaded {
+				return Some(address as usize);
+			}
+			raw_sections
+				.iter()
+				.find_map(|&(_, start, size, offset, _)| {
+					(start..start.checked_add(size)?)
+						.contains(&address)
+						.then(|| (offset + (address - start)) as usize)
+				})
+		};
+
+		let sections = raw_sections
+			.iter()
+			.map(
+				|&(name, address, virtual_size, offset, file_size)| Section {
+					name,
+					segment: "",
+					address: (headers.base + u64::from(address)) as usize,
+					size: virtual_size as usize,
+					data: if loaded {
+						slice(data, address.into(), virtual_size.into())
+					} else if file_size == 0 {
+						None
+					} else {
+						slice(data, offset.into(), file_size.min(virtual_size).into())
+					},
+				},
+			)
+			.collect();
+
+		// The export table's location is the first data directory
+		optional.skip(if headers.wide { 112 } else { 96 })?;
+		let exports = optional.le::<u32>().unwrap_or(0);
+		let exports_len = optional.le::<u32>().unwrap_or(0);
+		let mut symbols = Vec::new();
+		if exports != 0 {
+			let invalid = DecodeError::InvalidValue { position: 0 };
+			let mut table = Decoder::new(data);
+			table.seek(offset_of(exports).ok_or(invalid)?)?;
+			table.skip(20)?;
+			let _function_count = table.le::<u32>()?;
+			let name_count = table.le::<u32>()?;
+			let functions = offset_of(table.le::<u32>()?).ok_or(invalid)?;
+			let names = offset_of(table.le::<u32>()?).ok_or(invalid)?;
+			let ordinals = offset_of(table.le::<u32>()?).ok_or(invalid)?;
+
+			for idx in 0..name_count as usize {
+				let mut decoder = Decoder::new(data);
+				decoder.seek(names + idx * 4)?;
+				let name = offset_of(decoder.le::<u32>()?).ok_or(invalid)?;
+				decoder.seek(ordinals + idx * 2)?;
+				let ordinal = decoder.le::<u16>()? as usize;
+				decoder.seek(functions + ordinal * 4)?;
+				let address = decoder.le::<u32>()?;
+				// Forwarded exports point to the name of a function in
+				// another library, which is stored in the export table
+				if (exports..exports + exports_len).contains(&address) {
+					continue;
+				}
+
+				symbols.push(Symbol {
+					name: c_str_at(data, name)?,
+					address: (headers.base + u64::from(address)) as usize,
+					size: 0,
+				});
+			}
+		}
+
+		Ok(Image {
+			format: Format::Pe,
+			sections,
+			symbols,
+		})
+	}
+}
+
+/// An error from parsing an image.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageError {
+	/// The image's format or architecture isn't supported.
+	Unsupported,
+	/// The image's headers were invalid.
+	Malformed(DecodeError),
+	Os(os::Error),
+}
+impl From<DecodeError> for ImageError {
+	fn from(err: DecodeError) -> Self {
+		Self::Malformed(err)
+	}
+}
+impl From<os::Error> for ImageError {
+	fn from(err: os::Error) -> Self {
+		Self::Os(err)
+	}
+}
+impl Display for ImageError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Unsupported => f.write_str("unsupported image format"),
+			Self::Malformed(err) => write!(f, "malformed image: {err}"),
+			Self::Os(err) => write!(f, "{err}"),
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[inline(never)]
+	fn marker() -> usize {
+		marker as *const () as usize
+	}
+
+	#[test]
+	fn current() {
+		let image = Image::current().unwrap();
+		let address = marker();
+
+		let text = image
+			.sections()
+			.iter()
+			.find(|section| section.name == ".text" || section.name == "__text")
+			.unwrap();
+		assert!((text.address..text.address + text.size).contains(&address));
+		assert!(text.data.is_some());
+
+		// Windows only has exported symbols
+		if image.format() != Format::Pe {
+			let symbol = image.symbol_at(address).unwrap();
+			assert_eq!(symbol.address, address);
+			let name = symbol.name.to_str().unwrap();
+			assert!(name.contains("6marker"), "{name}");
+			assert_eq!(image.symbol(symbol.name), Some(symbol));
+		}
+	}
+
+	#[test]
+	fn invalid() {
+		assert_eq!(
+			Image::parse(b"not an image", 0),
+			Err(ImageError::Unsupported)
+		);
+		assert!(matches!(
+			Image::parse(b"\x7fELF\x02\x01\x01", 0),
+			Err(ImageError::Malformed(DecodeError::UnexpectedEnd { .. }))
+		));
+		assert_eq!(
+			Image::parse(b"\x7fELF\x03\x01\x01", 0),
+			Err(ImageError::Unsupported)
+		);
+	}
+}
@@ -1,6 +1,19 @@
 //! Items for working directly with memory and allocations.
 
-use crate::{lang::*, rt::os};
+use {
+	crate::{
+		crypto::hash::DefaultHashBuilder,
+		data_structures::{BTreeMap, BTreeSet, HashMap, HashSet},
+		lang::*,
+		rt::os,
+		text::FormatArgs,
+	},
+	core::{
+		env,
+		hint::spin_loop,
+		sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering as AtomicOrdering},
+	},
+};
 
 //
 //
@@ -94,7 +107,7 @@ impl ReservedMemory {
 	pub unsafe fn select_unchecked(self, offset: MemoryAmount, len: MemoryAmount) -> Self {
 		safety_assert!(
 			unsafe { self.base_ptr.byte_add(self.amount.amount_bytes()) }
-				> unsafe { self.base_ptr.byte_add((offset + len).amount_bytes()) }
+				>= unsafe { self.base_ptr.byte_add((offset + len).amount_bytes()) }
 		);
 
 		Self {
@@ -268,6 +281,37 @@ pub unsafe fn uncommit(mem: ReservedMemory) {
 	compile_error!("unimplemented on this operating system");
 }
 
+/// Maps a file into reserved memory, so reading and writing the memory reads
+/// and writes the file. Other processes that map the same file see the same
+/// memory, which makes this useful for sharing memory with
+/// [`MemoryFile`](os::unix::MemoryFile)s.
+///
+/// Mapping over memory that's already mapped replaces the old mapping, so a
+/// file can be remapped in place after it grows. Like committed memory, the
+/// mapping is released with [`unreserve`].
+///
+/// Errors if the OS fails to map the file, or if the `mem` argument doesn't
+/// actually point to reserved memory.
+///
+///
+/// # Safety
+///
+/// Any data in `mem` is replaced with the file's contents. Accessing memory
+/// past the end of the file crashes the program with `SIGBUS`, so the file
+/// must not shrink while the mapping is in use.
+#[cfg(unix)]
+pub unsafe fn map_file(mem: ReservedMemory, fd: os::unix::FileDescriptor) -> Result<(), ()> {
+	let ptr = os::unix::mmap(
+		Some(mem.base_ptr.cast()),
+		mem.amount.amount_bytes(),
+		libc::PROT_READ | libc::PROT_WRITE,
+		libc::MAP_SHARED | libc::MAP_FIXED,
+		fd.as_raw(),
+		0,
+	);
+	if ptr == libc::MAP_FAILED { Err(()) } else { Ok(()) }
+}
+
 /// Allocates read/write memory with standard operating system APIs. Returns an
 /// error if the OS fails to allocate.
 pub fn allocate(amount: MemoryAmount) -> Result<NonNull<()>, ()> {
@@ -359,7 +403,9 @@ unsafe impl Allocator for OsAllocator {
 }
 unsafe impl GlobalAlloc for OsAllocator {
 	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-		self.allocate(layout).unwrap().as_ptr().cast()
+		// Returning null makes the caller call the alloc error handler
+		self.allocate(layout)
+			.map_or(null_ptr_mut(), |ptr| ptr.as_ptr().cast())
 	}
 	// Windows: VirtualAlloc zeroes memory by default
 	// Unix: Using MAP_ANONYMOUS zeroes the memory by default
@@ -373,6 +419,61 @@ unsafe impl GlobalAlloc for OsAllocator {
 	}
 }
 
+/// A function called when an allocation fails and the program is about to
+/// exit. See [`set_alloc_error_hook`].
+pub type AllocErrorHook = fn(Layout);
+
+/// The currently registered alloc error hook, or null if there isn't one.
+static ALLOC_ERROR_HOOK: AtomicPtr<()> = AtomicPtr::new(null_ptr_mut());
+/// Set while the alloc error handler is running, to detect allocations failing
+/// inside it.
+static IN_ALLOC_ERROR_HANDLER: AtomicBool = AtomicBool::new(false);
+
+/// Registers a function that's called when an allocation fails, replacing the
+/// previously registered hook (if there was one). This is Crux's version of
+/// `std::alloc::set_alloc_error_hook`.
+///
+/// Allocation failures are usually fatal, so the hook is called by
+/// [`alloc_error_handler`](crate::rt::alloc_error_handler) right before the
+/// program exits. It's passed the layout that couldn't be allocated, and is a
+/// good place to log how much memory the program was using:
+///
+/// ```rs
+/// rt::set_alloc_error_hook(|_| {
+///     fatal!("Out of memory; {} left in the frame arena", FRAME_ARENA.available_total_memory());
+/// });
+/// ```
+///
+/// Memory is probably scarce when the hook runs, so it should allocate as
+/// little as possible. If an allocation fails while the hook is running, the
+/// program exits without calling the hook again.
+///
+/// Note that when the `std-compat` feature is enabled, `std` provides the alloc
+/// error handler, so hooks registered here are never called.
+pub fn set_alloc_error_hook(hook: AllocErrorHook) {
+	ALLOC_ERROR_HOOK.store(hook as *mut (), AtomicOrdering::Release);
+}
+/// Unregisters the current alloc error hook and returns it, if there was one.
+pub fn take_alloc_error_hook() -> Option<AllocErrorHook> {
+	let old = ALLOC_ERROR_HOOK.swap(null_ptr_mut(), AtomicOrdering::AcqRel);
+	(!old.is_null()).then(|| unsafe { transmute::<*mut (), AllocErrorHook>(old) })
+}
+/// Calls the registered alloc error hook, if there is one. Returns `false`
+/// without calling it if an allocation already failed inside the alloc error
+/// handler, in which case the handler should exit as quickly as possible.
+pub fn call_alloc_error_hook(layout: Layout) -> bool {
+	if IN_ALLOC_ERROR_HANDLER.swap(true, AtomicOrdering::AcqRel) {
+		return false;
+	}
+
+	let hook = ALLOC_ERROR_HOOK.load(AtomicOrdering::Acquire);
+	if !hook.is_null() {
+		(unsafe { transmute::<*mut (), AllocErrorHook>(hook) })(layout);
+	}
+
+	true
+}
+
 /// Represents a state of used memory in an [`ArenaAllocator`] that the arena
 /// can later reset to. Resetting to a checkpoint assumes that any memory
 /// allocated after the checkpoint was created is now available to use again,
@@ -533,28 +634,48 @@ unsafe impl Allocator for VirtualMemoryArena {
 		let committed = self.committed.get();
 		let used = self.used.get();
 
-		let available = committed - used;
 		let needed = MemoryAmount::from(layout);
+		let padding = unsafe { self.reserved.base_ptr.byte_add(used.amount_bytes()) }
+			.cast::<u8>()
+			.align_offset(layout.align());
+		let start = used + MemoryAmount::bytes(padding);
+		let Some(end) = start.checked_add(needed) else {
+			return Err(AllocError);
+		};
 
-		if available < needed {
-			let diff = needed - available;
-			let Ok(to_commit) = self.reserved.select(committed, diff) else {
+		if end > committed {
+			if end > self.reserved.amount {
 				return Err(AllocError);
+			}
+
+			// The OS commits whole pages, and the memory being committed has
+			// to start on a page boundary, so commit every page between the
+			// end of the committed memory and the end of this allocation.
+			let page_size = page_size();
+			let committed_end =
+				unsafe { self.reserved.base_ptr.byte_add(committed.amount_bytes()) };
+			let misalignment = committed_end.addr().get() % page_size;
+			let to_commit = ReservedMemory {
+				base_ptr: unsafe { committed_end.byte_sub(misalignment) },
+				amount: (end - committed + MemoryAmount::bytes(misalignment)).align_to(page_size),
 			};
 			let Ok(()) = commit(to_commit) else {
 				return Err(AllocError);
 			};
 
-			self.committed.set(committed + diff);
+			// Rounding up to whole pages may commit memory past the end of
+			// this arena, which belongs to whatever it was split from.
+			let new_committed = committed - MemoryAmount::bytes(misalignment) + to_commit.amount;
+			self.committed.set(new_committed.min(self.reserved.amount));
 		}
 
 		let ptr = unsafe {
 			NonNull::slice_from_raw_parts(
-				self.reserved.base_ptr.byte_add(used.amount_bytes()).cast(),
+				self.reserved.base_ptr.byte_add(start.amount_bytes()).cast(),
 				needed.amount_bytes(),
 			)
 		};
-		self.used.set(used + needed);
+		self.used.set(end);
 
 		Ok(ptr)
 	}
@@ -578,6 +699,293 @@ impl Drop for VirtualMemoryArena {
 	}
 }
 
+/// The states a [`StaticArena`] can be in.
+const ARENA_UNINIT: u8 = 0;
+const ARENA_INITIALISING: u8 = 1;
+const ARENA_READY: u8 = 2;
+const ARENA_FAILED: u8 = 3;
+
+/// A [`VirtualMemoryArena`] that can be created in a `const`, and stored in a
+/// `static`.
+///
+/// Reserving memory takes a syscall, so it can't happen at compile time.
+/// Instead, the arena reserves its memory the first time it's used, or when
+/// [`StaticArena::init`] is called - usually from a startup hook, so the first
+/// allocation doesn't have to:
+///
+/// ```rs
+/// static SCRATCH: StaticArena =
+///     StaticArena::new(MemoryAmount::mebibytes(64).align_to_assumed_page());
+///
+/// hook! {
+///     event: crux::events::startup,
+///     func: |_| SCRATCH.init().unwrap(),
+///     constraints: []
+/// }
+/// ```
+///
+/// Unlike [`VirtualMemoryArena`]s, static arenas can be shared between
+/// threads, so every allocation takes a spin lock. Threads that allocate a lot
+/// at the same time are better off with an arena each.
+pub struct StaticArena {
+	to_reserve: MemoryAmount,
+	state: AtomicU8,
+	/// Held while using `arena`, since [`VirtualMemoryArena`]s aren't
+	/// thread-safe.
+	locked: AtomicBool,
+	arena: UnsafeCell<MaybeUninit<VirtualMemoryArena>>,
+}
+impl StaticArena {
+	/// Creates an arena that will reserve `to_reserve` of virtual memory when
+	/// it's initialised.
+	pub const fn new(to_reserve: MemoryAmount) -> Self {
+		Self {
+			to_reserve,
+			state: AtomicU8::new(ARENA_UNINIT),
+			locked: AtomicBool::new(false),
+			arena: UnsafeCell::new(MaybeUninit::uninit()),
+		}
+	}
+
+	/// Reserves the arena's memory, if it hasn't been reserved already. Fails
+	/// if the OS fails to reserve the memory, whether that happens now or
+	/// happened in an earlier call.
+	pub fn init(&self) -> Result<(), ()> {
+		if self
+			.state
+			.compare_exchange(
+				ARENA_UNINIT,
+				ARENA_INITIALISING,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Acquire,
+			)
+			.is_ok()
+		{
+			let (state, result) = match VirtualMemoryArena::new(self.to_reserve) {
+				Ok(arena) => {
+					unsafe { (*self.arena.get()).write(arena) };
+					(ARENA_READY, Ok(()))
+				}
+				Err(()) => (ARENA_FAILED, Err(())),
+			};
+			self.state.store(state, AtomicOrdering::Release);
+			return result;
+		}
+
+		// Another thread is initialising the arena
+		loop {
+			match self.state.load(AtomicOrdering::Acquire) {
+				ARENA_READY => return Ok(()),
+				ARENA_FAILED => return Err(()),
+				_ => spin_loop(),
+			}
+		}
+	}
+
+	/// The amount of virtual memory the arena reserves.
+	pub const fn reserved(&self) -> MemoryAmount {
+		self.to_reserve
+	}
+	/// Returns the total amount of memory this arena has left. See
+	/// [`VirtualMemoryArena::available_total_memory`].
+	pub fn available_total_memory(&self) -> MemoryAmount {
+		self.with_arena(VirtualMemoryArena::available_total_memory)
+			.unwrap_or(MemoryAmount::ZERO)
+	}
+
+	/// Initialises the arena if needed, then calls `func` with it while
+	/// holding the lock.
+	fn with_arena<R>(&self, func: impl FnOnce(&VirtualMemoryArena) -> R) -> Result<R, ()> {
+		self.init()?;
+
+		while self
+			.locked
+			.compare_exchange_weak(
+				false,
+				true,
+				AtomicOrdering::Acquire,
+				AtomicOrdering::Relaxed,
+			)
+			.is_err()
+		{
+			spin_loop();
+		}
+		let result = func(unsafe { (*self.arena.get()).assume_init_ref() });
+		self.locked.store(false, AtomicOrdering::Release);
+
+		Ok(result)
+	}
+}
+unsafe impl Sync for StaticArena {}
+unsafe impl Allocator for StaticArena {
+	fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+		self.with_arena(|arena| arena.allocate(layout))
+			.unwrap_or(Err(AllocError))
+	}
+	unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+}
+impl Drop for StaticArena {
+	fn drop(&mut self) {
+		if *self.state.get_mut() == ARENA_READY {
+			unsafe { self.arena.get_mut().assume_init_drop() };
+		}
+	}
+}
+
+/// Convenience methods for allocating values in an arena.
+///
+/// Every method allocates with a reference to the arena, so the results borrow
+/// the arena and can't outlive it. The `alloc_*` methods leak their values into
+/// the arena; they're never dropped, but their memory is reclaimed when the
+/// arena is dropped (or reset to an earlier [`ArenaCheckpoint`]).
+///
+/// ```rs
+/// let arena = VirtualMemoryArena::new(MemoryAmount::megabytes(1)).unwrap();
+/// let mut names = arena.vec();
+/// names.push(arena.alloc_str("crux"));
+/// names.push(format_in!(&arena, "crux v{}", 2));
+/// ```
+pub trait ArenaExt: Allocator + Sized {
+	/// Moves `value` into a [`Box`] allocated in this arena.
+	fn boxed<T>(&self, value: T) -> Box<T, &Self> {
+		Box::new_in(value, self)
+	}
+	/// Like [`ArenaExt::boxed`], but returns an error if the arena runs out of
+	/// memory.
+	fn try_boxed<T>(&self, value: T) -> Result<Box<T, &Self>, AllocError> {
+		Box::try_new_in(value, self)
+	}
+	/// Clones `slice` into a [`Box`] allocated in this arena.
+	fn boxed_slice<T: Clone>(&self, slice: &[T]) -> Box<[T], &Self> {
+		let mut vec = Vec::with_capacity_in(slice.len(), self);
+		vec.extend_from_slice(slice);
+		vec.into_boxed_slice()
+	}
+	/// Creates an empty [`Vec`] that allocates in this arena.
+	fn vec<T>(&self) -> Vec<T, &Self> {
+		Vec::new_in(self)
+	}
+	/// Creates a [`Vec`] that allocates in this arena, with room for
+	/// `capacity` items.
+	fn vec_with_capacity<T>(&self, capacity: usize) -> Vec<T, &Self> {
+		Vec::with_capacity_in(capacity, self)
+	}
+	/// Creates an empty [`BTreeMap`] that allocates in this arena.
+	fn btree_map<K, V>(&self) -> BTreeMap<K, V, &Self> {
+		BTreeMap::new_in(self)
+	}
+	/// Creates an empty [`BTreeSet`] that allocates in this arena.
+	fn btree_set<T>(&self) -> BTreeSet<T, &Self> {
+		BTreeSet::new_in(self)
+	}
+	/// Creates an empty [`HashMap`] that allocates in this arena.
+	fn hash_map<K, V>(&self) -> HashMap<K, V, DefaultHashBuilder, &Self> {
+		HashMap::new_in(self)
+	}
+	/// Creates a [`HashMap`] that allocates in this arena, with room for at
+	/// least `capacity` items.
+	fn hash_map_with_capacity<K, V>(
+		&self,
+		capacity: usize,
+	) -> HashMap<K, V, DefaultHashBuilder, &Self> {
+		HashMap::with_capacity_in(capacity, self)
+	}
+	/// Creates an empty [`HashSet`] that allocates in this arena.
+	fn hash_set<T: Hash + Eq>(&self) -> HashSet<T, DefaultHashBuilder, &Self> {
+		HashSet::new_in(self)
+	}
+	/// Creates a [`HashSet`] that allocates in this arena, with room for at
+	/// least `capacity` items.
+	fn hash_set_with_capacity<T: Hash + Eq>(
+		&self,
+		capacity: usize,
+	) -> HashSet<T, DefaultHashBuilder, &Self> {
+		HashSet::with_capacity_in(capacity, self)
+	}
+
+	/// Moves `value` into the arena and returns a reference to it. The value
+	/// is never dropped.
+	fn alloc<T>(&self, value: T) -> &mut T {
+		Box::leak(self.boxed(value))
+	}
+	/// Like [`ArenaExt::alloc`], but returns an error if the arena runs out of
+	/// memory.
+	fn try_alloc<T>(&self, value: T) -> Result<&mut T, AllocError> {
+		self.try_boxed(value).map(Box::leak)
+	}
+	/// Copies `string` into the arena.
+	fn alloc_str(&self, string: &str) -> &str {
+		let bytes = Box::leak(self.boxed_slice(string.as_bytes()));
+		unsafe { core::str::from_utf8_unchecked(bytes) }
+	}
+	/// Like [`ArenaExt::alloc_str`], but returns an error if the arena runs
+	/// out of memory.
+	fn try_alloc_str(&self, string: &str) -> Result<&str, AllocError> {
+		let mut bytes = Vec::new_in(self);
+		bytes
+			.try_reserve_exact(string.len())
+			.map_err(|_| AllocError)?;
+		bytes.extend_from_slice(string.as_bytes());
+		Ok(unsafe { core::str::from_utf8_unchecked(bytes.leak()) })
+	}
+	/// Formats `args` directly into the arena. See [`format_in`].
+	///
+	/// [`format_in`]: crate::text::format_in
+	fn alloc_fmt(&self, args: FormatArgs) -> &str {
+		if let Some(string) = args.as_str() {
+			return string;
+		}
+
+		struct ArenaWriter<'a, A: Allocator>(Vec<u8, &'a A>);
+		impl<A: Allocator> core::fmt::Write for ArenaWriter<'_, A> {
+			fn write_str(&mut self, string: &str) -> core::fmt::Result {
+				self.0.extend_from_slice(string.as_bytes());
+				Ok(())
+			}
+		}
+
+		let mut writer = ArenaWriter(self.vec());
+		core::fmt::write(&mut writer, args)
+			.expect("Crux: A formatting trait implementation returned an error");
+		unsafe { core::str::from_utf8_unchecked(writer.0.leak()) }
+	}
+}
+impl ArenaExt for VirtualMemoryArena {}
+impl ArenaExt for StaticArena {}
+
+/// Formats text into an arena, like [`format`], and returns it as an `&str`
+/// borrowing the arena. See [`ArenaExt::alloc_fmt`].
+///
+/// ```rs
+/// let arena = VirtualMemoryArena::new(MemoryAmount::megabytes(1)).unwrap();
+/// let greeting: &str = format_in!(&arena, "Hello, {}!", name);
+/// ```
+///
+/// [`format`]: crate::text::format
+#[macro_export]
+macro_rules! format_in {
+	($arena:expr, $($arg:tt)*) => {
+		$crate::rt::mem::ArenaExt::alloc_fmt($arena, $crate::text::format_args!($($arg)*))
+	};
+}
+
+/// Formats text into [`GLOBAL_ARENA`], like [`format_in`], and returns it as an
+/// `&'static str`. Requires the `global-arena` crate feature.
+///
+/// ```rs
+/// let label: &'static str = format_global!("Player {}", id);
+/// ```
+///
+/// [`GLOBAL_ARENA`]: crate::rt::GLOBAL_ARENA
+/// [`format_in`]: crate::text::format_in
+#[cfg(feature = "global-arena")]
+#[macro_export]
+macro_rules! format_global {
+	($($arg:tt)*) => {
+		$crate::format_in!($crate::rt::global_arena(), $($arg)*)
+	};
+}
+
 //
 //
 // Other memory utils
@@ -585,18 +993,130 @@ impl Drop for VirtualMemoryArena {
 //
 
 /// The size of a single page of memory on the current machine.
+///
+/// This is loaded at startup by [`load_page_size`] if the `probe-page-size`
+/// crate feature is enabled, and the first time it's needed otherwise.
 pub fn page_size() -> usize {
-	crate::rt::info().page_size
+	match PAGE_SIZE.load(AtomicOrdering::Relaxed) {
+		0 => probe_page_size(),
+		size => size,
+	}
+}
+/// The cached result of [`page_size`], or 0 if it hasn't been loaded yet.
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+/// Asks the OS for the page size, checks it against [`ASSUMED_PAGE_SIZE`],
+/// and caches it in [`PAGE_SIZE`].
+#[cold]
+fn probe_page_size() -> usize {
+	#[cfg(unix)]
+	let size = os::unix::sysconf(libc::_SC_PAGE_SIZE) as usize;
+	#[cfg(windows)]
+	let size = os::win32::system_info().page_size as usize;
+	#[cfg(not(supported_os))]
+	compile_error!("unimplemented on this operating system");
+
+	assert!(
+		ASSUMED_PAGE_SIZE.is_multiple_of(size),
+		"Crux: Built assuming a page size of {ASSUMED_PAGE_SIZE} bytes, but the page size is {size} bytes. Rebuild with `CRUX_ASSUMED_PAGE_SIZE={size}`",
+	);
+	// Threads that race to load it all get the same size, so it doesn't
+	// matter which store wins
+	PAGE_SIZE.store(size, AtomicOrdering::Relaxed);
+
+	size
 }
 
+/// Loads [`page_size`] at startup, so the page size is checked against
+/// [`ASSUMED_PAGE_SIZE`] before the program runs. Only used if the
+/// `probe-page-size` crate feature is enabled.
+#[cfg(feature = "probe-page-size")]
+pub fn load_page_size(_: crate::rt::StartupHookInfo) {
+	page_size();
+}
+#[cfg(feature = "probe-page-size")]
+crate::rt::hook::hook! {
+	/// See [`crate::rt::mem::load_page_size`].
+	event: crate::events::startup,
+	func: load_page_size,
+	phase: Early,
+	constraints: [
+		before(crate::hooks::startup_hook),
+	]
+}
+/// The page size Crux assumes at compile time, for sizes that have to be
+/// page-aligned in a `const`. See [`MemoryAmount::align_to_assumed_page`].
+///
+/// This is 16 KiB on Apple Silicon and 4 KiB everywhere else, and can be
+/// changed by setting `CRUX_ASSUMED_PAGE_SIZE` when building. Any multiple of
+/// the real page size works, so [`page_size`] checks that it is one.
+pub const ASSUMED_PAGE_SIZE: usize = {
+	let size = env!("CRUX_ASSUMED_PAGE_SIZE");
+	match usize::from_str_radix(size, 10) {
+		Ok(size) if size.is_power_of_two() => size,
+		_ => panic!("Crux: CRUX_ASSUMED_PAGE_SIZE must be a power of 2"),
+	}
+};
+
 mod memory_amount {
 	// declared in a separate module so the `mem` module cannot access
 	// `MemoryAmount.0`
 
-	use super::*;
+	use {
+		super::*,
+		crate::num::{ParseAmountError, format::write_amount, parse::parse_amount},
+		core::{
+			fmt::{self, Display, Formatter},
+			num::NonZero,
+		},
+	};
+
+	/// Binary units, used when displaying memory amounts.
+	const BINARY_UNITS: &[(&str, u128)] = &[
+		("B", 1),
+		("KiB", 1 << 10),
+		("MiB", 1 << 20),
+		("GiB", 1 << 30),
+		("TiB", 1 << 40),
+		("PiB", 1 << 50),
+		("EiB", 1 << 60),
+	];
+	/// Decimal units, used when displaying memory amounts with `{:#}`.
+	const DECIMAL_UNITS: &[(&str, u128)] = &[
+		("B", 1),
+		("kB", 1_000),
+		("MB", 1_000_000),
+		("GB", 1_000_000_000),
+		("TB", 1_000_000_000_000),
+		("PB", 1_000_000_000_000_000),
+		("EB", 1_000_000_000_000_000_000),
+	];
+	/// Every unit accepted when parsing memory amounts.
+	const PARSE_UNITS: &[(&str, u128)] = &[
+		("", 1),
+		("b", 1),
+		("k", 1 << 10),
+		("kib", 1 << 10),
+		("kb", 1_000),
+		("m", 1 << 20),
+		("mib", 1 << 20),
+		("mb", 1_000_000),
+		("g", 1 << 30),
+		("gib", 1 << 30),
+		("gb", 1_000_000_000),
+		("t", 1 << 40),
+		("tib", 1 << 40),
+		("tb", 1_000_000_000_000),
+	];
 
 	/// An amount of memory, with convenience initializers for various units of
 	/// memory.
+	///
+	/// Memory amounts are displayed in binary units (e.g. `1.5 MiB`), or in
+	/// decimal units with `{:#}` (e.g. `1.5 MB`). They can be parsed from
+	/// strings like `4096`, `512k`, `2G`, or `1.5 MiB`, which is handy for CLI
+	/// flags; single-letter units and units ending in `iB` are binary, and
+	/// units ending in just `B` are decimal. Units are case-insensitive.
 	#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 	pub struct MemoryAmount(usize);
 
@@ -631,16 +1151,73 @@ mod memory_amount {
 		pub fn page_align(self) -> Self {
 			self.align_to(page_size())
 		}
+		/// Aligns to [`ASSUMED_PAGE_SIZE`]. Unlike
+		/// [`MemoryAmount::page_align`], this works in `const`s, which
+		/// lets static arenas be sized at compile time.
+		pub const fn align_to_assumed_page(self) -> Self {
+			self.align_to(ASSUMED_PAGE_SIZE)
+		}
 
 		pub const fn amount_bytes(self) -> usize {
 			self.0
 		}
+
+		pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+			match self.0.checked_add(rhs.0) {
+				Some(amount) => Some(Self(amount)),
+				None => None,
+			}
+		}
+		pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+			match self.0.checked_sub(rhs.0) {
+				Some(amount) => Some(Self(amount)),
+				None => None,
+			}
+		}
+
+		/// Parses a memory amount, like `512k` or `1.5 GiB`. See
+		/// [`MemoryAmount`] for the accepted units.
+		pub fn parse(s: &str) -> Result<Self, ParseAmountError> {
+			let amount = parse_amount(s, PARSE_UNITS)?;
+			usize::try_from(amount)
+				.map(Self)
+				.map_err(|_| ParseAmountError::Overflow)
+		}
 	}
 	impl From<Layout> for MemoryAmount {
 		fn from(value: Layout) -> Self {
 			Self(value.size())
 		}
 	}
+	impl From<NonZero<usize>> for MemoryAmount {
+		fn from(value: NonZero<usize>) -> Self {
+			Self(value.get())
+		}
+	}
+	impl TryFrom<MemoryAmount> for NonZero<usize> {
+		type Error = ();
+
+		fn try_from(value: MemoryAmount) -> Result<Self, ()> {
+			NonZero::new(value.0).ok_or(())
+		}
+	}
+	impl FromStr for MemoryAmount {
+		type Err = ParseAmountError;
+
+		fn from_str(s: &str) -> Result<Self, Self::Err> {
+			Self::parse(s)
+		}
+	}
+	impl Display for MemoryAmount {
+		fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+			let units = if f.alternate() {
+				DECIMAL_UNITS
+			} else {
+				BINARY_UNITS
+			};
+			write_amount(f, self.0 as u128, units)
+		}
+	}
 	impl const Add for MemoryAmount {
 		type Output = Self;
 
@@ -691,3 +1268,127 @@ mod memory_amount {
 	}
 }
 pub use memory_amount::MemoryAmount;
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::num::ParseAmountError,
+		core::{num::NonZero, ptr::fn_addr_eq},
+	};
+
+	#[test]
+	fn memory_amount() {
+		assert_eq!(MemoryAmount::parse("4096"), Ok(MemoryAmount::bytes(4096)));
+		assert_eq!(
+			MemoryAmount::parse("512k"),
+			Ok(MemoryAmount::kibibytes(512))
+		);
+		assert_eq!(MemoryAmount::parse("2G"), Ok(MemoryAmount::gibibytes(2)));
+		assert_eq!(
+			MemoryAmount::parse("1.5 MiB"),
+			Ok(MemoryAmount::kibibytes(1536))
+		);
+		assert_eq!(MemoryAmount::parse("3 MB"), Ok(MemoryAmount::megabytes(3)));
+		assert_eq!(
+			MemoryAmount::parse("1.2.3k"),
+			Err(ParseAmountError::InvalidNumber)
+		);
+		assert_eq!(
+			MemoryAmount::parse("1 parsec"),
+			Err(ParseAmountError::UnknownUnit)
+		);
+		assert_eq!(
+			MemoryAmount::parse("99999999999999999999"),
+			Err(ParseAmountError::Overflow)
+		);
+
+		assert_eq!(format!("{}", MemoryAmount::bytes(512)), "512 B");
+		assert_eq!(format!("{}", MemoryAmount::kibibytes(1536)), "1.5 MiB");
+		assert_eq!(format!("{}", MemoryAmount::bytes(1100)), "1.07 KiB");
+		assert_eq!(format!("{:#}", MemoryAmount::kilobytes(1500)), "1.5 MB");
+
+		let amount = MemoryAmount::bytes(usize::MAX);
+		assert_eq!(amount.checked_add(MemoryAmount::bytes(1)), None);
+		assert_eq!(amount.checked_sub(amount), Some(MemoryAmount::ZERO));
+		assert_eq!(
+			NonZero::try_from(MemoryAmount::ZERO),
+			Err::<NonZero<usize>, _>(())
+		);
+		assert_eq!(
+			MemoryAmount::from(NonZero::new(8).unwrap()),
+			MemoryAmount::bytes(8)
+		);
+	}
+
+	#[cfg(feature = "concurrency")]
+	#[test]
+	fn static_arena() {
+		const SIZE: MemoryAmount = MemoryAmount::kibibytes(10).align_to_assumed_page();
+		static ARENA: StaticArena = StaticArena::new(SIZE);
+		assert_eq!(SIZE.amount_bytes() % ASSUMED_PAGE_SIZE, 0);
+
+		let threads: Vec<_> = (0..4)
+			.map(|thread| {
+				crate::concurrency::spawn(move || {
+					let values: Vec<&u64> = (0..100)
+						.map(|idx| &*ARENA.alloc(thread * 100 + idx))
+						.collect();
+					(0..100).all(|idx| *values[idx as usize] == thread * 100 + idx)
+				})
+			})
+			.collect();
+		assert!(threads.into_iter().all(|thread| thread.join()));
+		assert_eq!(
+			ARENA.available_total_memory(),
+			SIZE - MemoryAmount::bytes(400 * size_of::<u64>())
+		);
+		assert_eq!(ARENA.init(), Ok(()));
+	}
+
+	#[test]
+	fn alloc_error_hooks() {
+		fn first(_: Layout) {}
+		fn second(_: Layout) {}
+
+		assert!(take_alloc_error_hook().is_none());
+		set_alloc_error_hook(first);
+		set_alloc_error_hook(second);
+		assert!(
+			take_alloc_error_hook().is_some_and(|hook| fn_addr_eq(hook, second as AllocErrorHook))
+		);
+		assert!(take_alloc_error_hook().is_none());
+
+		// Failed allocations return null, so the alloc error handler gets
+		// called
+		let huge = Layout::from_size_align(1 << 60, 1).unwrap();
+		assert!(unsafe { OsAllocator.alloc(huge) }.is_null());
+	}
+
+	#[cfg(linux)]
+	#[test]
+	fn shared_memory() {
+		let file = os::unix::MemoryFile::new(c"crux-test").unwrap();
+		file.set_len(page_size()).unwrap();
+
+		// Two mappings of the same file share their memory
+		let amount = MemoryAmount::bytes(page_size());
+		let [a, b] = [(); 2].map(|()| {
+			let mem = reserve(amount).unwrap();
+			unsafe { map_file(mem, file.fd()) }.unwrap();
+			mem
+		});
+		unsafe {
+			a.base_ptr.cast::<u32>().write(0xC0FFEE);
+			assert_eq!(b.base_ptr.cast::<u32>().read(), 0xC0FFEE);
+			unreserve(a);
+			unreserve(b);
+		}
+	}
+}
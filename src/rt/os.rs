@@ -1,6 +1,13 @@
 //! Items that interact with the operating system, and FFI bindings to operating
 //! system APIs.
 
+pub mod error;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod sys;
+
+pub use self::error::{Error, ErrorKind};
+
 //
 //
 // FFI
@@ -14,33 +21,42 @@ pub mod unix;
 pub mod win32 {
 	//! Win32 API bindings.
 
-	use core::{
-		ffi::c_void,
-		mem::MaybeUninit,
-		ptr::{NonNull, addr_of_mut},
+	use {
+		crate::{
+			io::{Reader, Writer},
+			rt::os,
+		},
+		core::{
+			ffi::{c_char, c_void},
+			mem::MaybeUninit,
+			ptr::NonNull,
+		},
 	};
 
-	pub static mut SYSTEM_INFO: MaybeUninit<SystemInfo> = MaybeUninit::uninit();
-	pub unsafe fn sysinfo() -> &'static mut MaybeUninit<SystemInfo> {
-		unsafe { &mut *addr_of_mut!(SYSTEM_INFO) }
+	/// Loads information about the system, like its page size and number of
+	/// processors. This is cheap, so it isn't cached.
+	pub fn system_info() -> SystemInfo {
+		let mut info = MaybeUninit::uninit();
+		unsafe { GetSystemInfo(NonNull::new_unchecked(info.as_mut_ptr())) };
+		unsafe { info.assume_init() }
 	}
 
 	#[repr(C)]
 	#[derive(Clone, Copy)]
-	struct SystemInfoProcessor {
+	pub struct SystemInfoProcessor {
 		pub processor_architecture: u16,
 		pub reserved: u16,
 	}
 	#[repr(C)]
 	#[derive(Clone, Copy)]
-	union SystemInfoUnion {
+	pub union SystemInfoUnion {
 		pub oem_id: u32,
 		pub system_info: SystemInfoProcessor,
 	}
 
 	/// https://learn.microsoft.com/en-us/windows/win32/api/sysinfoapi/ns-sysinfoapi-system_info
 	#[repr(C)]
-	struct SystemInfo {
+	pub struct SystemInfo {
 		pub oem: SystemInfoUnion,
 		pub page_size: u32,
 		pub minimum_application_address: *mut c_void,
@@ -72,9 +88,172 @@ pub mod win32 {
 		Release = 0x00008000,
 	}
 
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-exception_record
+	#[repr(C)]
+	pub struct ExceptionRecord {
+		pub exception_code: u32,
+		pub exception_flags: u32,
+		pub exception_record: Option<NonNull<ExceptionRecord>>,
+		pub exception_address: *mut c_void,
+		pub number_parameters: u32,
+		pub exception_information: [usize; 15],
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-exception_pointers
+	#[repr(C)]
+	pub struct ExceptionPointers {
+		pub exception_record: NonNull<ExceptionRecord>,
+		pub context_record: NonNull<c_void>,
+	}
+	pub type TopLevelExceptionFilter = extern "system" fn(NonNull<ExceptionPointers>) -> i32;
+
+	/// https://learn.microsoft.com/en-us/windows/win32/api/minwinbase/ns-minwinbase-overlapped
+	#[repr(C)]
+	#[derive(Debug)]
+	pub struct Overlapped {
+		pub internal: usize,
+		pub internal_high: usize,
+		pub offset: u32,
+		pub offset_high: u32,
+		pub event: Option<NonNull<c_void>>,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winsvc/ns-winsvc-service_table_entryw
+	#[repr(C)]
+	pub struct ServiceTableEntryW {
+		pub service_name: *const u16,
+		pub service_proc: Option<ServiceMain>,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winsvc/nc-winsvc-lpservice_main_functionw
+	pub type ServiceMain = extern "system" fn(u32, NonNull<NonNull<u16>>);
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winsvc/nc-winsvc-lphandler_function_ex
+	pub type HandlerEx = extern "system" fn(u32, u32, *mut c_void, *mut c_void) -> u32;
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winsvc/ns-winsvc-service_status
+	#[repr(C)]
+	pub struct ServiceStatus {
+		pub service_type: u32,
+		pub current_state: u32,
+		pub controls_accepted: u32,
+		pub win32_exit_code: u32,
+		pub service_specific_exit_code: u32,
+		pub check_point: u32,
+		pub wait_hint: u32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-file_notify_information
+	#[repr(C)]
+	pub struct FileNotifyInformation {
+		pub next_entry_offset: u32,
+		pub action: u32,
+		/// The length of `file_name`, in bytes.
+		pub file_name_length: u32,
+		/// The first character of the file name, which isn't nul-terminated.
+		pub file_name: [u16; 1],
+	}
+
+	/// https://learn.microsoft.com/en-us/windows/win32/api/dbghelp/ns-dbghelp-symbol_info
+	#[repr(C)]
+	pub struct SymbolInfo {
+		pub size_of_struct: u32,
+		pub type_index: u32,
+		pub reserved: [u64; 2],
+		pub index: u32,
+		pub size: u32,
+		pub mod_base: u64,
+		pub flags: u32,
+		pub value: u64,
+		pub address: u64,
+		pub register: u32,
+		pub scope: u32,
+		pub tag: u32,
+		pub name_len: u32,
+		/// How many characters fit in `name`, including the ones allocated
+		/// after this struct.
+		pub max_name_len: u32,
+		/// The first character of the nul-terminated symbol name.
+		pub name: [c_char; 1],
+	}
+
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winuser/nc-winuser-wndproc
+	pub type WindowProc = extern "system" fn(NonNull<c_void>, u32, usize, isize) -> isize;
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-wndclassexw
+	#[repr(C)]
+	pub struct WindowClassExW {
+		pub size: u32,
+		pub style: u32,
+		pub window_proc: WindowProc,
+		pub class_extra: i32,
+		pub window_extra: i32,
+		pub instance: Option<NonNull<c_void>>,
+		pub icon: Option<NonNull<c_void>>,
+		pub cursor: Option<NonNull<c_void>>,
+		pub background: Option<NonNull<c_void>>,
+		pub menu_name: Option<NonNullConst<u16>>,
+		pub class_name: NonNullConst<u16>,
+		pub small_icon: Option<NonNull<c_void>>,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/windef/ns-windef-point
+	#[repr(C)]
+	#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+	pub struct Point {
+		pub x: i32,
+		pub y: i32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/windef/ns-windef-rect
+	#[repr(C)]
+	#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+	pub struct Rect {
+		pub left: i32,
+		pub top: i32,
+		pub right: i32,
+		pub bottom: i32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-msg
+	#[repr(C)]
+	pub struct Msg {
+		pub hwnd: Option<NonNull<c_void>>,
+		pub message: u32,
+		pub wparam: usize,
+		pub lparam: isize,
+		pub time: u32,
+		pub point: Point,
+		pub private: u32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-trackmouseevent
+	#[repr(C)]
+	pub struct TrackMouseEventInfo {
+		pub size: u32,
+		pub flags: u32,
+		pub hwnd: NonNull<c_void>,
+		pub hover_time: u32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/wingdi/ns-wingdi-bitmapinfoheader
+	#[repr(C)]
+	pub struct BitmapInfoHeader {
+		pub size: u32,
+		pub width: i32,
+		/// Negative for images whose rows go from top to bottom.
+		pub height: i32,
+		pub planes: u16,
+		pub bit_count: u16,
+		pub compression: u32,
+		pub size_image: u32,
+		pub x_pels_per_meter: i32,
+		pub y_pels_per_meter: i32,
+		pub clr_used: u32,
+		pub clr_important: u32,
+	}
+	/// https://learn.microsoft.com/en-us/windows/win32/api/wingdi/ns-wingdi-bitmapinfo
+	#[repr(C)]
+	pub struct BitmapInfo {
+		pub header: BitmapInfoHeader,
+		pub colors: [u32; 1],
+	}
+
 	#[link(name = "kernel32")]
 	unsafe extern "C" {
 		pub unsafe fn GetSystemInfo(lpSystemInfo: NonNull<SystemInfo>);
+		pub safe fn GetCurrentProcess() -> NonNull<c_void>;
+		pub unsafe fn GetSystemTimePreciseAsFileTime(lpSystemTimeAsFileTime: NonNull<u64>);
+		pub unsafe fn QueryPerformanceCounter(lpPerformanceCount: NonNull<i64>) -> i32;
+		pub unsafe fn QueryPerformanceFrequency(lpFrequency: NonNull<i64>) -> i32;
 		pub safe fn VirtualAlloc(
 			lpAddress: Option<NonNull<c_void>>,
 			dwSize: usize,
@@ -86,6 +265,462 @@ pub mod win32 {
 			dwSize: usize,
 			dwFreeType: FreeType,
 		) -> bool;
+		pub unsafe fn RtlCaptureStackBackTrace(
+			FramesToSkip: u32,
+			FramesToCapture: u32,
+			BackTrace: NonNull<*mut c_void>,
+			BackTraceHash: Option<NonNull<u32>>,
+		) -> u16;
+		pub unsafe fn SetUnhandledExceptionFilter(
+			lpTopLevelExceptionFilter: Option<TopLevelExceptionFilter>,
+		) -> Option<TopLevelExceptionFilter>;
+		pub unsafe fn GetModuleFileNameW(
+			hModule: Option<NonNull<c_void>>,
+			lpFilename: NonNull<u16>,
+			nSize: u32,
+		) -> u32;
+		pub safe fn GetLastError() -> u32;
+		pub unsafe fn CreateThread(
+			lpThreadAttributes: Option<NonNull<c_void>>,
+			dwStackSize: usize,
+			lpStartAddress: extern "system" fn(NonNull<c_void>) -> u32,
+			lpParameter: NonNull<c_void>,
+			dwCreationFlags: u32,
+			lpThreadId: Option<NonNull<u32>>,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn WaitForSingleObject(hHandle: NonNull<c_void>, dwMilliseconds: u32) -> u32;
+		pub unsafe fn CloseHandle(hObject: NonNull<c_void>) -> i32;
+		pub unsafe fn CreatePipe(
+			hReadPipe: NonNull<Option<NonNull<c_void>>>,
+			hWritePipe: NonNull<Option<NonNull<c_void>>>,
+			lpPipeAttributes: Option<NonNull<c_void>>,
+			nSize: u32,
+		) -> i32;
+		pub unsafe fn ReadFile(
+			hFile: NonNull<c_void>,
+			lpBuffer: NonNull<c_void>,
+			nNumberOfBytesToRead: u32,
+			lpNumberOfBytesRead: Option<NonNull<u32>>,
+			lpOverlapped: Option<NonNull<c_void>>,
+		) -> i32;
+		pub unsafe fn WriteFile(
+			hFile: NonNull<c_void>,
+			lpBuffer: NonNull<c_void>,
+			nNumberOfBytesToWrite: u32,
+			lpNumberOfBytesWritten: Option<NonNull<u32>>,
+			lpOverlapped: Option<NonNull<c_void>>,
+		) -> i32;
+		pub safe fn GetStdHandle(nStdHandle: u32) -> Option<NonNull<c_void>>;
+		pub unsafe fn SetStdHandle(nStdHandle: u32, hHandle: NonNull<c_void>) -> i32;
+		pub unsafe fn LoadLibraryW(lpLibFileName: NonNullConst<u16>) -> Option<NonNull<c_void>>;
+		pub unsafe fn GetProcAddress(
+			hModule: NonNull<c_void>,
+			lpProcName: NonNullConst<u8>,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn FreeLibrary(hLibModule: NonNull<c_void>) -> i32;
+		pub unsafe fn CreateFileW(
+			lpFileName: NonNullConst<u16>,
+			dwDesiredAccess: u32,
+			dwShareMode: u32,
+			lpSecurityAttributes: Option<NonNull<c_void>>,
+			dwCreationDisposition: u32,
+			dwFlagsAndAttributes: u32,
+			hTemplateFile: Option<NonNull<c_void>>,
+		) -> isize;
+		pub unsafe fn GetFileAttributesW(lpFileName: NonNullConst<u16>) -> u32;
+		pub unsafe fn ReadDirectoryChangesW(
+			hDirectory: NonNull<c_void>,
+			lpBuffer: NonNull<c_void>,
+			nBufferLength: u32,
+			bWatchSubtree: i32,
+			dwNotifyFilter: u32,
+			lpBytesReturned: Option<NonNull<u32>>,
+			lpOverlapped: NonNull<Overlapped>,
+			lpCompletionRoutine: Option<NonNull<c_void>>,
+		) -> i32;
+		pub unsafe fn CreateIoCompletionPort(
+			FileHandle: isize,
+			ExistingCompletionPort: Option<NonNull<c_void>>,
+			CompletionKey: usize,
+			NumberOfConcurrentThreads: u32,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn GetQueuedCompletionStatus(
+			CompletionPort: NonNull<c_void>,
+			lpNumberOfBytesTransferred: NonNull<u32>,
+			lpCompletionKey: NonNull<usize>,
+			lpOverlapped: NonNull<*mut Overlapped>,
+			dwMilliseconds: u32,
+		) -> i32;
+		pub unsafe fn GetOverlappedResult(
+			hFile: NonNull<c_void>,
+			lpOverlapped: NonNull<Overlapped>,
+			lpNumberOfBytesTransferred: NonNull<u32>,
+			bWait: i32,
+		) -> i32;
+		pub unsafe fn CancelIoEx(
+			hFile: NonNull<c_void>,
+			lpOverlapped: Option<NonNull<Overlapped>>,
+		) -> i32;
+		pub unsafe fn GetModuleHandleW(
+			lpModuleName: Option<NonNullConst<u16>>,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn GetCurrentDirectoryW(nBufferLength: u32, lpBuffer: NonNull<u16>) -> u32;
+		pub unsafe fn SetCurrentDirectoryW(lpPathName: NonNullConst<u16>) -> i32;
+		pub unsafe fn GlobalAlloc(uFlags: u32, dwBytes: usize) -> Option<NonNull<c_void>>;
+		pub unsafe fn GlobalFree(hMem: NonNull<c_void>) -> Option<NonNull<c_void>>;
+		pub unsafe fn GlobalLock(hMem: NonNull<c_void>) -> Option<NonNull<c_void>>;
+		pub unsafe fn GlobalUnlock(hMem: NonNull<c_void>) -> i32;
+		pub unsafe fn GlobalSize(hMem: NonNull<c_void>) -> usize;
+	}
+	pub const GMEM_MOVEABLE: u32 = 0x0002;
+	pub const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+	pub const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+	pub const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+	/// Returned by `CreateFileW` on failure. Also passed to
+	/// `CreateIoCompletionPort` to create a new port.
+	pub const INVALID_HANDLE_VALUE: isize = -1;
+	pub const INVALID_FILE_ATTRIBUTES: u32 = u32::MAX;
+	pub const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+	pub const FILE_LIST_DIRECTORY: u32 = 0x0001;
+	pub const FILE_SHARE_READ: u32 = 0x0001;
+	pub const FILE_SHARE_WRITE: u32 = 0x0002;
+	pub const FILE_SHARE_DELETE: u32 = 0x0004;
+	pub const OPEN_EXISTING: u32 = 3;
+	pub const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x02000000;
+	pub const FILE_FLAG_OVERLAPPED: u32 = 0x40000000;
+	pub const FILE_NOTIFY_CHANGE_FILE_NAME: u32 = 0x0001;
+	pub const FILE_NOTIFY_CHANGE_DIR_NAME: u32 = 0x0002;
+	pub const FILE_NOTIFY_CHANGE_ATTRIBUTES: u32 = 0x0004;
+	pub const FILE_NOTIFY_CHANGE_SIZE: u32 = 0x0008;
+	pub const FILE_NOTIFY_CHANGE_LAST_WRITE: u32 = 0x0010;
+	pub const FILE_ACTION_ADDED: u32 = 1;
+	pub const FILE_ACTION_REMOVED: u32 = 2;
+	pub const FILE_ACTION_MODIFIED: u32 = 3;
+	pub const FILE_ACTION_RENAMED_OLD_NAME: u32 = 4;
+	pub const FILE_ACTION_RENAMED_NEW_NAME: u32 = 5;
+	#[link(name = "advapi32")]
+	unsafe extern "C" {
+		pub unsafe fn StartServiceCtrlDispatcherW(
+			lpServiceStartTable: NonNullConst<ServiceTableEntryW>,
+		) -> i32;
+		pub unsafe fn RegisterServiceCtrlHandlerExW(
+			lpServiceName: NonNullConst<u16>,
+			lpHandlerProc: HandlerEx,
+			lpContext: Option<NonNull<c_void>>,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn SetServiceStatus(
+			hServiceStatus: NonNull<c_void>,
+			lpServiceStatus: NonNullConst<ServiceStatus>,
+		) -> i32;
+	}
+	pub const SERVICE_WIN32_OWN_PROCESS: u32 = 0x10;
+	pub const SERVICE_STOPPED: u32 = 1;
+	pub const SERVICE_STOP_PENDING: u32 = 3;
+	pub const SERVICE_RUNNING: u32 = 4;
+	pub const SERVICE_ACCEPT_STOP: u32 = 0x1;
+	pub const SERVICE_ACCEPT_SHUTDOWN: u32 = 0x4;
+	pub const SERVICE_CONTROL_STOP: u32 = 1;
+	pub const SERVICE_CONTROL_INTERROGATE: u32 = 4;
+	pub const SERVICE_CONTROL_SHUTDOWN: u32 = 5;
+	pub const NO_ERROR: u32 = 0;
+	pub const ERROR_CALL_NOT_IMPLEMENTED: u32 = 120;
+
+	// DbgHelp isn't thread-safe, so calls to it must be serialised
+	#[link(name = "dbghelp")]
+	unsafe extern "C" {
+		pub unsafe fn SymInitialize(
+			hProcess: NonNull<c_void>,
+			UserSearchPath: *const c_char,
+			fInvadeProcess: i32,
+		) -> i32;
+		pub unsafe fn SymFromAddr(
+			hProcess: NonNull<c_void>,
+			Address: u64,
+			Displacement: Option<NonNull<u64>>,
+			Symbol: NonNull<SymbolInfo>,
+		) -> i32;
+	}
+
+	#[link(name = "bcryptprimitives")]
+	unsafe extern "C" {
+		pub unsafe fn ProcessPrng(pbData: NonNull<u8>, cbData: usize) -> i32;
+	}
+
+	#[link(name = "synchronization")]
+	unsafe extern "C" {
+		pub unsafe fn WaitOnAddress(
+			Address: NonNull<c_void>,
+			CompareAddress: NonNull<c_void>,
+			AddressSize: usize,
+			dwMilliseconds: u32,
+		) -> i32;
+		pub unsafe fn WakeByAddressSingle(Address: NonNull<c_void>);
+		pub unsafe fn WakeByAddressAll(Address: NonNull<c_void>);
+	}
+
+	// `GetWindowLongPtrW` and `SetWindowLongPtrW` are macros on 32-bit
+	// Windows, so these bindings only work on 64-bit Windows
+	#[link(name = "user32")]
+	unsafe extern "C" {
+		pub unsafe fn RegisterClassExW(lpwcx: NonNullConst<WindowClassExW>) -> u16;
+		pub unsafe fn CreateWindowExW(
+			dwExStyle: u32,
+			lpClassName: NonNullConst<u16>,
+			lpWindowName: NonNullConst<u16>,
+			dwStyle: u32,
+			X: i32,
+			Y: i32,
+			nWidth: i32,
+			nHeight: i32,
+			hWndParent: Option<NonNull<c_void>>,
+			hMenu: Option<NonNull<c_void>>,
+			hInstance: Option<NonNull<c_void>>,
+			lpParam: Option<NonNull<c_void>>,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn DestroyWindow(hWnd: NonNull<c_void>) -> i32;
+		pub unsafe fn DefWindowProcW(
+			hWnd: NonNull<c_void>,
+			Msg: u32,
+			wParam: usize,
+			lParam: isize,
+		) -> isize;
+		pub unsafe fn GetMessageW(
+			lpMsg: NonNull<Msg>,
+			hWnd: Option<NonNull<c_void>>,
+			wMsgFilterMin: u32,
+			wMsgFilterMax: u32,
+		) -> i32;
+		pub unsafe fn PeekMessageW(
+			lpMsg: NonNull<Msg>,
+			hWnd: Option<NonNull<c_void>>,
+			wMsgFilterMin: u32,
+			wMsgFilterMax: u32,
+			wRemoveMsg: u32,
+		) -> i32;
+		pub unsafe fn TranslateMessage(lpMsg: NonNullConst<Msg>) -> i32;
+		pub unsafe fn DispatchMessageW(lpMsg: NonNullConst<Msg>) -> isize;
+		pub unsafe fn GetWindowLongPtrW(hWnd: NonNull<c_void>, nIndex: i32) -> isize;
+		pub unsafe fn SetWindowLongPtrW(
+			hWnd: NonNull<c_void>,
+			nIndex: i32,
+			dwNewLong: isize,
+		) -> isize;
+		pub unsafe fn SetWindowTextW(hWnd: NonNull<c_void>, lpString: NonNullConst<u16>) -> i32;
+		pub unsafe fn AdjustWindowRectEx(
+			lpRect: NonNull<Rect>,
+			dwStyle: u32,
+			bMenu: i32,
+			dwExStyle: u32,
+		) -> i32;
+		pub unsafe fn ValidateRect(
+			hWnd: NonNull<c_void>,
+			lpRect: Option<NonNullConst<Rect>>,
+		) -> i32;
+		pub unsafe fn GetDC(hWnd: NonNull<c_void>) -> Option<NonNull<c_void>>;
+		pub unsafe fn ReleaseDC(hWnd: NonNull<c_void>, hDC: NonNull<c_void>) -> i32;
+		pub unsafe fn LoadCursorW(
+			hInstance: Option<NonNull<c_void>>,
+			lpCursorName: usize,
+		) -> Option<NonNull<c_void>>;
+		pub unsafe fn TrackMouseEvent(lpEventTrack: NonNull<TrackMouseEventInfo>) -> i32;
+		pub unsafe fn MsgWaitForMultipleObjects(
+			nCount: u32,
+			pHandles: Option<NonNullConst<NonNull<c_void>>>,
+			fWaitAll: i32,
+			dwMilliseconds: u32,
+			dwWakeMask: u32,
+		) -> u32;
+		pub unsafe fn OpenClipboard(hWndNewOwner: Option<NonNull<c_void>>) -> i32;
+		pub unsafe fn CloseClipboard() -> i32;
+		pub unsafe fn EmptyClipboard() -> i32;
+		pub unsafe fn IsClipboardFormatAvailable(format: u32) -> i32;
+		pub unsafe fn GetClipboardData(uFormat: u32) -> Option<NonNull<c_void>>;
+		pub unsafe fn SetClipboardData(
+			uFormat: u32,
+			hMem: NonNull<c_void>,
+		) -> Option<NonNull<c_void>>;
+	}
+	pub const CS_VREDRAW: u32 = 0x0001;
+	pub const CS_HREDRAW: u32 = 0x0002;
+	pub const WS_OVERLAPPEDWINDOW: u32 = 0x00CF0000;
+	pub const WS_VISIBLE: u32 = 0x10000000;
+	pub const CW_USEDEFAULT: i32 = 0x80000000u32 as i32;
+	/// Passed as a window's parent to make it a message-only window, which
+	/// is never shown.
+	pub const HWND_MESSAGE: isize = -3;
+	pub const WM_SIZE: u32 = 0x0005;
+	pub const WM_SETFOCUS: u32 = 0x0007;
+	pub const WM_KILLFOCUS: u32 = 0x0008;
+	pub const WM_PAINT: u32 = 0x000F;
+	pub const WM_CLOSE: u32 = 0x0010;
+	pub const WM_NCCREATE: u32 = 0x0081;
+	pub const WM_KEYDOWN: u32 = 0x0100;
+	pub const WM_KEYUP: u32 = 0x0101;
+	pub const WM_SYSKEYDOWN: u32 = 0x0104;
+	pub const WM_SYSKEYUP: u32 = 0x0105;
+	pub const WM_MOUSEMOVE: u32 = 0x0200;
+	pub const WM_LBUTTONDOWN: u32 = 0x0201;
+	pub const WM_LBUTTONUP: u32 = 0x0202;
+	pub const WM_RBUTTONDOWN: u32 = 0x0204;
+	pub const WM_RBUTTONUP: u32 = 0x0205;
+	pub const WM_MBUTTONDOWN: u32 = 0x0207;
+	pub const WM_MBUTTONUP: u32 = 0x0208;
+	pub const WM_MOUSEWHEEL: u32 = 0x020A;
+	pub const WM_XBUTTONDOWN: u32 = 0x020B;
+	pub const WM_XBUTTONUP: u32 = 0x020C;
+	pub const WM_MOUSEHWHEEL: u32 = 0x020E;
+	pub const WM_MOUSELEAVE: u32 = 0x02A3;
+	/// How far one notch of a mouse wheel scrolls in `WM_MOUSEWHEEL`.
+	pub const WHEEL_DELTA: i16 = 120;
+	pub const XBUTTON1: u16 = 0x0001;
+	pub const XBUTTON2: u16 = 0x0002;
+	pub const VK_PAUSE: usize = 0x13;
+	pub const TME_LEAVE: u32 = 0x0002;
+	pub const GWLP_USERDATA: i32 = -21;
+	pub const PM_REMOVE: u32 = 0x0001;
+	pub const QS_ALLINPUT: u32 = 0x04FF;
+	pub const CF_UNICODETEXT: u32 = 13;
+	pub const WAIT_TIMEOUT: u32 = 258;
+	pub const WAIT_FAILED: u32 = u32::MAX;
+	/// `LoadCursorW` takes built-in cursors' IDs in place of a name.
+	pub const IDC_ARROW: usize = 32512;
+	pub const ERROR_INVALID_PARAMETER: i32 = 87;
+	pub const WSAEWOULDBLOCK: i32 = 10035;
+	pub const ERROR_CLASS_ALREADY_EXISTS: i32 = 1410;
+
+	#[link(name = "gdi32")]
+	unsafe extern "C" {
+		pub unsafe fn StretchDIBits(
+			hdc: NonNull<c_void>,
+			xDest: i32,
+			yDest: i32,
+			DestWidth: i32,
+			DestHeight: i32,
+			xSrc: i32,
+			ySrc: i32,
+			SrcWidth: i32,
+			SrcHeight: i32,
+			lpBits: NonNullConst<c_void>,
+			lpbmi: NonNullConst<BitmapInfo>,
+			iUsage: u32,
+			rop: u32,
+		) -> i32;
+	}
+	pub const BI_RGB: u32 = 0;
+	pub const DIB_RGB_COLORS: u32 = 0;
+	pub const SRCCOPY: u32 = 0x00CC0020;
+
+	/// Creates an anonymous pipe: bytes written to the [`PipeWriter`] can be
+	/// read from the [`PipeReader`]. Each half closes its handle when it's
+	/// dropped.
+	pub fn pipe() -> Result<(PipeReader, PipeWriter), os::Error> {
+		let mut read = None;
+		let mut write = None;
+		let res =
+			unsafe { CreatePipe(NonNull::from(&mut read), NonNull::from(&mut write), None, 0) };
+		match (read, write) {
+			(Some(read), Some(write)) if res != 0 => Ok((PipeReader(read), PipeWriter(write))),
+			_ => Err(os::Error::last()),
+		}
+	}
+
+	/// The read end of a [`pipe`]. Reading returns 0 bytes once the
+	/// [`PipeWriter`] has been dropped.
+	pub struct PipeReader(NonNull<c_void>);
+	impl PipeReader {
+		pub fn handle(&self) -> NonNull<c_void> {
+			self.0
+		}
+	}
+	impl Reader for PipeReader {
+		type Error = os::Error;
+
+		fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+			const ERROR_BROKEN_PIPE: i32 = 109;
+
+			let len = buf.len().min(u32::MAX as usize) as u32;
+			let mut read = 0;
+			let res = unsafe {
+				ReadFile(
+					self.0,
+					NonNull::from(buf).cast(),
+					len,
+					Some(NonNull::from(&mut read)),
+					None,
+				)
+			};
+			if res != 0 {
+				return Ok(read as usize);
+			}
+
+			// The write end being closed just means there's nothing left to
+			// read
+			let err = os::Error::last();
+			if err.raw() == ERROR_BROKEN_PIPE {
+				Ok(0)
+			} else {
+				Err(err)
+			}
+		}
+	}
+	impl Drop for PipeReader {
+		fn drop(&mut self) {
+			unsafe { CloseHandle(self.0) };
+		}
+	}
+
+	/// The write end of a [`pipe`].
+	pub struct PipeWriter(NonNull<c_void>);
+	impl PipeWriter {
+		pub fn handle(&self) -> NonNull<c_void> {
+			self.0
+		}
+	}
+	impl Writer for PipeWriter {
+		type Error = os::Error;
+
+		fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+			let len = bytes.len().min(u32::MAX as usize) as u32;
+			let mut written = 0;
+			let res = unsafe {
+				WriteFile(
+					self.0,
+					NonNull::from(bytes).cast(),
+					len,
+					Some(NonNull::from(&mut written)),
+					None,
+				)
+			};
+			if res != 0 {
+				Ok(written as usize)
+			} else {
+				Err(os::Error::last())
+			}
+		}
+		/// Pipes aren't buffered, so this does nothing.
+		fn flush(&mut self) -> Result<(), Self::Error> {
+			Ok(())
+		}
+	}
+	impl Drop for PipeWriter {
+		fn drop(&mut self) {
+			unsafe { CloseHandle(self.0) };
+		}
+	}
+
+	/// Makes the standard handle `std_handle` (one of [`STD_INPUT_HANDLE`],
+	/// [`STD_OUTPUT_HANDLE`], or [`STD_ERROR_HANDLE`]) refer to `handle`.
+	/// Returns the handle it referred to before, so it can be restored later.
+	pub fn redirect(
+		handle: NonNull<c_void>,
+		std_handle: u32,
+	) -> Result<Option<NonNull<c_void>>, os::Error> {
+		let old = GetStdHandle(std_handle);
+		if unsafe { SetStdHandle(std_handle, handle) } != 0 {
+			Ok(old)
+		} else {
+			Err(os::Error::last())
+		}
 	}
 }
 
@@ -0,0 +1,165 @@
+//! Errors reported by the operating system.
+
+use crate::{rt::os, text::write};
+
+/// An error code from the operating system - `errno` on Unix, and
+/// `GetLastError` on Windows.
+///
+/// The raw code is platform-specific; use [`Error::kind`] to handle errors in a
+/// cross-platform way.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Error {
+	code: i32,
+}
+impl Error {
+	/// Wraps a raw OS error code.
+	pub const fn from_raw(code: i32) -> Self {
+		Self { code }
+	}
+	/// Returns the most recent error reported by the OS on the current thread.
+	///
+	/// This should be called immediately after the failing OS call, since
+	/// other calls (including ones made by Crux) may overwrite it.
+	pub fn last() -> Self {
+		#[cfg(unix)]
+		let code = os::unix::errno();
+		#[cfg(windows)]
+		let code = os::win32::GetLastError() as i32;
+
+		Self::from_raw(code)
+	}
+
+	/// The raw, platform-specific error code.
+	pub const fn raw(self) -> i32 {
+		self.code
+	}
+	/// Classifies the error into a cross-platform [`ErrorKind`].
+	pub fn kind(self) -> ErrorKind {
+		#[cfg(unix)]
+		{
+			match self.code {
+				libc::ENOENT => ErrorKind::NotFound,
+				libc::EACCES | libc::EPERM => ErrorKind::PermissionDenied,
+				libc::EEXIST => ErrorKind::AlreadyExists,
+				// `EWOULDBLOCK` is the same as `EAGAIN` on every supported OS
+				libc::EAGAIN => ErrorKind::WouldBlock,
+				libc::EINTR => ErrorKind::Interrupted,
+				libc::EINVAL => ErrorKind::InvalidInput,
+				libc::EPIPE => ErrorKind::BrokenPipe,
+				libc::ETIMEDOUT => ErrorKind::TimedOut,
+				libc::ENOMEM => ErrorKind::OutOfMemory,
+				libc::ECONNREFUSED => ErrorKind::ConnectionRefused,
+				libc::ECONNRESET => ErrorKind::ConnectionReset,
+				libc::EADDRINUSE => ErrorKind::AddrInUse,
+				libc::ENOSYS | libc::ENOTSUP => ErrorKind::Unsupported,
+				_ => ErrorKind::Other,
+			}
+		}
+		// https://learn.microsoft.com/en-us/windows/win32/debug/system-error-codes
+		#[cfg(windows)]
+		{
+			match self.code {
+				2 | 3 => ErrorKind::NotFound,
+				5 => ErrorKind::PermissionDenied,
+				80 | 183 => ErrorKind::AlreadyExists,
+				10035 => ErrorKind::WouldBlock,
+				995 | 10004 => ErrorKind::Interrupted,
+				87 => ErrorKind::InvalidInput,
+				109 | 232 => ErrorKind::BrokenPipe,
+				258 | 1460 => ErrorKind::TimedOut,
+				8 | 14 => ErrorKind::OutOfMemory,
+				10061 => ErrorKind::ConnectionRefused,
+				10054 => ErrorKind::ConnectionReset,
+				10048 => ErrorKind::AddrInUse,
+				50 => ErrorKind::Unsupported,
+				_ => ErrorKind::Other,
+			}
+		}
+	}
+}
+impl crate::text::Display for Error {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{} (os error {})", self.kind(), self.code)
+	}
+}
+
+/// A cross-platform category for an OS [`Error`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorKind {
+	/// A file or directory doesn't exist.
+	NotFound,
+	/// The process doesn't have permission for the operation.
+	PermissionDenied,
+	/// A file or directory already exists.
+	AlreadyExists,
+	/// The operation would block, but the resource is in non-blocking mode.
+	WouldBlock,
+	/// The operation was interrupted by a signal before it finished, and can
+	/// be retried.
+	Interrupted,
+	/// An argument was invalid.
+	InvalidInput,
+	/// Wrote to a pipe or socket whose other end was closed.
+	BrokenPipe,
+	/// The operation didn't finish before its timeout.
+	TimedOut,
+	/// The OS ran out of memory.
+	OutOfMemory,
+	/// The remote host refused a connection.
+	ConnectionRefused,
+	/// The remote host reset a connection.
+	ConnectionReset,
+	/// A socket address is already in use.
+	AddrInUse,
+	/// The operation isn't supported on this platform.
+	Unsupported,
+	/// Any error that doesn't fit in another category.
+	Other,
+}
+impl crate::text::Display for ErrorKind {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(match self {
+			Self::NotFound => "not found",
+			Self::PermissionDenied => "permission denied",
+			Self::AlreadyExists => "already exists",
+			Self::WouldBlock => "operation would block",
+			Self::Interrupted => "interrupted",
+			Self::InvalidInput => "invalid input",
+			Self::BrokenPipe => "broken pipe",
+			Self::TimedOut => "timed out",
+			Self::OutOfMemory => "out of memory",
+			Self::ConnectionRefused => "connection refused",
+			Self::ConnectionReset => "connection reset",
+			Self::AddrInUse => "address in use",
+			Self::Unsupported => "unsupported",
+			Self::Other => "other error",
+		})
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	#[cfg(unix)]
+	fn error() {
+		let err = Error::from_raw(libc::ENOENT);
+		assert_eq!(err.kind(), ErrorKind::NotFound);
+		assert_eq!(
+			format!("{err}"),
+			format!("not found (os error {})", libc::ENOENT)
+		);
+		assert_eq!(
+			Error::from_raw(libc::EWOULDBLOCK).kind(),
+			ErrorKind::WouldBlock
+		);
+		assert_eq!(Error::from_raw(-1).kind(), ErrorKind::Other);
+	}
+}
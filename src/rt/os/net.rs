@@ -0,0 +1,557 @@
+//! TCP sockets, and resolving hostnames to IP addresses.
+//!
+//! ```rs
+//! let mut stream = TcpStream::connect_to_host("example.com", 80)?;
+//! stream.write_all(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n")?;
+//!
+//! let mut response = Vec::new();
+//! stream.read_to_end(&mut response)?;
+//! ```
+
+#[cfg(not(unix))]
+compile_error!("`os::net` is only implemented on Unix");
+
+use crate::{
+	ffi::c_int,
+	io::{Reader, Writer},
+	lang::{size_of, zeroed},
+	rt::os::{
+		self,
+		unix::{self, FileDescriptor},
+	},
+	text::{CString, Display, write},
+};
+
+/// How many connections a [`TcpListener`] queues up before it starts refusing
+/// them.
+const LISTEN_BACKLOG: c_int = 128;
+
+/// An IPv4 or IPv6 address.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum IpAddr {
+	V4([u8; 4]),
+	V6([u16; 8]),
+}
+impl IpAddr {
+	/// `127.0.0.1`, the IPv4 loopback address.
+	pub const LOCALHOST: Self = Self::V4([127, 0, 0, 1]);
+	/// `0.0.0.0`, which listens on every IPv4 interface.
+	pub const UNSPECIFIED: Self = Self::V4([0; 4]);
+	/// `::1`, the IPv6 loopback address.
+	pub const LOCALHOST_V6: Self = Self::V6([0, 0, 0, 0, 0, 0, 0, 1]);
+	/// `::`, which listens on every IPv6 interface.
+	pub const UNSPECIFIED_V6: Self = Self::V6([0; 8]);
+}
+impl Display for IpAddr {
+	/// IPv6 addresses are formatted as described in RFC 5952: the longest run
+	/// of zero groups is shortened to `::`.
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::V4([a, b, c, d]) => write!(f, "{a}.{b}.{c}.{d}"),
+			Self::V6(groups) => {
+				// The start and length of the longest run of zeroes; a single
+				// zero isn't worth shortening
+				let mut zeroes = (0, 0);
+				let mut idx = 0;
+				while idx < groups.len() {
+					let len = groups[idx..].iter().take_while(|g| **g == 0).count();
+					if len > 1 && len > zeroes.1 {
+						zeroes = (idx, len);
+					}
+					idx += len.max(1);
+				}
+
+				let (start, len) = zeroes;
+				let write_groups = |f: &mut core::fmt::Formatter<'_>, groups: &[u16]| {
+					for (idx, group) in groups.iter().enumerate() {
+						if idx > 0 {
+							f.write_str(":")?;
+						}
+						write!(f, "{group:x}")?;
+					}
+					Ok(())
+				};
+				if len == 0 {
+					write_groups(f, groups)
+				} else {
+					write_groups(f, &groups[..start])?;
+					f.write_str("::")?;
+					write_groups(f, &groups[start + len..])
+				}
+			}
+		}
+	}
+}
+
+/// An IP address and port.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SocketAddr {
+	pub ip: IpAddr,
+	pub port: u16,
+}
+impl SocketAddr {
+	pub const fn new(ip: IpAddr, port: u16) -> Self {
+		Self { ip, port }
+	}
+
+	/// Converts this address to a `sockaddr_in` or `sockaddr_in6`, and
+	/// returns its length.
+	fn to_raw(self) -> (RawAddr, libc::socklen_t) {
+		let mut raw: RawAddr = unsafe { zeroed() };
+		match self.ip {
+			IpAddr::V4(octets) => {
+				let addr = unsafe { &mut raw.v4 };
+				addr.sin_family = libc::AF_INET as libc::sa_family_t;
+				addr.sin_port = self.port.to_be();
+				addr.sin_addr.s_addr = u32::from_ne_bytes(octets);
+				#[cfg(macos)]
+				{
+					addr.sin_len = size_of::<libc::sockaddr_in>() as u8;
+				}
+				(raw, size_of::<libc::sockaddr_in>() as libc::socklen_t)
+			}
+			IpAddr::V6(groups) => {
+				let addr = unsafe { &mut raw.v6 };
+				addr.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+				addr.sin6_port = self.port.to_be();
+				let (bytes, _) = addr.sin6_addr.s6_addr.as_chunks_mut::<2>();
+				for (dst, group) in bytes.iter_mut().zip(groups) {
+					*dst = group.to_be_bytes();
+				}
+				#[cfg(macos)]
+				{
+					addr.sin6_len = size_of::<libc::sockaddr_in6>() as u8;
+				}
+				(raw, size_of::<libc::sockaddr_in6>() as libc::socklen_t)
+			}
+		}
+	}
+	/// Reads an address the OS wrote. Returns `None` if it isn't an IPv4 or
+	/// IPv6 address.
+	///
+	///
+	/// # Safety
+	///
+	/// `addr` must point to a valid `sockaddr` whose size matches its family.
+	unsafe fn from_raw(addr: NonNullConst<libc::sockaddr>) -> Option<Self> {
+		match unsafe { addr.as_ref() }.sa_family as c_int {
+			libc::AF_INET => {
+				let addr = unsafe { addr.cast::<libc::sockaddr_in>().as_ref() };
+				Some(Self {
+					ip: IpAddr::V4(addr.sin_addr.s_addr.to_ne_bytes()),
+					port: u16::from_be(addr.sin_port),
+				})
+			}
+			libc::AF_INET6 => {
+				let addr = unsafe { addr.cast::<libc::sockaddr_in6>().as_ref() };
+				let (bytes, _) = addr.sin6_addr.s6_addr.as_chunks::<2>();
+				let mut groups = [0; 8];
+				for (group, src) in groups.iter_mut().zip(bytes) {
+					*group = u16::from_be_bytes(*src);
+				}
+				Some(Self {
+					ip: IpAddr::V6(groups),
+					port: u16::from_be(addr.sin6_port),
+				})
+			}
+			_ => None,
+		}
+	}
+	fn family(self) -> c_int {
+		match self.ip {
+			IpAddr::V4(_) => libc::AF_INET,
+			IpAddr::V6(_) => libc::AF_INET6,
+		}
+	}
+}
+impl Display for SocketAddr {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self.ip {
+			IpAddr::V4(_) => write!(f, "{}:{}", self.ip, self.port),
+			IpAddr::V6(_) => write!(f, "[{}]:{}", self.ip, self.port),
+		}
+	}
+}
+
+/// Space for either kind of address the OS can give back.
+#[repr(C)]
+union RawAddr {
+	v4: libc::sockaddr_in,
+	v6: libc::sockaddr_in6,
+}
+
+/// Looks up the addresses for `host`, which can be a domain name or an IP
+/// address. Each address is given `port`.
+///
+/// This blocks until the lookup finishes, which may involve a DNS query.
+pub fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>, ResolveError> {
+	let host = CString::new(host).map_err(|_| ResolveError::InvalidHost)?;
+	let mut hints: libc::addrinfo = unsafe { zeroed() };
+	hints.ai_family = libc::AF_UNSPEC;
+	hints.ai_socktype = libc::SOCK_STREAM;
+
+	let mut list = core::ptr::null_mut();
+	let res = unsafe {
+		unix::getaddrinfo(
+			NonNullConst::from_ref(host.as_c_str()).cast(),
+			core::ptr::null(),
+			NonNullConst::from_ref(&hints),
+			NonNull::from(&mut list),
+		)
+	};
+	if res != 0 {
+		return Err(ResolveError::from_raw(res));
+	}
+
+	let mut addrs = Vec::new();
+	let mut next = list;
+	while let Some(info) = NonNull::new(next) {
+		let info = unsafe { info.as_ref() };
+		if let Some(addr) = NonNullConst::new(info.ai_addr.cast_const())
+			.and_then(|addr| unsafe { SocketAddr::from_raw(addr) })
+		{
+			addrs.push(SocketAddr { port, ..addr });
+		}
+		next = info.ai_next;
+	}
+	if let Some(list) = NonNull::new(list) {
+		unsafe { unix::freeaddrinfo(list) };
+	}
+
+	if addrs.is_empty() {
+		Err(ResolveError::NotFound)
+	} else {
+		Ok(addrs)
+	}
+}
+
+/// An error from [`resolve`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ResolveError {
+	/// The host doesn't exist, or has no addresses.
+	NotFound,
+	/// The name server couldn't be reached, or didn't answer in time. Trying
+	/// again later may work.
+	TemporaryFailure,
+	/// The host contained a nul byte.
+	InvalidHost,
+	Os(os::Error),
+	/// Any other `getaddrinfo` error code.
+	Other(c_int),
+}
+impl ResolveError {
+	fn from_raw(code: c_int) -> Self {
+		match code {
+			libc::EAI_NONAME => Self::NotFound,
+			libc::EAI_AGAIN => Self::TemporaryFailure,
+			libc::EAI_SYSTEM => Self::Os(os::Error::last()),
+			code => Self::Other(code),
+		}
+	}
+}
+impl Display for ResolveError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::NotFound => f.write_str("host not found"),
+			Self::TemporaryFailure => f.write_str("temporary failure in name resolution"),
+			Self::InvalidHost => f.write_str("host contains a nul byte"),
+			Self::Os(err) => write!(f, "{err}"),
+			Self::Other(code) => write!(f, "name resolution failed (error {code})"),
+		}
+	}
+}
+
+/// An error from [`TcpStream::connect_to_host`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConnectError {
+	Resolve(ResolveError),
+	/// Connecting to the host's last address failed.
+	Os(os::Error),
+}
+impl From<ResolveError> for ConnectError {
+	fn from(err: ResolveError) -> Self {
+		Self::Resolve(err)
+	}
+}
+impl From<os::Error> for ConnectError {
+	fn from(err: os::Error) -> Self {
+		Self::Os(err)
+	}
+}
+impl Display for ConnectError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			Self::Resolve(err) => write!(f, "{err}"),
+			Self::Os(err) => write!(f, "{err}"),
+		}
+	}
+}
+
+/// A TCP connection. The connection is closed when this is dropped.
+#[derive(Debug)]
+pub struct TcpStream(FileDescriptor);
+impl TcpStream {
+	/// Connects to `addr`, blocking until the connection is made or fails.
+	pub fn connect(addr: SocketAddr) -> Result<Self, os::Error> {
+		let this = Self(unix::new_socket(addr.family(), libc::SOCK_STREAM)?);
+		let (raw, len) = addr.to_raw();
+		let res = unsafe { unix::connect(this.0, NonNullConst::from_ref(&raw).cast(), len) };
+		if res == -1 {
+			return Err(os::Error::last());
+		}
+
+		Ok(this)
+	}
+	/// [Resolves](resolve) `host`, then tries to connect to each of its
+	/// addresses in turn.
+	pub fn connect_to_host(host: &str, port: u16) -> Result<Self, ConnectError> {
+		let mut last_err = None;
+		for addr in resolve(host, port)? {
+			match Self::connect(addr) {
+				Ok(this) => return Ok(this),
+				Err(err) => last_err = Some(err),
+			}
+		}
+
+		// `resolve` never returns an empty list
+		Err(ConnectError::Os(last_err.unwrap()))
+	}
+
+	/// The address of this end of the connection.
+	pub fn local_addr(&self) -> Result<SocketAddr, os::Error> {
+		local_addr(self.0)
+	}
+	/// Sets whether reading and writing block. Non-blocking streams error with
+	/// [`os::ErrorKind::WouldBlock`] instead.
+	pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), os::Error> {
+		set_nonblocking(self.0, nonblocking)
+	}
+	/// The stream's file descriptor, which can be waited on with `poll` and
+	/// similar APIs.
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+}
+impl Reader for TcpStream {
+	type Error = os::Error;
+
+	/// Reads as many bytes as are available, blocking until at least one is.
+	/// Returns 0 once the other end has closed the connection.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		unix::read_fd(self.0, buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+impl Writer for TcpStream {
+	type Error = os::Error;
+
+	/// Writes as many of `bytes` as the socket accepts. Errors with
+	/// [`os::ErrorKind::BrokenPipe`] once the other end has closed the
+	/// connection, instead of killing the process with `SIGPIPE`.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		unix::send_fd(self.0, bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+	/// Sockets aren't buffered, so this does nothing.
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+/// Lets one part of a program read from a stream while another writes to it.
+impl Reader for &TcpStream {
+	type Error = os::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		unix::read_fd(self.0, buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+impl Writer for &TcpStream {
+	type Error = os::Error;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		unix::send_fd(self.0, bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+impl Drop for TcpStream {
+	fn drop(&mut self) {
+		unsafe { unix::close(self.0) };
+	}
+}
+
+/// A TCP socket that accepts connections. The socket is closed when this is
+/// dropped.
+#[derive(Debug)]
+pub struct TcpListener(FileDescriptor);
+impl TcpListener {
+	/// Listens for connections to `addr`. If its port is 0, the OS picks a free
+	/// port; use [`TcpListener::local_addr`] to find out which.
+	pub fn bind(addr: SocketAddr) -> Result<Self, os::Error> {
+		let this = Self(unix::new_socket(addr.family(), libc::SOCK_STREAM)?);
+
+		// Lets a server restart straight away, instead of waiting for
+		// connections from its last run to time out
+		let enable: c_int = 1;
+		let res = unsafe {
+			unix::setsockopt(
+				this.0,
+				libc::SOL_SOCKET,
+				libc::SO_REUSEADDR,
+				NonNullConst::from_ref(&enable).cast(),
+				size_of::<c_int>() as libc::socklen_t,
+			)
+		};
+		if res == -1 {
+			return Err(os::Error::last());
+		}
+
+		let (raw, len) = addr.to_raw();
+		if unsafe { unix::bind(this.0, NonNullConst::from_ref(&raw).cast(), len) } == -1 {
+			return Err(os::Error::last());
+		}
+		if unix::listen(this.0, LISTEN_BACKLOG) == -1 {
+			return Err(os::Error::last());
+		}
+
+		Ok(this)
+	}
+
+	/// Waits for a connection, and returns it along with the address it came
+	/// from.
+	pub fn accept(&self) -> Result<(TcpStream, SocketAddr), os::Error> {
+		let mut raw: RawAddr = unsafe { zeroed() };
+		let mut len = size_of::<RawAddr>() as libc::socklen_t;
+		let fd = unsafe {
+			unix::accept_socket(
+				self.0,
+				NonNull::from(&mut raw).cast(),
+				NonNull::from(&mut len),
+			)?
+		};
+		let stream = TcpStream(fd);
+		// TCP connections always come from an IP address
+		let addr = unsafe { SocketAddr::from_raw(NonNullConst::from_ref(&raw).cast()) }.unwrap();
+
+		Ok((stream, addr))
+	}
+	/// The address this listener is bound to.
+	pub fn local_addr(&self) -> Result<SocketAddr, os::Error> {
+		local_addr(self.0)
+	}
+	/// Sets whether [`TcpListener::accept`] blocks when there aren't any
+	/// connections waiting. Non-blocking listeners error with
+	/// [`os::ErrorKind::WouldBlock`] instead.
+	pub fn set_nonblocking(&self, nonblocking: bool) -> Result<(), os::Error> {
+		set_nonblocking(self.0, nonblocking)
+	}
+	/// The listener's file descriptor. It becomes readable when there's a
+	/// connection to accept, so it can be waited on with `poll` and similar
+	/// APIs.
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+}
+impl Drop for TcpListener {
+	fn drop(&mut self) {
+		unsafe { unix::close(self.0) };
+	}
+}
+
+fn set_nonblocking(fd: FileDescriptor, nonblocking: bool) -> Result<(), os::Error> {
+	let flags = unsafe { unix::fcntl(fd, libc::F_GETFL) };
+	if flags == -1 {
+		return Err(os::Error::last());
+	}
+	let flags = if nonblocking {
+		flags | libc::O_NONBLOCK
+	} else {
+		flags & !libc::O_NONBLOCK
+	};
+	if unsafe { unix::fcntl(fd, libc::F_SETFL, flags) } == -1 {
+		return Err(os::Error::last());
+	}
+
+	Ok(())
+}
+fn local_addr(fd: FileDescriptor) -> Result<SocketAddr, os::Error> {
+	let mut raw: RawAddr = unsafe { zeroed() };
+	let mut len = size_of::<RawAddr>() as libc::socklen_t;
+	let res =
+		unsafe { unix::getsockname(fd, NonNull::from(&mut raw).cast(), NonNull::from(&mut len)) };
+	if res == -1 {
+		return Err(os::Error::last());
+	}
+
+	// TCP sockets are always bound to an IP address
+	Ok(unsafe { SocketAddr::from_raw(NonNullConst::from_ref(&raw).cast()) }.unwrap())
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn display() {
+		assert_eq!(format!("{}", IpAddr::LOCALHOST), "127.0.0.1");
+		assert_eq!(format!("{}", IpAddr::UNSPECIFIED_V6), "::");
+		assert_eq!(format!("{}", IpAddr::LOCALHOST_V6), "::1");
+		assert_eq!(
+			format!("{}", IpAddr::V6([0x2001, 0xdb8, 0, 0, 1, 0, 0, 1])),
+			"2001:db8::1:0:0:1"
+		);
+		assert_eq!(
+			format!("{}", IpAddr::V6([0x2001, 0xdb8, 0, 1, 1, 1, 1, 1])),
+			"2001:db8:0:1:1:1:1:1"
+		);
+		assert_eq!(
+			format!("{}", SocketAddr::new(IpAddr::LOCALHOST_V6, 80)),
+			"[::1]:80"
+		);
+	}
+
+	#[test]
+	fn loopback() {
+		let listener = TcpListener::bind(SocketAddr::new(IpAddr::LOCALHOST, 0)).unwrap();
+		let addr = listener.local_addr().unwrap();
+		assert_eq!(addr.ip, IpAddr::LOCALHOST);
+		assert_ne!(addr.port, 0);
+
+		// The connection is queued until it's accepted, so this doesn't block
+		let mut client = TcpStream::connect(addr).unwrap();
+		let (mut server, peer) = listener.accept().unwrap();
+		assert_eq!(peer, client.local_addr().unwrap());
+
+		client.write_all(b"ping").unwrap();
+		let mut buf = [0; 4];
+		assert_eq!(server.read(&mut buf), Ok(4));
+		assert_eq!(&buf, b"ping");
+
+		drop(client);
+		assert_eq!(server.read(&mut buf), Ok(0));
+	}
+
+	#[test]
+	fn resolve_localhost() {
+		let addrs = resolve("127.0.0.1", 8080).unwrap();
+		assert_eq!(addrs[0], SocketAddr::new(IpAddr::LOCALHOST, 8080));
+		assert_eq!(resolve("local\0host", 80), Err(ResolveError::InvalidHost));
+	}
+}
@@ -0,0 +1,215 @@
+//! Information about the machine the program is running on.
+//!
+//! Everything here is queried from the OS when it's called, so values that
+//! can change while the program runs (like [`available_memory`]) are always
+//! up to date. Most of it is useful for sizing thread pools and arenas:
+//!
+//! ```rs
+//! let pool = ThreadPool::new(sys::logical_cpus());
+//! let arena = VirtualMemoryArena::new(sys::total_memory() / MemoryAmount::bytes(4))?;
+//! ```
+//!
+//! Only the CPU counts are available on Windows for now; everything else is
+//! Unix-only.
+
+#[cfg(unix)]
+use crate::{
+	ffi::{CStr, c_char},
+	rt::os::unix,
+};
+use crate::{lang::cfg, rt::os};
+
+/// The CPU architecture the program was compiled for, in the same format as
+/// Rust's `target_arch` (e.g. `x86_64` or `aarch64`).
+pub const ARCH: &str = if cfg!(target_arch = "x86_64") {
+	"x86_64"
+} else if cfg!(target_arch = "aarch64") {
+	"aarch64"
+} else if cfg!(target_arch = "x86") {
+	"x86"
+} else if cfg!(target_arch = "arm") {
+	"arm"
+} else if cfg!(target_arch = "riscv64") {
+	"riscv64"
+} else {
+	"unknown"
+};
+
+/// How many CPUs can run threads at the same time, counting each hardware
+/// thread of a core with simultaneous multithreading (e.g. Hyper-Threading)
+/// as its own CPU. This is always at least 1.
+pub fn logical_cpus() -> usize {
+	#[cfg(unix)]
+	let count = unix::sysconf(libc::_SC_NPROCESSORS_ONLN) as usize;
+	#[cfg(windows)]
+	let count = os::win32::system_info().number_of_processors as usize;
+
+	count.max(1)
+}
+/// How many physical CPU cores the machine has. This is less than
+/// [`logical_cpus`] on CPUs with simultaneous multithreading. Falls back to
+/// [`logical_cpus`] if the OS doesn't say how many cores there are.
+pub fn physical_cpus() -> usize {
+	#[cfg(linux)]
+	let count = {
+		// Each logical CPU has a block in `/proc/cpuinfo`; logical CPUs on
+		// the same core have the same physical ID (socket) and core ID
+		let cpuinfo = unix::read_file(c"/proc/cpuinfo").unwrap_or_default();
+		let mut cores = Vec::new();
+		let mut socket = None;
+		for line in cpuinfo.split(|byte| *byte == b'\n') {
+			match proc_field(line) {
+				Some(("physical id", id)) => socket = Some(id),
+				Some(("core id", id)) => cores.push((socket, id)),
+				_ => {}
+			}
+		}
+		cores.sort_unstable();
+		cores.dedup();
+		cores.len()
+	};
+	#[cfg(macos)]
+	let count = sysctl_int::<i32>(c"hw.physicalcpu").unwrap_or(0) as usize;
+	#[cfg(windows)]
+	let count = 0;
+
+	if count == 0 { logical_cpus() } else { count }
+}
+
+/// How much physical memory the machine has.
+#[cfg(unix)]
+pub fn total_memory() -> MemoryAmount {
+	let pages = unix::sysconf(libc::_SC_PHYS_PAGES).max(0) as usize;
+	MemoryAmount::bytes(pages * crate::rt::mem::page_size())
+}
+/// How much physical memory can be allocated right now without swapping.
+///
+/// On Linux, this includes memory the OS is using for caches that it can
+/// free. On macOS, it's only memory that isn't being used at all, so it's
+/// usually an underestimate.
+#[cfg(unix)]
+pub fn available_memory() -> Result<MemoryAmount, os::Error> {
+	#[cfg(linux)]
+	{
+		let meminfo = unix::read_file(c"/proc/meminfo")?;
+		meminfo
+			.split(|byte| *byte == b'\n')
+			.find_map(|line| match proc_field(line)? {
+				("MemAvailable", amount) => amount.strip_suffix(" kB")?.parse().ok(),
+				_ => None,
+			})
+			.map(MemoryAmount::kibibytes)
+			.ok_or(os::Error::from_raw(libc::ENOTSUP))
+	}
+	#[cfg(macos)]
+	{
+		let pages = sysctl_int::<u32>(c"vm.page_free_count")? as usize;
+		Ok(MemoryAmount::bytes(pages * crate::rt::mem::page_size()))
+	}
+}
+
+/// The machine's name on the network.
+#[cfg(unix)]
+pub fn hostname() -> Result<String, os::Error> {
+	let name = uname()?.nodename;
+	Ok(c_chars_to_string(&name))
+}
+/// The version of the operating system. The format depends on the OS: this is
+/// the kernel version on Linux (e.g. `6.8.0-45-generic`), and the product
+/// version on macOS (e.g. `14.5`).
+#[cfg(unix)]
+pub fn os_version() -> Result<String, os::Error> {
+	#[cfg(linux)]
+	{
+		let release = uname()?.release;
+		Ok(c_chars_to_string(&release))
+	}
+	#[cfg(macos)]
+	{
+		let mut buf = [0 as c_char; 64];
+		sysctl(c"kern.osproductversion", &mut buf)?;
+		Ok(c_chars_to_string(&buf))
+	}
+}
+
+//
+//
+// OS helpers
+//
+//
+
+#[cfg(unix)]
+fn uname() -> Result<libc::utsname, os::Error> {
+	let mut info = MaybeUninit::uninit();
+	if unsafe { unix::uname(NonNull::from(&mut info).cast()) } != 0 {
+		return Err(os::Error::last());
+	}
+	Ok(unsafe { info.assume_init() })
+}
+
+/// Converts a nul-terminated string in a fixed-size buffer, like the ones in
+/// `utsname`, to a [`String`].
+#[cfg(unix)]
+fn c_chars_to_string(chars: &[c_char]) -> String {
+	let bytes = unsafe { &*(chars as *const [c_char] as *const [u8]) };
+	match CStr::from_bytes_until_nul(bytes) {
+		Ok(string) => string.to_string_lossy().into_owned(),
+		Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+	}
+}
+
+/// Splits a `key: value` line from a file in `/proc`, trimming the
+/// whitespace around the key and value.
+#[cfg(linux)]
+fn proc_field(line: &[u8]) -> Option<(&str, &str)> {
+	let (key, value) = str::from_utf8(line).ok()?.split_once(':')?;
+	Some((key.trim(), value.trim()))
+}
+
+/// Reads the sysctl called `name` into `buf`, and returns how many bytes were
+/// written.
+#[cfg(macos)]
+fn sysctl<T>(name: &CStr, buf: &mut [T]) -> Result<usize, os::Error> {
+	let mut len = size_of_val(buf);
+	let res = unsafe {
+		unix::sysctlbyname(
+			NonNullConst::from_ref(name).cast(),
+			Some(NonNull::from(buf).cast()),
+			Some(NonNull::from(&mut len)),
+			None,
+			0,
+		)
+	};
+	if res != 0 {
+		return Err(os::Error::last());
+	}
+	Ok(len)
+}
+/// Reads a sysctl that's an integer.
+#[cfg(macos)]
+fn sysctl_int<T: Default + Copy>(name: &CStr) -> Result<T, os::Error> {
+	let mut value = [T::default()];
+	sysctl(name, &mut value)?;
+	Ok(value[0])
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sys_info() {
+		assert!(physical_cpus() >= 1);
+		assert!(physical_cpus() <= logical_cpus());
+		assert!(available_memory().unwrap() <= total_memory());
+		assert!(!os_version().unwrap().is_empty());
+		assert!(hostname().is_ok());
+		assert_ne!(ARCH, "unknown");
+	}
+}
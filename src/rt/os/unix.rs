@@ -3,12 +3,15 @@
 use {
 	crate::{
 		ffi::*,
-		io::Writer,
-		lang::{Option, mem::NonNull},
+		io::{Reader, Writer},
+		lang::{Option, mem::NonNull, size_of, size_of_val, zeroed},
+		rt::{os, time::Duration},
 	},
 	libc,
 };
 
+pub mod users;
+
 /// An identifier for a currently open Unix file.
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -45,22 +48,665 @@ impl FileWriter {
 	}
 }
 impl Writer for FileWriter {
-	type Error = (); // TODO
+	type Error = os::Error;
 
+	/// Writes as many of `bytes` as the OS accepts. Errors with
+	/// [`os::ErrorKind::Interrupted`] if a signal arrives before anything is
+	/// written, and [`os::ErrorKind::WouldBlock`] if the file is non-blocking
+	/// and can't accept any bytes right now.
 	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		write_fd(self.0, bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		let res = unsafe { fsync(self.0) };
+		if res == 0 {
+			Ok(())
+		} else {
+			Err(os::Error::last())
+		}
+	}
+}
+
+/// Implements [`Reader`] for the given file descriptor.
+pub struct FileReader(FileDescriptor);
+impl FileReader {
+	/// Create a reader for the given [`FileDescriptor`].
+	///
+	///
+	/// # Safety
+	///
+	/// The caller must ensure they have exclusive read access to the given
+	/// file descriptor.
+	pub unsafe fn new(fd: FileDescriptor) -> Self {
+		Self(fd)
+	}
+}
+impl Reader for FileReader {
+	type Error = os::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		read_fd(self.0, buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+
+fn write_fd(fd: FileDescriptor, bytes: &[u8]) -> Result<usize, os::Error> {
+	let res = unsafe {
+		write(
+			fd,
+			NonNullConst::from_ref(bytes).cast(),
+			bytes.len() as c_size_t,
+		)
+	};
+	if res == -1 {
+		Err(os::Error::last())
+	} else {
+		Ok(res as usize)
+	}
+}
+pub(super) fn read_fd(fd: FileDescriptor, buf: &mut [u8]) -> Result<usize, os::Error> {
+	let len = buf.len() as c_size_t;
+	let res = unsafe { read(fd, NonNull::from(buf).cast(), len) };
+	if res == -1 {
+		Err(os::Error::last())
+	} else {
+		Ok(res as usize)
+	}
+}
+/// Reads the whole file at `path`. Meant for small files that report a size
+/// of 0 until they're read, like the ones in `/proc`.
+pub(crate) fn read_file(path: &CStr) -> Result<Vec<u8>, os::Error> {
+	let fd = unsafe { open(path.as_ptr(), OpenFlags::RDONLY | OpenFlags::CLOEXEC) };
+	if fd.as_raw() == -1 {
+		return Err(os::Error::last());
+	}
+	let mut contents = Vec::new();
+	let res = FileReader(fd).read_to_end(&mut contents);
+	unsafe { close(fd) };
+
+	res.map(|_| contents)
+}
+
+//
+//
+// Pipes & Redirection
+//
+//
+
+/// Creates a pipe: bytes written to the [`PipeWriter`] can be read from the
+/// [`PipeReader`]. Each half closes its end of the pipe when it's dropped.
+///
+/// ```rs
+/// let (mut reader, mut writer) = unix::pipe().unwrap();
+/// writer.write_all(b"hello").unwrap();
+/// drop(writer);
+///
+/// let mut output = Vec::new();
+/// reader.read_to_end(&mut output).unwrap();
+/// assert_eq!(output, b"hello");
+/// ```
+pub fn pipe() -> Result<(PipeReader, PipeWriter), os::Error> {
+	let mut fds = [FileDescriptor::STDIN; 2];
+	if unsafe { sys_pipe(NonNull::from(&mut fds)) } == -1 {
+		return Err(os::Error::last());
+	}
+	let [read_end, write_end] = fds;
+
+	Ok((PipeReader(read_end), PipeWriter(write_end)))
+}
+
+/// The read end of a [`pipe`]. Reading returns 0 bytes once every
+/// [`PipeWriter`] for the pipe has been dropped.
+#[derive(Debug)]
+pub struct PipeReader(FileDescriptor);
+impl PipeReader {
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+}
+impl Reader for PipeReader {
+	type Error = os::Error;
+
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		read_fd(self.0, buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+impl Drop for PipeReader {
+	fn drop(&mut self) {
+		unsafe { close(self.0) };
+	}
+}
+
+/// The write end of a [`pipe`]. Writing errors with
+/// [`os::ErrorKind::BrokenPipe`] once the [`PipeReader`] has been dropped.
+#[derive(Debug)]
+pub struct PipeWriter(FileDescriptor);
+impl PipeWriter {
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+}
+impl Writer for PipeWriter {
+	type Error = os::Error;
+
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		write_fd(self.0, bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+	/// Pipes aren't buffered, so this does nothing.
+	fn flush(&mut self) -> Result<(), Self::Error> {
+		Ok(())
+	}
+}
+impl Drop for PipeWriter {
+	fn drop(&mut self) {
+		unsafe { close(self.0) };
+	}
+}
+
+/// Makes `target` refer to the same file as `source`, e.g. to send the
+/// process' stdout into a pipe:
+///
+/// ```rs
+/// let (reader, writer) = unix::pipe().unwrap();
+/// unix::redirect(writer.fd(), FileDescriptor::STDOUT).unwrap();
+/// ```
+///
+/// If `target` was already open, it's closed first. See [`redirect_scoped`] to
+/// undo the redirection later.
+pub fn redirect(source: FileDescriptor, target: FileDescriptor) -> Result<(), os::Error> {
+	if unsafe { dup2(source, target) }.as_raw() == -1 {
+		Err(os::Error::last())
+	} else {
+		Ok(())
+	}
+}
+
+/// Like [`redirect`], but `target` is restored to the file it referred to
+/// before when the returned guard is dropped.
+pub fn redirect_scoped(
+	source: FileDescriptor,
+	target: FileDescriptor,
+) -> Result<Redirect, os::Error> {
+	let saved = unsafe { dup(target) };
+	if saved.as_raw() == -1 {
+		return Err(os::Error::last());
+	}
+	if let Err(err) = redirect(source, target) {
+		unsafe { close(saved) };
+		return Err(err);
+	}
+
+	Ok(Redirect { target, saved })
+}
+
+/// Restores a file descriptor that was redirected with [`redirect_scoped`]
+/// when it's dropped.
+#[derive(Debug)]
+pub struct Redirect {
+	target: FileDescriptor,
+	/// A duplicate of `target` from before it was redirected.
+	saved: FileDescriptor,
+}
+impl Drop for Redirect {
+	fn drop(&mut self) {
+		unsafe {
+			dup2(self.saved, self.target);
+			close(self.saved);
+		}
+	}
+}
+
+//
+//
+// Sockets
+//
+//
+
+/// A connected Unix domain socket: a two-way stream of bytes between two
+/// processes on the same machine. The socket is closed when this is dropped.
+///
+/// ```rs
+/// let mut socket = UnixStream::connect("/run/user/1000/wayland-0")?;
+/// socket.write_all(&message)?;
+/// ```
+#[derive(Debug)]
+pub struct UnixStream(FileDescriptor);
+impl UnixStream {
+	/// The most file descriptors [`UnixStream::read_with_fds`] receives at
+	/// once. Wayland, for example, never sends more than 28 per message.
+	pub const MAX_RECEIVED_FDS: usize = 32;
+
+	/// Connects to the socket at `path`.
+	pub fn connect(path: &str) -> Result<Self, os::Error> {
+		let mut addr: libc::sockaddr_un = unsafe { zeroed() };
+		addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+		// `sun_path` has to fit the nul terminator too
+		if path.len() >= addr.sun_path.len() {
+			return Err(os::Error::from_raw(libc::ENAMETOOLONG));
+		}
+		if path.contains('\0') {
+			return Err(os::Error::from_raw(libc::EINVAL));
+		}
+		for (dst, src) in addr.sun_path.iter_mut().zip(path.bytes()) {
+			*dst = src as c_char;
+		}
+
+		let this = Self(new_socket(libc::AF_UNIX, libc::SOCK_STREAM)?);
 		let res = unsafe {
-			write(
-				self.0,
-				NonNullConst::from_ref(&bytes[0]).cast(),
-				bytes.len() as c_size_t,
+			connect(
+				this.0,
+				NonNullConst::from_ref(&addr).cast(),
+				size_of::<libc::sockaddr_un>() as libc::socklen_t,
 			)
 		};
-		if res == -1 { Err(()) } else { Ok(res as usize) }
+		if res == -1 {
+			return Err(os::Error::last());
+		}
+
+		Ok(this)
+	}
+	/// Creates two sockets that are connected to each other.
+	pub fn pair() -> Result<(Self, Self), os::Error> {
+		let mut fds = [FileDescriptor::STDIN; 2];
+		let ty = socket_type(libc::SOCK_STREAM);
+		if unsafe { socketpair(libc::AF_UNIX, ty, 0, NonNull::from(&mut fds)) } == -1 {
+			return Err(os::Error::last());
+		}
+		let [a, b] = fds;
+		let pair = (Self(a), Self(b));
+		#[cfg(macos)]
+		{
+			configure_socket(a)?;
+			configure_socket(b)?;
+		}
+
+		Ok(pair)
+	}
+
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+	/// Waits until there's something to read from the socket, or the other
+	/// end closes it. Returns `false` if `timeout` passes first; `None` waits
+	/// forever.
+	pub fn wait_readable(&self, timeout: Option<Duration>) -> Result<bool, os::Error> {
+		let mut fd = libc::pollfd {
+			fd: self.0.as_raw(),
+			events: libc::POLLIN,
+			revents: 0,
+		};
+		// Rounded up, so short timeouts don't turn into busy loops
+		let timeout = match timeout {
+			Some(timeout) => timeout
+				.as_nanos()
+				.div_ceil(1_000_000)
+				.min(c_int::MAX as u128) as c_int,
+			None => -1,
+		};
+		match unsafe { poll(NonNull::from_mut(&mut fd), 1, timeout) } {
+			-1 => Err(os::Error::last()),
+			0 => Ok(false),
+			_ => Ok(true),
+		}
+	}
+
+	/// Like [`Writer::write`], but also sends `fds` to the other end of the
+	/// socket as `SCM_RIGHTS` ancillary data. The other process gets its own
+	/// copies of the file descriptors, so these can be closed once they've
+	/// been sent.
+	///
+	/// The file descriptors are only sent if at least one byte is written.
+	pub fn write_with_fds(
+		&mut self,
+		bytes: &[u8],
+		fds: &[FileDescriptor],
+	) -> Result<usize, os::Error> {
+		if fds.is_empty() {
+			return self.write(bytes);
+		}
+
+		let fds_len = size_of_val(fds) as c_uint;
+		let control_len = unsafe { libc::CMSG_SPACE(fds_len) } as usize;
+		// Stored as `u64`s so the control message is aligned
+		let mut control: Vec<u64> =
+			core::iter::repeat_n(0, control_len.div_ceil(size_of::<u64>())).collect();
+
+		let mut iov = libc::iovec {
+			iov_base: bytes.as_ptr().cast_mut().cast(),
+			iov_len: bytes.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { zeroed() };
+		msg.msg_iov = &raw mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = control.as_mut_ptr().cast();
+		msg.msg_controllen = control_len as _;
+		unsafe {
+			let cmsg = libc::CMSG_FIRSTHDR(&raw const msg);
+			(*cmsg).cmsg_level = libc::SOL_SOCKET;
+			(*cmsg).cmsg_type = libc::SCM_RIGHTS;
+			(*cmsg).cmsg_len = libc::CMSG_LEN(fds_len) as _;
+			libc::CMSG_DATA(cmsg)
+				.cast::<FileDescriptor>()
+				.copy_from_nonoverlapping(fds.as_ptr(), fds.len());
+		}
+
+		let res = unsafe { sendmsg(self.0, NonNullConst::from_ref(&msg), SEND_FLAGS) };
+		if res == -1 {
+			Err(os::Error::last())
+		} else {
+			Ok(res as usize)
+		}
+	}
+	/// Like [`Reader::read`], but also receives file descriptors sent with
+	/// `SCM_RIGHTS` ancillary data, and adds them to `fds`. The caller owns
+	/// the received file descriptors, and is responsible for closing them.
+	///
+	/// At most [`UnixStream::MAX_RECEIVED_FDS`] file descriptors are received
+	/// per call; the OS closes any others. On Linux, reads stop after bytes
+	/// that had file descriptors sent with them, so file descriptors always
+	/// arrive with (or before) the bytes they were sent with.
+	pub fn read_with_fds(
+		&mut self,
+		buf: &mut [u8],
+		fds: &mut Vec<FileDescriptor>,
+	) -> Result<usize, os::Error> {
+		let max_fds_len = (Self::MAX_RECEIVED_FDS * size_of::<FileDescriptor>()) as c_uint;
+		let control_len = unsafe { libc::CMSG_SPACE(max_fds_len) } as usize;
+		// Stored as `u64`s so the control message is aligned
+		let mut control: Vec<u64> =
+			core::iter::repeat_n(0, control_len.div_ceil(size_of::<u64>())).collect();
+
+		let mut iov = libc::iovec {
+			iov_base: buf.as_mut_ptr().cast(),
+			iov_len: buf.len(),
+		};
+		let mut msg: libc::msghdr = unsafe { zeroed() };
+		msg.msg_iov = &raw mut iov;
+		msg.msg_iovlen = 1;
+		msg.msg_control = control.as_mut_ptr().cast();
+		msg.msg_controllen = control_len as _;
+
+		let res = unsafe { recvmsg(self.0, NonNull::from_mut(&mut msg), RECV_FLAGS) };
+		if res == -1 {
+			return Err(os::Error::last());
+		}
+
+		let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&raw const msg) };
+		while !cmsg.is_null() {
+			let (level, ty, len) =
+				unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type, (*cmsg).cmsg_len) };
+			if level == libc::SOL_SOCKET && ty == libc::SCM_RIGHTS {
+				// `cmsg_len` is only 32 bits on macOS
+				#[allow(clippy::unnecessary_cast)]
+				let len = len as usize;
+				let data_len = len - unsafe { libc::CMSG_LEN(0) } as usize;
+				let data = unsafe { libc::CMSG_DATA(cmsg) }.cast::<FileDescriptor>();
+				for idx in 0..data_len / size_of::<FileDescriptor>() {
+					fds.push(unsafe { data.add(idx).read_unaligned() });
+				}
+			}
+			cmsg = unsafe { libc::CMSG_NXTHDR(&raw const msg, cmsg) };
+		}
+
+		Ok(res as usize)
+	}
+}
+impl Reader for UnixStream {
+	type Error = os::Error;
+
+	/// Reads as many bytes as are available, blocking until at least one is.
+	/// Returns 0 once the other end has closed the connection.
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+		read_fd(self.0, buf)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
+	}
+}
+impl Writer for UnixStream {
+	type Error = os::Error;
+
+	/// Writes as many of `bytes` as the socket accepts. Errors with
+	/// [`os::ErrorKind::BrokenPipe`] once the other end has closed the
+	/// connection, instead of killing the process with `SIGPIPE`.
+	fn write(&mut self, bytes: &[u8]) -> Result<usize, Self::Error> {
+		send_fd(self.0, bytes)
+	}
+	fn is_interrupted(error: &Self::Error) -> bool {
+		error.kind() == os::ErrorKind::Interrupted
 	}
+	/// Sockets aren't buffered, so this does nothing.
 	fn flush(&mut self) -> Result<(), Self::Error> {
-		let res = unsafe { fsync(self.0) };
-		if res == 0 { Ok(()) } else { Err(()) }
+		Ok(())
+	}
+}
+impl Drop for UnixStream {
+	fn drop(&mut self) {
+		unsafe { close(self.0) };
+	}
+}
+
+/// Flags for receiving on a socket. On Linux, received file descriptors are
+/// closed when the process `exec`s, like the sockets themselves; macOS
+/// doesn't support `MSG_CMSG_CLOEXEC`.
+#[cfg(linux)]
+const RECV_FLAGS: c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(macos)]
+const RECV_FLAGS: c_int = 0;
+
+/// Flags for sending on a socket. Writing to a closed socket errors instead of
+/// raising `SIGPIPE`; macOS doesn't support `MSG_NOSIGNAL`, so `SO_NOSIGPIPE`
+/// is set on the socket instead.
+#[cfg(linux)]
+const SEND_FLAGS: c_int = libc::MSG_NOSIGNAL;
+#[cfg(macos)]
+const SEND_FLAGS: c_int = 0;
+
+/// `ty` with `SOCK_CLOEXEC` on platforms that support it, so sockets aren't
+/// leaked into child processes.
+fn socket_type(ty: c_int) -> c_int {
+	#[cfg(linux)]
+	{
+		ty | libc::SOCK_CLOEXEC
+	}
+	#[cfg(macos)]
+	{
+		ty
+	}
+}
+/// Creates a socket that isn't leaked into child processes, and doesn't raise
+/// `SIGPIPE`.
+pub(super) fn new_socket(domain: c_int, ty: c_int) -> Result<FileDescriptor, os::Error> {
+	let fd = socket(domain, socket_type(ty), 0);
+	if fd.as_raw() == -1 {
+		return Err(os::Error::last());
+	}
+	#[cfg(macos)]
+	if let Err(err) = configure_socket(fd) {
+		unsafe { close(fd) };
+		return Err(err);
 	}
+
+	Ok(fd)
+}
+/// Accepts a connection on a listening socket, writing the peer's address to
+/// `addr`. The new socket is set up like ones from [`new_socket`].
+///
+///
+/// # Safety
+///
+/// `len` must hold the size of the buffer `addr` points to.
+#[cfg(feature = "net")]
+pub(super) unsafe fn accept_socket(
+	fd: FileDescriptor,
+	addr: NonNull<libc::sockaddr>,
+	len: NonNull<libc::socklen_t>,
+) -> Result<FileDescriptor, os::Error> {
+	#[cfg(linux)]
+	let new = unsafe { accept4(fd, Some(addr), Some(len), libc::SOCK_CLOEXEC) };
+	#[cfg(macos)]
+	let new = unsafe { accept(fd, Some(addr), Some(len)) };
+	if new.as_raw() == -1 {
+		return Err(os::Error::last());
+	}
+	#[cfg(macos)]
+	if let Err(err) = configure_socket(new) {
+		unsafe { close(new) };
+		return Err(err);
+	}
+
+	Ok(new)
+}
+/// Sends `bytes` on a connected socket, without raising `SIGPIPE` if the other
+/// end has closed it.
+pub(super) fn send_fd(fd: FileDescriptor, bytes: &[u8]) -> Result<usize, os::Error> {
+	let res = unsafe {
+		send(
+			fd,
+			NonNullConst::from_ref(bytes).cast(),
+			bytes.len() as c_size_t,
+			SEND_FLAGS,
+		)
+	};
+	if res == -1 {
+		Err(os::Error::last())
+	} else {
+		Ok(res as usize)
+	}
+}
+/// macOS can't set these flags when the socket is created.
+#[cfg(macos)]
+fn configure_socket(fd: FileDescriptor) -> Result<(), os::Error> {
+	if unsafe { fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+		return Err(os::Error::last());
+	}
+	let enable: c_int = 1;
+	let res = unsafe {
+		setsockopt(
+			fd,
+			libc::SOL_SOCKET,
+			libc::SO_NOSIGPIPE,
+			NonNullConst::from_ref(&enable).cast(),
+			size_of::<c_int>() as libc::socklen_t,
+		)
+	};
+	if res == -1 {
+		Err(os::Error::last())
+	} else {
+		Ok(())
+	}
+}
+
+//
+//
+// Shared Memory
+//
+//
+
+/// A file that only exists in RAM. Its file descriptor can be sent to another
+/// process (e.g. with [`UnixStream::write_with_fds`]), and both processes can
+/// map it with [`mem::map_file`] to share memory. The file is closed when this
+/// is dropped, but its memory lives until every mapping of it is unmapped.
+///
+/// [`mem::map_file`]: crate::rt::mem::map_file
+#[cfg(linux)]
+#[derive(Debug)]
+pub struct MemoryFile(FileDescriptor);
+#[cfg(linux)]
+impl MemoryFile {
+	/// Creates an empty file. `name` is only used for debugging (it shows up
+	/// in `/proc/<pid>/fd`), so it doesn't have to be unique.
+	pub fn new(name: &CStr) -> Result<Self, os::Error> {
+		let fd = unsafe { memfd_create(NonNullConst::from_ref(name).cast(), libc::MFD_CLOEXEC) };
+		if fd.as_raw() == -1 {
+			Err(os::Error::last())
+		} else {
+			Ok(Self(fd))
+		}
+	}
+
+	/// Grows or shrinks the file to `len` bytes. New bytes are zeroed.
+	pub fn set_len(&self, len: usize) -> Result<(), os::Error> {
+		let len = c_off_t::try_from(len).or(Err(os::Error::from_raw(libc::EFBIG)))?;
+		if unsafe { ftruncate(self.0, len) } == -1 {
+			Err(os::Error::last())
+		} else {
+			Ok(())
+		}
+	}
+	pub fn fd(&self) -> FileDescriptor {
+		self.0
+	}
+}
+#[cfg(linux)]
+impl Drop for MemoryFile {
+	fn drop(&mut self) {
+		unsafe { close(self.0) };
+	}
+}
+
+/// Returns the current thread's `errno`. Prefer [`os::Error::last`], which
+/// wraps this in a cross-platform error type.
+pub fn errno() -> c_int {
+	#[cfg(linux)]
+	let ptr = __errno_location();
+	#[cfg(macos)]
+	let ptr = __error();
+
+	unsafe { *ptr.as_ptr() }
+}
+
+/// A point in time, as returned by [`clock_gettime`].
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeSpec {
+	pub tv_sec: libc::time_t,
+	pub tv_nsec: c_long,
+}
+
+/// A duration with microsecond precision, as used by [`setitimer`].
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimeVal {
+	pub tv_sec: libc::time_t,
+	pub tv_usec: libc::suseconds_t,
+}
+
+/// The value of an interval timer, as used by [`setitimer`].
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ITimerVal {
+	/// How often the timer repeats after it first expires. Zero means the
+	/// timer only expires once.
+	pub it_interval: TimeVal,
+	/// How long until the timer expires. Zero disarms the timer.
+	pub it_value: TimeVal,
+}
+
+/// Information about the symbol an address belongs to, as returned by
+/// [`dladdr`].
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DlInfo {
+	/// The path of the object containing the address.
+	pub dli_fname: Option<NonNull<c_char>>,
+	/// The address the object was loaded at.
+	pub dli_fbase: Option<NonNull<c_void>>,
+	/// The name of the nearest symbol below the address.
+	pub dli_sname: Option<NonNull<c_char>>,
+	/// The address of the symbol in `dli_sname`.
+	pub dli_saddr: Option<NonNull<c_void>>,
 }
 
 bitset! {
@@ -71,13 +717,19 @@ bitset! {
 		CREAT = libc::O_CREAT,
 		DIRECTORY = libc::O_DIRECTORY,
 		DSYNC = libc::O_DSYNC,
+		/// Opens a file only to watch it for changes with `kqueue`.
+		#[cfg(macos)]
+		EVTONLY = libc::O_EVTONLY,
 		EXCL = libc::O_EXCL,
 		NOCTTY = libc::O_NOCTTY,
 		NOFOLLOW = libc::O_NOFOLLOW,
 		NONBLOCK = libc::O_NONBLOCK,
 		NDELAY = libc::O_NDELAY,
+		RDONLY = libc::O_RDONLY,
+		RDWR = libc::O_RDWR,
 		SYNC = libc::O_SYNC,
-		TRUNC = libc::O_TRUNC
+		TRUNC = libc::O_TRUNC,
+		WRONLY = libc::O_WRONLY
 	}
 }
 
@@ -94,7 +746,7 @@ unsafe extern "C" {
 	) -> *mut c_void;
 	pub unsafe fn munmap(addr: NonNull<c_void>, length: c_size_t) -> c_int;
 	pub unsafe fn mprotect(addr: NonNull<c_void>, size: c_size_t, prot: c_int) -> c_int;
-	pub unsafe fn open(path: *const c_char, flags: OpenFlags) -> FileDescriptor;
+	pub unsafe fn open(path: *const c_char, flags: OpenFlags, ...) -> FileDescriptor;
 	pub unsafe fn read(fd: FileDescriptor, buf: NonNull<c_void>, count: c_size_t) -> c_ssize_t;
 	pub unsafe fn write(
 		fd: FileDescriptor,
@@ -102,13 +754,227 @@ unsafe extern "C" {
 		count: c_size_t,
 	) -> c_ssize_t;
 	pub unsafe fn fsync(fd: FileDescriptor) -> c_int;
-	// The `Option<NonNullConst<c_char>>` triggers this. Even though
-	// `Option<NonNull<c_char>>` and `Option<*const c_char)` are fine. So
-	// presumably a linting mistake.
-	#[allow(improper_ctypes)]
-	pub unsafe fn getenv(name: NonNullConst<c_char>) -> Option<NonNullConst<c_char>>;
+	pub unsafe fn ftruncate(fd: FileDescriptor, len: c_off_t) -> c_int;
+	#[cfg(linux)]
+	pub unsafe fn memfd_create(name: NonNullConst<c_char>, flags: c_uint) -> FileDescriptor;
+	#[cfg_attr(all(macos, target_arch = "x86_64"), link_name = "stat$INODE64")]
+	pub unsafe fn stat(path: NonNullConst<c_char>, buf: NonNull<libc::stat>) -> c_int;
+	#[cfg_attr(all(macos, target_arch = "x86_64"), link_name = "lstat$INODE64")]
+	pub unsafe fn lstat(path: NonNullConst<c_char>, buf: NonNull<libc::stat>) -> c_int;
+	#[cfg_attr(all(macos, target_arch = "x86_64"), link_name = "fstat$INODE64")]
+	pub unsafe fn fstat(fd: FileDescriptor, buf: NonNull<libc::stat>) -> c_int;
+	pub unsafe fn readlink(
+		path: NonNullConst<c_char>,
+		buf: NonNull<c_char>,
+		size: c_size_t,
+	) -> c_ssize_t;
+	pub unsafe fn chmod(path: NonNullConst<c_char>, mode: libc::mode_t) -> c_int;
+	pub unsafe fn mkdir(path: NonNullConst<c_char>, mode: libc::mode_t) -> c_int;
+	pub unsafe fn rmdir(path: NonNullConst<c_char>) -> c_int;
+	pub unsafe fn unlink(path: NonNullConst<c_char>) -> c_int;
+	pub unsafe fn symlink(target: NonNullConst<c_char>, link: NonNullConst<c_char>) -> c_int;
+	#[cfg_attr(all(macos, target_arch = "x86_64"), link_name = "opendir$INODE64")]
+	pub unsafe fn opendir(path: NonNullConst<c_char>) -> Option<NonNull<libc::DIR>>;
+	#[cfg_attr(all(macos, target_arch = "x86_64"), link_name = "readdir$INODE64")]
+	pub unsafe fn readdir(dir: NonNull<libc::DIR>) -> Option<NonNull<libc::dirent>>;
+	pub unsafe fn closedir(dir: NonNull<libc::DIR>) -> c_int;
+	pub unsafe fn getentropy(buf: NonNull<c_void>, len: c_size_t) -> c_int;
+	#[cfg(linux)]
+	pub safe fn inotify_init1(flags: c_int) -> FileDescriptor;
+	#[cfg(linux)]
+	pub unsafe fn inotify_add_watch(
+		fd: FileDescriptor,
+		path: NonNullConst<c_char>,
+		mask: u32,
+	) -> c_int;
+	#[cfg(linux)]
+	pub safe fn inotify_rm_watch(fd: FileDescriptor, wd: c_int) -> c_int;
+	#[cfg(macos)]
+	pub safe fn kqueue() -> FileDescriptor;
+	#[cfg(macos)]
+	pub unsafe fn kevent(
+		kq: FileDescriptor,
+		changelist: *const libc::kevent,
+		nchanges: c_int,
+		eventlist: *mut libc::kevent,
+		nevents: c_int,
+		timeout: *const TimeSpec,
+	) -> c_int;
+	pub unsafe fn getenv(name: NonNullConst<c_char>) -> *const c_char;
+	pub unsafe fn getcwd(buf: NonNull<c_char>, size: c_size_t) -> Option<NonNull<c_char>>;
+	pub unsafe fn chdir(path: NonNullConst<c_char>) -> c_int;
+	#[cfg(macos)]
+	pub unsafe fn _NSGetExecutablePath(buf: NonNull<c_char>, bufsize: NonNull<u32>) -> c_int;
 	pub unsafe fn fcntl(fd: FileDescriptor, op: c_int, ...) -> c_int;
 	pub safe fn exit(status: c_int) -> !;
+	pub unsafe fn close(fd: FileDescriptor) -> c_int;
+	#[link_name = "pipe"]
+	pub unsafe fn sys_pipe(fds: NonNull<[FileDescriptor; 2]>) -> c_int;
+	pub unsafe fn dup(fd: FileDescriptor) -> FileDescriptor;
+	pub unsafe fn dup2(old: FileDescriptor, new: FileDescriptor) -> FileDescriptor;
+	pub safe fn isatty(fd: FileDescriptor) -> c_int;
+	pub safe fn socket(domain: c_int, ty: c_int, protocol: c_int) -> FileDescriptor;
+	pub unsafe fn socketpair(
+		domain: c_int,
+		ty: c_int,
+		protocol: c_int,
+		fds: NonNull<[FileDescriptor; 2]>,
+	) -> c_int;
+	pub unsafe fn connect(
+		fd: FileDescriptor,
+		addr: NonNullConst<libc::sockaddr>,
+		len: libc::socklen_t,
+	) -> c_int;
+	pub unsafe fn bind(
+		fd: FileDescriptor,
+		addr: NonNullConst<libc::sockaddr>,
+		len: libc::socklen_t,
+	) -> c_int;
+	pub safe fn listen(fd: FileDescriptor, backlog: c_int) -> c_int;
+	#[cfg(linux)]
+	pub unsafe fn accept4(
+		fd: FileDescriptor,
+		addr: Option<NonNull<libc::sockaddr>>,
+		len: Option<NonNull<libc::socklen_t>>,
+		flags: c_int,
+	) -> FileDescriptor;
+	#[cfg(macos)]
+	pub unsafe fn accept(
+		fd: FileDescriptor,
+		addr: Option<NonNull<libc::sockaddr>>,
+		len: Option<NonNull<libc::socklen_t>>,
+	) -> FileDescriptor;
+	pub unsafe fn getsockname(
+		fd: FileDescriptor,
+		addr: NonNull<libc::sockaddr>,
+		len: NonNull<libc::socklen_t>,
+	) -> c_int;
+	pub unsafe fn getaddrinfo(
+		node: NonNullConst<c_char>,
+		service: *const c_char,
+		hints: NonNullConst<libc::addrinfo>,
+		res: NonNull<*mut libc::addrinfo>,
+	) -> c_int;
+	pub unsafe fn freeaddrinfo(res: NonNull<libc::addrinfo>);
+	pub unsafe fn send(
+		fd: FileDescriptor,
+		buf: NonNullConst<c_void>,
+		len: c_size_t,
+		flags: c_int,
+	) -> c_ssize_t;
+	pub unsafe fn recvmsg(
+		fd: FileDescriptor,
+		msg: NonNull<libc::msghdr>,
+		flags: c_int,
+	) -> c_ssize_t;
+	pub unsafe fn sendmsg(
+		fd: FileDescriptor,
+		msg: NonNullConst<libc::msghdr>,
+		flags: c_int,
+	) -> c_ssize_t;
+	pub unsafe fn poll(fds: NonNull<libc::pollfd>, count: libc::nfds_t, timeout: c_int) -> c_int;
+	pub unsafe fn setsockopt(
+		fd: FileDescriptor,
+		level: c_int,
+		name: c_int,
+		value: NonNullConst<c_void>,
+		len: libc::socklen_t,
+	) -> c_int;
+	pub unsafe fn fork() -> libc::pid_t;
+	pub safe fn setsid() -> libc::pid_t;
+	pub safe fn getpid() -> libc::pid_t;
+	pub safe fn getuid() -> libc::uid_t;
+	pub safe fn geteuid() -> libc::uid_t;
+	pub safe fn getgid() -> libc::gid_t;
+	pub safe fn getegid() -> libc::gid_t;
+	pub unsafe fn setuid(uid: libc::uid_t) -> c_int;
+	pub unsafe fn setgid(gid: libc::gid_t) -> c_int;
+	#[cfg(linux)]
+	pub unsafe fn setgroups(size: c_size_t, list: *const libc::gid_t) -> c_int;
+	#[cfg(macos)]
+	pub unsafe fn setgroups(size: c_int, list: *const libc::gid_t) -> c_int;
+	pub unsafe fn getpwuid_r(
+		uid: libc::uid_t,
+		passwd: NonNull<libc::passwd>,
+		buf: NonNull<c_char>,
+		buflen: c_size_t,
+		result: NonNull<Option<NonNull<libc::passwd>>>,
+	) -> c_int;
+	pub unsafe fn uname(buf: NonNull<libc::utsname>) -> c_int;
+	#[cfg(macos)]
+	pub unsafe fn sysctlbyname(
+		name: NonNullConst<c_char>,
+		oldp: Option<NonNull<c_void>>,
+		oldlenp: Option<NonNull<c_size_t>>,
+		newp: Option<NonNull<c_void>>,
+		newlen: c_size_t,
+	) -> c_int;
+	pub unsafe fn getrusage(who: c_int, usage: NonNull<libc::rusage>) -> c_int;
+	#[cfg(macos)]
+	pub unsafe fn proc_pidinfo(
+		pid: libc::pid_t,
+		flavor: c_int,
+		arg: u64,
+		buffer: NonNull<c_void>,
+		size: c_int,
+	) -> c_int;
+	pub unsafe fn waitpid(
+		pid: libc::pid_t,
+		status: Option<NonNull<c_int>>,
+		options: c_int,
+	) -> libc::pid_t;
+	pub unsafe fn clock_gettime(clock: libc::clockid_t, time: NonNull<TimeSpec>) -> c_int;
+	pub unsafe fn sigaction(
+		signal: c_int,
+		action: NonNullConst<libc::sigaction>,
+		old: Option<NonNull<libc::sigaction>>,
+	) -> c_int;
+	pub unsafe fn sigaltstack(
+		stack: NonNullConst<libc::stack_t>,
+		old: Option<NonNull<libc::stack_t>>,
+	) -> c_int;
+	pub unsafe fn setitimer(
+		which: c_int,
+		new: NonNullConst<ITimerVal>,
+		old: Option<NonNull<ITimerVal>>,
+	) -> c_int;
+	pub unsafe fn syscall(number: c_long, ...) -> c_long;
+	pub unsafe fn pthread_create(
+		thread: NonNull<libc::pthread_t>,
+		attr: *const libc::pthread_attr_t,
+		start: extern "C" fn(NonNull<c_void>) -> Option<NonNull<c_void>>,
+		arg: NonNull<c_void>,
+	) -> c_int;
+	pub unsafe fn pthread_join(
+		thread: libc::pthread_t,
+		retval: Option<NonNull<Option<NonNull<c_void>>>>,
+	) -> c_int;
+	pub unsafe fn pthread_detach(thread: libc::pthread_t) -> c_int;
+	#[cfg(linux)]
+	pub safe fn __errno_location() -> NonNull<c_int>;
+	#[cfg(macos)]
+	pub safe fn __error() -> NonNull<c_int>;
+	// Undocumented, but stable; it's what libc++ and Rust's `std` use to
+	// implement futexes on macOS.
+	#[cfg(macos)]
+	pub unsafe fn __ulock_wait(
+		operation: u32,
+		addr: NonNull<c_void>,
+		value: u64,
+		timeout_us: u32,
+	) -> c_int;
+	#[cfg(macos)]
+	pub unsafe fn __ulock_wake(operation: u32, addr: NonNull<c_void>, wake_value: u64) -> c_int;
+	#[cfg(linux)]
+	pub safe fn getauxval(kind: c_ulong) -> c_ulong;
+	/// The path of the `index`th image dyld loaded. Image 0 is the
+	/// executable.
+	#[cfg(macos)]
+	pub safe fn _dyld_get_image_name(index: u32) -> Option<NonNull<c_char>>;
+	/// How far the `index`th image was loaded from the address it was linked
+	/// at.
+	#[cfg(macos)]
+	pub safe fn _dyld_get_image_vmaddr_slide(index: u32) -> isize;
 }
 #[link(name = "dl")]
 unsafe extern "C" {
@@ -129,4 +995,86 @@ unsafe extern "C" {
 		handle: NonNull<c_void>,
 		symbol: NonNullConst<c_char>,
 	) -> Option<NonNull<c_void>>;
+	pub unsafe fn dladdr(addr: NonNullConst<c_void>, info: NonNull<DlInfo>) -> c_int;
+	pub unsafe fn dlerror() -> *const c_char;
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pipes() {
+		let (mut reader, mut writer) = pipe().unwrap();
+		writer.write_all(b"hello").unwrap();
+		let mut buf = [0; 5];
+		assert_eq!(reader.read(&mut buf), Ok(5));
+		assert_eq!(&buf, b"hello");
+
+		// Once the pipe's buffer is full, non-blocking writes can't go through
+		unsafe { fcntl(writer.fd(), libc::F_SETFL, libc::O_NONBLOCK) };
+		let chunk = [0; 4096];
+		let err = loop {
+			if let Err(err) = writer.write(&chunk) {
+				break err;
+			}
+		};
+		assert_eq!(err.kind(), os::ErrorKind::WouldBlock);
+		assert_eq!(
+			writer.write_all(&chunk).unwrap_err().kind(),
+			os::ErrorKind::WouldBlock
+		);
+
+		drop(writer);
+		let mut output = Vec::new();
+		let read = reader.read_to_end(&mut output).unwrap();
+		assert!(read > 0 && read % chunk.len() == 0);
+	}
+
+	#[test]
+	fn redirection() {
+		let (mut reader, writer) = pipe().unwrap();
+		let (_, null) = pipe().unwrap();
+		{
+			let _redirect = redirect_scoped(writer.fd(), null.fd()).unwrap();
+			write_fd(null.fd(), b"redirected").unwrap();
+		}
+		drop(writer);
+		drop(null);
+
+		let mut output = Vec::new();
+		reader.read_to_end(&mut output).unwrap();
+		assert_eq!(output, b"redirected");
+	}
+
+	#[test]
+	fn sockets() {
+		let (mut a, mut b) = UnixStream::pair().unwrap();
+		assert_eq!(b.wait_readable(Some(Duration::ZERO)), Ok(false));
+		a.write_all(b"ping").unwrap();
+		assert_eq!(b.wait_readable(None), Ok(true));
+		let mut buf = [0; 4];
+		assert_eq!(b.read(&mut buf), Ok(4));
+		assert_eq!(&buf, b"ping");
+
+		drop(a);
+		assert_eq!(b.read(&mut buf), Ok(0));
+		assert_eq!(
+			b.write(b"pong").unwrap_err().kind(),
+			os::ErrorKind::BrokenPipe
+		);
+
+		assert_eq!(
+			UnixStream::connect("/nonexistent/crux.sock")
+				.unwrap_err()
+				.kind(),
+			os::ErrorKind::NotFound
+		);
+	}
 }
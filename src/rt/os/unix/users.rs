@@ -0,0 +1,156 @@
+//! Users, groups, and privileges.
+//!
+//! Daemons that need root to bind a low port usually drop to an unprivileged
+//! user as soon as it's bound, so a bug in the rest of the program can't be
+//! used to take over the machine:
+//!
+//! ```rs
+//! let listener = TcpListener::bind("0.0.0.0:80")?;
+//! if users::is_root() {
+//!     users::drop_privileges(nobody_uid, nobody_gid)?;
+//! }
+//! ```
+//!
+//! Every process has a real user ID, which is the user that started it, and an
+//! effective user ID, which is the user its permissions are checked as. They
+//! only differ for setuid programs. The same goes for group IDs.
+
+use crate::{
+	ffi::CStr,
+	rt::{
+		os::{self, unix},
+		proc,
+	},
+};
+
+/// The real user ID of the process: the user that started it.
+pub fn current_uid() -> u32 {
+	unix::getuid()
+}
+/// The effective user ID of the process: the user its permissions are
+/// checked as.
+pub fn effective_uid() -> u32 {
+	unix::geteuid()
+}
+/// The real group ID of the process.
+pub fn current_gid() -> u32 {
+	unix::getgid()
+}
+/// The effective group ID of the process.
+pub fn effective_gid() -> u32 {
+	unix::getegid()
+}
+
+/// If the process has root privileges, i.e. its effective user ID is 0.
+pub fn is_root() -> bool {
+	effective_uid() == 0
+}
+
+/// Returns the current user's home directory: the `HOME` environment variable
+/// if it's set, or the home directory in the user database otherwise.
+pub fn home_dir() -> Option<String> {
+	proc::get_env("HOME")
+		.filter(|dir| !dir.is_empty())
+		.or_else(|| passwd_home_dir(current_uid()))
+}
+
+/// Sets the process' user ID. If the process is root, this sets the real,
+/// effective, and saved user IDs, so root privileges can't be regained
+/// afterwards; otherwise, only the effective user ID can be changed, and only
+/// to the real or saved user ID.
+///
+/// To drop root privileges entirely, use [`drop_privileges`], which also
+/// changes the process' groups.
+pub fn set_uid(uid: u32) -> Result<(), os::Error> {
+	if unsafe { unix::setuid(uid) } == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+/// Sets the process' group ID. This follows the same rules as [`set_uid`],
+/// but checks the process' user ID, not its group ID, for root privileges;
+/// so groups have to be changed before the user is.
+pub fn set_gid(gid: u32) -> Result<(), os::Error> {
+	if unsafe { unix::setgid(gid) } == 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+/// Permanently switches a root process to the user `uid` and group `gid`,
+/// removing every supplementary group root was in. Errors if the process isn't
+/// root.
+///
+/// The changes are made in the order that works (groups, then the group ID,
+/// then the user ID), and the process checks it can't switch back to root
+/// afterwards, so a partial failure can't leave it with root privileges.
+pub fn drop_privileges(uid: u32, gid: u32) -> Result<(), os::Error> {
+	if unsafe { unix::setgroups(0, crate::lang::null_ptr()) } != 0 {
+		return Err(os::Error::last());
+	}
+	set_gid(gid)?;
+	set_uid(uid)?;
+
+	if uid != 0 && unsafe { unix::setuid(0) } == 0 {
+		return Err(os::Error::from_raw(libc::EPERM));
+	}
+	Ok(())
+}
+
+/// Looks up `uid`'s home directory in the user database (e.g.
+/// `/etc/passwd`).
+fn passwd_home_dir(uid: u32) -> Option<String> {
+	let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+	let mut result = None;
+	// Holds the strings `passwd` points to
+	let mut buf: Vec<u8> = Vec::with_capacity(1024);
+	loop {
+		let res = unsafe {
+			unix::getpwuid_r(
+				uid,
+				NonNull::from(&mut passwd).cast(),
+				NonNull::new_unchecked(buf.as_mut_ptr()).cast(),
+				buf.capacity(),
+				NonNull::from(&mut result),
+			)
+		};
+		match res {
+			0 => break,
+			libc::ERANGE => buf.reserve(buf.capacity() * 2),
+			_ => return None,
+		}
+	}
+
+	// `result` is only set if there's an entry for `uid`
+	let dir = unsafe { result?.as_ref() }.pw_dir;
+	NonNullConst::new(dir.cast_const())
+		.map(|dir| unsafe { CStr::from_ptr(dir.as_ptr()) })
+		.map(|dir| dir.to_string_lossy().into_owned())
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {super::*, crate::rt::os::ErrorKind};
+
+	#[test]
+	fn users() {
+		assert_eq!(is_root(), effective_uid() == 0);
+		assert!(home_dir().is_some_and(|dir| !dir.is_empty()));
+
+		// Switching to the IDs the process already has always works
+		set_gid(current_gid()).unwrap();
+		set_uid(current_uid()).unwrap();
+		if !is_root() {
+			assert_eq!(set_uid(0).unwrap_err().kind(), ErrorKind::PermissionDenied);
+			assert!(drop_privileges(current_uid(), current_gid()).is_err());
+		}
+	}
+}
@@ -0,0 +1,94 @@
+//! Lets applications run their own code when the program panics.
+//!
+//! A panic hook registered with [`set_hook`] is called by Crux's logging panic
+//! handler before it logs the panic and exits, which makes it a good place to
+//! flush buffers, write crash dumps, or notify a supervisor process.
+//!
+//! If the hook itself panics, the panic handler skips the hook the second time
+//! around, so a broken hook can't cause infinite recursion.
+//!
+//! Note that when the `std-compat` feature is enabled, `std` provides the panic
+//! handler, so hooks registered here are never called; use
+//! `std::panic::set_hook` instead.
+
+use core::{
+	panic::PanicInfo,
+	sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+/// A function called when the program panics. See [`set_hook`].
+///
+/// Hooks are `'static` references because a panicking thread may still be
+/// running a hook when another thread replaces it. Closures that don't capture
+/// anything can be passed directly; others can be leaked with [`Box::leak`].
+pub type PanicHook = &'static (dyn Fn(&PanicInfo) + Send + Sync);
+
+/// The currently registered hook, or null if there isn't one. [`PanicHook`] is
+/// a fat pointer and can't be stored atomically, so this points to a leaked
+/// allocation holding it.
+static HOOK: AtomicPtr<PanicHook> = AtomicPtr::new(core::ptr::null_mut());
+/// Set while the panic hook is running, to detect panics inside the hook.
+static IN_HOOK: AtomicBool = AtomicBool::new(false);
+
+/// Registers a function that's called when the program panics, replacing the
+/// previously registered hook (if there was one).
+///
+/// Each call leaks a pointer-sized allocation, so this shouldn't be called in a
+/// loop.
+pub fn set_hook(hook: PanicHook) {
+	HOOK.store(Box::leak(Box::new(hook)), Ordering::Release);
+}
+
+/// Unregisters the current panic hook and returns it, if there was one.
+pub fn take_hook() -> Option<PanicHook> {
+	let old = HOOK.swap(core::ptr::null_mut(), Ordering::AcqRel);
+	NonNull::new(old).map(|old| *unsafe { old.as_ref() })
+}
+
+/// Calls the registered panic hook, if there is one. Crux's logging panic
+/// handler calls this before logging the panic.
+///
+/// Does nothing if called while the hook is already running (i.e. if the hook
+/// panicked).
+pub fn call_hook(info: &PanicInfo) {
+	if IN_HOOK.swap(true, Ordering::AcqRel) {
+		return;
+	}
+
+	if let Some(hook) = NonNull::new(HOOK.load(Ordering::Acquire)) {
+		(unsafe { hook.as_ref() })(info);
+	}
+
+	IN_HOOK.store(false, Ordering::Release);
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn hooks() {
+		// Capture something so each hook has its own address
+		let make_hook = |id: u8| -> PanicHook {
+			Box::leak(Box::new(move |_: &PanicInfo| {
+				let _ = id;
+			}))
+		};
+		let first = make_hook(1);
+		let second = make_hook(2);
+
+		assert!(take_hook().is_none());
+		set_hook(first);
+		set_hook(second);
+
+		let hook = take_hook().unwrap();
+		assert!(core::ptr::addr_eq(hook, second));
+		assert!(take_hook().is_none());
+	}
+}
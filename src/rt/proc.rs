@@ -1,9 +1,20 @@
 //! Items for working with operating system processes.
 
+#[cfg(unix)]
+use crate::rt::time::Duration;
+#[cfg(windows)]
 use crate::{
-	ffi::{CStr, c_char},
-	rt::os,
-	text::FormatArgs,
+	concurrency::{AtomicBool, AtomicCell},
+	ffi::c_void,
+};
+use {
+	crate::{
+		ffi::{CStr, CString, c_char},
+		lang::{Cow, DoubleEndedIterator, null_ptr_mut},
+		rt::{hook::hook, os},
+		text::FormatArgs,
+	},
+	core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering as AtomicOrdering},
 };
 
 /// Halts the current process immediately.
@@ -89,10 +100,10 @@ pub fn write_stdout_fmt(args: FormatArgs) {
 #[macro_export]
 macro_rules! print {
 	($str:literal) => {
-		$crate::os::proc::write_stdout_fmt($crate::text::format_args!($str))
+		$crate::rt::proc::write_stdout_fmt($crate::text::format_args!($str))
 	};
 	($str:literal, $($arg:expr),*) => {
-		$crate::os::proc::write_stdout_fmt($crate::text::format_args!($str, $($arg),*))
+		$crate::rt::proc::write_stdout_fmt($crate::text::format_args!($str, $($arg),*))
 	};
 }
 pub use print;
@@ -175,7 +186,9 @@ pub fn get_env(name: &str) -> Option<String> {
 pub unsafe fn get_env_raw(name: &str) -> Option<NonNullConst<c_char>> {
 	#[cfg(unix)]
 	{
-		unsafe { os::unix::getenv(NonNullConst::from_ref(name).cast()) }
+		// `getenv` needs a nul-terminated name
+		let name = CString::new(name).ok()?;
+		NonNullConst::new(unsafe { os::unix::getenv(NonNullConst::from_ref(&*name).cast()) })
 	}
 	#[cfg(windows)]
 	{
@@ -185,13 +198,156 @@ pub unsafe fn get_env_raw(name: &str) -> Option<NonNullConst<c_char>> {
 	compile_error!("unimplemented on this operating system");
 }
 
+/// Returns an iterator over the arguments passed to the program via the CLI.
+/// Note that the 0th argument is typically the path to the executable, and not
+/// an argument you need to parse.
+///
+/// Arguments are read lazily: each [`Arg`] can be viewed as raw bytes or as
+/// UTF-8, and nothing is measured or converted until you ask for it.
+///
+/// The arguments are loaded by [`load_args`]. Before it runs (e.g. in ini
+/// functions, or startup hooks that run before [`startup_hook`]), or if the
+/// `cli-args` crate feature is disabled, this iterator is empty.
+///
+/// [`startup_hook`]: crate::rt::startup_hook
+pub fn args() -> Args {
+	Args {
+		#[cfg(unix)]
+		argv: argv().unwrap_or_default().iter(),
+	}
+}
+
+/// Stores the `argv` the program was started with, so [`args`] can read it.
+/// The arguments aren't measured or converted until they're read. Only used if
+/// the `cli-args` crate feature is enabled.
+#[cfg(feature = "cli-args")]
+pub fn load_args(#[allow(unused)] info: crate::rt::StartupHookInfo) {
+	// The length is stored first, so it's valid once the pointer is. If the
+	// hook somehow runs twice, it stores the same values again.
+	#[cfg(unix)]
+	{
+		ARGC.store(info.args.len(), AtomicOrdering::Relaxed);
+		ARGV.store(info.args.as_ptr().cast_mut(), AtomicOrdering::Release);
+	}
+}
+#[cfg(feature = "cli-args")]
+hook! {
+	/// See [`crate::rt::proc::load_args`].
+	event: crate::events::startup,
+	func: load_args,
+	phase: Early,
+	constraints: [
+		before(crate::hooks::startup_hook),
+	]
+}
+
+/// The `argv` stored by [`load_args`], or `None` if it hasn't run.
+fn argv() -> Option<&'static [*const u8]> {
+	#[cfg(unix)]
+	{
+		let argv = ARGV.load(AtomicOrdering::Acquire);
+		if argv.is_null() {
+			return None;
+		}
+		let argc = ARGC.load(AtomicOrdering::Relaxed);
+		Some(unsafe { core::slice::from_raw_parts(argv, argc) })
+	}
+	#[cfg(not(unix))]
+	{
+		None
+	}
+}
+// `argv` points to strings the OS gave the program, which nothing writes to,
+// so reading them from any thread is fine
+#[cfg(unix)]
+static ARGV: AtomicPtr<*const u8> = AtomicPtr::new(null_ptr_mut());
+#[cfg(unix)]
+static ARGC: AtomicUsize = AtomicUsize::new(0);
+
+/// An iterator over the program's CLI arguments. See [`args`].
+#[derive(Clone)]
+pub struct Args {
+	#[cfg(unix)]
+	argv: core::slice::Iter<'static, *const u8>,
+}
+impl Iterator for Args {
+	type Item = Arg;
+
+	fn next(&mut self) -> Option<Arg> {
+		#[cfg(unix)]
+		{
+			self.argv
+				.find_map(|arg| NonNullConst::new(*arg))
+				.map(|ptr| Arg(unsafe { CStr::from_ptr(ptr.as_ptr().cast()) }))
+		}
+		#[cfg(not(unix))]
+		{
+			None
+		}
+	}
+}
+impl DoubleEndedIterator for Args {
+	fn next_back(&mut self) -> Option<Arg> {
+		#[cfg(unix)]
+		{
+			self.argv
+				.rfind(|arg| !arg.is_null())
+				.map(|arg| Arg(unsafe { CStr::from_ptr(arg.cast()) }))
+		}
+		#[cfg(not(unix))]
+		{
+			None
+		}
+	}
+}
+
+/// One argument passed to the program via the CLI. See [`args`].
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Arg(&'static CStr);
+impl Arg {
+	/// The raw bytes of the argument. Their encoding is unspecified, as these
+	/// come straight from the operating system.
+	pub fn as_bytes(self) -> &'static [u8] {
+		self.0.to_bytes()
+	}
+	/// The argument as a UTF-8 string, if it's valid UTF-8.
+	pub fn to_str(self) -> Option<&'static str> {
+		self.0.to_str().ok()
+	}
+	/// The argument as a UTF-8 string, with invalid characters replaced by the
+	/// UTF-8 replacement character ('�'). Only allocates if the argument isn't
+	/// valid UTF-8.
+	pub fn to_str_lossy(self) -> Cow<'static, str> {
+		self.0.to_string_lossy()
+	}
+}
+impl Debug for Arg {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		Debug::fmt(&*self.to_str_lossy(), f)
+	}
+}
+impl crate::text::Display for Arg {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		f.write_str(&self.to_str_lossy())
+	}
+}
+
 /// Returns all of the arguments passed to the program via the CLI, lossily
 /// encoded as UTF-8. Note that the 0th argument is typically the path to the
 /// executable, and not an argument you need to parse.
 ///
-/// For an unencoded version, see [`cli_args_raw`].
+/// The arguments are converted the first time this is called, then cached
+/// until the program shuts down (see [`free_cli_args`]). Arguments that are
+/// already valid UTF-8 aren't copied. To avoid converting every argument, use
+/// [`args`] instead. For an unencoded version, see [`cli_args_raw`].
+///
+/// Like [`args`], this is empty if it's called before [`load_args`] runs; the
+/// empty result isn't cached.
 pub fn cli_args() -> &'static [&'static str] {
-	crate::rt::info().cli_args
+	if argv().is_none() {
+		return &[];
+	}
+	cached(&CLI_ARGS, Utf8Args::new).args.as_slice()
 }
 
 /// Returns all of the arguments passed to the program via the CLI. Note that
@@ -200,6 +356,702 @@ pub fn cli_args() -> &'static [&'static str] {
 ///
 /// The encoding of the arguments is unspecified, as these are the raw bytes
 /// from the operating system. For a UTF-8 encoded version, see [`cli_args`].
+///
+/// The list of arguments is built the first time this is called, and cached
+/// like [`cli_args`].
 pub fn cli_args_raw() -> &'static [&'static [u8]] {
-	crate::rt::info().cli_args_raw
+	if argv().is_none() {
+		return &[];
+	}
+	cached(&CLI_ARGS_RAW, || args().map(Arg::as_bytes).collect()).as_slice()
+}
+
+static CLI_ARGS: AtomicPtr<Utf8Args> = AtomicPtr::new(null_ptr_mut());
+static CLI_ARGS_RAW: AtomicPtr<Vec<&'static [u8]>> = AtomicPtr::new(null_ptr_mut());
+
+/// The arguments returned by [`cli_args`].
+struct Utf8Args {
+	/// Every argument that wasn't valid UTF-8, converted and joined together.
+	/// Entries in `args` point into this, so it's never changed after it's
+	/// built; it's only stored to keep it alive.
+	#[allow(dead_code)]
+	converted: String,
+	args: Vec<&'static str>,
+}
+impl Utf8Args {
+	fn new() -> Self {
+		// Converted arguments are copied into one string, then sliced once
+		// it's done growing
+		let mut converted = String::new();
+		let parts: Vec<Result<&'static str, (usize, usize)>> = args()
+			.map(|arg| match arg.to_str_lossy() {
+				Cow::Borrowed(arg) => Ok(arg),
+				Cow::Owned(arg) => {
+					let start = converted.len();
+					converted.push_str(&arg);
+					Err((start, converted.len()))
+				}
+			})
+			.collect();
+
+		let args = parts
+			.into_iter()
+			.map(|part| match part {
+				Ok(arg) => arg,
+				// The string's heap buffer lives as long as `Self`
+				Err((start, end)) => unsafe { &*(&converted[start..end] as *const str) },
+			})
+			.collect();
+
+		Self { converted, args }
+	}
+}
+
+/// Returns the value in `cache`, or computes it with `init` and stores it in
+/// `cache` if `cache` is empty. The value is boxed, so it stays at the same
+/// address until it's freed by [`free_cached`].
+///
+/// If multiple threads race to fill the cache, they may all run `init`, but
+/// only one of their values is kept.
+pub(crate) fn cached<T>(cache: &AtomicPtr<T>, init: impl FnOnce() -> T) -> &'static T {
+	if let Some(value) = NonNull::new(cache.load(AtomicOrdering::Acquire)) {
+		return unsafe { value.as_ref() };
+	}
+
+	let value = Box::into_raw(Box::new(init()));
+	match cache.compare_exchange(
+		null_ptr_mut(),
+		value,
+		AtomicOrdering::AcqRel,
+		AtomicOrdering::Acquire,
+	) {
+		Ok(_) => unsafe { &*value },
+		Err(other) => {
+			drop(unsafe { Box::from_raw(value) });
+			unsafe { &*other }
+		}
+	}
+}
+/// Empties `cache`, freeing the value [`cached`] stored in it.
+///
+///
+/// # Safety
+///
+/// Nothing can use references to the cached value after this is called.
+unsafe fn free_cached<T>(cache: &AtomicPtr<T>) {
+	let value = cache.swap(null_ptr_mut(), AtomicOrdering::AcqRel);
+	if !value.is_null() {
+		drop(unsafe { Box::from_raw(value) });
+	}
+}
+
+/// Frees the arguments cached by [`cli_args`] and [`cli_args_raw`], so leak
+/// checkers like Valgrind don't report them. Runs in the [`Late`] phase of
+/// the [`shutdown`] event, after `crux_main` has returned.
+///
+/// Slices returned by [`cli_args`] and [`cli_args_raw`] must not be used
+/// after this runs; other shutdown hooks that need the arguments should
+/// order themselves before it. If that's a problem (e.g. because detached
+/// threads may still be reading the arguments), unregister this hook:
+///
+/// ```rs
+/// unsafe { crux::events::shutdown::EVENT.unregister(crux::hooks::free_cli_args::HOOK.value.id) };
+/// ```
+///
+/// [`Late`]: crate::rt::hook::Phase::Late
+/// [`shutdown`]: crate::events::shutdown
+pub fn free_cli_args() {
+	unsafe {
+		free_cached(&CLI_ARGS);
+		free_cached(&CLI_ARGS_RAW);
+	}
+}
+hook! {
+	/// See [`free_cli_args`].
+	event: crate::events::shutdown,
+	func: free_cli_args,
+	phase: Late,
+	constraints: []
+}
+
+//
+//
+// Working directory
+//
+//
+
+/// Returns the path of the process' current working directory, which relative
+/// paths are resolved against.
+///
+/// Like the environment, the working directory belongs to the process, so
+/// it's shared by every thread. The path may not be valid UTF-8; any invalid
+/// characters are replaced with the UTF-8 replacement character ('�').
+pub fn current_dir() -> Result<String, os::Error> {
+	#[cfg(unix)]
+	{
+		let mut buf: Vec<u8> = Vec::with_capacity(256);
+		loop {
+			let res = unsafe {
+				os::unix::getcwd(
+					NonNull::new_unchecked(buf.as_mut_ptr()).cast(),
+					buf.capacity(),
+				)
+			};
+			if res.is_some() {
+				let path = unsafe { CStr::from_ptr(buf.as_ptr().cast()) };
+				return Ok(path.to_string_lossy().into_owned());
+			}
+
+			// `ERANGE` means the path didn't fit in the buffer
+			let err = os::Error::last();
+			if err.raw() != libc::ERANGE {
+				return Err(err);
+			}
+			buf.reserve(buf.capacity() * 2);
+		}
+	}
+	#[cfg(windows)]
+	{
+		read_wide_string(|buf| unsafe {
+			os::win32::GetCurrentDirectoryW(buf.len() as u32, NonNull::from(&mut buf[0]))
+		})
+	}
+	#[cfg(not(supported_os))]
+	compile_error!("unimplemented on this operating system");
+}
+/// Changes the process' current working directory to `path`. This affects
+/// every thread in the process.
+pub fn set_current_dir(path: &str) -> Result<(), os::Error> {
+	#[cfg(unix)]
+	{
+		let path = crate::rt::fs::c_path(path)?;
+		let res = unsafe { os::unix::chdir(NonNullConst::from_ref(path.as_c_str()).cast()) };
+		if res == 0 {
+			Ok(())
+		} else {
+			Err(os::Error::last())
+		}
+	}
+	#[cfg(windows)]
+	{
+		let path = crate::text::wide::encode_nul::<usize>(path)
+			.map_err(|_| os::Error::from_raw(os::win32::ERROR_INVALID_PARAMETER))?;
+		if unsafe { os::win32::SetCurrentDirectoryW(NonNullConst::from_ref(&path[0])) } != 0 {
+			Ok(())
+		} else {
+			Err(os::Error::last())
+		}
+	}
+	#[cfg(not(supported_os))]
+	compile_error!("unimplemented on this operating system");
+}
+
+/// Returns the absolute path of the program's executable. Like
+/// [`current_dir`], invalid UTF-8 in the path is replaced with the UTF-8
+/// replacement character ('�').
+///
+/// On macOS, the path may go through symlinks, since it's the path the
+/// executable was launched from.
+pub fn current_exe() -> Result<String, os::Error> {
+	#[cfg(linux)]
+	{
+		let path = crate::rt::fs::read_link("/proc/self/exe")?;
+		Ok(String::from_utf8_lossy(&path).into_owned())
+	}
+	#[cfg(macos)]
+	{
+		let mut len = 256u32;
+		let mut buf: Vec<u8> = Vec::with_capacity(len as usize);
+		// Fails and sets `len` to the size it needs if the buffer's too small
+		while unsafe {
+			os::unix::_NSGetExecutablePath(
+				NonNull::new_unchecked(buf.as_mut_ptr()).cast(),
+				NonNull::from(&mut len),
+			)
+		} != 0
+		{
+			buf.reserve(len as usize);
+		}
+		let path = unsafe { CStr::from_ptr(buf.as_ptr().cast()) };
+		Ok(path.to_string_lossy().into_owned())
+	}
+	#[cfg(windows)]
+	{
+		read_wide_string(|buf| unsafe {
+			os::win32::GetModuleFileNameW(None, NonNull::from(&mut buf[0]), buf.len() as u32)
+		})
+	}
+	#[cfg(not(supported_os))]
+	compile_error!("unimplemented on this operating system");
+}
+
+/// Calls a Win32 function that copies a UTF-16 string into a buffer, growing
+/// the buffer until the string fits. `fill` passes the buffer to the function,
+/// and returns what it returned: the string's length, 0 on failure, or at
+/// least the buffer's length if the buffer was too small.
+#[cfg(windows)]
+fn read_wide_string(mut fill: impl FnMut(&mut [u16]) -> u32) -> Result<String, os::Error> {
+	// `MAX_PATH`, which fits most paths on the first try
+	let mut buf: Vec<u16> = core::iter::repeat_n(0, 260).collect();
+	loop {
+		let len = fill(&mut buf) as usize;
+		if len == 0 {
+			return Err(os::Error::last());
+		}
+		if len < buf.len() {
+			return Ok(crate::text::wide::decode_lossy(&buf[..len]));
+		}
+		buf.resize(len.max(buf.len() * 2), 0);
+	}
+}
+
+//
+//
+// Daemons
+//
+//
+
+/// Options for [`daemonize`].
+#[cfg(unix)]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DaemonConfig<'a> {
+	/// A file to append the daemon's stdout and stderr to, which is created if
+	/// it doesn't exist. If this is [`None`], its output is thrown away.
+	pub log_file: Option<&'a str>,
+	/// A file to write the daemon's process ID to, so scripts and service
+	/// managers can signal it. It's overwritten if it already exists.
+	pub pid_file: Option<&'a str>,
+	/// Don't change the working directory to `/`. By default, daemons move to
+	/// `/`, so they don't keep the filesystem they were started in busy (which
+	/// would stop it from being unmounted).
+	pub keep_current_dir: bool,
+}
+
+/// Turns the process into a daemon: a background process that isn't attached
+/// to a terminal, so it keeps running after the terminal that started it is
+/// closed.
+///
+/// ```rs
+/// proc::daemonize(DaemonConfig {
+///     log_file: Some("/var/log/crux-server.log"),
+///     pid_file: Some("/run/crux-server.pid"),
+///     ..Default::default()
+/// })?;
+/// serve_forever();
+/// ```
+///
+/// This forks twice. The process that called it exits, and it returns in a
+/// grandchild process that's in a new session, so it can never get a
+/// controlling terminal. The daemon's stdin reads from `/dev/null`, and its
+/// stdout and stderr go to the log file.
+///
+/// Only the thread that calls this is copied into the daemon, so it should be
+/// called before any threads are spawned. If opening the log file or forking
+/// fails, the error is returned in the original process; errors after that
+/// (e.g. from writing the PID file) are returned in the daemon, before its
+/// output is redirected.
+///
+/// Daemons are a Unix concept, so this is only available on Unix. On Windows,
+/// use [`run_service`] instead.
+#[cfg(unix)]
+pub fn daemonize(config: DaemonConfig) -> Result<(), os::Error> {
+	use crate::{
+		io::Writer,
+		rt::os::unix::{self, FileDescriptor, FileWriter, OpenFlags},
+	};
+
+	let open = |path: &CStr, flags| {
+		let fd = unsafe { unix::open(path.as_ptr(), flags | OpenFlags::CLOEXEC, 0o644) };
+		if fd.as_raw() == -1 {
+			Err(os::Error::last())
+		} else {
+			Ok(fd)
+		}
+	};
+	let fork = || match unsafe { unix::fork() } {
+		-1 => Err(os::Error::last()),
+		pid => Ok(pid),
+	};
+
+	// Files are opened before forking, so the original process can
+	// report errors
+	let null = open(c"/dev/null", OpenFlags::RDWR)?;
+	let log = match config.log_file {
+		Some(path) => crate::rt::fs::c_path(path).and_then(|path| {
+			open(
+				&path,
+				OpenFlags::WRONLY | OpenFlags::CREAT | OpenFlags::APPEND,
+			)
+		}),
+		None => Ok(null),
+	};
+	let close = |fd| unsafe {
+		if fd != null {
+			unix::close(fd);
+		}
+		unix::close(null);
+	};
+	let log = log.inspect_err(|_| close(null))?;
+	match fork() {
+		Ok(0) => {}
+		Ok(_) => unix::exit(0),
+		Err(err) => {
+			close(log);
+			return Err(err);
+		}
+	}
+
+	// The child isn't a process group leader, so it can start a new
+	// session, which detaches it from the terminal. The session's leader
+	// could still get a controlling terminal by opening one, but its child
+	// can't.
+	unix::setsid();
+	if fork()? != 0 {
+		unix::exit(0);
+	}
+
+	if let Some(path) = config.pid_file {
+		let pid_file = crate::rt::fs::c_path(path)?;
+		let fd = open(
+			&pid_file,
+			OpenFlags::WRONLY | OpenFlags::CREAT | OpenFlags::TRUNC,
+		)?;
+		let res =
+			unsafe { FileWriter::new(fd) }.write_all(format!("{}\n", unix::getpid()).as_bytes());
+		unsafe { unix::close(fd) };
+		res?;
+	}
+	if !config.keep_current_dir {
+		set_current_dir("/")?;
+	}
+
+	unix::redirect(null, FileDescriptor::STDIN)?;
+	unix::redirect(log, FileDescriptor::STDOUT)?;
+	unix::redirect(log, FileDescriptor::STDERR)?;
+	close(log);
+
+	Ok(())
+}
+
+/// The function [`run_service`] runs as the service.
+#[cfg(windows)]
+static SERVICE_MAIN: AtomicCell<Option<fn(&Service)>> = AtomicCell::new(None);
+/// Set when the service control manager asks the service to stop.
+#[cfg(windows)]
+static SERVICE_STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// A running Windows service. See [`run_service`].
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct Service {
+	status: NonNull<c_void>,
+}
+#[cfg(windows)]
+impl Service {
+	/// Whether the service control manager has asked the service to stop,
+	/// e.g. because it was stopped by the user or the machine is shutting
+	/// down. The service should return soon after this becomes `true`.
+	pub fn stop_requested(&self) -> bool {
+		SERVICE_STOPPING.load(AtomicOrdering::Relaxed)
+	}
+
+	/// Tells the service control manager what state the service is in.
+	fn set_state(&self, state: u32) {
+		use os::win32::*;
+
+		let status = ServiceStatus {
+			service_type: SERVICE_WIN32_OWN_PROCESS,
+			current_state: state,
+			controls_accepted: if state == SERVICE_RUNNING {
+				SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN
+			} else {
+				0
+			},
+			win32_exit_code: NO_ERROR,
+			service_specific_exit_code: 0,
+			check_point: 0,
+			wait_hint: 0,
+		};
+		unsafe { SetServiceStatus(self.status, NonNullConst::from_ref(&status)) };
+	}
+}
+
+/// Runs `service` as a Windows service, which is the Windows equivalent of a
+/// daemon.
+///
+/// ```rs
+/// proc::run_service(|service| {
+///     while !service.stop_requested() {
+///         serve_one_request();
+///     }
+/// })?;
+/// ```
+///
+/// This connects to the service control manager and reports the service as
+/// running, then calls `service` on a thread the service control manager
+/// starts. It blocks until `service` returns, then reports the service as
+/// stopped. `service` should return soon after [`Service::stop_requested`]
+/// becomes `true`.
+///
+/// This has to be called from the main thread, soon after the program starts;
+/// the service control manager gives up on services that don't connect to it
+/// within 30 seconds. It errors if the program wasn't started as a service.
+#[cfg(windows)]
+pub fn run_service(service: fn(&Service)) -> Result<(), os::Error> {
+	use os::win32::*;
+
+	extern "system" fn service_main(_argc: u32, argv: NonNull<NonNull<u16>>) {
+		// The first argument is the service's name
+		let name = unsafe { NonNullConst::new_unchecked(argv.read().as_ptr()) };
+		let Some(status) = (unsafe { RegisterServiceCtrlHandlerExW(name, control_handler, None) })
+		else {
+			return;
+		};
+
+		let service = Service { status };
+		service.set_state(SERVICE_RUNNING);
+		if let Some(main) = SERVICE_MAIN.load() {
+			main(&service);
+		}
+		service.set_state(SERVICE_STOPPED);
+	}
+	extern "system" fn control_handler(
+		control: u32,
+		_event_type: u32,
+		_event_data: *mut c_void,
+		_context: *mut c_void,
+	) -> u32 {
+		match control {
+			SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+				SERVICE_STOPPING.store(true, AtomicOrdering::Relaxed);
+				NO_ERROR
+			}
+			SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+			_ => ERROR_CALL_NOT_IMPLEMENTED,
+		}
+	}
+
+	SERVICE_MAIN.store(Some(service));
+	// The name is ignored for services that run in their own process, but
+	// can't be null
+	let table = [
+		ServiceTableEntryW {
+			service_name: &0,
+			service_proc: Some(service_main),
+		},
+		ServiceTableEntryW {
+			service_name: crate::lang::null_ptr(),
+			service_proc: None,
+		},
+	];
+	if unsafe { StartServiceCtrlDispatcherW(NonNullConst::from_ref(&table[0])) } != 0 {
+		Ok(())
+	} else {
+		Err(os::Error::last())
+	}
+}
+
+//
+//
+// Resource usage
+//
+//
+
+/// How much memory and CPU time the process has used. See
+/// [`resource_usage`].
+#[cfg(unix)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ResourceUsage {
+	/// How much physical memory the process is using right now, also known as
+	/// its resident set size.
+	pub resident_memory: MemoryAmount,
+	/// The most physical memory the process has used at once.
+	pub peak_resident_memory: MemoryAmount,
+	/// How much CPU time the process has spent running its own code.
+	pub user_time: Duration,
+	/// How much CPU time the OS has spent working for the process, e.g. in
+	/// syscalls.
+	pub system_time: Duration,
+	/// Page faults the OS handled without reading from disk - for example, the
+	/// first time a page of committed memory is touched.
+	pub minor_page_faults: u64,
+	/// Page faults the OS had to read from disk to handle, e.g. from swap or a
+	/// mapped file.
+	pub major_page_faults: u64,
+	/// How many threads the process has, including the main thread.
+	pub threads: usize,
+}
+
+/// Measures how much memory and CPU time the process has used so far, so
+/// programs can report their own metrics, or tests can check that code stays
+/// under a memory ceiling:
+///
+/// ```rs
+/// let usage = proc::resource_usage()?;
+/// assert!(usage.peak_resident_memory < MemoryAmount::mebibytes(64));
+/// ```
+///
+/// Every field is measured for the whole process, not just the calling
+/// thread. This is only available on Unix for now.
+#[cfg(unix)]
+pub fn resource_usage() -> Result<ResourceUsage, os::Error> {
+	let mut usage = MaybeUninit::<libc::rusage>::uninit();
+	let res = unsafe { os::unix::getrusage(libc::RUSAGE_SELF, NonNull::from(&mut usage).cast()) };
+	if res != 0 {
+		return Err(os::Error::last());
+	}
+	let usage = unsafe { usage.assume_init() };
+	let (resident_memory, threads) = resident_memory_and_threads()?;
+
+	let time = |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+	// Linux measures the peak in KiB, and macOS in bytes
+	#[cfg(linux)]
+	let peak_resident_memory = MemoryAmount::kibibytes(usage.ru_maxrss as usize);
+	#[cfg(not(linux))]
+	let peak_resident_memory = MemoryAmount::bytes(usage.ru_maxrss as usize);
+
+	Ok(ResourceUsage {
+		resident_memory,
+		peak_resident_memory,
+		user_time: time(usage.ru_utime),
+		system_time: time(usage.ru_stime),
+		minor_page_faults: usage.ru_minflt as u64,
+		major_page_faults: usage.ru_majflt as u64,
+		threads,
+	})
+}
+
+/// Measures the process' current resident memory and thread count, which
+/// `getrusage` doesn't report.
+#[cfg(linux)]
+fn resident_memory_and_threads() -> Result<(MemoryAmount, usize), os::Error> {
+	let stat = os::unix::read_file(c"/proc/self/stat")?;
+	// The second field is the executable's name in parentheses, which can
+	// contain spaces and parentheses itself, so fields are counted from the
+	// last `)`. See `proc_pid_stat(5)`.
+	let after_name = stat
+		.iter()
+		.rposition(|byte| *byte == b')')
+		.map_or(0, |idx| idx + 1);
+	let mut fields = stat[after_name..]
+		.split(u8::is_ascii_whitespace)
+		.filter(|field| !field.is_empty())
+		.map(|field| str::from_utf8(field).ok()?.parse::<usize>().ok());
+	// Field 3 is the first one after the name; `num_threads` is field 20, and
+	// `rss` (in pages) is field 24
+	let threads = fields.nth(20 - 3).flatten();
+	let pages = fields.nth(24 - 21).flatten();
+
+	let (Some(pages), Some(threads)) = (pages, threads) else {
+		return Err(os::Error::from_raw(libc::EIO));
+	};
+	Ok((
+		MemoryAmount::bytes(pages * crate::rt::mem::page_size()),
+		threads,
+	))
+}
+/// Measures the process' current resident memory and thread count, which
+/// `getrusage` doesn't report.
+#[cfg(macos)]
+fn resident_memory_and_threads() -> Result<(MemoryAmount, usize), os::Error> {
+	let mut info = MaybeUninit::<libc::proc_taskinfo>::uninit();
+	let size = size_of::<libc::proc_taskinfo>() as _;
+	let written = unsafe {
+		os::unix::proc_pidinfo(
+			os::unix::getpid(),
+			libc::PROC_PIDTASKINFO,
+			0,
+			NonNull::from(&mut info).cast(),
+			size,
+		)
+	};
+	if written != size {
+		return Err(os::Error::last());
+	}
+	let info = unsafe { info.assume_init() };
+
+	Ok((
+		MemoryAmount::bytes(info.pti_resident_size as usize),
+		info.pti_threadnum as usize,
+	))
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn working_dir() {
+		let dir = current_dir().unwrap();
+		assert!(!dir.is_empty());
+		set_current_dir(&dir).unwrap();
+		assert_eq!(current_dir().unwrap(), dir);
+		assert!(set_current_dir("/this/path/does/not/exist").is_err());
+
+		let exe = current_exe().unwrap();
+		assert!(crate::rt::fs::metadata(&exe).unwrap().is_file());
+	}
+
+	#[test]
+	fn daemonize() {
+		use crate::{
+			io::{Reader, Writer},
+			rt::{fs::TempDir, os::unix},
+		};
+
+		let dir = TempDir::new().unwrap();
+		let (log_file, pid_file) = (dir.join("log"), dir.join("pid"));
+		// The daemon sends its PID and working directory back through the
+		// pipe, then exits, which closes the pipe
+		let (mut reader, mut writer) = unix::pipe().unwrap();
+
+		let child = unsafe { unix::fork() };
+		if child == 0 {
+			drop(reader);
+			super::daemonize(DaemonConfig {
+				log_file: Some(&log_file),
+				pid_file: Some(&pid_file),
+				keep_current_dir: false,
+			})
+			.unwrap();
+			println!("daemon started");
+			let msg = format!("{} {}", unix::getpid(), current_dir().unwrap());
+			let _ = writer.write_all(msg.as_bytes());
+			exit();
+		}
+		drop(writer);
+		unsafe { unix::waitpid(child, None, 0) };
+
+		let mut msg = Vec::new();
+		reader.read_to_end(&mut msg).unwrap();
+		let msg = String::from_utf8(msg).unwrap();
+		let (pid, cwd) = msg.split_once(' ').unwrap();
+		assert_ne!(pid, format!("{child}"));
+		assert_eq!(cwd, "/");
+
+		let read = |path: &str| unix::read_file(&CString::new(path).unwrap()).unwrap();
+		assert_eq!(read(&pid_file), format!("{pid}\n").as_bytes());
+		assert_eq!(read(&log_file), b"daemon started\n");
+	}
+
+	#[test]
+	fn resource_usage() {
+		let before = super::resource_usage().unwrap();
+		// Touch every page, so they're all resident
+		let mut block = Vec::<u8>::with_capacity(16 * 1024 * 1024);
+		block.resize(block.capacity(), 1);
+		let after = super::resource_usage().unwrap();
+		drop(block);
+
+		assert!(before.threads >= 1);
+		assert!(after.resident_memory > before.resident_memory);
+		assert!(after.peak_resident_memory >= after.resident_memory);
+		assert!(after.minor_page_faults > before.minor_page_faults);
+	}
 }
@@ -0,0 +1,748 @@
+//! Provides a harness for running functions decorated with `#[test]` via
+//! `cargo test`.
+//!
+//! The harness understands a subset of the flags supported by Rust's built-in
+//! test harness, so `cargo test` works as you'd expect:
+//! - `cargo test foo bar` only runs tests whose path contains `foo` or `bar`.
+//! - `cargo test -- --exact foo` only runs tests whose path is exactly `foo`.
+//! - `cargo test -- --list` lists tests instead of running them.
+//! - `cargo test -- --nocapture` shows tests' output as they run, instead of
+//!   capturing it and only showing it for failed tests.
+//! - `cargo test -- --show-output` shows the captured output of successful
+//!   tests, too.
+//! - `cargo test -- --ignored` only runs ignored tests.
+//! - `cargo test -- --include-ignored` runs ignored tests, too.
+//!
+//!
+//! # Test Attributes
+//!
+//! Like libtest, the [`#[test]`] attribute accepts a few arguments:
+//! - `#[test(should_panic)]` inverts the test: it passes only if it panics.
+//! - `#[test(should_panic = "msg")]` additionally requires the test's output to
+//!   contain `msg`. `#[test(should_panic(expected = "msg"))]` also works, to
+//!   make porting existing tests easier.
+//! - `#[test(ignore)]` or `#[test(ignore = "reason")]` skips the test unless
+//!   `--ignored` or `--include-ignored` is passed.
+//! - `#[test(timeout_ms = 500)]` fails the test if it runs for longer than the
+//!   given number of milliseconds.
+//!
+//! Tests can also be `async fn`s. Async tests are run to completion with
+//! [`crate::task::block_on`].
+//!
+//! Like libtest, tests can return a `Result<(), E>` instead of `()`, so they
+//! can use `?` instead of unwrapping everything. A test that returns `Err`
+//! fails, and the error's `Debug` output is shown with the test's output. See
+//! [`TestResult`].
+//!
+//! [`#[test]`]: crux_macros::test
+//!
+//!
+//! # Isolation
+//!
+//! Crux is compiled with `panic = "abort"`, so a failing test can't be caught
+//! and recovered from in-process. Instead, on Unix, the harness runs each test
+//! in a forked child process. If the child exits with a non-zero exit code (as
+//! Crux's panic handler does) or is killed by a signal, the test fails. The
+//! child's output is sent to the harness through a pipe.
+//!
+//! On other platforms, tests run in the harness' process, so the first failing
+//! test ends the test run. For the same reason, `should_panic` tests are
+//! ignored on those platforms, and timeouts aren't enforced.
+
+#[cfg(feature = "test-harness")]
+use crate::{
+	lang::cfg,
+	rt::time::Instant,
+	term::{
+		FG_GREEN, FG_RED, FG_YELLOW, RESET,
+		cli::{self, CliParser, CliParsingCtx, FlagClass, ParseError, ParseResult},
+	},
+};
+use {crate::rt::time::Duration, core::fmt::Debug};
+
+crate::rt::event! {
+	/// This event is used by the Crux test harness. All tests that should be
+	/// run should register with this event.
+	///
+	/// For convenience, Crux exposes a [`#[test]`] attribute macro. Putting
+	/// that on any function will register it with this event.
+	///
+	/// [`#[test]`]: crux_macros::test
+	run_tests, Test
+}
+
+/// A test registered with the [`run_tests`] event.
+#[derive(Clone, Copy, Debug)]
+pub struct Test {
+	/// The path of the module the test was declared in, as returned by
+	/// `module_path!()`.
+	pub module: &'static str,
+	/// The name of the test function.
+	pub name: &'static str,
+	/// The test function.
+	pub func: fn(),
+	/// Whether the test is expected to panic.
+	pub should_panic: ShouldPanic,
+	/// If true, the test is only run when `--ignored` or `--include-ignored`
+	/// is passed to the harness.
+	pub ignore: bool,
+	/// Why the test is ignored, if a reason was given.
+	pub ignore_reason: Option<&'static str>,
+	/// If set, the test fails if it runs for longer than this.
+	pub timeout: Option<Duration>,
+}
+impl Test {
+	/// The path to this test, without the crate name (e.g.
+	/// `term::cli::tests::help`). Test filters are matched against this path.
+	pub fn path(&self) -> String {
+		item_path(self.module, self.name)
+	}
+}
+
+/// Joins a module path and an item name, without the crate name at the start
+/// of the module path.
+pub(crate) fn item_path(module: &str, name: &str) -> String {
+	match module.split_once("::") {
+		Some((_crate_name, module)) => format!("{module}::{name}"),
+		None => String::from(name),
+	}
+}
+
+/// The exit code of a test that returned `Err`. Crux's panic handler exits
+/// with 101, so this is distinct from a panic.
+pub const ERROR_EXIT_CODE: i32 = 102;
+
+/// A type that can be returned from a [`#[test]`] function.
+///
+/// `()` always passes. `Result<(), E>` fails if it's `Err`: the error's
+/// `Debug` output is printed, then the test exits with [`ERROR_EXIT_CODE`],
+/// which the harness reports as [`TestFailure::ReturnedError`].
+///
+/// [`#[test]`]: crux_macros::test
+#[diagnostic::on_unimplemented(
+	message = "`#[test]` functions must return `()` or `Result<(), E>`, not `{Self}`",
+	note = "the error type of a `Result` must implement `Debug`"
+)]
+pub trait TestResult {
+	/// Reports the test's result, exiting the test's process if it failed.
+	fn report(self);
+}
+impl TestResult for () {
+	fn report(self) {}
+}
+impl<E: Debug> TestResult for Result<(), E> {
+	fn report(self) {
+		if let Err(error) = self {
+			println!("Error: {:?}", error);
+			crate::rt::proc::exit_with_code(ERROR_EXIT_CODE);
+		}
+	}
+}
+
+/// Whether a [`Test`] is expected to panic, as set by
+/// `#[test(should_panic)]`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShouldPanic {
+	/// The test should not panic.
+	No,
+	/// The test should panic.
+	Yes,
+	/// The test should panic, and its output should contain the given
+	/// message.
+	WithMessage(&'static str),
+}
+
+/// Options for the test harness. These are usually parsed from the CLI
+/// arguments passed by `cargo test`; see the [module-level docs].
+///
+/// [module-level docs]: crate::rt::test_harness
+#[cfg(feature = "test-harness")]
+#[derive(Default, Debug)]
+pub struct TestConfig<'a> {
+	/// Only run tests whose path contains one of these filters. If empty, all
+	/// tests are run.
+	pub filters: Vec<&'a str>,
+	/// If true, filters must match a test's path exactly (`--exact`).
+	pub exact: bool,
+	/// List tests instead of running them (`--list`).
+	pub list: bool,
+	/// Don't capture tests' output (`--nocapture`).
+	pub nocapture: bool,
+	/// Show the output of successful tests (`--show-output`).
+	pub show_output: bool,
+	/// Only print a summary of the test run (`-q`/`--quiet`).
+	pub quiet: bool,
+	/// Only run ignored tests (`--ignored`).
+	pub ignored: bool,
+	/// Run ignored tests alongside other tests (`--include-ignored`).
+	pub include_ignored: bool,
+	/// Run benchmarks instead of tests (`--bench`). `cargo bench` passes this
+	/// flag.
+	pub bench: bool,
+}
+#[cfg(feature = "test-harness")]
+impl TestConfig<'_> {
+	/// Returns true if the test at `path` should be run with this config.
+	pub fn matches(&self, path: &str) -> bool {
+		self.filters.is_empty()
+			|| self.filters.iter().any(|filter| {
+				if self.exact {
+					path == *filter
+				} else {
+					path.contains(filter)
+				}
+			})
+	}
+}
+#[cfg(feature = "test-harness")]
+impl<'a> CliParser<'a> for TestConfig<'a> {
+	fn parse(
+		&mut self,
+		flag: &'a str,
+		class: FlagClass<'a>,
+		ctx: &mut CliParsingCtx<'a, Self>,
+	) -> ParseResult {
+		match flag {
+			"exact" if class.is_long() => self.exact = true,
+			"list" if class.is_long() => self.list = true,
+			"nocapture" if class.is_long() => self.nocapture = true,
+			"show-output" if class.is_long() => self.show_output = true,
+			"ignored" if class.is_long() => self.ignored = true,
+			"include-ignored" if class.is_long() => self.include_ignored = true,
+			"bench" if class.is_long() => self.bench = true,
+			"q" | "quiet" if class.is_flag() => self.quiet = true,
+			_ if class.is_subcommand() => match ctx.positional() {
+				Some(filter) => self.filters.push(filter),
+				None => return ParseResult::NotRecognised,
+			},
+			_ => return ParseResult::NotRecognised,
+		}
+
+		ParseResult::Recognised
+	}
+	fn error(&mut self, error: ParseError<'a>) {
+		println!("Crux test harness: invalid arguments: {:?}", error);
+		crate::rt::proc::exit_with_code(1);
+	}
+}
+
+/// Returns all tests registered in this Crux binary, in the order they should
+/// be run.
+pub fn all_tests() -> &'static [&'static Test] {
+	#[cfg(feature = "concurrency")]
+	let tests = unsafe { run_tests::EVENT.solved() };
+	#[cfg(not(feature = "concurrency"))]
+	let tests = unsafe { run_tests::EVENT.solve() }.map(|tests| &*tests.leak());
+
+	tests.expect("Crux CRITICAL ERROR: Failed to solve `run_tests` event, cannot run unit tests")
+}
+
+/// Runs all tests registered in this Crux binary, using the CLI arguments
+/// passed to the program to configure the test harness. Exits the process
+/// with a non-zero exit code if any test fails.
+///
+/// If `--bench` is passed (as `cargo bench` does), this runs all benchmarks
+/// instead; see [`crate::bench`].
+#[cfg(feature = "test-harness")]
+pub fn run_all_tests() {
+	let mut config = TestConfig::default();
+	cli::parse(crate::rt::proc::cli_args(), &mut config, true);
+	if config.bench {
+		crate::bench::run_benches_with_config(&config);
+		return;
+	}
+
+	let report = run_tests_with_config(&config);
+
+	if report.failed() > 0 {
+		crate::rt::proc::exit_with_code(101);
+	}
+}
+
+/// Whether a test passed, failed, or was ignored.
+#[cfg(feature = "test-harness")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TestStatus {
+	Passed,
+	/// The test was ignored, and wasn't run.
+	Ignored,
+	Failed(TestFailure),
+}
+
+/// Why a test failed.
+#[cfg(feature = "test-harness")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TestFailure {
+	/// The test exited with a non-zero exit code. Crux's panic handler exits
+	/// with code 101.
+	ExitCode(i32),
+	/// The test was killed by the given signal.
+	Signal(i32),
+	/// The test was marked `should_panic`, but didn't panic.
+	DidNotPanic,
+	/// The test panicked, but its output didn't contain the expected message.
+	WrongPanicMessage(&'static str),
+	/// The test ran for longer than its timeout.
+	TimedOut(Duration),
+	/// The test returned `Err`. The error is printed in the test's output.
+	ReturnedError,
+}
+#[cfg(feature = "test-harness")]
+impl TestFailure {
+	/// Returns true if this failure was caused by a panic; that is, the test
+	/// exited with code 101, or was aborted (as panics do under `std`'s panic
+	/// handler with `panic = "abort"`).
+	pub fn is_panic(self) -> bool {
+		#[cfg(unix)]
+		let aborted = self == Self::Signal(libc::SIGABRT);
+		#[cfg(not(unix))]
+		let aborted = false;
+
+		self == Self::ExitCode(101) || aborted
+	}
+}
+
+/// The outcome of running a single test.
+#[cfg(feature = "test-harness")]
+#[derive(Debug)]
+pub struct TestOutcome {
+	/// The test's path (see [`Test::path`]).
+	pub path: String,
+	pub status: TestStatus,
+	/// How long the test took to run.
+	pub duration: Duration,
+	/// Everything the test printed, if its output was captured.
+	pub output: Vec<u8>,
+}
+
+/// The outcome of a whole test run.
+#[cfg(feature = "test-harness")]
+#[derive(Debug)]
+pub struct TestReport {
+	/// The outcome of every test that was run, in the order they were run.
+	pub outcomes: Vec<TestOutcome>,
+	/// How many tests were skipped because they didn't match the filters.
+	pub filtered_out: usize,
+	/// How long the whole test run took.
+	pub duration: Duration,
+}
+#[cfg(feature = "test-harness")]
+impl TestReport {
+	/// The number of tests that passed.
+	pub fn passed(&self) -> usize {
+		self.outcomes
+			.iter()
+			.filter(|outcome| outcome.status == TestStatus::Passed)
+			.count()
+	}
+	/// The number of tests that failed.
+	pub fn failed(&self) -> usize {
+		self.outcomes
+			.iter()
+			.filter(|outcome| matches!(outcome.status, TestStatus::Failed(_)))
+			.count()
+	}
+	/// The number of tests that were ignored.
+	pub fn ignored(&self) -> usize {
+		self.outcomes
+			.iter()
+			.filter(|outcome| outcome.status == TestStatus::Ignored)
+			.count()
+	}
+}
+
+/// Runs all tests registered in this Crux binary that match the given config,
+/// printing their results as they run and a summary at the end.
+#[cfg(feature = "test-harness")]
+pub fn run_tests_with_config(config: &TestConfig) -> TestReport {
+	let tests = all_tests();
+	let mut to_run = Vec::new();
+	for test in tests {
+		let path = test.path();
+		if config.matches(&path) && (!config.ignored || test.ignore) {
+			to_run.push((path, *test));
+		}
+	}
+	let filtered_out = tests.len() - to_run.len();
+
+	let mut report = TestReport {
+		outcomes: Vec::with_capacity(to_run.len()),
+		filtered_out,
+		duration: Duration::ZERO,
+	};
+
+	if config.list {
+		for (path, _) in &to_run {
+			println!("{}: test", path);
+		}
+		if !config.quiet {
+			println!("\n{} tests", to_run.len());
+		}
+		return report;
+	}
+
+	let (green, yellow, red, reset) = if use_color() {
+		(FG_GREEN, FG_YELLOW, FG_RED, RESET)
+	} else {
+		("", "", "", "")
+	};
+
+	let start = Instant::now();
+	println!("\nrunning {} tests", to_run.len());
+	for (path, test) in to_run {
+		if !config.quiet {
+			print!("test {} ... ", path);
+		}
+
+		let test_start = Instant::now();
+		let (status, output) = if should_ignore(test, config) {
+			(TestStatus::Ignored, Vec::new())
+		} else {
+			// The output has to be captured to check the panic message
+			let capture =
+				!config.nocapture || matches!(test.should_panic, ShouldPanic::WithMessage(_));
+			let (status, output) = run_test(test, capture);
+			if capture && config.nocapture {
+				crate::rt::proc::write_stdout(&output);
+			}
+
+			(check_should_panic(test, status, &output), output)
+		};
+		let outcome = TestOutcome {
+			path,
+			status,
+			duration: test_start.elapsed(),
+			output,
+		};
+
+		match (outcome.status, config.quiet) {
+			(TestStatus::Passed, false) => println!("{}ok{}", green, reset),
+			(TestStatus::Ignored, false) => match test.ignore_reason {
+				Some(reason) => println!("{}ignored, {}{}", yellow, reason, reset),
+				None => println!("{}ignored{}", yellow, reset),
+			},
+			(TestStatus::Failed(_), false) => println!("{}FAILED{}", red, reset),
+			(TestStatus::Passed, true) => print!("."),
+			(TestStatus::Ignored, true) => print!("{}i{}", yellow, reset),
+			(TestStatus::Failed(_), true) => print!("{}F{}", red, reset),
+		}
+
+		report.outcomes.push(outcome);
+	}
+	report.duration = start.elapsed();
+
+	if config.quiet {
+		println!("");
+	}
+	if config.show_output {
+		print_outputs(&report, "successes", true);
+	}
+	if report.failed() > 0 {
+		print_outputs(&report, "failures", false);
+
+		println!("\nfailures:");
+		for outcome in &report.outcomes {
+			let TestStatus::Failed(failure) = outcome.status else {
+				continue;
+			};
+			match failure {
+				TestFailure::ExitCode(code) => {
+					println!("    {} (exit code {})", outcome.path, code)
+				}
+				TestFailure::Signal(signal) => {
+					println!("    {} (killed by signal {})", outcome.path, signal)
+				}
+				TestFailure::DidNotPanic => {
+					println!("    {} (test did not panic as expected)", outcome.path)
+				}
+				TestFailure::ReturnedError => {
+					println!("    {} (test returned an error)", outcome.path)
+				}
+				TestFailure::TimedOut(timeout) => println!(
+					"    {} (timed out after {}ms)",
+					outcome.path,
+					timeout.as_millis()
+				),
+				TestFailure::WrongPanicMessage(expected) => println!(
+					"    {} (panic did not contain expected string \"{}\")",
+					outcome.path, expected
+				),
+			}
+		}
+	}
+
+	let (result, color) = if report.failed() == 0 {
+		("ok", green)
+	} else {
+		("FAILED", red)
+	};
+	println!(
+		"\ntest result: {}{}{}. {} passed; {} failed; {} ignored; {} filtered out; finished in {:.2}s\n",
+		color,
+		result,
+		reset,
+		report.passed(),
+		report.failed(),
+		report.ignored(),
+		report.filtered_out,
+		report.duration.as_secs_f64()
+	);
+
+	report
+}
+
+/// Prints the captured output of every test that passed, or of every test
+/// that failed, depending on `passed`.
+#[cfg(feature = "test-harness")]
+fn print_outputs(report: &TestReport, title: &str, passed: bool) {
+	let mut printed_title = false;
+
+	for outcome in &report.outcomes {
+		let shown = match outcome.status {
+			TestStatus::Passed => passed,
+			TestStatus::Failed(_) => !passed,
+			TestStatus::Ignored => false,
+		};
+		if !shown || outcome.output.is_empty() {
+			continue;
+		}
+		if !printed_title {
+			println!("\n{}:", title);
+			printed_title = true;
+		}
+
+		println!("\n---- {} stdout ----", outcome.path);
+		crate::rt::proc::write_stdout(&outcome.output);
+	}
+}
+
+/// Returns true if the given test should be skipped with the given config.
+#[cfg(feature = "test-harness")]
+fn should_ignore(test: &Test, config: &TestConfig) -> bool {
+	// Without process isolation, a panicking test would end the test run
+	if cfg!(not(unix)) && test.should_panic != ShouldPanic::No {
+		return true;
+	}
+
+	test.ignore && !config.ignored && !config.include_ignored
+}
+
+/// Converts the raw status of a test run to its real status, taking
+/// `should_panic` into account.
+#[cfg(feature = "test-harness")]
+fn check_should_panic(test: &Test, status: TestStatus, output: &[u8]) -> TestStatus {
+	let panicked = matches!(status, TestStatus::Failed(failure) if failure.is_panic());
+
+	match test.should_panic {
+		ShouldPanic::No => status,
+		ShouldPanic::Yes | ShouldPanic::WithMessage(_) if !panicked => match status {
+			TestStatus::Passed => TestStatus::Failed(TestFailure::DidNotPanic),
+			other => other,
+		},
+		ShouldPanic::Yes => TestStatus::Passed,
+		ShouldPanic::WithMessage(message) => {
+			let bytes = message.as_bytes();
+			if bytes.is_empty() || output.windows(bytes.len()).any(|window| window == bytes) {
+				TestStatus::Passed
+			} else {
+				TestStatus::Failed(TestFailure::WrongPanicMessage(message))
+			}
+		}
+	}
+}
+
+/// Returns true if the test harness should print with colors.
+#[cfg(feature = "test-harness")]
+pub(crate) fn use_color() -> bool {
+	#[cfg(unix)]
+	{
+		use crate::rt::os::unix::{FileDescriptor, isatty};
+
+		isatty(FileDescriptor::STDOUT) == 1
+	}
+	#[cfg(not(unix))]
+	{
+		false
+	}
+}
+
+/// Runs a single test in a child process, returning its status and, if
+/// `capture` is true, its output. See the [module-level docs] for more info.
+///
+/// [module-level docs]: crate::rt::test_harness
+#[cfg(all(feature = "test-harness", unix))]
+pub fn run_test(test: &Test, capture: bool) -> (TestStatus, Vec<u8>) {
+	use crate::{
+		io::Reader,
+		rt::os::unix::{self, FileDescriptor},
+	};
+
+	let pipe = capture.then(|| unix::pipe().expect("Crux test harness: Failed to create a pipe"));
+
+	let pid = unsafe { unix::fork() };
+	if pid == -1 {
+		panic!("Crux test harness: Failed to fork the test process");
+	}
+
+	if pid == 0 {
+		// Child process
+		if let Some((reader, writer)) = pipe {
+			drop(reader);
+			unix::redirect(writer.fd(), FileDescriptor::STDOUT)
+				.expect("Crux test harness: Failed to redirect stdout");
+			unix::redirect(writer.fd(), FileDescriptor::STDERR)
+				.expect("Crux test harness: Failed to redirect stderr");
+		}
+		// The timer sends SIGALRM when it expires, which kills the child
+		if let Some(timeout) = test.timeout {
+			let timer = unix::ITimerVal {
+				it_interval: unix::TimeVal {
+					tv_sec: 0,
+					tv_usec: 0,
+				},
+				it_value: unix::TimeVal {
+					tv_sec: timeout.as_secs() as libc::time_t,
+					tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+				},
+			};
+			unsafe { unix::setitimer(libc::ITIMER_REAL, NonNullConst::from_ref(&timer), None) };
+		}
+
+		(test.func)();
+		unix::exit(0);
+	}
+
+	// Parent process
+	let mut output = Vec::new();
+	if let Some((mut reader, writer)) = pipe {
+		drop(writer);
+		// If reading fails, just report whatever output was read
+		let _ = reader.read_to_end(&mut output);
+	}
+
+	let mut status = 0;
+	let res = unsafe { unix::waitpid(pid, Some(NonNull::from(&mut status)), 0) };
+	assert_eq!(
+		res, pid,
+		"Crux test harness: Failed to wait for the test process"
+	);
+
+	let status = if libc::WIFEXITED(status) {
+		match libc::WEXITSTATUS(status) {
+			0 => TestStatus::Passed,
+			ERROR_EXIT_CODE => TestStatus::Failed(TestFailure::ReturnedError),
+			code => TestStatus::Failed(TestFailure::ExitCode(code)),
+		}
+	} else {
+		match (libc::WTERMSIG(status), test.timeout) {
+			(libc::SIGALRM, Some(timeout)) => TestStatus::Failed(TestFailure::TimedOut(timeout)),
+			(signal, _) => TestStatus::Failed(TestFailure::Signal(signal)),
+		}
+	};
+
+	(status, output)
+}
+/// Runs a single test. On this platform, tests can't be isolated, so this
+/// just calls the test; if it fails, the whole process exits. See the
+/// [module-level docs] for more info.
+///
+/// [module-level docs]: crate::rt::test_harness
+#[cfg(all(feature = "test-harness", not(unix)))]
+pub fn run_test(test: &Test, _capture: bool) -> (TestStatus, Vec<u8>) {
+	(test.func)();
+	(TestStatus::Passed, Vec::new())
+}
+
+#[cfg(all(feature = "test-harness", test))]
+#[unsafe(no_mangle)]
+fn crux_main() {
+	run_all_tests();
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(all(test, feature = "test-harness"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn config() {
+		let mut config = TestConfig::default();
+		cli::parse(
+			&[
+				"tests",
+				"cli",
+				"--exact",
+				"rt::test_harness::tests::config",
+				"-q",
+				"--include-ignored",
+			],
+			&mut config,
+			true,
+		);
+		assert_eq!(config.filters, ["cli", "rt::test_harness::tests::config"]);
+		assert!(config.exact);
+		assert!(config.quiet);
+		assert!(config.include_ignored);
+		assert!(!config.list);
+		assert!(!config.ignored);
+
+		assert!(config.matches("rt::test_harness::tests::config"));
+		assert!(!config.matches("rt::test_harness::tests::config_2"));
+		config.exact = false;
+		assert!(config.matches("term::cli::tests::help"));
+		assert!(!config.matches("rt::test_harness::tests::matching"));
+
+		let test = Test {
+			module: "crux::rt::test_harness::tests",
+			name: "config",
+			func: || {},
+			should_panic: ShouldPanic::No,
+			ignore: false,
+			ignore_reason: None,
+			timeout: None,
+		};
+		assert_eq!(test.path(), "rt::test_harness::tests::config");
+	}
+
+	#[test]
+	fn should_panic() {
+		let mut test = Test {
+			module: "crux::rt::test_harness::tests",
+			name: "should_panic",
+			func: || {},
+			should_panic: ShouldPanic::Yes,
+			ignore: false,
+			ignore_reason: None,
+			timeout: None,
+		};
+		let panicked = TestStatus::Failed(TestFailure::ExitCode(101));
+
+		assert_eq!(check_should_panic(&test, panicked, b""), TestStatus::Passed);
+		assert_eq!(
+			check_should_panic(&test, TestStatus::Passed, b""),
+			TestStatus::Failed(TestFailure::DidNotPanic)
+		);
+		assert_eq!(
+			check_should_panic(&test, TestStatus::Failed(TestFailure::ExitCode(1)), b""),
+			TestStatus::Failed(TestFailure::ExitCode(1))
+		);
+
+		test.should_panic = ShouldPanic::WithMessage("out of bounds");
+		assert_eq!(
+			check_should_panic(&test, panicked, b"panicked: index out of bounds"),
+			TestStatus::Passed
+		);
+		assert_eq!(
+			check_should_panic(&test, panicked, b"panicked: overflow"),
+			TestStatus::Failed(TestFailure::WrongPanicMessage("out of bounds"))
+		);
+
+		test.should_panic = ShouldPanic::No;
+		assert_eq!(check_should_panic(&test, panicked, b""), panicked);
+	}
+}
@@ -0,0 +1,304 @@
+//! Items for measuring time.
+
+#[doc(inline)]
+pub use core::time::Duration;
+
+use {
+	crate::{
+		lang::{Add, AddAssign, FromStr, Sub, SubAssign},
+		num::{ParseAmountError, format::write_amount, parse::parse_amount},
+		rt::os,
+	},
+	core::fmt::{self, Display, Formatter},
+};
+
+/// A measurement of a monotonically nondecreasing clock. Instants are only
+/// useful for measuring how much time passed between them; they can't be
+/// converted to a date or time of day.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Instant(Duration);
+impl Instant {
+	/// Returns the current instant.
+	pub fn now() -> Self {
+		#[cfg(unix)]
+		{
+			let mut time = os::unix::TimeSpec {
+				tv_sec: 0,
+				tv_nsec: 0,
+			};
+			let res =
+				unsafe { os::unix::clock_gettime(libc::CLOCK_MONOTONIC, NonNull::from(&mut time)) };
+			assert_eq!(res, 0, "Crux: Failed to read the monotonic clock");
+
+			Self(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+		}
+		#[cfg(windows)]
+		{
+			// These can't fail on Windows XP or later
+			let mut count = 0i64;
+			let mut frequency = 0i64;
+			unsafe {
+				os::win32::QueryPerformanceCounter(NonNull::from(&mut count));
+				os::win32::QueryPerformanceFrequency(NonNull::from(&mut frequency));
+			}
+
+			// Whole seconds are split off first, so converting the remaining
+			// ticks to nanoseconds can't overflow
+			let secs = count / frequency;
+			let nanos = (count % frequency) * 1_000_000_000 / frequency;
+			Self(Duration::new(secs as u64, nanos as u32))
+		}
+		#[cfg(not(supported_os))]
+		compile_error!("unimplemented on this operating system");
+	}
+
+	/// Returns how much time passed between `earlier` and this instant, or a
+	/// zero duration if `earlier` is after this instant.
+	pub fn duration_since(&self, earlier: Instant) -> Duration {
+		self.0.saturating_sub(earlier.0)
+	}
+	/// Returns how much time has passed since this instant.
+	pub fn elapsed(&self) -> Duration {
+		Self::now().duration_since(*self)
+	}
+}
+
+/// A measurement of the system's wall clock, stored as the time since the Unix
+/// epoch (midnight UTC on January 1st, 1970).
+///
+/// Unlike [`Instant`], the system clock can be changed (e.g. by the user, or
+/// when syncing with a time server), so it can go backwards. Use [`Instant`]
+/// to measure how long something takes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SystemTime(Duration);
+impl SystemTime {
+	/// Midnight UTC on January 1st, 1970.
+	pub const UNIX_EPOCH: Self = Self(Duration::ZERO);
+
+	/// Returns the current time.
+	pub fn now() -> Self {
+		#[cfg(unix)]
+		{
+			let mut time = os::unix::TimeSpec {
+				tv_sec: 0,
+				tv_nsec: 0,
+			};
+			let res =
+				unsafe { os::unix::clock_gettime(libc::CLOCK_REALTIME, NonNull::from(&mut time)) };
+			assert_eq!(res, 0, "Crux: Failed to read the system clock");
+
+			// `time_t` is only 32 bits on some platforms
+			#[allow(clippy::unnecessary_cast)]
+			let secs = time.tv_sec as i64;
+
+			Self::from_unix(secs, time.tv_nsec as u32)
+		}
+		#[cfg(windows)]
+		{
+			/// 100ns intervals between 1601 (Windows' epoch) and 1970.
+			const UNIX_EPOCH_OFFSET: i64 = 116_444_736_000_000_000;
+
+			// `FILETIME` is a 64-bit count of 100ns intervals, split into two
+			// `u32`s, so it has the same layout as a little-endian `u64`
+			let mut time = 0u64;
+			unsafe { os::win32::GetSystemTimePreciseAsFileTime(NonNull::from(&mut time)) };
+
+			let intervals = time as i64 - UNIX_EPOCH_OFFSET;
+			Self::from_unix(
+				intervals.div_euclid(10_000_000),
+				(intervals.rem_euclid(10_000_000) * 100) as u32,
+			)
+		}
+		#[cfg(not(supported_os))]
+		compile_error!("unimplemented on this operating system");
+	}
+	/// Creates a time from a Unix timestamp. Times before the Unix epoch are
+	/// clamped to it.
+	pub const fn from_unix(secs: i64, nanos: u32) -> Self {
+		if secs < 0 {
+			Self::UNIX_EPOCH
+		} else {
+			Self(Duration::new(secs as u64, nanos))
+		}
+	}
+
+	/// How much time passed between the Unix epoch and this time.
+	pub const fn since_unix_epoch(&self) -> Duration {
+		self.0
+	}
+	/// Returns how much time passed between `earlier` and this time, or a zero
+	/// duration if `earlier` is after this time.
+	pub fn duration_since(&self, earlier: SystemTime) -> Duration {
+		self.0.saturating_sub(earlier.0)
+	}
+	/// Returns how much time has passed since this time.
+	pub fn elapsed(&self) -> Duration {
+		Self::now().duration_since(*self)
+	}
+}
+
+/// Units used when displaying time amounts, in nanoseconds.
+const DISPLAY_UNITS: &[(&str, u128)] = &[
+	("ns", 1),
+	("µs", 1_000),
+	("ms", 1_000_000),
+	("s", 1_000_000_000),
+	("min", 60_000_000_000),
+	("h", 3_600_000_000_000),
+];
+/// Every unit accepted when parsing time amounts, in nanoseconds.
+const PARSE_UNITS: &[(&str, u128)] = &[
+	("ns", 1),
+	("us", 1_000),
+	("µs", 1_000),
+	("ms", 1_000_000),
+	("s", 1_000_000_000),
+	("m", 60_000_000_000),
+	("min", 60_000_000_000),
+	("h", 3_600_000_000_000),
+	("d", 86_400_000_000_000),
+];
+
+/// An amount of time, with convenience initializers for various units of
+/// time. This is the time equivalent of
+/// [`MemoryAmount`](crate::rt::mem::MemoryAmount), and converts to and from a
+/// [`Duration`].
+///
+/// Time amounts are displayed in the largest unit that fits (e.g. `1.5 s` or
+/// `250 ms`), and can be parsed from strings like `500ms`, `2s`, or `1.5 h`,
+/// which is handy for CLI flags. The accepted units are `ns`, `us` (or `µs`),
+/// `ms`, `s`, `m` (or `min`), `h`, and `d`; unlike memory amounts, the unit
+/// can't be left out.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub struct TimeAmount(Duration);
+impl TimeAmount {
+	pub const ZERO: Self = Self(Duration::ZERO);
+
+	pub const fn nanoseconds(amount: u64) -> Self {
+		Self(Duration::from_nanos(amount))
+	}
+	pub const fn microseconds(amount: u64) -> Self {
+		Self(Duration::from_micros(amount))
+	}
+	pub const fn milliseconds(amount: u64) -> Self {
+		Self(Duration::from_millis(amount))
+	}
+	pub const fn seconds(amount: u64) -> Self {
+		Self(Duration::from_secs(amount))
+	}
+	pub const fn minutes(amount: u64) -> Self {
+		Self(Duration::from_secs(amount.saturating_mul(60)))
+	}
+	pub const fn hours(amount: u64) -> Self {
+		Self(Duration::from_secs(amount.saturating_mul(60 * 60)))
+	}
+
+	pub const fn as_duration(self) -> Duration {
+		self.0
+	}
+	pub const fn amount_nanoseconds(self) -> u128 {
+		self.0.as_nanos()
+	}
+
+	pub const fn checked_add(self, rhs: Self) -> Option<Self> {
+		match self.0.checked_add(rhs.0) {
+			Some(amount) => Some(Self(amount)),
+			None => None,
+		}
+	}
+	pub const fn checked_sub(self, rhs: Self) -> Option<Self> {
+		match self.0.checked_sub(rhs.0) {
+			Some(amount) => Some(Self(amount)),
+			None => None,
+		}
+	}
+
+	/// Parses a time amount, like `500ms` or `1.5 h`. See [`TimeAmount`] for
+	/// the accepted units.
+	pub fn parse(s: &str) -> Result<Self, ParseAmountError> {
+		let nanos = parse_amount(s, PARSE_UNITS)?;
+		let secs = u64::try_from(nanos / 1_000_000_000).map_err(|_| ParseAmountError::Overflow)?;
+		Ok(Self(Duration::new(secs, (nanos % 1_000_000_000) as u32)))
+	}
+}
+impl From<Duration> for TimeAmount {
+	fn from(value: Duration) -> Self {
+		Self(value)
+	}
+}
+impl From<TimeAmount> for Duration {
+	fn from(value: TimeAmount) -> Self {
+		value.0
+	}
+}
+impl const Add for TimeAmount {
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self::Output {
+		Self(self.0 + rhs.0)
+	}
+}
+impl AddAssign for TimeAmount {
+	fn add_assign(&mut self, rhs: Self) {
+		self.0 += rhs.0
+	}
+}
+impl const Sub for TimeAmount {
+	type Output = Self;
+
+	fn sub(self, rhs: Self) -> Self::Output {
+		Self(self.0 - rhs.0)
+	}
+}
+impl SubAssign for TimeAmount {
+	fn sub_assign(&mut self, rhs: Self) {
+		self.0 -= rhs.0
+	}
+}
+impl FromStr for TimeAmount {
+	type Err = ParseAmountError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Self::parse(s)
+	}
+}
+impl Display for TimeAmount {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write_amount(f, self.0.as_nanos(), DISPLAY_UNITS)
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn time_amount() {
+		assert_eq!(
+			TimeAmount::parse("500ms"),
+			Ok(TimeAmount::milliseconds(500))
+		);
+		assert_eq!(TimeAmount::parse("2s"), Ok(TimeAmount::seconds(2)));
+		assert_eq!(TimeAmount::parse("1.5 h"), Ok(TimeAmount::minutes(90)));
+		assert_eq!(TimeAmount::parse("10us"), Ok(TimeAmount::microseconds(10)));
+		assert_eq!(TimeAmount::parse("10"), Err(ParseAmountError::UnknownUnit));
+		assert_eq!(TimeAmount::parse("s"), Err(ParseAmountError::InvalidNumber));
+		assert_eq!(TimeAmount::parse(""), Err(ParseAmountError::Empty));
+
+		assert_eq!(format!("{}", TimeAmount::milliseconds(1500)), "1.5 s");
+		assert_eq!(format!("{}", TimeAmount::milliseconds(250)), "250 ms");
+		assert_eq!(format!("{}", TimeAmount::nanoseconds(1_250)), "1.25 µs");
+		assert_eq!(format!("{}", TimeAmount::hours(3)), "3 h");
+		assert_eq!(format!("{}", TimeAmount::ZERO), "0 ns");
+
+		let total = TimeAmount::seconds(1).checked_add(Duration::from_millis(1).into());
+		assert_eq!(total.map(Duration::from), Some(Duration::from_millis(1001)));
+		assert_eq!(TimeAmount::ZERO.checked_sub(TimeAmount::seconds(1)), None);
+	}
+}
@@ -0,0 +1,57 @@
+//! Items for working with asynchronous code.
+
+#[doc(inline)]
+pub use core::{
+	future::{Future, IntoFuture, pending, poll_fn, ready},
+	pin::{Pin, pin},
+	task::{Context, Poll, Waker},
+};
+
+/// Runs a future to completion on the current thread, returning its output.
+///
+/// This is a minimal executor: it polls the future in a loop until it's
+/// ready, and never sleeps, so a future that's pending for a long time will
+/// keep the CPU busy. It's meant for running small futures, such as async
+/// tests, from synchronous code.
+pub fn block_on<F: IntoFuture>(future: F) -> F::Output {
+	let mut future = pin!(future.into_future());
+	let mut ctx = Context::from_waker(Waker::noop());
+
+	loop {
+		if let Poll::Ready(output) = future.as_mut().poll(&mut ctx) {
+			return output;
+		}
+		core::hint::spin_loop();
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn executor() {
+		let mut polls = 0;
+		let output = block_on(poll_fn(|_| {
+			polls += 1;
+			if polls == 3 {
+				Poll::Ready(polls)
+			} else {
+				Poll::Pending
+			}
+		}));
+		assert_eq!(output, 3);
+		assert_eq!(block_on(async { 2 + 2 }), 4);
+	}
+
+	#[test]
+	async fn async_test() {
+		assert_eq!(ready(2).await + 2, 4);
+	}
+}
@@ -7,8 +7,9 @@
 //! any limitations).
 //!
 //! Crux's CLI library is somewhat limited compared to other CLI libraries:
-//! - It does not have automatic help message generation. You are responsible
-//!   for that.
+//! - Help messages are opt-in. The parser never sees a description of your CLI,
+//!   so if you want a generated help message, you have to describe your flags
+//!   and subcommands with a [`CommandSpec`] and render it with [`render_help`].
 //! - It only works with UTF-8 strings. This is largely just because its API is
 //!   built around a match statement, and you can't match on `OsStr`/`OsString`.
 //!
@@ -96,8 +97,98 @@
 //! - Arguments, including assignments (`--profile release`,
 //!   `--profile=release`, `-p=release`, `-p release`)
 //! - Combined short flags (`-rp release`, `-rp=release`)
+//! - Trailing arguments after a `--` (`run -- --not-a-flag`), which are passed
+//!   to your parser as [`FlagClass::Trailing`]
+//! - Negative numbers as arguments (`--offset -5`), if enabled with
+//!   [`ParseConfig::negative_numbers`]
+//!
+//!
+//! # Help Messages
+//!
+//! Because your CLI is just a match statement, Crux can't know what flags it
+//! accepts. To get a generated help message, describe your CLI with a
+//! [`CommandSpec`] (usually in a constant, or via the [`CliHelp`] trait), then
+//! print the output of [`render_help`] when the user passes `-h`/`--help`:
+//!
+//! ```rs
+//! const HELP: CommandSpec = CommandSpec {
+//!     name: "my-app",
+//!     about: "Does things.",
+//!     flags: &[
+//!         FlagSpec::HELP,
+//!         FlagSpec {
+//!             short: Some('n'),
+//!             long: Some("name"),
+//!             argument: Some("NAME"),
+//!             help: "The name to use",
+//!         },
+//!     ],
+//!     subcommands: &[],
+//! };
+//!
+//! // In your `CliParser::parse` implementation:
+//! "h" | "help" => {
+//!     print!("{}", render_help(&HELP));
+//!     crux::rt::proc::exit();
+//! }
+//! ```
+//!
+//!
+//! # Deriving
+//!
+//! For larger CLIs, writing the match statement by hand gets tedious. Instead,
+//! you can `#[derive(CliParser)]` on a struct, which generates the same match
+//! statement for you (and a [`CliHelp`] implementation, using doc comments as
+//! help text):
+//!
+//! ```rs
+//! use crux::term::cli::*;
+//!
+//! /// Does things.
+//! #[derive(CliParser)]
+//! struct MyCliApp<'a> {
+//!     #[cli(subcommand)]
+//!     cmd: Command,
+//!     /// The profile to use.
+//!     #[cli(short = 'p', long = "profile")]
+//!     profile: Option<&'a str>,
+//!     /// Print more output.
+//!     #[cli(short)]
+//!     verbose: bool,
+//! }
+//!
+//! #[derive(CliSubcommand)]
+//! enum Command {
+//!     /// Build the project.
+//!     #[cli(alias = "b")]
+//!     Build,
+//!     /// Run the project.
+//!     #[cli(alias = "r")]
+//!     Run,
+//! }
+//! ```
+//!
+//! Every field is a flag, named after the field (in kebab-case) unless you
+//! pass `long = "..."`. `#[cli(short)]` adds a short flag using the first
+//! character of the field's name, or you can set one explicitly with
+//! `#[cli(short = 'c')]`. How a field is parsed depends on its type (see
+//! [`CliField`]); fields can be skipped with `#[cli(skip)]`.
+//!
+//! Up to one field can be marked `#[cli(trailing)]`, which collects every
+//! argument after a `--` (with [`CliField::set_argument`]).
+//!
+//! Up to one field can be marked `#[cli(subcommand)]`. Its type must implement
+//! [`CliSubcommand`], which can be derived for enums with only unit variants.
+//! Variants are named after themselves (in kebab-case), unless you pass
+//! `#[cli(name = "...")]`, and can have extra names with
+//! `#[cli(alias = "...")]`.
+//!
+//! Errors panic by default. Use `#[cli(error = some_function)]` on the struct
+//! to handle them instead; it will be called like [`CliParser::error`].
 
-use crate::lang::PhantomData;
+pub use crate::macros::{CliParser, CliSubcommand};
+
+use crate::lang::{FromStr, PhantomData, type_name};
 
 /// A type that parses CLI arguments. See the [module-level docs] for more info.
 ///
@@ -136,6 +227,14 @@ pub enum ParseError<'a> {
 	NoFlag { num_dashes: u8 },
 	/// The user passed a flag that needs an argument without an argument.
 	MissingArgument { flag: &'a str, class: FlagClass<'a> },
+	/// The user passed an argument to a flag, but the argument couldn't be
+	/// parsed as the type that flag expects (see
+	/// [`CliParsingCtx::next_argument_parsed`]).
+	InvalidArgumentValue {
+		flag: &'a str,
+		arg: &'a str,
+		expected: &'static str,
+	},
 }
 
 //
@@ -144,22 +243,64 @@ pub enum ParseError<'a> {
 //
 //
 
+/// Options that change how [`parse_with_config`] parses CLI arguments.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseConfig {
+	/// Whether or not the parser will skip the first argument, which is
+	/// traditionally the path to the current executable and not an actual CLI
+	/// flag/argument to parse. You probably want to set it to true.
+	pub skip_executable: bool,
+	/// If true, arguments that are a dash followed by a number (e.g. `-5` or
+	/// `-0.5`) are classified as arguments instead of short flags, so they can
+	/// be passed as values (e.g. `--offset -5`). See [`classify_with_config`].
+	///
+	/// This only applies to items that are entirely a number; combined short
+	/// flags that contain digits, like `-v2`, are still flags.
+	pub negative_numbers: bool,
+}
+
 /// Parses the given slice of CLI arguments with the given [`CliParser`].
 ///
 /// `skip_executable` configures whether or not the parser will skip the first
 /// argument, which is traditionally the path to the current executable and
 /// not an actual CLI flag/argument to parse. You probably want to set it to
 /// true.
+///
+/// To configure the parser further, see [`parse_with_config`].
 pub fn parse<'a, P>(args: &'a [&'a str], parser: &mut P, skip_executable: bool)
 where
 	P: CliParser<'a>,
 {
-	let args = if skip_executable { &args[1..] } else { args };
+	parse_with_config(
+		args,
+		parser,
+		ParseConfig {
+			skip_executable,
+			..Default::default()
+		},
+	)
+}
+
+/// Parses the given slice of CLI arguments with the given [`CliParser`] and
+/// [`ParseConfig`].
+pub fn parse_with_config<'a, P>(args: &'a [&'a str], parser: &mut P, config: ParseConfig)
+where
+	P: CliParser<'a>,
+{
+	let args = if config.skip_executable {
+		&args[1..]
+	} else {
+		args
+	};
 
 	let mut ctx = CliParsingCtx {
 		args,
 		idx: usize::MAX, // this gets incremented, where it wraps around to 0
 		status: CliParsingStatus::Used,
+		flag: "",
+		peeking: false,
+		trailing: false,
+		config,
 		_ph: PhantomData,
 	};
 
@@ -210,7 +351,16 @@ where
 				}
 
 				let full_arg = args[ctx.idx];
-				let class = classify(full_arg);
+				if full_arg == "--" && !ctx.trailing {
+					ctx.trailing = true;
+					continue;
+				}
+
+				let class = if ctx.trailing {
+					FlagClass::Trailing { raw: full_arg }
+				} else {
+					classify_with_config(full_arg, &ctx.config)
+				};
 				match class {
 					FlagClass::Short { flag } => match flag.chars().count() {
 						0 => {
@@ -270,12 +420,18 @@ where
 					FlagClass::SubcommandOrArgumentAssigned { raw, equals_idx } => {
 						ctx.status = CliParsingStatus::StoppedAtEquals(equals_idx);
 
+						(raw, class)
+					}
+					FlagClass::Trailing { raw } => {
+						ctx.status = CliParsingStatus::Used;
+
 						(raw, class)
 					}
 				}
 			}
 		};
 
+		ctx.flag = flag;
 		match parser.parse(flag, class, &mut ctx) {
 			ParseResult::Recognised => {}
 			ParseResult::NotRecognised => parser.error(ParseError::UnknownFlag { flag }),
@@ -324,16 +480,89 @@ pub struct CliParsingCtx<'a, P: CliParser<'a>> {
 	pub args: &'a [&'a str],
 	pub idx: usize,
 	pub status: CliParsingStatus<'a>,
+	/// The flag currently being parsed.
+	pub flag: &'a str,
+	/// True while [`CliParsingCtx::next_argument`] is calling
+	/// [`CliParser::parse`] to check if an item is a flag or an argument.
+	pub peeking: bool,
+	/// True once the parser has passed a `--` argument. Every item after it is
+	/// passed to [`CliParser::parse`] as [`FlagClass::Trailing`].
+	pub trailing: bool,
+	/// The config passed to [`parse_with_config`].
+	pub config: ParseConfig,
 	pub _ph: PhantomData<P>,
 }
 impl<'a, P: CliParser<'a>> CliParsingCtx<'a, P> {
+	/// Reads the next argument with [`CliParsingCtx::next_argument`], then
+	/// parses it with [`FromStr`].
+	///
+	/// If there's no argument, this returns `None`. If there is an argument,
+	/// but it can't be parsed, this reports a
+	/// [`ParseError::InvalidArgumentValue`] to `parser` and then returns
+	/// `None`.
+	pub fn next_argument_parsed<T: FromStr>(&mut self, parser: &mut P) -> Option<T> {
+		let arg = self.next_argument(parser)?;
+		match arg.parse() {
+			Ok(val) => Some(val),
+			Err(_) => {
+				parser.error(ParseError::InvalidArgumentValue {
+					flag: self.flag,
+					arg,
+					expected: type_name::<T>(),
+				});
+				None
+			}
+		}
+	}
+
+	/// Treats the item currently being parsed as a positional argument, and
+	/// returns it exactly as the user passed it.
+	///
+	/// This is useful for flags with the [`FlagClass::SubcommandOrArgument`] or
+	/// [`FlagClass::SubcommandOrArgumentAssigned`] classes, which Crux doesn't
+	/// know how to interpret. For the latter, the `flag` passed to
+	/// [`CliParser::parse`] stops at the `=`, while this returns the whole
+	/// item (e.g. `key=value`) and stops Crux from reporting the assignment as
+	/// an unused argument.
+	///
+	/// Returns `None` if the current item is a flag (starts with a dash), or if
+	/// Crux is only checking whether the item is a flag (see
+	/// [`CliParsingCtx::peeking`]). In the latter case, your parser should
+	/// return [`ParseResult::NotRecognised`], so the item can be used as an
+	/// argument for the previous flag.
+	pub fn positional(&mut self) -> Option<&'a str> {
+		if self.peeking {
+			return None;
+		}
+		let raw = *self.args.get(self.idx)?;
+		if classify_with_config(raw, &self.config).is_flag() {
+			return None;
+		}
+		if let CliParsingStatus::StoppedAtEquals(_) = self.status {
+			self.status = CliParsingStatus::Used;
+		}
+
+		Some(raw)
+	}
+
+	/// Returns all of the items after the one currently being parsed. This
+	/// doesn't consume them, so Crux will continue parsing them as normal
+	/// afterwards.
+	pub fn remaining_args(&self) -> &'a [&'a str] {
+		self.args.get(self.idx.wrapping_add(1)..).unwrap_or(&[])
+	}
 	pub fn next_argument(&mut self, parser: &mut P) -> Option<&'a str> {
 		match self.status {
 			CliParsingStatus::Used => {
 				self.idx += 1;
 				let flag_or_arg = *self.args.get(self.idx)?;
-				let class = classify(flag_or_arg);
+				let class = if self.trailing {
+					FlagClass::Trailing { raw: flag_or_arg }
+				} else {
+					classify_with_config(flag_or_arg, &self.config)
+				};
 				let val = match class {
+					FlagClass::Trailing { raw } => Some(raw),
 					FlagClass::Long { flag: _ }
 					| FlagClass::LongAssigned {
 						flag: _,
@@ -345,13 +574,19 @@ impl<'a, P: CliParser<'a>> CliParsingCtx<'a, P> {
 						equals_idx: _,
 					} => None,
 					FlagClass::SubcommandOrArgumentAssigned { raw, equals_idx } => {
-						match parser.parse(&raw[..equals_idx], class, self) {
+						self.peeking = true;
+						let res = parser.parse(&raw[..equals_idx], class, self);
+						self.peeking = false;
+						match res {
 							ParseResult::NotRecognised => Some(raw),
 							ParseResult::Recognised | ParseResult::MissingArgument => None,
 						}
 					}
 					FlagClass::SubcommandOrArgument { raw } => {
-						match parser.parse(raw, class, self) {
+						self.peeking = true;
+						let res = parser.parse(raw, class, self);
+						self.peeking = false;
+						match res {
 							ParseResult::NotRecognised => Some(raw),
 							ParseResult::Recognised | ParseResult::MissingArgument => None,
 						}
@@ -388,6 +623,246 @@ impl<'a, P: CliParser<'a>> CliParsingCtx<'a, P> {
 	}
 }
 
+//
+//
+// Deriving
+//
+//
+
+/// A type that can be used as a field in a struct that derives [`CliParser`].
+pub trait CliField<'a> {
+	/// If true, the flag for this field takes an argument, which is passed to
+	/// [`CliField::set_argument`]. Otherwise, [`CliField::set_flag`] is called
+	/// when the flag is passed.
+	const TAKES_ARGUMENT: bool;
+
+	/// Called when this field's flag is passed, if it doesn't take an argument.
+	fn set_flag(&mut self) {}
+	/// Called when this field's flag is passed with an argument.
+	fn set_argument(&mut self, arg: &'a str) {
+		let _ = arg;
+	}
+}
+/// Set to `true` when the flag is passed.
+impl CliField<'_> for bool {
+	const TAKES_ARGUMENT: bool = false;
+
+	fn set_flag(&mut self) {
+		*self = true;
+	}
+}
+/// Set to the flag's argument.
+impl<'a> CliField<'a> for &'a str {
+	const TAKES_ARGUMENT: bool = true;
+
+	fn set_argument(&mut self, arg: &'a str) {
+		*self = arg;
+	}
+}
+/// Set to `Some` with the flag's argument.
+impl<'a> CliField<'a> for Option<&'a str> {
+	const TAKES_ARGUMENT: bool = true;
+
+	fn set_argument(&mut self, arg: &'a str) {
+		*self = Some(arg);
+	}
+}
+/// Collects the argument from every time the flag is passed.
+impl<'a> CliField<'a> for Vec<&'a str> {
+	const TAKES_ARGUMENT: bool = true;
+
+	fn set_argument(&mut self, arg: &'a str) {
+		self.push(arg);
+	}
+}
+
+/// A set of subcommands, used by structs that derive [`CliParser`].
+pub trait CliSubcommand: Sized {
+	/// Descriptions of every subcommand, for generating help messages.
+	const SUBCOMMANDS: &'static [CommandSpec<'static>];
+
+	/// Finds the subcommand with the given name.
+	fn from_name(name: &str) -> Option<Self>;
+}
+
+//
+//
+// Help generation
+//
+//
+
+/// A [`CliParser`] that describes its flags and subcommands, so a help message
+/// can be generated for it with [`render_help`].
+pub trait CliHelp {
+	/// The description of this CLI.
+	const SPEC: CommandSpec<'static>;
+
+	/// Generates a help message for this CLI. See [`render_help`].
+	fn help() -> String {
+		render_help(&Self::SPEC)
+	}
+}
+
+/// Describes a single flag, for generating help messages. See
+/// [`render_help`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagSpec<'a> {
+	/// The single-character version of the flag (e.g. `p` for `-p`).
+	pub short: Option<char>,
+	/// The multi-character version of the flag (e.g. `profile` for
+	/// `--profile`).
+	pub long: Option<&'a str>,
+	/// The name of the flag's argument, if it takes one (e.g. `PROFILE`).
+	pub argument: Option<&'a str>,
+	/// A short description of what the flag does.
+	pub help: &'a str,
+}
+impl FlagSpec<'_> {
+	/// The standard `-h`/`--help` flag.
+	pub const HELP: FlagSpec<'static> = FlagSpec {
+		short: Some('h'),
+		long: Some("help"),
+		argument: None,
+		help: "Print this help message",
+	};
+
+	/// Returns true if `flag` (as passed to [`CliParser::parse`]) is either the
+	/// short or long version of this flag.
+	pub fn matches(&self, flag: &str) -> bool {
+		if self.long == Some(flag) {
+			return true;
+		}
+
+		let mut chars = flag.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => self.short == Some(c),
+			_ => false,
+		}
+	}
+}
+
+/// Describes a command or subcommand, for generating help messages. See
+/// [`render_help`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandSpec<'a> {
+	/// The name of the command. For the top-level command, this should be the
+	/// name of the program.
+	pub name: &'a str,
+	/// A short description of what the command does.
+	pub about: &'a str,
+	/// The flags this command accepts.
+	pub flags: &'a [FlagSpec<'a>],
+	/// The subcommands this command accepts.
+	pub subcommands: &'a [CommandSpec<'a>],
+}
+impl<'a> CommandSpec<'a> {
+	/// Finds a subcommand of this command by name.
+	pub fn subcommand(&self, name: &str) -> Option<&CommandSpec<'a>> {
+		self.subcommands.iter().find(|cmd| cmd.name == name)
+	}
+	/// Finds a flag of this command, using [`FlagSpec::matches`].
+	pub fn flag(&self, flag: &str) -> Option<&FlagSpec<'a>> {
+		self.flags.iter().find(|spec| spec.matches(flag))
+	}
+}
+
+/// Generates a help message for the given command. The message has a usage
+/// line, followed by a list of subcommands and a list of flags (if the command
+/// has any), with descriptions aligned into columns:
+///
+/// ```text
+/// Does things.
+///
+/// Usage: my-app [COMMAND] [FLAGS]
+///
+/// Commands:
+///   build  Build the project
+///   run    Run the project
+///
+/// Flags:
+///   -h, --help            Print this help message
+///   -p, --profile <NAME>  The profile to use
+/// ```
+pub fn render_help(spec: &CommandSpec) -> String {
+	let mut out = String::new();
+
+	if !spec.about.is_empty() {
+		out.push_str(spec.about);
+		out.push_str("\n\n");
+	}
+
+	out.push_str("Usage: ");
+	out.push_str(spec.name);
+	if !spec.subcommands.is_empty() {
+		out.push_str(" [COMMAND]");
+	}
+	if !spec.flags.is_empty() {
+		out.push_str(" [FLAGS]");
+	}
+	out.push('\n');
+
+	if !spec.subcommands.is_empty() {
+		out.push_str("\nCommands:\n");
+		let width = spec
+			.subcommands
+			.iter()
+			.map(|cmd| cmd.name.chars().count())
+			.max()
+			.unwrap_or(0);
+		for cmd in spec.subcommands {
+			push_help_row(&mut out, cmd.name, width, cmd.about);
+		}
+	}
+
+	if !spec.flags.is_empty() {
+		out.push_str("\nFlags:\n");
+		let labels: Vec<String> = spec.flags.iter().map(flag_label).collect();
+		let width = labels
+			.iter()
+			.map(|label| label.chars().count())
+			.max()
+			.unwrap_or(0);
+		for (label, flag) in labels.iter().zip(spec.flags) {
+			push_help_row(&mut out, label, width, flag.help);
+		}
+	}
+
+	out
+}
+
+/// Formats a flag for the left column of a help message (e.g.
+/// `-p, --profile <NAME>`).
+fn flag_label(flag: &FlagSpec) -> String {
+	let mut label = match (flag.short, flag.long) {
+		(Some(short), Some(long)) => format!("-{short}, --{long}"),
+		(Some(short), None) => format!("-{short}"),
+		// Indent to line up with flags that have a short version
+		(None, Some(long)) => format!("    --{long}"),
+		(None, None) => String::new(),
+	};
+	if let Some(argument) = flag.argument {
+		label.push_str(" <");
+		label.push_str(argument);
+		label.push('>');
+	}
+
+	label
+}
+
+/// Writes one row of a help message, padding `label` to `width` characters so
+/// the descriptions line up.
+fn push_help_row(out: &mut String, label: &str, width: usize, help: &str) {
+	out.push_str("  ");
+	out.push_str(label);
+	if !help.is_empty() {
+		for _ in label.chars().count()..width + 2 {
+			out.push(' ');
+		}
+		out.push_str(help);
+	}
+	out.push('\n');
+}
+
 //
 //
 // Flag classes
@@ -410,6 +885,11 @@ pub enum FlagClass<'a> {
 	SubcommandOrArgument { raw: &'a str },
 	/// A flag with no dashes or an argument that is assigned to a value.
 	SubcommandOrArgumentAssigned { raw: &'a str, equals_idx: usize },
+	/// An argument after a `--` argument. These are passed as-is, without any
+	/// classification, even if they start with dashes.
+	///
+	/// [`classify`] never returns this; it's only used by [`parse`].
+	Trailing { raw: &'a str },
 }
 impl FlagClass<'_> {
 	/// Returns true if the flag is a short flag (`-r`) or long flag
@@ -479,6 +959,40 @@ impl FlagClass<'_> {
 				}
 		)
 	}
+	/// Returns true if the argument came after a `--` argument.
+	pub fn is_trailing(&self) -> bool {
+		matches!(self, Self::Trailing { raw: _ })
+	}
+}
+
+/// Classifies a single flag or argument, taking the given [`ParseConfig`] into
+/// account - see [`FlagClass`] for information on classifications.
+pub fn classify_with_config<'a>(arg: &'a str, config: &ParseConfig) -> FlagClass<'a> {
+	if config.negative_numbers && is_negative_number(arg) {
+		FlagClass::SubcommandOrArgument { raw: arg }
+	} else {
+		classify(arg)
+	}
+}
+
+/// Returns true if `arg` is a dash followed by a decimal number (e.g. `-5`,
+/// `-0.5`, `-.5`).
+fn is_negative_number(arg: &str) -> bool {
+	let Some(num) = arg.strip_prefix('-') else {
+		return false;
+	};
+
+	let mut seen_digit = false;
+	let mut seen_dot = false;
+	for byte in num.bytes() {
+		match byte {
+			b'0'..=b'9' => seen_digit = true,
+			b'.' if !seen_dot => seen_dot = true,
+			_ => return false,
+		}
+	}
+
+	seen_digit
 }
 
 /// Classifies a single flag or argument - see [`FlagClass`] for information on
@@ -831,4 +1345,333 @@ mod tests {
 			assert_eq!(parser, case.expected);
 		}
 	}
+
+	#[test]
+	fn help() {
+		const SPEC: CommandSpec = CommandSpec {
+			name: "my-app",
+			about: "Does things.",
+			flags: &[
+				FlagSpec::HELP,
+				FlagSpec {
+					short: Some('p'),
+					long: Some("profile"),
+					argument: Some("NAME"),
+					help: "The profile to use",
+				},
+				FlagSpec {
+					short: None,
+					long: Some("verbose"),
+					argument: None,
+					help: "Print more output",
+				},
+			],
+			subcommands: &[
+				CommandSpec {
+					name: "build",
+					about: "Build the project",
+					flags: &[],
+					subcommands: &[],
+				},
+				CommandSpec {
+					name: "run",
+					about: "Run the project",
+					flags: &[],
+					subcommands: &[],
+				},
+			],
+		};
+
+		assert_eq!(
+			render_help(&SPEC),
+			"Does things.\n\
+			\n\
+			Usage: my-app [COMMAND] [FLAGS]\n\
+			\n\
+			Commands:\n  \
+			build  Build the project\n  \
+			run    Run the project\n\
+			\n\
+			Flags:\n  \
+			-h, --help            Print this help message\n  \
+			-p, --profile <NAME>  The profile to use\n      \
+			--verbose         Print more output\n"
+		);
+		assert_eq!(
+			render_help(&SPEC.subcommands[0]),
+			"Build the project\n\nUsage: build\n"
+		);
+
+		assert!(SPEC.flag("h").is_some());
+		assert!(SPEC.flag("help").is_some());
+		assert!(SPEC.flag("p").unwrap().matches("profile"));
+		assert!(SPEC.flag("v").is_none());
+		assert_eq!(SPEC.subcommand("run").unwrap().about, "Run the project");
+	}
+
+	#[test]
+	fn derive() {
+		/// Does things.
+		#[derive(CliParser, PartialEq, Eq, Debug)]
+		#[cli(name = "my-app")]
+		struct MyCli<'a> {
+			#[cli(subcommand)]
+			cmd: Command,
+			/// The profile to use
+			#[cli(short = 'p', long = "profile")]
+			profile: Option<&'a str>,
+			/// Print more output
+			#[cli(short)]
+			verbose: bool,
+			dry_run: bool,
+			features: Vec<&'a str>,
+		}
+		#[derive(CliSubcommand, PartialEq, Eq, Debug)]
+		enum Command {
+			/// Build the project
+			#[cli(alias = "b")]
+			Build,
+			/// Run the project
+			#[cli(alias = "r")]
+			Run,
+			RunTests,
+		}
+
+		let mut parser = MyCli {
+			cmd: Command::Build,
+			profile: None,
+			verbose: false,
+			dry_run: false,
+			features: Vec::new(),
+		};
+		parse(
+			&[
+				"r",
+				"-vp=my-profile",
+				"--dry-run",
+				"--features",
+				"a",
+				"--features=b",
+			],
+			&mut parser,
+			false,
+		);
+		assert_eq!(
+			parser,
+			MyCli {
+				cmd: Command::Run,
+				profile: Some("my-profile"),
+				verbose: true,
+				dry_run: true,
+				features: Vec::from(["a", "b"]),
+			}
+		);
+
+		parse(&["run-tests"], &mut parser, false);
+		assert_eq!(parser.cmd, Command::RunTests);
+
+		assert_eq!(
+			render_help(&MyCli::SPEC),
+			"Does things.\n\
+			\n\
+			Usage: my-app [COMMAND] [FLAGS]\n\
+			\n\
+			Commands:\n  \
+			build      Build the project\n  \
+			run        Run the project\n  \
+			run-tests\n\
+			\n\
+			Flags:\n  \
+			-p, --profile <PROFILE>    The profile to use\n  \
+			-v, --verbose              Print more output\n      \
+			--dry-run\n      \
+			--features <FEATURES>\n"
+		);
+	}
+
+	#[test]
+	fn typed_arguments() {
+		#[derive(Default)]
+		struct MyCli<'a> {
+			jobs: Option<u32>,
+			files: Vec<&'a str>,
+			rest: &'a [&'a str],
+			errors: Vec<&'static str>,
+		}
+		impl<'a> CliParser<'a> for MyCli<'a> {
+			fn parse(
+				&mut self,
+				flag: &'a str,
+				class: FlagClass<'a>,
+				ctx: &mut CliParsingCtx<'a, Self>,
+			) -> ParseResult {
+				match flag {
+					"j" | "jobs" if class.is_flag() => {
+						self.jobs = ctx.next_argument_parsed(self);
+					}
+					"exec" if class.is_subcommand() => {
+						self.rest = ctx.remaining_args();
+						ctx.idx = ctx.args.len() - 1;
+					}
+					_ if class.is_subcommand() => match ctx.positional() {
+						Some(file) => self.files.push(file),
+						None => return ParseResult::NotRecognised,
+					},
+					_ => return ParseResult::NotRecognised,
+				}
+
+				ParseResult::Recognised
+			}
+			fn error(&mut self, error: ParseError<'a>) {
+				match error {
+					ParseError::InvalidArgumentValue {
+						flag: "jobs",
+						arg: "many",
+						expected: "u32",
+					} => self.errors.push("invalid jobs"),
+					other => panic!("CLI error: {other:?}"),
+				}
+			}
+		}
+
+		let mut cli = MyCli::default();
+		parse(
+			&["-j", "4", "a.txt", "key=value", "exec", "ls", "-la"],
+			&mut cli,
+			false,
+		);
+		assert_eq!(cli.jobs, Some(4));
+		assert_eq!(cli.files, ["a.txt", "key=value"]);
+		assert_eq!(cli.rest, ["ls", "-la"]);
+		assert!(cli.errors.is_empty());
+
+		let mut cli = MyCli::default();
+		parse(&["--jobs=many"], &mut cli, false);
+		assert_eq!(cli.jobs, None);
+		assert_eq!(cli.errors, ["invalid jobs"]);
+	}
+
+	#[test]
+	fn trailing() {
+		#[derive(CliParser, Default, PartialEq, Eq, Debug)]
+		struct MyCli<'a> {
+			#[cli(short)]
+			verbose: bool,
+			#[cli(short)]
+			profile: Option<&'a str>,
+			#[cli(trailing)]
+			rest: Vec<&'a str>,
+		}
+
+		let mut cli = MyCli::default();
+		parse(&["-v", "--", "--not-a-flag", "-p", "--"], &mut cli, false);
+		assert_eq!(
+			cli,
+			MyCli {
+				verbose: true,
+				profile: None,
+				rest: Vec::from(["--not-a-flag", "-p", "--"]),
+			}
+		);
+
+		let mut cli = MyCli::default();
+		parse(&["--", "-v"], &mut cli, false);
+		assert!(!cli.verbose);
+		assert_eq!(cli.rest, ["-v"]);
+
+		assert_eq!(
+			classify("--"),
+			FlagClass::Long { flag: "" },
+			"`--` is only special in `parse`"
+		);
+	}
+
+	#[test]
+	fn negative_numbers() {
+		let config = ParseConfig {
+			skip_executable: false,
+			negative_numbers: true,
+		};
+
+		for (input, expected) in [
+			("-5", FlagClass::SubcommandOrArgument { raw: "-5" }),
+			("-0.5", FlagClass::SubcommandOrArgument { raw: "-0.5" }),
+			("-.5", FlagClass::SubcommandOrArgument { raw: "-.5" }),
+			("-", FlagClass::Short { flag: "" }),
+			("-.", FlagClass::Short { flag: "." }),
+			("-1.2.3", FlagClass::Short { flag: "1.2.3" }),
+			("-v2", FlagClass::Short { flag: "v2" }),
+			("--5", FlagClass::Long { flag: "5" }),
+		] {
+			assert_eq!(classify_with_config(input, &config), expected);
+		}
+		assert_eq!(classify("-5"), FlagClass::Short { flag: "5" });
+
+		#[derive(Default, PartialEq, Debug)]
+		struct MyCli {
+			offset: Option<f32>,
+			verbosity: u8,
+			level: u8,
+		}
+		impl<'a> CliParser<'a> for MyCli {
+			fn parse(
+				&mut self,
+				flag: &'a str,
+				class: FlagClass<'a>,
+				ctx: &mut CliParsingCtx<'a, Self>,
+			) -> ParseResult {
+				match flag {
+					"offset" | "o" if class.is_flag() => {
+						self.offset = ctx.next_argument_parsed(self);
+					}
+					"v" if class.is_flag() => self.verbosity += 1,
+					"2" if class.is_short() => self.level = 2,
+					_ => return ParseResult::NotRecognised,
+				}
+
+				ParseResult::Recognised
+			}
+			fn error(&mut self, error: ParseError<'a>) {
+				panic!("CLI error: {error:?}");
+			}
+		}
+
+		for (flags, expected) in [
+			(
+				&["--offset", "-5"][..],
+				MyCli {
+					offset: Some(-5.0),
+					..Default::default()
+				},
+			),
+			(
+				&["-o", "-0.5", "-v"],
+				MyCli {
+					offset: Some(-0.5),
+					verbosity: 1,
+					..Default::default()
+				},
+			),
+			(
+				&["-o=-1.5"],
+				MyCli {
+					offset: Some(-1.5),
+					..Default::default()
+				},
+			),
+			// Combined short flags ending in digits are still flags
+			(
+				&["-vv2"],
+				MyCli {
+					verbosity: 2,
+					level: 2,
+					..Default::default()
+				},
+			),
+		] {
+			let mut cli = MyCli::default();
+			parse_with_config(flags, &mut cli, config);
+			assert_eq!(cli, expected);
+		}
+	}
 }
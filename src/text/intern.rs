@@ -0,0 +1,162 @@
+//! String interning: storing each unique string once, and referring to it
+//! with a small [`Symbol`].
+//!
+//! Comparing and hashing symbols is just comparing and hashing an integer,
+//! which makes them much cheaper than strings for identifiers, log targets,
+//! and other strings that show up over and over:
+//!
+//! ```rs
+//! let mut interner = Interner::new(MemoryAmount::mebibytes(16))?;
+//! let a = interner.intern("main");
+//! let b = interner.intern("main");
+//! assert_eq!(a, b);
+//! assert_eq!(interner.resolve(a), "main");
+//! ```
+//!
+//! Interned strings are stored in an [`ArenaString`], so they never move, and
+//! are only freed when the interner is dropped.
+
+use crate::{
+	crypto::hash::{BuildHasher, FoldHashBuilder},
+	data_structures::{ArenaString, HashTable, hash_table::Entry},
+	rt::mem::MemoryAmount,
+};
+
+/// A handle to a string stored in an [`Interner`]. Use
+/// [`Interner::resolve`] to get the string back.
+///
+/// Two symbols from the same interner are equal if and only if their strings
+/// are equal. Symbols are ordered by when their string was first interned,
+/// not alphabetically.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Symbol(u32);
+impl Symbol {
+	/// This symbol's index in its interner. The first string interned is `0`,
+	/// the next is `1`, etc.
+	pub const fn index(self) -> u32 {
+		self.0
+	}
+}
+
+/// Deduplicates strings. See the [module-level docs](self).
+pub struct Interner {
+	/// Every interned string, one after the other.
+	text: ArenaString,
+	/// The `(start, len)` of each symbol's string in `text`, indexed by
+	/// [`Symbol`].
+	spans: Vec<(usize, usize)>,
+	/// Finds the symbol for a string that's already interned.
+	lookup: HashTable<Symbol>,
+	hasher: FoldHashBuilder,
+}
+impl Interner {
+	/// Reserve virtual memory for a new interner's strings. Errors if
+	/// reserving virtual memory fails.
+	pub fn new(to_reserve: MemoryAmount) -> Result<Self, ()> {
+		Ok(Self {
+			text: ArenaString::new(to_reserve)?,
+			spans: Vec::new(),
+			lookup: HashTable::new(),
+			hasher: FoldHashBuilder::default(),
+		})
+	}
+
+	/// Returns the symbol for `string`, storing it in the interner if it
+	/// hasn't been interned yet.
+	pub fn intern(&mut self, string: &str) -> Symbol {
+		let hash = self.hasher.hash_one(string);
+		let Self {
+			text,
+			spans,
+			lookup,
+			hasher,
+		} = self;
+		let resolve = |symbol: &Symbol| {
+			let (start, len) = spans[symbol.0 as usize];
+			&text.as_str()[start..start + len]
+		};
+
+		match lookup.entry(
+			hash,
+			|symbol| resolve(symbol) == string,
+			|symbol| hasher.hash_one(resolve(symbol)),
+		) {
+			Entry::Occupied(entry) => *entry.get(),
+			Entry::Vacant(entry) => {
+				let symbol = Symbol(
+					u32::try_from(spans.len()).expect("Crux: Interned more than u32::MAX strings"),
+				);
+				spans.push((text.len(), string.len()));
+				text.push_str(string);
+				entry.insert(symbol);
+				symbol
+			}
+		}
+	}
+	/// Returns the symbol for `string` if it's already been interned, without
+	/// interning it.
+	pub fn get(&self, string: &str) -> Option<Symbol> {
+		self.lookup
+			.find(self.hasher.hash_one(string), |symbol| {
+				self.resolve(*symbol) == string
+			})
+			.copied()
+	}
+	/// Returns the string `symbol` refers to.
+	///
+	/// `symbol` must come from this interner; symbols from other interners
+	/// return the wrong string or panic.
+	pub fn resolve(&self, symbol: Symbol) -> &str {
+		let (start, len) = self.spans[symbol.0 as usize];
+		&self.text.as_str()[start..start + len]
+	}
+
+	/// How many unique strings have been interned.
+	pub fn len(&self) -> usize {
+		self.spans.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.spans.is_empty()
+	}
+	/// Iterates over every interned string and its symbol, in the order they
+	/// were interned.
+	pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+		(0..self.spans.len() as u32).map(|idx| (Symbol(idx), self.resolve(Symbol(idx))))
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn intern() {
+		let mut interner = Interner::new(MemoryAmount::mebibytes(1)).unwrap();
+		let main = interner.intern("main");
+		let empty = interner.intern("");
+		let crux = interner.intern("crux");
+
+		assert_eq!(interner.intern("main"), main);
+		assert_eq!(interner.intern(""), empty);
+		assert_ne!(main, crux);
+		assert_eq!(interner.len(), 3);
+
+		assert_eq!(interner.resolve(main), "main");
+		assert_eq!(interner.resolve(empty), "");
+		assert_eq!(interner.resolve(crux), "crux");
+		assert_eq!(interner.get("crux"), Some(crux));
+		assert_eq!(interner.get("std"), None);
+
+		assert!(
+			interner
+				.iter()
+				.eq([(main, "main"), (empty, ""), (crux, "crux")])
+		);
+	}
+}
@@ -0,0 +1,333 @@
+//! Utilities for inspecting and transforming strings.
+//!
+//! The splitting iterators in this module work on ASCII delimiters, which
+//! lets their `next_*` methods be `const`, so they can also be used to parse
+//! strings at compile time:
+//!
+//! ```rs
+//! const SECOND: &str = {
+//!     let mut words = Words::new("  hello  world ");
+//!     words.next_word();
+//!     words.next_word().unwrap()
+//! };
+//! assert_eq!(SECOND, "world");
+//! ```
+
+use crate::lang::{DoubleEndedIterator, Extend};
+
+//
+// Splitting
+//
+
+/// An iterator over the words in a string, where words are separated by any
+/// amount of ASCII whitespace. This is like [`str::split_ascii_whitespace`],
+/// but can be used in const contexts via [`Words::next_word`].
+#[derive(Clone, Copy, Debug)]
+pub struct Words<'a> {
+	remaining: &'a str,
+}
+impl<'a> Words<'a> {
+	pub const fn new(s: &'a str) -> Self {
+		Self { remaining: s }
+	}
+
+	/// The next word, or `None` if there are no words left.
+	pub const fn next_word(&mut self) -> Option<&'a str> {
+		let bytes = self.remaining.as_bytes();
+		let mut start = 0;
+		while start < bytes.len() && bytes[start].is_ascii_whitespace() {
+			start += 1;
+		}
+		if start == bytes.len() {
+			self.remaining = "";
+			return None;
+		}
+		let mut end = start;
+		while end < bytes.len() && !bytes[end].is_ascii_whitespace() {
+			end += 1;
+		}
+
+		// Splitting on ASCII bytes always leaves valid UTF-8 on both sides
+		let (word, rest) = bytes.split_at(end);
+		let (_, word) = word.split_at(start);
+		self.remaining = unsafe { str::from_utf8_unchecked(rest) };
+		Some(unsafe { str::from_utf8_unchecked(word) })
+	}
+	/// The part of the string that hasn't been split yet.
+	pub const fn remainder(&self) -> &'a str {
+		self.remaining
+	}
+}
+impl<'a> Iterator for Words<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_word()
+	}
+}
+
+/// An iterator over the parts of a string separated by an ASCII byte. This is
+/// like [`str::split`] with a `char`, but can be used in const contexts via
+/// [`SplitByte::next_part`].
+///
+/// Like [`str::split`], empty parts are kept, so `"a,,b"` split on `,` yields
+/// `"a"`, `""`, and `"b"`.
+#[derive(Clone, Copy, Debug)]
+pub struct SplitByte<'a> {
+	/// `None` once the last part has been returned.
+	remaining: Option<&'a str>,
+	delimiter: u8,
+}
+impl<'a> SplitByte<'a> {
+	/// Splits `s` on `delimiter`.
+	///
+	/// Panics if `delimiter` isn't ASCII, since non-ASCII bytes could split a
+	/// character in half.
+	pub const fn new(s: &'a str, delimiter: u8) -> Self {
+		assert!(delimiter.is_ascii(), "SplitByte's delimiter must be ASCII");
+		Self {
+			remaining: Some(s),
+			delimiter,
+		}
+	}
+
+	/// The next part of the string, or `None` if there are no parts left.
+	pub const fn next_part(&mut self) -> Option<&'a str> {
+		let Some(remaining) = self.remaining else {
+			return None;
+		};
+		let bytes = remaining.as_bytes();
+		let mut end = 0;
+		while end < bytes.len() && bytes[end] != self.delimiter {
+			end += 1;
+		}
+
+		let (part, rest) = bytes.split_at(end);
+		self.remaining = match rest.split_first() {
+			Some((_delimiter, rest)) => Some(unsafe { str::from_utf8_unchecked(rest) }),
+			None => None,
+		};
+		Some(unsafe { str::from_utf8_unchecked(part) })
+	}
+}
+impl<'a> Iterator for SplitByte<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_part()
+	}
+}
+
+//
+// Case conversion
+//
+
+/// A naming convention for identifiers, used by [`convert_case`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Case {
+	/// `snake_case`
+	Snake,
+	/// `SCREAMING_SNAKE_CASE`
+	ScreamingSnake,
+	/// `kebab-case`
+	Kebab,
+	/// `camelCase`
+	Camel,
+	/// `PascalCase`
+	Pascal,
+}
+
+/// Converts an identifier from any [`Case`] to `case`.
+///
+/// Words are split on `_`, `-`, whitespace, and changes from lowercase to
+/// uppercase letters. A run of uppercase letters is treated as one word, so
+/// `HTTPServer` converts to `http_server` in snake case.
+pub fn convert_case(s: &str, case: Case) -> String {
+	let mut out = String::with_capacity(s.len());
+	for (idx, word) in case_words(s).enumerate() {
+		match case {
+			Case::Snake | Case::ScreamingSnake if idx != 0 => out.push('_'),
+			Case::Kebab if idx != 0 => out.push('-'),
+			_ => {}
+		}
+		for (char_idx, c) in word.chars().enumerate() {
+			let upper = match case {
+				Case::Snake | Case::Kebab => false,
+				Case::ScreamingSnake => true,
+				Case::Camel => char_idx == 0 && idx != 0,
+				Case::Pascal => char_idx == 0,
+			};
+			if upper {
+				out.extend(c.to_uppercase());
+			} else {
+				out.extend(c.to_lowercase());
+			}
+		}
+	}
+
+	out
+}
+
+/// Splits an identifier into its words. See [`convert_case`].
+fn case_words(s: &str) -> impl Iterator<Item = &str> {
+	s.split(|c: char| c == '_' || c == '-' || c.is_whitespace())
+		.flat_map(|part| {
+			let mut words = Vec::new();
+			let mut start = 0;
+			let mut chars = part.char_indices().peekable();
+			let mut prev: Option<char> = None;
+			while let Some((idx, c)) = chars.next() {
+				let next = chars.peek().map(|(_, c)| *c);
+				let boundary = match prev {
+					// `fooBar`
+					Some(prev) if prev.is_lowercase() || prev.is_numeric() => c.is_uppercase(),
+					// The `S` in `HTTPServer`
+					Some(prev) if prev.is_uppercase() => {
+						c.is_uppercase() && next.is_some_and(char::is_lowercase)
+					}
+					_ => false,
+				};
+				if boundary {
+					words.push(&part[start..idx]);
+					start = idx;
+				}
+				prev = Some(c);
+			}
+			words.push(&part[start..]);
+			words
+		})
+		.filter(|word| !word.is_empty())
+}
+
+//
+// Truncation
+//
+
+/// Whether `c` attaches to the character before it, so that the two should
+/// never be separated. This covers combining marks, variation selectors, emoji
+/// modifiers, tag characters, and zero-width joiners.
+///
+/// This is a small approximation of Unicode's grapheme cluster rules that
+/// doesn't need Unicode's data tables.
+const fn extends_previous(c: char) -> bool {
+	matches!(
+		c as u32,
+		0x0300..=0x036F
+			| 0x1AB0..=0x1AFF
+			| 0x1DC0..=0x1DFF
+			| 0x200C..=0x200D
+			| 0x20D0..=0x20FF
+			| 0xFE00..=0xFE0F
+			| 0xFE20..=0xFE2F
+			| 0x1F3FB..=0x1F3FF
+			| 0xE0020..=0xE007F
+			| 0xE0100..=0xE01EF
+	)
+}
+
+/// Shortens `s` to at most `max_len` bytes, without splitting a character or
+/// separating a character from the combining marks and modifiers after it
+/// (e.g. `e` and an accent, or the parts of a joined emoji sequence).
+///
+/// The result may be shorter than `max_len` so that whole characters are
+/// kept together.
+pub fn truncate_graphemes(s: &str, max_len: usize) -> &str {
+	if s.len() <= max_len {
+		return s;
+	}
+
+	let mut end = max_len;
+	while !s.is_char_boundary(end) {
+		end -= 1;
+	}
+	loop {
+		let next = s[end..].chars().next();
+		let prev = s[..end].chars().next_back();
+		// Back up if the cut would separate a character from its modifier, or
+		// if it would leave a dangling zero-width joiner
+		let split = next.is_some_and(extends_previous) || prev == Some('\u{200D}');
+		match prev {
+			Some(prev) if split => end -= prev.len_utf8(),
+			_ => break,
+		}
+	}
+
+	&s[..end]
+}
+
+/// Shortens `s` to at most `max_chars` characters, with the same rules as
+/// [`truncate_graphemes`].
+pub fn truncate_chars(s: &str, max_chars: usize) -> &str {
+	match s.char_indices().nth(max_chars) {
+		Some((idx, _)) => truncate_graphemes(s, idx),
+		None => s,
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splitting() {
+		const SECOND: &str = {
+			let mut words = Words::new("  hello \t world ");
+			words.next_word();
+			match words.next_word() {
+				Some(word) => word,
+				None => "",
+			}
+		};
+		assert_eq!(SECOND, "world");
+		assert_eq!(Words::new("").count(), 0);
+		assert_eq!(
+			Words::new(" héllo\nwörld  ").collect::<Vec<_>>(),
+			["héllo", "wörld"]
+		);
+
+		assert_eq!(
+			SplitByte::new("a,,b,", b',').collect::<Vec<_>>(),
+			["a", "", "b", ""]
+		);
+		assert_eq!(SplitByte::new("", b',').collect::<Vec<_>>(), [""]);
+	}
+
+	#[test]
+	fn case_conversion() {
+		assert_eq!(convert_case("HTTPServer", Case::Snake), "http_server");
+		assert_eq!(convert_case("fooBar_baz", Case::Kebab), "foo-bar-baz");
+		assert_eq!(convert_case("foo-bar", Case::Camel), "fooBar");
+		assert_eq!(convert_case("foo_bar2", Case::Pascal), "FooBar2");
+		assert_eq!(convert_case("max value", Case::ScreamingSnake), "MAX_VALUE");
+		// Some characters change length when their case changes
+		assert_eq!(convert_case("straße", Case::ScreamingSnake), "STRASSE");
+		assert_eq!(convert_case("Ärger_über", Case::Camel), "ärgerÜber");
+	}
+
+	#[test]
+	fn truncation() {
+		assert_eq!(truncate_graphemes("hello", 10), "hello");
+		assert_eq!(truncate_graphemes("hello", 3), "hel");
+		// `é` is 2 bytes
+		assert_eq!(truncate_graphemes("héllo", 2), "h");
+		// `e` followed by a combining acute accent
+		assert_eq!(truncate_graphemes("he\u{301}llo", 2), "h");
+		// A family emoji, joined with zero-width joiners
+		let family = "a\u{1F468}\u{200D}\u{1F469}";
+		assert_eq!(truncate_graphemes(family, 9), "a");
+		// A thumbs up with a skin tone modifier
+		let thumbs_up = "a\u{1F44D}\u{1F3FD}";
+		assert_eq!(truncate_graphemes(thumbs_up, 5), "a");
+		assert_eq!(truncate_graphemes(thumbs_up, 9), thumbs_up);
+		// Cutting right after a zero-width joiner
+		assert_eq!(truncate_graphemes("ab\u{200D}c", 5), "a");
+		assert_eq!(truncate_chars("he\u{301}llo", 3), "he\u{301}");
+		assert_eq!(truncate_chars("he\u{301}llo", 2), "h");
+	}
+}
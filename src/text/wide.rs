@@ -0,0 +1,186 @@
+//! Conversions between UTF-8 and UTF-16 ("wide") strings, which is what most
+//! Windows APIs use.
+//!
+//! Strings can be encoded into a caller-provided buffer, which avoids
+//! allocating for short strings:
+//!
+//! ```rs
+//! let mut buf = [0; 260];
+//! let path = wide::encode_nul_into(path, &mut buf)?;
+//! unsafe { win32::DeleteFileW(NonNullConst::from_ref(&path[0])) };
+//! ```
+//!
+//! or into a [`SizedVec`] with [`encode`] and [`encode_nul`].
+//!
+//! Windows doesn't require UTF-16 strings to be valid, so decoding is always
+//! lossy: unpaired surrogates are replaced with
+//! `U+FFFD REPLACEMENT CHARACTER`.
+
+use crate::{
+	data_structures::{IndexSize, SizedVec},
+	lang::{Extend, NonNullConst},
+};
+
+/// An error from encoding a string as UTF-16.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WideEncodeError {
+	/// The buffer can't fit the encoded string. `needed` is how many `u16`s
+	/// the buffer needs to hold, including the nul terminator if there is one.
+	BufferTooSmall { needed: usize },
+	/// The string has a nul character, so it can't be encoded as a
+	/// nul-terminated string. `position` is the nul's byte index in the
+	/// string.
+	InteriorNul { position: usize },
+}
+
+/// How many `u16`s `s` takes up when encoded as UTF-16, without a nul
+/// terminator.
+pub const fn encoded_len(s: &str) -> usize {
+	let bytes = s.as_bytes();
+	let mut len = 0;
+	let mut idx = 0;
+	while idx < bytes.len() {
+		let byte = bytes[idx];
+		// Characters encoded in 4 UTF-8 bytes are outside the BMP, so they
+		// need a surrogate pair; everything else needs one `u16`
+		let (utf8_len, utf16_len) = match byte {
+			0x00..0x80 => (1, 1),
+			0xC0..0xE0 => (2, 1),
+			0xE0..0xF0 => (3, 1),
+			_ => (4, 2),
+		};
+		idx += utf8_len;
+		len += utf16_len;
+	}
+
+	len
+}
+
+/// Encodes `s` as UTF-16 into the start of `buf`, and returns the encoded
+/// part of `buf`.
+pub fn encode_into<'a>(s: &str, buf: &'a mut [u16]) -> Result<&'a mut [u16], WideEncodeError> {
+	let needed = encoded_len(s);
+	let Some(dest) = buf.get_mut(..needed) else {
+		return Err(WideEncodeError::BufferTooSmall { needed });
+	};
+	for (slot, unit) in dest.iter_mut().zip(s.encode_utf16()) {
+		*slot = unit;
+	}
+
+	Ok(dest)
+}
+/// Encodes `s` as a nul-terminated UTF-16 string into the start of `buf`, and
+/// returns the encoded part of `buf`, including the nul terminator.
+pub fn encode_nul_into<'a>(s: &str, buf: &'a mut [u16]) -> Result<&'a mut [u16], WideEncodeError> {
+	check_interior_nul(s)?;
+	let needed = encoded_len(s) + 1;
+	if buf.len() < needed {
+		return Err(WideEncodeError::BufferTooSmall { needed });
+	}
+	encode_into(s, buf)?;
+	buf[needed - 1] = 0;
+
+	Ok(&mut buf[..needed])
+}
+
+/// Encodes `s` as UTF-16.
+pub fn encode<S: const IndexSize>(s: &str) -> SizedVec<u16, S> {
+	let mut wide = SizedVec::with_capacity(S::usize_as_self(encoded_len(s)));
+	wide.extend(s.encode_utf16());
+	wide
+}
+/// Encodes `s` as a nul-terminated UTF-16 string.
+pub fn encode_nul<S: const IndexSize>(s: &str) -> Result<SizedVec<u16, S>, WideEncodeError> {
+	check_interior_nul(s)?;
+	let mut wide = SizedVec::with_capacity(S::usize_as_self(encoded_len(s) + 1));
+	wide.extend(s.encode_utf16());
+	wide.push(0);
+
+	Ok(wide)
+}
+
+fn check_interior_nul(s: &str) -> Result<(), WideEncodeError> {
+	match s.bytes().position(|byte| byte == 0) {
+		Some(position) => Err(WideEncodeError::InteriorNul { position }),
+		None => Ok(()),
+	}
+}
+
+/// Decodes a UTF-16 string, replacing unpaired surrogates with
+/// `U+FFFD REPLACEMENT CHARACTER`.
+pub fn decode_lossy(wide: &[u16]) -> String {
+	let mut out = String::with_capacity(wide.len());
+	out.extend(
+		char::decode_utf16(wide.iter().copied()).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)),
+	);
+	out
+}
+
+/// The number of `u16`s before the nul terminator of a nul-terminated UTF-16
+/// string.
+///
+///
+/// # Safety
+///
+/// `ptr` must point to a nul-terminated UTF-16 string that's valid for reads
+/// up to and including the nul terminator.
+pub unsafe fn nul_terminated_len(ptr: NonNullConst<u16>) -> usize {
+	let mut len = 0;
+	while unsafe { *ptr.as_ptr().add(len) } != 0 {
+		len += 1;
+	}
+	len
+}
+/// Borrows a nul-terminated UTF-16 string as a slice, without the nul
+/// terminator.
+///
+///
+/// # Safety
+///
+/// `ptr` must point to a nul-terminated UTF-16 string that's valid for reads
+/// up to and including the nul terminator, and that isn't modified for `'a`.
+pub unsafe fn from_nul_terminated<'a>(ptr: NonNullConst<u16>) -> &'a [u16] {
+	unsafe { core::slice::from_raw_parts(ptr.as_ptr(), nul_terminated_len(ptr)) }
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn wide() {
+		let s = "a\u{e9}\u{1F980}";
+		assert_eq!(encoded_len(s), s.encode_utf16().count());
+
+		let mut buf = [0xFFFF; 5];
+		assert_eq!(
+			encode_into(s, &mut buf).unwrap(),
+			[0x61, 0xE9, 0xD83E, 0xDD80]
+		);
+		assert_eq!(
+			encode_nul_into(s, &mut buf).unwrap(),
+			[0x61, 0xE9, 0xD83E, 0xDD80, 0]
+		);
+		assert_eq!(
+			encode_nul_into(s, &mut buf[..4]),
+			Err(WideEncodeError::BufferTooSmall { needed: 5 })
+		);
+		assert_eq!(
+			encode_nul::<u8>("a\0b").map(|wide| wide.len()),
+			Err(WideEncodeError::InteriorNul { position: 1 })
+		);
+
+		let wide = encode_nul::<usize>(s).unwrap();
+		let borrowed = unsafe { from_nul_terminated(NonNullConst::from_ref(&wide[0])) };
+		assert_eq!(borrowed, &*encode::<usize>(s));
+		assert_eq!(decode_lossy(borrowed), s);
+		// An unpaired surrogate
+		assert_eq!(decode_lossy(&[0x61, 0xD83E, 0x62]), "a\u{FFFD}b");
+	}
+}
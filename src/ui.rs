@@ -1,3 +1,6 @@
 //! Windowing and UI library for Crux.
 
+pub mod clipboard;
 pub mod display;
+pub mod draw;
+pub mod event;
@@ -0,0 +1,27 @@
+//! Copying and pasting text through the system clipboard.
+//!
+//! The clipboard belongs to the display server, so it's used through a
+//! [`Display`]:
+//!
+//! ```rs
+//! let mut display = Win32Display::new()?;
+//! display.set_clipboard_text("Hello from Crux")?;
+//! assert_eq!(display.clipboard_text()?.as_deref(), Some("Hello from Crux"));
+//! ```
+//!
+//! Display servers differ in when programs can use the clipboard. Win32 lets
+//! any program use it at any time. Wayland only shares the clipboard with
+//! programs that have a focused window, and only lets them set it in response
+//! to input, so CLI programs can't use it there; see the backends'
+//! implementations for details.
+
+use crate::ui::display::Display;
+
+/// A display server with a clipboard that holds text.
+pub trait Clipboard: Display {
+	/// The clipboard's text, or `None` if it's empty or holds something other
+	/// than text.
+	fn clipboard_text(&mut self) -> Result<Option<String>, Self::Error>;
+	/// Replaces the clipboard's contents with `text`.
+	fn set_clipboard_text(&mut self, text: &str) -> Result<(), Self::Error>;
+}
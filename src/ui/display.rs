@@ -1,15 +1,47 @@
 //! A display is a set of interfaces used by GUI applications to appear on
 //! screen and get input from the user.
 
+// Wayland compositors only run on Linux
+#[cfg(linux)]
 pub mod wayland;
+#[cfg(windows)]
+pub mod win32;
 
-pub trait Display {
+use crate::{
+	rt::time::Duration,
+	ui::event::{Event, WindowId},
+};
+
+pub trait Display: Sized {
 	type WindowHandle;
+	type Error;
 
-	fn new() -> Self;
+	/// Connects to the display server.
+	fn new() -> Result<Self, Self::Error>;
+	/// Creates a window with the given title. `width` and `height` are the
+	/// size of the area the program draws to, if the backend lets the program
+	/// pick it.
+	fn create_window(
+		&mut self,
+		title: &str,
+		width: u32,
+		height: u32,
+	) -> Result<Self::WindowHandle, Self::Error>;
+	/// The ID `window`'s events are sent with.
+	fn window_id(window: &Self::WindowHandle) -> WindowId;
+	/// Waits up to `timeout` for events (or forever, if it's `None`), then
+	/// translates every available event for `windows` and calls `handler` with
+	/// it. Returns without calling `handler` if the timeout passes first.
+	fn dispatch_events(
+		&mut self,
+		windows: &mut [Self::WindowHandle],
+		timeout: Option<Duration>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) -> Result<(), Self::Error>;
 }
 
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum DisplayType {
 	Wayland,
+	Win32,
 }
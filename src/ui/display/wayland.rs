@@ -1,2 +1,1091 @@
+//! A Wayland client, which talks to the compositor over a Unix socket.
+//!
+//! [`WaylandDisplay`] manages the connection: it keeps track of which objects
+//! exist, which globals the compositor advertises, and sends and receives
+//! messages. Requests and events are the enums generated for each interface
+//! in [`interfaces`]:
+//!
+//! ```rs
+//! let mut display = WaylandDisplay::connect()?;
+//! let compositor: WlCompositor = display.bind()?;
+//! let surface = display.create::<WlSurface>();
+//! display.send(compositor, wl_compositor::Request::CreateSurface { id: surface.id() })?;
+//!
+//! loop {
+//!     display.dispatch(|display, event| match event {
+//!         SomeEvent::WlSurface(surface, wl_surface::Event::Enter { output }) => {}
+//!         _ => {}
+//!     })?;
+//! }
+//! ```
+//!
+//! [`WaylandWindow`] and [`Buffer`] wrap the objects needed to put pixels on
+//! screen; see the [`window`] module for an example. [`WaylandInput`] turns
+//! keyboard and pointer events into [`Event`]s, and [`WaylandClipboard`]
+//! handles copy and paste.
+//!
+//! Wayland docs: https://wayland.freedesktop.org/docs/html/ch04.html
+
+pub mod clipboard;
+pub mod input;
 pub mod interfaces;
+pub mod shm;
+pub mod window;
 pub mod wire;
+
+pub use self::{
+	clipboard::WaylandClipboard,
+	input::WaylandInput,
+	shm::{Buffer, ShmFormat, ShmPool},
+	window::WaylandWindow,
+};
+
+use {
+	self::{
+		interfaces::{
+			SomeEvent, SomeObject, WlCallback, WlDataDeviceManager, WlDataOffer, WlDisplay,
+			WlRegistry, WlSeat, wl_callback, wl_data_device, wl_display, wl_registry, xdg_wm_base,
+		},
+		wire::{FromWire, FromWireError, Message, ObjectId, ToWireError, UntypedNewId},
+	},
+	crate::{
+		lang::take,
+		rt::{
+			os::{
+				self,
+				unix::{FileDescriptor, UnixStream},
+			},
+			proc::get_env,
+			time::Duration,
+		},
+		ui::{
+			clipboard::Clipboard,
+			display::Display,
+			event::{Event, WindowId},
+		},
+	},
+};
+
+/// The first ID the compositor uses for objects it creates, like the
+/// `wl_data_offer`s from `wl_data_device.data_offer` events. IDs below this
+/// are picked by the client.
+const SERVER_ID_START: u32 = 0xFF00_0000;
+
+/// A Wayland interface: a set of requests the client can send to an object,
+/// and events the compositor can send back. Implemented by the handle types
+/// in [`interfaces`].
+pub trait Interface: Copy + Into<SomeObject> {
+	/// The interface's name in the protocol, e.g. `wl_display`.
+	const NAME: &str;
+	/// The newest version of the interface that Crux supports.
+	const VERSION: u32;
+	type Request<'a>;
+	type Event<'a>;
+
+	/// The object's ID, which is how it's referred to in messages.
+	fn id(self) -> ObjectId;
+	/// Creates a handle to an existing object.
+	///
+	///
+	/// # Safety
+	///
+	/// `id` must refer to an object that implements this interface.
+	unsafe fn from_id(id: ObjectId) -> Self;
+	/// Writes `request`'s arguments to `buffer`, and returns its opcode. File
+	/// descriptor arguments are added to `fds` instead.
+	fn encode_request(
+		request: &Self::Request<'_>,
+		buffer: &mut Vec<u8>,
+		fds: &mut Vec<FileDescriptor>,
+	) -> Result<u16, ToWireError>;
+	/// Decodes the arguments of the event with the given opcode. File
+	/// descriptor arguments are taken from the start of `fds`.
+	fn decode_event<'a>(
+		opcode: u16,
+		args: &'a [u8],
+		fds: &mut Vec<FileDescriptor>,
+	) -> Result<Self::Event<'a>, FromWireError>;
+}
+
+/// A global object advertised by the compositor, which clients can bind to
+/// with [`WaylandDisplay::bind`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Global {
+	/// The compositor's ID for this global, which isn't an object ID.
+	pub name: u32,
+	pub interface: String,
+	/// The newest version of the interface the compositor supports.
+	pub version: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum WaylandError {
+	/// `$XDG_RUNTIME_DIR` isn't set, so the compositor's socket can't be found.
+	NoRuntimeDir,
+	/// Reading from or writing to the socket failed.
+	Os(os::Error),
+	/// The compositor closed the connection.
+	Disconnected,
+	/// The compositor doesn't advertise a global for this interface.
+	MissingGlobal(&'static str),
+	/// A request couldn't be encoded.
+	Encode(ToWireError),
+	/// A message from the compositor couldn't be decoded.
+	Decode(FromWireError),
+	/// The compositor sent an event to an object that doesn't exist.
+	UnknownObject(ObjectId),
+	/// The compositor reported a protocol error. These are fatal: the
+	/// compositor closes the connection after sending one.
+	Protocol {
+		object: ObjectId,
+		code: u32,
+		message: String,
+	},
+	/// Reserving or mapping memory for a shared memory pool failed.
+	OutOfMemory,
+	/// A buffer doesn't fit in its shared memory pool, or the pool can't grow
+	/// that large.
+	PoolTooSmall,
+	/// A buffer was presented to a window before the compositor configured
+	/// it.
+	Unconfigured,
+	/// A request has to be sent in response to user input, but the seat
+	/// hasn't sent any input events yet.
+	NoInputSerial,
+}
+impl From<os::Error> for WaylandError {
+	fn from(value: os::Error) -> Self {
+		Self::Os(value)
+	}
+}
+impl From<ToWireError> for WaylandError {
+	fn from(value: ToWireError) -> Self {
+		Self::Encode(value)
+	}
+}
+impl From<FromWireError> for WaylandError {
+	fn from(value: FromWireError) -> Self {
+		Self::Decode(value)
+	}
+}
+
+/// A connection to a Wayland compositor. See the [module-level docs](self).
+pub struct WaylandDisplay {
+	socket: UnixStream,
+	/// Every object the client created that currently exists, indexed by ID.
+	/// IDs that aren't in use are `None`.
+	objects: Vec<Option<SomeObject>>,
+	/// Every object the compositor created that currently exists, indexed by
+	/// ID minus [`SERVER_ID_START`].
+	server_objects: Vec<Option<SomeObject>>,
+	/// IDs the compositor has finished with, which can be reused.
+	free_ids: Vec<ObjectId>,
+	registry: WlRegistry,
+	globals: Vec<Global>,
+	/// Bytes read from the socket that don't make up a full message yet.
+	incoming: Vec<u8>,
+	/// File descriptors received from the compositor that haven't been
+	/// decoded yet, in the order they were sent.
+	incoming_fds: Vec<FileDescriptor>,
+	/// Messages that haven't been sent yet.
+	outgoing: Vec<u8>,
+	/// File descriptors to send along with `outgoing`.
+	outgoing_fds: Vec<FileDescriptor>,
+	/// Globals bound with [`WaylandDisplay::get_or_bind`].
+	bound: Vec<SomeObject>,
+	/// The seat's input devices, for [`Display::dispatch_events`]. Created
+	/// once the compositor advertises a seat.
+	input: Option<WaylandInput>,
+	/// The seat's clipboard, for [`Clipboard`]. Created along with `input`, if
+	/// the compositor supports `wl_data_device_manager`.
+	clipboard: Option<WaylandClipboard>,
+}
+impl WaylandDisplay {
+	/// Connects to the compositor at `$WAYLAND_DISPLAY`, which is either an
+	/// absolute path or a path in `$XDG_RUNTIME_DIR`. Defaults to
+	/// `wayland-0`.
+	pub fn connect() -> Result<Self, WaylandError> {
+		let display = get_env("WAYLAND_DISPLAY").unwrap_or_else(|| String::from("wayland-0"));
+		let path = if display.starts_with('/') {
+			display
+		} else {
+			let runtime_dir = get_env("XDG_RUNTIME_DIR").ok_or(WaylandError::NoRuntimeDir)?;
+			format!("{runtime_dir}/{display}")
+		};
+
+		Self::from_socket(UnixStream::connect(&path)?)
+	}
+	/// Starts a Wayland session over an already-connected socket. This
+	/// blocks until the compositor has advertised its globals.
+	pub fn from_socket(socket: UnixStream) -> Result<Self, WaylandError> {
+		// The display is always object 1, and the registry is always object 2
+		let display = unsafe { WlDisplay::from_id(ObjectId::DISPLAY) };
+		let registry = unsafe { WlRegistry::from_id(ObjectId::new(2).unwrap()) };
+		let mut this = Self {
+			socket,
+			objects: Vec::from([None, Some(display.into()), Some(registry.into())]),
+			server_objects: Vec::new(),
+			free_ids: Vec::new(),
+			registry,
+			globals: Vec::new(),
+			incoming: Vec::new(),
+			incoming_fds: Vec::new(),
+			outgoing: Vec::new(),
+			outgoing_fds: Vec::new(),
+			bound: Vec::new(),
+			input: None,
+			clipboard: None,
+		};
+
+		this.send(
+			this.display(),
+			wl_display::Request::GetRegistry {
+				registry: this.registry.id(),
+			},
+		)?;
+		this.roundtrip(|_, _| {})?;
+
+		Ok(this)
+	}
+
+	/// The `wl_display` singleton, which is used to create the registry and
+	/// to sync with the compositor.
+	pub fn display(&self) -> WlDisplay {
+		unsafe { WlDisplay::from_id(ObjectId::DISPLAY) }
+	}
+	/// Every global the compositor currently advertises.
+	pub fn globals(&self) -> &[Global] {
+		&self.globals
+	}
+	/// Binds to the compositor's global for interface `I`, using the newest
+	/// version that both Crux and the compositor support.
+	pub fn bind<I: Interface>(&mut self) -> Result<I, WaylandError> {
+		let Some(global) = self
+			.globals
+			.iter()
+			.find(|global| global.interface == I::NAME)
+		else {
+			return Err(WaylandError::MissingGlobal(I::NAME));
+		};
+		let (name, version) = (global.name, global.version.min(I::VERSION));
+
+		let object = self.create::<I>();
+		self.send(
+			self.registry,
+			wl_registry::Request::Bind {
+				name,
+				id: UntypedNewId {
+					interface: I::NAME,
+					version,
+					id: object.id(),
+				},
+			},
+		)?;
+
+		Ok(object)
+	}
+	/// Returns the object bound to the compositor's global for interface `I`,
+	/// binding it with [`WaylandDisplay::bind`] the first time. This lets
+	/// singletons like `wl_compositor` be shared instead of bound over and
+	/// over.
+	pub fn get_or_bind<I: Interface>(&mut self) -> Result<I, WaylandError> {
+		if let Some(object) = self.bound.iter().find(|obj| obj.interface() == I::NAME) {
+			return Ok(unsafe { I::from_id(object.id()) });
+		}
+
+		let object = self.bind::<I>()?;
+		self.bound.push(object.into());
+		Ok(object)
+	}
+
+	/// Allocates an ID for a new object. The object has to be created by
+	/// sending a request that takes its ID as a `new_id` argument.
+	pub fn create<I: Interface>(&mut self) -> I {
+		let id = self
+			.free_ids
+			.pop()
+			.or_else(|| ObjectId::new(self.objects.len() as u32))
+			.unwrap();
+		let object = unsafe { I::from_id(id) };
+		if id.get() as usize == self.objects.len() {
+			self.objects.push(Some(object.into()));
+		} else {
+			self.objects[id.get() as usize] = Some(object.into());
+		}
+
+		object
+	}
+	/// Forgets an object the compositor created, after its destructor has
+	/// been sent. Unlike the client's objects, the compositor doesn't confirm
+	/// these with `wl_display.delete_id`.
+	pub fn forget_server_object<I: Interface>(&mut self, object: I) {
+		if let Some(idx) = object.id().get().checked_sub(SERVER_ID_START)
+			&& let Some(object) = self.server_objects.get_mut(idx as usize)
+		{
+			*object = None;
+		}
+	}
+	/// Queues a request to be sent to `object`. Requests are sent when
+	/// [`WaylandDisplay::flush`] or [`WaylandDisplay::dispatch`] is called.
+	pub fn send<I: Interface>(
+		&mut self,
+		object: I,
+		request: I::Request<'_>,
+	) -> Result<(), WaylandError> {
+		let (start, fds_start) = (self.outgoing.len(), self.outgoing_fds.len());
+		// The header has the message's size, so it's filled in once the
+		// arguments are encoded
+		let args_start = start + Message::HEADER_SIZE;
+		self.outgoing.resize(args_start, 0);
+		let header = I::encode_request(&request, &mut self.outgoing, &mut self.outgoing_fds)
+			.and_then(|opcode| {
+				Message::encode_header(object.id(), opcode, self.outgoing.len() - args_start)
+			});
+		match header {
+			Ok(header) => {
+				self.outgoing[start..args_start].copy_from_slice(&header);
+				Ok(())
+			}
+			Err(err) => {
+				self.outgoing.truncate(start);
+				self.outgoing_fds.truncate(fds_start);
+				Err(err.into())
+			}
+		}
+	}
+	/// Sends every queued request to the compositor.
+	///
+	/// File descriptors in requests are sent at this point, so they have to
+	/// stay open until this is called.
+	pub fn flush(&mut self) -> Result<(), WaylandError> {
+		let (mut bytes, mut fds) = (&self.outgoing[..], &self.outgoing_fds[..]);
+		let res = loop {
+			if bytes.is_empty() {
+				break Ok(());
+			}
+			match self.socket.write_with_fds(bytes, fds) {
+				Ok(0) => break Err(WaylandError::Disconnected),
+				// The compositor receives every file descriptor along with the
+				// first bytes sent
+				Ok(written) => (bytes, fds) = (&bytes[written..], &[]),
+				Err(err) if err.kind() == os::ErrorKind::Interrupted => {}
+				Err(err) if err.kind() == os::ErrorKind::BrokenPipe => {
+					break Err(WaylandError::Disconnected);
+				}
+				Err(err) => break Err(err.into()),
+			}
+		};
+		self.outgoing.clear();
+		self.outgoing_fds.clear();
+
+		res
+	}
+
+	/// Sends queued requests, waits for the compositor to send events, and
+	/// calls `handler` with each of them.
+	///
+	/// Events for `wl_display` are handled internally. Registry events are
+	/// passed to `handler` after the list of globals has been updated, and
+	/// `xdg_wm_base` pings are answered before they're passed to it.
+	///
+	/// File descriptors in events belong to `handler`, which has to close
+	/// them.
+	pub fn dispatch(
+		&mut self,
+		mut handler: impl FnMut(&mut Self, SomeEvent<'_>),
+	) -> Result<(), WaylandError> {
+		self.flush()?;
+
+		let mut buf = [0; 4096];
+		let read = loop {
+			match self.socket.read_with_fds(&mut buf, &mut self.incoming_fds) {
+				Err(err) if err.kind() == os::ErrorKind::Interrupted => {}
+				res => break res?,
+			}
+		};
+		if read == 0 {
+			return Err(WaylandError::Disconnected);
+		}
+
+		// `handler` gets `&mut self`, so it can send requests in response to
+		// events; events borrow from `incoming`, so it has to be moved out
+		let mut incoming = take(&mut self.incoming);
+		incoming.extend_from_slice(&buf[..read]);
+		let res = self.dispatch_messages(&incoming, &mut handler);
+		match res {
+			Ok(used) => {
+				incoming.drain(..used);
+				self.incoming = incoming;
+				Ok(())
+			}
+			Err(err) => Err(err),
+		}
+	}
+	/// Waits until the compositor has processed every request sent so far,
+	/// dispatching events to `handler` in the meantime.
+	pub fn roundtrip(
+		&mut self,
+		mut handler: impl FnMut(&mut Self, SomeEvent<'_>),
+	) -> Result<(), WaylandError> {
+		let callback = self.create::<WlCallback>();
+		self.send(
+			self.display(),
+			wl_display::Request::Sync {
+				callback: callback.id(),
+			},
+		)?;
+
+		let mut done = false;
+		while !done {
+			self.dispatch(|display, event| match event {
+				SomeEvent::WlCallback(object, wl_callback::Event::Done { .. })
+					if object == callback =>
+				{
+					done = true
+				}
+				event => handler(display, event),
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// Dispatches every complete message in `bytes`. Returns how many bytes
+	/// were used.
+	fn dispatch_messages(
+		&mut self,
+		bytes: &[u8],
+		handler: &mut impl FnMut(&mut Self, SomeEvent<'_>),
+	) -> Result<usize, WaylandError> {
+		let mut used = 0;
+		loop {
+			let message = match Message::from_wire(&bytes[used..]) {
+				Ok((size, message)) => {
+					used += size as usize;
+					message
+				}
+				// The rest of the message hasn't been read yet
+				Err(FromWireError::UnexpectedEnd) => break,
+				Err(err) => return Err(err.into()),
+			};
+
+			let Some(object) = self.object(message.object) else {
+				return Err(WaylandError::UnknownObject(message.object));
+			};
+			let event =
+				object.decode_event(message.opcode, message.args, &mut self.incoming_fds)?;
+			self.handle_event(event)?;
+			if !matches!(event, SomeEvent::WlDisplay(..)) {
+				handler(self, event);
+			}
+		}
+
+		Ok(used)
+	}
+	/// The object with the given ID, if it exists.
+	fn object(&self, id: ObjectId) -> Option<SomeObject> {
+		let object = match id.get().checked_sub(SERVER_ID_START) {
+			Some(idx) => self.server_objects.get(idx as usize),
+			None => self.objects.get(id.get() as usize),
+		};
+		object.copied().flatten()
+	}
+	/// Sets up [`WaylandInput`] and [`WaylandClipboard`] for the seat, if the
+	/// compositor advertises one and they haven't been set up yet.
+	fn bind_seat(&mut self) -> Result<(), WaylandError> {
+		if self.input.is_none() && self.globals.iter().any(|g| g.interface == WlSeat::NAME) {
+			self.input = Some(WaylandInput::new(self)?);
+		}
+		if let Some(input) = &self.input
+			&& self.clipboard.is_none()
+			&& self
+				.globals
+				.iter()
+				.any(|g| g.interface == WlDataDeviceManager::NAME)
+		{
+			let seat = input.seat();
+			self.clipboard = Some(WaylandClipboard::new(self, seat)?);
+		}
+
+		Ok(())
+	}
+	/// Updates the display's state for events from core objects.
+	fn handle_event(&mut self, event: SomeEvent<'_>) -> Result<(), WaylandError> {
+		match event {
+			SomeEvent::WlDisplay(
+				_,
+				wl_display::Event::Error {
+					object_id,
+					code,
+					message,
+				},
+			) => {
+				return Err(WaylandError::Protocol {
+					object: object_id,
+					code,
+					message: String::from(message),
+				});
+			}
+			SomeEvent::WlDisplay(_, wl_display::Event::DeleteId { id }) => {
+				if let (Some(object), Some(id)) =
+					(self.objects.get_mut(id as usize), ObjectId::new(id))
+				{
+					*object = None;
+					self.free_ids.push(id);
+				}
+			}
+			SomeEvent::WlRegistry(
+				_,
+				wl_registry::Event::Global {
+					name,
+					interface,
+					version,
+				},
+			) => self.globals.push(Global {
+				name,
+				interface: String::from(interface),
+				version,
+			}),
+			SomeEvent::WlRegistry(_, wl_registry::Event::GlobalRemove { name }) => {
+				self.globals.retain(|global| global.name != name)
+			}
+			SomeEvent::WlDataDevice(_, wl_data_device::Event::DataOffer { id }) => {
+				let Some(idx) = id.get().checked_sub(SERVER_ID_START) else {
+					return Err(WaylandError::UnknownObject(id));
+				};
+				let (idx, offer) = (idx as usize, unsafe { WlDataOffer::from_id(id) });
+				if idx >= self.server_objects.len() {
+					self.server_objects.resize(idx + 1, None);
+				}
+				self.server_objects[idx] = Some(offer.into());
+			}
+			// The compositor assumes the client has frozen if it doesn't
+			// answer pings
+			SomeEvent::XdgWmBase(wm_base, xdg_wm_base::Event::Ping { serial }) => {
+				self.send(wm_base, xdg_wm_base::Request::Pong { serial })?
+			}
+			_ => {}
+		}
+
+		Ok(())
+	}
+}
+impl Display for WaylandDisplay {
+	type WindowHandle = WaylandWindow;
+	type Error = WaylandError;
+
+	fn new() -> Result<Self, Self::Error> {
+		Self::connect()
+	}
+	/// The window's size is the size of the buffers presented to it, so
+	/// `width` and `height` aren't used.
+	fn create_window(
+		&mut self,
+		title: &str,
+		_width: u32,
+		_height: u32,
+	) -> Result<Self::WindowHandle, Self::Error> {
+		WaylandWindow::new(self, title)
+	}
+	fn window_id(window: &Self::WindowHandle) -> WindowId {
+		window.id()
+	}
+	fn dispatch_events(
+		&mut self,
+		windows: &mut [Self::WindowHandle],
+		timeout: Option<Duration>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) -> Result<(), Self::Error> {
+		self.bind_seat()?;
+
+		self.flush()?;
+		loop {
+			match self.socket.wait_readable(timeout) {
+				Ok(true) => break,
+				Ok(false) => return Ok(()),
+				Err(err) if err.kind() == os::ErrorKind::Interrupted => {}
+				Err(err) => return Err(err.into()),
+			}
+		}
+
+		// `dispatch` needs `&mut self`, so the seat's state has to be moved out
+		let (mut input, mut clipboard) = (take(&mut self.input), take(&mut self.clipboard));
+		let mut res = Ok(());
+		let dispatched = self.dispatch(|display, event| {
+			let mut translate = || {
+				if let Some(input) = &mut input
+					&& input.handle_event(display, event, handler)?
+				{
+					return Ok(());
+				}
+				if let Some(clipboard) = &mut clipboard
+					&& clipboard.handle_event(display, event)?
+				{
+					return Ok(());
+				}
+				for window in windows.iter_mut() {
+					window.translate_event(display, event, handler)?;
+				}
+				Ok(())
+			};
+			if res.is_ok() {
+				res = translate();
+			}
+		});
+		(self.input, self.clipboard) = (input, clipboard);
+
+		dispatched.and(res)
+	}
+}
+impl Clipboard for WaylandDisplay {
+	/// The compositor only sends the clipboard's contents while one of the
+	/// client's windows has keyboard focus, so this returns `None` until then.
+	fn clipboard_text(&mut self) -> Result<Option<String>, Self::Error> {
+		self.bind_seat()?;
+		let Some(mut clipboard) = take(&mut self.clipboard) else {
+			return Ok(None);
+		};
+		let res = clipboard.text(self);
+		self.clipboard = Some(clipboard);
+
+		res
+	}
+	/// Wayland only lets clients set the clipboard in response to input, so
+	/// this errors with [`WaylandError::NoInputSerial`] until the seat has
+	/// sent a key or button event.
+	fn set_clipboard_text(&mut self, text: &str) -> Result<(), Self::Error> {
+		self.bind_seat()?;
+		let Some(mut clipboard) = take(&mut self.clipboard) else {
+			return Err(WaylandError::MissingGlobal(WlDataDeviceManager::NAME));
+		};
+		let res = match self.input.as_ref().and_then(WaylandInput::serial) {
+			Some(serial) => clipboard.set_text(self, text, serial),
+			None => Err(WaylandError::NoInputSerial),
+		};
+		self.clipboard = Some(clipboard);
+
+		res
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use {
+		super::*,
+		crate::{
+			concurrency::thread,
+			io::{Reader, Writer},
+			rt::{
+				mem::{self, MemoryAmount},
+				os::unix::{self, FileWriter},
+			},
+			ui::{
+				display::wayland::wire::{Fixed, ToWire},
+				event::{Key, KeyEvent, PointerButton},
+			},
+		},
+	};
+
+	/// Writes an event from a fake compositor.
+	fn event(socket: &mut UnixStream, id: u32, opcode: u16, args: impl FnOnce(&mut Vec<u8>)) {
+		let mut message = Vec::new();
+		args(&mut message);
+
+		let mut bytes = Vec::new();
+		Message {
+			object: ObjectId::new(id).unwrap(),
+			opcode,
+			args: &message,
+		}
+		.to_wire(&mut bytes)
+		.unwrap();
+		socket.write_all(&bytes).unwrap();
+	}
+
+	#[test]
+	fn registry() {
+		let (client, mut compositor) = UnixStream::pair().unwrap();
+
+		// The registry is object 2, and the first roundtrip's callback is 3
+		event(&mut compositor, 2, 0, |args| {
+			let Ok(()) = 1u32.to_wire(args);
+			"wl_compositor".to_wire(args).unwrap();
+			let Ok(()) = 6u32.to_wire(args);
+		});
+		event(&mut compositor, 3, 0, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+		});
+		let mut display = WaylandDisplay::from_socket(client).unwrap();
+		assert_eq!(
+			display.globals(),
+			[Global {
+				name: 1,
+				interface: String::from("wl_compositor"),
+				version: 6
+			}]
+		);
+		assert_eq!(
+			display.bind::<WlCallback>(),
+			Err(WaylandError::MissingGlobal("wl_callback"))
+		);
+
+		// wl_display.get_registry(2), then wl_display.sync(3)
+		let mut bytes = [0; 24];
+		assert_eq!(compositor.read(&mut bytes), Ok(24));
+		let (words, _) = bytes.as_chunks::<4>();
+		let requests: Vec<u32> = words.iter().map(|word| u32::from_ne_bytes(*word)).collect();
+		assert_eq!(requests, [1, 12 << 16 | 1, 2, 1, 12 << 16, 3]);
+
+		// The compositor is done with the callback, so its ID is reused
+		event(&mut compositor, 1, 1, |args| {
+			let Ok(()) = 3u32.to_wire(args);
+		});
+		display.dispatch(|_, _| {}).unwrap();
+		assert_eq!(display.create::<WlCallback>().id().get(), 3);
+
+		event(&mut compositor, 1, 0, |args| {
+			let Ok(()) = 2u32.to_wire(args);
+			let Ok(()) = 1u32.to_wire(args);
+			"bad request".to_wire(args).unwrap();
+		});
+		assert_eq!(
+			display.dispatch(|_, _| {}),
+			Err(WaylandError::Protocol {
+				object: ObjectId::new(2).unwrap(),
+				code: 1,
+				message: String::from("bad request")
+			})
+		);
+	}
+
+	#[test]
+	fn window() {
+		let (client, mut compositor) = UnixStream::pair().unwrap();
+		for (name, interface, version) in [
+			(1, "wl_compositor", 6),
+			(2, "wl_shm", 1),
+			(3, "xdg_wm_base", 6),
+		] {
+			event(&mut compositor, 2, 0, |args| {
+				let Ok(()) = (name as u32).to_wire(args);
+				interface.to_wire(args).unwrap();
+				let Ok(()) = (version as u32).to_wire(args);
+			});
+		}
+		event(&mut compositor, 3, 0, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+		});
+		let mut display = WaylandDisplay::from_socket(client).unwrap();
+
+		// Objects 4-8 are the compositor, xdg_wm_base, surface, xdg_surface,
+		// and toplevel; 9-11 are wl_shm, the pool, and the buffer
+		let mut window = WaylandWindow::new(&mut display, "Crux").unwrap();
+		let mut buffer = Buffer::new(&mut display, 4, 2, ShmFormat::Xrgb8888).unwrap();
+		assert_eq!(buffer.object().id().get(), 11);
+		assert_eq!(buffer.pixels().len(), 32);
+
+		// The pool's file is sent with `wl_shm.create_pool(10, size: 32)`,
+		// which isn't part of the message's bytes. Reads stop after a message
+		// with file descriptors, so this reads every request sent so far.
+		let mut bytes = [0; 4096];
+		let read = compositor.read(&mut bytes).unwrap();
+		let (words, _) = bytes[..read].as_chunks::<4>();
+		let requests: Vec<u32> = words.iter().map(|word| u32::from_ne_bytes(*word)).collect();
+		assert_eq!(requests[requests.len() - 4..], [9, 16 << 16, 10, 32]);
+		assert_eq!(
+			window.present(&mut display, &mut buffer),
+			Err(WaylandError::Unconfigured)
+		);
+
+		event(&mut compositor, 8, 0, |args| {
+			let Ok(()) = 640i32.to_wire(args);
+			let Ok(()) = 480i32.to_wire(args);
+			[].as_slice().to_wire(args).unwrap();
+		});
+		event(&mut compositor, 7, 0, |args| {
+			let Ok(()) = 5u32.to_wire(args);
+		});
+		event(&mut compositor, 5, 0, |args| {
+			let Ok(()) = 9u32.to_wire(args);
+		});
+		let mut configured = false;
+		display
+			.dispatch(|display, event| configured |= window.handle_event(display, event).unwrap())
+			.unwrap();
+		assert!(configured);
+		assert_eq!(window.size(), Some((640, 480)));
+
+		// xdg_surface.ack_configure(5), then xdg_wm_base.pong(9)
+		display.flush().unwrap();
+		let read = compositor.read(&mut bytes).unwrap();
+		let (words, _) = bytes[..read].as_chunks::<4>();
+		let requests: Vec<u32> = words.iter().map(|word| u32::from_ne_bytes(*word)).collect();
+		assert_eq!(
+			requests[requests.len() - 6..],
+			[7, 12 << 16 | 4, 5, 5, 12 << 16 | 3, 9]
+		);
+
+		buffer.pixels().fill(0xFF);
+		window.present(&mut display, &mut buffer).unwrap();
+		assert!(buffer.is_busy());
+		event(&mut compositor, 11, 0, |_| {});
+		display
+			.dispatch(|_, event| {
+				buffer.handle_event(event);
+			})
+			.unwrap();
+		assert!(!buffer.is_busy());
+	}
+
+	#[test]
+	fn input() {
+		let (client, mut compositor) = UnixStream::pair().unwrap();
+		for (name, interface, version) in [
+			(1, "wl_compositor", 6),
+			(2, "xdg_wm_base", 6),
+			(3, "wl_seat", 9),
+		] {
+			event(&mut compositor, 2, 0, |args| {
+				let Ok(()) = (name as u32).to_wire(args);
+				interface.to_wire(args).unwrap();
+				let Ok(()) = (version as u32).to_wire(args);
+			});
+		}
+		event(&mut compositor, 3, 0, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+		});
+		let mut display = WaylandDisplay::from_socket(client).unwrap();
+		// The seat is bound as object 4 when events are first dispatched, and
+		// nothing arrives before the timeout
+		let no_events = &mut |_, event| panic!("unexpected event {event:?}");
+		display
+			.dispatch_events(&mut [], Some(Duration::ZERO), no_events)
+			.unwrap();
+
+		// Objects 5-9 are the compositor, xdg_wm_base, surface, xdg_surface,
+		// and toplevel; the seat's keyboard and pointer are 10 and 11
+		let mut windows = [display.create_window("Crux", 640, 480).unwrap()];
+		let window = WaylandDisplay::window_id(&windows[0]);
+		assert_eq!(window, WindowId(7));
+		event(&mut compositor, 4, 0, |args| {
+			let Ok(()) = 3u32.to_wire(args);
+		});
+		display
+			.dispatch_events(&mut windows, None, no_events)
+			.unwrap();
+
+		// wl_keyboard.enter and wl_keyboard.key
+		event(&mut compositor, 10, 1, |args| {
+			let Ok(()) = 1u32.to_wire(args);
+			let Ok(()) = ObjectId::new(7).unwrap().to_wire(args);
+			[].as_slice().to_wire(args).unwrap();
+		});
+		event(&mut compositor, 10, 3, |args| {
+			let Ok(()) = 2u32.to_wire(args);
+			let Ok(()) = 0u32.to_wire(args);
+			let Ok(()) = 30u32.to_wire(args);
+			let Ok(()) = 1u32.to_wire(args);
+		});
+		// wl_pointer.enter, wl_pointer.button, and wl_pointer.axis
+		event(&mut compositor, 11, 0, |args| {
+			let Ok(()) = 3u32.to_wire(args);
+			let Ok(()) = ObjectId::new(7).unwrap().to_wire(args);
+			let Ok(()) = Fixed::from_f64(1.5).to_wire(args);
+			let Ok(()) = Fixed::from_int(2).to_wire(args);
+		});
+		event(&mut compositor, 11, 3, |args| {
+			let Ok(()) = 4u32.to_wire(args);
+			let Ok(()) = 0u32.to_wire(args);
+			let Ok(()) = 0x110u32.to_wire(args);
+			let Ok(()) = 1u32.to_wire(args);
+		});
+		event(&mut compositor, 11, 4, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+			let Ok(()) = 0u32.to_wire(args);
+			let Ok(()) = Fixed::from_int(-20).to_wire(args);
+		});
+		// xdg_toplevel.close
+		event(&mut compositor, 9, 1, |_| {});
+
+		let mut events = Vec::new();
+		display
+			.dispatch_events(&mut windows, None, &mut |id, event| {
+				assert_eq!(id, window);
+				events.push(event);
+			})
+			.unwrap();
+		assert_eq!(
+			events,
+			[
+				Event::Focused(true),
+				Event::Key(KeyEvent {
+					key: Key::A,
+					scancode: 30,
+					pressed: true
+				}),
+				Event::PointerEnter { x: 1.5, y: 2.0 },
+				Event::PointerButton {
+					button: PointerButton::Left,
+					pressed: true
+				},
+				Event::Scroll { x: 0.0, y: -2.0 },
+				Event::CloseRequested,
+			]
+		);
+	}
+
+	#[test]
+	fn clipboard() {
+		let (client, mut compositor) = UnixStream::pair().unwrap();
+		for (name, interface, version) in [(1, "wl_seat", 9), (2, "wl_data_device_manager", 3)] {
+			event(&mut compositor, 2, 0, |args| {
+				let Ok(()) = (name as u32).to_wire(args);
+				interface.to_wire(args).unwrap();
+				let Ok(()) = (version as u32).to_wire(args);
+			});
+		}
+		event(&mut compositor, 3, 0, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+		});
+		let mut display = WaylandDisplay::from_socket(client).unwrap();
+
+		// The seat, data device manager, and data device are objects 4-6.
+		// Nothing has been copied, and there's been no input to copy with.
+		assert_eq!(display.clipboard_text(), Ok(None));
+		assert_eq!(
+			display.set_clipboard_text("copied"),
+			Err(WaylandError::NoInputSerial)
+		);
+
+		// The seat's keyboard (7) gets focus
+		event(&mut compositor, 4, 0, |args| {
+			let Ok(()) = 2u32.to_wire(args);
+		});
+		event(&mut compositor, 7, 1, |args| {
+			let Ok(()) = 10u32.to_wire(args);
+			let Ok(()) = ObjectId::new(20).unwrap().to_wire(args);
+			[].as_slice().to_wire(args).unwrap();
+		});
+		// Another client's selection is offered as text and an image
+		event(&mut compositor, 6, 0, |args| {
+			let Ok(()) = SERVER_ID_START.to_wire(args);
+		});
+		for mime_type in ["text/plain", "text/plain;charset=utf-8", "image/png"] {
+			event(&mut compositor, SERVER_ID_START, 0, |args| {
+				mime_type.to_wire(args).unwrap();
+			});
+		}
+		event(&mut compositor, 6, 5, |args| {
+			let Ok(()) = SERVER_ID_START.to_wire(args);
+		});
+		display
+			.dispatch_events(&mut [], None, &mut |_, _| {})
+			.unwrap();
+
+		// Pasting asks the other client to write the text to a pipe, with
+		// `wl_data_offer.receive("text/plain;charset=utf-8", fd)`
+		let paste = thread::spawn(move || {
+			let (mut bytes, mut fds, mut read) = ([0; 4096], Vec::new(), 0);
+			while fds.is_empty() {
+				read = compositor.read_with_fds(&mut bytes, &mut fds).unwrap();
+			}
+			let mut writer = unsafe { FileWriter::new(fds[0]) };
+			writer.write_all("pasted ✓".as_bytes()).unwrap();
+			unsafe { unix::close(fds[0]) };
+
+			let (words, _) = bytes[read - 40..read - 32].as_chunks::<4>();
+			let header: Vec<u32> = words.iter().map(|word| u32::from_ne_bytes(*word)).collect();
+			(compositor, header)
+		});
+		assert_eq!(display.clipboard_text(), Ok(Some(String::from("pasted ✓"))));
+		let (mut compositor, header) = paste.join();
+		assert_eq!(header, [SERVER_ID_START, 40 << 16 | 1]);
+
+		// Copying creates a data source (8), and sets it as the selection with
+		// the serial from the keyboard's focus
+		display.set_clipboard_text("copied").unwrap();
+		assert_eq!(display.clipboard_text(), Ok(Some(String::from("copied"))));
+		display.flush().unwrap();
+		let mut bytes = [0; 4096];
+		let read = compositor.read(&mut bytes).unwrap();
+		let (words, _) = bytes[..read].as_chunks::<4>();
+		let requests: Vec<u32> = words.iter().map(|word| u32::from_ne_bytes(*word)).collect();
+		assert_eq!(requests[..3], [5, 12 << 16, 8]);
+		assert_eq!(requests[requests.len() - 4..], [6, 16 << 16 | 1, 8, 10]);
+
+		// Other clients paste with `wl_data_source.send`
+		let (mut reader, writer) = unix::pipe().unwrap();
+		let mut args = Vec::new();
+		"text/plain".to_wire(&mut args).unwrap();
+		let mut message = Vec::new();
+		Message {
+			object: ObjectId::new(8).unwrap(),
+			opcode: 1,
+			args: &args,
+		}
+		.to_wire(&mut message)
+		.unwrap();
+		compositor.write_with_fds(&message, &[writer.fd()]).unwrap();
+		drop(writer);
+		display
+			.dispatch_events(&mut [], None, &mut |_, _| {})
+			.unwrap();
+		let mut pasted = Vec::new();
+		reader.read_to_end(&mut pasted).unwrap();
+		assert_eq!(pasted, b"copied");
+
+		// Once another client copies something that isn't text, the clipboard
+		// is empty
+		event(&mut compositor, 8, 2, |_| {});
+		event(&mut compositor, 6, 5, |args| {
+			let Ok(()) = None::<ObjectId>.to_wire(args);
+		});
+		display
+			.dispatch_events(&mut [], None, &mut |_, _| {})
+			.unwrap();
+		assert_eq!(display.clipboard_text(), Ok(None));
+	}
+
+	#[test]
+	fn shared_memory() {
+		let (client, mut compositor) = UnixStream::pair().unwrap();
+		event(&mut compositor, 2, 0, |args| {
+			let Ok(()) = 1u32.to_wire(args);
+			"wl_shm".to_wire(args).unwrap();
+			let Ok(()) = 1u32.to_wire(args);
+		});
+		event(&mut compositor, 3, 0, |args| {
+			let Ok(()) = 0u32.to_wire(args);
+		});
+		let mut display = WaylandDisplay::from_socket(client).unwrap();
+		let mut pool = ShmPool::new(&mut display, 16, MemoryAmount::kibibytes(64)).unwrap();
+
+		// The pool's file is sent with `wl_shm.create_pool`, after the requests
+		// from the first roundtrip
+		let (mut bytes, mut fds) = ([0; 4096], Vec::new());
+		while fds.is_empty() {
+			assert_ne!(compositor.read_with_fds(&mut bytes, &mut fds), Ok(0));
+		}
+		assert_eq!(fds.len(), 1);
+
+		// The compositor sees what the client draws, even after the pool grows
+		pool.memory().fill(0xAB);
+		pool.grow(&mut display, 8192).unwrap();
+		pool.memory()[8191] = 0xCD;
+		let mem = mem::reserve(MemoryAmount::bytes(8192)).unwrap();
+		unsafe { mem::map_file(mem, fds[0]) }.unwrap();
+		let mapped =
+			unsafe { core::slice::from_raw_parts(mem.base_ptr.as_ptr().cast::<u8>(), 8192) };
+		assert!(mapped[..16].iter().all(|byte| *byte == 0xAB));
+		assert_eq!(mapped[8191], 0xCD);
+
+		unsafe { mem::unreserve(mem) };
+		unsafe { crate::rt::os::unix::close(fds[0]) };
+	}
+}
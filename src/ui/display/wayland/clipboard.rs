@@ -0,0 +1,249 @@
+//! Copy and paste, through the `wl_data_device_manager` global.
+//!
+//! The compositor tells each client about the clipboard's contents (the
+//! "selection") with a `wl_data_offer`, which lists the MIME types the
+//! contents are available as. Reading them means asking the client that owns
+//! the selection to write them to a pipe. Setting the clipboard works the
+//! other way around: the client creates a `wl_data_source`, and writes its
+//! contents to a pipe whenever another client pastes them.
+//!
+//! ```rs
+//! let mut clipboard = WaylandClipboard::new(&mut display, input.seat())?;
+//!
+//! display.dispatch(|display, event| {
+//!     clipboard.handle_event(display, event).unwrap();
+//! })?;
+//! println!("{:?}", clipboard.text(&mut display)?);
+//! ```
+//!
+//! The compositor only sends the selection to clients with keyboard focus,
+//! and only lets them set it in response to input, so a client without a
+//! window can't use the clipboard.
+
+use {
+	super::{
+		Interface, WaylandDisplay, WaylandError,
+		interfaces::{
+			SomeEvent, WlDataDevice, WlDataDeviceManager, WlDataOffer, WlDataSource, WlSeat,
+			wl_data_device, wl_data_device_manager, wl_data_offer, wl_data_source,
+		},
+		wire::ObjectId,
+	},
+	crate::{
+		io::{Reader, Writer},
+		lang::take,
+		rt::os::unix::{self, FileWriter},
+	},
+};
+
+/// The MIME types text is offered as, from most to least preferred.
+/// `UTF8_STRING` is what X11 programs running under XWayland use.
+const TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+/// The clipboard of the compositor's seat. See the
+/// [module-level docs](self).
+#[derive(Debug)]
+pub struct WaylandClipboard {
+	device: WlDataDevice,
+	/// Offers the compositor has introduced, but not used yet, and the best
+	/// text MIME type each one has.
+	offers: Vec<(WlDataOffer, Option<&'static str>)>,
+	/// The current selection, if it's text from another client.
+	selection: Option<(WlDataOffer, &'static str)>,
+	/// The data source for the text this client put on the clipboard, if it
+	/// still owns the selection.
+	source: Option<(WlDataSource, String)>,
+}
+impl WaylandClipboard {
+	/// Gets the data device for `seat`, which the compositor sends selections
+	/// to.
+	pub fn new(display: &mut WaylandDisplay, seat: WlSeat) -> Result<Self, WaylandError> {
+		let manager: WlDataDeviceManager = display.get_or_bind()?;
+		let device = display.create::<WlDataDevice>();
+		display.send(
+			manager,
+			wl_data_device_manager::Request::GetDataDevice {
+				id: device.id(),
+				seat: seat.id(),
+			},
+		)?;
+
+		Ok(Self {
+			device,
+			offers: Vec::new(),
+			selection: None,
+			source: None,
+		})
+	}
+
+	/// Reads the clipboard's text. Returns `None` if the clipboard is empty,
+	/// doesn't hold text, or the compositor hasn't sent the selection (e.g.
+	/// because none of the client's windows have keyboard focus). Bytes that
+	/// aren't valid UTF-8 are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+	///
+	/// This blocks until the client that owns the selection has sent all of
+	/// it.
+	pub fn text(&mut self, display: &mut WaylandDisplay) -> Result<Option<String>, WaylandError> {
+		// The compositor would ask this client to send the text to itself,
+		// which would block forever
+		if let Some((_, text)) = &self.source {
+			return Ok(Some(text.clone()));
+		}
+		let Some((offer, mime_type)) = self.selection else {
+			return Ok(None);
+		};
+
+		let (mut reader, writer) = unix::pipe()?;
+		display.send(
+			offer,
+			wl_data_offer::Request::Receive {
+				mime_type,
+				fd: writer.fd(),
+			},
+		)?;
+		display.flush()?;
+		// The pipe only ends once every copy of the write end is closed
+		drop(writer);
+
+		let mut bytes = Vec::new();
+		reader.read_to_end(&mut bytes)?;
+		Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+	}
+	/// Puts `text` on the clipboard. `serial` has to be from a recent input
+	/// event (see [`WaylandInput::serial`](super::WaylandInput::serial)), or
+	/// the compositor ignores the request.
+	///
+	/// The text is sent to other clients while events are dispatched, so this
+	/// client has to keep handling events for pasting to work.
+	pub fn set_text(
+		&mut self,
+		display: &mut WaylandDisplay,
+		text: &str,
+		serial: u32,
+	) -> Result<(), WaylandError> {
+		let manager: WlDataDeviceManager = display.get_or_bind()?;
+		let source = display.create::<WlDataSource>();
+		display.send(
+			manager,
+			wl_data_device_manager::Request::CreateDataSource { id: source.id() },
+		)?;
+		for mime_type in TEXT_MIME_TYPES {
+			display.send(source, wl_data_source::Request::Offer { mime_type })?;
+		}
+		display.send(
+			self.device,
+			wl_data_device::Request::SetSelection {
+				source: Some(source.id()),
+				serial,
+			},
+		)?;
+
+		// The compositor cancels the old source when the selection changes,
+		// but this client doesn't need to wait for that
+		if let Some((old, _)) = self.source.replace((source, String::from(text))) {
+			display.send(old, wl_data_source::Request::Destroy)?;
+		}
+
+		Ok(())
+	}
+
+	/// Updates the clipboard's state for an event from
+	/// [`WaylandDisplay::dispatch`], and sends this client's text to clients
+	/// that paste it. Returns `true` if the event was for the clipboard.
+	pub fn handle_event(
+		&mut self,
+		display: &mut WaylandDisplay,
+		event: SomeEvent<'_>,
+	) -> Result<bool, WaylandError> {
+		match event {
+			SomeEvent::WlDataDevice(device, event) if device == self.device => {
+				self.handle_device_event(display, event)?
+			}
+			SomeEvent::WlDataOffer(offer, wl_data_offer::Event::Offer { mime_type }) => {
+				let Some((_, best)) = self.offers.iter_mut().find(|(o, _)| *o == offer) else {
+					return Ok(false);
+				};
+				let rank = |mime_type| TEXT_MIME_TYPES.iter().position(|m| *m == mime_type);
+				if let Some(new) = rank(mime_type)
+					&& best.and_then(rank).is_none_or(|old| new < old)
+				{
+					*best = Some(TEXT_MIME_TYPES[new]);
+				}
+			}
+			SomeEvent::WlDataSource(source, event)
+				if self.source.as_ref().is_some_and(|(s, _)| *s == source) =>
+			{
+				match event {
+					wl_data_source::Event::Send { fd, .. } => {
+						let (_, text) = self.source.as_ref().unwrap();
+						let mut writer = unsafe { FileWriter::new(fd) };
+						// The pasting client may have given up on the
+						// transfer, which isn't this client's problem
+						let _ = writer.write_all(text.as_bytes());
+						unsafe { unix::close(fd) };
+					}
+					// Another client took the selection
+					wl_data_source::Event::Cancelled => {
+						display.send(source, wl_data_source::Request::Destroy)?;
+						self.source = None;
+					}
+					_ => {}
+				}
+			}
+			_ => return Ok(false),
+		}
+
+		Ok(true)
+	}
+
+	fn handle_device_event(
+		&mut self,
+		display: &mut WaylandDisplay,
+		event: wl_data_device::Event<'_>,
+	) -> Result<(), WaylandError> {
+		match event {
+			// The display has already added the offer to its objects
+			wl_data_device::Event::DataOffer { id } => {
+				let offer = unsafe { WlDataOffer::from_id(id) };
+				self.offers.push((offer, None));
+			}
+			// Drag and drop isn't supported, so the offer is declined
+			wl_data_device::Event::Enter { id: Some(id), .. } => {
+				if let Some((offer, _)) = self.take_offer(id) {
+					destroy_offer(display, offer)?;
+				}
+			}
+			wl_data_device::Event::Selection { id } => {
+				if let Some((old, _)) = self.selection.take() {
+					destroy_offer(display, old)?;
+				}
+				match id.and_then(|id| self.take_offer(id)) {
+					Some((offer, Some(mime_type))) => self.selection = Some((offer, mime_type)),
+					Some((offer, None)) => destroy_offer(display, offer)?,
+					None => {}
+				}
+				// Offers are always used right after they're introduced, so any
+				// others are stale
+				for (offer, _) in take(&mut self.offers) {
+					destroy_offer(display, offer)?;
+				}
+			}
+			_ => {}
+		}
+
+		Ok(())
+	}
+
+	/// Removes `id` from the offers that haven't been used yet.
+	fn take_offer(&mut self, id: ObjectId) -> Option<(WlDataOffer, Option<&'static str>)> {
+		let idx = self.offers.iter().position(|(offer, _)| offer.id() == id)?;
+		Some(self.offers.remove(idx))
+	}
+}
+
+/// Destroys an offer that won't be used.
+fn destroy_offer(display: &mut WaylandDisplay, offer: WlDataOffer) -> Result<(), WaylandError> {
+	display.send(offer, wl_data_offer::Request::Destroy)?;
+	display.forget_server_object(offer);
+	Ok(())
+}
@@ -0,0 +1,239 @@
+//! Keyboard and pointer input, from the `wl_seat` global.
+//!
+//! A seat is a group of input devices used by one user. [`WaylandInput`]
+//! gets the seat's keyboard and pointer as the compositor advertises them, and
+//! translates their events into [`Event`]s:
+//!
+//! ```rs
+//! let mut input = WaylandInput::new(&mut display)?;
+//!
+//! display.dispatch(|display, event| {
+//!     input
+//!         .handle_event(display, event, &mut |window, event| println!("{window:?}: {event:?}"))
+//!         .unwrap();
+//! })?;
+//! ```
+//!
+//! Wayland sends keys as evdev keycodes, which are what [`Key::from_evdev`]
+//! expects. The compositor also sends an XKB keymap for turning keys into
+//! text, which Crux doesn't use yet.
+
+use {
+	super::{
+		Interface, WaylandDisplay, WaylandError,
+		interfaces::{SomeEvent, WlKeyboard, WlPointer, WlSeat, wl_keyboard, wl_pointer, wl_seat},
+		window,
+		wire::ObjectId,
+	},
+	crate::{
+		rt::os::unix,
+		ui::event::{Event, Key, KeyEvent, PointerButton, WindowId},
+	},
+};
+
+/// The `wl_seat.capabilities` bit for seats with a pointer.
+const CAPABILITY_POINTER: u32 = 1;
+/// The `wl_seat.capabilities` bit for seats with a keyboard.
+const CAPABILITY_KEYBOARD: u32 = 2;
+/// How far one notch of a mouse wheel scrolls in `wl_pointer.axis` events.
+/// This isn't part of the protocol, but it's what every major compositor
+/// uses.
+const SCROLL_PER_LINE: f64 = 10.0;
+
+/// The keyboard and pointer of the compositor's seat. See the
+/// [module-level docs](self).
+#[derive(Debug)]
+pub struct WaylandInput {
+	seat: WlSeat,
+	keyboard: Option<WlKeyboard>,
+	pointer: Option<WlPointer>,
+	/// The surface with keyboard focus, which key events are for.
+	keyboard_focus: Option<ObjectId>,
+	/// The surface the pointer is over, which pointer events are for.
+	pointer_focus: Option<ObjectId>,
+	/// The serial of the last keyboard focus, key, or button event.
+	serial: Option<u32>,
+}
+impl WaylandInput {
+	/// Binds to the compositor's seat. Its keyboard and pointer are set up
+	/// when the compositor sends the seat's capabilities.
+	pub fn new(display: &mut WaylandDisplay) -> Result<Self, WaylandError> {
+		Ok(Self {
+			seat: display.get_or_bind()?,
+			keyboard: None,
+			pointer: None,
+			keyboard_focus: None,
+			pointer_focus: None,
+			serial: None,
+		})
+	}
+
+	/// The seat's handle, e.g. for sending requests to it directly.
+	pub fn seat(&self) -> WlSeat {
+		self.seat
+	}
+	/// The serial of the last keyboard focus, key, or button event. Requests
+	/// that are only allowed in response to user input, like
+	/// `wl_data_device.set_selection`, have to send this.
+	pub fn serial(&self) -> Option<u32> {
+		self.serial
+	}
+
+	/// Updates the input state for an event from
+	/// [`WaylandDisplay::dispatch`], and calls `handler` with the window and
+	/// [`Event`] it translates to, if any. Returns `true` if the event was for
+	/// the seat or one of its devices.
+	pub fn handle_event(
+		&mut self,
+		display: &mut WaylandDisplay,
+		event: SomeEvent<'_>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) -> Result<bool, WaylandError> {
+		match event {
+			SomeEvent::WlSeat(seat, event) if seat == self.seat => {
+				if let wl_seat::Event::Capabilities { capabilities } = event {
+					self.set_capabilities(display, capabilities)?;
+				}
+			}
+			SomeEvent::WlKeyboard(keyboard, event) if Some(keyboard) == self.keyboard => {
+				self.handle_keyboard_event(event, handler)
+			}
+			SomeEvent::WlPointer(pointer, event) if Some(pointer) == self.pointer => {
+				self.handle_pointer_event(event, handler)
+			}
+			_ => return Ok(false),
+		}
+
+		Ok(true)
+	}
+
+	/// Gets the seat's keyboard and pointer when the seat gains them.
+	fn set_capabilities(
+		&mut self,
+		display: &mut WaylandDisplay,
+		capabilities: u32,
+	) -> Result<(), WaylandError> {
+		let has_keyboard = capabilities & CAPABILITY_KEYBOARD != 0;
+		if has_keyboard && self.keyboard.is_none() {
+			let keyboard = display.create::<WlKeyboard>();
+			display.send(
+				self.seat,
+				wl_seat::Request::GetKeyboard { id: keyboard.id() },
+			)?;
+			self.keyboard = Some(keyboard);
+		} else if !has_keyboard {
+			// Releasing a device needs version 3 of its interface, which the
+			// compositor may not support, so the old objects are just ignored
+			self.keyboard = None;
+			self.keyboard_focus = None;
+		}
+
+		let has_pointer = capabilities & CAPABILITY_POINTER != 0;
+		if has_pointer && self.pointer.is_none() {
+			let pointer = display.create::<WlPointer>();
+			display.send(self.seat, wl_seat::Request::GetPointer { id: pointer.id() })?;
+			self.pointer = Some(pointer);
+		} else if !has_pointer {
+			self.pointer = None;
+			self.pointer_focus = None;
+		}
+
+		Ok(())
+	}
+
+	fn handle_keyboard_event(
+		&mut self,
+		event: wl_keyboard::Event<'_>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) {
+		match event {
+			// Crux doesn't use the keymap, but it still owns the file
+			wl_keyboard::Event::Keymap { fd, .. } => {
+				unsafe { unix::close(fd) };
+			}
+			wl_keyboard::Event::Enter {
+				serial, surface, ..
+			} => {
+				self.keyboard_focus = Some(surface);
+				self.serial = Some(serial);
+				handler(window::window_id(surface), Event::Focused(true));
+			}
+			wl_keyboard::Event::Leave { surface, .. } => {
+				self.keyboard_focus = None;
+				handler(window::window_id(surface), Event::Focused(false));
+			}
+			wl_keyboard::Event::Key {
+				serial, key, state, ..
+			} => {
+				self.serial = Some(serial);
+				if let Some(surface) = self.keyboard_focus {
+					let event = KeyEvent {
+						key: Key::from_evdev(key),
+						scancode: key,
+						pressed: state == 1,
+					};
+					handler(window::window_id(surface), Event::Key(event));
+				}
+			}
+			_ => {}
+		}
+	}
+
+	fn handle_pointer_event(
+		&mut self,
+		event: wl_pointer::Event<'_>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) {
+		let event = match event {
+			wl_pointer::Event::Enter {
+				surface,
+				surface_x,
+				surface_y,
+				..
+			} => {
+				self.pointer_focus = Some(surface);
+				Event::PointerEnter {
+					x: surface_x.to_f64(),
+					y: surface_y.to_f64(),
+				}
+			}
+			wl_pointer::Event::Leave { surface, .. } => {
+				self.pointer_focus = None;
+				handler(window::window_id(surface), Event::PointerLeave);
+				return;
+			}
+			wl_pointer::Event::Motion {
+				surface_x,
+				surface_y,
+				..
+			} => Event::PointerMotion {
+				x: surface_x.to_f64(),
+				y: surface_y.to_f64(),
+			},
+			wl_pointer::Event::Button {
+				serial,
+				button,
+				state,
+				..
+			} => {
+				self.serial = Some(serial);
+				Event::PointerButton {
+					button: PointerButton::from_evdev(button),
+					pressed: state == 1,
+				}
+			}
+			wl_pointer::Event::Axis { axis, value, .. } => {
+				let lines = value.to_f64() / SCROLL_PER_LINE;
+				match axis {
+					0 => Event::Scroll { x: 0.0, y: lines },
+					1 => Event::Scroll { x: lines, y: 0.0 },
+					_ => return,
+				}
+			}
+			_ => return,
+		};
+		if let Some(surface) = self.pointer_focus {
+			handler(window::window_id(surface), event);
+		}
+	}
+}
@@ -1,71 +1,181 @@
+//! The Wayland interfaces Crux understands, and the requests, events, and
+//! errors in each of them.
+//!
+//! Every interface gets a module named after it (e.g. [`wl_display`]) with
+//! its `Request`, `Event`, and `Error` enums, and a handle type (e.g.
+//! [`WlDisplay`]) that implements [`Interface`].
+//!
+//! The interfaces are generated from the protocol XML files in `protocols/`
+//! by `crux_macros::wayland_protocols!`. To support a new interface, copy it
+//! from upstream's protocol file into the matching file there.
+
+use {
+	super::{
+		Interface,
+		wire::{FromWireError, ObjectId, ToWire, ToWireError, UntypedNewId, read_arg},
+	},
+	crate::{
+		lang::{Infallible, PhantomData},
+		rt::os::unix::FileDescriptor,
+	},
+};
+
+/// The Rust type used for each argument type in the Wayland protocol.
+macro_rules! wire_type {
+	($lt:lifetime, int) => { i32 };
+	($lt:lifetime, uint) => { u32 };
+	// These aren't imported, since no interface may use them
+	($lt:lifetime, fixed) => { crate::ui::display::wayland::wire::Fixed };
+	($lt:lifetime, string) => { &$lt str };
+	($lt:lifetime, nullable string) => { Option<&$lt str> };
+	($lt:lifetime, object) => { ObjectId };
+	($lt:lifetime, nullable object) => { Option<ObjectId> };
+	($lt:lifetime, new_id) => { ObjectId };
+	($lt:lifetime, untyped new_id) => { UntypedNewId<$lt> };
+	($lt:lifetime, array) => { &$lt [u8] };
+	($lt:lifetime, fd) => { FileDescriptor };
+}
+
+/// Encodes a request argument. File descriptors aren't part of the message's
+/// bytes; they're sent alongside it, in the order they appear in requests.
+macro_rules! encode_arg {
+	($arg:ident: fd, $buffer:ident, $fds:ident) => {
+		$fds.push($arg)
+	};
+	($arg:ident: $($ty:ident)+, $buffer:ident, $fds:ident) => {
+		ToWire::to_wire(&$arg, $buffer)?
+	};
+}
+
+/// Decodes an event argument, taking file descriptors from the ones received
+/// alongside events.
+macro_rules! decode_arg {
+	(fd, $args:ident, $fds:ident) => {
+		crate::ui::display::wayland::wire::read_fd($fds)?
+	};
+	($($ty:ident)+, $args:ident, $fds:ident) => {
+		read_arg(&mut $args)?
+	};
+}
+
 macro_rules! interfaces {
 	(
 		$(
+		$(#[$interface_attr:meta])*
 		interface $interface_name:ident {
 			ffi_name: $interface_ffi_name:ident;
+			version: $version:literal;
 
 			$(requests {
-				$($request_name:ident$(($($request_arg_name:ident: $request_arg_ty:ty),*))*: $request_opcode:literal)*
-			})*
+				$($(#[$request_attr:meta])* $request_name:ident$(($($request_arg_name:ident: $($request_arg_ty:ident)+),*))?: $request_opcode:literal)*
+			})?
 			$(events {
-				$($event_name:ident$(($($event_arg_name:ident: $event_arg_ty:ty),*))*: $event_opcode:literal)*
-			})*
+				$($(#[$event_attr:meta])* $event_name:ident$(($($event_arg_name:ident: $($event_arg_ty:ident)+),*))?: $event_opcode:literal)*
+			})?
 			$(errors {
-				$($error_name:ident: $error_opcode:literal)*
-			})*
+				$($(#[$error_attr:meta])* $error_name:ident: $error_code:literal)*
+			})?
 		}
 		)*
 	) => {
 		$(
-			mod $interface_ffi_name {
+			pub mod $interface_ffi_name {
 				use super::*;
 
-				pub enum Request {
+				/// Messages the client can send to this interface.
+				#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+				pub enum Request<'a> {
 					$($(
-						$request_name$(($($request_arg_ty),*))*
-					),*)*
+						$(#[$request_attr])*
+						$request_name$({ $($request_arg_name: wire_type!('a, $($request_arg_ty)+)),* })?,
+					)*)?
+					#[doc(hidden)]
+					__Lifetime(Infallible, PhantomData<&'a ()>),
 				}
 
-				pub enum Event {
+				/// Messages the compositor can send to this interface.
+				#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+				pub enum Event<'a> {
 					$($(
-						$event_name$(($($event_arg_ty),*))*
-					),*)*
+						$(#[$event_attr])*
+						$event_name$({ $($event_arg_name: wire_type!('a, $($event_arg_ty)+)),* })?,
+					)*)?
+					#[doc(hidden)]
+					__Lifetime(Infallible, PhantomData<&'a ()>),
 				}
 
+				/// Errors the compositor can report for this interface.
+				#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 				pub enum Error {
-					$($($error_name),*)*
+					$($(
+						$(#[$error_attr])*
+						$error_name = $error_code,
+					)*)?
 				}
-
-				pub struct $interface_name(u32);
-				impl $interface_name {
-					pub type Request = Request;
-					pub type Event = Event;
+				impl Error {
+					/// Converts the code from a `wl_display.error` event to an
+					/// error.
+					pub fn from_code(code: u32) -> Option<Self> {
+						match code {
+							$($($error_code => Some(Self::$error_name),)*)?
+							_ => None,
+						}
+					}
 				}
+
+				$(#[$interface_attr])*
+				#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+				pub struct $interface_name(ObjectId);
 				impl Interface for $interface_name {
-					const NAME: &str = stringify!($interface_ffi_name);
-					type Request = Request;
-					type Event = Event;
+					const NAME: &str = $crate::lang::stringify!($interface_ffi_name);
+					const VERSION: u32 = $version;
+					type Request<'a> = Request<'a>;
+					type Event<'a> = Event<'a>;
 
-					fn id(self) -> u32 {
+					fn id(self) -> ObjectId {
 						self.0
 					}
-					unsafe fn new(id: u32) -> Self {
+					unsafe fn from_id(id: ObjectId) -> Self {
 						Self(id)
 					}
-					fn msg(&self, req: Request, msg_builder: WireWriter<'_, 0>) {
-						let msg_builder = msg_builder.object_id(self.0);
-
-						match req {
+					#[allow(unused_variables)]
+					fn encode_request(
+						request: &Request<'_>,
+						buffer: &mut Vec<u8>,
+						fds: &mut Vec<FileDescriptor>,
+					) -> Result<u16, ToWireError> {
+						match *request {
 							$($(
-								Request::$request_name$(($($request_arg_name),*))* => {
-									let msg_builder = msg_builder.opcode($request_opcode);
+								Request::$request_name$({ $($request_arg_name),* })? => {
 									$($(
-										$request_arg_name.to_wire(&msg_builder);
-									)*)*
+										encode_arg!($request_arg_name: $($request_arg_ty)+, buffer, fds);
+									)*)?
+									Ok($request_opcode)
 								}
-							)*)*
+							)*)?
+							Request::__Lifetime(never, _) => match never {},
 						}
 					}
+					#[allow(unused_mut, unused_variables, unreachable_code)]
+					fn decode_event<'a>(
+						opcode: u16,
+						mut args: &'a [u8],
+						fds: &mut Vec<FileDescriptor>,
+					) -> Result<Event<'a>, FromWireError> {
+						let event = match opcode {
+							$($(
+								$event_opcode => Event::$event_name$({
+									$($event_arg_name: decode_arg!($($event_arg_ty)+, args, fds)),*
+								})?,
+							)*)?
+							_ => return Err(FromWireError::UnknownOpcode(opcode)),
+						};
+						if !args.is_empty() {
+							return Err(FromWireError::TrailingBytes);
+						}
+
+						Ok(event)
+					}
 				}
 				impl From<$interface_name> for SomeObject {
 					fn from(obj: $interface_name) -> Self {
@@ -77,18 +187,130 @@ macro_rules! interfaces {
 			pub use $interface_ffi_name::$interface_name;
 		)*
 
+		/// An object of any interface.
+		#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 		pub enum SomeObject {
 			$($interface_name($interface_name)),*
 		}
 		impl SomeObject {
-			pub fn decode_event() -> SomeEvent {
-				todo!()
+			pub fn id(self) -> ObjectId {
+				match self {
+					$(Self::$interface_name(obj) => obj.id()),*
+				}
+			}
+			/// The name of this object's interface, e.g. `wl_display`.
+			pub fn interface(self) -> &'static str {
+				match self {
+					$(Self::$interface_name(_) => $interface_name::NAME),*
+				}
+			}
+			/// Decodes an event sent to this object.
+			pub fn decode_event<'a>(
+				self,
+				opcode: u16,
+				args: &'a [u8],
+				fds: &mut Vec<FileDescriptor>,
+			) -> Result<SomeEvent<'a>, FromWireError> {
+				match self {
+					$(Self::$interface_name(obj) => Ok(SomeEvent::$interface_name(
+						obj,
+						$interface_name::decode_event(opcode, args, fds)?,
+					))),*
+				}
 			}
 		}
-		pub enum SomeEvent {
-			$($interface_name($interface_ffi_name::Event)),*
+		/// An event sent to an object of any interface.
+		#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+		pub enum SomeEvent<'a> {
+			$($interface_name($interface_name, $interface_ffi_name::Event<'a>)),*
 		}
 	};
 }
 
-interfaces! {}
+crux_macros::wayland_protocols!(
+	"src/ui/display/wayland/protocols/wayland.xml",
+	"src/ui/display/wayland/protocols/xdg-shell.xml",
+);
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn generated() {
+		assert_eq!(WlSurface::NAME, "wl_surface");
+		assert_eq!(WlSurface::VERSION, 6);
+		assert_eq!(
+			xdg_surface::Error::from_code(4),
+			Some(xdg_surface::Error::InvalidSerial)
+		);
+		assert_eq!(xdg_surface::Error::from_code(0), None);
+
+		// Opcodes come from the order of messages in the protocol file
+		let mut buffer = Vec::new();
+		let request = wl_surface::Request::DamageBuffer {
+			x: 0,
+			y: 0,
+			width: 640,
+			height: 480,
+		};
+		let mut fds = Vec::new();
+		assert_eq!(
+			WlSurface::encode_request(&request, &mut buffer, &mut fds),
+			Ok(9)
+		);
+		assert_eq!(buffer.len(), 16);
+
+		// File descriptors are sent separately from the message's bytes
+		buffer.clear();
+		let request = wl_shm::Request::CreatePool {
+			id: ObjectId::new(3).unwrap(),
+			fd: FileDescriptor::STDIN,
+			size: 4096,
+		};
+		assert_eq!(
+			WlShm::encode_request(&request, &mut buffer, &mut fds),
+			Ok(0)
+		);
+		assert_eq!(buffer.len(), 8);
+		assert_eq!(fds, [FileDescriptor::STDIN]);
+
+		let args = 7u32.to_ne_bytes();
+		let mut fds = Vec::new();
+		assert_eq!(
+			XdgWmBase::decode_event(0, &args, &mut fds),
+			Ok(xdg_wm_base::Event::Ping { serial: 7 })
+		);
+		assert_eq!(
+			WlSurface::decode_event(2, &args, &mut fds),
+			Ok(wl_surface::Event::PreferredBufferScale { factor: 7 })
+		);
+		assert_eq!(
+			WlCompositor::decode_event(0, &[], &mut fds),
+			Err(FromWireError::UnknownOpcode(0))
+		);
+
+		// Object arguments can't be null unless they're nullable
+		assert_eq!(
+			WlSurface::decode_event(0, &[0; 4], &mut fds),
+			Err(FromWireError::UnexpectedNull)
+		);
+		let request = wl_surface::Request::Attach {
+			buffer: None,
+			x: 0,
+			y: 0,
+		};
+		buffer.clear();
+		assert_eq!(
+			WlSurface::encode_request(&request, &mut buffer, &mut fds),
+			Ok(1)
+		);
+		assert_eq!(buffer[..4], [0; 4]);
+	}
+}
@@ -0,0 +1,285 @@
+//! Shared memory buffers, which is how a client draws to a window without a
+//! GPU: the client writes pixels to memory that it shares with the
+//! compositor, then tells the compositor which part of the memory to show.
+//!
+//! ```rs
+//! let mut buffer = Buffer::new(&mut display, 640, 480, ShmFormat::Xrgb8888)?;
+//! buffer.pixels().fill(0xFF);
+//! window.present(&mut display, &mut buffer)?;
+//! ```
+//!
+//! The shared memory is a [`MemoryFile`], which is sent to the compositor
+//! and mapped into reserved virtual memory so it never moves, even if the pool
+//! grows.
+
+use {
+	super::{
+		Interface, WaylandDisplay, WaylandError,
+		interfaces::{SomeEvent, WlBuffer, WlShm, WlShmPool, wl_buffer, wl_shm, wl_shm_pool},
+	},
+	crate::{
+		rt::{
+			mem::{self, MemoryAmount, ReservedMemory},
+			os::unix::MemoryFile,
+		},
+		ui::draw::Canvas,
+	},
+};
+
+/// The layout of each pixel in a buffer. Every compositor supports these
+/// formats; others have to be checked for with `wl_shm.format` events.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShmFormat {
+	/// 32-bit pixels with alpha, stored as little-endian `0xAARRGGBB`.
+	Argb8888 = 0,
+	/// 32-bit pixels without alpha, stored as little-endian `0xXXRRGGBB`.
+	/// The `X` byte is ignored.
+	Xrgb8888 = 1,
+}
+impl ShmFormat {
+	/// How many bytes each pixel takes up.
+	pub const fn bytes_per_pixel(self) -> usize {
+		4
+	}
+}
+
+/// Memory shared with the compositor, which buffers can be created from.
+/// Most programs can use [`Buffer`] instead, which manages a pool for a
+/// single buffer.
+///
+/// The pool's memory is unmapped when this is dropped. Buffers created from it
+/// stay valid for the compositor, but the client can't draw to them anymore.
+pub struct ShmPool {
+	pool: WlShmPool,
+	file: MemoryFile,
+	/// The virtual memory reserved for the pool, the start of which the file
+	/// is mapped into.
+	mem: ReservedMemory,
+	len: usize,
+}
+impl ShmPool {
+	/// Creates a pool that's `len` bytes large. `to_reserve` is how large
+	/// the pool can grow; the memory is reserved up front so the pool never
+	/// moves.
+	pub fn new(
+		display: &mut WaylandDisplay,
+		len: usize,
+		to_reserve: MemoryAmount,
+	) -> Result<Self, WaylandError> {
+		let size = i32::try_from(len).or(Err(WaylandError::PoolTooSmall))?;
+		if len > to_reserve.amount_bytes() {
+			return Err(WaylandError::PoolTooSmall);
+		}
+		let shm: WlShm = display.get_or_bind()?;
+
+		let file = MemoryFile::new(c"crux-wl-shm")?;
+		file.set_len(len)?;
+		let mem = mem::reserve(to_reserve).or(Err(WaylandError::OutOfMemory))?;
+		let this = Self {
+			pool: display.create(),
+			file,
+			mem,
+			len,
+		};
+		this.map()?;
+
+		display.send(
+			shm,
+			wl_shm::Request::CreatePool {
+				id: this.pool.id(),
+				fd: this.file.fd(),
+				size,
+			},
+		)?;
+		// The file has to be open when it's sent
+		display.flush()?;
+
+		Ok(this)
+	}
+	/// Maps the file into the start of the reserved memory.
+	fn map(&self) -> Result<(), WaylandError> {
+		let mem = self
+			.mem
+			.select(MemoryAmount::ZERO, MemoryAmount::bytes(self.len))
+			.or(Err(WaylandError::PoolTooSmall))?;
+		unsafe { mem::map_file(mem, self.file.fd()) }.or(Err(WaylandError::OutOfMemory))
+	}
+
+	/// The pool's handle, e.g. for sending requests to it directly.
+	pub fn object(&self) -> WlShmPool {
+		self.pool
+	}
+	/// How many bytes large the pool is.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The pool's memory. Changes are visible to the compositor, so buffers
+	/// shouldn't be drawn to while the compositor is using them.
+	pub fn memory(&mut self) -> &mut [u8] {
+		unsafe { core::slice::from_raw_parts_mut(self.mem.base_ptr.as_ptr().cast(), self.len) }
+	}
+
+	/// Grows the pool to `len` bytes. Pools can't shrink, since buffers may
+	/// still be using their memory.
+	pub fn grow(&mut self, display: &mut WaylandDisplay, len: usize) -> Result<(), WaylandError> {
+		if len <= self.len {
+			return Ok(());
+		}
+		let size = i32::try_from(len).or(Err(WaylandError::PoolTooSmall))?;
+		if len > self.mem.amount.amount_bytes() {
+			return Err(WaylandError::PoolTooSmall);
+		}
+
+		self.file.set_len(len)?;
+		self.len = len;
+		self.map()?;
+		display.send(self.pool, wl_shm_pool::Request::Resize { size })
+	}
+
+	/// Creates a buffer that uses the pool's memory from `offset` onwards.
+	/// Rows of pixels are right after each other, with no padding.
+	pub fn create_buffer(
+		&mut self,
+		display: &mut WaylandDisplay,
+		offset: usize,
+		width: u32,
+		height: u32,
+		format: ShmFormat,
+	) -> Result<WlBuffer, WaylandError> {
+		let stride = width as usize * format.bytes_per_pixel();
+		let fits = (stride as u64 * height as u64)
+			.checked_add(offset as u64)
+			.is_some_and(|end| end <= self.len as u64);
+		if !fits {
+			return Err(WaylandError::PoolTooSmall);
+		}
+
+		// The pool is at most `i32::MAX` bytes, so these all fit
+		let buffer = display.create::<WlBuffer>();
+		display.send(
+			self.pool,
+			wl_shm_pool::Request::CreateBuffer {
+				id: buffer.id(),
+				offset: offset as i32,
+				width: width as i32,
+				height: height as i32,
+				stride: stride as i32,
+				format: format as u32,
+			},
+		)?;
+
+		Ok(buffer)
+	}
+
+	/// Destroys the pool. Buffers created from it stay valid.
+	pub fn destroy(self, display: &mut WaylandDisplay) -> Result<(), WaylandError> {
+		display.send(self.pool, wl_shm_pool::Request::Destroy)
+	}
+}
+impl Drop for ShmPool {
+	fn drop(&mut self) {
+		unsafe { mem::unreserve(self.mem) };
+	}
+}
+
+/// An image in shared memory that can be shown in a window with
+/// [`WaylandWindow::present`](super::WaylandWindow::present). See the
+/// [module-level docs](self).
+pub struct Buffer {
+	pool: ShmPool,
+	buffer: WlBuffer,
+	width: u32,
+	height: u32,
+	format: ShmFormat,
+	/// If the compositor is reading from the buffer.
+	busy: bool,
+}
+impl Buffer {
+	/// Creates a `width` by `height` buffer, with its own shared memory
+	/// pool. The pixels start out zeroed.
+	pub fn new(
+		display: &mut WaylandDisplay,
+		width: u32,
+		height: u32,
+		format: ShmFormat,
+	) -> Result<Self, WaylandError> {
+		let len = (width as usize)
+			.checked_mul(height as usize)
+			.and_then(|pixels| pixels.checked_mul(format.bytes_per_pixel()))
+			.ok_or(WaylandError::PoolTooSmall)?;
+		let mut pool = ShmPool::new(display, len, MemoryAmount::bytes(len).page_align())?;
+		let buffer = pool.create_buffer(display, 0, width, height, format)?;
+		// The compositor keeps the memory alive for the buffer, so the pool
+		// isn't needed anymore
+		display.send(pool.pool, wl_shm_pool::Request::Destroy)?;
+
+		Ok(Self {
+			pool,
+			buffer,
+			width,
+			height,
+			format,
+			busy: false,
+		})
+	}
+
+	/// The buffer's handle, e.g. for attaching it to a surface.
+	pub fn object(&self) -> WlBuffer {
+		self.buffer
+	}
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+	pub fn format(&self) -> ShmFormat {
+		self.format
+	}
+	/// How many bytes are between the start of each row of pixels.
+	pub fn stride(&self) -> usize {
+		self.width as usize * self.format.bytes_per_pixel()
+	}
+	/// The buffer's pixels, row by row, in its [`ShmFormat`].
+	///
+	/// The compositor reads from this memory after the buffer is presented,
+	/// until it sends a `wl_buffer.release` event. Drawing before then may
+	/// show a half-drawn frame; see [`Buffer::is_busy`].
+	pub fn pixels(&mut self) -> &mut [u8] {
+		self.pool.memory()
+	}
+	/// A [`Canvas`] for drawing to the buffer's pixels. See
+	/// [`Buffer::pixels`] for when it's safe to draw.
+	pub fn canvas(&mut self) -> Canvas<'_> {
+		let (width, height, stride) = (self.width, self.height, self.stride());
+		Canvas::new(self.pool.memory(), width, height, stride)
+	}
+
+	/// If the compositor may be reading from the buffer.
+	pub fn is_busy(&self) -> bool {
+		self.busy
+	}
+	pub(super) fn set_busy(&mut self) {
+		self.busy = true;
+	}
+	/// Updates the buffer for an event from [`WaylandDisplay::dispatch`].
+	/// Returns `true` if the event was for this buffer.
+	pub fn handle_event(&mut self, event: SomeEvent<'_>) -> bool {
+		match event {
+			SomeEvent::WlBuffer(buffer, wl_buffer::Event::Release) if buffer == self.buffer => {
+				self.busy = false;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	/// Destroys the buffer. It shouldn't be destroyed while it's attached to
+	/// a surface.
+	pub fn destroy(self, display: &mut WaylandDisplay) -> Result<(), WaylandError> {
+		display.send(self.buffer, wl_buffer::Request::Destroy)
+	}
+}
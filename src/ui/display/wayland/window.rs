@@ -0,0 +1,249 @@
+//! Windows, which are `xdg_toplevel` surfaces from the xdg-shell protocol.
+//!
+//! A window has to be configured by the compositor before anything can be
+//! drawn to it, so windows are driven by events:
+//!
+//! ```rs
+//! let mut window = WaylandWindow::new(&mut display, "Crux")?;
+//! let mut buffer = Buffer::new(&mut display, 640, 480, ShmFormat::Xrgb8888)?;
+//!
+//! while !window.close_requested() {
+//!     display.dispatch(|display, event| {
+//!         buffer.handle_event(event);
+//!         let configured = window.handle_event(display, event).unwrap();
+//!         if configured && !buffer.is_busy() {
+//!             buffer.pixels().fill(0xFF);
+//!             window.present(display, &mut buffer).unwrap();
+//!         }
+//!     })?;
+//! }
+//! ```
+//!
+//! [`EventLoop`](crate::ui::event::EventLoop) handles this for programs that
+//! only need [`Event`]s.
+
+use {
+	super::{
+		Buffer, Interface, WaylandDisplay, WaylandError,
+		interfaces::{
+			SomeEvent, WlCompositor, WlSurface, XdgSurface, XdgToplevel, XdgWmBase, wl_compositor,
+			wl_surface, xdg_surface, xdg_toplevel, xdg_wm_base,
+		},
+		wire::ObjectId,
+	},
+	crate::{
+		lang::take,
+		ui::event::{Event, WindowId},
+	},
+};
+
+/// The ID of the window whose surface is `surface`.
+pub(super) fn window_id(surface: ObjectId) -> WindowId {
+	WindowId(surface.get() as u64)
+}
+
+/// A top-level window. See the [module-level docs](self).
+#[derive(Debug)]
+pub struct WaylandWindow {
+	surface: WlSurface,
+	xdg_surface: XdgSurface,
+	toplevel: XdgToplevel,
+	/// The size from the last `xdg_toplevel.configure` event, which is applied
+	/// when its `xdg_surface.configure` event arrives.
+	pending_size: Option<(u32, u32)>,
+	size: Option<(u32, u32)>,
+	configured: bool,
+	close_requested: bool,
+}
+impl WaylandWindow {
+	/// Creates a window with the given title. It doesn't appear until a
+	/// buffer is presented to it, which can only happen after the compositor
+	/// configures it.
+	pub fn new(display: &mut WaylandDisplay, title: &str) -> Result<Self, WaylandError> {
+		let compositor: WlCompositor = display.get_or_bind()?;
+		let wm_base: XdgWmBase = display.get_or_bind()?;
+
+		let surface = display.create::<WlSurface>();
+		display.send(
+			compositor,
+			wl_compositor::Request::CreateSurface { id: surface.id() },
+		)?;
+		let xdg_surface = display.create::<XdgSurface>();
+		display.send(
+			wm_base,
+			xdg_wm_base::Request::GetXdgSurface {
+				id: xdg_surface.id(),
+				surface: surface.id(),
+			},
+		)?;
+		let toplevel = display.create::<XdgToplevel>();
+		display.send(
+			xdg_surface,
+			xdg_surface::Request::GetToplevel { id: toplevel.id() },
+		)?;
+		display.send(toplevel, xdg_toplevel::Request::SetTitle { title })?;
+		// Committing without a buffer asks the compositor to configure the
+		// window
+		display.send(surface, wl_surface::Request::Commit)?;
+
+		Ok(Self {
+			surface,
+			xdg_surface,
+			toplevel,
+			pending_size: None,
+			size: None,
+			configured: false,
+			close_requested: false,
+		})
+	}
+
+	/// The ID the window's [`Event`]s are sent with.
+	pub fn id(&self) -> WindowId {
+		window_id(self.surface.id())
+	}
+	/// The window's surface, e.g. for sending requests to it directly.
+	pub fn surface(&self) -> WlSurface {
+		self.surface
+	}
+	/// The window's `xdg_toplevel`, e.g. for sending requests to it directly.
+	pub fn toplevel(&self) -> XdgToplevel {
+		self.toplevel
+	}
+	pub fn set_title(
+		&mut self,
+		display: &mut WaylandDisplay,
+		title: &str,
+	) -> Result<(), WaylandError> {
+		display.send(self.toplevel, xdg_toplevel::Request::SetTitle { title })
+	}
+
+	/// The size the compositor wants the window to be, or `None` if it's up
+	/// to the program.
+	pub fn size(&self) -> Option<(u32, u32)> {
+		self.size
+	}
+	/// If the compositor has configured the window. Buffers can only be
+	/// presented once it has.
+	pub fn is_configured(&self) -> bool {
+		self.configured
+	}
+	/// If the user asked to close the window, e.g. by clicking its close
+	/// button. The window stays open until it's destroyed.
+	pub fn close_requested(&self) -> bool {
+		self.close_requested
+	}
+
+	/// Updates the window for an event from [`WaylandDisplay::dispatch`].
+	/// Returns `true` if the window was just configured, in which case it
+	/// should be redrawn.
+	pub fn handle_event(
+		&mut self,
+		display: &mut WaylandDisplay,
+		event: SomeEvent<'_>,
+	) -> Result<bool, WaylandError> {
+		match event {
+			SomeEvent::XdgToplevel(
+				toplevel,
+				xdg_toplevel::Event::Configure { width, height, .. },
+			) if toplevel == self.toplevel => {
+				// Zero (or a nonsense negative size) means the program picks
+				// the size
+				self.pending_size = match (u32::try_from(width), u32::try_from(height)) {
+					(Ok(width @ 1..), Ok(height @ 1..)) => Some((width, height)),
+					_ => None,
+				};
+			}
+			SomeEvent::XdgToplevel(toplevel, xdg_toplevel::Event::Close)
+				if toplevel == self.toplevel =>
+			{
+				self.close_requested = true
+			}
+			SomeEvent::XdgSurface(xdg_surface, xdg_surface::Event::Configure { serial })
+				if xdg_surface == self.xdg_surface =>
+			{
+				display.send(
+					self.xdg_surface,
+					xdg_surface::Request::AckConfigure { serial },
+				)?;
+				self.size = take(&mut self.pending_size);
+				self.configured = true;
+				return Ok(true);
+			}
+			_ => {}
+		}
+
+		Ok(false)
+	}
+
+	/// Like [`WaylandWindow::handle_event`], but calls `handler` with the
+	/// [`Event`]s the event translates to.
+	///
+	/// The window is resized when it's configured with a new size; if the
+	/// compositor lets the program pick the size, there's no
+	/// [`Event::Resized`].
+	pub fn translate_event(
+		&mut self,
+		display: &mut WaylandDisplay,
+		event: SomeEvent<'_>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) -> Result<(), WaylandError> {
+		if let SomeEvent::XdgToplevel(toplevel, xdg_toplevel::Event::Close) = event
+			&& toplevel == self.toplevel
+		{
+			handler(self.id(), Event::CloseRequested);
+		}
+
+		let old_size = self.size;
+		if self.handle_event(display, event)? {
+			if let Some((width, height)) = self.size
+				&& self.size != old_size
+			{
+				handler(self.id(), Event::Resized { width, height });
+			}
+			handler(self.id(), Event::RedrawRequested);
+		}
+
+		Ok(())
+	}
+
+	/// Shows `buffer` in the window. The compositor reads from the buffer
+	/// until it releases it, so it's marked as busy.
+	pub fn present(
+		&mut self,
+		display: &mut WaylandDisplay,
+		buffer: &mut Buffer,
+	) -> Result<(), WaylandError> {
+		if !self.configured {
+			return Err(WaylandError::Unconfigured);
+		}
+
+		display.send(
+			self.surface,
+			wl_surface::Request::Attach {
+				buffer: Some(buffer.object().id()),
+				x: 0,
+				y: 0,
+			},
+		)?;
+		display.send(
+			self.surface,
+			wl_surface::Request::Damage {
+				x: 0,
+				y: 0,
+				width: i32::MAX,
+				height: i32::MAX,
+			},
+		)?;
+		display.send(self.surface, wl_surface::Request::Commit)?;
+		buffer.set_busy();
+
+		Ok(())
+	}
+
+	/// Closes the window.
+	pub fn destroy(self, display: &mut WaylandDisplay) -> Result<(), WaylandError> {
+		display.send(self.toplevel, xdg_toplevel::Request::Destroy)?;
+		display.send(self.xdg_surface, xdg_surface::Request::Destroy)?;
+		display.send(self.surface, wl_surface::Request::Destroy)
+	}
+}
@@ -3,7 +3,10 @@
 //!
 //! Wayland docs: https://wayland.freedesktop.org/docs/html/ch04.html#sect-Protocol-Wire-Format
 
-use {crate::lang::Infallible, core::iter::Extend};
+use {
+	crate::{lang::Infallible, rt::os::unix::FileDescriptor},
+	core::{iter::Extend, num::NonZero},
+};
 
 pub trait ToWire: Sized {
 	type Error;
@@ -13,18 +16,80 @@ pub trait ToWire: Sized {
 pub trait FromWire<'a>: Sized {
 	type Error;
 
+	/// Decodes a value from the start of `buffer`. Returns how many bytes the
+	/// value took up, and the value.
 	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error>;
 }
 
+/// An error from encoding a value.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ToWireError {
+	/// Strings can't contain nul bytes, because they're nul-terminated.
+	InteriorNullByte,
+	/// The value is too large to fit in a message.
+	TooLarge,
+}
+impl From<Infallible> for ToWireError {
+	fn from(value: Infallible) -> Self {
+		match value {}
+	}
+}
+
+/// An error from decoding a value.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FromWireError {
+	/// The message ended before the value did.
+	UnexpectedEnd,
+	/// The message had bytes left over after decoding all of its arguments.
+	TrailingBytes,
+	/// A string wasn't valid UTF-8.
+	InvalidUtf8,
+	/// A string didn't end with a nul byte.
+	MissingNullByte,
+	/// A non-nullable argument was null.
+	UnexpectedNull,
+	/// The message's opcode doesn't exist in its object's interface.
+	UnknownOpcode(u16),
+	/// A message's header said it was smaller than the header itself.
+	InvalidSize(u16),
+	/// A message had a file descriptor argument, but no more file descriptors
+	/// were received.
+	MissingFileDescriptor,
+}
+
+/// Decodes the next argument in `args`, then moves `args` past it.
+pub fn read_arg<'a, T: FromWire<'a>>(args: &mut &'a [u8]) -> Result<T, FromWireError>
+where
+	FromWireError: From<T::Error>,
+{
+	let (len, value) = T::from_wire(args)?;
+	*args = &args[len as usize..];
+	Ok(value)
+}
+
+/// Takes the next file descriptor received from the compositor. File
+/// descriptors are sent alongside messages instead of in their bytes, in the
+/// order they appear in messages.
+pub fn read_fd(fds: &mut Vec<FileDescriptor>) -> Result<FileDescriptor, FromWireError> {
+	if fds.is_empty() {
+		return Err(FromWireError::MissingFileDescriptor);
+	}
+	Ok(fds.remove(0))
+}
+
+/// Strings and arrays are padded with zeroes to a multiple of 4 bytes.
+fn padded_len(len: usize) -> usize {
+	len.next_multiple_of(4)
+}
+
 impl FromWire<'_> for u32 {
-	type Error = Infallible;
+	type Error = FromWireError;
 
 	fn from_wire(buffer: &[u8]) -> Result<(u16, Self), Self::Error> {
-		let bytes = &buffer[..4];
-		Ok((
-			4,
-			Self::from_ne_bytes(unsafe { bytes.try_into().unwrap_unchecked() }),
-		))
+		let Some(bytes) = buffer.first_chunk() else {
+			return Err(FromWireError::UnexpectedEnd);
+		};
+		Ok((4, Self::from_ne_bytes(*bytes)))
 	}
 }
 impl ToWire for u32 {
@@ -36,14 +101,13 @@ impl ToWire for u32 {
 	}
 }
 impl FromWire<'_> for i32 {
-	type Error = Infallible;
+	type Error = FromWireError;
 
 	fn from_wire(buffer: &[u8]) -> Result<(u16, Self), Self::Error> {
-		let bytes = &buffer[..4];
-		Ok((
-			4,
-			Self::from_ne_bytes(unsafe { bytes.try_into().unwrap_unchecked() }),
-		))
+		let Some(bytes) = buffer.first_chunk() else {
+			return Err(FromWireError::UnexpectedEnd);
+		};
+		Ok((4, Self::from_ne_bytes(*bytes)))
 	}
 }
 impl ToWire for i32 {
@@ -55,44 +119,470 @@ impl ToWire for i32 {
 	}
 }
 
+/// A signed 24.8 fixed-point number: the upper 24 bits are the integer part,
+/// and the lower 8 bits are the fraction. Used for things like pointer
+/// coordinates.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Fixed(i32);
+impl Fixed {
+	pub const ZERO: Self = Self(0);
+
+	/// Creates a number from its raw bits, as sent on the wire.
+	pub const fn from_bits(bits: i32) -> Self {
+		Self(bits)
+	}
+	/// The number's raw bits, as sent on the wire.
+	pub const fn to_bits(self) -> i32 {
+		self.0
+	}
+	/// Converts an integer to a fixed-point number. Integers that don't fit
+	/// in 24 bits wrap around.
+	pub const fn from_int(int: i32) -> Self {
+		Self(int.wrapping_shl(8))
+	}
+	/// The integer part of the number, rounded towards negative infinity.
+	pub const fn to_int(self) -> i32 {
+		self.0 >> 8
+	}
+	/// Converts a float to a fixed-point number, rounding towards zero.
+	/// Floats that are out of range saturate.
+	pub fn from_f64(float: f64) -> Self {
+		Self((float * 256.0) as i32)
+	}
+	pub fn to_f64(self) -> f64 {
+		self.0 as f64 / 256.0
+	}
+}
+impl FromWire<'_> for Fixed {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &[u8]) -> Result<(u16, Self), Self::Error> {
+		let (len, bits) = i32::from_wire(buffer)?;
+		Ok((len, Self(bits)))
+	}
+}
+impl ToWire for Fixed {
+	type Error = Infallible;
+
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		self.0.to_wire(buffer)
+	}
+}
+
+/// The ID of an object, which is how messages refer to it. Object arguments
+/// are 0 when they're null, so IDs are never 0.
+///
+/// `new_id` arguments are also IDs: the client (or compositor) picks the ID
+/// for the object, and sends it in the message that creates the object.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ObjectId(NonZero<u32>);
+impl ObjectId {
+	/// The `wl_display` singleton, which always exists.
+	pub const DISPLAY: Self = Self(NonZero::new(1).unwrap());
+
+	/// Returns `None` if `id` is 0, which means null.
+	pub const fn new(id: u32) -> Option<Self> {
+		match NonZero::new(id) {
+			Some(id) => Some(Self(id)),
+			None => None,
+		}
+	}
+	pub const fn get(self) -> u32 {
+		self.0.get()
+	}
+}
+impl FromWire<'_> for Option<ObjectId> {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &[u8]) -> Result<(u16, Self), Self::Error> {
+		let (len, id) = u32::from_wire(buffer)?;
+		Ok((len, ObjectId::new(id)))
+	}
+}
+impl FromWire<'_> for ObjectId {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &[u8]) -> Result<(u16, Self), Self::Error> {
+		match Option::<ObjectId>::from_wire(buffer)? {
+			(len, Some(id)) => Ok((len, id)),
+			(_, None) => Err(FromWireError::UnexpectedNull),
+		}
+	}
+}
+impl ToWire for Option<ObjectId> {
+	type Error = Infallible;
+
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		self.map_or(0, ObjectId::get).to_wire(buffer)
+	}
+}
+impl ToWire for ObjectId {
+	type Error = Infallible;
+
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		self.get().to_wire(buffer)
+	}
+}
+
+impl<'a> FromWire<'a> for Option<&'a str> {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error> {
+		// The length includes the nul terminator, so only null strings are
+		// empty
+		let (_, len) = u32::from_wire(buffer)?;
+		if len == 0 {
+			return Ok((4, None));
+		}
+
+		let padded = padded_len(len as usize);
+		let Some(bytes) = buffer.get(4..4 + padded) else {
+			return Err(FromWireError::UnexpectedEnd);
+		};
+		let Some((0, str)) = bytes[..len as usize].split_last() else {
+			return Err(FromWireError::MissingNullByte);
+		};
+		let str = crate::text::str_from_utf8(str).or(Err(FromWireError::InvalidUtf8))?;
+
+		Ok(((4 + padded) as u16, Some(str)))
+	}
+}
 impl<'a> FromWire<'a> for &'a str {
-	type Error = core::str::Utf8Error;
+	type Error = FromWireError;
 
 	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error> {
-		let Ok((_, mut len)) = u32::from_wire(buffer);
-		let str = crate::text::str_from_utf8(&buffer[4..len as usize + 4])?;
+		match Option::<&str>::from_wire(buffer)? {
+			(len, Some(str)) => Ok((len, str)),
+			(_, None) => Err(FromWireError::UnexpectedNull),
+		}
+	}
+}
+impl ToWire for Option<&str> {
+	type Error = ToWireError;
 
-		// strings must be padded to 4 bytes
-		if !len.is_multiple_of(4) {
-			len += len % 4;
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		let Some(str) = self else {
+			let Ok(()) = 0u32.to_wire(buffer);
+			return Ok(());
+		};
+		if str.contains('\0') {
+			return Err(ToWireError::InteriorNullByte);
 		}
+		let len: u32 = (str.len() + 1).try_into().or(Err(ToWireError::TooLarge))?;
+
+		let Ok(()) = len.to_wire(buffer);
+		buffer.extend(str.bytes());
+		// The padding includes the nul terminator
+		let padding = padded_len(len as usize) - str.len();
+		buffer.extend(core::iter::repeat_n(0, padding));
+
+		Ok(())
+	}
+}
+impl ToWire for &str {
+	type Error = ToWireError;
 
-		Ok((4 + len as u16, str))
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		Some(*self).to_wire(buffer)
 	}
 }
+
+impl<'a> FromWire<'a> for &'a [u8] {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error> {
+		let (_, len) = u32::from_wire(buffer)?;
+		let padded = padded_len(len as usize);
+		let Some(bytes) = buffer.get(4..4 + padded) else {
+			return Err(FromWireError::UnexpectedEnd);
+		};
+
+		Ok(((4 + padded) as u16, &bytes[..len as usize]))
+	}
+}
+impl ToWire for &[u8] {
+	type Error = ToWireError;
+
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		let len: u32 = self.len().try_into().or(Err(ToWireError::TooLarge))?;
+
+		let Ok(()) = len.to_wire(buffer);
+		buffer.extend(self.iter().copied());
+		buffer.extend(core::iter::repeat_n(0, padded_len(self.len()) - self.len()));
+
+		Ok(())
+	}
+}
+
+/// A `new_id` argument that doesn't specify which interface it creates, like
+/// in `wl_registry.bind`. The interface and its version are sent along with
+/// the new object's ID.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub enum StringToWireError {
-	InteriorNullByte,
-	TooLarge,
+pub struct UntypedNewId<'a> {
+	pub interface: &'a str,
+	pub version: u32,
+	pub id: ObjectId,
 }
-impl ToWire for &str {
-	type Error = StringToWireError;
+impl<'a> FromWire<'a> for UntypedNewId<'a> {
+	type Error = FromWireError;
+
+	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error> {
+		let mut args = buffer;
+		let this = Self {
+			interface: read_arg(&mut args)?,
+			version: read_arg(&mut args)?,
+			id: read_arg(&mut args)?,
+		};
+
+		Ok(((buffer.len() - args.len()) as u16, this))
+	}
+}
+impl ToWire for UntypedNewId<'_> {
+	type Error = ToWireError;
 
 	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
-		let mut len: u32 = self.len().try_into().or(Err(StringToWireError::TooLarge))?;
+		self.interface.to_wire(buffer)?;
+		self.version.to_wire(buffer)?;
+		self.id.to_wire(buffer)?;
+		Ok(())
+	}
+}
 
-		if self.contains('0') {
-			return Err(StringToWireError::InteriorNullByte);
-		}
+/// A message between the client and compositor: a request if it's sent by
+/// the client, or an event if it's sent by the compositor.
+///
+/// On the wire, every message starts with a header: the object ID, then the
+/// message's size in bytes (in the upper 16 bits) and its opcode (in the lower
+/// 16 bits). The size includes the header.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Message<'a> {
+	/// The object the message was sent to, or is from.
+	pub object: ObjectId,
+	/// Which of the interface's requests or events this is.
+	pub opcode: u16,
+	/// The message's encoded arguments.
+	pub args: &'a [u8],
+}
+impl Message<'_> {
+	pub const HEADER_SIZE: usize = 8;
+
+	/// Encodes the header of a message with `args_len` bytes of arguments.
+	pub fn encode_header(
+		object: ObjectId,
+		opcode: u16,
+		args_len: usize,
+	) -> Result<[u8; 8], ToWireError> {
+		let size = u16::try_from(Self::HEADER_SIZE + args_len).or(Err(ToWireError::TooLarge))?;
+
+		let mut header = [0; 8];
+		header[..4].copy_from_slice(&object.get().to_ne_bytes());
+		header[4..].copy_from_slice(&((size as u32) << 16 | opcode as u32).to_ne_bytes());
+		Ok(header)
+	}
+}
+impl<'a> FromWire<'a> for Message<'a> {
+	type Error = FromWireError;
 
-		// strings must be padded to 4 bytes
-		if !len.is_multiple_of(4) {
-			len += len % 4;
+	/// Decodes the message at the start of `buffer`. Errors with
+	/// [`FromWireError::UnexpectedEnd`] if `buffer` doesn't have the whole
+	/// message yet.
+	fn from_wire(buffer: &'a [u8]) -> Result<(u16, Self), Self::Error> {
+		let mut header = buffer;
+		let object = read_arg(&mut header)?;
+		let size_opcode: u32 = read_arg(&mut header)?;
+		let (size, opcode) = ((size_opcode >> 16) as u16, size_opcode as u16);
+		if (size as usize) < Self::HEADER_SIZE {
+			return Err(FromWireError::InvalidSize(size));
 		}
+		let Some(args) = buffer.get(Self::HEADER_SIZE..size as usize) else {
+			return Err(FromWireError::UnexpectedEnd);
+		};
 
-		len.to_wire(buffer);
-		buffer.extend(self.as_bytes().iter().copied());
+		Ok((
+			size,
+			Self {
+				object,
+				opcode,
+				args,
+			},
+		))
+	}
+}
+impl ToWire for Message<'_> {
+	type Error = ToWireError;
 
+	fn to_wire(&self, buffer: &mut impl Extend<u8>) -> Result<(), Self::Error> {
+		buffer.extend(Self::encode_header(
+			self.object,
+			self.opcode,
+			self.args.len(),
+		)?);
+		buffer.extend(self.args.iter().copied());
 		Ok(())
 	}
 }
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn strings() {
+		for (str, len) in [("", 8), ("abc", 8), ("abcd", 12), ("wl_shm", 12)] {
+			let mut buffer = Vec::new();
+			str.to_wire(&mut buffer).unwrap();
+			assert_eq!(buffer.len(), len);
+			assert_eq!(buffer[..4], (str.len() as u32 + 1).to_ne_bytes());
+			assert!(buffer[4 + str.len()..].iter().all(|byte| *byte == 0));
+			assert_eq!(<&str>::from_wire(&buffer), Ok((len as u16, str)));
+		}
+
+		let mut buffer = Vec::new();
+		None::<&str>.to_wire(&mut buffer).unwrap();
+		assert_eq!(buffer, [0; 4]);
+		assert_eq!(Option::<&str>::from_wire(&buffer), Ok((4, None)));
+		assert_eq!(
+			<&str>::from_wire(&buffer),
+			Err(FromWireError::UnexpectedNull)
+		);
+
+		assert_eq!(
+			"a\0b".to_wire(&mut buffer),
+			Err(ToWireError::InteriorNullByte)
+		);
+		assert_eq!(
+			<&str>::from_wire(&[5, 0, 0, 0, b'a']),
+			Err(FromWireError::UnexpectedEnd)
+		);
+	}
+
+	#[test]
+	fn arrays() {
+		let mut buffer = Vec::new();
+		let states: &[u8] = &[1, 0, 0, 0, 4, 0, 0, 0];
+		states.to_wire(&mut buffer).unwrap();
+		assert_eq!(buffer.len(), 12);
+		assert_eq!(<&[u8]>::from_wire(&buffer), Ok((12, states)));
+
+		buffer.clear();
+		[1u8, 2, 3].as_slice().to_wire(&mut buffer).unwrap();
+		assert_eq!(buffer[..4], 3u32.to_ne_bytes());
+		assert_eq!(buffer[4..], [1, 2, 3, 0]);
+		assert_eq!(
+			<&[u8]>::from_wire(&buffer[..6]),
+			Err(FromWireError::UnexpectedEnd)
+		);
+	}
+
+	#[test]
+	fn fixed() {
+		assert_eq!(Fixed::from_int(3).to_bits(), 3 << 8);
+		assert_eq!(Fixed::from_f64(1.5).to_bits(), 0x180);
+		assert_eq!(Fixed::from_f64(-1.5).to_int(), -2);
+		assert_eq!(Fixed::from_bits(-0x80).to_f64(), -0.5);
+		assert_eq!(Fixed::from_f64(f64::MAX).to_bits(), i32::MAX);
+
+		let mut buffer = Vec::new();
+		let Ok(()) = Fixed::from_f64(-2.25).to_wire(&mut buffer);
+		assert_eq!(buffer, (-0x240i32).to_ne_bytes());
+		assert_eq!(Fixed::from_wire(&buffer), Ok((4, Fixed::from_f64(-2.25))));
+	}
+
+	#[test]
+	fn object_ids() {
+		let mut buffer = Vec::new();
+		let Ok(()) = ObjectId::DISPLAY.to_wire(&mut buffer);
+		let Ok(()) = None::<ObjectId>.to_wire(&mut buffer);
+		assert_eq!(buffer[..4], 1u32.to_ne_bytes());
+		assert_eq!(buffer[4..], [0; 4]);
+
+		let mut args = &buffer[..];
+		assert_eq!(read_arg(&mut args), Ok(Some(ObjectId::DISPLAY)));
+		assert_eq!(read_arg::<Option<ObjectId>>(&mut args), Ok(None));
+		assert_eq!(
+			ObjectId::from_wire(&buffer[4..]),
+			Err(FromWireError::UnexpectedNull)
+		);
+		assert_eq!(ObjectId::new(0), None);
+	}
+
+	#[test]
+	fn file_descriptors() {
+		let mut fds = Vec::from([FileDescriptor::STDOUT, FileDescriptor::STDERR]);
+		assert_eq!(read_fd(&mut fds), Ok(FileDescriptor::STDOUT));
+		assert_eq!(read_fd(&mut fds), Ok(FileDescriptor::STDERR));
+		assert_eq!(read_fd(&mut fds), Err(FromWireError::MissingFileDescriptor));
+	}
+
+	/// Messages from a real session (on a little-endian machine), as captured
+	/// from the socket.
+	#[test]
+	#[cfg(target_endian = "little")]
+	fn captured_messages() {
+		// wl_registry@2.global(1, "wl_compositor", 6), then
+		// wl_callback@3.done(42)
+		let events: &[u8] = &[
+			0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x24, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0e, 0x00,
+			0x00, 0x00, b'w', b'l', b'_', b'c', b'o', b'm', b'p', b'o', b's', b'i', b't', b'o',
+			b'r', 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x0c, 0x00, 0x2a, 0x00, 0x00, 0x00,
+		];
+		let (len, global) = Message::from_wire(events).unwrap();
+		assert_eq!(len, 36);
+		assert_eq!(global.object, ObjectId::new(2).unwrap());
+		assert_eq!(global.opcode, 0);
+		let mut args = global.args;
+		assert_eq!(read_arg(&mut args), Ok(1u32));
+		assert_eq!(read_arg(&mut args), Ok("wl_compositor"));
+		assert_eq!(read_arg(&mut args), Ok(6u32));
+		assert!(args.is_empty());
+
+		let (len, done) = Message::from_wire(&events[36..]).unwrap();
+		assert_eq!(len, 12);
+		assert_eq!(done.object, ObjectId::new(3).unwrap());
+		assert_eq!(done.args, 42u32.to_ne_bytes());
+
+		// Messages are only decoded once all of their bytes have arrived
+		assert_eq!(
+			Message::from_wire(&events[..35]),
+			Err(FromWireError::UnexpectedEnd)
+		);
+		assert_eq!(
+			Message::from_wire(&[1, 0, 0, 0, 0, 0, 4, 0]),
+			Err(FromWireError::InvalidSize(4))
+		);
+
+		// wl_registry@2.bind(1, "wl_compositor", 6, new id 4)
+		let mut args = Vec::new();
+		let Ok(()) = 1u32.to_wire(&mut args);
+		UntypedNewId {
+			interface: "wl_compositor",
+			version: 6,
+			id: ObjectId::new(4).unwrap(),
+		}
+		.to_wire(&mut args)
+		.unwrap();
+		let mut request = Vec::new();
+		Message {
+			object: ObjectId::new(2).unwrap(),
+			opcode: 0,
+			args: &args,
+		}
+		.to_wire(&mut request)
+		.unwrap();
+		assert_eq!(
+			request,
+			[
+				0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0e, 0x00,
+				0x00, 0x00, b'w', b'l', b'_', b'c', b'o', b'm', b'p', b'o', b's', b'i', b't', b'o',
+				b'r', 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+			]
+		);
+	}
+}
@@ -0,0 +1,768 @@
+//! A display backend for Windows, which uses the Win32 API.
+//!
+//! Each [`Win32Window`] is an `HWND`. Programs draw to a [`Buffer`] in
+//! memory, then present it, which copies it to the window with
+//! `StretchDIBits`:
+//!
+//! ```rs
+//! let mut display = Win32Display::connect()?;
+//! let mut window = Win32Window::new(&mut display, "Crux", 640, 480)?;
+//! let mut buffer = Buffer::new(640, 480);
+//!
+//! while !window.close_requested() {
+//!     display.dispatch()?;
+//!     for event in window.events() {
+//!         println!("{event:?}");
+//!     }
+//!     if window.needs_redraw() {
+//!         let (width, height) = window.size();
+//!         buffer.resize(width, height);
+//!         buffer.pixels().fill(0xFF);
+//!         window.present(&buffer)?;
+//!     }
+//! }
+//! ```
+//!
+//! Messages are translated into [`Event`]s, which are queued on the window
+//! they're for. [`EventLoop`](crate::ui::event::EventLoop) takes care of
+//! dispatching and taking the events for programs that only need those.
+//!
+//! Buffers store pixels the same way as Wayland's `ShmFormat::Xrgb8888`, so
+//! drawing code can be shared between the backends.
+//!
+//! Windows can only be used from the thread that created them, since Windows
+//! sends a window's messages to that thread.
+//!
+//! The display implements [`Clipboard`], which works without any windows.
+//!
+//! Win32 docs: https://learn.microsoft.com/en-us/windows/win32/winmsg/windowing
+
+use crate::{
+	ffi::c_void,
+	lang::{size_of, zeroed},
+	rt::{
+		os::{self, win32::*},
+		time::Duration,
+	},
+	text::wide,
+	ui::{
+		clipboard::Clipboard,
+		display::Display,
+		draw::Canvas,
+		event::{Event, Key, KeyEvent, PointerButton, WindowId},
+	},
+};
+
+/// The name of the window class every Crux window uses, as a nul-terminated
+/// UTF-16 string.
+const CLASS_NAME: [u16; 11] = {
+	let name = b"CruxWindow\0";
+	let mut wide = [0; 11];
+	let mut idx = 0;
+	while idx < name.len() {
+		wide[idx] = name[idx] as u16;
+		idx += 1;
+	}
+	wide
+};
+/// The style of every Crux window: a normal window with a title bar, borders,
+/// and minimize/maximize buttons.
+const WINDOW_STYLE: u32 = WS_OVERLAPPEDWINDOW;
+
+/// The Win32 display. Win32 doesn't have a display server to connect to, so
+/// this just registers the window class Crux's windows use.
+#[derive(Debug)]
+pub struct Win32Display {
+	/// The module windows are created by: the program's executable.
+	instance: NonNull<c_void>,
+	/// A hidden window that owns the text this program puts on the
+	/// clipboard. Created the first time the clipboard is used.
+	clipboard_owner: Option<NonNull<c_void>>,
+}
+impl Win32Display {
+	/// Registers Crux's window class. This can be called more than once; the
+	/// class is only registered the first time.
+	pub fn connect() -> Result<Self, os::Error> {
+		let instance = unsafe { GetModuleHandleW(None) }.ok_or_else(os::Error::last)?;
+
+		let class = WindowClassExW {
+			size: size_of::<WindowClassExW>() as u32,
+			// Redraw the whole window when it's resized
+			style: CS_HREDRAW | CS_VREDRAW,
+			window_proc,
+			class_extra: 0,
+			window_extra: 0,
+			instance: Some(instance),
+			icon: None,
+			cursor: unsafe { LoadCursorW(None, IDC_ARROW) },
+			background: None,
+			menu_name: None,
+			class_name: NonNullConst::from_ref(&CLASS_NAME[0]),
+			small_icon: None,
+		};
+		if unsafe { RegisterClassExW(NonNullConst::from_ref(&class)) } == 0 {
+			let err = os::Error::last();
+			if err.raw() != ERROR_CLASS_ALREADY_EXISTS {
+				return Err(err);
+			}
+		}
+
+		Ok(Self {
+			instance,
+			clipboard_owner: None,
+		})
+	}
+
+	/// Waits for a message from Windows, then handles every message that's
+	/// queued. Windows update their state as messages are handled, so check
+	/// [`Win32Window::needs_redraw`] and [`Win32Window::close_requested`]
+	/// after calling this.
+	pub fn dispatch(&mut self) -> Result<(), os::Error> {
+		let mut msg: Msg = unsafe { zeroed() };
+		if unsafe { GetMessageW(NonNull::from(&mut msg), None, 0, 0) } == -1 {
+			return Err(os::Error::last());
+		}
+		loop {
+			unsafe {
+				TranslateMessage(NonNullConst::from_ref(&msg));
+				DispatchMessageW(NonNullConst::from_ref(&msg));
+			}
+			if unsafe { PeekMessageW(NonNull::from(&mut msg), None, 0, 0, PM_REMOVE) } == 0 {
+				break;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Opens the clipboard, creating the window that owns it if needed.
+	/// Fails with [`os::ErrorKind::PermissionDenied`] if another program has
+	/// it open.
+	fn open_clipboard(&mut self) -> Result<ClipboardLock, os::Error> {
+		let owner = match self.clipboard_owner {
+			Some(owner) => owner,
+			None => {
+				// The window has no state, so `window_proc` passes all of its
+				// messages to `DefWindowProcW`
+				let owner = unsafe {
+					CreateWindowExW(
+						0,
+						NonNullConst::from_ref(&CLASS_NAME[0]),
+						NonNullConst::from_ref(&CLASS_NAME[0]),
+						0,
+						0,
+						0,
+						0,
+						0,
+						NonNull::new(HWND_MESSAGE as *mut c_void),
+						None,
+						Some(self.instance),
+						None,
+					)
+				}
+				.ok_or_else(os::Error::last)?;
+				*self.clipboard_owner.insert(owner)
+			}
+		};
+
+		if unsafe { OpenClipboard(Some(owner)) } == 0 {
+			return Err(os::Error::last());
+		}
+		Ok(ClipboardLock)
+	}
+}
+impl Drop for Win32Display {
+	fn drop(&mut self) {
+		if let Some(owner) = self.clipboard_owner {
+			unsafe { DestroyWindow(owner) };
+		}
+	}
+}
+impl Display for Win32Display {
+	type WindowHandle = Win32Window;
+	type Error = os::Error;
+
+	fn new() -> Result<Self, Self::Error> {
+		Self::connect()
+	}
+	fn create_window(
+		&mut self,
+		title: &str,
+		width: u32,
+		height: u32,
+	) -> Result<Self::WindowHandle, Self::Error> {
+		Win32Window::new(self, title, width, height)
+	}
+	fn window_id(window: &Self::WindowHandle) -> WindowId {
+		window.id()
+	}
+	fn dispatch_events(
+		&mut self,
+		windows: &mut [Self::WindowHandle],
+		timeout: Option<Duration>,
+		handler: &mut dyn FnMut(WindowId, Event),
+	) -> Result<(), Self::Error> {
+		const INFINITE: u32 = u32::MAX;
+
+		// Windows get messages while they're being created, so there may
+		// already be events to handle
+		let queued = windows
+			.iter()
+			.any(|window| !window.state().events.is_empty());
+		if !queued {
+			let timeout = match timeout {
+				// Rounded up, so short timeouts don't turn into busy loops
+				Some(timeout) => timeout
+					.as_nanos()
+					.div_ceil(1_000_000)
+					.min((INFINITE - 1) as u128) as u32,
+				None => INFINITE,
+			};
+			match unsafe { MsgWaitForMultipleObjects(0, None, 0, timeout, QS_ALLINPUT) } {
+				WAIT_FAILED => return Err(os::Error::last()),
+				WAIT_TIMEOUT => return Ok(()),
+				_ => {}
+			}
+		}
+
+		let mut msg: Msg = unsafe { zeroed() };
+		while unsafe { PeekMessageW(NonNull::from(&mut msg), None, 0, 0, PM_REMOVE) } != 0 {
+			unsafe {
+				TranslateMessage(NonNullConst::from_ref(&msg));
+				DispatchMessageW(NonNullConst::from_ref(&msg));
+			}
+		}
+		for window in windows {
+			let id = window.id();
+			for event in window.events() {
+				handler(id, event);
+			}
+		}
+
+		Ok(())
+	}
+}
+impl Clipboard for Win32Display {
+	fn clipboard_text(&mut self) -> Result<Option<String>, Self::Error> {
+		let _lock = self.open_clipboard()?;
+		if unsafe { IsClipboardFormatAvailable(CF_UNICODETEXT) } == 0 {
+			return Ok(None);
+		}
+		// The clipboard owns the data, so it isn't freed here
+		let data = unsafe { GetClipboardData(CF_UNICODETEXT) }.ok_or_else(os::Error::last)?;
+		let ptr = unsafe { GlobalLock(data) }.ok_or_else(os::Error::last)?;
+		let wide = unsafe {
+			core::slice::from_raw_parts(ptr.as_ptr().cast::<u16>(), GlobalSize(data) / 2)
+		};
+		// The text is nul-terminated, but its memory may be bigger than it
+		let len = wide.iter().position(|c| *c == 0).unwrap_or(wide.len());
+		let text = wide::decode_lossy(&wide[..len]);
+		unsafe { GlobalUnlock(data) };
+
+		Ok(Some(text))
+	}
+	/// Text after a nul character is cut off by programs that paste it.
+	fn set_clipboard_text(&mut self, text: &str) -> Result<(), Self::Error> {
+		let wide = wide::encode::<usize>(text);
+		let _lock = self.open_clipboard()?;
+		if unsafe { EmptyClipboard() } == 0 {
+			return Err(os::Error::last());
+		}
+
+		let size = (wide.len() + 1) * size_of::<u16>();
+		let data = unsafe { GlobalAlloc(GMEM_MOVEABLE, size) }.ok_or_else(os::Error::last)?;
+		let Some(ptr) = (unsafe { GlobalLock(data) }) else {
+			let err = os::Error::last();
+			unsafe { GlobalFree(data) };
+			return Err(err);
+		};
+		unsafe {
+			let ptr = ptr.as_ptr().cast::<u16>();
+			ptr.copy_from_nonoverlapping(wide.as_ptr(), wide.len());
+			ptr.add(wide.len()).write(0);
+			GlobalUnlock(data);
+		}
+
+		// The clipboard takes ownership of the data if this succeeds
+		if unsafe { SetClipboardData(CF_UNICODETEXT, data) }.is_none() {
+			let err = os::Error::last();
+			unsafe { GlobalFree(data) };
+			return Err(err);
+		}
+		Ok(())
+	}
+}
+
+/// Closes the clipboard when it's dropped. Returned by
+/// [`Win32Display::open_clipboard`].
+struct ClipboardLock;
+impl Drop for ClipboardLock {
+	fn drop(&mut self) {
+		unsafe { CloseClipboard() };
+	}
+}
+
+/// A window's state, which [`window_proc`] updates as the window gets
+/// messages.
+struct WindowState {
+	/// The size of the window's client area, i.e. without its borders and
+	/// title bar.
+	size: (u32, u32),
+	needs_redraw: bool,
+	close_requested: bool,
+	/// If the pointer is over the window. Windows doesn't tell windows when
+	/// the pointer enters them, so this is set by the first `WM_MOUSEMOVE`.
+	pointer_inside: bool,
+	/// Events that haven't been taken with [`Win32Window::events`] yet.
+	events: Vec<Event>,
+}
+
+/// A top-level window. The window is destroyed when this is dropped. See the
+/// [module-level docs](self).
+#[derive(Debug)]
+pub struct Win32Window {
+	hwnd: NonNull<c_void>,
+	/// Owned by the window, and freed when it's dropped. This isn't a `Box`,
+	/// since [`window_proc`] mutates it through the `HWND`'s user data.
+	state: NonNull<WindowState>,
+}
+impl Win32Window {
+	/// Creates and shows a window with the given title. `width` and `height`
+	/// are the size of the area the program can draw to.
+	pub fn new(
+		display: &mut Win32Display,
+		title: &str,
+		width: u32,
+		height: u32,
+	) -> Result<Self, os::Error> {
+		let title = encode_title(title)?;
+		// The window's size includes its borders and title bar
+		let mut rect = Rect {
+			left: 0,
+			top: 0,
+			right: width.min(i32::MAX as u32) as i32,
+			bottom: height.min(i32::MAX as u32) as i32,
+		};
+		if unsafe { AdjustWindowRectEx(NonNull::from(&mut rect), WINDOW_STYLE, 0, 0) } == 0 {
+			return Err(os::Error::last());
+		}
+
+		let state = NonNull::from(Box::leak(Box::new(WindowState {
+			size: (width, height),
+			needs_redraw: true,
+			close_requested: false,
+			pointer_inside: false,
+			events: Vec::new(),
+		})));
+		let hwnd = unsafe {
+			CreateWindowExW(
+				0,
+				NonNullConst::from_ref(&CLASS_NAME[0]),
+				NonNullConst::from_ref(&title[0]),
+				WINDOW_STYLE | WS_VISIBLE,
+				CW_USEDEFAULT,
+				CW_USEDEFAULT,
+				rect.right - rect.left,
+				rect.bottom - rect.top,
+				None,
+				None,
+				Some(display.instance),
+				// Passed to `window_proc` with `WM_NCCREATE`
+				Some(state.cast()),
+			)
+		};
+		let Some(hwnd) = hwnd else {
+			let err = os::Error::last();
+			drop(unsafe { Box::from_raw(state.as_ptr()) });
+			return Err(err);
+		};
+
+		Ok(Self { hwnd, state })
+	}
+
+	/// The ID the window's [`Event`]s are sent with.
+	pub fn id(&self) -> WindowId {
+		WindowId(self.hwnd.as_ptr() as usize as u64)
+	}
+	/// The window's `HWND`, e.g. for calling Win32 functions on it directly.
+	pub fn hwnd(&self) -> NonNull<c_void> {
+		self.hwnd
+	}
+	pub fn set_title(&mut self, title: &str) -> Result<(), os::Error> {
+		let title = encode_title(title)?;
+		if unsafe { SetWindowTextW(self.hwnd, NonNullConst::from_ref(&title[0])) } == 0 {
+			return Err(os::Error::last());
+		}
+		Ok(())
+	}
+
+	/// The size of the area the program can draw to, which is the window
+	/// without its borders and title bar.
+	pub fn size(&self) -> (u32, u32) {
+		self.state().size
+	}
+	/// If the window was resized or uncovered since it was last presented
+	/// to, so it should be redrawn.
+	pub fn needs_redraw(&self) -> bool {
+		self.state().needs_redraw
+	}
+	/// If the user asked to close the window, e.g. by clicking its close
+	/// button. The window stays open until it's dropped.
+	pub fn close_requested(&self) -> bool {
+		self.state().close_requested
+	}
+	/// Takes the events the window got since this was last called. Events
+	/// are queued as [`Win32Display::dispatch`] handles messages, so this
+	/// should be called after it.
+	pub fn events(&mut self) -> impl Iterator<Item = Event> + '_ {
+		unsafe { self.state.as_mut() }.events.drain(..)
+	}
+	fn state(&self) -> &WindowState {
+		unsafe { self.state.as_ref() }
+	}
+
+	/// Shows `buffer` in the window. The buffer is stretched to fill the
+	/// window if they're different sizes.
+	pub fn present(&mut self, buffer: &Buffer) -> Result<(), os::Error> {
+		let (width, height) = self.size();
+		// Minimized windows are 0x0, and there's nothing to draw anyway
+		if width == 0 || height == 0 || buffer.pixels.is_empty() {
+			unsafe { self.state.as_mut() }.needs_redraw = false;
+			return Ok(());
+		}
+
+		let info = BitmapInfo {
+			header: BitmapInfoHeader {
+				size: size_of::<BitmapInfoHeader>() as u32,
+				width: buffer.width as i32,
+				height: -(buffer.height as i32),
+				planes: 1,
+				bit_count: 32,
+				compression: BI_RGB,
+				size_image: 0,
+				x_pels_per_meter: 0,
+				y_pels_per_meter: 0,
+				clr_used: 0,
+				clr_important: 0,
+			},
+			colors: [0],
+		};
+		let dc = unsafe { GetDC(self.hwnd) }.ok_or_else(os::Error::last)?;
+		let res = unsafe {
+			StretchDIBits(
+				dc,
+				0,
+				0,
+				width as i32,
+				height as i32,
+				0,
+				0,
+				buffer.width as i32,
+				buffer.height as i32,
+				NonNullConst::from_ref(&buffer.pixels[0]).cast(),
+				NonNullConst::from_ref(&info),
+				DIB_RGB_COLORS,
+				SRCCOPY,
+			)
+		};
+		// `ReleaseDC` may overwrite the error
+		let res = if res == 0 {
+			Err(os::Error::last())
+		} else {
+			Ok(())
+		};
+		unsafe { ReleaseDC(self.hwnd, dc) };
+
+		unsafe { self.state.as_mut() }.needs_redraw = false;
+		res
+	}
+}
+impl Drop for Win32Window {
+	fn drop(&mut self) {
+		unsafe {
+			DestroyWindow(self.hwnd);
+			drop(Box::from_raw(self.state.as_ptr()));
+		}
+	}
+}
+
+/// Encodes a window title as a nul-terminated UTF-16 string.
+fn encode_title(title: &str) -> Result<SizedVec<u16, usize>, os::Error> {
+	wide::encode_nul(title).or(Err(os::Error::from_raw(ERROR_INVALID_PARAMETER)))
+}
+
+/// Handles messages for every Crux window. This runs while
+/// [`Win32Display::dispatch`] is handling messages, and while some Win32
+/// functions that send messages (like `CreateWindowExW`) are running.
+extern "system" fn window_proc(
+	hwnd: NonNull<c_void>,
+	message: u32,
+	wparam: usize,
+	lparam: isize,
+) -> isize {
+	// This is the first message the window gets with a pointer to its state
+	if message == WM_NCCREATE {
+		// `lparam` points to a `CREATESTRUCTW`, which starts with the last
+		// argument passed to `CreateWindowExW`
+		let state = unsafe { *(lparam as *const *mut WindowState) };
+		unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, state as isize) };
+	}
+	let state = unsafe { GetWindowLongPtrW(hwnd, GWLP_USERDATA) } as *mut WindowState;
+	let Some(mut state) = NonNull::new(state) else {
+		return unsafe { DefWindowProcW(hwnd, message, wparam, lparam) };
+	};
+	let state = unsafe { state.as_mut() };
+
+	match message {
+		WM_SIZE => {
+			let (width, height) = (lparam as u32 & 0xFFFF, (lparam as u32 >> 16) & 0xFFFF);
+			state.size = (width, height);
+			state.needs_redraw = true;
+			state.events.push(Event::Resized { width, height });
+			0
+		}
+		WM_PAINT => {
+			// Windows keeps sending `WM_PAINT` until the window is marked as
+			// painted; it's actually painted by `Win32Window::present`
+			unsafe { ValidateRect(hwnd, None) };
+			state.needs_redraw = true;
+			state.events.push(Event::RedrawRequested);
+			0
+		}
+		// The window is closed when it's dropped, not when the user asks
+		WM_CLOSE => {
+			state.close_requested = true;
+			state.events.push(Event::CloseRequested);
+			0
+		}
+		WM_SETFOCUS | WM_KILLFOCUS => {
+			state.events.push(Event::Focused(message == WM_SETFOCUS));
+			0
+		}
+		WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => {
+			// Bits 16-23 of `lparam` are the key's scancode, and bit 24 is set
+			// for extended keys
+			let mut scancode = (lparam as u32 >> 16) & 0xFF;
+			if lparam as u32 & (1 << 24) != 0 {
+				scancode |= 0xE000;
+			}
+			// Pause sends the same scancode as Num Lock, so it can only be told
+			// apart by its virtual key code
+			let key = if wparam == VK_PAUSE {
+				Key::Pause
+			} else {
+				Key::from_set1_scancode(scancode)
+			};
+			state.events.push(Event::Key(KeyEvent {
+				key,
+				scancode,
+				pressed: matches!(message, WM_KEYDOWN | WM_SYSKEYDOWN),
+			}));
+
+			// System keys still need their default behaviour, like Alt+F4
+			// closing the window
+			if matches!(message, WM_SYSKEYDOWN | WM_SYSKEYUP) {
+				unsafe { DefWindowProcW(hwnd, message, wparam, lparam) }
+			} else {
+				0
+			}
+		}
+		WM_MOUSEMOVE => {
+			let (x, y) = pointer_position(lparam);
+			if state.pointer_inside {
+				state.events.push(Event::PointerMotion { x, y });
+			} else {
+				// Windows only sends `WM_MOUSELEAVE` if it's asked to, and it
+				// has to be asked again each time the pointer enters
+				let mut track = TrackMouseEventInfo {
+					size: size_of::<TrackMouseEventInfo>() as u32,
+					flags: TME_LEAVE,
+					hwnd,
+					hover_time: 0,
+				};
+				unsafe { TrackMouseEvent(NonNull::from(&mut track)) };
+				state.pointer_inside = true;
+				state.events.push(Event::PointerEnter { x, y });
+			}
+			0
+		}
+		WM_MOUSELEAVE => {
+			state.pointer_inside = false;
+			state.events.push(Event::PointerLeave);
+			0
+		}
+		WM_LBUTTONDOWN | WM_LBUTTONUP | WM_RBUTTONDOWN | WM_RBUTTONUP | WM_MBUTTONDOWN
+		| WM_MBUTTONUP | WM_XBUTTONDOWN | WM_XBUTTONUP => {
+			let button = match message {
+				WM_LBUTTONDOWN | WM_LBUTTONUP => PointerButton::Left,
+				WM_RBUTTONDOWN | WM_RBUTTONUP => PointerButton::Right,
+				WM_MBUTTONDOWN | WM_MBUTTONUP => PointerButton::Middle,
+				// The high word of `wparam` says which X button it is
+				_ => match (wparam >> 16) as u16 {
+					XBUTTON1 => PointerButton::Back,
+					XBUTTON2 => PointerButton::Forward,
+					button => PointerButton::Other(button as u32),
+				},
+			};
+			state.events.push(Event::PointerButton {
+				button,
+				pressed: matches!(
+					message,
+					WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN
+				),
+			});
+
+			// Unlike the other buttons, X button messages return `TRUE`
+			if matches!(message, WM_XBUTTONDOWN | WM_XBUTTONUP) {
+				1
+			} else {
+				0
+			}
+		}
+		WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+			let lines = f64::from((wparam >> 16) as i16) / f64::from(WHEEL_DELTA);
+			// Positive deltas scroll up for the vertical wheel, but right for
+			// the horizontal one
+			state.events.push(if message == WM_MOUSEWHEEL {
+				Event::Scroll { x: 0.0, y: -lines }
+			} else {
+				Event::Scroll { x: lines, y: 0.0 }
+			});
+			0
+		}
+		_ => unsafe { DefWindowProcW(hwnd, message, wparam, lparam) },
+	}
+}
+
+/// The pointer position in a mouse message's `lparam`, relative to the
+/// window's client area. The coordinates are signed, since they can be
+/// outside the window while a button is held.
+fn pointer_position(lparam: isize) -> (f64, f64) {
+	let (x, y) = (lparam as u32 as i16, (lparam as u32 >> 16) as i16);
+	(f64::from(x), f64::from(y))
+}
+
+/// An image in memory that can be shown in a window with
+/// [`Win32Window::present`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Buffer {
+	pixels: Vec<u8>,
+	width: u32,
+	height: u32,
+}
+impl Buffer {
+	/// How many bytes each pixel takes up.
+	pub const BYTES_PER_PIXEL: usize = 4;
+
+	/// Creates a `width` by `height` buffer. The pixels start out zeroed
+	/// (black).
+	pub fn new(width: u32, height: u32) -> Self {
+		let mut this = Self {
+			pixels: Vec::new(),
+			width: 0,
+			height: 0,
+		};
+		this.resize(width, height);
+		this
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+	/// How many bytes are between the start of each row of pixels.
+	pub fn stride(&self) -> usize {
+		self.width as usize * Self::BYTES_PER_PIXEL
+	}
+	/// The buffer's pixels, row by row from the top. Each pixel is a
+	/// little-endian `0xXXRRGGBB`, where the `X` byte is ignored.
+	pub fn pixels(&mut self) -> &mut [u8] {
+		&mut self.pixels
+	}
+	/// A [`Canvas`] for drawing to the buffer's pixels.
+	pub fn canvas(&mut self) -> Canvas<'_> {
+		let stride = self.stride();
+		Canvas::new(&mut self.pixels, self.width, self.height, stride)
+	}
+
+	/// Changes the buffer's size. Does nothing if it's already `width` by
+	/// `height`; otherwise, the pixels are zeroed.
+	pub fn resize(&mut self, width: u32, height: u32) {
+		if (width, height) == (self.width, self.height) {
+			return;
+		}
+		// Win32 takes sizes as `i32`s
+		let (width, height) = (width.min(i32::MAX as u32), height.min(i32::MAX as u32));
+
+		self.pixels.clear();
+		self.pixels
+			.resize(width as usize * height as usize * Self::BYTES_PER_PIXEL, 0);
+		(self.width, self.height) = (width, height);
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn buffer() {
+		let mut buffer = Buffer::new(4, 2);
+		assert_eq!(buffer.stride(), 16);
+		assert_eq!(buffer.pixels().len(), 32);
+		assert!(buffer.pixels().iter().all(|byte| *byte == 0));
+
+		buffer.pixels().fill(0xFF);
+		buffer.resize(4, 2);
+		assert!(buffer.pixels().iter().all(|byte| *byte == 0xFF));
+		buffer.resize(2, 3);
+		assert_eq!((buffer.width(), buffer.height()), (2, 3));
+		assert_eq!(buffer.pixels().len(), 24);
+		assert!(buffer.pixels().iter().all(|byte| *byte == 0));
+	}
+
+	#[test]
+	fn window() {
+		let mut display = Win32Display::connect().unwrap();
+		// Registering the class again is fine
+		Win32Display::connect().unwrap();
+
+		let mut window = Win32Window::new(&mut display, "Crux", 64, 32).unwrap();
+		assert_eq!(window.size(), (64, 32));
+		assert!(window.needs_redraw());
+		assert!(!window.close_requested());
+		window.set_title("Crux test").unwrap();
+		assert_eq!(
+			window.set_title("a\0b").map_err(os::Error::kind),
+			Err(os::ErrorKind::InvalidInput)
+		);
+
+		let mut buffer = Buffer::new(64, 32);
+		buffer.pixels().fill(0x80);
+		window.present(&buffer).unwrap();
+		assert!(!window.needs_redraw());
+
+		// Showing the window while it's created sizes it
+		let id = window.id();
+		let mut windows = [window];
+		let mut events = Vec::new();
+		display
+			.dispatch_events(&mut windows, Some(Duration::ZERO), &mut |window, event| {
+				assert_eq!(window, id);
+				events.push(event);
+			})
+			.unwrap();
+		assert!(events.contains(&Event::Resized {
+			width: 64,
+			height: 32
+		}));
+	}
+}
@@ -0,0 +1,391 @@
+//! Software drawing to framebuffers, for debug overlays and simple tools that
+//! don't need a GPU.
+//!
+//! A [`Canvas`] borrows the pixels of a buffer from a display backend (see
+//! `Buffer::canvas`), and draws shapes, images, and text to them:
+//!
+//! ```rs
+//! let mut canvas = buffer.canvas();
+//! canvas.clear(Color::BLACK);
+//! canvas.fill_rect(Rect::new(10, 10, 100, 50), Color::rgb(0x33, 0x66, 0x99));
+//! canvas.draw_line((0, 0), (639, 479), Color::WHITE);
+//! canvas.draw_text(12, 12, "Hello, world!", &FONT_8X8.scaled(2), Color::WHITE);
+//! ```
+//!
+//! Pixels are 32-bit little-endian `0xAARRGGBB`, which is the layout of both
+//! Wayland's `Argb8888`/`Xrgb8888` formats and Win32's bitmaps. Drawing
+//! replaces pixels; nothing is blended.
+
+pub mod font;
+
+pub use font::{FONT_8X8, Font};
+
+/// A 32-bit colour, as `0xAARRGGBB`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Color(pub u32);
+impl Color {
+	pub const BLACK: Self = Self::rgb(0, 0, 0);
+	pub const WHITE: Self = Self::rgb(0xFF, 0xFF, 0xFF);
+	pub const RED: Self = Self::rgb(0xFF, 0, 0);
+	pub const GREEN: Self = Self::rgb(0, 0xFF, 0);
+	pub const BLUE: Self = Self::rgb(0, 0, 0xFF);
+
+	/// An opaque colour.
+	pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Self::argb(0xFF, r, g, b)
+	}
+	pub const fn argb(a: u8, r: u8, g: u8, b: u8) -> Self {
+		Self(u32::from_be_bytes([a, r, g, b]))
+	}
+
+	pub const fn a(self) -> u8 {
+		self.0.to_be_bytes()[0]
+	}
+	pub const fn r(self) -> u8 {
+		self.0.to_be_bytes()[1]
+	}
+	pub const fn g(self) -> u8 {
+		self.0.to_be_bytes()[2]
+	}
+	pub const fn b(self) -> u8 {
+		self.0.to_be_bytes()[3]
+	}
+}
+
+/// A rectangle in pixels, from its top-left corner. Rectangles can be
+/// partially (or entirely) outside a canvas; only the part inside it is
+/// drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rect {
+	pub x: i32,
+	pub y: i32,
+	pub width: u32,
+	pub height: u32,
+}
+impl Rect {
+	pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+		Self {
+			x,
+			y,
+			width,
+			height,
+		}
+	}
+
+	/// The part of the rectangle inside a `width` by `height` area, as the
+	/// start and end (exclusive) of its columns and rows.
+	fn clip(self, width: u32, height: u32) -> Option<(usize, usize, usize, usize)> {
+		let clip_axis = |start: i32, len: u32, max: u32| {
+			let end = (start as i64 + len as i64).min(max as i64);
+			let start = (start as i64).max(0);
+			(start < end).then_some((start as usize, end as usize))
+		};
+		let (x_start, x_end) = clip_axis(self.x, self.width, width)?;
+		let (y_start, y_end) = clip_axis(self.y, self.height, height)?;
+		Some((x_start, x_end, y_start, y_end))
+	}
+}
+
+/// Pixels that can be drawn to. See the [module-level docs](self).
+#[derive(Debug)]
+pub struct Canvas<'a> {
+	pixels: &'a mut [u8],
+	width: u32,
+	height: u32,
+	/// How many bytes are between the start of each row of pixels.
+	stride: usize,
+}
+impl<'a> Canvas<'a> {
+	/// How many bytes each pixel takes up.
+	pub const BYTES_PER_PIXEL: usize = 4;
+
+	/// Draws to `pixels`, which are `height` rows of `width` pixels each, with
+	/// `stride` bytes between the start of each row.
+	///
+	/// # Panics
+	///
+	/// Panics if the rows don't fit in `stride` bytes, or `pixels` is too
+	/// small.
+	pub fn new(pixels: &'a mut [u8], width: u32, height: u32, stride: usize) -> Self {
+		let row_len = width as usize * Self::BYTES_PER_PIXEL;
+		assert!(row_len <= stride, "rows are longer than the stride");
+		let len = match height {
+			0 => 0,
+			height => stride * (height as usize - 1) + row_len,
+		};
+		assert!(len <= pixels.len(), "the canvas is larger than its pixels");
+
+		Self {
+			pixels,
+			width,
+			height,
+			stride,
+		}
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+	pub fn stride(&self) -> usize {
+		self.stride
+	}
+	/// Where the pixel at (`x`, `y`) starts in `pixels`.
+	fn offset(&self, x: usize, y: usize) -> usize {
+		y * self.stride + x * Self::BYTES_PER_PIXEL
+	}
+
+	/// The colour of the pixel at (`x`, `y`), or `None` if it's outside the
+	/// canvas.
+	pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+		let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+		if x >= self.width || y >= self.height {
+			return None;
+		}
+		let offset = self.offset(x as usize, y as usize);
+		let bytes = self.pixels[offset..offset + Self::BYTES_PER_PIXEL]
+			.try_into()
+			.unwrap();
+		Some(Color(u32::from_le_bytes(bytes)))
+	}
+	/// Sets the pixel at (`x`, `y`). Does nothing if it's outside the canvas.
+	pub fn set(&mut self, x: i32, y: i32, color: Color) {
+		let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+			return;
+		};
+		if x < self.width && y < self.height {
+			let offset = self.offset(x as usize, y as usize);
+			self.pixels[offset..offset + Self::BYTES_PER_PIXEL]
+				.copy_from_slice(&color.0.to_le_bytes());
+		}
+	}
+
+	/// Fills the whole canvas with `color`.
+	pub fn clear(&mut self, color: Color) {
+		self.fill_rect(Rect::new(0, 0, self.width, self.height), color);
+	}
+	pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+		let Some((x_start, x_end, y_start, y_end)) = rect.clip(self.width, self.height) else {
+			return;
+		};
+		let color = color.0.to_le_bytes();
+		for y in y_start..y_end {
+			let (start, end) = (self.offset(x_start, y), self.offset(x_end, y));
+			for pixel in self.pixels[start..end].as_chunks_mut::<4>().0 {
+				*pixel = color;
+			}
+		}
+	}
+	/// Draws a one pixel wide outline of `rect`, inside its bounds.
+	pub fn draw_rect(&mut self, rect: Rect, color: Color) {
+		if rect.width == 0 || rect.height == 0 {
+			return;
+		}
+		let (right, bottom) = (
+			rect.x.saturating_add_unsigned(rect.width - 1),
+			rect.y.saturating_add_unsigned(rect.height - 1),
+		);
+		self.fill_rect(Rect::new(rect.x, rect.y, rect.width, 1), color);
+		self.fill_rect(Rect::new(rect.x, bottom, rect.width, 1), color);
+		self.fill_rect(Rect::new(rect.x, rect.y, 1, rect.height), color);
+		self.fill_rect(Rect::new(right, rect.y, 1, rect.height), color);
+	}
+	/// Draws a one pixel wide line from `from` to `to`, including both ends.
+	pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), color: Color) {
+		// Bresenham's line algorithm, which works for lines in any direction
+		// by stepping along both axes
+		let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+		let (to_x, to_y) = (to.0 as i64, to.1 as i64);
+		let (dx, dy) = ((to_x - x).abs(), -(to_y - y).abs());
+		let (step_x, step_y) = (if x < to_x { 1 } else { -1 }, if y < to_y { 1 } else { -1 });
+		let mut error = dx + dy;
+
+		loop {
+			self.set(x as i32, y as i32, color);
+			if x == to_x && y == to_y {
+				break;
+			}
+			let doubled = error * 2;
+			if doubled >= dy {
+				error += dy;
+				x += step_x;
+			}
+			if doubled <= dx {
+				error += dx;
+				y += step_y;
+			}
+		}
+	}
+
+	/// Copies every pixel from `src` into this canvas, with `src`'s top-left
+	/// corner at (`x`, `y`).
+	pub fn blit(&mut self, x: i32, y: i32, src: &Canvas<'_>) {
+		let Some((x_start, x_end, y_start, y_end)) =
+			Rect::new(x, y, src.width, src.height).clip(self.width, self.height)
+		else {
+			return;
+		};
+		// Where the visible part starts in `src`
+		let (src_x, src_y) = (
+			(x_start as i64 - x as i64) as usize,
+			(y_start as i64 - y as i64) as usize,
+		);
+		let row_len = (x_end - x_start) * Self::BYTES_PER_PIXEL;
+		for row in 0..y_end - y_start {
+			let dst = self.offset(x_start, y_start + row);
+			let src_offset = src.offset(src_x, src_y + row);
+			self.pixels[dst..dst + row_len]
+				.copy_from_slice(&src.pixels[src_offset..src_offset + row_len]);
+		}
+	}
+
+	/// Draws `text` with its top-left corner at (`x`, `y`). Lines are split
+	/// at `\n`, and characters the font doesn't have are drawn as `?`. Only
+	/// the glyphs' pixels are drawn, so the background shows through.
+	pub fn draw_text(&mut self, x: i32, y: i32, text: &str, font: &Font, color: Color) {
+		let (char_width, line_height) = (font.char_width() as i32, font.line_height() as i32);
+		let scale = font.scale();
+
+		for (line_idx, line) in text.split('\n').enumerate() {
+			let line_y = y.saturating_add((line_idx as i32).saturating_mul(line_height));
+			for (char_idx, char) in line.chars().enumerate() {
+				let char_x = x.saturating_add((char_idx as i32).saturating_mul(char_width));
+				let Some(glyph) = font.glyph(char).or_else(|| font.glyph('?')) else {
+					continue;
+				};
+
+				for (row_idx, row) in glyph.iter().enumerate() {
+					for column in 0..font.char_width() / scale {
+						if row & (1 << column) == 0 {
+							continue;
+						}
+						let pixel = Rect::new(
+							char_x.saturating_add((column * scale) as i32),
+							line_y.saturating_add((row_idx as u32 * scale) as i32),
+							scale,
+							scale,
+						);
+						self.fill_rect(pixel, color);
+					}
+				}
+			}
+		}
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Renders a canvas's pixels as `#` for `color`, and `.` for anything
+	/// else, one string per row.
+	fn render(canvas: &Canvas<'_>, color: Color) -> Vec<String> {
+		(0..canvas.height() as i32)
+			.map(|y| {
+				(0..canvas.width() as i32)
+					.map(|x| {
+						if canvas.get(x, y) == Some(color) {
+							'#'
+						} else {
+							'.'
+						}
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	#[test]
+	fn shapes() {
+		let mut pixels = [0; 6 * 4 * 4];
+		let mut canvas = Canvas::new(&mut pixels, 6, 4, 6 * 4);
+		canvas.clear(Color::BLACK);
+		assert_eq!(canvas.get(5, 3), Some(Color::BLACK));
+		assert_eq!(canvas.get(6, 0), None);
+		assert_eq!(canvas.get(0, -1), None);
+
+		// Rectangles are clipped to the canvas
+		canvas.fill_rect(Rect::new(-2, -2, 4, 3), Color::WHITE);
+		canvas.fill_rect(Rect::new(5, 3, 10, 10), Color::WHITE);
+		canvas.fill_rect(Rect::new(100, 0, 1, 1), Color::WHITE);
+		assert_eq!(
+			render(&canvas, Color::WHITE),
+			["##....", "......", "......", ".....#"]
+		);
+
+		canvas.clear(Color::BLACK);
+		canvas.draw_rect(Rect::new(1, 0, 4, 4), Color::WHITE);
+		assert_eq!(
+			render(&canvas, Color::WHITE),
+			[".####.", ".#..#.", ".#..#.", ".####."]
+		);
+
+		canvas.clear(Color::BLACK);
+		canvas.draw_line((0, 0), (5, 3), Color::WHITE);
+		canvas.draw_line((-10, 3), (1, 3), Color::WHITE);
+		assert_eq!(
+			render(&canvas, Color::WHITE),
+			["#.....", ".##...", "...##.", "##...#"]
+		);
+
+		// Colours are stored as little-endian `0xAARRGGBB`
+		canvas.set(0, 0, Color::rgb(0x12, 0x34, 0x56));
+		assert_eq!(pixels[..4], [0x56, 0x34, 0x12, 0xFF]);
+	}
+
+	#[test]
+	fn blit() {
+		let mut src_pixels = [0; 2 * 2 * 4];
+		let mut src = Canvas::new(&mut src_pixels, 2, 2, 8);
+		src.clear(Color::RED);
+
+		// Rows can have padding after them
+		let mut pixels = [0; 4 * 20];
+		let mut canvas = Canvas::new(&mut pixels, 4, 4, 20);
+		canvas.blit(-1, -1, &src);
+		canvas.blit(3, 2, &src);
+		assert_eq!(
+			render(&canvas, Color::RED),
+			["#...", "....", "...#", "...#"]
+		);
+	}
+
+	#[test]
+	fn text() {
+		let mut pixels = [0; 16 * 16 * 4];
+		let mut canvas = Canvas::new(&mut pixels, 16, 16, 16 * 4);
+		canvas.draw_text(0, 0, "A\n\u{80}", &FONT_8X8, Color::WHITE);
+		assert_eq!(
+			render(&canvas, Color::WHITE)[..8],
+			[
+				"..##............",
+				".####...........",
+				"##..##..........",
+				"##..##..........",
+				"######..........",
+				"##..##..........",
+				"##..##..........",
+				"................",
+			]
+		);
+		// Unknown characters are question marks
+		assert_eq!(render(&canvas, Color::WHITE)[8], ".####...........");
+
+		canvas.clear(Color::BLACK);
+		canvas.draw_text(0, 0, ".", &FONT_8X8.scaled(2), Color::WHITE);
+		assert_eq!(canvas.get(4, 10), Some(Color::WHITE));
+		assert_eq!(canvas.get(5, 11), Some(Color::WHITE));
+		assert_eq!(canvas.get(3, 10), Some(Color::BLACK));
+
+		assert_eq!(FONT_8X8.text_size("abc\nde"), (24, 16));
+		assert_eq!(FONT_8X8.scaled(3).text_size("a"), (24, 24));
+	}
+}
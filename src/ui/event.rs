@@ -0,0 +1,568 @@
+//! Input and window events, independent of the display backend.
+//!
+//! Each backend translates its own events (Wayland's `wl_keyboard.key`,
+//! Win32's `WM_KEYDOWN`, etc.) into [`Event`]s, so programs only have to
+//! handle one set of events. [`EventLoop`] owns a display and its windows,
+//! and hands out their events one at a time:
+//!
+//! ```rs
+//! let mut events = EventLoop::<WaylandDisplay>::new()?;
+//! let window = events.create_window("Crux", 640, 480)?;
+//!
+//! loop {
+//!     let (id, event) = events.next_event()?;
+//!     match event {
+//!         Event::Key(KeyEvent { key: Key::Escape, pressed: true, .. }) => break,
+//!         Event::CloseRequested if id == window => break,
+//!         _ => {}
+//!     }
+//! }
+//! ```
+//!
+//! There's no X11 backend yet, so on Linux events come from Wayland.
+
+use {
+	crate::{rt::time::Duration, ui::display::Display},
+	alloc::collections::VecDeque,
+};
+
+/// Identifies which window an event is for. On Wayland this is the ID of the
+/// window's surface, and on Win32 it's the window's `HWND`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct WindowId(pub u64);
+
+/// Something that happened to a window, or input the user sent to it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Event {
+	/// A key was pressed or released while the window had keyboard focus.
+	Key(KeyEvent),
+	/// The pointer moved into the window, at the given position.
+	PointerEnter { x: f64, y: f64 },
+	/// The pointer left the window.
+	PointerLeave,
+	/// The pointer moved. Positions are in pixels, relative to the top-left
+	/// corner of the area the program draws to.
+	PointerMotion { x: f64, y: f64 },
+	PointerButton {
+		button: PointerButton,
+		pressed: bool,
+	},
+	/// The user scrolled, e.g. with a mouse wheel or touchpad. Amounts are in
+	/// lines, where one notch of a mouse wheel is one line; positive values
+	/// scroll down and to the right.
+	Scroll { x: f64, y: f64 },
+	/// The area the program draws to changed size.
+	Resized { width: u32, height: u32 },
+	/// The window should be drawn again, e.g. because it was just resized or
+	/// uncovered.
+	RedrawRequested,
+	/// The user asked to close the window, e.g. by clicking its close button.
+	/// The window stays open until the program closes it.
+	CloseRequested,
+	/// The window gained (`true`) or lost (`false`) keyboard focus.
+	Focused(bool),
+}
+
+/// A key being pressed or released.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct KeyEvent {
+	/// The physical key, independent of the keyboard layout.
+	pub key: Key,
+	/// The backend's raw code for the key: an evdev keycode on Wayland, or a
+	/// set 1 scancode on Win32 (with `0xE0` in the high byte for extended
+	/// keys). Useful for keys that map to [`Key::Unknown`].
+	pub scancode: u32,
+	pub pressed: bool,
+}
+
+/// A physical key on the keyboard, named after what it shows on a US QWERTY
+/// layout. Keys are identified by where they are, not what they type, so
+/// [`Key::A`] is the key to the right of Caps Lock even on an AZERTY layout.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+	Escape,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+	PrintScreen,
+	ScrollLock,
+	Pause,
+
+	Backquote,
+	Digit1,
+	Digit2,
+	Digit3,
+	Digit4,
+	Digit5,
+	Digit6,
+	Digit7,
+	Digit8,
+	Digit9,
+	Digit0,
+	Minus,
+	Equal,
+	Backspace,
+	Tab,
+	Q,
+	W,
+	E,
+	R,
+	T,
+	Y,
+	U,
+	I,
+	O,
+	P,
+	BracketLeft,
+	BracketRight,
+	Backslash,
+	CapsLock,
+	A,
+	S,
+	D,
+	F,
+	G,
+	H,
+	J,
+	K,
+	L,
+	Semicolon,
+	Quote,
+	Enter,
+	ShiftLeft,
+	/// The extra key next to left shift on ISO keyboards.
+	IntlBackslash,
+	Z,
+	X,
+	C,
+	V,
+	B,
+	N,
+	M,
+	Comma,
+	Period,
+	Slash,
+	ShiftRight,
+	ControlLeft,
+	/// The Windows or Command key.
+	MetaLeft,
+	AltLeft,
+	Space,
+	AltRight,
+	MetaRight,
+	ContextMenu,
+	ControlRight,
+
+	Insert,
+	Delete,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	ArrowUp,
+	ArrowDown,
+	ArrowLeft,
+	ArrowRight,
+
+	NumLock,
+	NumpadDivide,
+	NumpadMultiply,
+	NumpadSubtract,
+	NumpadAdd,
+	NumpadEnter,
+	NumpadDecimal,
+	Numpad0,
+	Numpad1,
+	Numpad2,
+	Numpad3,
+	Numpad4,
+	Numpad5,
+	Numpad6,
+	Numpad7,
+	Numpad8,
+	Numpad9,
+
+	/// A key Crux doesn't have a name for. Check [`KeyEvent::scancode`].
+	Unknown,
+}
+impl Key {
+	/// Looks up a Linux evdev keycode (`KEY_*` in `linux/input-event-codes.h`),
+	/// which is what Wayland's `wl_keyboard.key` sends.
+	pub fn from_evdev(code: u32) -> Self {
+		match code {
+			1 => Self::Escape,
+			2 => Self::Digit1,
+			3 => Self::Digit2,
+			4 => Self::Digit3,
+			5 => Self::Digit4,
+			6 => Self::Digit5,
+			7 => Self::Digit6,
+			8 => Self::Digit7,
+			9 => Self::Digit8,
+			10 => Self::Digit9,
+			11 => Self::Digit0,
+			12 => Self::Minus,
+			13 => Self::Equal,
+			14 => Self::Backspace,
+			15 => Self::Tab,
+			16 => Self::Q,
+			17 => Self::W,
+			18 => Self::E,
+			19 => Self::R,
+			20 => Self::T,
+			21 => Self::Y,
+			22 => Self::U,
+			23 => Self::I,
+			24 => Self::O,
+			25 => Self::P,
+			26 => Self::BracketLeft,
+			27 => Self::BracketRight,
+			28 => Self::Enter,
+			29 => Self::ControlLeft,
+			30 => Self::A,
+			31 => Self::S,
+			32 => Self::D,
+			33 => Self::F,
+			34 => Self::G,
+			35 => Self::H,
+			36 => Self::J,
+			37 => Self::K,
+			38 => Self::L,
+			39 => Self::Semicolon,
+			40 => Self::Quote,
+			41 => Self::Backquote,
+			42 => Self::ShiftLeft,
+			43 => Self::Backslash,
+			44 => Self::Z,
+			45 => Self::X,
+			46 => Self::C,
+			47 => Self::V,
+			48 => Self::B,
+			49 => Self::N,
+			50 => Self::M,
+			51 => Self::Comma,
+			52 => Self::Period,
+			53 => Self::Slash,
+			54 => Self::ShiftRight,
+			55 => Self::NumpadMultiply,
+			56 => Self::AltLeft,
+			57 => Self::Space,
+			58 => Self::CapsLock,
+			59 => Self::F1,
+			60 => Self::F2,
+			61 => Self::F3,
+			62 => Self::F4,
+			63 => Self::F5,
+			64 => Self::F6,
+			65 => Self::F7,
+			66 => Self::F8,
+			67 => Self::F9,
+			68 => Self::F10,
+			69 => Self::NumLock,
+			70 => Self::ScrollLock,
+			71 => Self::Numpad7,
+			72 => Self::Numpad8,
+			73 => Self::Numpad9,
+			74 => Self::NumpadSubtract,
+			75 => Self::Numpad4,
+			76 => Self::Numpad5,
+			77 => Self::Numpad6,
+			78 => Self::NumpadAdd,
+			79 => Self::Numpad1,
+			80 => Self::Numpad2,
+			81 => Self::Numpad3,
+			82 => Self::Numpad0,
+			83 => Self::NumpadDecimal,
+			86 => Self::IntlBackslash,
+			87 => Self::F11,
+			88 => Self::F12,
+			96 => Self::NumpadEnter,
+			97 => Self::ControlRight,
+			98 => Self::NumpadDivide,
+			99 => Self::PrintScreen,
+			100 => Self::AltRight,
+			102 => Self::Home,
+			103 => Self::ArrowUp,
+			104 => Self::PageUp,
+			105 => Self::ArrowLeft,
+			106 => Self::ArrowRight,
+			107 => Self::End,
+			108 => Self::ArrowDown,
+			109 => Self::PageDown,
+			110 => Self::Insert,
+			111 => Self::Delete,
+			119 => Self::Pause,
+			125 => Self::MetaLeft,
+			126 => Self::MetaRight,
+			127 => Self::ContextMenu,
+			_ => Self::Unknown,
+		}
+	}
+
+	/// Looks up a PC set 1 scancode, which is what Win32 reports. Extended
+	/// keys have `0xE0` in the high byte, e.g. `0xE048` for the up arrow.
+	pub fn from_set1_scancode(code: u32) -> Self {
+		match code {
+			// Most evdev keycodes are the set 1 scancode of the same key
+			0x01..=0x58 => Self::from_evdev(code),
+			0xE01C => Self::NumpadEnter,
+			0xE01D => Self::ControlRight,
+			0xE035 => Self::NumpadDivide,
+			0xE037 => Self::PrintScreen,
+			0xE038 => Self::AltRight,
+			// Windows reports Num Lock as extended, since Pause sends the
+			// scancode Num Lock normally has
+			0xE045 => Self::NumLock,
+			0xE047 => Self::Home,
+			0xE048 => Self::ArrowUp,
+			0xE049 => Self::PageUp,
+			0xE04B => Self::ArrowLeft,
+			0xE04D => Self::ArrowRight,
+			0xE04F => Self::End,
+			0xE050 => Self::ArrowDown,
+			0xE051 => Self::PageDown,
+			0xE052 => Self::Insert,
+			0xE053 => Self::Delete,
+			0xE05B => Self::MetaLeft,
+			0xE05C => Self::MetaRight,
+			0xE05D => Self::ContextMenu,
+			_ => Self::Unknown,
+		}
+	}
+}
+
+/// A button on a mouse or touchpad.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PointerButton {
+	Left,
+	Right,
+	Middle,
+	/// The "back" side button, for going back in a browser.
+	Back,
+	/// The "forward" side button, for going forward in a browser.
+	Forward,
+	/// Any other button, as the backend's raw code for it.
+	Other(u32),
+}
+impl PointerButton {
+	/// Looks up a Linux evdev button code (`BTN_*` in
+	/// `linux/input-event-codes.h`), which is what Wayland's
+	/// `wl_pointer.button` sends.
+	pub fn from_evdev(code: u32) -> Self {
+		match code {
+			0x110 => Self::Left,
+			0x111 => Self::Right,
+			0x112 => Self::Middle,
+			0x113 => Self::Back,
+			0x114 => Self::Forward,
+			code => Self::Other(code),
+		}
+	}
+}
+
+/// Owns a display and the windows created on it, and queues up their events.
+/// See the [module-level docs](self).
+pub struct EventLoop<D: Display> {
+	display: D,
+	windows: Vec<D::WindowHandle>,
+	/// Events that were dispatched but haven't been returned yet. Backends
+	/// usually dispatch several events at once.
+	queue: VecDeque<(WindowId, Event)>,
+}
+impl<D: Display> EventLoop<D> {
+	/// Connects to the display server.
+	pub fn new() -> Result<Self, D::Error> {
+		D::new().map(Self::from_display)
+	}
+	/// Creates an event loop for a display that's already connected.
+	pub fn from_display(display: D) -> Self {
+		Self {
+			display,
+			windows: Vec::new(),
+			queue: VecDeque::new(),
+		}
+	}
+
+	pub fn display(&mut self) -> &mut D {
+		&mut self.display
+	}
+	/// Creates a window, and returns the ID its events will have. See
+	/// [`Display::create_window`].
+	pub fn create_window(
+		&mut self,
+		title: &str,
+		width: u32,
+		height: u32,
+	) -> Result<WindowId, D::Error> {
+		let window = self.display.create_window(title, width, height)?;
+		let id = D::window_id(&window);
+		self.windows.push(window);
+		Ok(id)
+	}
+	/// The window with the given ID, e.g. to present a buffer to it.
+	pub fn window(&mut self, id: WindowId) -> Option<&mut D::WindowHandle> {
+		self.windows
+			.iter_mut()
+			.find(|window| D::window_id(window) == id)
+	}
+	/// Removes a window from the event loop and returns it, so it can be
+	/// closed. Its queued events are dropped.
+	pub fn remove_window(&mut self, id: WindowId) -> Option<D::WindowHandle> {
+		let idx = self
+			.windows
+			.iter()
+			.position(|window| D::window_id(window) == id)?;
+		self.queue.retain(|(window, _)| *window != id);
+		Some(self.windows.remove(idx))
+	}
+
+	/// Waits until there's an event, then returns it.
+	pub fn next_event(&mut self) -> Result<(WindowId, Event), D::Error> {
+		loop {
+			if let Some(event) = self.wait_event(None)? {
+				return Ok(event);
+			}
+		}
+	}
+	/// Returns the next event if there is one, without waiting.
+	pub fn poll_event(&mut self) -> Result<Option<(WindowId, Event)>, D::Error> {
+		self.wait_event(Some(Duration::ZERO))
+	}
+	/// Waits up to `timeout` for an event, or forever if it's `None`. Returns
+	/// `None` if no events arrived in time.
+	///
+	/// A timeout lets programs do other work between events, e.g. drawing
+	/// animation frames.
+	pub fn wait_event(
+		&mut self,
+		timeout: Option<Duration>,
+	) -> Result<Option<(WindowId, Event)>, D::Error> {
+		if self.queue.is_empty() {
+			let queue = &mut self.queue;
+			self.display
+				.dispatch_events(&mut self.windows, timeout, &mut |id, event| {
+					queue.push_back((id, event))
+				})?;
+		}
+
+		Ok(self.queue.pop_front())
+	}
+}
+
+//
+//
+// Tests
+//
+//
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A display that sends each window a resize event the first time
+	/// events are dispatched, then a close event.
+	struct FakeDisplay {
+		dispatched: u32,
+	}
+	impl Display for FakeDisplay {
+		type WindowHandle = WindowId;
+		type Error = ();
+
+		fn new() -> Result<Self, Self::Error> {
+			Ok(Self { dispatched: 0 })
+		}
+		fn create_window(
+			&mut self,
+			title: &str,
+			_width: u32,
+			_height: u32,
+		) -> Result<Self::WindowHandle, Self::Error> {
+			Ok(WindowId(title.len() as u64))
+		}
+		fn window_id(window: &Self::WindowHandle) -> WindowId {
+			*window
+		}
+		fn dispatch_events(
+			&mut self,
+			windows: &mut [Self::WindowHandle],
+			timeout: Option<Duration>,
+			handler: &mut dyn FnMut(WindowId, Event),
+		) -> Result<(), Self::Error> {
+			// Waiting without a timeout would never return
+			if timeout.is_none() && self.dispatched >= 2 {
+				return Err(());
+			}
+			for window in windows {
+				match self.dispatched {
+					0 => handler(
+						*window,
+						Event::Resized {
+							width: 1,
+							height: 2,
+						},
+					),
+					1 => handler(*window, Event::CloseRequested),
+					_ => {}
+				}
+			}
+			self.dispatched += 1;
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn event_loop() {
+		let mut events = EventLoop::<FakeDisplay>::new().unwrap();
+		let a = events.create_window("a", 0, 0).unwrap();
+		let b = events.create_window("bb", 0, 0).unwrap();
+		assert_eq!(events.window(b), Some(&mut WindowId(2)));
+
+		let resized = Event::Resized {
+			width: 1,
+			height: 2,
+		};
+		assert_eq!(events.next_event(), Ok((a, resized)));
+		assert_eq!(events.poll_event(), Ok(Some((b, resized))));
+		assert_eq!(events.remove_window(a), Some(a));
+		assert_eq!(events.window(a), None);
+		assert_eq!(events.next_event(), Ok((b, Event::CloseRequested)));
+		assert_eq!(events.poll_event(), Ok(None));
+		assert_eq!(events.next_event(), Err(()));
+	}
+
+	#[test]
+	fn keys() {
+		assert_eq!(Key::from_evdev(1), Key::Escape);
+		assert_eq!(Key::from_evdev(30), Key::A);
+		assert_eq!(Key::from_evdev(88), Key::F12);
+		assert_eq!(Key::from_evdev(103), Key::ArrowUp);
+		assert_eq!(Key::from_evdev(125), Key::MetaLeft);
+		assert_eq!(Key::from_evdev(240), Key::Unknown);
+
+		// Scancodes without the extended prefix are the same as evdev's
+		for code in 0x01..=0x58 {
+			assert_eq!(Key::from_set1_scancode(code), Key::from_evdev(code));
+		}
+		assert_eq!(Key::from_set1_scancode(0x1D), Key::ControlLeft);
+		assert_eq!(Key::from_set1_scancode(0xE01D), Key::ControlRight);
+		assert_eq!(Key::from_set1_scancode(0x48), Key::Numpad8);
+		assert_eq!(Key::from_set1_scancode(0xE048), Key::ArrowUp);
+		assert_eq!(Key::from_set1_scancode(0xE0FF), Key::Unknown);
+
+		assert_eq!(PointerButton::from_evdev(0x110), PointerButton::Left);
+		assert_eq!(PointerButton::from_evdev(0x114), PointerButton::Forward);
+		assert_eq!(
+			PointerButton::from_evdev(0x120),
+			PointerButton::Other(0x120)
+		);
+	}
+}
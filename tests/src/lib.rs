@@ -21,6 +21,7 @@ fn log_macro() {
 			level: LogLevel::Info,
 			module: MODULE_PATH,
 			msg: Cow::Borrowed("Hello, world!"),
+			spans: Cow::Borrowed(""),
 			line: 18,
 			column: 3,
 			file: "tests/src/lib.rs"
@@ -32,7 +33,8 @@ fn log_macro() {
 			level: LogLevel::Info,
 			module: MODULE_PATH,
 			msg: Cow::Owned(String::from("Hello, world!")),
-			line: 29,
+			spans: Cow::Borrowed(""),
+			line: 30,
 			column: 3,
 			file: "tests/src/lib.rs"
 		}
@@ -85,3 +87,38 @@ fn sized_arenavec() {
 	assert_eq!(vec[1u32], 1u8);
 	assert_eq!(vec[0u32..=1u32], [0u8, 1u8]);
 }
+
+#[test]
+fn result_test() -> Result<(), crux::num::ParseIntError> {
+	let port: u16 = crux::num::parse("8080")?;
+	assert_eq!(port, 8080);
+	Ok(())
+}
+
+#[test]
+fn derive_macros() {
+	#[derive(CruxDebug, CruxDefault)]
+	struct Config<T> {
+		name: &'static str,
+		#[crux(default = 8)]
+		threads: u32,
+		#[crux(skip)]
+		_extra: T,
+	}
+
+	#[derive(CruxDebug, CruxDefault)]
+	enum Mode {
+		Fast(u8),
+		#[crux(default)]
+		Slow,
+	}
+
+	let config = Config::<u8>::default();
+	assert_eq!(config.threads, 8);
+	assert_eq!(
+		format!("{config:?}"),
+		"Config { name: \"\", threads: 8, .. }"
+	);
+	assert_eq!(format!("{:?}", Mode::default()), "Slow");
+	assert_eq!(format!("{:?}", Mode::Fast(1)), "Fast(1)");
+}